@@ -0,0 +1,350 @@
+//! Idempotency key middleware.
+//!
+//! Clients send an `Idempotency-Key` header with unsafe requests, for example payments. The first response
+//! for a key is stored and replayed for retries with the same key, so the request is processed only once.
+use std::error::Error as StdError;
+use std::future::Future;
+use std::sync::Arc;
+
+use salvo_core::http::header::HeaderName;
+use salvo_core::http::{HeaderValue, StatusError};
+use salvo_core::{async_trait, Depot, Error, FlowCtrl, Handler, Request, Response};
+
+use super::{CachedBody, CachedEntry};
+
+/// Header carrying the idempotency key.
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+/// Header added to replayed responses.
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// State of an idempotency key, returned by [`IdempotencyStore::start`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum IdempotencyState {
+    /// The key is new, it is marked as processing by this request.
+    Started,
+    /// Another request with the same key is being processed.
+    Processing,
+    /// A request with the same key is completed, the stored response should be replayed.
+    Completed(CachedEntry),
+}
+
+/// Store idempotency keys and their responses.
+///
+/// Keys should expire after a TTL, so clients can not replay responses forever and keys left by
+/// crashed requests are released.
+pub trait IdempotencyStore: Send + Sync + 'static {
+    /// Error type for IdempotencyStore.
+    type Error: StdError + Sync + Send + 'static;
+    /// Marks the key as processing if it is unknown, this must be atomic, and returns the state of the key.
+    fn start(&self, key: &str) -> impl Future<Output = Result<IdempotencyState, Self::Error>> + Send;
+    /// Stores the response of a processed key.
+    fn complete(&self, key: &str, entry: CachedEntry) -> impl Future<Output = Result<(), Self::Error>> + Send;
+    /// Releases a processing key, so it can be retried.
+    fn abort(&self, key: &str) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+cfg_feature! {
+    #![feature = "moka-store"]
+
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    use moka::future::Cache as MokaCache;
+
+    #[derive(Clone, Debug)]
+    enum Slot {
+        Processing,
+        Completed(CachedEntry),
+    }
+
+    /// A simple in-memory store for [`Idempotency`].
+    pub struct MokaIdempotencyStore {
+        inner: MokaCache<String, Slot>,
+    }
+    impl MokaIdempotencyStore {
+        /// Create a new `MokaIdempotencyStore`, keys are kept for `ttl`.
+        pub fn new(max_capacity: u64, ttl: Duration) -> Self {
+            Self {
+                inner: MokaCache::builder().max_capacity(max_capacity).time_to_live(ttl).build(),
+            }
+        }
+    }
+
+    impl IdempotencyStore for MokaIdempotencyStore {
+        type Error = Infallible;
+
+        async fn start(&self, key: &str) -> Result<IdempotencyState, Self::Error> {
+            let entry = self.inner.entry(key.to_owned()).or_insert(Slot::Processing).await;
+            if entry.is_fresh() {
+                return Ok(IdempotencyState::Started);
+            }
+            Ok(match entry.into_value() {
+                Slot::Processing => IdempotencyState::Processing,
+                Slot::Completed(entry) => IdempotencyState::Completed(entry),
+            })
+        }
+
+        async fn complete(&self, key: &str, entry: CachedEntry) -> Result<(), Self::Error> {
+            self.inner.insert(key.to_owned(), Slot::Completed(entry)).await;
+            Ok(())
+        }
+
+        async fn abort(&self, key: &str) -> Result<(), Self::Error> {
+            self.inner.invalidate(key).await;
+            Ok(())
+        }
+    }
+}
+
+/// Idempotency middleware.
+///
+/// Requests with an `Idempotency-Key` header are processed once per key, method and path. The response is
+/// stored and replayed with an `Idempotent-Replayed: true` header for retries, and `409 Conflict` is
+/// rendered while the first request is still being processed. Server errors, error and streaming responses
+/// are not stored, so the request can be retried. The key is released too when the request is cancelled or the
+/// handler panics.
+///
+/// Safe methods (`GET`, `HEAD`, `OPTIONS` and `TRACE`) are passed through.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use salvo_core::Router;
+/// use salvo_cache::idempotency::{Idempotency, MokaIdempotencyStore};
+///
+/// let idempotency = Idempotency::new(MokaIdempotencyStore::new(10_000, Duration::from_secs(24 * 60 * 60)))
+///     .required(true);
+/// let router = Router::with_path("payments").hoop(idempotency);
+/// ```
+#[non_exhaustive]
+pub struct Idempotency<S> {
+    /// Idempotency store.
+    pub store: Arc<S>,
+    /// Whether requests with unsafe methods must have an idempotency key.
+    pub required: bool,
+}
+
+impl<S> Idempotency<S> {
+    /// Create new `Idempotency`.
+    #[inline]
+    pub fn new(store: S) -> Self {
+        Idempotency {
+            store: Arc::new(store),
+            required: false,
+        }
+    }
+
+    /// Sets whether requests with unsafe methods without an idempotency key are rejected with `400 Bad Request`.
+    #[inline]
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+}
+
+/// Releases the key with [`IdempotencyStore::abort`] when it is dropped before the request is completed.
+struct AbortGuard<S: IdempotencyStore> {
+    store: Arc<S>,
+    key: Option<String>,
+}
+impl<S: IdempotencyStore> AbortGuard<S> {
+    fn disarm(&mut self) {
+        self.key = None;
+    }
+}
+impl<S: IdempotencyStore> Drop for AbortGuard<S> {
+    fn drop(&mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        let store = self.store.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = store.abort(&key).await {
+                        tracing::error!(error = ?e, "idempotency store failed");
+                    }
+                });
+            }
+            Err(_) => tracing::error!(key, "idempotency key not released, no runtime"),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Handler for Idempotency<S>
+where
+    S: IdempotencyStore,
+{
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        if req.method().is_safe() {
+            return;
+        }
+        let Some(value) = req.headers().get(IDEMPOTENCY_KEY) else {
+            if self.required {
+                res.render(StatusError::bad_request().brief("Idempotency-Key header is required."));
+                ctrl.skip_rest();
+            }
+            return;
+        };
+        let value = match value.to_str() {
+            Ok(value) if !value.is_empty() && value.len() <= 255 => value,
+            _ => {
+                res.render(StatusError::bad_request().brief("Idempotency-Key header is invalid."));
+                ctrl.skip_rest();
+                return;
+            }
+        };
+        let key = format!("{} {}|{}", req.method(), req.uri().path(), value);
+
+        match self.store.start(&key).await {
+            Ok(IdempotencyState::Started) => {}
+            Ok(IdempotencyState::Processing) => {
+                res.render(
+                    StatusError::conflict().brief("A request with the same Idempotency-Key is being processed."),
+                );
+                ctrl.skip_rest();
+                return;
+            }
+            Ok(IdempotencyState::Completed(entry)) => {
+                let CachedEntry {
+                    status, headers, body, ..
+                } = entry;
+                if let Some(status) = status {
+                    res.status_code(status);
+                }
+                *res.headers_mut() = headers;
+                res.headers_mut()
+                    .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+                *res.body_mut() = body.into();
+                ctrl.skip_rest();
+                return;
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "idempotency store failed");
+                res.render(StatusError::internal_server_error().cause(Error::other(e)));
+                ctrl.skip_rest();
+                return;
+            }
+        }
+
+        let mut guard = AbortGuard {
+            store: self.store.clone(),
+            key: Some(key.clone()),
+        };
+        ctrl.call_next(req, depot, res).await;
+        let is_server_error = res.status_code.is_some_and(|status| status.is_server_error());
+        let body = if is_server_error || res.body.is_error() {
+            None
+        } else {
+            CachedBody::try_from(&res.body).ok()
+        };
+        let result = match body {
+            Some(body) => {
+                let entry = CachedEntry::new(res.status_code, res.headers().clone(), body);
+                self.store.complete(&key, entry).await
+            }
+            None => self.store.abort(&key).await,
+        };
+        guard.disarm();
+        if let Err(e) = result {
+            tracing::error!(error = ?e, "idempotency store failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    #[handler]
+    async fn pay(req: &mut Request) -> String {
+        if req.query::<bool>("slow").unwrap_or_default() {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        format!("payment {}", COUNTER.fetch_add(1, Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_idempotency() {
+        let idempotency = Idempotency::new(MokaIdempotencyStore::new(100, Duration::from_secs(60))).required(true);
+        let router = Router::with_hoop(idempotency).post(pay);
+        let service = Service::new(router);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(IDEMPOTENCY_KEY, "key1", true)
+            .send(&service)
+            .await;
+        let first = res.take_string().await.unwrap();
+        assert!(res.headers().get(IDEMPOTENT_REPLAYED).is_none());
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(IDEMPOTENCY_KEY, "key1", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), first);
+        assert_eq!(res.headers()[IDEMPOTENT_REPLAYED], "true");
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(IDEMPOTENCY_KEY, "key2", true)
+            .send(&service)
+            .await;
+        assert_ne!(res.take_string().await.unwrap(), first);
+
+        let res = TestClient::post("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_concurrent() {
+        let idempotency = Idempotency::new(MokaIdempotencyStore::new(100, Duration::from_secs(60)));
+        let router = Router::with_hoop(idempotency).post(pay);
+        let service = Service::new(router);
+
+        let first = TestClient::post("http://127.0.0.1:5801/?slow=true")
+            .add_header(IDEMPOTENCY_KEY, "key", true)
+            .send(&service);
+        let second = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            TestClient::post("http://127.0.0.1:5801/?slow=true")
+                .add_header(IDEMPOTENCY_KEY, "key", true)
+                .send(&service)
+                .await
+        };
+        let (first, second) = tokio::join!(first, second);
+        assert_eq!(first.status_code, Some(StatusCode::OK));
+        assert_eq!(second.status_code, Some(StatusCode::CONFLICT));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_cancelled() {
+        let idempotency = Idempotency::new(MokaIdempotencyStore::new(100, Duration::from_secs(60)));
+        let router = Router::with_hoop(idempotency).post(pay);
+        let service = Service::new(router);
+
+        let cancelled = TestClient::post("http://127.0.0.1:5801/?slow=true")
+            .add_header(IDEMPOTENCY_KEY, "key", true)
+            .send(&service);
+        assert!(tokio::time::timeout(Duration::from_millis(50), cancelled)
+            .await
+            .is_err());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(IDEMPOTENCY_KEY, "key", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+}
@@ -32,6 +32,9 @@ pub use skipper::MethodSkipper;
 #[macro_use]
 mod cfg;
 
+pub mod idempotency;
+pub use idempotency::{Idempotency, IdempotencyStore};
+
 cfg_feature! {
     #![feature = "moka-store"]
 
@@ -359,4 +362,35 @@ mod tests {
 
         assert_ne!(content0, content2);
     }
+
+    #[tokio::test]
+    async fn test_cache_mock_clock() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        use salvo_core::clock::MockClock;
+
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        #[handler]
+        async fn counted() -> String {
+            COUNT.fetch_add(1, Ordering::Relaxed).to_string()
+        }
+
+        let clock = MockClock::new();
+        let store = MokaStore::builder()
+            .time_to_live(Duration::from_secs(60))
+            .clock(clock.clone())
+            .build();
+        let cache = Cache::new(store, RequestIssuer::default());
+        let service = Service::new(Router::new().hoop(cache).goal(counted));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), "0");
+        clock.advance(Duration::from_secs(59));
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), "0");
+        clock.advance(Duration::from_secs(1));
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), "1");
+    }
 }
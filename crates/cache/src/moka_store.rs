@@ -3,17 +3,27 @@ use std::borrow::Borrow;
 use std::convert::Infallible;
 use std::hash::Hash;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use moka::future::Cache as MokaCache;
 use moka::future::CacheBuilder as MokaCacheBuilder;
 use moka::notification::RemovalCause;
+use salvo_core::clock::{Clock, SystemClock};
 
 use super::{CacheStore, CachedEntry};
 
+/// A cached entry with the time it is saved, read from the [`Clock`] of the store.
+#[derive(Clone, Debug)]
+struct StoredEntry {
+    entry: CachedEntry,
+    created_at: SystemTime,
+}
+
 /// A builder for [`MokaStore`].
 pub struct Builder<K> {
-    inner: MokaCacheBuilder<K, CachedEntry, MokaCache<K, CachedEntry>>,
+    inner: MokaCacheBuilder<K, StoredEntry, MokaCache<K, StoredEntry>>,
+    time_to_live: Option<Duration>,
+    clock: Arc<dyn Clock>,
 }
 impl<K> Builder<K>
 where
@@ -58,6 +68,16 @@ where
     /// expiration.
     pub fn time_to_live(mut self, duration: Duration) -> Self {
         self.inner = self.inner.time_to_live(duration);
+        self.time_to_live = Some(duration);
+        self
+    }
+
+    /// Sets the [`Clock`] used to expire entries after `time_to_live`, default is [`SystemClock`].
+    ///
+    /// A [`MockClock`](salvo_core::clock::MockClock) can be used in tests, so expiration can be tested without
+    /// sleeping.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
         self
     }
 
@@ -73,7 +93,9 @@ where
         mut self,
         listener: impl Fn(Arc<K>, CachedEntry, RemovalCause) + Send + Sync + 'static,
     ) -> Self {
-        self.inner = self.inner.eviction_listener(listener);
+        self.inner = self
+            .inner
+            .eviction_listener(move |key, stored: StoredEntry, cause| listener(key, stored.entry, cause));
         self
     }
 
@@ -87,12 +109,16 @@ where
     pub fn build(self) -> MokaStore<K> {
         MokaStore {
             inner: self.inner.build(),
+            time_to_live: self.time_to_live,
+            clock: self.clock,
         }
     }
 }
 /// A simple in-memory store for rate limiter.
 pub struct MokaStore<K> {
-    inner: MokaCache<K, CachedEntry>,
+    inner: MokaCache<K, StoredEntry>,
+    time_to_live: Option<Duration>,
+    clock: Arc<dyn Clock>,
 }
 impl<K> MokaStore<K>
 where
@@ -102,6 +128,8 @@ where
     pub fn new(max_capacity: u64) -> Self {
         Self {
             inner: MokaCache::new(max_capacity),
+            time_to_live: None,
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -109,6 +137,8 @@ where
     pub fn builder() -> Builder<K> {
         Builder {
             inner: MokaCache::builder(),
+            time_to_live: None,
+            clock: Arc::new(SystemClock),
         }
     }
 }
@@ -125,11 +155,20 @@ where
         Self::Key: Borrow<Q>,
         Q: Hash + Eq + Sync,
     {
-        self.inner.get(key).await
+        let stored = self.inner.get(key).await?;
+        if let Some(time_to_live) = self.time_to_live {
+            let age = self.clock.now().duration_since(stored.created_at).unwrap_or_default();
+            if age >= time_to_live {
+                self.inner.invalidate(key).await;
+                return None;
+            }
+        }
+        Some(stored.entry)
     }
 
     async fn save_entry(&self, key: Self::Key, entry: CachedEntry) -> Result<(), Self::Error> {
-        self.inner.insert(key, entry).await;
+        let created_at = self.clock.now();
+        self.inner.insert(key, StoredEntry { entry, created_at }).await;
         Ok(())
     }
 }
@@ -0,0 +1,230 @@
+//! Decompress the body of a request.
+use std::io::{Read, Result as IoResult};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Limited};
+use salvo_core::http::body::ReqBody;
+use salvo_core::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH};
+use salvo_core::http::request::secure_max_size;
+use salvo_core::http::StatusError;
+use salvo_core::{async_trait, Depot, Error, FlowCtrl, Handler, Request, Response};
+
+use super::CompressionAlgo;
+
+/// Reads at most `max_size + 1` bytes, so a too large body can be detected without decoding all of it.
+fn read_limited(reader: impl Read, max_size: usize) -> IoResult<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.take(max_size as u64 + 1).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn decode(algo: CompressionAlgo, data: &[u8], max_size: usize) -> IoResult<Vec<u8>> {
+    match algo {
+        #[cfg(feature = "brotli")]
+        CompressionAlgo::Brotli => read_limited(brotli::Decompressor::new(data, 4096), max_size),
+        #[cfg(feature = "deflate")]
+        CompressionAlgo::Deflate => read_limited(flate2::read::ZlibDecoder::new(data), max_size),
+        #[cfg(feature = "gzip")]
+        CompressionAlgo::Gzip => read_limited(flate2::read::GzDecoder::new(data), max_size),
+        #[cfg(feature = "zstd")]
+        CompressionAlgo::Zstd => read_limited(zstd::stream::read::Decoder::new(data)?, max_size),
+    }
+}
+
+/// Decompression
+///
+/// Decompresses request bodies with `Content-Encoding` header, so the handlers can parse them as usual. The
+/// whole body is read into memory and decoded in a blocking task, `Content-Encoding` header is removed and
+/// `Content-Length` header is updated.
+///
+/// Requests are rejected with `413 Payload Too Large` if the decompressed body is larger than
+/// [`max_size`](Decompression::max_size), and with `415 Unsupported Media Type` if the encoding is not supported.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_compression::Decompression;
+///
+/// let router = Router::with_path("upload").hoop(Decompression::new().max_size(10 * 1024 * 1024));
+/// ```
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Decompression {
+    /// Max size of the decompressed body.
+    pub max_size: usize,
+}
+
+impl Default for Decompression {
+    fn default() -> Self {
+        Self {
+            max_size: secure_max_size(),
+        }
+    }
+}
+
+impl Decompression {
+    /// Create a new `Decompression`.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets max size of the decompressed body, defaults to [`secure_max_size`].
+    #[inline]
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    async fn decompress(&self, req: &mut Request, algos: Vec<CompressionAlgo>) -> Result<Bytes, StatusError> {
+        let body = req.take_body();
+        let mut data = Limited::new(body, self.max_size)
+            .collect()
+            .await
+            .map_err(|e| {
+                StatusError::payload_too_large()
+                    .brief("Request body is too large.")
+                    .cause(Error::other(e))
+            })?
+            .to_bytes();
+        // Encodings are listed in the order they were applied.
+        for algo in algos.into_iter().rev() {
+            let max_size = self.max_size;
+            let decoded = tokio::task::spawn_blocking(move || decode(algo, &data, max_size))
+                .await
+                .map_err(|e| StatusError::internal_server_error().cause(Error::other(e)))?
+                .map_err(|e| {
+                    StatusError::bad_request()
+                        .brief("Decompress request body failed.")
+                        .cause(e)
+                })?;
+            if decoded.len() > self.max_size {
+                return Err(StatusError::payload_too_large().brief("Decompressed request body is too large."));
+            }
+            data = decoded.into();
+        }
+        Ok(data)
+    }
+}
+
+#[async_trait]
+impl Handler for Decompression {
+    async fn handle(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let Some(encoding) = req.headers().get(CONTENT_ENCODING) else {
+            return;
+        };
+        let algos = encoding
+            .to_str()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|algo| !algo.is_empty() && !algo.eq_ignore_ascii_case("identity"))
+            .map(|algo| algo.to_ascii_lowercase().parse::<CompressionAlgo>())
+            .collect::<Result<Vec<_>, _>>();
+        let algos = match algos {
+            Ok(algos) => algos,
+            Err(e) => {
+                res.render(
+                    StatusError::unsupported_media_type()
+                        .brief("Unsupported request content encoding.")
+                        .cause(Error::other(e)),
+                );
+                ctrl.skip_rest();
+                return;
+            }
+        };
+        match self.decompress(req, algos).await {
+            Ok(data) => {
+                let headers = req.headers_mut();
+                headers.remove(CONTENT_ENCODING);
+                headers.insert(CONTENT_LENGTH, HeaderValue::from(data.len()));
+                req.replace_body(ReqBody::Once(data));
+            }
+            Err(e) => {
+                res.render(e);
+                ctrl.skip_rest();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::CompressionLevel;
+
+    #[handler]
+    async fn echo(req: &mut Request) -> String {
+        String::from_utf8_lossy(req.payload().await.unwrap()).into_owned()
+    }
+
+    fn encode(algo: CompressionAlgo, data: &[u8]) -> Bytes {
+        let mut encoder = Encoder::new(algo, CompressionLevel::Default);
+        encoder.write(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn service() -> Service {
+        Service::new(Router::with_hoop(Decompression::new().max_size(1024)).post(echo))
+    }
+
+    #[tokio::test]
+    async fn test_decompression() {
+        let service = service();
+        let mut algos = Vec::new();
+        #[cfg(feature = "brotli")]
+        algos.push(CompressionAlgo::Brotli);
+        #[cfg(feature = "deflate")]
+        algos.push(CompressionAlgo::Deflate);
+        #[cfg(feature = "gzip")]
+        algos.push(CompressionAlgo::Gzip);
+        #[cfg(feature = "zstd")]
+        algos.push(CompressionAlgo::Zstd);
+        for algo in algos {
+            let mut res = TestClient::post("http://127.0.0.1:5801/")
+                .add_header(CONTENT_ENCODING, algo.to_string(), true)
+                .body(encode(algo, b"hello world"))
+                .send(&service)
+                .await;
+            assert_eq!(res.take_string().await.unwrap(), "hello world");
+        }
+
+        let res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(CONTENT_ENCODING, "compress", true)
+            .body("hello")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+    }
+
+    #[cfg(all(feature = "gzip", feature = "zstd"))]
+    #[tokio::test]
+    async fn test_decompression_gzip() {
+        let service = service();
+        let mut res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(CONTENT_ENCODING, "gzip, zstd", true)
+            .body(encode(CompressionAlgo::Zstd, &encode(CompressionAlgo::Gzip, b"twice")))
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "twice");
+
+        let res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(CONTENT_ENCODING, "gzip", true)
+            .body(encode(CompressionAlgo::Gzip, &[b'a'; 2048]))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::PAYLOAD_TOO_LARGE));
+
+        let res = TestClient::post("http://127.0.0.1:5801/")
+            .add_header(CONTENT_ENCODING, "gzip", true)
+            .body("not gzip")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::BAD_REQUEST));
+    }
+}
@@ -117,6 +117,21 @@ impl Encoder {
             CompressionAlgo::Zstd => Self::Zstd(level.into_zstd()),
         }
     }
+    /// Takes the compressed data written by the encoder, without flushing it.
+    pub(super) fn drain(&mut self) -> Bytes {
+        match *self {
+            #[cfg(feature = "brotli")]
+            Self::Brotli(ref mut encoder) => encoder.get_mut().take(),
+            #[cfg(feature = "deflate")]
+            Self::Deflate(ref mut encoder) => encoder.get_mut().take(),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(ref mut encoder) => encoder.get_mut().take(),
+        }
+    }
+
+    /// Flushes the encoder and takes the compressed data.
     pub(super) fn take(&mut self) -> IoResult<Bytes> {
         match *self {
             #[cfg(feature = "brotli")]
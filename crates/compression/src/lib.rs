@@ -14,8 +14,12 @@ use salvo_core::http::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, C
 use salvo_core::http::{self, mime, Mime, StatusCode};
 use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
 
+#[cfg(any(feature = "brotli", feature = "deflate", feature = "gzip", feature = "zstd"))]
+mod decompression;
 mod encoder;
 mod stream;
+#[cfg(any(feature = "brotli", feature = "deflate", feature = "gzip", feature = "zstd"))]
+pub use decompression::Decompression;
 use encoder::Encoder;
 use stream::EncodeStream;
 
@@ -391,7 +395,20 @@ impl Handler for Compression {
                     }
                 }
             }
-            _ => {}
+            body @ (ResBody::Boxed(_) | ResBody::Channel(_)) => match self.negotiate(req, res) {
+                Some((algo, level)) => {
+                    res.stream(EncodeStream::new(algo, level, body));
+                    res.headers_mut().append(CONTENT_ENCODING, algo.into());
+                }
+                None => {
+                    res.body(body);
+                    return;
+                }
+            },
+            body => {
+                res.body(body);
+                return;
+            }
         }
         res.headers_mut().remove(CONTENT_LENGTH);
     }
@@ -451,4 +468,87 @@ mod tests {
         let content = res.take_string().await.unwrap();
         assert_eq!(content, "hello");
     }
+
+    #[tokio::test]
+    async fn test_channel_body() {
+        #[handler]
+        async fn events(res: &mut Response) {
+            res.headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+            let mut sender = res.channel();
+            tokio::spawn(async move {
+                for i in 0..3 {
+                    sender.send_data(format!("data: {i}\n\n")).await.unwrap();
+                }
+            });
+        }
+        let router = Router::with_hoop(Compression::new()).push(Router::with_path("events").get(events));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/events")
+            .add_header(ACCEPT_ENCODING, "gzip", true)
+            .send(router)
+            .await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let content = res.take_string().await.unwrap();
+        assert_eq!(content, "data: 0\n\ndata: 1\n\ndata: 2\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_flush_on_event() {
+        use std::io::Write;
+        use std::time::Duration;
+
+        use futures_util::StreamExt;
+
+        let (mut sender, body) = ResBody::channel();
+        let mut stream = EncodeStream::new(CompressionAlgo::Gzip, CompressionLevel::Default, body);
+        let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+        for event in ["data: 1\n\n", "data: 2\n\n"] {
+            sender.send_data(event).await.unwrap();
+            // The event is sent without waiting for the next one or the end of the body.
+            while !decoder.get_ref().ends_with(event.as_bytes()) {
+                let chunk = tokio::time::timeout(Duration::from_secs(1), stream.next())
+                    .await
+                    .expect("event should be flushed")
+                    .unwrap()
+                    .unwrap();
+                decoder.write_all(&chunk.into_data().unwrap()).unwrap();
+                decoder.flush().unwrap();
+            }
+        }
+        drop(sender);
+        while let Some(chunk) = stream.next().await {
+            decoder.write_all(&chunk.unwrap().into_data().unwrap()).unwrap();
+        }
+        assert_eq!(decoder.finish().unwrap(), b"data: 1\n\ndata: 2\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_trailers() {
+        use std::io::Write;
+
+        use futures_util::StreamExt;
+        use salvo_core::http::HeaderMap;
+
+        let (mut sender, body) = ResBody::channel();
+        let mut stream = EncodeStream::new(CompressionAlgo::Gzip, CompressionLevel::Default, body);
+        sender.send_data("hello").await.unwrap();
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", "0".parse().unwrap());
+        sender.send_trailers(trailers).await.unwrap();
+        drop(sender);
+
+        let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+        let mut received = None;
+        while let Some(frame) = stream.next().await {
+            let frame = frame.unwrap();
+            assert!(received.is_none(), "trailers should be the last frame");
+            match frame.into_data() {
+                Ok(data) => decoder.write_all(&data).unwrap(),
+                Err(frame) => received = Some(frame.into_trailers().unwrap()),
+            }
+        }
+        assert_eq!(decoder.finish().unwrap(), b"hello");
+        assert_eq!(received.unwrap()["grpc-status"], "0");
+    }
 }
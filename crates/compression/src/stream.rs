@@ -1,4 +1,8 @@
 //! Compress the body of a response.
+//!
+//! Chunks are compressed as they are received, the compressed data is flushed when the body has no chunk ready, so
+//! streaming bodies, like server-sent events, are sent as soon as they are produced, while bodies available at once
+//! are compressed as a whole. Trailers of the body are sent after the compressed data.
 use std::collections::VecDeque;
 use std::future::Future;
 use std::io::{self, Error as IoError, ErrorKind, Result as IoResult};
@@ -9,7 +13,9 @@ use bytes::Bytes;
 use futures_util::stream::{BoxStream, Stream};
 use tokio::task::{spawn_blocking, JoinHandle};
 
-use salvo_core::http::body::{Body, BytesFrame, HyperBody};
+use salvo_core::http::body::{Body, BytesFrame, HyperBody, ResBody};
+use salvo_core::http::HeaderMap;
+use salvo_core::hyper::body::Frame;
 use salvo_core::BoxedError;
 
 use super::{CompressionAlgo, CompressionLevel, Encoder};
@@ -21,6 +27,10 @@ pub(super) struct EncodeStream<B> {
     body: B,
     eof: bool,
     encoding: Option<JoinHandle<IoResult<Encoder>>>,
+    /// Whether chunks are written to the encoder since the last flush.
+    unflushed: bool,
+    /// Trailers of the body, sent after the compressed data.
+    trailers: Option<HeaderMap>,
 }
 
 impl<B> EncodeStream<B> {
@@ -31,6 +41,21 @@ impl<B> EncodeStream<B> {
             eof: false,
             encoding: None,
             encoder: Some(Encoder::new(algo, level)),
+            unflushed: false,
+            trailers: None,
+        }
+    }
+
+    /// Returns the data of a frame, trailers are kept to be sent at the end.
+    fn frame_data(&mut self, frame: Frame<Bytes>) -> Bytes {
+        match frame.into_data() {
+            Ok(data) => data,
+            Err(frame) => {
+                if let Ok(trailers) = frame.into_trailers() {
+                    self.trailers.get_or_insert_with(HeaderMap::new).extend(trailers);
+                }
+                Bytes::new()
+            }
         }
     }
 }
@@ -42,20 +67,31 @@ impl EncodeStream<BoxStream<'static, Result<Bytes, BoxedError>>> {
 }
 impl EncodeStream<BoxStream<'static, Result<BytesFrame, BoxedError>>> {
     fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<IoResult<Bytes>>> {
-        Stream::poll_next(Pin::new(&mut self.body), cx)
-            .map_ok(|f| f.into_data().unwrap_or_default())
-            .map_err(|e| IoError::new(ErrorKind::Other, e))
+        match ready!(Stream::poll_next(Pin::new(&mut self.body), cx)) {
+            Some(Ok(frame)) => Poll::Ready(Some(Ok(self.frame_data(frame.0)))),
+            Some(Err(e)) => Poll::Ready(Some(Err(IoError::new(ErrorKind::Other, e)))),
+            None => Poll::Ready(None),
+        }
     }
 }
 impl EncodeStream<HyperBody> {
     fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<IoResult<Bytes>>> {
         match ready!(Body::poll_frame(Pin::new(&mut self.body), cx)) {
-            Some(Ok(frame)) => Poll::Ready(frame.into_data().map(Ok).ok()),
+            Some(Ok(frame)) => Poll::Ready(Some(Ok(self.frame_data(frame)))),
             Some(Err(e)) => Poll::Ready(Some(Err(IoError::new(ErrorKind::Other, e)))),
             None => Poll::Ready(None),
         }
     }
 }
+impl EncodeStream<ResBody> {
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<IoResult<Bytes>>> {
+        match ready!(Body::poll_frame(Pin::new(&mut self.body), cx)) {
+            Some(Ok(frame)) => Poll::Ready(Some(Ok(self.frame_data(frame)))),
+            Some(Err(e)) => Poll::Ready(Some(Err(e))),
+            None => Poll::Ready(None),
+        }
+    }
+}
 impl EncodeStream<Option<Bytes>> {
     fn poll_chunk(&mut self, _cx: &mut Context<'_>) -> Poll<Option<IoResult<Bytes>>> {
         if let Some(body) = Pin::new(&mut self.body).take() {
@@ -78,12 +114,13 @@ impl EncodeStream<VecDeque<Bytes>> {
 macro_rules! impl_stream {
     ($name: ty) => {
         impl Stream for EncodeStream<$name> {
-            type Item = IoResult<Bytes>;
+            type Item = IoResult<BytesFrame>;
             fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
                 let this = self.get_mut();
                 loop {
                     if this.eof {
-                        return Poll::Ready(None);
+                        let trailers = this.trailers.take();
+                        return Poll::Ready(trailers.map(|trailers| Ok(BytesFrame(Frame::trailers(trailers)))));
                     }
                     if let Some(encoding) = &mut this.encoding {
                         let mut encoder = ready!(Pin::new(encoding).poll(cx)).map_err(|e| {
@@ -93,24 +130,39 @@ macro_rules! impl_stream {
                             )
                         })??;
 
-                        let chunk = encoder.take()?;
+                        let chunk = encoder.drain();
                         this.encoder = Some(encoder);
                         this.encoding.take();
 
                         if !chunk.is_empty() {
-                            return Poll::Ready(Some(Ok(chunk)));
+                            return Poll::Ready(Some(Ok(BytesFrame::data(chunk))));
                         }
                     }
-                    match ready!(this.poll_chunk(cx)) {
+                    let Poll::Ready(chunk) = this.poll_chunk(cx) else {
+                        // No chunk is ready, flush the compressed data so it is not delayed until the next chunk.
+                        if let (true, Some(encoder)) = (this.unflushed, &mut this.encoder) {
+                            this.unflushed = false;
+                            let chunk = encoder.take()?;
+                            if !chunk.is_empty() {
+                                return Poll::Ready(Some(Ok(BytesFrame::data(chunk))));
+                            }
+                        }
+                        return Poll::Pending;
+                    };
+                    match chunk {
                         Some(Ok(chunk)) => {
+                            if chunk.is_empty() {
+                                continue;
+                            }
                             if let Some(mut encoder) = this.encoder.take() {
+                                this.unflushed = true;
                                 if chunk.len() < MAX_CHUNK_SIZE_ENCODE_IN_PLACE {
                                     encoder.write(&chunk)?;
-                                    let chunk = encoder.take()?;
+                                    let chunk = encoder.drain();
                                     this.encoder = Some(encoder);
 
                                     if !chunk.is_empty() {
-                                        return Poll::Ready(Some(Ok(chunk)));
+                                        return Poll::Ready(Some(Ok(BytesFrame::data(chunk))));
                                     }
                                 } else {
                                     this.encoding = Some(spawn_blocking(move || {
@@ -119,21 +171,18 @@ macro_rules! impl_stream {
                                     }));
                                 }
                             } else {
-                                return Poll::Ready(Some(Ok(chunk)));
+                                return Poll::Ready(Some(Ok(BytesFrame::data(chunk))));
                             }
                         }
                         Some(Err(e)) => return Poll::Ready(Some(Err(e))),
                         None => {
+                            // The trailers are sent after the end of the compressed data.
+                            this.eof = true;
                             if let Some(encoder) = this.encoder.take() {
                                 let chunk = encoder.finish()?;
-                                if chunk.is_empty() {
-                                    return Poll::Ready(None);
-                                } else {
-                                    this.eof = true;
-                                    return Poll::Ready(Some(Ok(chunk)));
+                                if !chunk.is_empty() {
+                                    return Poll::Ready(Some(Ok(BytesFrame::data(chunk))));
                                 }
-                            } else {
-                                return Poll::Ready(None);
                             }
                         }
                     }
@@ -147,3 +196,4 @@ impl_stream!(BoxStream<'static, Result<BytesFrame, BoxedError>>);
 impl_stream!(HyperBody);
 impl_stream!(Option<Bytes>);
 impl_stream!(VecDeque<Bytes>);
+impl_stream!(ResBody);
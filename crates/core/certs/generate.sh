@@ -101,6 +101,13 @@ cp rsa/end.key key.pem
 cp rsa/end.cert cert.pem
 cp rsa/end.fullchain chain.pem
 
+openssl pkcs8 -topk8 \
+    -in key.pem \
+    -out key-encrypted.pem \
+    -v2 aes-256-cbc \
+    -v2prf hmacWithSHA256 \
+    -passout pass:mypass
+
 openssl pkcs12 -export \
     -inkey rsa/inter.key \
     -in rsa/inter.cert \
@@ -115,4 +122,106 @@ openssl pkcs12 -export -legacy \
     -out identity-legacy.p12 \
     -passout pass:mypass
 
-rm -rf rsa
\ No newline at end of file
+rm -rf rsa
+# Client certificate revoked by `client-crl.pem`, used by CRL tests.
+mkdir -p crl/
+touch crl/index.txt
+echo 01 > crl/crlnumber
+cat > crl/ca.cnf <<CNF
+[ ca ]
+default_ca = CA_default
+[ CA_default ]
+database = crl/index.txt
+crlnumber = crl/crlnumber
+default_md = sha256
+default_crl_days = 36500
+crl_extensions = crl_ext
+[ v3_client ]
+basicConstraints = critical,CA:false
+keyUsage = critical,digitalSignature
+extendedKeyUsage = clientAuth
+authorityKeyIdentifier = keyid
+[ crl_ext ]
+authorityKeyIdentifier = keyid:always
+CNF
+
+openssl req -x509 -nodes \
+          -newkey ec -pkeyopt ec_paramgen_curve:P-256 \
+          -keyout crl/ca.key \
+          -out client-ca.pem \
+          -days 36500 \
+          -subj "/CN=salvo test client CA" \
+          -addext "basicConstraints=critical,CA:true" \
+          -addext "keyUsage=critical,keyCertSign,cRLSign"
+
+openssl req -nodes \
+          -newkey ec -pkeyopt ec_paramgen_curve:P-256 \
+          -keyout client-key.pem \
+          -out crl/client.req \
+          -subj "/CN=salvo test client"
+
+openssl x509 -req \
+          -in crl/client.req \
+          -CA client-ca.pem \
+          -CAkey crl/ca.key \
+          -CAcreateserial \
+          -out client-cert.pem \
+          -days 36500 \
+          -extfile crl/ca.cnf \
+          -extensions v3_client
+
+openssl ca -config crl/ca.cnf -keyfile crl/ca.key -cert client-ca.pem -revoke client-cert.pem
+openssl ca -config crl/ca.cnf -keyfile crl/ca.key -cert client-ca.pem -gencrl -out client-crl.pem
+
+rm -rf crl client-ca.srl
+
+# Client certificate with OCSP responses, `ocsp-good.der` says it is good and `ocsp-revoked.der` that it is revoked.
+mkdir -p ocsp/
+touch ocsp/index.txt
+cat > ocsp/ca.cnf <<CNF
+[ ca ]
+default_ca = CA_default
+[ CA_default ]
+database = ocsp/index.txt
+default_md = sha256
+[ v3_client ]
+basicConstraints = critical,CA:false
+keyUsage = critical,digitalSignature
+extendedKeyUsage = clientAuth
+authorityKeyIdentifier = keyid
+CNF
+
+openssl req -x509 -nodes \
+          -newkey ec -pkeyopt ec_paramgen_curve:P-256 \
+          -keyout ocsp/ca.key \
+          -out ocsp-ca.pem \
+          -days 36500 \
+          -subj "/CN=salvo test OCSP CA" \
+          -addext "basicConstraints=critical,CA:true" \
+          -addext "keyUsage=critical,keyCertSign,cRLSign,digitalSignature"
+
+openssl req -nodes \
+          -newkey ec -pkeyopt ec_paramgen_curve:P-256 \
+          -keyout ocsp-client-key.pem \
+          -out ocsp/client.req \
+          -subj "/CN=salvo test OCSP client"
+
+openssl x509 -req \
+          -in ocsp/client.req \
+          -CA ocsp-ca.pem \
+          -CAkey ocsp/ca.key \
+          -set_serial 0x0568 \
+          -out ocsp-client-cert.pem \
+          -days 36500 \
+          -extfile ocsp/ca.cnf \
+          -extensions v3_client
+
+openssl ocsp -issuer ocsp-ca.pem -cert ocsp-client-cert.pem -no_nonce -reqout ocsp/req.der
+openssl ca -config ocsp/ca.cnf -keyfile ocsp/ca.key -cert ocsp-ca.pem -valid ocsp-client-cert.pem
+openssl ocsp -index ocsp/index.txt -rsigner ocsp-ca.pem -rkey ocsp/ca.key -CA ocsp-ca.pem \
+          -reqin ocsp/req.der -respout ocsp-good.der -ndays 36500
+openssl ca -config ocsp/ca.cnf -keyfile ocsp/ca.key -cert ocsp-ca.pem -revoke ocsp-client-cert.pem
+openssl ocsp -index ocsp/index.txt -rsigner ocsp-ca.pem -rkey ocsp/ca.key -CA ocsp-ca.pem \
+          -reqin ocsp/req.der -respout ocsp-revoked.der -ndays 36500
+
+rm -rf ocsp
@@ -0,0 +1,102 @@
+//! Clock abstraction, used by middlewares depending on the current time, like rate limiters.
+//!
+//! Middlewares read the current time from a [`Clock`], which defaults to [`SystemClock`]. In tests, a
+//! [`MockClock`] can be injected instead, so time dependent behaviors like expiration can be tested
+//! deterministically without sleeping.
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Source of the current time.
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// [`Clock`] returning the time of the system.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// [`Clock`] which time only changes when it is set or advanced, for tests.
+///
+/// Clones share the same time, so a clone can be injected into a middleware and the
+/// original one advanced in the test.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use salvo_core::clock::{Clock, MockClock};
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(clock.now(), start + Duration::from_secs(60));
+/// ```
+#[derive(Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for MockClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockClock").field("now", &self.now()).finish()
+    }
+}
+
+impl MockClock {
+    /// Create a new `MockClock` starting at the current time of the system.
+    pub fn new() -> Self {
+        Self::with_time(SystemTime::now())
+    }
+
+    /// Create a new `MockClock` starting at the given time.
+    pub fn with_time(now: SystemTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    /// Moves the current time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -2,15 +2,23 @@
 A default implementation for `AsRef<Path>` (`Sting`, `OsString`, `PathBuf`, ...)
 allows the use of a local directory as cache.
 Note that the files contain private keys.
+
+[`MemoryCache`] keeps the data in memory. Implement [`AcmeCache`] for a shared storage (Redis, S3, ...)
+to let multiple instances use the same certificates instead of issuing one per instance.
 */
 
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::error::Error as StdError;
 use std::future::Future;
 use std::io::{Error as IoError, ErrorKind, Result as IoResult};
 use std::path::Path;
+use std::sync::Arc;
 
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::engine::Engine;
+use futures_util::future::BoxFuture;
+use parking_lot::RwLock;
 use ring::digest::{Context, SHA256};
 use tokio::fs::{create_dir_all, read, OpenOptions};
 use tokio::io::AsyncWriteExt;
@@ -109,12 +117,7 @@ where
 
     async fn read_key(&self, directory_name: &str, domains: &[String]) -> Result<Option<Vec<u8>>, Self::Error> {
         let mut path = self.as_ref().to_path_buf();
-        path.push(format!(
-            "{}{}-{}",
-            KEY_PEM_PREFIX,
-            directory_name,
-            file_hash_part(domains)
-        ));
+        path.push(cache_key(KEY_PEM_PREFIX, directory_name, domains));
         match read(path).await {
             Ok(data) => Ok(Some(data)),
             Err(e) => match e.kind() {
@@ -126,23 +129,13 @@ where
     async fn write_key(&self, directory_name: &str, domains: &[String], data: &[u8]) -> Result<(), Self::Error> {
         let mut path = self.as_ref().to_path_buf();
         create_dir_all(&path).await?;
-        path.push(format!(
-            "{}{}-{}",
-            KEY_PEM_PREFIX,
-            directory_name,
-            file_hash_part(domains)
-        ));
+        path.push(cache_key(KEY_PEM_PREFIX, directory_name, domains));
         write_data(path, data).await
     }
 
     async fn read_cert(&self, directory_name: &str, domains: &[String]) -> Result<Option<Vec<u8>>, Self::Error> {
         let mut path = self.as_ref().to_path_buf();
-        path.push(format!(
-            "{}{}-{}",
-            CERT_PEM_PREFIX,
-            directory_name,
-            file_hash_part(domains)
-        ));
+        path.push(cache_key(CERT_PEM_PREFIX, directory_name, domains));
         match read(path).await {
             Ok(data) => Ok(Some(data)),
             Err(e) => match e.kind() {
@@ -154,12 +147,7 @@ where
     async fn write_cert(&self, directory_name: &str, domains: &[String], data: &[u8]) -> Result<(), Self::Error> {
         let mut path = self.as_ref().to_path_buf();
         create_dir_all(&path).await?;
-        path.push(format!(
-            "{}{}-{}",
-            CERT_PEM_PREFIX,
-            directory_name,
-            file_hash_part(domains)
-        ));
+        path.push(cache_key(CERT_PEM_PREFIX, directory_name, domains));
         write_data(path, data).await
     }
 }
@@ -175,6 +163,135 @@ async fn write_data(file_path: impl AsRef<Path> + Send, data: impl AsRef<[u8]> +
     Ok(())
 }
 
+/// An in-memory [`AcmeCache`], clones of it share the same storage.
+///
+/// The cached data is lost when the process exits.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryCache {
+    entries: Arc<RwLock<HashMap<String, Vec<u8>>>>,
+}
+
+impl MemoryCache {
+    /// Create a new empty `MemoryCache`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AcmeCache for MemoryCache {
+    type Error = Infallible;
+
+    async fn read_key(&self, directory_name: &str, domains: &[String]) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self
+            .entries
+            .read()
+            .get(&cache_key(KEY_PEM_PREFIX, directory_name, domains))
+            .cloned())
+    }
+    async fn write_key(&self, directory_name: &str, domains: &[String], data: &[u8]) -> Result<(), Self::Error> {
+        self.entries
+            .write()
+            .insert(cache_key(KEY_PEM_PREFIX, directory_name, domains), data.to_vec());
+        Ok(())
+    }
+    async fn read_cert(&self, directory_name: &str, domains: &[String]) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self
+            .entries
+            .read()
+            .get(&cache_key(CERT_PEM_PREFIX, directory_name, domains))
+            .cloned())
+    }
+    async fn write_cert(&self, directory_name: &str, domains: &[String], data: &[u8]) -> Result<(), Self::Error> {
+        self.entries
+            .write()
+            .insert(cache_key(CERT_PEM_PREFIX, directory_name, domains), data.to_vec());
+        Ok(())
+    }
+}
+
+/// Object safe wrapper of [`AcmeCache`], so that any cache can be stored in the config.
+pub(crate) trait DynAcmeCache: Send + Sync {
+    fn read_key<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+    ) -> BoxFuture<'a, IoResult<Option<Vec<u8>>>>;
+    fn write_key<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+        data: &'a [u8],
+    ) -> BoxFuture<'a, IoResult<()>>;
+    fn read_cert<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+    ) -> BoxFuture<'a, IoResult<Option<Vec<u8>>>>;
+    fn write_cert<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+        data: &'a [u8],
+    ) -> BoxFuture<'a, IoResult<()>>;
+}
+
+impl<T> DynAcmeCache for T
+where
+    T: AcmeCache + Send + Sync,
+{
+    fn read_key<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+    ) -> BoxFuture<'a, IoResult<Option<Vec<u8>>>> {
+        Box::pin(async move {
+            AcmeCache::read_key(self, directory_name, domains)
+                .await
+                .map_err(|e| IoError::new(ErrorKind::Other, e))
+        })
+    }
+    fn write_key<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+        data: &'a [u8],
+    ) -> BoxFuture<'a, IoResult<()>> {
+        Box::pin(async move {
+            AcmeCache::write_key(self, directory_name, domains, data)
+                .await
+                .map_err(|e| IoError::new(ErrorKind::Other, e))
+        })
+    }
+    fn read_cert<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+    ) -> BoxFuture<'a, IoResult<Option<Vec<u8>>>> {
+        Box::pin(async move {
+            AcmeCache::read_cert(self, directory_name, domains)
+                .await
+                .map_err(|e| IoError::new(ErrorKind::Other, e))
+        })
+    }
+    fn write_cert<'a>(
+        &'a self,
+        directory_name: &'a str,
+        domains: &'a [String],
+        data: &'a [u8],
+    ) -> BoxFuture<'a, IoResult<()>> {
+        Box::pin(async move {
+            AcmeCache::write_cert(self, directory_name, domains, data)
+                .await
+                .map_err(|e| IoError::new(ErrorKind::Other, e))
+        })
+    }
+}
+
+fn cache_key(prefix: &str, directory_name: &str, domains: &[String]) -> String {
+    format!("{}{}-{}", prefix, directory_name, file_hash_part(domains))
+}
+
 fn file_hash_part(data: &[String]) -> String {
     let mut ctx = Context::new(&SHA256);
     for el in data {
@@ -184,35 +301,46 @@ fn file_hash_part(data: &[String]) -> String {
     URL_SAFE_NO_PAD.encode(ctx.finish())
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use std::path::PathBuf;
-
-//     #[tokio::test]
-//     async fn test_acme_cache() {
-//         let cache_path = PathBuf::from("temp/test_cache");
-//         let directory_name = "test_directory";
-//         let domains = vec!["example.com".to_string(), "www.example.com".to_string()];
-//         let key_data = b"test_key_data";
-//         let cert_data = b"test_cert_data";
-
-//         // Test write_key
-//         let result = AcmeCache::write_key(&cache_path, directory_name, &domains, key_data).await;
-//         assert!(result.is_ok());
-
-//         // Test read_key
-//         let result = AcmeCache::read_key(&cache_path, directory_name, &domains).await;
-//         assert!(result.is_ok());
-//         assert_eq!(result.unwrap().unwrap(), key_data);
-
-//         // Test write_cert
-//         let result = AcmeCache::write_cert(&cache_path, directory_name, &domains, cert_data).await;
-//         assert!(result.is_ok());
-
-//         // Test read_cert
-//         let result = AcmeCache::read_cert(&cache_path, directory_name, &domains).await;
-//         assert!(result.is_ok());
-//         assert_eq!(result.unwrap().unwrap(), cert_data);
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn check_cache(cache: impl AcmeCache) {
+        let directory_name = "test_directory";
+        let domains = vec!["example.com".to_string(), "www.example.com".to_string()];
+        let key_data = b"test_key_data";
+        let cert_data = b"test_cert_data";
+
+        assert!(cache.read_key(directory_name, &domains).await.unwrap().is_none());
+        assert!(cache.read_cert(directory_name, &domains).await.unwrap().is_none());
+
+        cache.write_key(directory_name, &domains, key_data).await.unwrap();
+        assert_eq!(
+            cache.read_key(directory_name, &domains).await.unwrap().unwrap(),
+            key_data
+        );
+
+        cache.write_cert(directory_name, &domains, cert_data).await.unwrap();
+        assert_eq!(
+            cache.read_cert(directory_name, &domains).await.unwrap().unwrap(),
+            cert_data
+        );
+
+        let other_domains = vec!["example.com".to_string()];
+        assert!(cache.read_cert(directory_name, &other_domains).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_path_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        check_cache(dir.path().join("acme")).await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache() {
+        let cache = MemoryCache::new();
+        check_cache(cache.clone()).await;
+        // Clones share the same storage.
+        assert!(cache.entries.read().len() == 2);
+    }
+}
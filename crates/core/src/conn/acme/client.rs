@@ -8,7 +8,9 @@ use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use hyper_util::client::legacy::{connect::HttpConnector, Client};
 use hyper_util::rt::TokioExecutor;
 use serde::{Deserialize, Serialize};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
 
+use super::config::{AcmeConfig, Eab};
 use super::{jose, key_pair::KeyPair, ChallengeType};
 use super::{Challenge, Problem};
 use super::{Directory, Identifier};
@@ -40,24 +42,40 @@ pub(crate) struct AcmeClient {
     pub(crate) directory: Directory,
     pub(crate) key_pair: Arc<KeyPair>,
     pub(crate) contacts: Vec<String>,
+    pub(crate) eab: Option<Eab>,
     pub(crate) kid: Option<String>,
 }
 
 impl AcmeClient {
-    pub(crate) async fn new(directory_url: &str, key_pair: Arc<KeyPair>, contacts: Vec<String>) -> crate::Result<Self> {
-        let https = HttpsConnectorBuilder::new()
-            .with_native_roots()
-            .expect("no native root CA certificates found")
-            .https_only()
-            .enable_http1()
-            .build();
+    pub(crate) async fn new(config: &AcmeConfig) -> crate::Result<Self> {
+        let https = match &config.directory_ca_certs {
+            Some(ca_certs) => {
+                let mut store = RootCertStore::empty();
+                for cert in rustls_pemfile::certs(&mut ca_certs.as_slice()) {
+                    store
+                        .add(cert?)
+                        .map_err(|e| Error::other(format!("invalid directory ca certificate: {}", e)))?;
+                }
+                let tls_config = ClientConfig::builder()
+                    .with_root_certificates(store)
+                    .with_no_client_auth();
+                HttpsConnectorBuilder::new().with_tls_config(tls_config)
+            }
+            None => HttpsConnectorBuilder::new()
+                .with_native_roots()
+                .expect("no native root CA certificates found"),
+        }
+        .https_only()
+        .enable_http1()
+        .build();
         let client = Client::builder(TokioExecutor::new()).build(https);
-        let directory = get_directory(&client, directory_url).await?;
+        let directory = get_directory(&client, &config.directory_url).await?;
         Ok(Self {
             client,
             directory,
-            key_pair,
-            contacts,
+            key_pair: config.key_pair.clone(),
+            contacts: config.contacts.clone(),
+            eab: config.eab.clone(),
             kid: None,
         })
     }
@@ -82,8 +100,14 @@ impl AcmeClient {
             Some(kid) => kid,
             None => {
                 // create account
-                let kid =
-                    create_acme_account(&self.client, &self.directory, &self.key_pair, self.contacts.clone()).await?;
+                let kid = create_acme_account(
+                    &self.client,
+                    &self.directory,
+                    &self.key_pair,
+                    self.contacts.clone(),
+                    self.eab.as_ref(),
+                )
+                .await?;
                 self.kid = Some(kid);
                 self.kid.as_ref().unwrap()
             }
@@ -269,6 +293,7 @@ async fn create_acme_account(
     directory: &Directory,
     key_pair: &KeyPair,
     contacts: Vec<String>,
+    eab: Option<&Eab>,
 ) -> crate::Result<String> {
     tracing::debug!("creating acme account");
 
@@ -278,8 +303,13 @@ async fn create_acme_account(
         only_return_existing: bool,
         terms_of_service_agreed: bool,
         contacts: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        external_account_binding: Option<jose::Body>,
     }
 
+    let external_account_binding = eab
+        .map(|eab| jose::external_account_binding(eab, key_pair, &directory.new_account))
+        .transpose()?;
     let nonce = get_nonce(client, &directory.new_nonce).await?;
     let res = jose::request(
         client,
@@ -291,6 +321,7 @@ async fn create_acme_account(
             only_return_existing: false,
             terms_of_service_agreed: true,
             contacts,
+            external_account_binding,
         }),
     )
     .await?;
@@ -5,23 +5,42 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
 use http::Uri;
 use parking_lot::RwLock;
 
+use super::cache::{AcmeCache, DynAcmeCache};
 use super::key_pair::KeyPair;
-use super::{ChallengeType, LETS_ENCRYPT_PRODUCTION};
+use super::{AcmeEvent, ChallengeType, LETS_ENCRYPT_PRODUCTION};
+
+pub(crate) type EventHandler = Arc<dyn Fn(&AcmeEvent) + Send + Sync>;
+
+/// External account binding credentials, issued by the CA out of band.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.4>
+#[derive(Clone)]
+pub(crate) struct Eab {
+    pub(crate) kid: String,
+    pub(crate) hmac_key: Vec<u8>,
+}
 
 /// ACME configuration
 pub struct AcmeConfig {
     pub(crate) directory_name: String,
     pub(crate) directory_url: String,
+    pub(crate) directory_ca_certs: Option<Vec<u8>>,
+    pub(crate) eab: Option<Eab>,
     pub(crate) domains: Vec<String>,
     pub(crate) contacts: Vec<String>,
     pub(crate) key_pair: Arc<KeyPair>,
     pub(crate) challenge_type: ChallengeType,
     pub(crate) cache_path: Option<PathBuf>,
+    pub(crate) cache: Option<Arc<dyn DynAcmeCache>>,
     pub(crate) keys_for_http01: Option<Arc<RwLock<HashMap<String, String>>>>,
     pub(crate) before_expired: Duration,
+    pub(crate) retry_interval: Duration,
+    pub(crate) max_retry_interval: Duration,
+    pub(crate) event_handler: Option<EventHandler>,
 }
 
 impl AcmeConfig {
@@ -30,6 +49,18 @@ impl AcmeConfig {
     pub fn builder() -> AcmeConfigBuilder {
         AcmeConfigBuilder::new()
     }
+
+    /// Delay before the next attempt after `attempts` consecutive failures, doubled for each failure.
+    pub(crate) fn retry_delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32 << attempts.saturating_sub(1).min(16);
+        self.retry_interval.saturating_mul(factor).min(self.max_retry_interval)
+    }
+
+    pub(crate) fn emit(&self, event: AcmeEvent) {
+        if let Some(handler) = &self.event_handler {
+            handler(&event);
+        }
+    }
 }
 
 impl Debug for AcmeConfig {
@@ -37,6 +68,7 @@ impl Debug for AcmeConfig {
         f.debug_struct("AcmeConfig")
             .field("directory_name", &self.directory_name)
             .field("directory_url", &self.directory_url)
+            .field("eab_kid", &self.eab.as_ref().map(|eab| &eab.kid))
             .field("domains", &self.domains)
             .field("contacts", &self.contacts)
             .field("cache_path", &self.cache_path)
@@ -48,12 +80,18 @@ impl Debug for AcmeConfig {
 pub struct AcmeConfigBuilder {
     pub(crate) directory_name: String,
     pub(crate) directory_url: String,
+    pub(crate) directory_ca_certs: Option<Vec<u8>>,
+    pub(crate) eab: Option<(String, String)>,
     pub(crate) domains: Vec<String>,
     pub(crate) contacts: Vec<String>,
     pub(crate) challenge_type: ChallengeType,
     pub(crate) cache_path: Option<PathBuf>,
+    pub(crate) cache: Option<Arc<dyn DynAcmeCache>>,
     pub(crate) keys_for_http01: Option<Arc<RwLock<HashMap<String, String>>>>,
     pub(crate) before_expired: Duration,
+    pub(crate) retry_interval: Duration,
+    pub(crate) max_retry_interval: Duration,
+    pub(crate) event_handler: Option<EventHandler>,
 }
 
 impl AcmeConfigBuilder {
@@ -62,12 +100,18 @@ impl AcmeConfigBuilder {
         Self {
             directory_name: "lets_encrypt".to_string(),
             directory_url: LETS_ENCRYPT_PRODUCTION.to_string(),
+            directory_ca_certs: None,
+            eab: None,
             domains: Vec::new(),
             contacts: Default::default(),
             challenge_type: ChallengeType::TlsAlpn01,
             cache_path: None,
+            cache: None,
             keys_for_http01: None,
             before_expired: Duration::from_secs(12 * 60 * 60),
+            retry_interval: Duration::from_secs(10 * 60),
+            max_retry_interval: Duration::from_secs(6 * 60 * 60),
+            event_handler: None,
         }
     }
 
@@ -83,6 +127,30 @@ impl AcmeConfigBuilder {
         }
     }
 
+    /// Sets the PEM encoded root certificates used to connect to the directory.
+    ///
+    /// This is needed by private ACME CAs (such as step-ca) which are not trusted by the system.
+    /// When it is set, the system root certificates are not used.
+    #[inline]
+    pub fn directory_ca_certs(self, certs: impl Into<Vec<u8>>) -> Self {
+        Self {
+            directory_ca_certs: Some(certs.into()),
+            ..self
+        }
+    }
+
+    /// Sets the external account binding credentials.
+    ///
+    /// Some CAs (such as ZeroSSL or Google Trust Services) require new accounts to be bound to an
+    /// existing account on their side. `hmac_key` is the base64url encoded key given by the CA.
+    #[inline]
+    pub fn eab(self, kid: impl Into<String>, hmac_key: impl Into<String>) -> Self {
+        Self {
+            eab: Some((kid.into(), hmac_key.into())),
+            ..self
+        }
+    }
+
     /// Sets domains.
     #[inline]
     pub fn domains(mut self, domains: impl Into<Vec<String>>) -> Self {
@@ -135,8 +203,22 @@ impl AcmeConfigBuilder {
     /// obtained again when the server is restarted next time.
     #[inline]
     pub fn cache_path(self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            cache: Some(Arc::new(path.clone())),
+            cache_path: Some(path),
+            ..self
+        }
+    }
+
+    /// Sets the cache for certificates, see [`cache`](super::cache) module.
+    ///
+    /// Use a shared cache when running multiple instances, so that a certificate is issued only once.
+    #[inline]
+    pub fn cache(self, cache: impl AcmeCache + Send + Sync + 'static) -> Self {
         Self {
-            cache_path: Some(path.into()),
+            cache: Some(Arc::new(cache)),
+            cache_path: None,
             ..self
         }
     }
@@ -147,6 +229,28 @@ impl AcmeConfigBuilder {
         Self { before_expired, ..self }
     }
 
+    /// Sets the retry delays when issuing a certificate fails.
+    ///
+    /// The delay starts at `interval` and doubles after each consecutive failure, up to `max_interval`.
+    /// Defaults to 10 minutes and 6 hours.
+    #[inline]
+    pub fn retry_backoff(self, interval: Duration, max_interval: Duration) -> Self {
+        Self {
+            retry_interval: interval,
+            max_retry_interval: max_interval,
+            ..self
+        }
+    }
+
+    /// Sets a callback receiving [`AcmeEvent`]s when certificates are issued, renewed or fail to renew.
+    #[inline]
+    pub fn on_event(self, handler: impl Fn(&AcmeEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            event_handler: Some(Arc::new(handler)),
+            ..self
+        }
+    }
+
     /// Consumes this builder and returns a [`AcmeConfig`] object.
     pub fn build(self) -> IoResult<AcmeConfig> {
         self.directory_url
@@ -155,27 +259,48 @@ impl AcmeConfigBuilder {
         if self.domains.is_empty() {
             return Err(IoError::new(ErrorKind::Other, "at least one domain name is expected"));
         }
+        let eab = self
+            .eab
+            .map(|(kid, hmac_key)| {
+                URL_SAFE_NO_PAD
+                    .decode(hmac_key.trim_end_matches('='))
+                    .map(|hmac_key| Eab { kid, hmac_key })
+                    .map_err(|e| IoError::new(ErrorKind::Other, format!("invalid eab hmac key: {}", e)))
+            })
+            .transpose()?;
         let Self {
             directory_name,
             directory_url,
+            directory_ca_certs,
             domains,
             contacts,
             challenge_type,
             cache_path,
+            cache,
             keys_for_http01,
             before_expired,
+            retry_interval,
+            max_retry_interval,
+            event_handler,
+            ..
         } = self;
 
         Ok(AcmeConfig {
             directory_name,
             directory_url,
+            directory_ca_certs,
+            eab,
             domains,
             contacts,
             key_pair: Arc::new(KeyPair::generate()?),
             challenge_type,
             cache_path,
+            cache,
             keys_for_http01,
             before_expired,
+            retry_interval,
+            max_retry_interval,
+            event_handler,
         })
     }
 }
@@ -207,4 +332,48 @@ mod tests {
         assert_eq!(acme_config.cache_path, Some(PathBuf::from("test_cache_path")));
         assert_eq!(acme_config.before_expired, Duration::from_secs(24 * 60 * 60));
     }
+
+    #[test]
+    fn test_acme_config_retry_delay() {
+        let acme_config = AcmeConfig::builder()
+            .add_domain("example.com")
+            .retry_backoff(Duration::from_secs(60), Duration::from_secs(300))
+            .build()
+            .unwrap();
+        assert_eq!(acme_config.retry_delay(1), Duration::from_secs(60));
+        assert_eq!(acme_config.retry_delay(2), Duration::from_secs(120));
+        assert_eq!(acme_config.retry_delay(3), Duration::from_secs(240));
+        assert_eq!(acme_config.retry_delay(4), Duration::from_secs(300));
+        assert_eq!(acme_config.retry_delay(100), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_acme_config_cache() {
+        let acme_config = AcmeConfig::builder()
+            .add_domain("example.com")
+            .cache_path("test_cache_path")
+            .cache(crate::conn::acme::cache::MemoryCache::new())
+            .build()
+            .unwrap();
+        assert!(acme_config.cache.is_some());
+        assert!(acme_config.cache_path.is_none());
+    }
+
+    #[test]
+    fn test_acme_config_eab() {
+        let acme_config = AcmeConfig::builder()
+            .add_domain("example.com")
+            .eab("kid-1", "c2VjcmV0LWtleQ")
+            .build()
+            .unwrap();
+        let eab = acme_config.eab.unwrap();
+        assert_eq!(eab.kid, "kid-1");
+        assert_eq!(eab.hmac_key, b"secret-key");
+
+        assert!(AcmeConfig::builder()
+            .add_domain("example.com")
+            .eab("kid-1", "not base64!")
+            .build()
+            .is_err());
+    }
 }
@@ -0,0 +1,44 @@
+use std::time::{Duration, SystemTime};
+
+/// Events emitted by the ACME listener while managing certificates.
+///
+/// Register a callback with [`AcmeListener::on_event`](super::AcmeListener::on_event) to receive them,
+/// for example to alert operators when renewal keeps failing before the certificate expires.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum AcmeEvent {
+    /// A certificate is loaded from cache when the listener starts.
+    Loaded {
+        /// Domains of the certificate.
+        domains: Vec<String>,
+        /// Expiration time of the certificate.
+        expires_at: Option<SystemTime>,
+    },
+    /// A certificate is issued for the first time.
+    Issued {
+        /// Domains of the certificate.
+        domains: Vec<String>,
+        /// Expiration time of the certificate.
+        expires_at: Option<SystemTime>,
+    },
+    /// An existing certificate is renewed.
+    Renewed {
+        /// Domains of the certificate.
+        domains: Vec<String>,
+        /// Expiration time of the new certificate.
+        expires_at: Option<SystemTime>,
+    },
+    /// Issuing or renewing a certificate failed, it will be retried after `retry_in`.
+    Failed {
+        /// Domains of the certificate.
+        domains: Vec<String>,
+        /// Error message.
+        error: String,
+        /// Number of consecutive failures.
+        attempts: u32,
+        /// Delay before the next attempt.
+        retry_in: Duration,
+        /// Expiration time of the certificate currently in use, `None` if there is no certificate.
+        expires_at: Option<SystemTime>,
+    },
+}
@@ -6,7 +6,6 @@ use rcgen::{Certificate, CertificateParams, CustomExtension, DistinguishedName,
 use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
 use tokio_rustls::rustls::{crypto::ring::sign::any_ecdsa_type, sign::CertifiedKey};
 
-use super::cache::AcmeCache;
 use super::client::AcmeClient;
 use super::config::AcmeConfig;
 use super::resolver::ResolveServerCert;
@@ -21,57 +20,23 @@ pub(crate) async fn issue_cert(
 ) -> crate::Result<()> {
     tracing::debug!("issue certificate");
     let order_res = client.new_order(&config.domains).await?;
-    // trigger challenge
-    let mut valid = false;
-    for i in 1..5 {
-        let mut all_valid = true;
-        for auth_url in &order_res.authorizations {
-            let res = client.fetch_authorization(auth_url).await?;
-            if res.status == "valid" {
-                continue;
-            }
-            all_valid = false;
-            if res.status == "pending" {
-                let challenge = res.find_challenge(config.challenge_type)?;
-                match config.challenge_type {
-                    ChallengeType::Http01 => {
-                        if let Some(keys) = &config.keys_for_http01 {
-                            let key_authorization = jose::key_authorization(&config.key_pair, &challenge.token)?;
-                            let mut keys = keys.write();
-                            keys.insert(challenge.token.to_string(), key_authorization);
-                        }
-                    }
-                    ChallengeType::TlsAlpn01 => {
-                        let key_authorization_sha256 =
-                            jose::key_authorization_sha256(&config.key_pair, &challenge.token)?;
-                        let auth_key = gen_acme_cert(&res.identifier.value, key_authorization_sha256.as_ref())?;
-                        resolver
-                            .acme_keys
-                            .write()
-                            .insert(res.identifier.value.to_string(), Arc::new(auth_key));
-                    }
-                }
-                client
-                    .trigger_challenge(&res.identifier.value, config.challenge_type, &challenge.url)
-                    .await?;
-            } else if res.status == "invalid" {
-                tracing::error!(response = ?res, "unable to authorize");
-                return Err(Error::other(format!(
-                    "unable to authorize `{}`: {}",
-                    res.identifier.value,
-                    res.error.as_ref().map(|problem| &*problem.detail).unwrap_or("unknown")
-                )));
-            }
+    let mut challenges = Challenges::default();
+    let result = authorize(client, config, resolver, &order_res.authorizations, &mut challenges).await;
+    // Challenge responses are useless once the authorization is done, whether it succeeds or not. Only the responses
+    // of this order are removed, other orders may be authorized at the same time.
+    if let Some(keys) = &config.keys_for_http01 {
+        let mut keys = keys.write();
+        for token in &challenges.http01_tokens {
+            keys.remove(token);
         }
-        if all_valid {
-            valid = true;
-            break;
-        }
-        tokio::time::sleep(Duration::from_secs(i * 10)).await;
     }
-    if !valid {
-        return Err(Error::other("authorization failed too many times"));
+    {
+        let mut acme_keys = resolver.acme_keys.write();
+        for domain in &challenges.tls_alpn01_domains {
+            acme_keys.remove(domain);
+        }
     }
+    result?;
     // send csr
     let mut params = CertificateParams::new(config.domains.clone());
     params.distinguished_name = DistinguishedName::new();
@@ -118,18 +83,88 @@ pub(crate) async fn issue_cert(
     let cert_key = CertifiedKey::new(cert_chain, pk);
     *resolver.cert.write() = Some(Arc::new(cert_key));
     tracing::debug!("certificate obtained");
-    if let Some(cache_path) = &config.cache_path {
-        cache_path
+    if let Some(cache) = &config.cache {
+        cache
             .write_key(&config.directory_name, &config.domains, key_pem.as_bytes())
             .await?;
-        cache_path
+        cache
             .write_cert(&config.directory_name, &config.domains, &cert_pem)
             .await?;
     }
     Ok(())
 }
 
-fn gen_acme_cert(domain: &str, acme_hash: &[u8]) -> crate::Result<CertifiedKey> {
+/// Challenge responses set up for an order.
+#[derive(Default)]
+struct Challenges {
+    http01_tokens: Vec<String>,
+    tls_alpn01_domains: Vec<String>,
+}
+
+async fn authorize(
+    client: &mut AcmeClient,
+    config: &AcmeConfig,
+    resolver: &ResolveServerCert,
+    authorizations: &[String],
+    challenges: &mut Challenges,
+) -> crate::Result<()> {
+    // trigger challenge
+    let mut valid = false;
+    for i in 1..5 {
+        let mut all_valid = true;
+        for auth_url in authorizations {
+            let res = client.fetch_authorization(auth_url).await?;
+            if res.status == "valid" {
+                continue;
+            }
+            all_valid = false;
+            if res.status == "pending" {
+                let challenge = res.find_challenge(config.challenge_type)?;
+                match config.challenge_type {
+                    ChallengeType::Http01 => {
+                        if let Some(keys) = &config.keys_for_http01 {
+                            let key_authorization = jose::key_authorization(&config.key_pair, &challenge.token)?;
+                            let mut keys = keys.write();
+                            keys.insert(challenge.token.to_string(), key_authorization);
+                            challenges.http01_tokens.push(challenge.token.to_string());
+                        }
+                    }
+                    ChallengeType::TlsAlpn01 => {
+                        let key_authorization_sha256 =
+                            jose::key_authorization_sha256(&config.key_pair, &challenge.token)?;
+                        let auth_key = gen_acme_cert(&res.identifier.value, key_authorization_sha256.as_ref())?;
+                        resolver
+                            .acme_keys
+                            .write()
+                            .insert(res.identifier.value.to_string(), Arc::new(auth_key));
+                        challenges.tls_alpn01_domains.push(res.identifier.value.to_string());
+                    }
+                }
+                client
+                    .trigger_challenge(&res.identifier.value, config.challenge_type, &challenge.url)
+                    .await?;
+            } else if res.status == "invalid" {
+                tracing::error!(response = ?res, "unable to authorize");
+                return Err(Error::other(format!(
+                    "unable to authorize `{}`: {}",
+                    res.identifier.value,
+                    res.error.as_ref().map(|problem| &*problem.detail).unwrap_or("unknown")
+                )));
+            }
+        }
+        if all_valid {
+            valid = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_secs(i * 10)).await;
+    }
+    if !valid {
+        return Err(Error::other("authorization failed too many times"));
+    }
+    Ok(())
+}
+
+pub(crate) fn gen_acme_cert(domain: &str, acme_hash: &[u8]) -> crate::Result<CertifiedKey> {
     let mut params = CertificateParams::new(vec![domain.to_string()]);
     params.alg = &PKCS_ECDSA_P256_SHA256;
     params.custom_extensions = vec![CustomExtension::new_acme_identifier(acme_hash)];
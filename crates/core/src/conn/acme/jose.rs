@@ -6,8 +6,10 @@ use base64::Engine;
 use http_body_util::{BodyExt, Full};
 use hyper::{body::Incoming as HyperBody, Method};
 use ring::digest::{digest, Digest, SHA256};
+use ring::hmac;
 use serde::{de::DeserializeOwned, Serialize};
 
+use crate::conn::acme::config::Eab;
 use crate::conn::acme::key_pair::KeyPair;
 use crate::Error;
 
@@ -89,7 +91,7 @@ fn sha256(data: impl AsRef<[u8]>) -> Digest {
 }
 
 #[derive(Serialize)]
-struct Body {
+pub(crate) struct Body {
     protected: String,
     payload: String,
     signature: String,
@@ -160,6 +162,36 @@ where
     serde_json::from_slice(&data).map_err(|e| Error::other(format!("response is not a valid json: {}", e)))
 }
 
+/// Create the `externalAccountBinding` field of a new account request.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc8555#section-7.3.4>
+pub(crate) fn external_account_binding(eab: &Eab, key_pair: &KeyPair, url: &str) -> IoResult<Body> {
+    #[derive(Serialize)]
+    struct EabProtected<'a> {
+        alg: &'static str,
+        kid: &'a str,
+        url: &'a str,
+    }
+
+    let protected = serde_json::to_vec(&EabProtected {
+        alg: "HS256",
+        kid: &eab.kid,
+        url,
+    })
+    .map_err(|e| IoError::new(ErrorKind::Other, format!("failed to encode jwt: {}", e)))?;
+    let protected = URL_SAFE_NO_PAD.encode(protected);
+    let payload = serde_json::to_vec(&Jwk::new(key_pair))
+        .map_err(|e| IoError::new(ErrorKind::Other, format!("failed to encode jwk: {}", e)))?;
+    let payload = URL_SAFE_NO_PAD.encode(payload);
+    let key = hmac::Key::new(hmac::HMAC_SHA256, &eab.hmac_key);
+    let signature = hmac::sign(&key, format!("{}.{}", &protected, &payload).as_bytes());
+    Ok(Body {
+        protected,
+        payload,
+        signature: URL_SAFE_NO_PAD.encode(signature.as_ref()),
+    })
+}
+
 #[inline]
 pub(crate) fn key_authorization(key: &KeyPair, token: &str) -> IoResult<String> {
     let jwk = Jwk::new(key);
@@ -171,3 +203,35 @@ pub(crate) fn key_authorization(key: &KeyPair, token: &str) -> IoResult<String>
 pub(crate) fn key_authorization_sha256(key: &KeyPair, token: &str) -> IoResult<impl AsRef<[u8]>> {
     Ok(sha256(key_authorization(key, token)?.as_bytes()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_account_binding() {
+        let eab = Eab {
+            kid: "kid-1".into(),
+            hmac_key: b"secret-key".to_vec(),
+        };
+        let key_pair = KeyPair::generate().unwrap();
+        let body = external_account_binding(&eab, &key_pair, "https://example.com/acme/new-account").unwrap();
+
+        let protected: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(&body.protected).unwrap()).unwrap();
+        assert_eq!(
+            protected,
+            serde_json::json!({"alg": "HS256", "kid": "kid-1", "url": "https://example.com/acme/new-account"})
+        );
+        let payload: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(&body.payload).unwrap()).unwrap();
+        assert_eq!(payload["kty"], "EC");
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret-key");
+        hmac::verify(
+            &key,
+            format!("{}.{}", body.protected, body.payload).as_bytes(),
+            &URL_SAFE_NO_PAD.decode(&body.signature).unwrap(),
+        )
+        .unwrap();
+    }
+}
@@ -11,7 +11,7 @@ use tokio_rustls::rustls::sign::CertifiedKey;
 use tokio_rustls::server::TlsStream;
 use tokio_rustls::TlsAcceptor;
 
-use crate::conn::{Accepted, Acceptor, Holding, Listener};
+use crate::conn::{Accepted, Acceptor, HandshakeFailure, HandshakeObserver, HandshakeStats, Holding, Listener};
 
 use crate::conn::HandshakeStream;
 use crate::fuse::ArcFuseFactory;
@@ -21,7 +21,7 @@ use crate::Router;
 
 use super::config::{AcmeConfig, AcmeConfigBuilder};
 use super::resolver::{ResolveServerCert, ACME_TLS_ALPN_NAME};
-use super::{AcmeCache, AcmeClient, ChallengeType, Http01Handler, WELL_KNOWN_PATH};
+use super::{AcmeCache, AcmeClient, AcmeEvent, ChallengeType, Http01Handler, WELL_KNOWN_PATH};
 
 cfg_feature! {
     #![feature = "quinn"]
@@ -43,6 +43,7 @@ pub struct AcmeListener<T> {
     inner: T,
     config_builder: AcmeConfigBuilder,
     check_duration: Duration,
+    handshake: HandshakeObserver,
 }
 
 impl<T> AcmeListener<T> {
@@ -53,6 +54,7 @@ impl<T> AcmeListener<T> {
             inner,
             config_builder: AcmeConfig::builder(),
             check_duration: Duration::from_secs(10 * 60),
+            handshake: HandshakeObserver::default(),
         }
     }
 
@@ -67,6 +69,30 @@ impl<T> AcmeListener<T> {
         }
     }
 
+    /// Sets the PEM encoded root certificates used to connect to the directory.
+    ///
+    /// This is needed by private ACME CAs (such as step-ca) which are not trusted by the system.
+    /// When it is set, the system root certificates are not used.
+    #[inline]
+    pub fn directory_ca_certs(self, certs: impl Into<Vec<u8>>) -> Self {
+        Self {
+            config_builder: self.config_builder.directory_ca_certs(certs),
+            ..self
+        }
+    }
+
+    /// Sets the external account binding credentials.
+    ///
+    /// Some CAs (such as ZeroSSL or Google Trust Services) require new accounts to be bound to an
+    /// existing account on their side. `hmac_key` is the base64url encoded key given by the CA.
+    #[inline]
+    pub fn eab(self, kid: impl Into<String>, hmac_key: impl Into<String>) -> Self {
+        Self {
+            config_builder: self.config_builder.eab(kid, hmac_key),
+            ..self
+        }
+    }
+
     /// Sets domains.
     #[inline]
     pub fn domains(self, domains: impl Into<Vec<String>>) -> Self {
@@ -117,7 +143,10 @@ impl<T> AcmeListener<T> {
         }
         Self { config_builder, ..self }
     }
-    /// Create an handler for HTTP-01 challenge
+    /// Use TLS-ALPN-01 challenge, this is the default challenge type.
+    ///
+    /// The challenge is answered on the TLS listener itself through the `acme-tls/1` ALPN protocol,
+    /// so port 80 is not required.
     #[inline]
     pub fn tls_alpn01_challege(self) -> Self {
         Self {
@@ -139,6 +168,89 @@ impl<T> AcmeListener<T> {
         }
     }
 
+    /// Sets the cache for certificates, see [`cache`](super::cache) module.
+    ///
+    /// Use a shared cache when running multiple instances, so that a certificate is issued only once.
+    #[inline]
+    pub fn cache(self, cache: impl AcmeCache + Send + Sync + 'static) -> Self {
+        Self {
+            config_builder: self.config_builder.cache(cache),
+            ..self
+        }
+    }
+
+    /// Sets the duration update certificate before it expired.
+    #[inline]
+    pub fn before_expired(self, before_expired: Duration) -> Self {
+        Self {
+            config_builder: self.config_builder.before_expired(before_expired),
+            ..self
+        }
+    }
+
+    /// Sets the retry delays when issuing a certificate fails.
+    ///
+    /// The delay starts at `interval` and doubles after each consecutive failure, up to `max_interval`.
+    /// Defaults to 10 minutes and 6 hours.
+    #[inline]
+    pub fn retry_backoff(self, interval: Duration, max_interval: Duration) -> Self {
+        Self {
+            config_builder: self.config_builder.retry_backoff(interval, max_interval),
+            ..self
+        }
+    }
+
+    /// Sets a callback receiving [`AcmeEvent`]s when certificates are issued, renewed or fail to renew.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use salvo_core::conn::acme::AcmeEvent;
+    /// use salvo_core::prelude::*;
+    ///
+    /// # async fn example() {
+    /// let acceptor = TcpListener::new("0.0.0.0:443")
+    ///     .acme()
+    ///     .add_domain("example.com")
+    ///     .on_event(|event| {
+    ///         if let AcmeEvent::Failed { error, attempts, .. } = event {
+    ///             tracing::error!(error, attempts, "certificate renewal failed");
+    ///         }
+    ///     })
+    ///     .bind()
+    ///     .await;
+    /// # }
+    /// ```
+    #[inline]
+    pub fn on_event(self, handler: impl Fn(&AcmeEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            config_builder: self.config_builder.on_event(handler),
+            ..self
+        }
+    }
+
+    /// Sets the time clients have to complete the TLS handshake, slow clients are disconnected. Defaults to
+    /// [`DEFAULT_HANDSHAKE_TIMEOUT`](crate::conn::DEFAULT_HANDSHAKE_TIMEOUT), `None` disables the timeout.
+    #[inline]
+    pub fn handshake_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.handshake.timeout = timeout.into();
+        self
+    }
+
+    /// Sets a callback receiving the failed TLS handshakes, like clients sending a server name without
+    /// certificate or speaking another protocol.
+    #[inline]
+    pub fn on_handshake_failure(mut self, handler: impl Fn(&HandshakeFailure) + Send + Sync + 'static) -> Self {
+        self.handshake.on_failure = Some(Arc::new(handler));
+        self
+    }
+
+    /// Get the counters of TLS handshakes, which are updated by the acceptor once bound.
+    #[inline]
+    pub fn handshake_stats(&self) -> HandshakeStats {
+        self.handshake.stats.clone()
+    }
+
     cfg_feature! {
         #![feature = "quinn"]
         /// Enable Http3 using quinn.
@@ -152,8 +264,8 @@ impl<T> AcmeListener<T> {
         async fn build_server_config_old(acme_config: &AcmeConfig) -> crate::Result<ServerConfigOld> {
             let mut cached_key = None;
             let mut cached_cert = None;
-            if let Some(cache_path) = &acme_config.cache_path {
-                let key_data = cache_path
+            if let Some(cache) = &acme_config.cache {
+                let key_data = cache
                     .read_key(&acme_config.directory_name, &acme_config.domains)
                     .await?;
                 if let Some(key_data) = key_data {
@@ -165,7 +277,7 @@ impl<T> AcmeListener<T> {
                         }
                     };
                 }
-                let cert_data = cache_path
+                let cert_data = cache
                     .read_cert(&acme_config.directory_name, &acme_config.domains)
                     .await?;
                 if let Some(cert_data) = cert_data {
@@ -208,8 +320,8 @@ impl<T> AcmeListener<T> {
     async fn build_server_config(acme_config: &AcmeConfig) -> crate::Result<(ServerConfig, Arc<ResolveServerCert>)> {
         let mut cached_key = None;
         let mut cached_certs = None;
-        if let Some(cache_path) = &acme_config.cache_path {
-            let key_data = cache_path
+        if let Some(cache) = &acme_config.cache {
+            let key_data = cache
                 .read_key(&acme_config.directory_name, &acme_config.domains)
                 .await?;
             if let Some(key_data) = key_data {
@@ -227,7 +339,7 @@ impl<T> AcmeListener<T> {
                     tracing::warn!("parse cached private key failed");
                 }
             }
-            let cert_data = cache_path
+            let cert_data = cache
                 .read_cert(&acme_config.directory_name, &acme_config.domains)
                 .await?;
             if let Some(cert_data) = cert_data {
@@ -278,7 +390,7 @@ where
             inner,
             config_builder,
             check_duration,
-            ..
+            handshake,
         } = self;
         let acme_config = config_builder.build()?;
 
@@ -288,7 +400,7 @@ where
         let inner = inner.try_bind().await?;
         #[cfg(feature = "quinn")]
         let server_config_old = Self::build_server_config_old(&acme_config).await?;
-        let acceptor = AcmeAcceptor::new(
+        let mut acceptor = AcmeAcceptor::new(
             acme_config,
             server_config,
             #[cfg(feature = "quinn")]
@@ -299,6 +411,7 @@ where
             check_duration,
         )
         .await?;
+        acceptor.handshake = handshake;
         Ok(acceptor)
     }
 }
@@ -351,6 +464,7 @@ pub struct AcmeAcceptor<T> {
     inner: T,
     holdings: Vec<Holding>,
     tls_acceptor: tokio_rustls::TlsAcceptor,
+    handshake: HandshakeObserver,
 }
 
 impl<T> AcmeAcceptor<T>
@@ -398,19 +512,50 @@ where
             inner,
             holdings,
             tls_acceptor,
+            handshake: HandshakeObserver::default(),
         };
         let config = acceptor.config.clone();
         let weak_cert_resolver = Arc::downgrade(&cert_resolver);
-        let mut client =
-            AcmeClient::new(&config.directory_url, config.key_pair.clone(), config.contacts.clone()).await?;
+        let mut client = AcmeClient::new(&config).await?;
+        if cert_resolver.cert.read().is_some() {
+            config.emit(AcmeEvent::Loaded {
+                domains: config.domains.clone(),
+                expires_at: cert_resolver.expires_at(),
+            });
+        }
         tokio::spawn(async move {
+            let mut attempts = 0;
             while let Some(cert_resolver) = Weak::upgrade(&weak_cert_resolver) {
+                let mut wait = check_duration;
                 if cert_resolver.will_expired(config.before_expired) {
-                    if let Err(e) = super::issuer::issue_cert(&mut client, &config, &cert_resolver).await {
-                        tracing::error!(error = ?e, "issue certificate failed");
+                    let renewing = cert_resolver.cert.read().is_some();
+                    match super::issuer::issue_cert(&mut client, &config, &cert_resolver).await {
+                        Ok(()) => {
+                            attempts = 0;
+                            let domains = config.domains.clone();
+                            let expires_at = cert_resolver.expires_at();
+                            config.emit(if renewing {
+                                AcmeEvent::Renewed { domains, expires_at }
+                            } else {
+                                AcmeEvent::Issued { domains, expires_at }
+                            });
+                        }
+                        Err(e) => {
+                            attempts += 1;
+                            wait = config.retry_delay(attempts);
+                            tracing::error!(error = ?e, attempts, retry_in = ?wait, "issue certificate failed");
+                            config.emit(AcmeEvent::Failed {
+                                domains: config.domains.clone(),
+                                error: e.to_string(),
+                                attempts,
+                                retry_in: wait,
+                                expires_at: cert_resolver.expires_at(),
+                            });
+                        }
                     }
                 }
-                tokio::time::sleep(check_duration).await;
+                drop(cert_resolver);
+                tokio::time::sleep(wait).await;
             }
         });
         Ok(acceptor)
@@ -418,6 +563,11 @@ where
     pub fn server_config(&self) -> Arc<ServerConfig> {
         self.server_config.clone()
     }
+    /// Get the counters of TLS handshakes.
+    #[inline]
+    pub fn handshake_stats(&self) -> HandshakeStats {
+        self.handshake.stats.clone()
+    }
 }
 
 impl<T: Acceptor> Acceptor for AcmeAcceptor<T>
@@ -442,8 +592,11 @@ where
             ..
         } = self.inner.accept(fuse_factory).await?;
         let fusewire = conn.fusewire();
+        let handshake = self
+            .handshake
+            .observe(self.tls_acceptor.accept(conn), local_addr.clone(), remote_addr.clone());
         Ok(Accepted {
-            conn: HandshakeStream::new(self.tls_acceptor.accept(conn), fusewire),
+            conn: HandshakeStream::new(handshake, fusewire),
             local_addr,
             remote_addr,
             http_version,
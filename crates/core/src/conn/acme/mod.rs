@@ -31,6 +31,9 @@
 //!
 //! * TLS ALPN-01
 //!
+//! This is the default challenge type. It is answered by the TLS listener itself using the `acme-tls/1`
+//! ALPN protocol, so port 80 does not need to be exposed.
+//!
 //! # Example
 //!
 //! ```no_run
@@ -57,6 +60,7 @@
 pub mod cache;
 mod client;
 mod config;
+mod event;
 mod issuer;
 mod jose;
 mod key_pair;
@@ -75,6 +79,7 @@ use crate::http::StatusError;
 use crate::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
 use cache::AcmeCache;
 pub use config::{AcmeConfig, AcmeConfigBuilder};
+pub use event::AcmeEvent;
 pub use listener::AcmeListener;
 // TODO: waiting quinn update
 // cfg_feature! {
@@ -86,6 +91,16 @@ pub use listener::AcmeListener;
 pub const LETS_ENCRYPT_PRODUCTION: &str = "https://acme-v02.api.letsencrypt.org/directory";
 /// Letsencrypt staging directory url
 pub const LETS_ENCRYPT_STAGING: &str = "https://acme-staging-v02.api.letsencrypt.org/directory";
+/// ZeroSSL production directory url, external account binding is required.
+pub const ZERO_SSL_PRODUCTION: &str = "https://acme.zerossl.com/v2/DV90";
+/// Buypass production directory url
+pub const BUYPASS_PRODUCTION: &str = "https://api.buypass.com/acme/directory";
+/// Buypass staging directory url
+pub const BUYPASS_STAGING: &str = "https://api.test4.buypass.no/acme/directory";
+/// Google Trust Services production directory url, external account binding is required.
+pub const GOOGLE_TRUST_SERVICES_PRODUCTION: &str = "https://dv.acme-v02.api.pki.goog/directory";
+/// Google Trust Services staging directory url, external account binding is required.
+pub const GOOGLE_TRUST_SERVICES_STAGING: &str = "https://dv.acme-v02.test-api.pki.goog/directory";
 
 /// Well known acme challenge path
 pub(crate) const WELL_KNOWN_PATH: &str = "/.well-known/acme-challenge";
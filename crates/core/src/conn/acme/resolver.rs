@@ -29,19 +29,20 @@ pub(crate) struct ResolveServerCert {
 impl ResolveServerCert {
     #[inline]
     pub(crate) fn will_expired(&self, before: Duration) -> bool {
+        match self.expires_at() {
+            Some(expires_at) => SystemTime::now() + before > expires_at,
+            None => true,
+        }
+    }
+
+    /// Expiration time of the current certificate.
+    pub(crate) fn expires_at(&self) -> Option<SystemTime> {
         let cert = self.cert.read();
-        match cert
-            .as_ref()
+        cert.as_ref()
             .and_then(|cert| cert.cert.first())
             .and_then(|cert| X509Certificate::from_der(cert.as_ref()).ok())
             .map(|(_, cert)| cert.validity().not_after.timestamp())
-        {
-            Some(valid_until) => {
-                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-                (now + before).as_secs() as i64 > valid_until
-            }
-            None => true,
-        }
+            .map(|valid_until| UNIX_EPOCH + Duration::from_secs(valid_until.max(0) as u64))
     }
 }
 
@@ -103,3 +104,81 @@ impl ResolvesServerCertOld for ResolveServerCertOld {
         self.cert.read().as_ref().cloned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use tokio_rustls::rustls::crypto::ring::default_provider;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, Error, ServerConfig, SignatureScheme};
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+    use super::*;
+    use crate::conn::acme::issuer::gen_acme_cert;
+
+    #[derive(Debug)]
+    struct AcceptAnyCert;
+    impl ServerCertVerifier for AcceptAnyCert {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            default_provider().signature_verification_algorithms.supported_schemes()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_tls_alpn01_challenge() {
+        let resolver = Arc::new(ResolveServerCert::default());
+        let challenge = gen_acme_cert("example.com", &[0; 32]).unwrap();
+        let challenge_cert = challenge.cert[0].clone();
+        resolver
+            .acme_keys
+            .write()
+            .insert("example.com".to_string(), Arc::new(challenge));
+        let mut server_config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+        server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec(), ACME_TLS_ALPN_NAME.to_vec()];
+
+        let mut client_config = ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+            .with_no_client_auth();
+        client_config.alpn_protocols = vec![ACME_TLS_ALPN_NAME.to_vec()];
+
+        let (client_io, server_io) = tokio::io::duplex(8192);
+        let server = tokio::spawn(async move { TlsAcceptor::from(Arc::new(server_config)).accept(server_io).await });
+        let stream = TlsConnector::from(Arc::new(client_config))
+            .connect(ServerName::try_from("example.com").unwrap(), client_io)
+            .await
+            .unwrap();
+        let (_, conn) = stream.get_ref();
+        assert_eq!(conn.alpn_protocol(), Some(ACME_TLS_ALPN_NAME));
+        assert_eq!(conn.peer_certificates().unwrap()[0], challenge_cert);
+        assert!(server.await.unwrap().is_ok());
+    }
+}
@@ -18,6 +18,20 @@ pub use proto::HttpBuilder;
 mod stream;
 pub use stream::*;
 
+cfg_feature! {
+    #![any(feature = "native-tls", feature = "rustls", feature = "openssl", feature = "acme")]
+    mod peer_cert;
+    pub use peer_cert::PeerCert;
+    pub(crate) use peer_cert::PeerCerts;
+}
+
+cfg_feature! {
+    #![any(feature = "rustls", feature = "acme")]
+    mod tls_handshake;
+    pub use tls_handshake::{HandshakeFailure, HandshakeFailureKind, HandshakeStats, DEFAULT_HANDSHAKE_TIMEOUT};
+    pub(crate) use tls_handshake::HandshakeObserver;
+}
+
 cfg_feature! {
     #![feature = "acme"]
     pub mod acme;
@@ -62,8 +76,15 @@ pub use addr::SocketAddr;
 pub mod tcp;
 pub use tcp::TcpListener;
 
+pub mod proxy_protocol;
+pub use proxy_protocol::ProxyProtocolListener;
+
 mod joined;
 pub use joined::JoinedListener;
+pub(crate) use joined::JoinedStream;
+
+pub mod routed;
+pub use routed::RoutedListener;
 
 cfg_feature! {
     #![unix]
@@ -188,4 +209,14 @@ pub trait Listener {
     {
         JoinedListener::new(self, other)
     }
+
+    /// Serve connections of current Listener with its own [`Service`](crate::Service), instead of the one
+    /// passed to [`Server::serve`](crate::Server::serve).
+    #[inline]
+    fn with_service(self, service: impl Into<crate::Service>) -> RoutedListener<Self>
+    where
+        Self: Sized + Send,
+    {
+        RoutedListener::new(self, service)
+    }
 }
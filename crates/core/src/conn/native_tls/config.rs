@@ -1,9 +1,9 @@
 //! native_tls module
 use std::fmt::{self, Formatter};
 use std::fs::File;
-use std::io::{Error as IoError, ErrorKind, Result as IoResult, Read};
-use std::path::{Path, PathBuf};
 use std::future::{ready, Ready};
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
+use std::path::{Path, PathBuf};
 
 use futures_util::stream::{once, Once, Stream};
 
@@ -12,6 +12,9 @@ pub use tokio_native_tls::native_tls::Identity;
 use crate::conn::IntoConfigStream;
 
 /// Builder to set the configuration for the TLS server.
+///
+/// The identity can be provided either as a PKCS #12 archive, or as a PEM encoded certificate
+/// chain with a PKCS #8 private key.
 #[non_exhaustive]
 pub struct NativeTlsConfig {
     pkcs12_path: Option<PathBuf>,
@@ -19,6 +22,12 @@ pub struct NativeTlsConfig {
     pub pkcs12: Vec<u8>,
     /// The password for the pkcs12 data.
     pub password: String,
+    cert_path: Option<PathBuf>,
+    key_path: Option<PathBuf>,
+    /// The PEM encoded certificate chain.
+    pub cert: Vec<u8>,
+    /// The PEM encoded PKCS #8 private key.
+    pub key: Vec<u8>,
 }
 
 impl fmt::Debug for NativeTlsConfig {
@@ -42,6 +51,10 @@ impl NativeTlsConfig {
             pkcs12_path: None,
             pkcs12: vec![],
             password: String::new(),
+            cert_path: None,
+            key_path: None,
+            cert: vec![],
+            key: vec![],
         }
     }
 
@@ -65,7 +78,37 @@ impl NativeTlsConfig {
         self
     }
 
+    /// Sets the PEM encoded certificate chain via File Path.
+    #[inline]
+    pub fn cert_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.cert_path = Some(path.as_ref().into());
+        self
+    }
+
+    /// Sets the PEM encoded certificate chain via bytes slice.
+    #[inline]
+    pub fn cert(mut self, cert: impl Into<Vec<u8>>) -> Self {
+        self.cert = cert.into();
+        self
+    }
+
+    /// Sets the PEM encoded PKCS #8 private key via File Path.
+    #[inline]
+    pub fn key_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.key_path = Some(path.as_ref().into());
+        self
+    }
+
+    /// Sets the PEM encoded PKCS #8 private key via bytes slice.
+    #[inline]
+    pub fn key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.key = key.into();
+        self
+    }
+
     /// Build identity
+    ///
+    /// PKCS #12 data is used if it is provided, otherwise the PEM certificate and key are used.
     pub fn build_identity(mut self) -> IoResult<Identity> {
         if self.pkcs12.is_empty() {
             if let Some(path) = &self.pkcs12_path {
@@ -73,7 +116,30 @@ impl NativeTlsConfig {
                 file.read_to_end(&mut self.pkcs12)?;
             }
         }
-        Identity::from_pkcs12(&self.pkcs12, &self.password).map_err(|e| IoError::new(ErrorKind::Other, e.to_string()))
+        if !self.pkcs12.is_empty() {
+            return Identity::from_pkcs12(&self.pkcs12, &self.password)
+                .map_err(|e| IoError::new(ErrorKind::Other, e.to_string()));
+        }
+
+        if self.cert.is_empty() {
+            if let Some(path) = &self.cert_path {
+                let mut file = File::open(path)?;
+                file.read_to_end(&mut self.cert)?;
+            }
+        }
+        if self.key.is_empty() {
+            if let Some(path) = &self.key_path {
+                let mut file = File::open(path)?;
+                file.read_to_end(&mut self.key)?;
+            }
+        }
+        if self.cert.is_empty() || self.key.is_empty() {
+            return Err(IoError::new(
+                ErrorKind::Other,
+                "native_tls: pkcs12 or certificate and private key are required",
+            ));
+        }
+        Identity::from_pkcs8(&self.cert, &self.key).map_err(|e| IoError::new(ErrorKind::Other, e.to_string()))
     }
 }
 
@@ -45,4 +45,32 @@ mod tests {
         let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
         assert_eq!(conn.read_i32().await.unwrap(), 10);
     }
+
+    #[tokio::test]
+    async fn test_native_tls_listener_with_pem() {
+        let mut acceptor = TcpListener::new("127.0.0.1:0")
+            .native_tls(
+                NativeTlsConfig::new()
+                    .cert_path("certs/cert.pem")
+                    .key_path("certs/key.pem"),
+            )
+            .bind()
+            .await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        tokio::spawn(async move {
+            let connector = tokio_native_tls::TlsConnector::from(
+                tokio_native_tls::native_tls::TlsConnector::builder()
+                    .danger_accept_invalid_certs(true)
+                    .build()
+                    .unwrap(),
+            );
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let mut stream = connector.connect("127.0.0.1", stream).await.unwrap();
+            stream.write_i32(12).await.unwrap();
+        });
+
+        let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert_eq!(conn.read_i32().await.unwrap(), 12);
+    }
 }
@@ -1,13 +1,13 @@
 //! openssl module
 use std::fmt::{self, Formatter};
 use std::fs::File;
-use std::future::{Ready, ready};
+use std::future::{ready, Ready};
 use std::io::{Error as IoError, Read, Result as IoResult};
 use std::path::Path;
 
 use futures_util::stream::{once, Once, Stream};
 use openssl::pkey::PKey;
-use openssl::ssl::{SslAcceptor, SslMethod, SslRef};
+use openssl::ssl::{SslAcceptor, SslMethod};
 use openssl::x509::X509;
 use tokio::io::ErrorKind;
 
@@ -93,6 +93,7 @@ type BuilderModifier = Box<dyn FnMut(&mut SslAcceptorBuilder) + Send + 'static>;
 #[non_exhaustive]
 pub struct OpensslConfig {
     keycert: Keycert,
+    alpn_protocols: Vec<Vec<u8>>,
     /// Builder modifier.
     pub builder_modifier: Option<BuilderModifier>,
 }
@@ -110,10 +111,20 @@ impl OpensslConfig {
     pub fn new(keycert: Keycert) -> Self {
         OpensslConfig {
             keycert,
+            alpn_protocols: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
             builder_modifier: None,
         }
     }
 
+    /// Sets the ALPN protocols, in order of preference.
+    ///
+    /// Defaults to `h2` and `http/1.1`.
+    #[inline]
+    pub fn alpn_protocols(mut self, alpn_protocols: impl Into<Vec<Vec<u8>>>) -> Self {
+        self.alpn_protocols = alpn_protocols.into();
+        self
+    }
+
     /// Set builder modifier.
     pub fn builder_modifier<F>(mut self, modifier: F) -> Self
     where
@@ -138,13 +149,22 @@ impl OpensslConfig {
         certs.try_for_each(|cert| builder.add_extra_chain_cert(cert))?;
         builder.set_private_key(PKey::private_key_from_pem(self.keycert.key()?)?.as_ref())?;
 
-        // set ALPN protocols
-        static PROTOS: &[u8] = b"\x02h2\x08http/1.1";
-        builder.set_alpn_protos(PROTOS)?;
-        // set uo ALPN selection routine - as select_next_proto
-        builder.set_alpn_select_callback(move |_: &mut SslRef, list: &[u8]| {
-            openssl::ssl::select_next_proto(PROTOS, list).ok_or(openssl::ssl::AlpnError::NOACK)
-        });
+        // set ALPN protocols, encoded as length prefixed strings
+        let mut protos = Vec::new();
+        for proto in &self.alpn_protocols {
+            let len = u8::try_from(proto.len())
+                .map_err(|_| IoError::new(ErrorKind::Other, "alpn protocol name is too long"))?;
+            protos.push(len);
+            protos.extend_from_slice(proto);
+        }
+        if !protos.is_empty() {
+            builder.set_alpn_protos(&protos)?;
+            // set uo ALPN selection routine - as select_next_proto
+            let alpn_protocols = self.alpn_protocols.clone();
+            builder.set_alpn_select_callback(move |_, list| {
+                select_alpn_protocol(&alpn_protocols, list).ok_or(openssl::ssl::AlpnError::NOACK)
+            });
+        }
         if let Some(modifier) = &mut self.builder_modifier {
             modifier(&mut builder);
         }
@@ -152,6 +172,24 @@ impl OpensslConfig {
     }
 }
 
+/// Select the first protocol in server preference order which is also offered by the client.
+fn select_alpn_protocol<'a>(server: &[Vec<u8>], client: &'a [u8]) -> Option<&'a [u8]> {
+    let mut offered = Vec::new();
+    let mut rest = client;
+    while let Some((&len, tail)) = rest.split_first() {
+        let len = len as usize;
+        if tail.len() < len {
+            break;
+        }
+        let (proto, tail) = tail.split_at(len);
+        offered.push(proto);
+        rest = tail;
+    }
+    server
+        .iter()
+        .find_map(|proto| offered.iter().find(|offered| **offered == proto.as_slice()).copied())
+}
+
 impl TryInto<SslAcceptorBuilder> for OpensslConfig {
     type Error = IoError;
 
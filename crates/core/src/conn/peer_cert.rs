@@ -0,0 +1,155 @@
+//! Certificates presented by the peer of a TLS connection.
+use std::fmt::{self, Debug, Formatter};
+
+use sha2::{Digest, Sha256};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::FromDer;
+
+/// A certificate presented by the client during the TLS handshake.
+///
+/// It is only available when client authentication is enabled on the listener, see
+/// [`Request::peer_certs`](crate::http::Request::peer_certs).
+#[derive(Clone, PartialEq, Eq)]
+pub struct PeerCert {
+    der: Vec<u8>,
+}
+
+impl PeerCert {
+    /// Create a new `PeerCert` from DER encoded certificate.
+    #[inline]
+    pub fn new(der: impl Into<Vec<u8>>) -> Self {
+        Self { der: der.into() }
+    }
+
+    /// Raw DER encoded certificate.
+    #[inline]
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// SHA-256 fingerprint of the DER encoded certificate.
+    #[inline]
+    pub fn fingerprint(&self) -> [u8; 32] {
+        Sha256::digest(&self.der).into()
+    }
+
+    /// SHA-256 fingerprint as lowercase hex string.
+    pub fn fingerprint_hex(&self) -> String {
+        self.fingerprint().iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Subject distinguished name, for example `CN=client.example.com`.
+    ///
+    /// Returns `None` if the certificate can not be parsed.
+    pub fn subject(&self) -> Option<String> {
+        self.parse().map(|cert| cert.subject().to_string())
+    }
+
+    /// Issuer distinguished name.
+    ///
+    /// Returns `None` if the certificate can not be parsed.
+    pub fn issuer(&self) -> Option<String> {
+        self.parse().map(|cert| cert.issuer().to_string())
+    }
+
+    /// Serial number as colon separated hex string.
+    ///
+    /// Returns `None` if the certificate can not be parsed.
+    pub fn serial(&self) -> Option<String> {
+        self.parse().map(|cert| cert.raw_serial_as_string())
+    }
+
+    /// DNS names, email addresses, URIs and IP addresses in the subject alternative name extension.
+    pub fn subject_alt_names(&self) -> Vec<String> {
+        let Some(cert) = self.parse() else {
+            return vec![];
+        };
+        let Ok(Some(san)) = cert.subject_alternative_name() else {
+            return vec![];
+        };
+        san.value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(name) | GeneralName::RFC822Name(name) | GeneralName::URI(name) => {
+                    Some(name.to_string())
+                }
+                GeneralName::IPAddress(ip) => match ip.len() {
+                    4 => <[u8; 4]>::try_from(*ip)
+                        .ok()
+                        .map(|ip| std::net::IpAddr::from(ip).to_string()),
+                    16 => <[u8; 16]>::try_from(*ip)
+                        .ok()
+                        .map(|ip| std::net::IpAddr::from(ip).to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn parse(&self) -> Option<X509Certificate<'_>> {
+        X509Certificate::from_der(&self.der).ok().map(|(_, cert)| cert)
+    }
+}
+
+impl Debug for PeerCert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PeerCert")
+            .field("subject", &self.subject())
+            .field("fingerprint", &self.fingerprint_hex())
+            .finish()
+    }
+}
+
+/// Read peer certificates from an established TLS stream.
+pub(crate) trait PeerCerts {
+    /// Certificates presented by the peer, end-entity certificate first.
+    fn peer_certs(&self) -> Vec<PeerCert>;
+}
+
+#[cfg(any(feature = "rustls", feature = "acme"))]
+impl<S> PeerCerts for tokio_rustls::server::TlsStream<S> {
+    fn peer_certs(&self) -> Vec<PeerCert> {
+        self.get_ref()
+            .1
+            .peer_certificates()
+            .map(|certs| certs.iter().map(|cert| PeerCert::new(cert.as_ref())).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "native-tls")]
+impl<S> PeerCerts for tokio_native_tls::TlsStream<S>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    fn peer_certs(&self) -> Vec<PeerCert> {
+        self.get_ref()
+            .peer_certificate()
+            .ok()
+            .flatten()
+            .and_then(|cert| cert.to_der().ok())
+            .map(|der| vec![PeerCert::new(der)])
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<S> PeerCerts for tokio_openssl::SslStream<S> {
+    fn peer_certs(&self) -> Vec<PeerCert> {
+        let ssl = self.ssl();
+        let mut certs: Vec<PeerCert> = ssl
+            .peer_certificate()
+            .and_then(|cert| cert.to_der().ok())
+            .map(PeerCert::new)
+            .into_iter()
+            .collect();
+        // On the server side the chain does not include the peer certificate itself.
+        if let Some(chain) = ssl.peer_cert_chain() {
+            certs.extend(chain.iter().filter_map(|cert| cert.to_der().ok()).map(PeerCert::new));
+        }
+        certs
+    }
+}
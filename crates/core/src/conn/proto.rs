@@ -4,6 +4,8 @@ use std::future::Future;
 use std::io::{Error as IoError, ErrorKind, IoSlice, Result as IoResult};
 use std::marker::{PhantomPinned, Unpin};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::{self, ready, Context, Poll};
 
 use bytes::{Buf, Bytes};
@@ -12,10 +14,11 @@ use http::{Request, Response, Version};
 use hyper::service::Service;
 use pin_project::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Duration, Sleep};
 use tokio_util::sync::CancellationToken;
 
 use crate::fuse::ArcFusewire;
-use crate::http::body::{Body, HyperBody};
+use crate::http::body::{Body, Frame, HyperBody, SizeHint};
 #[cfg(any(feature = "http1", feature = "http2"))]
 use crate::rt::tokio::TokioIo;
 
@@ -41,6 +44,10 @@ pub struct HttpBuilder {
     pub(crate) http2: http2::Builder<TokioExecutor>,
     #[cfg(feature = "quinn")]
     pub(crate) quinn: quinn::Builder,
+    pub(crate) h2c_prior_knowledge: bool,
+    pub(crate) h2c_upgrade: bool,
+    pub(crate) read_timeout: Option<Duration>,
+    pub(crate) write_timeout: Option<Duration>,
 }
 impl Default for HttpBuilder {
     fn default() -> Self {
@@ -57,10 +64,15 @@ impl HttpBuilder {
             http2: http2::Builder::new(crate::rt::tokio::TokioExecutor::new()),
             #[cfg(feature = "quinn")]
             quinn: crate::conn::quinn::Builder::new(),
+            h2c_prior_knowledge: true,
+            h2c_upgrade: false,
+            read_timeout: None,
+            write_timeout: None,
         }
     }
 
     /// Serve a connection with the given service.
+    #[inline]
     pub async fn serve_connection<I, S, B>(
         &self,
         socket: I,
@@ -69,7 +81,7 @@ impl HttpBuilder {
         graceful_stop_token: CancellationToken,
     ) -> Result<()>
     where
-        S: Service<Request<HyperBody>, Response = Response<B>> + Send,
+        S: Service<Request<HyperBody>, Response = Response<B>> + Send + Sync,
         S::Future: Send + 'static,
         S::Error: Into<Box<dyn StdError + Send + Sync>>,
         B: Body + Send + 'static,
@@ -77,16 +89,52 @@ impl HttpBuilder {
         B::Error: Into<Box<dyn StdError + Send + Sync>>,
         I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     {
+        self.serve_connection_with(socket, service, fusewire, graceful_stop_token, true, false)
+            .await
+    }
+
+    /// Serve a connection with the given service, `detect_h2` is whether to detect the HTTP/2 connection preface,
+    /// `h2c_upgrade` whether to upgrade HTTP/1.1 requests with `Upgrade: h2c` to HTTP/2.
+    pub(crate) async fn serve_connection_with<I, S, B>(
+        &self,
+        socket: I,
+        service: S,
+        fusewire: ArcFusewire,
+        graceful_stop_token: CancellationToken,
+        detect_h2: bool,
+        h2c_upgrade: bool,
+    ) -> Result<()>
+    where
+        S: Service<Request<HyperBody>, Response = Response<B>> + Send + Sync,
+        S::Future: Send + 'static,
+        S::Error: Into<Box<dyn StdError + Send + Sync>>,
+        B: Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn StdError + Send + Sync>>,
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let handling = Arc::new(AtomicUsize::new(0));
+        let service = HandlingService {
+            inner: Arc::new(service),
+            handling: handling.clone(),
+        };
+        let socket = TimeoutIo::new(socket, self.read_timeout, self.write_timeout, handling);
         #[cfg(all(feature = "http1", feature = "http2"))]
-        let (version, socket) = tokio::select! {
-            result = read_version(socket) => {
-                result?
-            },
-            _ = fusewire.fused() => {
-                tracing::info!("closing connection due to fused");
-                return Ok(());
-            },
+        let (version, socket) = if detect_h2 {
+            tokio::select! {
+                result = read_version(socket) => {
+                    result?
+                },
+                _ = fusewire.fused() => {
+                    tracing::info!("closing connection due to fused");
+                    return Ok(());
+                },
+            }
+        } else {
+            (Version::HTTP_11, Rewind::new(socket))
         };
+        #[cfg(not(all(feature = "http1", feature = "http2")))]
+        let _ = (detect_h2, h2c_upgrade);
         #[cfg(all(not(feature = "http1"), not(feature = "http2")))]
         let version = Version::HTTP_11; // Just make the compiler happy.
         #[cfg(all(feature = "http1", not(feature = "http2")))]
@@ -100,14 +148,32 @@ impl HttpBuilder {
                 return Err(std::io::Error::new(std::io::ErrorKind::Other, "http1 feature not enabled").into());
                 #[cfg(feature = "http1")]
                 {
+                    #[cfg(feature = "http2")]
+                    let upgrade = Arc::new(std::sync::Mutex::new(None));
+                    #[cfg(feature = "http2")]
+                    let http1_service = H2cUpgradeService {
+                        inner: service.clone(),
+                        enabled: h2c_upgrade,
+                        upgrade: upgrade.clone(),
+                    };
+                    #[cfg(not(feature = "http2"))]
+                    let http1_service = service;
                     let mut conn = self
                         .http1
-                        .serve_connection(TokioIo::new(socket), service)
+                        .serve_connection(TokioIo::new(socket), http1_service)
                         .with_upgrades();
 
                     tokio::select! {
                         _ = &mut conn => {
-                            // Connection completed successfully.
+                            // Connection completed successfully, unless it is upgraded to HTTP/2.
+                            #[cfg(feature = "http2")]
+                            {
+                                let upgrade = upgrade.lock().unwrap_or_else(|e| e.into_inner()).take();
+                                if let Some((on_upgrade, headers)) = upgrade {
+                                    let io = read_h2c_preface(TokioIo::new(on_upgrade.await?), headers).await?;
+                                    return self.serve_http2(io, service, &fusewire, &graceful_stop_token).await;
+                                }
+                            }
                             return Ok(());
                         },
                         _ = fusewire.fused() => {
@@ -127,26 +193,7 @@ impl HttpBuilder {
                 #[cfg(not(feature = "http2"))]
                 return Err(std::io::Error::new(std::io::ErrorKind::Other, "http2 feature not enabled").into());
                 #[cfg(feature = "http2")]
-                {
-                    let mut conn = self.http2.serve_connection(TokioIo::new(socket), service);
-
-                    tokio::select! {
-                        _ = &mut conn => {
-                            // Connection completed successfully.
-                            return Ok(());
-                        },
-                        _ = fusewire.fused() => {
-                            tracing::info!("closing connection due to fused");
-                        },
-                        _ = graceful_stop_token.cancelled() => {
-                            tracing::info!("closing connection due to inactivity");
-
-                            // Init graceful shutdown for connection (`GOAWAY` for `HTTP/2` or disabling `keep-alive` for `HTTP/1`)
-                            Pin::new(&mut conn).graceful_shutdown();
-                            conn.await.ok();
-                        }
-                    }
-                }
+                return self.serve_http2(socket, service, &fusewire, &graceful_stop_token).await;
             }
             _ => {
                 tracing::info!("unsupported protocol version: {:?}", version);
@@ -155,6 +202,267 @@ impl HttpBuilder {
 
         Ok(())
     }
+
+    #[cfg(feature = "http2")]
+    async fn serve_http2<I, S, B>(
+        &self,
+        socket: I,
+        service: S,
+        fusewire: &ArcFusewire,
+        graceful_stop_token: &CancellationToken,
+    ) -> Result<()>
+    where
+        S: Service<Request<HyperBody>, Response = Response<B>> + Send,
+        S::Future: Send + 'static,
+        S::Error: Into<Box<dyn StdError + Send + Sync>>,
+        B: Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn StdError + Send + Sync>>,
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut conn = self.http2.serve_connection(TokioIo::new(socket), service);
+
+        tokio::select! {
+            _ = &mut conn => {
+                // Connection completed successfully.
+                return Ok(());
+            },
+            _ = fusewire.fused() => {
+                tracing::info!("closing connection due to fused");
+            },
+            _ = graceful_stop_token.cancelled() => {
+                tracing::info!("closing connection due to inactivity");
+
+                // Init graceful shutdown for connection (`GOAWAY` for `HTTP/2` or disabling `keep-alive` for `HTTP/1`)
+                Pin::new(&mut conn).graceful_shutdown();
+                conn.await.ok();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The pending upgrade of a connection to HTTP/2, with the HEADERS frame of the request which asked for it.
+#[cfg(all(feature = "http1", feature = "http2"))]
+type H2cUpgrade = (hyper::upgrade::OnUpgrade, Bytes);
+
+/// Answers the HTTP/1.1 requests asking to upgrade to HTTP/2 over cleartext with `101 Switching Protocols`, the
+/// request is then served as the stream 1 of the HTTP/2 connection, see RFC 7540 section 3.2.
+///
+/// Requests with a body are answered with HTTP/1.1, as the upgrade may be ignored by servers.
+#[cfg(all(feature = "http1", feature = "http2"))]
+struct H2cUpgradeService<S> {
+    inner: S,
+    enabled: bool,
+    upgrade: Arc<std::sync::Mutex<Option<H2cUpgrade>>>,
+}
+#[cfg(all(feature = "http1", feature = "http2"))]
+impl<S, B> Service<Request<HyperBody>> for H2cUpgradeService<S>
+where
+    S: Service<Request<HyperBody>, Response = Response<B>>,
+{
+    type Response = Response<UpgradeBody<B>>;
+    type Error = S::Error;
+    type Future = UpgradeFuture<S::Future, B>;
+
+    fn call(&self, mut req: Request<HyperBody>) -> Self::Future {
+        if self.enabled && is_h2c_upgrade(&req) {
+            if let Some(headers) = h2c_headers_frame(&req) {
+                let on_upgrade = hyper::upgrade::on(&mut req);
+                *self.upgrade.lock().unwrap_or_else(|e| e.into_inner()) = Some((on_upgrade, headers));
+                let mut res = Response::new(UpgradeBody::Empty);
+                *res.status_mut() = http::StatusCode::SWITCHING_PROTOCOLS;
+                res.headers_mut()
+                    .insert(http::header::CONNECTION, http::HeaderValue::from_static("upgrade"));
+                res.headers_mut()
+                    .insert(http::header::UPGRADE, http::HeaderValue::from_static("h2c"));
+                return UpgradeFuture::Upgraded(Some(res));
+            }
+        }
+        UpgradeFuture::Inner(self.inner.call(req))
+    }
+}
+
+#[cfg(all(feature = "http1", feature = "http2"))]
+#[pin_project(project = UpgradeFutureProj)]
+enum UpgradeFuture<F, B> {
+    Inner(#[pin] F),
+    Upgraded(Option<Response<UpgradeBody<B>>>),
+}
+#[cfg(all(feature = "http1", feature = "http2"))]
+impl<F, B, E> Future for UpgradeFuture<F, B>
+where
+    F: Future<Output = std::result::Result<Response<B>, E>>,
+{
+    type Output = std::result::Result<Response<UpgradeBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            UpgradeFutureProj::Inner(inner) => inner.poll(cx).map(|res| res.map(|res| res.map(UpgradeBody::Inner))),
+            UpgradeFutureProj::Upgraded(res) => Poll::Ready(Ok(res.take().expect("future polled after completion"))),
+        }
+    }
+}
+
+/// The body of the responses of [`H2cUpgradeService`], empty for `101 Switching Protocols`.
+#[cfg(all(feature = "http1", feature = "http2"))]
+#[pin_project(project = UpgradeBodyProj)]
+enum UpgradeBody<B> {
+    Inner(#[pin] B),
+    Empty,
+}
+#[cfg(all(feature = "http1", feature = "http2"))]
+impl<B> Body for UpgradeBody<B>
+where
+    B: Body,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Self::Data>, Self::Error>>> {
+        match self.project() {
+            UpgradeBodyProj::Inner(inner) => inner.poll_frame(cx),
+            UpgradeBodyProj::Empty => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match self {
+            Self::Inner(inner) => inner.is_end_stream(),
+            Self::Empty => true,
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self {
+            Self::Inner(inner) => inner.size_hint(),
+            Self::Empty => SizeHint::with_exact(0),
+        }
+    }
+}
+
+/// Whether the request asks to upgrade to HTTP/2 over cleartext, and has no body.
+#[cfg(all(feature = "http1", feature = "http2"))]
+fn is_h2c_upgrade(req: &Request<HyperBody>) -> bool {
+    let has_token = |name, token: &str| {
+        req.headers().get_all(name).iter().any(|value| {
+            value
+                .to_str()
+                .map(|value| value.split(',').any(|v| v.trim().eq_ignore_ascii_case(token)))
+                .unwrap_or(false)
+        })
+    };
+    req.version() == Version::HTTP_11
+        && has_token(http::header::UPGRADE, "h2c")
+        && has_token(http::header::CONNECTION, "upgrade")
+        && req.headers().get_all("http2-settings").iter().count() == 1
+        && req.body().is_end_stream()
+}
+
+/// Encodes the request as the HEADERS frame of the stream 1 of the HTTP/2 connection, `None` if it is larger than
+/// the default max frame size.
+///
+/// Header fields are encoded as literals without indexing, so the HPACK state of the client is not changed.
+#[cfg(all(feature = "http1", feature = "http2"))]
+fn h2c_headers_frame(req: &Request<HyperBody>) -> Option<Bytes> {
+    use http::header::{CONNECTION, HOST, TE, TRANSFER_ENCODING, UPGRADE};
+
+    let connection_headers = req
+        .headers()
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let authority = req
+        .uri()
+        .authority()
+        .map(|authority| authority.as_str())
+        .or_else(|| req.headers().get(HOST).and_then(|host| host.to_str().ok()));
+    let path = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+
+    let mut block = Vec::new();
+    hpack_literal(&mut block, b":method", req.method().as_str().as_bytes());
+    hpack_literal(&mut block, b":scheme", b"http");
+    hpack_literal(&mut block, b":path", path.as_bytes());
+    if let Some(authority) = authority {
+        hpack_literal(&mut block, b":authority", authority.as_bytes());
+    }
+    for (name, value) in req.headers() {
+        let connection_specific = [HOST, CONNECTION, UPGRADE, TRANSFER_ENCODING].contains(name)
+            || ["http2-settings", "keep-alive", "proxy-connection"].contains(&name.as_str())
+            || (*name == TE && value != "trailers")
+            || connection_headers.iter().any(|header| header == name.as_str());
+        if !connection_specific {
+            hpack_literal(&mut block, name.as_str().as_bytes(), value.as_bytes());
+        }
+    }
+    if block.len() > 16_384 {
+        return None;
+    }
+
+    let mut frame = Vec::with_capacity(9 + block.len());
+    frame.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    // HEADERS frame with END_STREAM and END_HEADERS flags on the stream 1.
+    frame.extend_from_slice(&[0x1, 0x1 | 0x4]);
+    frame.extend_from_slice(&1u32.to_be_bytes());
+    frame.extend_from_slice(&block);
+    Some(frame.into())
+}
+
+/// Encodes a literal header field without indexing and with a new name (RFC 7541 section 6.2.2).
+#[cfg(all(feature = "http1", feature = "http2"))]
+fn hpack_literal(block: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    block.push(0);
+    for string in [name, value] {
+        // Strings are not Huffman encoded, the length is an integer with a 7 bits prefix.
+        let mut len = string.len();
+        if len < 0x7f {
+            block.push(len as u8);
+        } else {
+            block.push(0x7f);
+            len -= 0x7f;
+            while len >= 0x80 {
+                block.push((len & 0x7f) as u8 | 0x80);
+                len >>= 7;
+            }
+            block.push(len as u8);
+        }
+        block.extend_from_slice(string);
+    }
+}
+
+/// Reads the connection preface of the client after `101 Switching Protocols`, up to its first SETTINGS frame, and
+/// returns the IO replaying it, followed by the HEADERS frame of the upgraded request.
+#[cfg(all(feature = "http1", feature = "http2"))]
+async fn read_h2c_preface<A>(mut reader: A, headers: Bytes) -> IoResult<Rewind<A>>
+where
+    A: AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut buf = Vec::with_capacity(H2_PREFACE.len() + 9);
+    let mut len = H2_PREFACE.len() + 9;
+    while buf.len() < len {
+        if reader.read_buf(&mut buf).await? == 0 {
+            return Err(IoError::new(ErrorKind::UnexpectedEof, "early eof"));
+        }
+        if len == H2_PREFACE.len() + 9 && buf.len() >= len {
+            let settings = &buf[H2_PREFACE.len()..];
+            if !buf.starts_with(H2_PREFACE) || settings[3] != 0x4 {
+                return Err(IoError::new(ErrorKind::InvalidData, "invalid http2 connection preface"));
+            }
+            len += u32::from_be_bytes([0, settings[0], settings[1], settings[2]]) as usize;
+        }
+    }
+    let rest = buf.split_off(len);
+    buf.extend_from_slice(&headers);
+    buf.extend_from_slice(&rest);
+    Ok(Rewind::new_buffered(Bytes::from(buf), reader))
 }
 
 #[allow(dead_code)]
@@ -222,6 +530,10 @@ pub(crate) struct Rewind<T> {
 }
 #[allow(dead_code)]
 impl<T> Rewind<T> {
+    fn new(io: T) -> Self {
+        Rewind { pre: None, inner: io }
+    }
+
     fn new_buffered(buf: Bytes, io: T) -> Self {
         Rewind {
             pre: Some(buf),
@@ -281,3 +593,202 @@ where
         self.inner.is_write_vectored()
     }
 }
+
+/// Counts a request of a connection as being handled until it is dropped.
+#[derive(Debug)]
+struct HandlingGuard(Arc<AtomicUsize>);
+impl HandlingGuard {
+    fn new(handling: &Arc<AtomicUsize>) -> Self {
+        handling.fetch_add(1, Ordering::Relaxed);
+        Self(handling.clone())
+    }
+}
+impl Drop for HandlingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A service counting the requests being handled, from their heads being read until their responses are dropped.
+#[derive(Debug)]
+struct HandlingService<S> {
+    inner: Arc<S>,
+    handling: Arc<AtomicUsize>,
+}
+impl<S> Clone for HandlingService<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            handling: self.handling.clone(),
+        }
+    }
+}
+impl<S, B> Service<Request<HyperBody>> for HandlingService<S>
+where
+    S: Service<Request<HyperBody>, Response = Response<B>>,
+{
+    type Response = Response<HandlingBody<B>>;
+    type Error = S::Error;
+    type Future = HandlingFuture<S::Future>;
+
+    fn call(&self, req: Request<HyperBody>) -> Self::Future {
+        HandlingFuture {
+            inner: self.inner.call(req),
+            guard: Some(HandlingGuard::new(&self.handling)),
+        }
+    }
+}
+
+#[pin_project]
+struct HandlingFuture<F> {
+    #[pin]
+    inner: F,
+    guard: Option<HandlingGuard>,
+}
+impl<F, B, E> Future for HandlingFuture<F>
+where
+    F: Future<Output = std::result::Result<Response<B>, E>>,
+{
+    type Output = std::result::Result<Response<HandlingBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx));
+        let guard = this.guard.take();
+        Poll::Ready(res.map(|res| res.map(|inner| HandlingBody { inner, _guard: guard })))
+    }
+}
+
+#[pin_project]
+struct HandlingBody<B> {
+    #[pin]
+    inner: B,
+    _guard: Option<HandlingGuard>,
+}
+impl<B> Body for HandlingBody<B>
+where
+    B: Body,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Self::Data>, Self::Error>>> {
+        self.project().inner.poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// An IO failing with a `TimedOut` error when a read or a write is pending for longer than its timeout.
+///
+/// The read timeout is not armed while requests are handled: hyper keeps reading then to detect closed connections,
+/// which may not send anything until the response is written.
+#[derive(Debug)]
+pub(crate) struct TimeoutIo<T> {
+    inner: T,
+    read_timeout: Option<Duration>,
+    read_sleep: Option<Pin<Box<Sleep>>>,
+    write_timeout: Option<Duration>,
+    write_sleep: Option<Pin<Box<Sleep>>>,
+    handling: Arc<AtomicUsize>,
+}
+
+impl<T> TimeoutIo<T> {
+    /// Creates a new `TimeoutIo`, the read timeout is only armed when the `handling` count of requests is zero.
+    pub(crate) fn new(
+        inner: T,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+        handling: Arc<AtomicUsize>,
+    ) -> Self {
+        Self {
+            inner,
+            read_timeout,
+            read_sleep: None,
+            write_timeout,
+            write_sleep: None,
+            handling,
+        }
+    }
+}
+
+/// Returns a `TimedOut` error if the operation is pending and `timeout` elapsed since it started pending.
+fn poll_timeout<R>(
+    poll: Poll<IoResult<R>>,
+    timeout: Option<Duration>,
+    sleep: &mut Option<Pin<Box<Sleep>>>,
+    cx: &mut Context<'_>,
+) -> Poll<IoResult<R>> {
+    if poll.is_ready() {
+        *sleep = None;
+        return poll;
+    }
+    if let Some(timeout) = timeout {
+        let sleep = sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+        if sleep.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Err(IoError::new(ErrorKind::TimedOut, "connection timed out")));
+        }
+    }
+    Poll::Pending
+}
+
+impl<T> AsyncRead for TimeoutIo<T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        let this = &mut *self;
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if this.handling.load(Ordering::Relaxed) > 0 {
+            this.read_sleep = None;
+            return poll;
+        }
+        poll_timeout(poll, this.read_timeout, &mut this.read_sleep, cx)
+    }
+}
+
+impl<T> AsyncWrite for TimeoutIo<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = &mut *self;
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        poll_timeout(poll, this.write_timeout, &mut this.write_sleep, cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        let this = &mut *self;
+        let poll = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        poll_timeout(poll, this.write_timeout, &mut this.write_sleep, cx)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = &mut *self;
+        let poll = Pin::new(&mut this.inner).poll_flush(cx);
+        poll_timeout(poll, this.write_timeout, &mut this.write_sleep, cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = &mut *self;
+        let poll = Pin::new(&mut this.inner).poll_shutdown(cx);
+        poll_timeout(poll, this.write_timeout, &mut this.write_sleep, cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
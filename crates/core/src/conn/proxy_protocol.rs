@@ -0,0 +1,414 @@
+//! ProxyProtocolListener and it's implements.
+//!
+//! Parses the [PROXY protocol](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt) header sent by
+//! load balancers such as HAProxy or AWS NLB, and uses the client address it carries as the remote address.
+//!
+//! The header is sent before the TLS handshake, so it must be read on the raw TCP connection: TLS listeners wrap
+//! the `ProxyProtocolListener`, like `ProxyProtocolListener::new(TcpListener::new(addr)).rustls(config)`, and not the
+//! other way around.
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::conn::{Accepted, Acceptor, Holding, Listener};
+use crate::fuse::ArcFuseFactory;
+use crate::http::uri::Scheme;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// A wrapper of `Listener` which requires every connection to start with a PROXY protocol (v1 or v2) header.
+///
+/// Connections without a valid header are dropped. Only put it behind a trusted proxy, otherwise
+/// clients can spoof their address.
+///
+/// The header is read before the TLS handshake, so use the TLS methods of this listener, like
+/// [`rustls`](ProxyProtocolListener::rustls), to serve HTTPS. Binding a `ProxyProtocolListener` wrapping a TLS
+/// listener fails, since the header would be read from the decrypted stream.
+///
+/// # Example
+///
+/// ```no_run
+/// use salvo_core::prelude::*;
+/// use salvo_core::conn::ProxyProtocolListener;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let acceptor = ProxyProtocolListener::new(TcpListener::new("0.0.0.0:5800")).bind().await;
+///     Server::new(acceptor).serve(Router::new()).await;
+/// }
+/// ```
+pub struct ProxyProtocolListener<T> {
+    inner: T,
+    header_timeout: Duration,
+}
+
+impl<T> ProxyProtocolListener<T> {
+    /// Create a new `ProxyProtocolListener`.
+    #[inline]
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            header_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets the timeout for receiving the PROXY protocol header, defaults to 5 seconds.
+    #[inline]
+    pub fn header_timeout(mut self, timeout: Duration) -> Self {
+        self.header_timeout = timeout;
+        self
+    }
+
+    cfg_feature! {
+        #![feature = "rustls"]
+
+        /// Creates a new `RustlsListener` from current `ProxyProtocolListener`, the header is read before the
+        /// TLS handshake.
+        #[inline]
+        pub fn rustls<S, C, E>(self, config_stream: S) -> crate::conn::rustls::RustlsListener<S, C, Self, E>
+        where
+            S: crate::conn::IntoConfigStream<C> + Send + 'static,
+            C: TryInto<crate::conn::rustls::ServerConfig, Error = E> + Send + 'static,
+            E: std::error::Error + Send,
+            T: Listener + Send,
+        {
+            crate::conn::rustls::RustlsListener::new(config_stream, self)
+        }
+    }
+
+    cfg_feature! {
+        #![feature = "native-tls"]
+
+        /// Creates a new `NativeTlsListener` from current `ProxyProtocolListener`, the header is read before the
+        /// TLS handshake.
+        #[inline]
+        pub fn native_tls<S, C, E>(self, config_stream: S) -> crate::conn::native_tls::NativeTlsListener<S, C, Self, E>
+        where
+            S: crate::conn::IntoConfigStream<C> + Send + 'static,
+            C: TryInto<crate::conn::native_tls::Identity, Error = E> + Send + 'static,
+            E: std::error::Error + Send,
+            T: Listener + Send,
+        {
+            crate::conn::native_tls::NativeTlsListener::new(config_stream, self)
+        }
+    }
+
+    cfg_feature! {
+        #![feature = "openssl"]
+
+        /// Creates a new `OpensslListener` from current `ProxyProtocolListener`, the header is read before the
+        /// TLS handshake.
+        #[inline]
+        pub fn openssl<S, C, E>(self, config_stream: S) -> crate::conn::openssl::OpensslListener<S, C, Self, E>
+        where
+            S: crate::conn::IntoConfigStream<C> + Send + 'static,
+            C: TryInto<crate::conn::openssl::SslAcceptorBuilder, Error = E> + Send + 'static,
+            E: std::error::Error + Send,
+            T: Listener + Send,
+        {
+            crate::conn::openssl::OpensslListener::new(config_stream, self)
+        }
+    }
+}
+
+impl<T> Listener for ProxyProtocolListener<T>
+where
+    T: Listener + Send,
+    T::Acceptor: Send + 'static,
+{
+    type Acceptor = ProxyProtocolAcceptor<T::Acceptor>;
+
+    async fn try_bind(self) -> crate::Result<Self::Acceptor> {
+        let inner = self.inner.try_bind().await?;
+        if inner.holdings().iter().any(|h| h.http_scheme == Scheme::HTTPS) {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                "proxy protocol: the header must be read before tls, wrap the tcp listener instead of the tls listener",
+            )
+            .into());
+        }
+        Ok(ProxyProtocolAcceptor::new(inner, self.header_timeout))
+    }
+}
+
+/// A wrapper of `Acceptor` which parses the PROXY protocol header.
+pub struct ProxyProtocolAcceptor<T: Acceptor> {
+    inner: T,
+    header_timeout: Duration,
+    tx: UnboundedSender<Accepted<T::Conn>>,
+    rx: UnboundedReceiver<Accepted<T::Conn>>,
+}
+
+impl<T: Acceptor> ProxyProtocolAcceptor<T> {
+    /// Create a new `ProxyProtocolAcceptor`.
+    pub fn new(inner: T, header_timeout: Duration) -> Self {
+        let (tx, rx) = unbounded_channel();
+        Self {
+            inner,
+            header_timeout,
+            tx,
+            rx,
+        }
+    }
+}
+
+impl<T> Acceptor for ProxyProtocolAcceptor<T>
+where
+    T: Acceptor + Send + 'static,
+{
+    type Conn = T::Conn;
+
+    #[inline]
+    fn holdings(&self) -> &[Holding] {
+        self.inner.holdings()
+    }
+
+    async fn accept(&mut self, fuse_factory: ArcFuseFactory) -> IoResult<Accepted<Self::Conn>> {
+        let tx = self.tx.clone();
+        let header_timeout = self.header_timeout;
+        loop {
+            tokio::select! {
+                Some(accepted) = self.rx.recv() => return Ok(accepted),
+                accepted = self.inner.accept(fuse_factory.clone()) => {
+                    let mut accepted = accepted?;
+                    let tx = tx.clone();
+                    // Read the header in a separate task, so that slow clients do not block the accept loop.
+                    tokio::spawn(async move {
+                        match tokio::time::timeout(header_timeout, read_header(&mut accepted.conn)).await {
+                            Ok(Ok(addr)) => {
+                                if let Some(addr) = addr {
+                                    accepted.remote_addr = addr.into();
+                                }
+                                tx.send(accepted).ok();
+                            }
+                            Ok(Err(e)) => {
+                                tracing::debug!(error = ?e, remote_addr = %accepted.remote_addr, "invalid proxy protocol header");
+                            }
+                            Err(_) => {
+                                tracing::debug!(remote_addr = %accepted.remote_addr, "proxy protocol header timeout");
+                            }
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Read the PROXY protocol header and returns the source address.
+///
+/// Returns `None` for `LOCAL` and `UNKNOWN` connections, whose remote address should be kept.
+pub(crate) async fn read_header<R>(reader: &mut R) -> IoResult<Option<SocketAddr>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = vec![0u8; 8];
+    reader.read_exact(&mut buf).await?;
+    if buf.starts_with(V1_PREFIX) {
+        while !buf.ends_with(b"\r\n") {
+            if buf.len() >= V1_MAX_LEN {
+                return Err(invalid_header("v1 header is too long"));
+            }
+            buf.push(reader.read_u8().await?);
+        }
+        parse_v1(&buf[..buf.len() - 2])
+    } else if buf[..] == V2_SIGNATURE[..8] {
+        buf.resize(16, 0);
+        reader.read_exact(&mut buf[8..]).await?;
+        if buf[8..12] != V2_SIGNATURE[8..] {
+            return Err(invalid_header("invalid v2 signature"));
+        }
+        if buf[12] >> 4 != 2 {
+            return Err(invalid_header("unsupported version"));
+        }
+        let command = buf[12] & 0x0F;
+        let family = buf[13];
+        let len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+        let mut addrs = vec![0u8; len];
+        reader.read_exact(&mut addrs).await?;
+        match command {
+            0x0 => Ok(None),
+            0x1 => parse_v2_addrs(family, &addrs),
+            _ => Err(invalid_header("unsupported v2 command")),
+        }
+    } else {
+        Err(invalid_header("missing header"))
+    }
+}
+
+fn parse_v1(line: &[u8]) -> IoResult<Option<SocketAddr>> {
+    let line = std::str::from_utf8(line).map_err(|_| invalid_header("v1 header is not utf-8"))?;
+    let mut parts = line.split(' ').skip(1);
+    match parts.next() {
+        Some("TCP4") | Some("TCP6") => {}
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(invalid_header("unsupported v1 protocol")),
+    }
+    let ip = parts
+        .next()
+        .and_then(|ip| ip.parse::<IpAddr>().ok())
+        .ok_or_else(|| invalid_header("invalid v1 source address"))?;
+    let _destination = parts.next();
+    let port = parts
+        .next()
+        .and_then(|port| port.parse::<u16>().ok())
+        .ok_or_else(|| invalid_header("invalid v1 source port"))?;
+    Ok(Some(SocketAddr::new(ip, port)))
+}
+
+fn parse_v2_addrs(family: u8, addrs: &[u8]) -> IoResult<Option<SocketAddr>> {
+    match family >> 4 {
+        // AF_INET
+        0x1 if addrs.len() >= 12 => {
+            let ip = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+            let port = u16::from_be_bytes([addrs[8], addrs[9]]);
+            Ok(Some(SocketAddr::new(ip.into(), port)))
+        }
+        // AF_INET6
+        0x2 if addrs.len() >= 36 => {
+            let mut ip = [0u8; 16];
+            ip.copy_from_slice(&addrs[..16]);
+            let port = u16::from_be_bytes([addrs[32], addrs[33]]);
+            Ok(Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port)))
+        }
+        0x1 | 0x2 => Err(invalid_header("v2 address block is too short")),
+        // AF_UNSPEC, AF_UNIX
+        _ => Ok(None),
+    }
+}
+
+fn invalid_header(msg: &'static str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, format!("proxy protocol: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::conn::TcpListener;
+    use crate::fuse::SteadyFusewire;
+
+    #[tokio::test]
+    async fn test_read_v1_header() {
+        let mut data = &b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET /"[..];
+        let addr = read_header(&mut data).await.unwrap();
+        assert_eq!(addr, Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(data, b"GET /");
+
+        let mut data = &b"PROXY TCP6 ::1 ::1 56324 443\r\n"[..];
+        assert_eq!(
+            read_header(&mut data).await.unwrap(),
+            Some("[::1]:56324".parse().unwrap())
+        );
+
+        let mut data = &b"PROXY UNKNOWN\r\n"[..];
+        assert_eq!(read_header(&mut data).await.unwrap(), None);
+
+        let mut data = &b"GET / HTTP/1.1\r\n"[..];
+        assert!(read_header(&mut data).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_v2_header() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend([0x21, 0x11, 0, 12]);
+        header.extend([10, 0, 0, 1, 10, 0, 0, 2]);
+        header.extend(8080u16.to_be_bytes());
+        header.extend(443u16.to_be_bytes());
+        header.extend(b"GET /");
+        let mut data = &header[..];
+        let addr = read_header(&mut data).await.unwrap();
+        assert_eq!(addr, Some("10.0.0.1:8080".parse().unwrap()));
+        assert_eq!(data, b"GET /");
+
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend([0x20, 0x00, 0, 0]);
+        let mut data = &header[..];
+        assert_eq!(read_header(&mut data).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_protocol_listener() {
+        let mut acceptor = ProxyProtocolListener::new(TcpListener::new("127.0.0.1:0")).bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        tokio::spawn(async move {
+            // A client never sending the header must not block other connections.
+            let _idle = TcpStream::connect(addr).await.unwrap();
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"PROXY TCP4 203.0.113.7 127.0.0.1 40000 443\r\n")
+                .await
+                .unwrap();
+            stream.write_i32(575).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+
+        let Accepted {
+            mut conn, remote_addr, ..
+        } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert_eq!(remote_addr.into_std().unwrap(), "203.0.113.7:40000".parse().unwrap());
+        assert_eq!(conn.read_i32().await.unwrap(), 575);
+    }
+
+    #[cfg(feature = "rustls")]
+    #[tokio::test]
+    async fn test_proxy_protocol_rustls() {
+        use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig};
+        use tokio_rustls::TlsConnector;
+
+        use crate::conn::rustls::{read_trust_anchor, Keycert, RustlsConfig};
+
+        let config = || {
+            RustlsConfig::new(
+                Keycert::new()
+                    .key_from_path("certs/key.pem")
+                    .unwrap()
+                    .cert_from_path("certs/cert.pem")
+                    .unwrap(),
+            )
+        };
+        let wrong = ProxyProtocolListener::new(TcpListener::new("127.0.0.1:0").rustls(config()));
+        assert!(wrong.try_bind().await.is_err());
+
+        let mut acceptor = ProxyProtocolListener::new(TcpListener::new("127.0.0.1:0"))
+            .rustls(config())
+            .bind()
+            .await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"PROXY TCP4 203.0.113.7 127.0.0.1 40000 443\r\n")
+                .await
+                .unwrap();
+            let trust_anchor = include_bytes!("../../certs/chain.pem");
+            let client_config = ClientConfig::builder()
+                .with_root_certificates(read_trust_anchor(trust_anchor.as_slice()).unwrap())
+                .with_no_client_auth();
+            let connector = TlsConnector::from(Arc::new(client_config));
+            let mut tls_stream = connector
+                .connect(ServerName::try_from("testserver.com").unwrap(), stream)
+                .await
+                .unwrap();
+            tls_stream.write_i32(575).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+
+        let Accepted {
+            mut conn, remote_addr, ..
+        } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert_eq!(remote_addr.into_std().unwrap(), "203.0.113.7:40000".parse().unwrap());
+        assert_eq!(conn.read_i32().await.unwrap(), 575);
+    }
+}
@@ -0,0 +1,209 @@
+//! RoutedListener and it's implements.
+use std::io::Result as IoResult;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use pin_project::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_util::sync::CancellationToken;
+
+use crate::conn::{Accepted, Acceptor, Holding, HttpBuilder, Listener};
+use crate::fuse::{ArcFuseFactory, ArcFusewire};
+use crate::http::HttpConnection;
+use crate::service::HyperHandler;
+use crate::Service;
+
+/// A wrapper of `Listener` which serves its connections with its own [`Service`] instead of the one passed
+/// to [`Server::serve`](crate::Server::serve).
+///
+/// It is usually created by [`Listener::with_service`] and joined with other listeners, so one server can
+/// serve different routers on different addresses.
+///
+/// # Example
+///
+/// ```no_run
+/// use salvo_core::prelude::*;
+///
+/// #[handler]
+/// async fn metrics() -> &'static str {
+///     "metrics"
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let admin = TcpListener::new("127.0.0.1:9000").with_service(Router::with_path("metrics").get(metrics));
+///     let acceptor = TcpListener::new("0.0.0.0:5800").join(admin).bind().await;
+///     Server::new(acceptor).serve(Router::new()).await;
+/// }
+/// ```
+pub struct RoutedListener<T> {
+    inner: T,
+    service: Arc<Service>,
+}
+
+impl<T> RoutedListener<T> {
+    /// Create a new `RoutedListener`.
+    #[inline]
+    pub fn new(inner: T, service: impl Into<Service>) -> Self {
+        Self {
+            inner,
+            service: Arc::new(service.into()),
+        }
+    }
+}
+
+impl<T> Listener for RoutedListener<T>
+where
+    T: Listener + Send,
+    T::Acceptor: Send + 'static,
+{
+    type Acceptor = RoutedAcceptor<T::Acceptor>;
+
+    async fn try_bind(self) -> crate::Result<Self::Acceptor> {
+        Ok(RoutedAcceptor {
+            inner: self.inner.try_bind().await?,
+            service: self.service,
+        })
+    }
+}
+
+/// A wrapper of `Acceptor` which serves its connections with its own [`Service`].
+pub struct RoutedAcceptor<T> {
+    inner: T,
+    service: Arc<Service>,
+}
+
+impl<T> RoutedAcceptor<T> {
+    /// Create a new `RoutedAcceptor`.
+    #[inline]
+    pub fn new(inner: T, service: impl Into<Service>) -> Self {
+        Self {
+            inner,
+            service: Arc::new(service.into()),
+        }
+    }
+}
+
+impl<T> Acceptor for RoutedAcceptor<T>
+where
+    T: Acceptor + Send + 'static,
+{
+    type Conn = RoutedStream<T::Conn>;
+
+    #[inline]
+    fn holdings(&self) -> &[Holding] {
+        self.inner.holdings()
+    }
+
+    #[inline]
+    async fn accept(&mut self, fuse_factory: ArcFuseFactory) -> IoResult<Accepted<Self::Conn>> {
+        let accepted = self.inner.accept(fuse_factory).await?;
+        let service = self.service.clone();
+        Ok(accepted.map_conn(|inner| RoutedStream { inner, service }))
+    }
+}
+
+/// A stream which is served with its own [`Service`].
+#[pin_project]
+pub struct RoutedStream<C> {
+    #[pin]
+    inner: C,
+    service: Arc<Service>,
+}
+
+impl<C> HttpConnection for RoutedStream<C>
+where
+    C: HttpConnection + Send,
+{
+    async fn serve(
+        self,
+        handler: HyperHandler,
+        builder: Arc<HttpBuilder>,
+        graceful_stop_token: CancellationToken,
+    ) -> IoResult<()> {
+        let handler = handler.with_service(&self.service);
+        self.inner.serve(handler, builder, graceful_stop_token).await
+    }
+    fn fusewire(&self) -> ArcFusewire {
+        self.inner.fusewire()
+    }
+}
+
+impl<C> AsyncRead for RoutedStream<C>
+where
+    C: AsyncRead,
+{
+    #[inline]
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        self.project().inner.poll_read(cx, buf)
+    }
+}
+
+impl<C> AsyncWrite for RoutedStream<C>
+where
+    C: AsyncWrite,
+{
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    use crate::conn::{Acceptor, Listener, TcpListener};
+    use crate::prelude::*;
+
+    async fn get(addr: std::net::SocketAddr) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_routed_listener() {
+        #[handler]
+        async fn public() -> &'static str {
+            "public"
+        }
+        #[handler]
+        async fn admin() -> &'static str {
+            "admin"
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0")
+            .join(TcpListener::new("127.0.0.1:0").with_service(Router::new().get(admin)))
+            .bind()
+            .await;
+        let public_addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        let admin_addr = acceptor.holdings()[1].local_addr.clone().into_std().unwrap();
+        let server = Server::new(acceptor);
+        let handle = server.handle();
+        tokio::spawn(server.serve(Router::new().get(public)));
+
+        assert!(get(public_addr).await.ends_with("public"));
+        assert!(get(admin_addr).await.ends_with("admin"));
+        handle.stop_graceful(Duration::from_secs(1));
+    }
+}
@@ -1,16 +1,25 @@
 //! rustls module
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs::File;
-use std::future::{ready, Ready};
+use std::future::{ready, Future, Ready};
 use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
 use std::path::Path;
 use std::sync::Arc;
 
 use futures_util::stream::{once, Once, Stream};
-use tokio_rustls::rustls::crypto::ring::sign::any_supported_type;
-use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::client::danger::HandshakeSignatureValid;
+use tokio_rustls::rustls::crypto::ring::{default_provider, sign::any_supported_type};
+use tokio_rustls::rustls::crypto::CryptoProvider;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+use tokio_rustls::rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
 use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
 use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::{
+    DigitallySignedStruct, DistinguishedName, Error as RustlsError, ProtocolVersion, SignatureScheme,
+    SupportedCipherSuite, SupportedProtocolVersion, DEFAULT_VERSIONS,
+};
 
 #[cfg(feature = "quinn")]
 use tokio_rustls_old::rustls::{
@@ -30,7 +39,7 @@ pub use tokio_rustls::rustls::server::ServerConfig;
 
 use crate::conn::IntoConfigStream;
 
-use super::read_trust_anchor;
+use super::{read_crls, read_trust_anchor};
 
 /// Private key and certificate
 #[derive(Clone, Debug)]
@@ -42,6 +51,21 @@ pub struct Keycert {
     pub cert: Vec<u8>,
     /// OCSP response.
     pub ocsp_resp: Vec<u8>,
+    client_auth: Option<TlsClientAuth>,
+    #[cfg(feature = "encrypted-pem")]
+    passphrase: Option<PassphraseProvider>,
+}
+
+/// Callback providing the passphrase of an encrypted private key.
+#[cfg(feature = "encrypted-pem")]
+#[derive(Clone)]
+struct PassphraseProvider(Arc<dyn Fn() -> IoResult<String> + Send + Sync>);
+
+#[cfg(feature = "encrypted-pem")]
+impl std::fmt::Debug for PassphraseProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PassphraseProvider")
+    }
 }
 
 impl Default for Keycert {
@@ -58,6 +82,9 @@ impl Keycert {
             key: vec![],
             cert: vec![],
             ocsp_resp: vec![],
+            client_auth: None,
+            #[cfg(feature = "encrypted-pem")]
+            passphrase: None,
         }
     }
     /// Sets the Tls private key via File Path, returns [`IoError`] if the file cannot be open.
@@ -90,25 +117,156 @@ impl Keycert {
         self
     }
 
+    /// Sets the Tls private key via file path, read without blocking the async runtime.
+    #[inline]
+    pub async fn key_from_path_async(mut self, path: impl AsRef<Path>) -> IoResult<Self> {
+        self.key = tokio::fs::read(path).await?;
+        Ok(self)
+    }
+
+    /// Sets the Tls certificate via file path, read without blocking the async runtime.
+    ///
+    /// Prefer it to [`Keycert::cert_from_path`] in async code, like config streams reloading certificates, as
+    /// long certificate chains or paths on network file systems may take a while to read.
+    #[inline]
+    pub async fn cert_from_path_async(mut self, path: impl AsRef<Path>) -> IoResult<Self> {
+        self.cert = tokio::fs::read(path).await?;
+        Ok(self)
+    }
+
     /// Get ocsp_resp.
     #[inline]
     pub fn ocsp_resp(&self) -> &[u8] {
         &self.ocsp_resp
     }
 
+    cfg_feature! {
+        #![feature = "pkcs12"]
+        /// Sets the Tls private key and certificate chain via a PKCS#12 bundle, like the `.p12` or `.pfx` files
+        /// issued by many PKIs.
+        ///
+        /// The first private key of the bundle is used, along with its certificate chain.
+        pub fn pkcs12(self, data: &[u8], password: &str) -> IoResult<Self> {
+            let key_store = p12_keystore::KeyStore::from_pkcs12(data, password)
+                .map_err(|e| IoError::new(ErrorKind::InvalidData, format!("failed to read pkcs12 bundle: {e}")))?;
+            let (_, chain) = key_store
+                .private_key_chain()
+                .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "no private key found in pkcs12 bundle"))?;
+            let mut cert = String::new();
+            for certificate in chain.chain() {
+                cert.push_str(&encode_pem("CERTIFICATE", certificate.as_der())?);
+            }
+            Ok(self.key(encode_pem("PRIVATE KEY", chain.key())?).cert(cert))
+        }
+
+        /// Sets the Tls private key and certificate chain via the file path of a PKCS#12 bundle.
+        #[inline]
+        pub fn pkcs12_from_path(self, path: impl AsRef<Path>, password: &str) -> IoResult<Self> {
+            let mut data = vec![];
+            File::open(path)?.read_to_end(&mut data)?;
+            self.pkcs12(&data, password)
+        }
+    }
+
+    cfg_feature! {
+        #![feature = "encrypted-pem"]
+        /// Sets the callback providing the passphrase of the private key, when it is an encrypted PKCS#8 PEM
+        /// (`ENCRYPTED PRIVATE KEY`).
+        ///
+        /// The callback is called each time the Tls config is built, so the passphrase can be read from a secret
+        /// store instead of being kept in memory.
+        #[inline]
+        pub fn key_passphrase(mut self, provider: impl Fn() -> IoResult<String> + Send + Sync + 'static) -> Self {
+            self.passphrase = Some(PassphraseProvider(Arc::new(provider)));
+            self
+        }
+    }
+
+    /// Get the private key PEM, decrypted if it is encrypted.
+    fn key_pem(&self) -> IoResult<Cow<'_, [u8]>> {
+        #[cfg(feature = "encrypted-pem")]
+        if let Some(PassphraseProvider(provider)) = &self.passphrase {
+            let (label, der) = pkcs8::der::pem::decode_vec(&self.key).unwrap_or_default();
+            if label == "ENCRYPTED PRIVATE KEY" {
+                let passphrase = provider()?;
+                let key = pkcs8::EncryptedPrivateKeyInfo::try_from(der.as_slice())
+                    .map_err(pkcs8::Error::from)
+                    .and_then(|info| info.decrypt(passphrase.as_bytes()))
+                    .map_err(|e| IoError::new(ErrorKind::InvalidData, format!("failed to decrypt private key: {e}")))?;
+                return encode_pem("PRIVATE KEY", key.as_bytes()).map(|pem| Cow::Owned(pem.into_bytes()));
+            }
+        }
+        Ok(Cow::Borrowed(&self.key))
+    }
+
+    /// Sets the trust anchor for optional Tls client authentication of the server names using this keycert.
+    ///
+    /// It overrides the `client_auth_` options of [`RustlsConfig`] for these server names, anonymous and
+    /// authenticated clients will be accepted.
+    #[inline]
+    pub fn client_auth_optional(mut self, trust_anchor: impl Into<Vec<u8>>) -> Self {
+        self.client_auth = Some(TlsClientAuth::Optional(trust_anchor.into()));
+        self
+    }
+
+    /// Sets the trust anchor for required Tls client authentication of the server names using this keycert.
+    ///
+    /// It overrides the `client_auth_` options of [`RustlsConfig`] for these server names, only authenticated
+    /// clients will be accepted.
+    #[inline]
+    pub fn client_auth_required(mut self, trust_anchor: impl Into<Vec<u8>>) -> Self {
+        self.client_auth = Some(TlsClientAuth::Required(trust_anchor.into()));
+        self
+    }
+
+    /// Disables Tls client authentication for the server names using this keycert, even if it is enabled by the
+    /// `client_auth_` options of [`RustlsConfig`].
+    #[inline]
+    pub fn no_client_auth(mut self) -> Self {
+        self.client_auth = Some(TlsClientAuth::Off);
+        self
+    }
+
+    cfg_feature! {
+        #![feature = "self-signed"]
+        /// Generates a self-signed certificate and its private key in memory, valid for the given domains or IP
+        /// addresses, like `["localhost", "127.0.0.1"]`.
+        ///
+        /// Clients do not trust this certificate, it is only intended for local development and tests, where
+        /// features requiring HTTPS, like secure cookies or HTTP/2, need to be tried without creating certificates
+        /// by hand.
+        pub fn self_signed<I, S>(domains: I) -> IoResult<Self>
+        where
+            I: IntoIterator<Item = S>,
+            S: Into<String>,
+        {
+            let domains: Vec<String> = domains.into_iter().map(Into::into).collect();
+            if domains.is_empty() {
+                return Err(IoError::new(ErrorKind::InvalidInput, "self-signed certificate needs a domain"));
+            }
+            let cert = rcgen::generate_simple_self_signed(domains)
+                .map_err(|e| IoError::other(format!("failed to generate self-signed certificate: {e}")))?;
+            let cert_pem = cert
+                .serialize_pem()
+                .map_err(|e| IoError::other(format!("failed to serialize self-signed certificate: {e}")))?;
+            Ok(Self::new().cert(cert_pem).key(cert.serialize_private_key_pem()))
+        }
+    }
+
     fn build_certified_key(&mut self) -> IoResult<CertifiedKey> {
         let cert = rustls_pemfile::certs(&mut self.cert.as_ref())
             .flat_map(|certs| certs.into_iter().collect::<Vec<CertificateDer<'static>>>())
             .collect::<Vec<_>>();
 
+        let key_pem = self.key_pem()?;
         let key = {
-            let mut pkcs8 = rustls_pemfile::pkcs8_private_keys(&mut self.key.as_ref())
+            let mut pkcs8 = rustls_pemfile::pkcs8_private_keys(&mut key_pem.as_ref())
                 .collect::<Result<Vec<_>, _>>()
                 .map_err(|_| IoError::new(ErrorKind::Other, "failed to parse tls private keys"))?;
             if !pkcs8.is_empty() {
                 PrivateKeyDer::Pkcs8(pkcs8.remove(0))
             } else {
-                let mut rsa = rustls_pemfile::rsa_private_keys(&mut self.key.as_ref())
+                let mut rsa = rustls_pemfile::rsa_private_keys(&mut key_pem.as_ref())
                     .collect::<Result<Vec<_>, _>>()
                     .map_err(|_| IoError::new(ErrorKind::Other, "failed to parse tls private keys"))?;
 
@@ -139,13 +297,14 @@ impl Keycert {
             .map(|certs| certs.into_iter().map(CertificateOld).collect())
             .map_err(|_| IoError::new(ErrorKind::Other, "failed to parse tls certificates"))?;
 
+        let key_pem = self.key_pem()?;
         let key = {
-            let mut pkcs8 = rustls_pemfile_old::pkcs8_private_keys(&mut self.key.as_ref())
+            let mut pkcs8 = rustls_pemfile_old::pkcs8_private_keys(&mut key_pem.as_ref())
                 .map_err(|_| IoError::new(ErrorKind::Other, "failed to parse tls private keys"))?;
             if !pkcs8.is_empty() {
                 PrivateKeyOld(pkcs8.remove(0))
             } else {
-                let mut rsa = rustls_pemfile_old::rsa_private_keys(&mut self.key.as_ref())
+                let mut rsa = rustls_pemfile_old::rsa_private_keys(&mut key_pem.as_ref())
                     .map_err(|_| IoError::new(ErrorKind::Other, "failed to parse tls private keys"))?;
 
                 if !rsa.is_empty() {
@@ -171,6 +330,12 @@ impl Keycert {
     }
 }
 
+#[cfg(any(feature = "pkcs12", feature = "encrypted-pem"))]
+fn encode_pem(label: &'static str, der: &[u8]) -> IoResult<String> {
+    pkcs8::der::pem::encode_string(label, pkcs8::der::pem::LineEnding::LF, der)
+        .map_err(|e| IoError::new(ErrorKind::InvalidData, format!("failed to encode pem: {e}")))
+}
+
 /// Tls client authentication configuration.
 #[derive(Clone, Debug)]
 pub(crate) enum TlsClientAuth {
@@ -188,7 +353,13 @@ pub struct RustlsConfig {
     fallback: Option<Keycert>,
     keycerts: HashMap<String, Keycert>,
     client_auth: TlsClientAuth,
+    client_auth_crls: Vec<u8>,
+    allow_unknown_revocation_status: bool,
+    client_cert_verifier: Option<Arc<dyn ClientCertVerifier>>,
     alpn_protocols: Vec<Vec<u8>>,
+    tls_versions: Vec<&'static SupportedProtocolVersion>,
+    cipher_suites: Vec<SupportedCipherSuite>,
+    server_config: Option<ServerConfig>,
 }
 
 impl RustlsConfig {
@@ -199,7 +370,26 @@ impl RustlsConfig {
             fallback: fallback.into(),
             keycerts: HashMap::new(),
             client_auth: TlsClientAuth::Off,
+            client_auth_crls: vec![],
+            allow_unknown_revocation_status: false,
+            client_cert_verifier: None,
             alpn_protocols: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            tls_versions: DEFAULT_VERSIONS.to_vec(),
+            cipher_suites: vec![],
+            server_config: None,
+        }
+    }
+
+    /// Create new `RustlsConfig` from a fully customized [`ServerConfig`].
+    ///
+    /// The given `ServerConfig` is used as is, all other options of this builder are ignored.
+    /// This is useful when you need some options which are not exposed by `RustlsConfig`,
+    /// such as session storage, key logging or a custom certificate resolver.
+    #[inline]
+    pub fn from_server_config(server_config: ServerConfig) -> Self {
+        RustlsConfig {
+            server_config: Some(server_config),
+            ..Self::new(None)
         }
     }
 
@@ -246,22 +436,131 @@ impl RustlsConfig {
         self
     }
 
+    /// Sets the certificate revocation lists used to check client certificates via file path.
+    ///
+    /// The file may contain multiple PEM encoded CRLs. Client certificates revoked by any of them are
+    /// rejected during the handshake. To reload the CRLs, yield a new `RustlsConfig` from the config stream.
+    ///
+    /// To check client certificates against OCSP responses instead, see
+    /// [`client_cert_verifier`](RustlsConfig::client_cert_verifier).
+    pub fn client_auth_crl_path(mut self, path: impl AsRef<Path>) -> IoResult<Self> {
+        let mut data = vec![];
+        let mut file = File::open(path)?;
+        file.read_to_end(&mut data)?;
+        self.client_auth_crls = data;
+        Ok(self)
+    }
+
+    /// Sets the certificate revocation lists used to check client certificates via bytes slice.
+    ///
+    /// The data may contain multiple PEM encoded CRLs. Client certificates revoked by any of them are
+    /// rejected during the handshake. To reload the CRLs, yield a new `RustlsConfig` from the config stream.
+    #[inline]
+    pub fn client_auth_crl(mut self, crl: impl Into<Vec<u8>>) -> Self {
+        self.client_auth_crls = crl.into();
+        self
+    }
+
+    /// Accept client certificates whose revocation status can not be determined from the given CRLs.
+    ///
+    /// By default, a certificate issued by an authority without a matching CRL is rejected.
+    #[inline]
+    pub fn allow_unknown_revocation_status(mut self, allow: bool) -> Self {
+        self.allow_unknown_revocation_status = allow;
+        self
+    }
+
+    /// Sets a custom client certificate verifier, it takes precedence over all `client_auth_` options.
+    ///
+    /// Use it when you need checks not done by the builtin verifier. Clients do not staple OCSP responses and the
+    /// verifier runs synchronously during the handshake, so it can not query a responder. To check client
+    /// certificates against OCSP responses fetched ahead of time, wrap a [`WebPkiClientVerifier`] in an
+    /// [`OcspClientVerifier`](super::OcspClientVerifier).
+    #[inline]
+    pub fn client_cert_verifier(mut self, verifier: Arc<dyn ClientCertVerifier>) -> Self {
+        self.client_cert_verifier = Some(verifier);
+        self
+    }
+
     /// Add a new keycert to be used for the given SNI `name`.
+    ///
+    /// The client authentication set on the keycert, like [`Keycert::client_auth_required`], only applies to
+    /// this name, so each name can have its own policy. The one set on the fallback keycert applies to the other
+    /// names, the `client_auth_` options of `RustlsConfig` are used when no keycert sets it.
     #[inline]
     pub fn keycert(mut self, name: impl Into<String>, keycert: Keycert) -> Self {
         self.keycerts.insert(name.into(), keycert);
         self
     }
 
-    /// Add a new keycert to be used for the given SNI `name`.
+    /// Sets the ALPN protocols, in order of preference.
+    ///
+    /// Defaults to `h2` and `http/1.1`. For example, you can disable HTTP/2 by only
+    /// setting `http/1.1`.
     #[inline]
     pub fn alpn_protocols(mut self, alpn_protocols: impl Into<Vec<Vec<u8>>>) -> Self {
         self.alpn_protocols = alpn_protocols.into();
         self
     }
 
+    /// Add a ALPN protocol, it will have the lowest preference.
+    #[inline]
+    pub fn add_alpn_protocol(mut self, alpn_protocol: impl Into<Vec<u8>>) -> Self {
+        self.alpn_protocols.push(alpn_protocol.into());
+        self
+    }
+
+    /// Sets the supported TLS protocol versions.
+    ///
+    /// Defaults to TLS 1.2 and TLS 1.3.
+    #[inline]
+    pub fn tls_versions(mut self, tls_versions: impl Into<Vec<&'static SupportedProtocolVersion>>) -> Self {
+        self.tls_versions = tls_versions.into();
+        self
+    }
+
+    /// Sets the minimum TLS protocol version, lower versions will be rejected.
+    #[inline]
+    pub fn min_tls_version(mut self, version: ProtocolVersion) -> Self {
+        self.tls_versions.retain(|v| v.version.get_u16() >= version.get_u16());
+        self
+    }
+
+    /// Sets the maximum TLS protocol version, higher versions will be rejected.
+    #[inline]
+    pub fn max_tls_version(mut self, version: ProtocolVersion) -> Self {
+        self.tls_versions.retain(|v| v.version.get_u16() <= version.get_u16());
+        self
+    }
+
+    /// Sets the allowed cipher suites, in order of preference.
+    ///
+    /// If it is not set or empty, the default cipher suites of the `ring` crypto provider are used.
+    #[inline]
+    pub fn cipher_suites(mut self, cipher_suites: impl Into<Vec<SupportedCipherSuite>>) -> Self {
+        self.cipher_suites = cipher_suites.into();
+        self
+    }
+
+    /// Builds the [`ServerConfig`] on the blocking thread pool, so parsing keys and certificates does not block
+    /// the async runtime.
+    pub async fn build(self) -> IoResult<ServerConfig> {
+        tokio::task::spawn_blocking(move || self.build_server_config())
+            .await
+            .map_err(|e| IoError::new(ErrorKind::Other, e))?
+    }
+
     /// ServerConfig
     pub(crate) fn build_server_config(mut self) -> IoResult<ServerConfig> {
+        if let Some(server_config) = self.server_config {
+            return Ok(server_config);
+        }
+        let mut provider = default_provider();
+        if !self.cipher_suites.is_empty() {
+            provider.cipher_suites = self.cipher_suites.clone();
+        }
+        let provider = Arc::new(provider);
+
         let fallback = self
             .fallback
             .as_mut()
@@ -274,22 +573,32 @@ impl RustlsConfig {
             certified_keys.insert(name.clone(), Arc::new(keycert.build_certified_key()?));
         }
 
-        let client_auth = match &self.client_auth {
-            TlsClientAuth::Off => WebPkiClientVerifier::no_client_auth(),
-            TlsClientAuth::Optional(trust_anchor) => {
-                WebPkiClientVerifier::builder(read_trust_anchor(trust_anchor)?.into())
-                    .allow_unauthenticated()
-                    .build()
-                    .map_err(|e| IoError::new(ErrorKind::Other, format!("failed to build server config: {}", e)))?
-            }
-            TlsClientAuth::Required(trust_anchor) => {
-                WebPkiClientVerifier::builder(read_trust_anchor(trust_anchor)?.into())
-                    .build()
-                    .map_err(|e| IoError::new(ErrorKind::Other, format!("failed to build server config: {}", e)))?
+        let client_auth = match &self.client_cert_verifier {
+            Some(verifier) => verifier.clone(),
+            None => {
+                let default_auth = self
+                    .fallback
+                    .as_ref()
+                    .and_then(|fallback| fallback.client_auth.as_ref())
+                    .unwrap_or(&self.client_auth);
+                let default = self.build_client_verifier(default_auth, &provider)?;
+                let mut verifiers = HashMap::new();
+                for (name, keycert) in &self.keycerts {
+                    if let Some(client_auth) = &keycert.client_auth {
+                        verifiers.insert(name.clone(), self.build_client_verifier(client_auth, &provider)?);
+                    }
+                }
+                if verifiers.is_empty() {
+                    default
+                } else {
+                    Arc::new(SniClientCertVerifier { default, verifiers })
+                }
             }
         };
 
-        let mut config = ServerConfig::builder()
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_protocol_versions(&self.tls_versions)
+            .map_err(|e| IoError::new(ErrorKind::Other, format!("failed to build server config: {}", e)))?
             .with_client_cert_verifier(client_auth)
             .with_cert_resolver(Arc::new(CertResolver {
                 certified_keys,
@@ -299,9 +608,58 @@ impl RustlsConfig {
         Ok(config)
     }
 
+    fn build_client_verifier(
+        &self,
+        client_auth: &TlsClientAuth,
+        provider: &Arc<CryptoProvider>,
+    ) -> IoResult<Arc<dyn ClientCertVerifier>> {
+        match client_auth {
+            TlsClientAuth::Off => Ok(WebPkiClientVerifier::no_client_auth()),
+            TlsClientAuth::Optional(trust_anchor) | TlsClientAuth::Required(trust_anchor) => {
+                let mut builder = WebPkiClientVerifier::builder_with_provider(
+                    read_trust_anchor(trust_anchor)?.into(),
+                    provider.clone(),
+                )
+                .with_crls(read_crls(&self.client_auth_crls)?);
+                if self.allow_unknown_revocation_status {
+                    builder = builder.allow_unknown_revocation_status();
+                }
+                if let TlsClientAuth::Optional(_) = client_auth {
+                    builder = builder.allow_unauthenticated();
+                }
+                builder
+                    .build()
+                    .map_err(|e| IoError::new(ErrorKind::Other, format!("failed to build server config: {}", e)))
+            }
+        }
+    }
+
     /// ServerConfigOld
     #[cfg(feature = "quinn")]
     pub(crate) fn build_server_config_old(mut self) -> IoResult<ServerConfigOld> {
+        if self.server_config.is_some() {
+            return Err(IoError::new(
+                ErrorKind::Other,
+                "custom server config is not supported by quinn listener",
+            ));
+        }
+        if !self.client_auth_crls.is_empty() || self.client_cert_verifier.is_some() {
+            return Err(IoError::new(
+                ErrorKind::Other,
+                "client certificate revocation and custom verifier are not supported by quinn listener",
+            ));
+        }
+        if self
+            .fallback
+            .iter()
+            .chain(self.keycerts.values())
+            .any(|keycert| keycert.client_auth.is_some())
+        {
+            return Err(IoError::new(
+                ErrorKind::Other,
+                "client authentication per server name is not supported by quinn listener",
+            ));
+        }
         let fallback = self
             .fallback
             .as_mut()
@@ -337,6 +695,13 @@ impl RustlsConfig {
     }
 }
 
+impl From<ServerConfig> for RustlsConfig {
+    #[inline]
+    fn from(server_config: ServerConfig) -> Self {
+        RustlsConfig::from_server_config(server_config)
+    }
+}
+
 impl TryInto<ServerConfig> for RustlsConfig {
     type Error = IoError;
 
@@ -362,6 +727,9 @@ pub(crate) struct CertResolver {
 
 impl ResolvesServerCert for CertResolver {
     fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        SERVER_NAME
+            .try_with(|name| *name.borrow_mut() = client_hello.server_name().map(ToOwned::to_owned))
+            .ok();
         client_hello
             .server_name()
             .and_then(|name| self.certified_keys.get(name).map(Arc::clone))
@@ -369,6 +737,80 @@ impl ResolvesServerCert for CertResolver {
     }
 }
 
+tokio::task_local! {
+    // Server name of the handshake being processed, set by `CertResolver` for `SniClientCertVerifier`, which
+    // rustls does not give the client hello.
+    static SERVER_NAME: RefCell<Option<String>>;
+}
+
+/// Runs a TLS handshake in a scope tracking its server name, required by the client authentication per server name.
+pub(crate) fn scope_server_name<F: Future>(handshake: F) -> impl Future<Output = F::Output> {
+    SERVER_NAME.scope(RefCell::new(None), handshake)
+}
+
+/// Client certificate verifier delegating to the verifier of the server name of the handshake.
+#[derive(Debug)]
+struct SniClientCertVerifier {
+    default: Arc<dyn ClientCertVerifier>,
+    verifiers: HashMap<String, Arc<dyn ClientCertVerifier>>,
+}
+
+impl SniClientCertVerifier {
+    fn current(&self) -> &dyn ClientCertVerifier {
+        SERVER_NAME
+            .try_with(|name| name.borrow().as_ref().and_then(|name| self.verifiers.get(name)))
+            .ok()
+            .flatten()
+            .unwrap_or(&self.default)
+            .as_ref()
+    }
+}
+
+impl ClientCertVerifier for SniClientCertVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.current().offer_client_auth()
+    }
+
+    fn client_auth_mandatory(&self) -> bool {
+        self.current().client_auth_mandatory()
+    }
+
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        self.current().root_hint_subjects()
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, RustlsError> {
+        self.current().verify_client_cert(end_entity, intermediates, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, RustlsError> {
+        self.current().verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, RustlsError> {
+        self.current().verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.current().supported_verify_schemes()
+    }
+}
+
 impl IntoConfigStream<RustlsConfig> for RustlsConfig {
     type Stream = Once<Ready<RustlsConfig>>;
 
@@ -5,23 +5,30 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use futures_util::stream::{BoxStream, Stream, StreamExt};
 use futures_util::task::noop_waker_ref;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::task::JoinHandle;
 use tokio_rustls::server::TlsStream;
 
-use crate::conn::{Accepted, Acceptor, HandshakeStream, Holding, IntoConfigStream, Listener};
+use crate::conn::{
+    Accepted, Acceptor, HandshakeFailure, HandshakeObserver, HandshakeStats, HandshakeStream, Holding,
+    IntoConfigStream, Listener,
+};
 use crate::fuse::ArcFuseFactory;
 use crate::http::uri::Scheme;
 use crate::http::{HttpConnection, Version};
 
+use super::config::scope_server_name;
 use super::ServerConfig;
 
 /// A wrapper of `Listener` with rustls.
 pub struct RustlsListener<S, C, T, E> {
     config_stream: S,
     inner: T,
+    handshake: HandshakeObserver,
     _phantom: PhantomData<(C, E)>,
 }
 
@@ -38,9 +45,32 @@ where
         RustlsListener {
             config_stream,
             inner,
+            handshake: HandshakeObserver::default(),
             _phantom: PhantomData,
         }
     }
+
+    /// Sets the time clients have to complete the TLS handshake, slow clients are disconnected. Defaults to
+    /// [`DEFAULT_HANDSHAKE_TIMEOUT`](crate::conn::DEFAULT_HANDSHAKE_TIMEOUT), `None` disables the timeout.
+    #[inline]
+    pub fn handshake_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.handshake.timeout = timeout.into();
+        self
+    }
+
+    /// Sets a callback receiving the failed TLS handshakes, like clients sending an unknown server name, speaking
+    /// another protocol or rejected by client authentication.
+    #[inline]
+    pub fn on_handshake_failure(mut self, handler: impl Fn(&HandshakeFailure) + Send + Sync + 'static) -> Self {
+        self.handshake.on_failure = Some(Arc::new(handler));
+        self
+    }
+
+    /// Get the counters of TLS handshakes, which are updated by the acceptor once bound.
+    #[inline]
+    pub fn handshake_stats(&self) -> HandshakeStats {
+        self.handshake.stats.clone()
+    }
 }
 
 impl<S, C, T, E> Listener for RustlsListener<S, C, T, E>
@@ -54,10 +84,9 @@ where
     type Acceptor = RustlsAcceptor<BoxStream<'static, C>, C, T::Acceptor, E>;
 
     async fn try_bind(self) -> crate::Result<Self::Acceptor> {
-        Ok(RustlsAcceptor::new(
-            self.config_stream.into_stream().boxed(),
-            self.inner.try_bind().await?,
-        ))
+        let mut acceptor = RustlsAcceptor::new(self.config_stream.into_stream().boxed(), self.inner.try_bind().await?);
+        acceptor.handshake = self.handshake;
+        Ok(acceptor)
     }
 }
 
@@ -67,6 +96,9 @@ pub struct RustlsAcceptor<S, C, T, E> {
     inner: T,
     holdings: Vec<Holding>,
     tls_acceptor: Option<tokio_rustls::TlsAcceptor>,
+    /// The config being built, kept across `accept` calls so it is not lost if `accept` is cancelled.
+    building: Option<JoinHandle<Result<ServerConfig, String>>>,
+    handshake: HandshakeObserver,
     _phantom: PhantomData<(C, E)>,
 }
 impl<S, C, T, E> RustlsAcceptor<S, C, T, E>
@@ -103,9 +135,17 @@ where
             inner,
             holdings,
             tls_acceptor: None,
+            building: None,
+            handshake: HandshakeObserver::default(),
             _phantom: PhantomData,
         }
     }
+
+    /// Get the counters of TLS handshakes.
+    #[inline]
+    pub fn handshake_stats(&self) -> HandshakeStats {
+        self.handshake.stats.clone()
+    }
 }
 
 impl<S, C, T, E> Acceptor for RustlsAcceptor<S, C, T, E>
@@ -133,9 +173,23 @@ where
             config
         };
         if let Some(config) = config {
-            let config: ServerConfig = config
-                .try_into()
-                .map_err(|e| IoError::new(ErrorKind::Other, e.to_string()))?;
+            // Building the config may read files and parse certificates, which must not block the runtime.
+            // A config still being built is superseded by the newer one.
+            if let Some(building) = self.building.take() {
+                building.abort();
+            }
+            self.building = Some(tokio::task::spawn_blocking(move || {
+                let config: Result<ServerConfig, E> = config.try_into();
+                config.map_err(|e| e.to_string())
+            }));
+        }
+        if let Some(building) = &mut self.building {
+            // Awaiting the handle by reference is cancellation safe, the build is awaited again by the next call.
+            let result = building.await;
+            self.building = None;
+            let config = result
+                .map_err(|e| IoError::new(ErrorKind::Other, e))?
+                .map_err(|e| IoError::new(ErrorKind::Other, e))?;
             let tls_acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
             if self.tls_acceptor.is_some() {
                 tracing::info!("tls config changed.");
@@ -157,8 +211,12 @@ where
             ..
         } = self.inner.accept(fuse_factory).await?;
         let fusewire = conn.fusewire();
+        let handshake = scope_server_name(tls_acceptor.accept(conn));
+        let handshake = self
+            .handshake
+            .observe(handshake, local_addr.clone(), remote_addr.clone());
         Ok(Accepted {
-            conn: HandshakeStream::new(tls_acceptor.accept(conn), fusewire),
+            conn: HandshakeStream::new(handshake, fusewire),
             local_addr,
             remote_addr,
             http_version,
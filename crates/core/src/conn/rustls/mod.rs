@@ -1,6 +1,7 @@
 //! `RustlsListener` and utils.
 use std::io::{Error as IoError, ErrorKind, Result as IoResult};
 
+use tokio_rustls::rustls::pki_types::CertificateRevocationListDer;
 use tokio_rustls::rustls::RootCertStore;
 
 pub(crate) mod config;
@@ -9,6 +10,9 @@ pub use config::{Keycert, RustlsConfig, ServerConfig};
 mod listener;
 pub use listener::{RustlsAcceptor, RustlsListener};
 
+mod ocsp;
+pub use ocsp::OcspClientVerifier;
+
 pub(crate) fn read_trust_anchor(mut trust_anchor: &[u8]) -> IoResult<RootCertStore> {
     let certs = rustls_pemfile::certs(&mut trust_anchor).collect::<IoResult<Vec<_>>>()?;
     let mut store = RootCertStore::empty();
@@ -20,13 +24,17 @@ pub(crate) fn read_trust_anchor(mut trust_anchor: &[u8]) -> IoResult<RootCertSto
     Ok(store)
 }
 
+pub(crate) fn read_crls(mut crls: &[u8]) -> IoResult<Vec<CertificateRevocationListDer<'static>>> {
+    rustls_pemfile::crls(&mut crls).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::TcpStream;
-    use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig};
+    use tokio_rustls::rustls::{pki_types::ServerName, version::TLS12, ClientConfig, ProtocolVersion};
     use tokio_rustls::TlsConnector;
 
     use super::*;
@@ -64,4 +72,448 @@ mod tests {
         let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
         assert_eq!(conn.read_i32().await.unwrap(), 518);
     }
+
+    #[tokio::test]
+    async fn test_rustls_accept_cancelled_while_building() {
+        use std::time::Duration;
+
+        struct SlowConfig(RustlsConfig);
+        impl TryInto<ServerConfig> for SlowConfig {
+            type Error = IoError;
+
+            fn try_into(self) -> IoResult<ServerConfig> {
+                std::thread::sleep(Duration::from_millis(300));
+                self.0.try_into()
+            }
+        }
+
+        let config = SlowConfig(RustlsConfig::new(
+            Keycert::new()
+                .key_from_path("certs/key.pem")
+                .unwrap()
+                .cert_from_path("certs/cert.pem")
+                .unwrap(),
+        ));
+        let inner = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = inner.holdings()[0].local_addr.clone().into_std().unwrap();
+        let mut acceptor = RustlsAcceptor::new(futures_util::stream::iter([config]), inner);
+
+        // The accept future is dropped while the config is being built, the config must not be lost.
+        let accept = acceptor.accept(Arc::new(SteadyFusewire));
+        assert!(tokio::time::timeout(Duration::from_millis(50), accept).await.is_err());
+
+        tokio::spawn(async move {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let trust_anchor = include_bytes!("../../../certs/chain.pem");
+            let client_config = ClientConfig::builder()
+                .with_root_certificates(read_trust_anchor(trust_anchor.as_slice()).unwrap())
+                .with_no_client_auth();
+            let connector = TlsConnector::from(Arc::new(client_config));
+            let mut tls_stream = connector
+                .connect(ServerName::try_from("testserver.com").unwrap(), stream)
+                .await
+                .unwrap();
+            tls_stream.write_i32(518).await.unwrap();
+        });
+        let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert_eq!(conn.read_i32().await.unwrap(), 518);
+    }
+
+    #[tokio::test]
+    async fn test_rustls_build_async() {
+        let keycert = Keycert::new()
+            .key_from_path_async("certs/key.pem")
+            .await
+            .unwrap()
+            .cert_from_path_async("certs/cert.pem")
+            .await
+            .unwrap();
+        assert_eq!(keycert.cert, include_bytes!("../../../certs/cert.pem"));
+        let server_config = RustlsConfig::new(keycert).build().await.unwrap();
+        assert_eq!(server_config.alpn_protocols, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+
+        assert!(Keycert::new().key_from_path_async("certs/missing.pem").await.is_err());
+        assert!(RustlsConfig::new(Keycert::new()).build().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rustls_min_tls_version() {
+        let config = RustlsConfig::new(
+            Keycert::new()
+                .key_from_path("certs/key.pem")
+                .unwrap()
+                .cert_from_path("certs/cert.pem")
+                .unwrap(),
+        )
+        .min_tls_version(ProtocolVersion::TLSv1_3)
+        .alpn_protocols([b"http/1.1".to_vec()]);
+        let server_config = config.clone().build_server_config().unwrap();
+        assert_eq!(server_config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+
+        let mut acceptor = TcpListener::new("127.0.0.1:0").rustls(config).bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        let client = tokio::spawn(async move {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let trust_anchor = include_bytes!("../../../certs/chain.pem");
+            let client_config = ClientConfig::builder_with_protocol_versions(&[&TLS12])
+                .with_root_certificates(read_trust_anchor(trust_anchor.as_slice()).unwrap())
+                .with_no_client_auth();
+            let connector = TlsConnector::from(Arc::new(client_config));
+            connector
+                .connect(ServerName::try_from("testserver.com").unwrap(), stream)
+                .await
+                .is_ok()
+        });
+
+        let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert!(conn.read_i32().await.is_err());
+        assert!(!client.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rustls_client_peer_certs() {
+        use crate::conn::HttpBuilder;
+        use crate::http::HttpConnection;
+        use crate::prelude::*;
+
+        #[handler]
+        async fn hello(req: &mut Request) -> String {
+            let certs = req.peer_certs().unwrap();
+            format!(
+                "{}|{}",
+                certs[0].subject().unwrap(),
+                certs[0].subject_alt_names().join(",")
+            )
+        }
+
+        let config = RustlsConfig::new(
+            Keycert::new()
+                .key_from_path("certs/key.pem")
+                .unwrap()
+                .cert_from_path("certs/cert.pem")
+                .unwrap(),
+        )
+        .client_auth_required_path("certs/chain.pem")
+        .unwrap();
+        let mut acceptor = TcpListener::new("127.0.0.1:0").rustls(config).bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        let client = tokio::spawn(async move {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let trust_anchor = include_bytes!("../../../certs/chain.pem");
+            let certs = rustls_pemfile::certs(&mut include_bytes!("../../../certs/cert.pem").as_slice())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let key = rustls_pemfile::private_key(&mut include_bytes!("../../../certs/key.pem").as_slice())
+                .unwrap()
+                .unwrap();
+            let client_config = ClientConfig::builder()
+                .with_root_certificates(read_trust_anchor(trust_anchor.as_slice()).unwrap())
+                .with_client_auth_cert(certs, key)
+                .unwrap();
+            let connector = TlsConnector::from(Arc::new(client_config));
+            let mut tls_stream = connector
+                .connect(ServerName::try_from("testserver.com").unwrap(), stream)
+                .await
+                .unwrap();
+            tls_stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: testserver.com\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            tls_stream.read_to_string(&mut response).await.ok();
+            response
+        });
+
+        let Accepted {
+            conn,
+            local_addr,
+            remote_addr,
+            http_scheme,
+            ..
+        } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        let service = Service::new(Router::new().get(hello));
+        let handler = service.hyper_handler(local_addr, remote_addr, http_scheme, conn.fusewire(), None);
+        conn.serve(handler, Arc::new(HttpBuilder::new()), Default::default())
+            .await
+            .unwrap();
+        let response = client.await.unwrap();
+        assert!(response.ends_with("CN=testserver.com|testserver.com,second.testserver.com,localhost"));
+    }
+
+    #[tokio::test]
+    async fn test_rustls_client_auth_crl() {
+        async fn connect(config: RustlsConfig) -> bool {
+            let mut acceptor = TcpListener::new("127.0.0.1:0").rustls(config).bind().await;
+            let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+            tokio::spawn(async move {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                let trust_anchor = include_bytes!("../../../certs/chain.pem");
+                let certs = rustls_pemfile::certs(&mut include_bytes!("../../../certs/client-cert.pem").as_slice())
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+                let key = rustls_pemfile::private_key(&mut include_bytes!("../../../certs/client-key.pem").as_slice())
+                    .unwrap()
+                    .unwrap();
+                let client_config = ClientConfig::builder()
+                    .with_root_certificates(read_trust_anchor(trust_anchor.as_slice()).unwrap())
+                    .with_client_auth_cert(certs, key)
+                    .unwrap();
+                let connector = TlsConnector::from(Arc::new(client_config));
+                if let Ok(mut tls_stream) = connector
+                    .connect(ServerName::try_from("testserver.com").unwrap(), stream)
+                    .await
+                {
+                    tls_stream.write_i32(568).await.ok();
+                }
+            });
+
+            let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+            conn.read_i32().await.is_ok()
+        }
+
+        let config = RustlsConfig::new(
+            Keycert::new()
+                .key_from_path("certs/key.pem")
+                .unwrap()
+                .cert_from_path("certs/cert.pem")
+                .unwrap(),
+        )
+        .client_auth_required_path("certs/client-ca.pem")
+        .unwrap();
+        assert!(connect(config.clone()).await);
+        let config = config.client_auth_crl_path("certs/client-crl.pem").unwrap();
+        assert!(!connect(config).await);
+    }
+
+    #[tokio::test]
+    async fn test_rustls_client_auth_ocsp() {
+        use tokio_rustls::rustls::crypto::ring::default_provider;
+        use tokio_rustls::rustls::server::WebPkiClientVerifier;
+
+        async fn connect(config: RustlsConfig) -> bool {
+            let mut acceptor = TcpListener::new("127.0.0.1:0").rustls(config).bind().await;
+            let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+            tokio::spawn(async move {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                let trust_anchor = include_bytes!("../../../certs/chain.pem");
+                let certs =
+                    rustls_pemfile::certs(&mut include_bytes!("../../../certs/ocsp-client-cert.pem").as_slice())
+                        .collect::<Result<Vec<_>, _>>()
+                        .unwrap();
+                let key =
+                    rustls_pemfile::private_key(&mut include_bytes!("../../../certs/ocsp-client-key.pem").as_slice())
+                        .unwrap()
+                        .unwrap();
+                let client_config = ClientConfig::builder()
+                    .with_root_certificates(read_trust_anchor(trust_anchor.as_slice()).unwrap())
+                    .with_client_auth_cert(certs, key)
+                    .unwrap();
+                let connector = TlsConnector::from(Arc::new(client_config));
+                if let Ok(mut tls_stream) = connector
+                    .connect(ServerName::try_from("testserver.com").unwrap(), stream)
+                    .await
+                {
+                    tls_stream.write_i32(568).await.ok();
+                }
+            });
+
+            let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+            conn.read_i32().await.is_ok()
+        }
+
+        let issuers = include_bytes!("../../../certs/ocsp-ca.pem").as_slice();
+        let verifier = |allow_unknown_status: bool| {
+            let roots = Arc::new(read_trust_anchor(issuers).unwrap());
+            let inner = WebPkiClientVerifier::builder_with_provider(roots, Arc::new(default_provider()))
+                .build()
+                .unwrap();
+            Arc::new(
+                OcspClientVerifier::new(inner, issuers)
+                    .unwrap()
+                    .allow_unknown_status(allow_unknown_status),
+            )
+        };
+        let config = |verifier: Arc<OcspClientVerifier>| {
+            RustlsConfig::new(
+                Keycert::new()
+                    .key_from_path("certs/key.pem")
+                    .unwrap()
+                    .cert_from_path("certs/cert.pem")
+                    .unwrap(),
+            )
+            .client_cert_verifier(verifier)
+        };
+
+        let good = include_bytes!("../../../certs/ocsp-good.der");
+        let revoked = include_bytes!("../../../certs/ocsp-revoked.der");
+        let mut tampered = good.to_vec();
+        // A digit of the `producedAt` time, which is signed.
+        let produced_at = tampered.windows(2).position(|w| w == [0x18, 0x0f]).unwrap();
+        tampered[produced_at + 5] ^= 1;
+        assert!(verifier(false).update_response(tampered).is_err());
+
+        let strict = verifier(false);
+        assert!(!connect(config(strict.clone())).await);
+        strict.update_response(good).unwrap();
+        assert!(connect(config(strict.clone())).await);
+        strict.update_response(revoked).unwrap();
+        assert!(!connect(config(strict)).await);
+
+        assert!(connect(config(verifier(true))).await);
+    }
+
+    #[cfg(feature = "pkcs12")]
+    #[test]
+    fn test_rustls_pkcs12() {
+        let data = include_bytes!("../../../certs/identity.p12");
+        let keycert = Keycert::new().pkcs12(data, "mypass").unwrap();
+        assert!(rustls_pemfile::certs(&mut keycert.cert.as_slice()).count() > 0);
+        assert!(RustlsConfig::new(keycert).build_server_config().is_ok());
+        assert!(Keycert::new().pkcs12(data, "wrong").is_err());
+    }
+
+    #[cfg(feature = "encrypted-pem")]
+    #[test]
+    fn test_rustls_encrypted_pem() {
+        let keycert = |passphrase: &'static str| {
+            Keycert::new()
+                .key_from_path("certs/key-encrypted.pem")
+                .unwrap()
+                .cert_from_path("certs/cert.pem")
+                .unwrap()
+                .key_passphrase(move || Ok(passphrase.to_owned()))
+        };
+        assert!(RustlsConfig::new(keycert("mypass")).build_server_config().is_ok());
+        assert!(RustlsConfig::new(keycert("wrong")).build_server_config().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rustls_client_auth_per_server_name() {
+        let keycert = Keycert::new()
+            .key_from_path("certs/key.pem")
+            .unwrap()
+            .cert_from_path("certs/cert.pem")
+            .unwrap();
+        let config = RustlsConfig::new(None)
+            .keycert(
+                "testserver.com",
+                keycert
+                    .clone()
+                    .client_auth_required(include_bytes!("../../../certs/chain.pem").as_slice()),
+            )
+            .keycert("localhost", keycert);
+        let mut acceptor = TcpListener::new("127.0.0.1:0").rustls(config).bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        for (name, accepted) in [("localhost", true), ("testserver.com", false)] {
+            tokio::spawn(async move {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                let trust_anchor = include_bytes!("../../../certs/chain.pem");
+                let client_config = ClientConfig::builder()
+                    .with_root_certificates(read_trust_anchor(trust_anchor.as_slice()).unwrap())
+                    .with_no_client_auth();
+                let connector = TlsConnector::from(Arc::new(client_config));
+                if let Ok(mut tls_stream) = connector.connect(ServerName::try_from(name).unwrap(), stream).await {
+                    tls_stream.write_i32(200).await.ok();
+                }
+            });
+            let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+            assert_eq!(conn.read_i32().await.is_ok(), accepted, "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rustls_handshake_failures() {
+        use std::sync::Mutex;
+        use std::time::Duration;
+
+        use crate::conn::HandshakeFailureKind;
+
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let listener = TcpListener::new("127.0.0.1:0")
+            .rustls(RustlsConfig::new(
+                Keycert::new()
+                    .key_from_path("certs/key.pem")
+                    .unwrap()
+                    .cert_from_path("certs/cert.pem")
+                    .unwrap(),
+            ))
+            .handshake_timeout(Duration::from_millis(200))
+            .on_handshake_failure({
+                let failures = failures.clone();
+                move |failure| failures.lock().unwrap().push(failure.kind)
+            });
+        let stats = listener.handshake_stats();
+        let mut acceptor = listener.bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await.ok();
+        });
+        let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert!(conn.read_i32().await.is_err());
+
+        let idle = tokio::spawn(async move {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(stream);
+        });
+        let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert!(conn.read_i32().await.is_err());
+        idle.abort();
+
+        assert_eq!(stats.succeeded(), 0);
+        assert_eq!(stats.failed(HandshakeFailureKind::ProtocolMismatch), 1);
+        assert_eq!(stats.failed(HandshakeFailureKind::TimedOut), 1);
+        assert_eq!(stats.total_failed(), 2);
+        assert_eq!(
+            *failures.lock().unwrap(),
+            [HandshakeFailureKind::ProtocolMismatch, HandshakeFailureKind::TimedOut]
+        );
+    }
+
+    #[cfg(feature = "self-signed")]
+    #[tokio::test]
+    async fn test_rustls_self_signed() {
+        use tokio_rustls::rustls::RootCertStore;
+
+        let keycert = Keycert::self_signed(["localhost", "127.0.0.1"]).unwrap();
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut keycert.cert.as_slice()) {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        assert!(Keycert::self_signed(Vec::<String>::new()).is_err());
+
+        let mut acceptor = TcpListener::new("127.0.0.1:0")
+            .rustls(RustlsConfig::new(keycert))
+            .bind()
+            .await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+
+        tokio::spawn(async move {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let client_config = ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let connector = TlsConnector::from(Arc::new(client_config));
+            let mut tls_stream = connector
+                .connect(ServerName::try_from("localhost").unwrap(), stream)
+                .await
+                .unwrap();
+            tls_stream.write_i32(443).await.unwrap();
+        });
+
+        let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert_eq!(conn.read_i32().await.unwrap(), 443);
+    }
 }
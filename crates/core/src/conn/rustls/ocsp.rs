@@ -0,0 +1,525 @@
+//! OCSP checking of client certificates.
+use std::collections::HashMap;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::sync::{Arc, RwLock};
+
+use ring::digest::{digest, Algorithm, SHA1_FOR_LEGACY_USE_ONLY, SHA256};
+use tokio_rustls::rustls::client::danger::HandshakeSignatureValid;
+use tokio_rustls::rustls::crypto::ring::default_provider;
+use tokio_rustls::rustls::pki_types::{CertificateDer, UnixTime};
+use tokio_rustls::rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use tokio_rustls::rustls::{
+    CertificateError, DigitallySignedStruct, DistinguishedName, Error as RustlsError, SignatureScheme,
+};
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_ENUMERATED: u8 = 0x0a;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// `id-pkix-ocsp-basic`, 1.3.6.1.5.5.7.48.1.1.
+const OID_OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+/// `id-ce-extKeyUsage`, 2.5.29.37.
+const OID_EXT_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x25];
+/// `id-kp-OCSPSigning`, 1.3.6.1.5.5.7.3.9.
+const OID_KP_OCSP_SIGNING: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x09];
+/// `id-sha1`, 1.3.14.3.2.26.
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+/// `id-sha256`, 2.16.840.1.101.3.4.2.1.
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+
+/// Accepted difference between the clocks of the OCSP responder and of the server, in seconds.
+const MAX_CLOCK_SKEW: u64 = 300;
+
+/// A client certificate verifier checking the revocation status of client certificates with OCSP responses fetched
+/// ahead of time.
+///
+/// Clients do not staple OCSP responses and verifiers can not query responders during handshakes, so the responses
+/// of the client certificates are fetched by the application, from the responders of their issuers, and given to
+/// [`update_response`](OcspClientVerifier::update_response), which checks that they are signed by an issuer or by
+/// its delegated responder. They can be updated at any time, before their next update time.
+///
+/// The chain is validated by the `inner` verifier, usually a [`WebPkiClientVerifier`], then certificates without a
+/// fresh response are rejected, unless [`allow_unknown_status`](OcspClientVerifier::allow_unknown_status) is set.
+/// Only end entity certificates are checked.
+///
+/// [`WebPkiClientVerifier`]: tokio_rustls::rustls::server::WebPkiClientVerifier
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use salvo_core::conn::rustls::{Keycert, OcspClientVerifier, RustlsConfig};
+/// use tokio_rustls::rustls::server::WebPkiClientVerifier;
+/// use tokio_rustls::rustls::RootCertStore;
+///
+/// fn config(keycert: Keycert, roots: RootCertStore, issuers: &[u8], ocsp: &[u8]) -> std::io::Result<RustlsConfig> {
+///     let inner = WebPkiClientVerifier::builder(Arc::new(roots)).build().unwrap();
+///     let verifier = Arc::new(OcspClientVerifier::new(inner, issuers)?);
+///     // Keep `verifier` to update the response before its next update time.
+///     verifier.update_response(ocsp)?;
+///     Ok(RustlsConfig::new(keycert).client_cert_verifier(verifier))
+/// }
+/// ```
+#[derive(Debug)]
+pub struct OcspClientVerifier {
+    inner: Arc<dyn ClientCertVerifier>,
+    issuers: Vec<Issuer>,
+    statuses: RwLock<HashMap<(Vec<u8>, Vec<u8>), OcspStatus>>,
+    allow_unknown_status: bool,
+}
+
+/// An authority issuing client certificates.
+#[derive(Debug)]
+struct Issuer {
+    subject: Vec<u8>,
+    key_alg: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+/// The status of a certificate, given by an OCSP response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CertStatus {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+#[derive(Debug)]
+struct OcspStatus {
+    status: CertStatus,
+    this_update: u64,
+    next_update: Option<u64>,
+}
+
+impl OcspClientVerifier {
+    /// Create a new `OcspClientVerifier`, `issuers` are the PEM encoded certificates of the authorities issuing
+    /// client certificates, which sign the OCSP responses.
+    pub fn new(inner: Arc<dyn ClientCertVerifier>, issuers: impl AsRef<[u8]>) -> IoResult<Self> {
+        let issuers = rustls_pemfile::certs(&mut issuers.as_ref())
+            .map(|cert| {
+                let cert = cert?;
+                let cert = parse_cert(&cert).ok_or_else(|| invalid_data("invalid issuer certificate"))?;
+                Ok(Issuer {
+                    subject: cert.subject.to_vec(),
+                    key_alg: cert.key_alg.to_vec(),
+                    public_key: cert.public_key.to_vec(),
+                })
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+        if issuers.is_empty() {
+            return Err(invalid_data("no issuer certificate"));
+        }
+        Ok(Self {
+            inner,
+            issuers,
+            statuses: RwLock::new(HashMap::new()),
+            allow_unknown_status: false,
+        })
+    }
+
+    /// Accept client certificates without a fresh OCSP response, or whose status is unknown to the responder.
+    ///
+    /// By default, they are rejected.
+    #[inline]
+    pub fn allow_unknown_status(mut self, allow: bool) -> Self {
+        self.allow_unknown_status = allow;
+        self
+    }
+
+    /// Checks and stores a DER encoded OCSP response, it replaces the responses previously given for the same
+    /// certificates.
+    ///
+    /// Returns an error if the response is not successful, is not signed by an issuer or by its delegated
+    /// responder, or does not contain the status of a certificate of an issuer.
+    pub fn update_response(&self, response: impl AsRef<[u8]>) -> IoResult<()> {
+        let response = parse_response(response.as_ref()).ok_or_else(|| invalid_data("invalid ocsp response"))?;
+        let now = UnixTime::now().as_secs();
+        let issuer = self
+            .issuers
+            .iter()
+            .find(|issuer| {
+                if verify_signature(&issuer.key_alg, &issuer.public_key, &response.signed) {
+                    return true;
+                }
+                // Delegated responder, its certificate is issued by the issuer for OCSP signing.
+                response.certs.iter().filter_map(|cert| parse_cert(cert)).any(|cert| {
+                    cert.ocsp_signing
+                        && cert.issuer == issuer.subject
+                        && (cert.not_before..=cert.not_after).contains(&now)
+                        && verify_signature(&issuer.key_alg, &issuer.public_key, &cert.signed)
+                        && verify_signature(cert.key_alg, cert.public_key, &response.signed)
+                })
+            })
+            .ok_or_else(|| invalid_data("ocsp response is not signed by an issuer"))?;
+
+        let mut updated = false;
+        let mut statuses = self.statuses.write().unwrap_or_else(|e| e.into_inner());
+        for single in response.responses {
+            let Some(hash) = single.hash_alg else {
+                continue;
+            };
+            if digest(hash, &issuer.subject).as_ref() == single.issuer_name_hash
+                && digest(hash, &issuer.public_key).as_ref() == single.issuer_key_hash
+            {
+                let status = OcspStatus {
+                    status: single.status,
+                    this_update: single.this_update,
+                    next_update: single.next_update,
+                };
+                statuses.insert((issuer.subject.clone(), single.serial.to_vec()), status);
+                updated = true;
+            }
+        }
+        if updated {
+            Ok(())
+        } else {
+            Err(invalid_data("ocsp response has no status of an issuer certificate"))
+        }
+    }
+
+    fn status(&self, end_entity: &CertificateDer<'_>, now: UnixTime) -> Result<CertStatus, RustlsError> {
+        let cert = parse_cert(end_entity).ok_or(RustlsError::InvalidCertificate(CertificateError::BadEncoding))?;
+        let now = now.as_secs();
+        let statuses = self.statuses.read().unwrap_or_else(|e| e.into_inner());
+        Ok(statuses
+            .get(&(cert.issuer.to_vec(), cert.serial.to_vec()))
+            .filter(|status| {
+                status.this_update <= now + MAX_CLOCK_SKEW
+                    && status.next_update.map_or(true, |next| now <= next + MAX_CLOCK_SKEW)
+            })
+            .map(|status| status.status)
+            .unwrap_or(CertStatus::Unknown))
+    }
+}
+
+impl ClientCertVerifier for OcspClientVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.inner.offer_client_auth()
+    }
+
+    fn client_auth_mandatory(&self) -> bool {
+        self.inner.client_auth_mandatory()
+    }
+
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        self.inner.root_hint_subjects()
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, RustlsError> {
+        let verified = self.inner.verify_client_cert(end_entity, intermediates, now)?;
+        match self.status(end_entity, now)? {
+            CertStatus::Good => Ok(verified),
+            CertStatus::Revoked => Err(RustlsError::InvalidCertificate(CertificateError::Revoked)),
+            CertStatus::Unknown if self.allow_unknown_status => Ok(verified),
+            CertStatus::Unknown => Err(RustlsError::InvalidCertificate(
+                CertificateError::UnknownRevocationStatus,
+            )),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, RustlsError> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, RustlsError> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+fn invalid_data(msg: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg)
+}
+
+/// Data signed by a certificate or an OCSP response.
+struct Signed<'a> {
+    data: &'a [u8],
+    alg: &'a [u8],
+    signature: &'a [u8],
+}
+
+/// Verifies a signature with the algorithms of the default crypto provider.
+fn verify_signature(key_alg: &[u8], public_key: &[u8], signed: &Signed<'_>) -> bool {
+    default_provider()
+        .signature_verification_algorithms
+        .all
+        .iter()
+        .filter(|alg| *alg.public_key_alg_id() == *key_alg && *alg.signature_alg_id() == *signed.alg)
+        .any(|alg| alg.verify_signature(public_key, signed.data, signed.signature).is_ok())
+}
+
+/// The fields of a certificate used to check OCSP responses.
+struct Cert<'a> {
+    signed: Signed<'a>,
+    serial: &'a [u8],
+    issuer: &'a [u8],
+    subject: &'a [u8],
+    not_before: u64,
+    not_after: u64,
+    key_alg: &'a [u8],
+    public_key: &'a [u8],
+    ocsp_signing: bool,
+}
+
+fn parse_cert(der: &[u8]) -> Option<Cert<'_>> {
+    let mut input = expect(&mut &*der, TAG_SEQUENCE)?.content;
+    let tbs = expect(&mut input, TAG_SEQUENCE)?;
+    let alg = expect(&mut input, TAG_SEQUENCE)?.content;
+    let signature = bit_string(expect(&mut input, TAG_BIT_STRING)?)?;
+
+    let mut fields = tbs.content;
+    let mut serial = read_der(&mut fields)?;
+    if serial.tag == 0xa0 {
+        // Version.
+        serial = read_der(&mut fields)?;
+    }
+    if serial.tag != TAG_INTEGER {
+        return None;
+    }
+    expect(&mut fields, TAG_SEQUENCE)?;
+    let issuer = expect(&mut fields, TAG_SEQUENCE)?.raw;
+    let mut validity = expect(&mut fields, TAG_SEQUENCE)?.content;
+    let not_before = parse_time(&read_der(&mut validity)?)?;
+    let not_after = parse_time(&read_der(&mut validity)?)?;
+    let subject = expect(&mut fields, TAG_SEQUENCE)?.raw;
+    let mut spki = expect(&mut fields, TAG_SEQUENCE)?.content;
+    let key_alg = expect(&mut spki, TAG_SEQUENCE)?.content;
+    let public_key = bit_string(expect(&mut spki, TAG_BIT_STRING)?)?;
+
+    let mut ocsp_signing = false;
+    while let Some(field) = read_der(&mut fields) {
+        if field.tag != 0xa3 {
+            continue;
+        }
+        let mut extensions = expect(&mut &*field.content, TAG_SEQUENCE)?.content;
+        while let Some(extension) = read_der(&mut extensions) {
+            let mut extension = extension.content;
+            if expect(&mut extension, TAG_OID)?.content != OID_EXT_KEY_USAGE {
+                continue;
+            }
+            let mut value = read_der(&mut extension)?;
+            if value.tag == TAG_BOOLEAN {
+                value = read_der(&mut extension)?;
+            }
+            if value.tag != TAG_OCTET_STRING {
+                return None;
+            }
+            let mut usages = expect(&mut &*value.content, TAG_SEQUENCE)?.content;
+            while let Some(usage) = read_der(&mut usages) {
+                ocsp_signing |= usage.tag == TAG_OID && usage.content == OID_KP_OCSP_SIGNING;
+            }
+        }
+    }
+    Some(Cert {
+        signed: Signed {
+            data: tbs.raw,
+            alg,
+            signature,
+        },
+        serial: serial.content,
+        issuer,
+        subject,
+        not_before,
+        not_after,
+        key_alg,
+        public_key,
+        ocsp_signing,
+    })
+}
+
+/// The fields of a basic OCSP response.
+struct Response<'a> {
+    signed: Signed<'a>,
+    responses: Vec<SingleResponse<'a>>,
+    certs: Vec<&'a [u8]>,
+}
+
+struct SingleResponse<'a> {
+    hash_alg: Option<&'static Algorithm>,
+    issuer_name_hash: &'a [u8],
+    issuer_key_hash: &'a [u8],
+    serial: &'a [u8],
+    status: CertStatus,
+    this_update: u64,
+    next_update: Option<u64>,
+}
+
+fn parse_response(der: &[u8]) -> Option<Response<'_>> {
+    let mut input = expect(&mut &*der, TAG_SEQUENCE)?.content;
+    if expect(&mut input, TAG_ENUMERATED)?.content != [0] {
+        // Not successful.
+        return None;
+    }
+    let mut bytes = expect(&mut &*expect(&mut input, 0xa0)?.content, TAG_SEQUENCE)?.content;
+    if expect(&mut bytes, TAG_OID)?.content != OID_OCSP_BASIC {
+        return None;
+    }
+    let mut basic = expect(&mut &*expect(&mut bytes, TAG_OCTET_STRING)?.content, TAG_SEQUENCE)?.content;
+    let tbs = expect(&mut basic, TAG_SEQUENCE)?;
+    let alg = expect(&mut basic, TAG_SEQUENCE)?.content;
+    let signature = bit_string(expect(&mut basic, TAG_BIT_STRING)?)?;
+    let mut certs = Vec::new();
+    if let Some(field) = read_der(&mut basic) {
+        if field.tag == 0xa0 {
+            let mut list = expect(&mut &*field.content, TAG_SEQUENCE)?.content;
+            while let Some(cert) = read_der(&mut list) {
+                certs.push(cert.raw);
+            }
+        }
+    }
+
+    let mut fields = tbs.content;
+    let mut field = read_der(&mut fields)?;
+    if field.tag == 0xa0 {
+        // Version.
+        field = read_der(&mut fields)?;
+    }
+    if field.tag != 0xa1 && field.tag != 0xa2 {
+        // Responder id.
+        return None;
+    }
+    expect(&mut fields, TAG_GENERALIZED_TIME)?;
+    let mut list = expect(&mut fields, TAG_SEQUENCE)?.content;
+    let mut responses = Vec::new();
+    while let Some(single) = read_der(&mut list) {
+        let mut single = single.content;
+        let mut cert_id = expect(&mut single, TAG_SEQUENCE)?.content;
+        let mut hash_alg = expect(&mut cert_id, TAG_SEQUENCE)?.content;
+        let hash_alg = match expect(&mut hash_alg, TAG_OID)?.content {
+            OID_SHA1 => Some(&SHA1_FOR_LEGACY_USE_ONLY),
+            OID_SHA256 => Some(&SHA256),
+            _ => None,
+        };
+        let issuer_name_hash = expect(&mut cert_id, TAG_OCTET_STRING)?.content;
+        let issuer_key_hash = expect(&mut cert_id, TAG_OCTET_STRING)?.content;
+        let serial = expect(&mut cert_id, TAG_INTEGER)?.content;
+        let status = match read_der(&mut single)?.tag {
+            0x80 => CertStatus::Good,
+            0xa1 => CertStatus::Revoked,
+            _ => CertStatus::Unknown,
+        };
+        let this_update = parse_time(&read_der(&mut single)?)?;
+        let next_update = match read_der(&mut single) {
+            Some(field) if field.tag == 0xa0 => Some(parse_time(&read_der(&mut &*field.content)?)?),
+            _ => None,
+        };
+        responses.push(SingleResponse {
+            hash_alg,
+            issuer_name_hash,
+            issuer_key_hash,
+            serial,
+            status,
+            this_update,
+            next_update,
+        });
+    }
+    Some(Response {
+        signed: Signed {
+            data: tbs.raw,
+            alg,
+            signature,
+        },
+        responses,
+        certs,
+    })
+}
+
+/// A DER encoded value.
+struct Der<'a> {
+    tag: u8,
+    content: &'a [u8],
+    raw: &'a [u8],
+}
+
+/// Reads the next value of `input`, only single byte tags are supported.
+fn read_der<'a>(input: &mut &'a [u8]) -> Option<Der<'a>> {
+    let data = *input;
+    let tag = *data.first()?;
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+    let first = *data.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let len = data
+            .get(2..2 + count)?
+            .iter()
+            .fold(0, |len, byte| (len << 8) | *byte as usize);
+        (len, 2 + count)
+    };
+    let raw = data.get(..header.checked_add(len)?)?;
+    *input = &data[raw.len()..];
+    Some(Der {
+        tag,
+        content: &raw[header..],
+        raw,
+    })
+}
+
+fn expect<'a>(input: &mut &'a [u8], tag: u8) -> Option<Der<'a>> {
+    read_der(input).filter(|der| der.tag == tag)
+}
+
+/// Returns the bits of a bit string without unused bits.
+fn bit_string(der: Der<'_>) -> Option<&[u8]> {
+    match der.content.split_first()? {
+        (0, bits) => Some(bits),
+        _ => None,
+    }
+}
+
+/// Parses an `UTCTime` or a `GeneralizedTime` in UTC and without fractional seconds, as seconds since the epoch.
+fn parse_time(der: &Der<'_>) -> Option<u64> {
+    let text = std::str::from_utf8(der.content).ok()?.strip_suffix('Z')?;
+    let (year, rest) = match der.tag {
+        TAG_UTC_TIME if text.len() == 12 => {
+            let year: i64 = text.get(..2)?.parse().ok()?;
+            (if year >= 50 { 1900 + year } else { 2000 + year }, &text[2..])
+        }
+        TAG_GENERALIZED_TIME if text.len() == 14 => (text.get(..4)?.parse().ok()?, &text[4..]),
+        _ => return None,
+    };
+    let field = |idx: usize| -> Option<i64> { rest.get(idx * 2..idx * 2 + 2)?.parse().ok() };
+    let (month, day) = (field(0)?, field(1)?);
+    let (hour, minute, second) = (field(2)?, field(3)?, field(4)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    // Days from civil, http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let days = era * 146_097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719_468;
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).ok()
+}
@@ -8,7 +8,7 @@ use futures_util::{future::BoxFuture, FutureExt};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result};
 use tokio_util::sync::CancellationToken;
 
-use crate::conn::HttpBuilder;
+use crate::conn::{HttpBuilder, PeerCerts};
 use crate::fuse::{ArcFusewire, FuseEvent, Fusewire};
 use crate::http::HttpConnection;
 use crate::service::HyperHandler;
@@ -41,18 +41,52 @@ impl<S> HandshakeStream<S> {
         self.state = State::Ready(stream);
         self.fusewire.event(FuseEvent::TlsHandshaked);
     }
+
+    fn poll_handshake(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        match &mut self.state {
+            State::Handshaking(fut) => match fut.poll_unpin(cx) {
+                Poll::Ready(Ok(s)) => {
+                    self.set_state_ready(s);
+                    Poll::Ready(Ok(()))
+                }
+                Poll::Ready(Err(err)) => {
+                    self.state = State::Error;
+                    Poll::Ready(Err(err))
+                }
+                Poll::Pending => {
+                    self.fusewire.event(FuseEvent::Alive);
+                    Poll::Pending
+                }
+            },
+            State::Ready(_) => Poll::Ready(Ok(())),
+            State::Error => Poll::Ready(Err(invalid_data_error("handshake invalid data"))),
+        }
+    }
 }
 impl<S> HttpConnection for HandshakeStream<S>
 where
-    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    S: PeerCerts + AsyncRead + AsyncWrite + Send + Unpin + 'static,
 {
     async fn serve(
-        self,
-        handler: HyperHandler,
+        mut self,
+        mut handler: HyperHandler,
         builder: Arc<HttpBuilder>,
         graceful_stop_token: CancellationToken,
     ) -> IoResult<()> {
         let fusewire = self.fusewire.clone();
+        tokio::select! {
+            result = futures_util::future::poll_fn(|cx| self.poll_handshake(cx)) => result?,
+            _ = fusewire.fused() => {
+                tracing::info!("closing connection due to fused");
+                return Ok(());
+            },
+        }
+        if let State::Ready(stream) = &self.state {
+            let peer_certs = stream.peer_certs();
+            if !peer_certs.is_empty() {
+                handler.extensions.insert(Arc::new(peer_certs));
+            }
+        }
         builder
             .serve_connection(self, handler, fusewire, graceful_stop_token)
             .await
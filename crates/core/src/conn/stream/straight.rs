@@ -5,6 +5,7 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use http::Extensions;
 use pin_project::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
@@ -19,6 +20,7 @@ pub struct StraightStream<C> {
     #[pin]
     inner: C,
     fusewire: ArcFusewire,
+    extensions: Extensions,
 }
 
 impl<C> StraightStream<C>
@@ -27,7 +29,20 @@ where
 {
     /// Create a new `StraightStream`.
     pub fn new(inner: C, fusewire: ArcFusewire) -> Self {
-        Self { inner, fusewire }
+        Self {
+            inner,
+            fusewire,
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// Insert a connection level extension, it will be copied into each request of this connection.
+    #[inline]
+    pub fn insert_extension<T>(&mut self, value: T)
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.extensions.insert(value);
     }
 }
 
@@ -36,15 +51,18 @@ where
     C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
     async fn serve(
-        self,
-        handler: HyperHandler,
+        mut self,
+        mut handler: HyperHandler,
         builder: Arc<HttpBuilder>,
         graceful_stop_token: CancellationToken,
     ) -> std::io::Result<()> {
+        handler.extensions.extend(std::mem::take(&mut self.extensions));
         let fusewire = self.fusewire.clone();
         fusewire.event(FuseEvent::Alive);
+        // HTTP/2 over cleartext, with prior knowledge or `Upgrade: h2c`.
+        let (prior_knowledge, upgrade) = (builder.h2c_prior_knowledge, builder.h2c_upgrade);
         builder
-            .serve_connection(self, handler, fusewire, graceful_stop_token)
+            .serve_connection_with(self, handler, fusewire, graceful_stop_token, prior_knowledge, upgrade)
             .await
             .map_err(|e| IoError::new(ErrorKind::Other, e.to_string()))
     }
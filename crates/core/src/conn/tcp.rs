@@ -1,8 +1,11 @@
 //! TcpListener and it's implements.
 use std::io::{Error as IoError, Result as IoResult};
-use std::net::SocketAddr;
+use std::net::{SocketAddr, TcpListener as StdTcpListener};
+use std::time::Duration;
 use std::vec;
 
+use socket2::{SockRef, TcpKeepalive};
+
 use tokio::net::{TcpListener as TokioTcpListener, TcpStream, ToSocketAddrs};
 
 use crate::conn::{Holding, StraightStream};
@@ -29,13 +32,58 @@ use crate::conn::acme::AcmeListener;
 
 /// `TcpListener` is used to create a TCP connection listener.
 pub struct TcpListener<T> {
-    local_addr: T,
+    target: BindTarget<T>,
+    options: TcpOptions,
+}
+#[derive(Clone, Copy, Default, Debug)]
+struct TcpOptions {
+    nodelay: Option<bool>,
+    keepalive: Option<Duration>,
+    #[cfg(unix)]
+    reuse_port: bool,
+}
+enum BindTarget<T> {
+    Addr(T),
+    Std(StdTcpListener),
 }
 impl<T: ToSocketAddrs + Send> TcpListener<T> {
     /// Bind to socket address.
     #[inline]
     pub fn new(local_addr: T) -> Self {
-        TcpListener { local_addr }
+        TcpListener {
+            target: BindTarget::Addr(local_addr),
+            options: TcpOptions::default(),
+        }
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on accepted connections.
+    #[inline]
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.options.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Enables TCP keepalive on accepted connections, `time` is the idle time before keepalive probes are sent.
+    ///
+    /// Dead peers, for example clients behind a NAT which dropped the connection, are detected and
+    /// their connections are closed.
+    #[inline]
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        self.options.keepalive = Some(time);
+        self
+    }
+
+    cfg_feature! {
+        #![unix]
+
+        /// Sets the `SO_REUSEPORT` option before binding, so several processes can listen on the same address.
+        ///
+        /// It is required for zero-downtime upgrades, see [`upgrade`](crate::upgrade).
+        #[inline]
+        pub fn reuse_port(mut self, reuse_port: bool) -> Self {
+            self.options.reuse_port = reuse_port;
+            self
+        }
     }
 
     cfg_feature! {
@@ -93,6 +141,52 @@ impl<T: ToSocketAddrs + Send> TcpListener<T> {
         }
     }
 }
+impl TcpListener<SocketAddr> {
+    /// Creates a new `TcpListener` from an already bound standard library listener.
+    ///
+    /// This is useful when the socket is bound by a supervisor, for example to hand it over to a
+    /// new process without dropping connections.
+    #[inline]
+    pub fn from_std(listener: StdTcpListener) -> Self {
+        TcpListener {
+            target: BindTarget::Std(listener),
+            options: TcpOptions::default(),
+        }
+    }
+
+    cfg_feature! {
+        #![unix]
+
+        /// Creates a new `TcpListener` from the file descriptor of a bound TCP socket.
+        #[inline]
+        pub fn from_fd(fd: std::os::unix::io::OwnedFd) -> Self {
+            Self::from_std(StdTcpListener::from(fd))
+        }
+    }
+
+    cfg_feature! {
+        #![feature = "listenfd"]
+
+        /// Creates listeners from sockets passed by systemd socket activation or a compatible
+        /// supervisor such as `systemfd`.
+        ///
+        /// Reads the `LISTEN_PID` and `LISTEN_FDS` environment variables, returns an empty `Vec`
+        /// if they are not set or not meant for the current process. Sockets which are not TCP
+        /// listeners are skipped.
+        pub fn from_listen_fds() -> Vec<Self> {
+            let mut fds = listenfd::ListenFd::from_env();
+            let mut listeners = Vec::with_capacity(fds.len());
+            for idx in 0..fds.len() {
+                match fds.take_tcp_listener(idx) {
+                    Ok(Some(listener)) => listeners.push(Self::from_std(listener)),
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!(error = ?e, idx, "skip inherited socket"),
+                }
+            }
+            listeners
+        }
+    }
+}
 impl<T> Listener for TcpListener<T>
 where
     T: ToSocketAddrs + Send,
@@ -100,13 +194,48 @@ where
     type Acceptor = TcpAcceptor;
 
     async fn try_bind(self) -> crate::Result<Self::Acceptor> {
-        Ok(TokioTcpListener::bind(self.local_addr).await?.try_into()?)
+        let inner = match self.target {
+            #[cfg(unix)]
+            BindTarget::Addr(local_addr) if self.options.reuse_port => bind_reuse_port(local_addr).await?,
+            BindTarget::Addr(local_addr) => TokioTcpListener::bind(local_addr).await?,
+            BindTarget::Std(listener) => {
+                listener.set_nonblocking(true)?;
+                TokioTcpListener::from_std(listener)?
+            }
+        };
+        let mut acceptor: TcpAcceptor = inner.try_into()?;
+        acceptor.options = self.options;
+        Ok(acceptor)
     }
 }
+#[cfg(unix)]
+async fn bind_reuse_port(local_addr: impl ToSocketAddrs) -> IoResult<TokioTcpListener> {
+    use socket2::{Domain, Socket, Type};
+
+    let mut last_err = None;
+    for addr in tokio::net::lookup_host(local_addr).await? {
+        let bind = || {
+            let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+            socket.set_reuse_address(true)?;
+            socket.set_reuse_port(true)?;
+            socket.set_nonblocking(true)?;
+            socket.bind(&addr.into())?;
+            socket.listen(1024)?;
+            TokioTcpListener::from_std(socket.into())
+        };
+        match bind() {
+            Ok(listener) => return Ok(listener),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| IoError::new(std::io::ErrorKind::InvalidInput, "could not resolve to any address")))
+}
+
 /// `TcpAcceptor` is used to accept a TCP connection.
 pub struct TcpAcceptor {
     inner: TokioTcpListener,
     holdings: Vec<Holding>,
+    options: TcpOptions,
 }
 
 impl TcpAcceptor {
@@ -130,6 +259,16 @@ impl TcpAcceptor {
     pub fn set_ttl(&self, ttl: u32) -> IoResult<()> {
         self.inner.set_ttl(ttl)
     }
+
+    fn set_options(&self, conn: &TcpStream) -> IoResult<()> {
+        if let Some(nodelay) = self.options.nodelay {
+            conn.set_nodelay(nodelay)?;
+        }
+        if let Some(time) = self.options.keepalive {
+            SockRef::from(conn).set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?;
+        }
+        Ok(())
+    }
 }
 
 impl TryFrom<TokioTcpListener> for TcpAcceptor {
@@ -144,6 +283,7 @@ impl TryFrom<TokioTcpListener> for TcpAcceptor {
         Ok(TcpAcceptor {
             inner,
             holdings: vec![holding],
+            options: TcpOptions::default(),
         })
     }
 }
@@ -158,7 +298,11 @@ impl Acceptor for TcpAcceptor {
 
     #[inline]
     async fn accept(&mut self, fuse_factory: ArcFuseFactory) -> IoResult<Accepted<Self::Conn>> {
-        self.inner.accept().await.map(move |(conn, remote_addr)| Accepted {
+        let (conn, remote_addr) = self.inner.accept().await?;
+        if let Err(e) = self.set_options(&conn) {
+            tracing::warn!(error = ?e, "failed to set tcp options");
+        }
+        Ok(Accepted {
             conn: StraightStream::new(conn, fuse_factory.create(TransProto::Tcp)),
             local_addr: self.holdings[0].local_addr.clone(),
             remote_addr: remote_addr.into(),
@@ -191,4 +335,41 @@ mod tests {
         let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
         assert_eq!(conn.read_i32().await.unwrap(), 150);
     }
+
+    #[tokio::test]
+    async fn test_tcp_listener_from_std() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut acceptor = TcpListener::from_std(listener).bind().await;
+        assert_eq!(acceptor.local_addr().unwrap(), addr);
+        tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_i32(576).await.unwrap();
+        });
+
+        let Accepted { mut conn, .. } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        assert_eq!(conn.read_i32().await.unwrap(), 576);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_listener_options() {
+        let acceptor = TcpListener::new("127.0.0.1:0")
+            .nodelay(true)
+            .keepalive(Duration::from_secs(60))
+            .bind()
+            .await;
+        let conn = TcpStream::connect(acceptor.local_addr().unwrap()).await.unwrap();
+        acceptor.set_options(&conn).unwrap();
+        assert!(conn.nodelay().unwrap());
+        assert!(SockRef::from(&conn).keepalive().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tcp_listener_reuse_port() {
+        let first = TcpListener::new("127.0.0.1:0").reuse_port(true).bind().await;
+        let addr = first.local_addr().unwrap();
+        let second = TcpListener::new(addr).reuse_port(true).bind().await;
+        assert_eq!(second.local_addr().unwrap(), addr);
+    }
 }
@@ -0,0 +1,206 @@
+//! Timeout and failure reporting of the TLS handshakes of rustls based listeners.
+use std::fmt::{self, Debug, Display, Formatter};
+use std::future::Future;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_rustls::rustls::Error as RustlsError;
+
+use crate::conn::SocketAddr;
+
+/// Default timeout of TLS handshakes.
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const KIND_COUNT: usize = 6;
+
+/// Reason of a failed TLS handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HandshakeFailureKind {
+    /// The client did not complete the handshake before the timeout.
+    TimedOut,
+    /// No certificate matches the server name (SNI) sent by the client.
+    UnknownServerName,
+    /// The client does not support the protocol versions, cipher suites or application protocols of the server, or
+    /// does not speak TLS at all.
+    ProtocolMismatch,
+    /// The client certificate is missing or rejected.
+    ClientAuthRejected,
+    /// The client closed the connection during the handshake.
+    Closed,
+    /// Other errors.
+    Other,
+}
+impl HandshakeFailureKind {
+    /// Get the name of the kind, like `timed_out`, usable as a label of metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::TimedOut => "timed_out",
+            Self::UnknownServerName => "unknown_server_name",
+            Self::ProtocolMismatch => "protocol_mismatch",
+            Self::ClientAuthRejected => "client_auth_rejected",
+            Self::Closed => "closed",
+            Self::Other => "other",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::TimedOut => 0,
+            Self::UnknownServerName => 1,
+            Self::ProtocolMismatch => 2,
+            Self::ClientAuthRejected => 3,
+            Self::Closed => 4,
+            Self::Other => 5,
+        }
+    }
+
+    fn classify(e: &IoError) -> Self {
+        if e.kind() == ErrorKind::TimedOut {
+            return Self::TimedOut;
+        }
+        match e.get_ref().and_then(|inner| inner.downcast_ref::<RustlsError>()) {
+            Some(
+                RustlsError::NoCertificatesPresented
+                | RustlsError::InvalidCertificate(_)
+                | RustlsError::InvalidCertRevocationList(_),
+            ) => Self::ClientAuthRejected,
+            Some(RustlsError::UnsupportedNameType) => Self::UnknownServerName,
+            // Returned by rustls when the certificate resolver finds no certificate for the server name.
+            Some(RustlsError::General(message)) if message.contains("certificate chain") => Self::UnknownServerName,
+            Some(
+                RustlsError::PeerIncompatible(_)
+                | RustlsError::NoApplicationProtocol
+                | RustlsError::InvalidMessage(_)
+                | RustlsError::InappropriateMessage { .. }
+                | RustlsError::InappropriateHandshakeMessage { .. },
+            ) => Self::ProtocolMismatch,
+            Some(_) => Self::Other,
+            None if matches!(
+                e.kind(),
+                ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset | ErrorKind::BrokenPipe
+            ) =>
+            {
+                Self::Closed
+            }
+            None => Self::Other,
+        }
+    }
+}
+impl Display for HandshakeFailureKind {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A failed TLS handshake, passed to the callback set with `on_handshake_failure` of the listeners.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct HandshakeFailure {
+    /// Reason of the failure.
+    pub kind: HandshakeFailureKind,
+    /// Local address of the connection.
+    pub local_addr: SocketAddr,
+    /// Remote address of the connection.
+    pub remote_addr: SocketAddr,
+    /// Error message.
+    pub error: String,
+}
+
+/// Counters of the TLS handshakes of a listener, shared by its clones.
+#[derive(Clone, Debug, Default)]
+pub struct HandshakeStats {
+    succeeded: Arc<AtomicU64>,
+    failed: Arc<[AtomicU64; KIND_COUNT]>,
+}
+impl HandshakeStats {
+    /// Get the number of completed handshakes.
+    #[inline]
+    pub fn succeeded(&self) -> u64 {
+        self.succeeded.load(Ordering::Relaxed)
+    }
+    /// Get the number of handshakes failed for a reason.
+    #[inline]
+    pub fn failed(&self, kind: HandshakeFailureKind) -> u64 {
+        self.failed[kind.index()].load(Ordering::Relaxed)
+    }
+    /// Get the number of failed handshakes.
+    #[inline]
+    pub fn total_failed(&self) -> u64 {
+        self.failed.iter().map(|count| count.load(Ordering::Relaxed)).sum()
+    }
+}
+
+type FailureCallback = Arc<dyn Fn(&HandshakeFailure) + Send + Sync>;
+
+/// Applies the timeout to TLS handshakes, counts them and reports their failures.
+#[derive(Clone)]
+pub(crate) struct HandshakeObserver {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) on_failure: Option<FailureCallback>,
+    pub(crate) stats: HandshakeStats,
+}
+impl Default for HandshakeObserver {
+    fn default() -> Self {
+        Self {
+            timeout: Some(DEFAULT_HANDSHAKE_TIMEOUT),
+            on_failure: None,
+            stats: HandshakeStats::default(),
+        }
+    }
+}
+impl Debug for HandshakeObserver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandshakeObserver")
+            .field("timeout", &self.timeout)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl HandshakeObserver {
+    pub(crate) fn observe<F, S>(
+        &self,
+        handshake: F,
+        local_addr: SocketAddr,
+        remote_addr: SocketAddr,
+    ) -> impl Future<Output = IoResult<S>> + Send + 'static
+    where
+        F: Future<Output = IoResult<S>> + Send + 'static,
+        S: Send + 'static,
+    {
+        let observer = self.clone();
+        async move {
+            let result = match observer.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, handshake)
+                    .await
+                    .unwrap_or_else(|_| Err(IoError::new(ErrorKind::TimedOut, "tls handshake timed out"))),
+                None => handshake.await,
+            };
+            match &result {
+                Ok(_) => {
+                    observer.stats.succeeded.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => observer.report(e, local_addr, remote_addr),
+            }
+            result
+        }
+    }
+
+    fn report(&self, e: &IoError, local_addr: SocketAddr, remote_addr: SocketAddr) {
+        let kind = HandshakeFailureKind::classify(e);
+        self.stats.failed[kind.index()].fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(kind = kind.as_str(), %local_addr, %remote_addr, error = %e, "tls handshake failed");
+        if let Some(on_failure) = &self.on_failure {
+            on_failure(&HandshakeFailure {
+                kind,
+                local_addr,
+                remote_addr,
+                error: e.to_string(),
+            });
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! UnixListener module
-use std::fs::{set_permissions, Permissions};
-use std::io::Result as IoResult;
+use std::fs::{remove_file, set_permissions, symlink_metadata, Permissions};
+use std::io::{ErrorKind, Result as IoResult};
+use std::os::unix::fs::FileTypeExt;
 use std::path::Path;
 
 use http::uri::Scheme;
@@ -55,6 +56,7 @@ where
     type Acceptor = UnixAcceptor;
 
     async fn try_bind(self) -> crate::Result<Self::Acceptor> {
+        remove_stale_socket(self.path.as_ref()).await?;
         let inner = match (self.permissions, self.owner) {
             (Some(permissions), Some((uid, gid))) => {
                 let inner = TokioUnixListener::bind(self.path.clone())?;
@@ -87,6 +89,20 @@ where
     }
 }
 
+/// Remove the socket file left by a previous process, if no one is listening on it anymore.
+async fn remove_stale_socket(path: &Path) -> IoResult<()> {
+    match symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => match UnixStream::connect(path).await {
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                tracing::info!(path = ?path, "removing stale unix socket file");
+                remove_file(path)
+            }
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
 /// `UnixAcceptor` is used to accept a Unix socket connection.
 pub struct UnixAcceptor {
     inner: TokioUnixListener,
@@ -104,12 +120,20 @@ impl Acceptor for UnixAcceptor {
 
     #[inline]
     async fn accept(&mut self, fuse_factory: ArcFuseFactory) -> IoResult<Accepted<Self::Conn>> {
-        self.inner.accept().await.map(move |(conn, remote_addr)| Accepted {
-            conn: StraightStream::new(conn, fuse_factory.create(TransProto::Tcp)),
-            local_addr: self.holdings[0].local_addr.clone(),
-            remote_addr: remote_addr.into(),
-            http_version: Version::HTTP_11,
-            http_scheme: Scheme::HTTP,
+        self.inner.accept().await.map(move |(conn, remote_addr)| {
+            let peer_cred = conn.peer_cred();
+            let mut conn = StraightStream::new(conn, fuse_factory.create(TransProto::Tcp));
+            match peer_cred {
+                Ok(peer_cred) => conn.insert_extension(peer_cred),
+                Err(e) => tracing::warn!(error = ?e, "failed to get peer credentials of unix socket"),
+            }
+            Accepted {
+                conn,
+                local_addr: self.holdings[0].local_addr.clone(),
+                remote_addr: remote_addr.into(),
+                http_version: Version::HTTP_11,
+                http_scheme: Scheme::HTTP,
+            }
         })
     }
 }
@@ -138,4 +162,61 @@ mod tests {
         assert_eq!(conn.read_i32().await.unwrap(), 518);
         std::fs::remove_file(sock_file).unwrap();
     }
+
+    #[tokio::test]
+    async fn test_unix_listener_stale_socket() {
+        let sock_file = "/tmp/test-salvo-stale.sock";
+        let _ = std::fs::remove_file(sock_file);
+        drop(UnixListener::new(sock_file).bind().await);
+        assert!(Path::new(sock_file).exists());
+
+        // The socket file left by the dropped acceptor is removed on bind.
+        let acceptor = UnixListener::new(sock_file).bind().await;
+        // The socket file of a live acceptor is kept.
+        assert!(UnixListener::new(sock_file).try_bind().await.is_err());
+        drop(acceptor);
+        std::fs::remove_file(sock_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unix_peer_cred() {
+        use crate::conn::HttpBuilder;
+        use crate::http::HttpConnection;
+        use crate::prelude::*;
+
+        #[handler]
+        async fn whoami(req: &mut Request) -> String {
+            req.peer_cred().unwrap().uid().to_string()
+        }
+
+        let sock_file = "/tmp/test-salvo-peer-cred.sock";
+        let _ = std::fs::remove_file(sock_file);
+        let mut acceptor = UnixListener::new(sock_file).bind().await;
+        let client = tokio::spawn(async move {
+            let mut stream = tokio::net::UnixStream::connect(sock_file).await.unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.ok();
+            response
+        });
+
+        let Accepted {
+            conn,
+            local_addr,
+            remote_addr,
+            http_scheme,
+            ..
+        } = acceptor.accept(Arc::new(SteadyFusewire)).await.unwrap();
+        let service = Service::new(Router::new().get(whoami));
+        let handler = service.hyper_handler(local_addr, remote_addr, http_scheme, conn.fusewire(), None);
+        conn.serve(handler, Arc::new(HttpBuilder::new()), Default::default())
+            .await
+            .unwrap();
+        let response = client.await.unwrap();
+        assert!(response.ends_with(&nix::unistd::getuid().to_string()));
+        std::fs::remove_file(sock_file).unwrap();
+    }
 }
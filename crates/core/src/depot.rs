@@ -1,6 +1,8 @@
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
 use std::collections::HashMap;
 use std::fmt::{self, Formatter};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
 
 /// `Depot` is for store temp data of current request.
 ///
@@ -33,11 +35,75 @@ use std::fmt::{self, Formatter};
 #[derive(Default)]
 pub struct Depot {
     map: HashMap<String, Box<dyn Any + Send + Sync>>,
+    types: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    scopes: Vec<Vec<Change>>,
 }
 
-#[inline]
-fn type_key<T: 'static>() -> String {
-    format!("{:?}", TypeId::of::<T>())
+/// A key changed in a scope, with its value before the change.
+type Change = (Key, Option<Box<dyn Any + Send + Sync>>);
+
+/// Key of a value changed in a scope.
+#[derive(PartialEq, Eq)]
+enum Key {
+    Name(String),
+    Type(TypeId),
+}
+
+/// A typed key of a value stored in [`Depot`].
+///
+/// The value is stored with the name of the key, so it can be accessed by name too, but the typed accessors
+/// like [`Depot::get_key`] don't need to repeat the type of the value.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_core::DepotKey;
+///
+/// const USER_ID: DepotKey<u64> = DepotKey::new("user_id");
+///
+/// let mut depot = Depot::new();
+/// depot.insert_key(&USER_ID, 7);
+/// assert_eq!(depot.get_key(&USER_ID), Some(&7));
+/// assert_eq!(depot.get::<u64>("user_id").ok(), Some(&7));
+/// ```
+pub struct DepotKey<T> {
+    name: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> DepotKey<T> {
+    /// Create a new `DepotKey` with the given name.
+    #[inline]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the name of the key.
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Clone for DepotKey<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for DepotKey<T> {}
+
+impl<T> fmt::Debug for DepotKey<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DepotKey")
+            .field("name", &self.name)
+            .field("type", &type_name::<T>())
+            .finish()
+    }
 }
 
 impl Depot {
@@ -46,10 +112,12 @@ impl Depot {
     /// The depot is initially created with a capacity of 0, so it will not allocate until it is first inserted into.
     #[inline]
     pub fn new() -> Depot {
-        Depot { map: HashMap::new() }
+        Depot::default()
     }
 
     /// Get reference to depot inner map.
+    ///
+    /// **Note: It only contains the values inserted with a key, injected values are stored by type.**
     #[inline]
     pub fn inner(&self) -> &HashMap<String, Box<dyn Any + Send + Sync>> {
         &self.map
@@ -62,6 +130,7 @@ impl Depot {
     pub fn with_capacity(capacity: usize) -> Self {
         Depot {
             map: HashMap::with_capacity(capacity),
+            ..Default::default()
         }
     }
     /// Returns the number of elements the depot can hold without reallocating.
@@ -70,10 +139,76 @@ impl Depot {
         self.map.capacity()
     }
 
+    /// Records the previous value of a key changed in the current scope, only the first change is recorded.
+    fn record(&mut self, key: Key, previous: Option<Box<dyn Any + Send + Sync>>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.iter().any(|(changed, _)| *changed == key) {
+                scope.push((key, previous));
+            }
+        }
+    }
+    fn insert_named(&mut self, key: String, value: Box<dyn Any + Send + Sync>) {
+        if self.scopes.is_empty() {
+            self.map.insert(key, value);
+        } else {
+            let previous = self.map.insert(key.clone(), value);
+            self.record(Key::Name(key), previous);
+        }
+    }
+    fn remove_named(&mut self, key: &str) -> Option<Box<dyn Any + Send + Sync>> {
+        let value = self.map.remove(key);
+        if value.is_some() && !self.scopes.is_empty() {
+            self.record(Key::Name(key.to_owned()), None);
+        }
+        value
+    }
+    fn insert_typed(&mut self, id: TypeId, value: Box<dyn Any + Send + Sync>) {
+        let previous = self.types.insert(id, value);
+        self.record(Key::Type(id), previous);
+    }
+    fn remove_typed(&mut self, id: TypeId) -> Option<Box<dyn Any + Send + Sync>> {
+        let value = self.types.remove(&id);
+        if value.is_some() {
+            self.record(Key::Type(id), None);
+        }
+        value
+    }
+
+    /// Starts a scope of the depot, like a child depot for sub-handlers.
+    ///
+    /// The returned [`DepotScope`] dereferences to this depot, so values of the parent can be read in the scope.
+    /// When it is dropped, the values inserted, replaced or deleted in the scope are reverted, while the values of the
+    /// parent taken by [`remove`](Depot::remove) or [`scrape`](Depot::scrape) in the scope are moved out of the
+    /// depot. Values mutated in place with [`get_mut`](Depot::get_mut)
+    /// or [`obtain_mut`](Depot::obtain_mut) are not reverted.
+    ///
+    /// Starting a scope does not allocate nor copy the values of the depot.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use salvo_core::prelude::*;
+    ///
+    /// let mut depot = Depot::new();
+    /// depot.insert("user", "alice");
+    /// {
+    ///     let mut child = depot.scope();
+    ///     child.insert("user", "bob").insert("role", "admin");
+    ///     assert_eq!(child.get::<&str>("user").ok(), Some(&"bob"));
+    /// }
+    /// assert_eq!(depot.get::<&str>("user").ok(), Some(&"alice"));
+    /// assert!(!depot.contains_key("role"));
+    /// ```
+    #[inline]
+    pub fn scope(&mut self) -> DepotScope<'_> {
+        self.scopes.push(Vec::new());
+        DepotScope { depot: self }
+    }
+
     /// Inject a value into the depot.
     #[inline]
     pub fn inject<V: Any + Send + Sync>(&mut self, value: V) -> &mut Self {
-        self.map.insert(type_key::<V>(), Box::new(value));
+        self.insert_typed(TypeId::of::<V>(), Box::new(value));
         self
     }
 
@@ -83,7 +218,11 @@ impl Depot {
     /// Returns `Err(Some(Box<dyn Any + Send + Sync>))` if value is present in depot but downcast failed.
     #[inline]
     pub fn obtain<T: Any + Send + Sync>(&self) -> Result<&T, Option<&Box<dyn Any + Send + Sync>>> {
-        self.get(&type_key::<T>())
+        if let Some(value) = self.types.get(&TypeId::of::<T>()) {
+            value.downcast_ref::<T>().ok_or(Some(value))
+        } else {
+            Err(None)
+        }
     }
 
     /// Obtain a mutable reference to a value previous inject to the depot.
@@ -92,7 +231,15 @@ impl Depot {
     /// Returns `Err(Some(Box<dyn Any + Send + Sync>))` if value is present in depot but downcast failed.
     #[inline]
     pub fn obtain_mut<T: Any + Send + Sync>(&mut self) -> Result<&mut T, Option<&mut Box<dyn Any + Send + Sync>>> {
-        self.get_mut(&type_key::<T>())
+        if let Some(value) = self.types.get_mut(&TypeId::of::<T>()) {
+            if value.is::<T>() {
+                Ok(value.downcast_mut::<T>().expect("downcast_mut shuold not be failed"))
+            } else {
+                Err(Some(value))
+            }
+        } else {
+            Err(None)
+        }
     }
 
     /// Inserts a key-value pair into the depot.
@@ -102,7 +249,14 @@ impl Depot {
         K: Into<String>,
         V: Any + Send + Sync,
     {
-        self.map.insert(key.into(), Box::new(value));
+        self.insert_named(key.into(), Box::new(value));
+        self
+    }
+
+    /// Inserts a value with a typed key into the depot.
+    #[inline]
+    pub fn insert_key<V: Any + Send + Sync>(&mut self, key: &DepotKey<V>, value: V) -> &mut Self {
+        self.insert_named(key.name.to_owned(), Box::new(value));
         self
     }
 
@@ -116,7 +270,7 @@ impl Depot {
     /// **Note: This is only check injected value.**
     #[inline]
     pub fn contains<T: Any + Send + Sync>(&self) -> bool {
-        self.map.contains_key(&type_key::<T>())
+        self.types.contains_key(&TypeId::of::<T>())
     }
 
     /// Immutably borrows value from depot.
@@ -132,6 +286,15 @@ impl Depot {
         }
     }
 
+    /// Immutably borrows value of a typed key from depot.
+    ///
+    /// Returns `None` if value is not present in depot, or if another type of value is inserted with the name of the
+    /// key.
+    #[inline]
+    pub fn get_key<V: Any + Send + Sync>(&self, key: &DepotKey<V>) -> Option<&V> {
+        self.get(key.name).ok()
+    }
+
     /// Mutably borrows value from depot.
     ///
     /// Returns `Err(None)` if value is not present in depot.
@@ -151,32 +314,111 @@ impl Depot {
         }
     }
 
+    /// Mutably borrows value of a typed key from depot.
+    ///
+    /// Returns `None` if value is not present in depot, or if another type of value is inserted with the name of the
+    /// key.
+    #[inline]
+    pub fn get_key_mut<V: Any + Send + Sync>(&mut self, key: &DepotKey<V>) -> Option<&mut V> {
+        self.get_mut(key.name).ok()
+    }
+
     /// Remove value from depot and returning the value at the key if the key was previously in the depot.
     #[inline]
     pub fn remove<V: Any + Send + Sync>(&mut self, key: &str) -> Result<V, Option<Box<dyn Any + Send + Sync>>> {
-        if let Some(value) = self.map.remove(key) {
+        if let Some(value) = self.remove_named(key) {
             value.downcast::<V>().map(|b| *b).map_err(Some)
         } else {
             Err(None)
         }
     }
 
+    /// Remove value of a typed key from depot and returning it if the key was previously in the depot.
+    #[inline]
+    pub fn remove_key<V: Any + Send + Sync>(&mut self, key: &DepotKey<V>) -> Option<V> {
+        self.remove(key.name).ok()
+    }
+
     /// Delete the key from depot, if the key is not present, return `false`.
     #[inline]
     pub fn delete(&mut self, key: &str) -> bool {
-        self.map.remove(key).is_some()
+        let Some(value) = self.map.remove(key) else {
+            return false;
+        };
+        // The deleted value is kept by the scope, so it can be restored.
+        self.record(Key::Name(key.to_owned()), Some(value));
+        true
     }
 
     /// Remove value from depot and returning the value if the type was previously in the depot.
     #[inline]
     pub fn scrape<T: Any + Send + Sync>(&mut self) -> Result<T, Option<Box<dyn Any + Send + Sync>>> {
-        self.remove(&type_key::<T>())
+        if let Some(value) = self.remove_typed(TypeId::of::<T>()) {
+            value.downcast::<T>().map(|b| *b).map_err(Some)
+        } else {
+            Err(None)
+        }
+    }
+}
+
+/// A scope of a [`Depot`], created by [`Depot::scope`].
+///
+/// It dereferences to the depot, and reverts the changes made in the scope when it is dropped.
+pub struct DepotScope<'a> {
+    depot: &'a mut Depot,
+}
+
+impl Deref for DepotScope<'_> {
+    type Target = Depot;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.depot
+    }
+}
+impl DerefMut for DepotScope<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.depot
+    }
+}
+
+impl Drop for DepotScope<'_> {
+    fn drop(&mut self) {
+        let Some(changes) = self.depot.scopes.pop() else {
+            return;
+        };
+        for change in changes {
+            match change {
+                (Key::Name(name), Some(value)) => {
+                    self.depot.map.insert(name, value);
+                }
+                (Key::Name(name), None) => {
+                    self.depot.map.remove(&name);
+                }
+                (Key::Type(id), Some(value)) => {
+                    self.depot.types.insert(id, value);
+                }
+                (Key::Type(id), None) => {
+                    self.depot.types.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DepotScope<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DepotScope").field(&self.depot).finish()
     }
 }
 
 impl fmt::Debug for Depot {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Depot").field("keys", &self.map.keys()).finish()
+        f.debug_struct("Depot")
+            .field("keys", &self.map.keys())
+            .field("types", &self.types.len())
+            .finish()
     }
 }
 
@@ -199,6 +441,50 @@ mod test {
         assert_eq!(depot.get_mut::<String>("one").unwrap(), &mut "ONE".to_owned());
     }
 
+    #[test]
+    fn test_depot_typed_keys() {
+        const COUNT: DepotKey<u32> = DepotKey::new("count");
+
+        let mut depot = Depot::new();
+        depot.inject(1u32).insert_key(&COUNT, 2);
+        assert_eq!(depot.obtain::<u32>().unwrap(), &1);
+        assert_eq!(depot.get_key(&COUNT), Some(&2));
+        assert!(depot.inner().contains_key("count"));
+        assert_eq!(depot.inner().len(), 1);
+
+        *depot.get_key_mut(&COUNT).unwrap() += 1;
+        assert_eq!(depot.remove_key(&COUNT), Some(3));
+        assert_eq!(depot.get_key(&COUNT), None);
+        depot.insert("count", "three");
+        assert_eq!(depot.get_key(&COUNT), None);
+        assert_eq!(depot.scrape::<u32>().unwrap(), 1);
+        assert!(!depot.contains::<u32>());
+    }
+
+    #[test]
+    fn test_depot_scope() {
+        let mut depot = Depot::new();
+        depot.insert("user", "alice").insert("token", "secret").inject(1u8);
+        {
+            let mut child = depot.scope();
+            child.insert("user", "bob").insert("role", "admin").inject(2u8);
+            assert_eq!(child.remove::<&str>("token").unwrap(), "secret");
+            {
+                let mut grandchild = child.scope();
+                grandchild.insert("role", "guest").delete("user");
+                assert_eq!(grandchild.get::<&str>("role").unwrap(), &"guest");
+                assert!(!grandchild.contains_key("user"));
+            }
+            assert_eq!(child.get::<&str>("user").unwrap(), &"bob");
+            assert_eq!(child.get::<&str>("role").unwrap(), &"admin");
+            assert_eq!(child.obtain::<u8>().unwrap(), &2);
+        }
+        assert_eq!(depot.get::<&str>("user").unwrap(), &"alice");
+        assert!(!depot.contains_key("role"));
+        assert!(!depot.contains_key("token"));
+        assert_eq!(depot.obtain::<u8>().unwrap(), &1);
+    }
+
     #[tokio::test]
     async fn test_middleware_use_depot() {
         #[handler]
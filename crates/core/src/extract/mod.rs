@@ -67,6 +67,8 @@ pub mod metadata;
 pub use metadata::Metadata;
 mod case;
 pub use case::RenameRule;
+mod state;
+pub use state::State;
 
 use std::fmt::Debug;
 use std::future::Future;
@@ -0,0 +1,122 @@
+use std::fmt::{self, Formatter};
+use std::ops::{Deref, DerefMut};
+
+use crate::extract::{Extractible, Metadata};
+use crate::http::{Request, StatusError};
+
+/// Extractor of the application state added by [`Service::with_state`](crate::Service::with_state).
+///
+/// The state is cloned for each request, so it should be cheap to clone, like a database pool or an `Arc`.
+/// If no state of type `T` is added to the service, the request fails with `500 Internal Server Error`.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use salvo_core::prelude::*;
+///
+/// struct Config {
+///     greeting: String,
+/// }
+///
+/// #[handler]
+/// async fn hello(config: State<Arc<Config>>) -> String {
+///     format!("{} world", config.greeting)
+/// }
+///
+/// let config = Arc::new(Config {
+///     greeting: "Hello".into(),
+/// });
+/// let service = Service::new(Router::new().get(hello)).with_state(config);
+/// ```
+pub struct State<T>(pub T);
+impl<T> State<T> {
+    /// Consumes self and returns the state.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for State<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for State<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("State").field(&self.0).finish()
+    }
+}
+
+impl<'ex, T> Extractible<'ex> for State<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn metadata() -> &'ex Metadata {
+        static METADATA: Metadata = Metadata::new("");
+        &METADATA
+    }
+    #[allow(refining_impl_trait)]
+    async fn extract(req: &'ex mut Request) -> Result<Self, StatusError> {
+        req.extensions().get::<T>().cloned().map(State).ok_or_else(|| {
+            tracing::error!(
+                state = std::any::type_name::<T>(),
+                "state not found, it should be added by `Service::with_state`"
+            );
+            StatusError::internal_server_error()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::prelude::*;
+    use crate::test::{ResponseExt, TestClient};
+
+    #[tokio::test]
+    async fn test_state() {
+        #[handler]
+        async fn count(counter: State<Arc<AtomicUsize>>, name: State<&'static str>) -> String {
+            format!("{} {}", *name, counter.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+        #[handler]
+        async fn missing(_state: State<u32>) {}
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let router = Router::new().get(count).push(Router::with_path("missing").get(missing));
+        let service = Service::new(router).with_state(counter.clone()).with_state("visits");
+
+        for expected in ["visits 1", "visits 2"] {
+            let content = TestClient::get("http://127.0.0.1:5800")
+                .send(&service)
+                .await
+                .take_string()
+                .await
+                .unwrap();
+            assert_eq!(content, expected);
+        }
+        assert_eq!(counter.load(Ordering::Relaxed), 2);
+
+        let res = TestClient::get("http://127.0.0.1:5800/missing").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
@@ -0,0 +1,205 @@
+//! Host gRPC services on a [`Router`].
+//!
+//! Services generated by [`tonic`](https://docs.rs/tonic) are tower services, they can be mounted with
+//! [`GrpcService`] on the same listener, TLS config and middlewares as the other routes, so one server can serve
+//! REST and gRPC requests on a single port. gRPC clients need HTTP/2, so the listener must support it.
+use std::error::Error as StdError;
+use std::fmt::{self, Formatter};
+use std::future::Future;
+
+use futures_util::stream::{StreamExt, TryStreamExt};
+use http_body_util::{BodyExt, BodyStream};
+use hyper::body::{Body, Bytes};
+use sync_wrapper::SyncWrapper;
+use tower::{Service, ServiceExt};
+
+use crate::http::body::BytesFrame;
+use crate::http::header::{HeaderValue, CONTENT_TYPE};
+use crate::http::{ReqBody, ResBody, StatusCode, StatusError};
+use crate::routing::Router;
+use crate::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+
+/// The `grpc-status` code of an internal error.
+const GRPC_INTERNAL: &str = "13";
+/// The `grpc-status` code of an unavailable service.
+const GRPC_UNAVAILABLE: &str = "14";
+
+/// Handler calling a gRPC service, like a server generated by `tonic`.
+///
+/// Requests without an `application/grpc` content type are rejected with `415 Unsupported Media Type`. Errors of
+/// the service itself are sent as gRPC errors, with `grpc-status` header, so gRPC clients can read them.
+///
+/// # Example
+///
+/// ```ignore
+/// use salvo_core::grpc::GrpcService;
+/// use salvo_core::prelude::*;
+///
+/// let greeter = GreeterServer::new(MyGreeter::default());
+/// let router = Router::new()
+///     .push(Router::with_path("api").get(hello))
+///     .push(GrpcService::new(greeter).router("helloworld.Greeter"));
+/// ```
+#[derive(Clone)]
+pub struct GrpcService<S> {
+    inner: S,
+}
+impl<S> fmt::Debug for GrpcService<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcService").finish()
+    }
+}
+
+impl<S> GrpcService<S> {
+    /// Create a new `GrpcService`.
+    #[inline]
+    pub fn new(inner: S) -> Self {
+        GrpcService { inner }
+    }
+
+    /// Returns a router matching the methods of the service named `name`, like `helloworld.Greeter`.
+    ///
+    /// gRPC methods are called by `POST /{name}/{method}`.
+    pub fn router(self, name: &str) -> Router
+    where
+        Self: Handler,
+    {
+        Router::with_path(format!("{}/<**>", name.trim_matches('/'))).post(self)
+    }
+}
+
+fn grpc_error(res: &mut Response, status: &'static str, message: &str) {
+    res.status_code(StatusCode::OK);
+    let headers = res.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+    headers.insert("grpc-status", HeaderValue::from_static(status));
+    if let Ok(message) = HeaderValue::from_str(message) {
+        headers.insert("grpc-message", message);
+    }
+}
+
+#[async_trait]
+impl<S, B, Fut> Handler for GrpcService<S>
+where
+    S: Service<hyper::Request<ReqBody>, Response = hyper::Response<B>, Future = Fut> + Clone + Send + Sync + 'static,
+    S::Error: StdError + Send + Sync + 'static,
+    Fut: Future<Output = Result<hyper::Response<B>, S::Error>> + Send + 'static,
+    B: Body + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: StdError + Send + Sync + 'static,
+{
+    async fn handle(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let is_grpc = req
+            .content_type()
+            .is_some_and(|ct| ct.essence_str().starts_with("application/grpc"));
+        if !is_grpc {
+            res.render(StatusError::unsupported_media_type().brief("Content type should be `application/grpc`."));
+            ctrl.skip_rest();
+            return;
+        }
+        let mut svc = self.inner.clone();
+        if let Err(e) = svc.ready().await {
+            tracing::error!(error = ?e, "grpc service not ready");
+            grpc_error(res, GRPC_UNAVAILABLE, "service not ready");
+            return;
+        }
+        let hyper_req = match req.strip_to_hyper::<ReqBody>() {
+            Ok(hyper_req) => hyper_req,
+            Err(e) => {
+                tracing::error!(error = ?e, "strip request to hyper failed");
+                grpc_error(res, GRPC_INTERNAL, "invalid request");
+                return;
+            }
+        };
+        match svc.call(hyper_req).await {
+            Ok(hyper_res) => {
+                // Bodies of tonic are not `Sync`, they are polled as a stream, trailers included.
+                res.merge_hyper(hyper_res.map(|body| {
+                    let stream = BodyStream::new(body.map_frame(|frame| frame.map_data(Into::into)))
+                        .map_ok(BytesFrame)
+                        .map_err(Into::into)
+                        .boxed();
+                    ResBody::Stream(SyncWrapper::new(stream))
+                }));
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "call grpc service failed");
+                grpc_error(res, GRPC_INTERNAL, &e.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use http_body_util::Full;
+    use hyper::body::Frame;
+    use hyper::HeaderMap;
+
+    use super::*;
+    use crate::test::TestClient;
+
+    #[derive(Clone)]
+    struct Echo;
+    impl Service<hyper::Request<ReqBody>> for Echo {
+        type Response = hyper::Response<http_body_util::combinators::UnsyncBoxBody<Bytes, Infallible>>;
+        type Error = Infallible;
+        type Future = futures_util::future::BoxFuture<'static, Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Infallible>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: hyper::Request<ReqBody>) -> Self::Future {
+            Box::pin(async move {
+                let path = req.uri().path().to_owned();
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                let body = Full::new(Bytes::from(path))
+                    .map_err(|e| match e {})
+                    .with_trailers(async move { Some(Ok::<_, Infallible>(trailers)) })
+                    .boxed_unsync();
+                let mut res = hyper::Response::new(body);
+                res.headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+                Ok(res)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grpc_service() {
+        let router = Router::new().push(GrpcService::new(Echo).router("echo.Echo"));
+        let service = crate::Service::new(router);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/echo.Echo/Say")
+            .add_header(CONTENT_TYPE, "application/grpc", true)
+            .send(&service)
+            .await;
+        let mut trailers = None;
+        let mut data = Vec::new();
+        while let Some(frame) = res.body.frame().await {
+            let frame: Frame<Bytes> = frame.unwrap();
+            match frame.into_data() {
+                Ok(chunk) => data.extend_from_slice(&chunk),
+                Err(frame) => trailers = frame.into_trailers().ok(),
+            }
+        }
+        assert_eq!(data, b"/echo.Echo/Say");
+        assert_eq!(trailers.unwrap()["grpc-status"], "0");
+
+        let res = TestClient::post("http://127.0.0.1:5801/echo.Echo/Say")
+            .add_header(CONTENT_TYPE, "application/json", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+
+        let res = TestClient::post("http://127.0.0.1:5801/other.Other/Say")
+            .add_header(CONTENT_TYPE, "application/grpc", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+    }
+}
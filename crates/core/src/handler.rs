@@ -54,6 +54,61 @@
 //! }
 //! ````
 //!
+//! ## Macro `#[controller]`
+//!
+//! `#[controller]` can be added to the `impl` of a `struct` to map its methods to routes, as an alternative to
+//! free function handlers. The methods can access the fields of the `struct`, like a database pool or a config,
+//! with `&self`, and their other arguments and return value are handled like with `#[handler]`. Each method is
+//! mapped to a route by an attribute named after the methods of [`Router`](crate::Router), with an optional path:
+//!
+//! ```
+//! use salvo_core::prelude::*;
+//! use salvo_core::test::{ResponseExt, TestClient};
+//!
+//! struct UserController {
+//!     greeting: String,
+//! }
+//!
+//! #[controller]
+//! impl UserController {
+//!     #[get]
+//!     async fn list(&self) -> &'static str {
+//!         "[]"
+//!     }
+//!
+//!     #[get("<name>")]
+//!     async fn show(&self, req: &mut Request) -> String {
+//!         self.greet(req.param::<&str>("name").unwrap_or_default())
+//!     }
+//!
+//!     #[delete("<name>")]
+//!     async fn delete(&self) -> StatusCode {
+//!         StatusCode::NO_CONTENT
+//!     }
+//!
+//!     fn greet(&self, name: &str) -> String {
+//!         format!("{} {name}", self.greeting)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let controller = UserController {
+//!     greeting: "Hello".into(),
+//! };
+//! let router = Router::with_path("users").push(controller.into());
+//! let service = Service::new(router);
+//!
+//! let mut res = TestClient::get("http://127.0.0.1:5800/users/alice").send(&service).await;
+//! assert_eq!(res.take_string().await.unwrap(), "Hello alice");
+//! let res = TestClient::delete("http://127.0.0.1:5800/users/alice").send(&service).await;
+//! assert_eq!(res.status_code, Some(StatusCode::NO_CONTENT));
+//! # }
+//! ```
+//!
+//! The `struct` is converted to a [`Router`](crate::Router) with `Router::from`, and is shared by the routes in an
+//! `Arc`, so it should be `Send + Sync + 'static`.
+//!
 //! ## Handle errors
 //!
 //! `Handler` in Salvo can return `Result`, only the types of `Ok` and `Err` in `Result` are implemented `Writer` trait.
@@ -116,6 +171,8 @@
 //!     }
 //! }
 //! ```
+use futures_util::future::BoxFuture;
+
 use crate::http::StatusCode;
 use crate::{async_trait, Depot, FlowCtrl, Request, Response};
 
@@ -172,6 +229,43 @@ where
     }
 }
 
+/// The rest of the handlers of a request, passed to the middleware functions added by
+/// [`Router::hoop_fn`](crate::Router::hoop_fn) and [`Service::hoop_fn`](crate::Service::hoop_fn).
+pub struct Next<'a> {
+    ctrl: &'a mut FlowCtrl,
+}
+impl Next<'_> {
+    /// Run the rest of the handlers. If it is not called, the rest of the handlers are skipped.
+    #[inline]
+    pub async fn run(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+        self.ctrl.call_next(req, depot, res).await;
+    }
+}
+
+/// Middleware created from a function by [`Router::hoop_fn`](crate::Router::hoop_fn) and
+/// [`Service::hoop_fn`](crate::Service::hoop_fn).
+#[non_exhaustive]
+pub struct FnHoop<F> {
+    /// The middleware function.
+    pub f: F,
+}
+#[async_trait]
+impl<F> Handler for FnHoop<F>
+where
+    F: for<'a> Fn(&'a mut Request, &'a mut Depot, &'a mut Response, Next<'a>) -> BoxFuture<'a, ()>
+        + Send
+        + Sync
+        + 'static,
+{
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        (self.f)(req, depot, res, Next { ctrl: &mut *ctrl }).await;
+        // The function returned without running the rest of the handlers.
+        if ctrl.has_next() {
+            ctrl.skip_rest();
+        }
+    }
+}
+
 /// `Skipper` is used to check if the request should be skipped.
 ///
 /// `Skipper` is used in many middlewares.
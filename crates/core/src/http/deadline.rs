@@ -0,0 +1,127 @@
+//! Per-request deadlines, propagated between services by headers.
+//!
+//! A client can limit the time it waits for a response with the `X-Request-Timeout` header, in milliseconds, or
+//! with the `grpc-timeout` header used by gRPC. The deadline of the request is computed from these headers when it is
+//! received, and read with [`Request::deadline`](crate::Request::deadline) and
+//! [`Request::remaining_time`](crate::Request::remaining_time). Middlewares like the timeout hoop stop handling the
+//! request when the deadline is exceeded, and proxies forward the remaining time to upstream services with
+//! [`write_timeout_headers`], so the budget of the original request is shared by the whole chain of services.
+use std::time::Duration;
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// Header of the timeout of a request, in milliseconds.
+pub const REQUEST_TIMEOUT: HeaderName = HeaderName::from_static("x-request-timeout");
+/// Header of the timeout of a gRPC request, like `100m` for 100 milliseconds.
+pub const GRPC_TIMEOUT: HeaderName = HeaderName::from_static("grpc-timeout");
+
+/// Units of `grpc-timeout`, from the smallest one.
+const GRPC_UNITS: [(char, Duration); 6] = [
+    ('n', Duration::from_nanos(1)),
+    ('u', Duration::from_micros(1)),
+    ('m', Duration::from_millis(1)),
+    ('S', Duration::from_secs(1)),
+    ('M', Duration::from_secs(60)),
+    ('H', Duration::from_secs(3600)),
+];
+/// Max value of `grpc-timeout`, which has at most 8 digits.
+const GRPC_MAX_VALUE: u128 = 99_999_999;
+
+/// Parses the value of the `X-Request-Timeout` header, a number of milliseconds.
+pub fn parse_request_timeout(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_millis)
+}
+
+/// Parses the value of the `grpc-timeout` header, at most 8 digits followed by a unit: `H` for hours, `M` for
+/// minutes, `S` for seconds, `m` for milliseconds, `u` for microseconds or `n` for nanoseconds.
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit = value.chars().last()?;
+    let digits = &value[..value.len() - unit.len_utf8()];
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (_, unit) = GRPC_UNITS.iter().find(|(name, _)| *name == unit)?;
+    unit.checked_mul(digits.parse().ok()?)
+}
+
+/// Formats a timeout as the value of the `grpc-timeout` header, with the smallest unit it fits in.
+///
+/// The value is rounded down, so the timeout sent is never longer than the remaining time.
+pub fn format_grpc_timeout(timeout: Duration) -> String {
+    for (name, unit) in GRPC_UNITS {
+        let value = timeout.as_nanos() / unit.as_nanos();
+        if value <= GRPC_MAX_VALUE {
+            return format!("{value}{name}");
+        }
+    }
+    format!("{GRPC_MAX_VALUE}H")
+}
+
+/// Returns the timeout of a request from its headers, the shortest one if both headers are set.
+pub fn timeout_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let request_timeout = headers
+        .get(REQUEST_TIMEOUT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_request_timeout);
+    let grpc_timeout = headers
+        .get(GRPC_TIMEOUT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_grpc_timeout);
+    match (request_timeout, grpc_timeout) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Writes the remaining time of a request to the headers of a request sent to another service.
+///
+/// `X-Request-Timeout` is always written, `grpc-timeout` is replaced if it is set, so gRPC requests keep their
+/// format.
+pub fn write_timeout_headers(headers: &mut HeaderMap, timeout: Duration) {
+    let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+    headers.insert(REQUEST_TIMEOUT, HeaderValue::from(millis));
+    if headers.contains_key(GRPC_TIMEOUT) {
+        if let Ok(value) = HeaderValue::from_str(&format_grpc_timeout(timeout)) {
+            headers.insert(GRPC_TIMEOUT, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timeouts() {
+        assert_eq!(parse_request_timeout("1500"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_request_timeout("1.5"), None);
+        assert_eq!(parse_grpc_timeout("100m"), Some(Duration::from_millis(100)));
+        assert_eq!(parse_grpc_timeout("2H"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_grpc_timeout("123456789n"), None);
+        assert_eq!(parse_grpc_timeout("10"), None);
+        assert_eq!(parse_grpc_timeout("m"), None);
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(timeout_from_headers(&headers), None);
+        headers.insert(REQUEST_TIMEOUT, HeaderValue::from_static("300"));
+        headers.insert(GRPC_TIMEOUT, HeaderValue::from_static("1S"));
+        assert_eq!(timeout_from_headers(&headers), Some(Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_write_timeout_headers() {
+        assert_eq!(format_grpc_timeout(Duration::from_millis(250)), "250000u");
+        assert_eq!(format_grpc_timeout(Duration::from_secs(1000)), "1000000m");
+        assert_eq!(format_grpc_timeout(Duration::from_secs(3600 * 24 * 365)), "31536000S");
+
+        let mut headers = HeaderMap::new();
+        write_timeout_headers(&mut headers, Duration::from_millis(1500));
+        assert_eq!(headers[REQUEST_TIMEOUT], "1500");
+        assert!(!headers.contains_key(GRPC_TIMEOUT));
+        headers.insert(GRPC_TIMEOUT, HeaderValue::from_static("5S"));
+        write_timeout_headers(&mut headers, Duration::from_secs(2));
+        assert_eq!(headers[GRPC_TIMEOUT], "2000000u");
+        assert_eq!(parse_grpc_timeout("2000000u"), Some(Duration::from_secs(2)));
+    }
+}
@@ -69,6 +69,18 @@ pub enum ParseError {
     #[error("InvalidRange")]
     InvalidRange,
 
+    /// An uploaded file is larger than the max file size.
+    #[error("An uploaded file is larger than the max file size.")]
+    FileTooLarge,
+
+    /// The request body is larger than the max size.
+    #[error("The request body is larger than the max size.")]
+    PayloadTooLarge,
+
+    /// The detected type of an uploaded file is not allowed.
+    #[error("The type of an uploaded file is not allowed.")]
+    UnsupportedFileType,
+
     /// An multer error.
     #[error("Multer error: {0}")]
     Multer(#[from] multer::Error),
@@ -104,7 +116,12 @@ impl ParseError {
 #[async_trait]
 impl Writer for ParseError {
     async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-        res.render(StatusError::bad_request().brief("parse http data failed.").cause(self));
+        let error = match self {
+            Self::FileTooLarge | Self::PayloadTooLarge => StatusError::payload_too_large(),
+            Self::UnsupportedFileType => StatusError::unsupported_media_type(),
+            _ => StatusError::bad_request(),
+        };
+        res.render(error.brief("parse http data failed.").cause(self));
     }
 }
 
@@ -1,23 +1,26 @@
 //! form parse module
 use std::ffi::OsStr;
+use std::fmt::{self, Formatter};
 use std::io::{Cursor, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::engine::Engine;
+use bytes::Bytes;
 use futures_util::StreamExt;
 use http_body_util::BodyExt;
 use mime::Mime;
-use multer::{Field, Multipart};
+use multer::{Constraints, Field, Multipart, SizeLimit};
 use multimap::MultiMap;
 use rand::rngs::OsRng;
 use rand::RngCore;
-use tempfile::Builder;
+use tempfile::{Builder, TempDir};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 
 use crate::http::body::ReqBody;
-use crate::http::header::{HeaderMap, CONTENT_TYPE};
+use crate::http::header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE};
 use crate::http::ParseError;
 
 /// The extracted text fields and uploaded files from a `multipart/form-data` request.
@@ -44,6 +47,16 @@ impl FormData {
 
     /// Parse MIME `multipart/*` information from a stream as a [`FormData`].
     pub(crate) async fn read(headers: &HeaderMap, body: ReqBody) -> Result<FormData, ParseError> {
+        FormData::read_with_options(headers, body, &UploadOptions::default()).await
+    }
+
+    /// Parse MIME `multipart/*` information from a stream as a [`FormData`], with limits and spooling of uploaded
+    /// files set by `options`.
+    pub(crate) async fn read_with_options(
+        headers: &HeaderMap,
+        body: ReqBody,
+        options: &UploadOptions,
+    ) -> Result<FormData, ParseError> {
         let ctype: Option<Mime> = headers
             .get(CONTENT_TYPE)
             .and_then(|h| h.to_str().ok())
@@ -63,13 +76,30 @@ impl FormData {
                     .and_then(|ct| multer::parse_boundary(ct).ok())
                 {
                     let body = body.map(|f| f.map(|f| f.into_data().unwrap_or_default()));
-                    let mut multipart = Multipart::new(body, boundary);
-                    while let Some(mut field) = multipart.next_field().await? {
+                    let mut multipart = match options.max_total_size {
+                        Some(max_size) => Multipart::with_constraints(
+                            body,
+                            boundary,
+                            Constraints::new().size_limit(SizeLimit::new().whole_stream(max_size)),
+                        ),
+                        None => Multipart::new(body, boundary),
+                    };
+                    let mut progress = UploadProgress {
+                        total_expected: headers
+                            .get(CONTENT_LENGTH)
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(|v| v.parse().ok()),
+                        ..Default::default()
+                    };
+                    while let Some(mut field) = next_field(&mut multipart).await? {
                         if let Some(name) = field.name().map(|s| s.to_owned()) {
                             if field.headers().get(CONTENT_TYPE).is_some() {
-                                form_data.files.insert(name, FilePart::create(&mut field).await?);
+                                let file = FilePart::receive(&mut field, options, &mut progress).await?;
+                                form_data.files.insert(name, file);
                             } else {
-                                form_data.fields.insert(name, field.text().await?);
+                                let text = field.text().await.map_err(map_multer_error)?;
+                                progress.total_received += text.len() as u64;
+                                form_data.fields.insert(name, text);
                             }
                         }
                     }
@@ -86,6 +116,187 @@ impl Default for FormData {
         Self::new()
     }
 }
+/// Receives the next field of a multipart stream, reporting an exceeded size limit as
+/// [`ParseError::PayloadTooLarge`].
+async fn next_field<'a>(multipart: &mut Multipart<'a>) -> Result<Option<Field<'a>>, ParseError> {
+    multipart.next_field().await.map_err(map_multer_error)
+}
+fn map_multer_error(e: multer::Error) -> ParseError {
+    match e {
+        multer::Error::StreamSizeExceeded { .. } => ParseError::PayloadTooLarge,
+        e => ParseError::Multer(e),
+    }
+}
+
+/// Progress of an upload, reported to the callback of [`UploadOptions::on_progress`] each time a chunk of a file is
+/// received.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct UploadProgress {
+    /// The name of the form field of the file being received.
+    pub field_name: Option<String>,
+    /// The name of the file being received, as sent by the client.
+    pub file_name: Option<String>,
+    /// The number of bytes of the current file received.
+    pub file_received: u64,
+    /// The number of bytes of all fields received, without the multipart boundaries and headers.
+    pub total_received: u64,
+    /// The size of the request body, from the `Content-Length` header.
+    pub total_expected: Option<u64>,
+}
+
+type ProgressCallback = Arc<dyn Fn(&UploadProgress) + Send + Sync>;
+
+/// Options of the parsing of uploaded files, used by
+/// [`Request::form_data_with_options`](crate::Request::form_data_with_options).
+///
+/// By default, all files are written to disk, no limit is set and any file type is accepted.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::http::form::UploadOptions;
+/// use salvo_core::prelude::*;
+///
+/// #[handler]
+/// async fn upload(req: &mut Request, res: &mut Response) {
+///     let options = UploadOptions::new()
+///         .spool_threshold(64 * 1024)
+///         .max_file_size(10 * 1024 * 1024)
+///         .allowed_types(vec!["image/*".parse().unwrap()]);
+///     match req.form_data_with_options(&options).await {
+///         Ok(form_data) => res.render(format!("{} files", form_data.files.len())),
+///         Err(e) => res.render(e),
+///     }
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct UploadOptions {
+    spool_threshold: usize,
+    max_file_size: Option<u64>,
+    max_total_size: Option<u64>,
+    allowed_types: Vec<Mime>,
+    on_progress: Option<ProgressCallback>,
+}
+impl fmt::Debug for UploadOptions {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("UploadOptions")
+            .field("spool_threshold", &self.spool_threshold)
+            .field("max_file_size", &self.max_file_size)
+            .field("max_total_size", &self.max_total_size)
+            .field("allowed_types", &self.allowed_types)
+            .finish()
+    }
+}
+impl UploadOptions {
+    /// Create new `UploadOptions`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the size under which files are kept in memory instead of written to disk, defaults to 0.
+    #[inline]
+    pub fn spool_threshold(mut self, size: usize) -> Self {
+        self.spool_threshold = size;
+        self
+    }
+    /// Sets the max size of each file, larger files are rejected with [`ParseError::FileTooLarge`].
+    #[inline]
+    pub fn max_file_size(mut self, size: u64) -> Self {
+        self.max_file_size = Some(size);
+        self
+    }
+    /// Sets the max size of the whole multipart body, larger bodies are rejected with
+    /// [`ParseError::PayloadTooLarge`].
+    #[inline]
+    pub fn max_total_size(mut self, size: u64) -> Self {
+        self.max_total_size = Some(size);
+        self
+    }
+    /// Sets the allowed types of files, like `image/png` or `image/*`.
+    ///
+    /// The type of a file is detected from its first bytes with [`sniff_mime`], not from the `Content-Type` sent by
+    /// the client. Files of another type, or whose type is not detected, are rejected with
+    /// [`ParseError::UnsupportedFileType`], unless `application/octet-stream` is allowed.
+    #[inline]
+    pub fn allowed_types(mut self, types: impl Into<Vec<Mime>>) -> Self {
+        self.allowed_types = types.into();
+        self
+    }
+    /// Sets a callback called with the progress of the upload each time a chunk of a file is received, for example
+    /// to publish it to an upload progress endpoint.
+    #[inline]
+    pub fn on_progress(mut self, callback: impl Fn(&UploadProgress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    fn is_allowed(&self, head: &[u8]) -> bool {
+        if self.allowed_types.is_empty() {
+            return true;
+        }
+        let detected = sniff_mime(head).unwrap_or(mime::APPLICATION_OCTET_STREAM);
+        self.allowed_types.iter().any(|allowed| {
+            (allowed.type_() == mime::STAR || allowed.type_() == detected.type_())
+                && (allowed.subtype() == mime::STAR || allowed.subtype() == detected.subtype())
+        })
+    }
+}
+
+/// Signatures of the file types detected by [`sniff_mime`], with the offset of the signature.
+const MAGIC_NUMBERS: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (8, b"WEBP", "image/webp"),
+    (0, b"BM", "image/bmp"),
+    (0, b"\x00\x00\x01\x00", "image/x-icon"),
+    (0, b"II*\x00", "image/tiff"),
+    (0, b"MM\x00*", "image/tiff"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (8, b"WAVE", "audio/wav"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"\x1a\x45\xdf\xa3", "video/webm"),
+];
+/// Number of bytes needed by [`sniff_mime`] to detect all known types.
+const SNIFF_LEN: usize = 16;
+
+/// Detects the type of a file from the magic number at its start, returns `None` if it is not a known type.
+pub fn sniff_mime(data: &[u8]) -> Option<Mime> {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(offset, magic, _)| data.get(*offset..*offset + magic.len()) == Some(*magic))
+        .and_then(|(_, _, mime)| mime.parse().ok())
+}
+
+/// Sanitizes a file name sent by a client so it can be used as a file name on disk.
+///
+/// Directories are removed, like `../../etc/passwd` becoming `passwd`, as well as control characters and characters
+/// reserved on Windows. Returns `None` if nothing is left.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    const MAX_LEN: usize = 255;
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*') {
+            continue;
+        }
+        if sanitized.len() + c.len_utf8() > MAX_LEN {
+            break;
+        }
+        sanitized.push(c);
+    }
+    let sanitized = sanitized.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    (!sanitized.is_empty()).then(|| sanitized.to_owned())
+}
+
 /// A file that is to be inserted into a `multipart/*` or alternatively an uploaded file that
 /// was received as part of `multipart/*` parsing.
 #[derive(Clone, Debug)]
@@ -100,6 +311,8 @@ pub struct FilePart {
     size: u64,
     // The temporary directory the upload was put into, saved for the Drop trait
     temp_dir: Option<PathBuf>,
+    /// The content of the file, if it is smaller than the spool threshold and kept in memory.
+    data: Option<Bytes>,
 }
 impl FilePart {
     /// Get file name.
@@ -129,11 +342,36 @@ impl FilePart {
             .and_then(|h| h.to_str().ok())
             .and_then(|v| v.parse().ok())
     }
+    /// Get the name of the file sanitized by [`sanitize_file_name`], safe to be used as a file name on disk.
+    #[inline]
+    pub fn sanitized_name(&self) -> Option<String> {
+        self.name.as_deref().and_then(sanitize_file_name)
+    }
     /// Get file path.
+    ///
+    /// The path is empty if the file is kept in memory, see [`FilePart::data`].
     #[inline]
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
+    /// Get the content of the file if it is kept in memory, because it is smaller than
+    /// [`UploadOptions::spool_threshold`].
+    #[inline]
+    pub fn data(&self) -> Option<&Bytes> {
+        self.data.as_ref()
+    }
+    /// Check if the file is kept in memory instead of written to disk.
+    #[inline]
+    pub fn is_in_memory(&self) -> bool {
+        self.data.is_some()
+    }
+    /// Read the content of the file, from memory or from disk.
+    pub async fn read(&self) -> Result<Bytes, ParseError> {
+        match &self.data {
+            Some(data) => Ok(data.clone()),
+            None => Ok(tokio::fs::read(&self.path).await?.into()),
+        }
+    }
     /// Get file size.
     #[inline]
     pub fn size(&self) -> u64 {
@@ -149,35 +387,113 @@ impl FilePart {
     /// Create a new temporary FilePart (when created this way, the file will be
     /// deleted once the FilePart object goes out of scope).
     pub async fn create(field: &mut Field<'_>) -> Result<FilePart, ParseError> {
-        // Setup a file to capture the contents.
-        let mut path = tokio::task::spawn_blocking(|| Builder::new().prefix("salvo_http_multipart").tempdir())
-            .await
-            .expect("Runtime spawn blocking poll error")?
-            .into_path();
-        let temp_dir = Some(path.clone());
+        FilePart::receive(field, &UploadOptions::default(), &mut UploadProgress::default()).await
+    }
+
+    /// Receives the content of a field, kept in memory until it is larger than the spool threshold.
+    async fn receive(
+        field: &mut Field<'_>,
+        options: &UploadOptions,
+        progress: &mut UploadProgress,
+    ) -> Result<FilePart, ParseError> {
         let name = field.file_name().map(|s| s.to_owned());
-        path.push(format!(
-            "{}.{}",
-            text_nonce(),
-            name.as_deref()
-                .and_then(|name| { Path::new(name).extension().and_then(OsStr::to_str) })
-                .unwrap_or("unknown")
-        ));
-        let mut file = File::create(&path).await?;
-        let mut size = 0;
-        while let Some(chunk) = field.chunk().await? {
-            size += chunk.len() as u64;
-            file.write_all(&chunk).await?;
+        progress.field_name = field.name().map(|s| s.to_owned());
+        progress.file_name = name.clone();
+        progress.file_received = 0;
+
+        let mut buffer = Vec::new();
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        let mut checked = options.allowed_types.is_empty();
+        // The temporary directory is removed by the `TempDir` guard if the field is not fully received.
+        let mut spooled: Option<(File, PathBuf, TempDir)> = None;
+        while let Some(chunk) = field.chunk().await.map_err(map_multer_error)? {
+            progress.file_received += chunk.len() as u64;
+            progress.total_received += chunk.len() as u64;
+            if options.max_file_size.is_some_and(|max| progress.file_received > max) {
+                return Err(ParseError::FileTooLarge);
+            }
+            if !checked {
+                let take = (SNIFF_LEN - head.len()).min(chunk.len());
+                head.extend_from_slice(&chunk[..take]);
+                if head.len() >= SNIFF_LEN {
+                    if !options.is_allowed(&head) {
+                        return Err(ParseError::UnsupportedFileType);
+                    }
+                    checked = true;
+                }
+            }
+            if let Some((file, _, _)) = &mut spooled {
+                file.write_all(&chunk).await?;
+            } else if buffer.len() + chunk.len() > options.spool_threshold {
+                let (mut file, path, temp_dir) = create_temp_file(name.as_deref()).await?;
+                file.write_all(&buffer).await?;
+                file.write_all(&chunk).await?;
+                buffer = Vec::new();
+                spooled = Some((file, path, temp_dir));
+            } else {
+                buffer.extend_from_slice(&chunk);
+            }
+            if let Some(on_progress) = &options.on_progress {
+                on_progress(progress);
+            }
+        }
+        if !checked && !options.is_allowed(&head) {
+            return Err(ParseError::UnsupportedFileType);
+        }
+        let headers = field.headers().to_owned();
+        let size = progress.file_received;
+        match spooled {
+            Some((mut file, path, temp_dir)) => {
+                file.flush().await?;
+                Ok(FilePart {
+                    name,
+                    headers,
+                    path,
+                    size,
+                    temp_dir: Some(temp_dir.into_path()),
+                    data: None,
+                })
+            }
+            None if options.spool_threshold == 0 => {
+                // Empty files are written to disk too when files are never kept in memory.
+                let (_, path, temp_dir) = create_temp_file(name.as_deref()).await?;
+                Ok(FilePart {
+                    name,
+                    headers,
+                    path,
+                    size,
+                    temp_dir: Some(temp_dir.into_path()),
+                    data: None,
+                })
+            }
+            None => Ok(FilePart {
+                name,
+                headers,
+                path: PathBuf::new(),
+                size,
+                temp_dir: None,
+                data: Some(buffer.into()),
+            }),
         }
-        Ok(FilePart {
-            name,
-            headers: field.headers().to_owned(),
-            path,
-            size,
-            temp_dir,
-        })
     }
 }
+
+/// Creates a file in a new temporary directory, named with a nonce and the extension of `name`.
+///
+/// The directory is removed when the returned `TempDir` is dropped, until it is kept with [`TempDir::into_path`].
+async fn create_temp_file(name: Option<&str>) -> Result<(File, PathBuf, TempDir), ParseError> {
+    let temp_dir = tokio::task::spawn_blocking(|| Builder::new().prefix("salvo_http_multipart").tempdir())
+        .await
+        .expect("Runtime spawn blocking poll error")?;
+    let path = temp_dir.path().join(format!(
+        "{}.{}",
+        text_nonce(),
+        name.and_then(|name| Path::new(name).extension().and_then(OsStr::to_str))
+            .unwrap_or("unknown")
+    ));
+    let file = File::create(&path).await?;
+    Ok((file, path, temp_dir))
+}
 impl Drop for FilePart {
     fn drop(&mut self) {
         if let Some(temp_dir) = &self.temp_dir {
@@ -214,3 +530,31 @@ fn text_nonce() -> String {
     // base64 encode
     URL_SAFE_NO_PAD.encode(&raw)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("photo.png").as_deref(), Some("photo.png"));
+        assert_eq!(sanitize_file_name("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_file_name("C:\\Users\\me\\a<b>.txt").as_deref(), Some("ab.txt"));
+        assert_eq!(sanitize_file_name(" ..hidden\n").as_deref(), Some("hidden"));
+        assert_eq!(sanitize_file_name("dir/.."), None);
+        assert_eq!(sanitize_file_name(&"a".repeat(300)).map(|name| name.len()), Some(255));
+    }
+
+    #[test]
+    fn test_sniff_mime() {
+        assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some(mime::IMAGE_PNG));
+        assert_eq!(sniff_mime(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp".parse().ok());
+        assert_eq!(sniff_mime(b"%PDF-1.7"), Some(mime::APPLICATION_PDF));
+        assert_eq!(sniff_mime(b"hello world"), None);
+
+        let options = UploadOptions::new().allowed_types(vec!["image/*".parse().unwrap()]);
+        assert!(options.is_allowed(b"\xff\xd8\xff\xe0"));
+        assert!(!options.is_allowed(b"%PDF-1.7"));
+        assert!(!options.is_allowed(b"plain text"));
+    }
+}
@@ -1,5 +1,6 @@
 //! The http related types and functions.
 
+pub mod deadline;
 pub mod errors;
 pub mod form;
 mod range;
@@ -1,8 +1,10 @@
 //! Http request.
 use std::error::Error as StdError;
 use std::fmt::{self, Formatter};
+use std::future::Future;
 #[cfg(feature = "quinn")]
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
 #[cfg(feature = "cookie")]
@@ -13,19 +15,22 @@ pub use http::request::Parts;
 use http::uri::{Scheme, Uri};
 use http::{self, Extensions};
 use http_body_util::{BodyExt, Limited};
+use hyper::upgrade::{OnUpgrade, Upgraded};
 use indexmap::IndexMap;
 use mime;
 use multimap::MultiMap;
 use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use serde::de::Deserialize;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
 
 use crate::conn::SocketAddr;
 use crate::extract::{Extractible, Metadata};
 use crate::fuse::TransProto;
 use crate::http::body::ReqBody;
-use crate::http::form::{FilePart, FormData};
-use crate::http::{Mime, ParseError, Version};
+use crate::http::form::{FilePart, FormData, UploadOptions};
+use crate::http::{deadline, Mime, ParseError, Version};
+use crate::rt::tokio::TokioIo;
 use crate::serde::{from_request, from_str_map, from_str_multi_map, from_str_multi_val, from_str_val};
 use crate::Error;
 
@@ -63,6 +68,7 @@ pub struct Request {
     pub(crate) cookies: CookieJar,
 
     pub(crate) params: IndexMap<String, String>,
+    pub(crate) matched_path: Option<String>,
 
     // accept: Option<Vec<Mime>>,
     pub(crate) queries: OnceCell<MultiMap<String, String>>,
@@ -74,6 +80,8 @@ pub struct Request {
     pub(crate) scheme: Scheme,
     pub(crate) local_addr: SocketAddr,
     pub(crate) remote_addr: SocketAddr,
+    deadline: Option<Instant>,
+    pub(crate) cancel_token: CancellationToken,
 }
 
 impl fmt::Debug for Request {
@@ -112,6 +120,7 @@ impl Request {
             #[cfg(feature = "cookie")]
             cookies: CookieJar::default(),
             params: IndexMap::new(),
+            matched_path: None,
             queries: OnceCell::new(),
             form_data: tokio::sync::OnceCell::new(),
             payload: tokio::sync::OnceCell::new(),
@@ -119,6 +128,8 @@ impl Request {
             scheme: Scheme::HTTP,
             local_addr: SocketAddr::Unknown,
             remote_addr: SocketAddr::Unknown,
+            deadline: None,
+            cancel_token: CancellationToken::new(),
         }
     }
     #[doc(hidden)]
@@ -162,6 +173,8 @@ impl Request {
             cookie_jar
         };
 
+        let deadline = deadline::timeout_from_headers(&headers).and_then(|timeout| Instant::now().checked_add(timeout));
+
         Request {
             queries: OnceCell::new(),
             uri,
@@ -173,6 +186,7 @@ impl Request {
             cookies,
             // accept: None,
             params: IndexMap::new(),
+            matched_path: None,
             form_data: tokio::sync::OnceCell::new(),
             payload: tokio::sync::OnceCell::new(),
             // multipart: OnceCell::new(),
@@ -180,6 +194,8 @@ impl Request {
             remote_addr: SocketAddr::Unknown,
             version,
             scheme,
+            deadline,
+            cancel_token: CancellationToken::new(),
         }
     }
 
@@ -332,6 +348,93 @@ impl Request {
         &mut self.remote_addr
     }
 
+    /// Get the deadline of the request, after which the client does not wait for the response.
+    ///
+    /// It is computed from the `X-Request-Timeout` or `grpc-timeout` header when the request is received, see
+    /// [`deadline`](crate::http::deadline).
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+    /// Set the deadline of the request, middlewares can shorten it to limit the time of the handlers after them.
+    #[inline]
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+    /// Get the time remaining before the deadline of the request, zero if the deadline is exceeded.
+    #[inline]
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Returns a future which completes when the client disconnects before the response is sent.
+    ///
+    /// Hyper drops the handler future when the connection is closed or the HTTP/2 stream is reset, this signal lets
+    /// work spawned by the handler, like a long-running query, stop too.
+    #[inline]
+    pub fn cancelled(&self) -> WaitForCancellationFutureOwned {
+        self.cancel_token.clone().cancelled_owned()
+    }
+    /// Check if the client disconnected before the response is sent.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.is_cancelled()
+    }
+    /// Get the token cancelled when the client disconnects, to be moved into spawned tasks.
+    #[inline]
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
+    }
+    /// Runs `fut` until it completes or the client disconnects, returns `None` if the client disconnected first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use salvo_core::prelude::*;
+    ///
+    /// #[handler]
+    /// async fn report(req: &mut Request, res: &mut Response) {
+    ///     let query = async { tokio::time::sleep(std::time::Duration::from_secs(5)).await; "report" };
+    ///     if let Some(report) = req.until_cancelled(query).await {
+    ///         res.render(report);
+    ///     }
+    /// }
+    /// ```
+    pub fn until_cancelled<F>(&self, fut: F) -> impl Future<Output = Option<F::Output>>
+    where
+        F: Future,
+    {
+        let cancelled = self.cancelled();
+        async move {
+            tokio::select! {
+                biased;
+                _ = cancelled => None,
+                output = fut => Some(output),
+            }
+        }
+    }
+
+    /// Get certificates presented by the client during the TLS handshake, end-entity certificate first.
+    ///
+    /// Returns `None` if the connection is not TLS or client authentication is not enabled.
+    #[cfg(any(feature = "native-tls", feature = "rustls", feature = "openssl", feature = "acme"))]
+    #[inline]
+    pub fn peer_certs(&self) -> Option<&[crate::conn::PeerCert]> {
+        self.extensions
+            .get::<std::sync::Arc<Vec<crate::conn::PeerCert>>>()
+            .map(|certs| certs.as_slice())
+    }
+
+    /// Get credentials of the peer process connected through a Unix domain socket.
+    ///
+    /// Returns `None` if the request is not received by [`UnixListener`](crate::conn::UnixListener).
+    #[cfg(unix)]
+    #[inline]
+    pub fn peer_cred(&self) -> Option<&tokio::net::unix::UCred> {
+        self.extensions.get::<tokio::net::unix::UCred>()
+    }
+
     /// Get request remote address reference.
     #[inline]
     pub fn local_addr(&self) -> &SocketAddr {
@@ -343,6 +446,40 @@ impl Request {
         &mut self.local_addr
     }
 
+    /// Takes the underlying connection, so the handler can speak another protocol on it.
+    ///
+    /// The handler should render a `101 Switching Protocols` response with `Connection: upgrade` and `Upgrade`
+    /// headers. The returned future resolves to the connection once the response is sent, which happens after
+    /// the handler returns, so it should be spawned.
+    ///
+    /// Returns `None` if the connection can not be upgraded, for example HTTP/2 connections, or if it is taken.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use salvo_core::http::header::{CONNECTION, UPGRADE};
+    /// use salvo_core::prelude::*;
+    /// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    ///
+    /// #[handler]
+    /// async fn tunnel(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+    ///     let io = req.take_io().ok_or_else(StatusError::bad_request)?;
+    ///     res.status_code(StatusCode::SWITCHING_PROTOCOLS);
+    ///     res.add_header(CONNECTION, "upgrade", true).ok();
+    ///     res.add_header(UPGRADE, "tunnel", true).ok();
+    ///     tokio::spawn(async move {
+    ///         if let Ok(mut io) = io.await {
+    ///             io.write_all(b"hello").await.ok();
+    ///         }
+    ///     });
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn take_io(&mut self) -> Option<impl Future<Output = Result<TokioIo<Upgraded>, Error>> + Send + 'static> {
+        let on_upgrade = self.extensions.remove::<OnUpgrade>()?;
+        Some(async move { on_upgrade.await.map(TokioIo::new).map_err(Error::Hyper) })
+    }
+
     /// Returns a reference to the associated header field map.
     ///
     /// # Examples
@@ -564,6 +701,15 @@ impl Request {
         &mut self.params
     }
 
+    /// Get the path pattern of the matched router, for example `/users/<id>`.
+    ///
+    /// It is joined from the path filters of the matched routers, and is `None` if no router matched.
+    /// Unlike the request path, it has a bounded number of values, so it is suitable for logs and metrics.
+    #[inline]
+    pub fn matched_path(&self) -> Option<&str> {
+        self.matched_path.as_deref()
+    }
+
     /// Get param value from params.
     #[inline]
     pub fn param<'de, T>(&'de self, key: &str) -> Option<T>
@@ -715,6 +861,29 @@ impl Request {
         }
     }
 
+    /// Get `FormData` reference from request, with limits, spooling and progress of uploaded files set by
+    /// `options`.
+    ///
+    /// Options are ignored if the form data is already parsed, by this method or [`Request::form_data`].
+    ///
+    /// *Notice: This method takes body.
+    #[inline]
+    pub async fn form_data_with_options(&mut self, options: &UploadOptions) -> Result<&FormData, ParseError> {
+        if let Some(ctype) = self.content_type() {
+            if ctype.subtype() == mime::WWW_FORM_URLENCODED || ctype.type_() == mime::MULTIPART {
+                let body = self.take_body();
+                let headers = self.headers();
+                self.form_data
+                    .get_or_try_init(|| async { FormData::read_with_options(headers, body, options).await })
+                    .await
+            } else {
+                Err(ParseError::NotFormData)
+            }
+        } else {
+            Err(ParseError::NotFormData)
+        }
+    }
+
     /// Extract request as type `T` from request's different parts.
     #[inline]
     pub async fn extract<'de, T>(&'de mut self) -> Result<T, ParseError>
@@ -951,4 +1120,88 @@ file content\r\n\
         let files = req.files("file1").await.unwrap();
         assert_eq!(files[0].name().unwrap(), "err.txt");
     }
+    #[tokio::test]
+    async fn test_form_data_with_options() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        fn build() -> Request {
+            TestClient::post("http://127.0.0.1:5800/upload")
+                .add_header("content-type", "multipart/form-data; boundary=boundary", true)
+                .body(
+                    "--boundary\r\n\
+Content-Disposition: form-data; name=\"file1\"; filename=\"../note.txt\"\r\n\
+Content-Type: text/plain\r\n\r\n\
+file content\r\n\
+--boundary--\r\n",
+                )
+                .build()
+        }
+
+        let received = Arc::new(AtomicU64::new(0));
+        let options = UploadOptions::new().spool_threshold(1024).on_progress({
+            let received = received.clone();
+            move |progress| received.store(progress.file_received, Ordering::SeqCst)
+        });
+        let mut req = build();
+        let form_data = req.form_data_with_options(&options).await.unwrap();
+        let file = form_data.files.get("file1").unwrap();
+        assert!(file.is_in_memory());
+        assert_eq!(file.data().unwrap(), "file content");
+        assert_eq!(file.read().await.unwrap(), "file content");
+        assert_eq!(file.sanitized_name().unwrap(), "note.txt");
+        assert_eq!(received.load(Ordering::SeqCst), 12);
+
+        let mut req = build();
+        let file = req
+            .form_data_with_options(&UploadOptions::new())
+            .await
+            .unwrap()
+            .files
+            .get("file1")
+            .unwrap();
+        assert!(!file.is_in_memory());
+        assert_eq!(file.read().await.unwrap(), "file content");
+
+        let mut req = build();
+        let options = UploadOptions::new().max_file_size(4);
+        assert!(matches!(
+            req.form_data_with_options(&options).await,
+            Err(ParseError::FileTooLarge)
+        ));
+
+        let mut req = build();
+        let options = UploadOptions::new().allowed_types(vec![mime::IMAGE_PNG]);
+        assert!(matches!(
+            req.form_data_with_options(&options).await,
+            Err(ParseError::UnsupportedFileType)
+        ));
+    }
+    #[tokio::test]
+    async fn test_deadline() {
+        let req = TestClient::get("http://127.0.0.1:5800/hello").build();
+        assert_eq!(req.deadline(), None);
+        assert_eq!(req.remaining_time(), None);
+
+        let mut req = TestClient::get("http://127.0.0.1:5800/hello")
+            .add_header("x-request-timeout", "2000", true)
+            .add_header("grpc-timeout", "5S", true)
+            .build();
+        let remaining = req.remaining_time().unwrap();
+        assert!(remaining <= Duration::from_secs(2) && remaining > Duration::from_secs(1));
+
+        req.set_deadline(Instant::now().checked_sub(Duration::from_secs(1)));
+        assert_eq!(req.remaining_time(), Some(Duration::ZERO));
+    }
+    #[tokio::test]
+    async fn test_cancelled() {
+        let req = TestClient::get("http://127.0.0.1:5800/hello").build();
+        assert!(!req.is_cancelled());
+        assert_eq!(req.until_cancelled(async { 1 }).await, Some(1));
+
+        req.cancellation_token().cancel();
+        assert!(req.is_cancelled());
+        req.cancelled().await;
+        assert_eq!(req.until_cancelled(std::future::pending::<()>()).await, None);
+    }
 }
@@ -6,7 +6,7 @@
 /// Re-export `async_trait`.
 pub use async_trait::async_trait;
 pub use hyper;
-pub use salvo_macros::handler;
+pub use salvo_macros::{controller, handler};
 
 pub use salvo_macros as macros;
 // https://github.com/bkchr/proc-macro-crate/issues/10
@@ -16,6 +16,7 @@ extern crate self as salvo_core;
 mod cfg;
 
 pub mod catcher;
+pub mod clock;
 pub mod conn;
 mod depot;
 mod error;
@@ -25,6 +26,7 @@ pub mod fuse;
 pub mod handler;
 pub mod http;
 pub mod proto;
+pub mod rng;
 pub mod routing;
 pub mod rt;
 #[doc(hidden)]
@@ -35,6 +37,10 @@ cfg_feature! {
     pub use self::server::Server;
 }
 mod service;
+cfg_feature! {
+    #![all(feature = "server", unix)]
+    pub mod upgrade;
+}
 pub mod writing;
 cfg_feature! {
     #![feature ="test"]
@@ -44,6 +50,10 @@ cfg_feature! {
     #![feature ="quinn"]
     pub use proto::webtransport;
 }
+cfg_feature! {
+    #![feature ="grpc"]
+    pub mod grpc;
+}
 cfg_feature! {
     #![feature ="tower-compat"]
     pub mod tower_compat;
@@ -51,7 +61,7 @@ cfg_feature! {
 }
 
 pub use self::conn::Listener;
-pub use self::depot::Depot;
+pub use self::depot::{Depot, DepotKey, DepotScope};
 pub use self::error::{BoxedError, Error};
 pub use self::extract::Extractible;
 pub use self::handler::Handler;
@@ -65,9 +75,10 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// A list of things that automatically imports into application use salvo_core.
 pub mod prelude {
     pub use async_trait::async_trait;
-    pub use salvo_macros::{handler, Extractible};
+    pub use salvo_macros::{controller, handler, Extractible};
 
     pub use crate::depot::Depot;
+    pub use crate::extract::State;
     pub use crate::http::{Request, Response, StatusCode, StatusError};
     cfg_feature! {
         #![feature = "acme"]
@@ -105,7 +116,7 @@ pub mod prelude {
         pub use crate::server::Server;
     }
     pub use crate::service::Service;
-    pub use crate::writing::{Json, Redirect, Scribe, Text, Writer};
+    pub use crate::writing::{Json, Jsonp, Redirect, Scribe, Text, Writer};
 }
 
 #[doc(hidden)]
@@ -0,0 +1,150 @@
+//! Random source abstraction, used by middlewares making random decisions, like request sampling.
+//!
+//! Middlewares read random numbers from a [`Rng`], which defaults to [`SystemRng`]. In tests, a [`MockRng`] can be
+//! injected instead, so random behaviors can be tested deterministically.
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// Source of random numbers.
+pub trait Rng: Send + Sync + 'static {
+    /// Returns a random `u64`.
+    fn next_u64(&self) -> u64;
+
+    /// Returns a random `f64` in the range `[0, 1)`.
+    #[inline]
+    fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl<R: Rng + ?Sized> Rng for Arc<R> {
+    #[inline]
+    fn next_u64(&self) -> u64 {
+        (**self).next_u64()
+    }
+    #[inline]
+    fn next_f64(&self) -> f64 {
+        (**self).next_f64()
+    }
+}
+
+impl<R: Rng + ?Sized> Rng for Box<R> {
+    #[inline]
+    fn next_u64(&self) -> u64 {
+        (**self).next_u64()
+    }
+    #[inline]
+    fn next_f64(&self) -> f64 {
+        (**self).next_f64()
+    }
+}
+
+/// [`Rng`] returning numbers of the thread local generator of `fastrand`.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct SystemRng;
+
+impl Rng for SystemRng {
+    #[inline]
+    fn next_u64(&self) -> u64 {
+        fastrand::u64(..)
+    }
+    #[inline]
+    fn next_f64(&self) -> f64 {
+        fastrand::f64()
+    }
+}
+
+/// [`Rng`] returning the values it is given, for tests.
+///
+/// Values are returned in order and the last one is repeated. Clones share the same values, so a clone can be
+/// injected into a middleware and the values set in the test.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::rng::{MockRng, Rng};
+///
+/// let rng = MockRng::new(1);
+/// assert_eq!(rng.next_u64(), 1);
+/// rng.set([2, 3]);
+/// assert_eq!(rng.next_u64(), 2);
+/// assert_eq!(rng.next_u64(), 3);
+/// assert_eq!(rng.next_u64(), 3);
+/// ```
+#[derive(Clone)]
+pub struct MockRng {
+    values: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Default for MockRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Debug for MockRng {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("MockRng").field("values", &*values).finish()
+    }
+}
+
+impl MockRng {
+    /// Create a new `MockRng` always returning `value`.
+    pub fn new(value: u64) -> Self {
+        Self {
+            values: Arc::new(Mutex::new(vec![value])),
+        }
+    }
+
+    /// Create a new `MockRng` which `next_f64` always returns `value`, clamped to `[0, 1)`.
+    pub fn with_f64(value: f64) -> Self {
+        Self::new(Self::from_f64(value))
+    }
+
+    /// Sets the values returned next, the last one is repeated. Empty values are ignored.
+    pub fn set(&self, values: impl IntoIterator<Item = u64>) {
+        let mut values: Vec<u64> = values.into_iter().collect();
+        if values.is_empty() {
+            return;
+        }
+        values.reverse();
+        *self.values.lock().unwrap_or_else(|e| e.into_inner()) = values;
+    }
+
+    /// Sets the values returned next by `next_f64`, the last one is repeated.
+    pub fn set_f64(&self, values: impl IntoIterator<Item = f64>) {
+        self.set(values.into_iter().map(Self::from_f64));
+    }
+
+    fn from_f64(value: f64) -> u64 {
+        let value = value.clamp(0.0, 1.0 - f64::EPSILON);
+        ((value * (1u64 << 53) as f64) as u64) << 11
+    }
+}
+
+impl Rng for MockRng {
+    fn next_u64(&self) -> u64 {
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        if values.len() > 1 {
+            values.pop().unwrap_or_default()
+        } else {
+            values.first().copied().unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_rng_f64() {
+        let rng = MockRng::with_f64(0.25);
+        assert_eq!(rng.next_f64(), 0.25);
+        rng.set_f64([0.5, 2.0]);
+        assert_eq!(rng.next_f64(), 0.5);
+        assert!(rng.next_f64() < 1.0);
+        assert!(SystemRng.next_f64() < 1.0);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fmt::{self, Formatter};
+use std::sync::Arc;
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::{Request, Response};
+use crate::rng::{Rng, SystemRng};
+use crate::routing::{Filter, FlowCtrl, PathState};
+use crate::{async_trait, Depot, DepotKey, Handler};
+
+/// Key of the [`Variants`] assigned to the current request in depot.
+pub const VARIANTS_KEY: DepotKey<Variants> = DepotKey::new("::salvo::experiment::variants");
+
+/// Variants of experiments assigned to a request, by experiment name.
+///
+/// They are stored in the extensions of the request when its routes are detected, and copied into the depot by the
+/// [`Experiment`] hoop.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Variants(HashMap<String, Option<String>>);
+impl Variants {
+    /// Get the variant assigned to the request in an experiment, `None` if the request is in the control group or
+    /// not assigned yet.
+    #[inline]
+    pub fn get(&self, experiment: &str) -> Option<&str> {
+        self.0.get(experiment).and_then(|variant| variant.as_deref())
+    }
+    /// Iterate over the experiments and their assigned variants.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.0
+            .iter()
+            .map(|(experiment, variant)| (experiment.as_str(), variant.as_deref()))
+    }
+}
+
+/// The value used to assign the same variant to the same client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StickyKey {
+    /// The value of a cookie, like a session or a visitor id.
+    #[cfg(feature = "cookie")]
+    Cookie(String),
+    /// The value of a header, like `X-User-Id` set by an authentication proxy.
+    Header(HeaderName),
+}
+
+#[derive(Clone)]
+struct ExperimentConfig {
+    name: String,
+    variants: Vec<(String, f64)>,
+    sticky_key: Option<StickyKey>,
+    response_header: Option<HeaderName>,
+    rng: Arc<dyn Rng>,
+}
+
+/// An experiment splitting traffic between variants, like an A/B test.
+///
+/// Each variant receives a percentage of requests, and the other requests are in the control group. When a
+/// [`StickyKey`] is set, the variant of a request is chosen from the hash of the key, so a client always gets the same
+/// variant, otherwise it is random.
+///
+/// [`Experiment::filter`] creates a filter matching requests assigned to a variant, to route them to alternative
+/// handlers. Used as a hoop, the experiment stores the assigned variants in the depot with [`VARIANTS_KEY`] and
+/// writes the variant in the [`response_header`](Experiment::response_header).
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_core::routing::{Experiment, StickyKey};
+///
+/// #[handler]
+/// async fn checkout() -> &'static str {
+///     "checkout"
+/// }
+/// #[handler]
+/// async fn new_checkout() -> &'static str {
+///     "new checkout"
+/// }
+///
+/// let experiment = Experiment::new("checkout")
+///     .variant("new", 20.0)
+///     .sticky_key(StickyKey::Header("x-user-id".parse().unwrap()))
+///     .response_header("x-variant".parse().unwrap());
+/// let router = Router::with_path("checkout")
+///     .hoop(experiment.clone())
+///     .push(Router::with_filter(experiment.filter("new")).get(new_checkout))
+///     .get(checkout);
+/// ```
+#[derive(Clone)]
+pub struct Experiment {
+    config: Arc<ExperimentConfig>,
+}
+impl fmt::Debug for Experiment {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "experiment:{}", self.config.name)
+    }
+}
+
+impl Experiment {
+    /// Create a new `Experiment` without variant.
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Experiment {
+            config: Arc::new(ExperimentConfig {
+                name: name.into(),
+                variants: vec![],
+                sticky_key: None,
+                response_header: None,
+                rng: Arc::new(SystemRng),
+            }),
+        }
+    }
+
+    /// Get the name of the experiment.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Adds a variant receiving a percentage of requests, from 0 to 100.
+    ///
+    /// The percentages of all variants should not exceed 100, the remaining requests are in the control group.
+    #[inline]
+    pub fn variant(mut self, name: impl Into<String>, percentage: f64) -> Self {
+        Arc::make_mut(&mut self.config)
+            .variants
+            .push((name.into(), percentage.clamp(0.0, 100.0)));
+        self
+    }
+
+    /// Sets the value used to assign the same variant to the same client.
+    #[inline]
+    pub fn sticky_key(mut self, sticky_key: StickyKey) -> Self {
+        Arc::make_mut(&mut self.config).sticky_key = Some(sticky_key);
+        self
+    }
+
+    /// Sets the response header the assigned variant is written to, `control` for the control group.
+    #[inline]
+    pub fn response_header(mut self, name: HeaderName) -> Self {
+        Arc::make_mut(&mut self.config).response_header = Some(name);
+        self
+    }
+
+    /// Sets the [`Rng`] assigning variants to requests without sticky key, defaults to [`SystemRng`].
+    ///
+    /// A [`MockRng`](crate::rng::MockRng) can be used in tests, so assignments are deterministic.
+    #[inline]
+    pub fn rng(mut self, rng: impl Rng) -> Self {
+        Arc::make_mut(&mut self.config).rng = Arc::new(rng);
+        self
+    }
+
+    /// Create a filter matching requests assigned to `variant`.
+    #[inline]
+    pub fn filter(&self, variant: impl Into<String>) -> VariantFilter {
+        VariantFilter {
+            experiment: self.clone(),
+            variant: variant.into(),
+        }
+    }
+
+    /// Get the variant assigned to the request, assigning it if it is not assigned yet.
+    ///
+    /// Returns `None` if the request is in the control group.
+    pub fn assign(&self, req: &mut Request) -> Option<String> {
+        if let Some(variant) = req
+            .extensions()
+            .get::<Variants>()
+            .and_then(|variants| variants.0.get(&self.config.name))
+        {
+            return variant.clone();
+        }
+        let bucket = match self.sticky_value(req) {
+            Some(value) => {
+                let hash = fnv1a(self.config.name.as_bytes(), value.as_bytes());
+                (hash % 10_000) as f64 / 100.0
+            }
+            None => self.config.rng.next_f64() * 100.0,
+        };
+        let mut upper = 0.0;
+        let variant = self
+            .config
+            .variants
+            .iter()
+            .find(|(_, percentage)| {
+                upper += percentage;
+                bucket < upper
+            })
+            .map(|(name, _)| name.clone());
+        let extensions = req.extensions_mut();
+        if extensions.get::<Variants>().is_none() {
+            extensions.insert(Variants::default());
+        }
+        if let Some(variants) = extensions.get_mut::<Variants>() {
+            variants.0.insert(self.config.name.clone(), variant.clone());
+        }
+        variant
+    }
+
+    fn sticky_value(&self, req: &Request) -> Option<String> {
+        match self.config.sticky_key.as_ref()? {
+            #[cfg(feature = "cookie")]
+            StickyKey::Cookie(name) => req.cookie(name).map(|cookie| cookie.value().to_owned()),
+            StickyKey::Header(name) => req.header::<String>(name),
+        }
+    }
+}
+
+/// Hashes the experiment name and the sticky value with FNV-1a, which is stable between processes, so a client keeps
+/// its variant when the server is restarted.
+fn fnv1a(name: &[u8], value: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.iter().chain(b":").chain(value) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[async_trait]
+impl Handler for Experiment {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let variant = self.assign(req);
+        if let Some(variants) = req.extensions().get::<Variants>() {
+            depot.insert_key(&VARIANTS_KEY, variants.clone());
+        }
+        if let Some(name) = &self.config.response_header {
+            if let Ok(value) = HeaderValue::from_str(variant.as_deref().unwrap_or("control")) {
+                res.headers_mut().insert(name, value);
+            }
+        }
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
+/// Filter matching requests assigned to a variant of an [`Experiment`].
+#[derive(Clone)]
+pub struct VariantFilter {
+    experiment: Experiment,
+    variant: String,
+}
+impl Filter for VariantFilter {
+    #[inline]
+    fn filter(&self, req: &mut Request, _state: &mut PathState) -> bool {
+        self.experiment.assign(req).as_deref() == Some(&*self.variant)
+    }
+}
+impl fmt::Debug for VariantFilter {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "variant:{}:{}", self.experiment.config.name, self.variant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::test::{ResponseExt, TestClient};
+
+    #[test]
+    fn test_assign() {
+        let experiment = Experiment::new("checkout")
+            .variant("a", 30.0)
+            .variant("b", 30.0)
+            .sticky_key(StickyKey::Header(HeaderName::from_static("x-user-id")));
+        let mut counts = HashMap::new();
+        for id in 0..1000 {
+            let mut req = Request::new();
+            req.headers_mut().insert("x-user-id", id.into());
+            let variant = experiment.assign(&mut req);
+            assert_eq!(experiment.assign(&mut req), variant);
+            let mut other = Request::new();
+            other.headers_mut().insert("x-user-id", id.into());
+            assert_eq!(experiment.assign(&mut other), variant);
+            *counts.entry(variant).or_insert(0) += 1;
+        }
+        assert!((200..400).contains(&counts[&Some("a".to_owned())]));
+        assert!((200..400).contains(&counts[&Some("b".to_owned())]));
+        assert!((300..500).contains(&counts[&None]));
+    }
+
+    #[test]
+    fn test_assign_random() {
+        use crate::rng::MockRng;
+
+        let rng = MockRng::with_f64(0.1);
+        let experiment = Experiment::new("checkout")
+            .variant("a", 30.0)
+            .variant("b", 30.0)
+            .rng(rng.clone());
+        assert_eq!(experiment.assign(&mut Request::new()).as_deref(), Some("a"));
+        rng.set_f64([0.5]);
+        assert_eq!(experiment.assign(&mut Request::new()).as_deref(), Some("b"));
+        rng.set_f64([0.9]);
+        assert_eq!(experiment.assign(&mut Request::new()), None);
+    }
+
+    #[tokio::test]
+    async fn test_experiment_routing() {
+        #[handler]
+        async fn old() -> &'static str {
+            "old"
+        }
+        #[handler]
+        async fn new_checkout(depot: &mut Depot) -> String {
+            format!("new {}", depot.get_key(&VARIANTS_KEY).unwrap().get("checkout").unwrap())
+        }
+        let experiment = Experiment::new("checkout")
+            .variant("new", 100.0)
+            .response_header(HeaderName::from_static("x-variant"));
+        let router = Router::with_path("checkout")
+            .hoop(experiment.clone())
+            .push(Router::with_filter(experiment.filter("new")).get(new_checkout))
+            .get(old);
+        let service = Service::new(router);
+        let mut res = TestClient::get("http://127.0.0.1:5801/checkout").send(&service).await;
+        assert_eq!(res.headers()["x-variant"], "new");
+        assert_eq!(res.take_string().await.unwrap(), "new new");
+
+        let experiment = Experiment::new("checkout")
+            .variant("new", 0.0)
+            .response_header(HeaderName::from_static("x-variant"));
+        let router = Router::with_path("checkout")
+            .hoop(experiment.clone())
+            .push(Router::with_filter(experiment.filter("new")).get(new_checkout))
+            .get(old);
+        let mut res = TestClient::get("http://127.0.0.1:5801/checkout").send(router).await;
+        assert_eq!(res.headers()["x-variant"], "control");
+        assert_eq!(res.take_string().await.unwrap(), "old");
+    }
+}
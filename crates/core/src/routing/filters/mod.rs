@@ -3,6 +3,7 @@
 //! This module provides filters for routing requests based on various criteria
 //! such as uri scheme, hostname, port, path, and HTTP method.
 
+mod experiment;
 mod opts;
 mod others;
 mod path;
@@ -14,6 +15,7 @@ use crate::http::uri::Scheme;
 use crate::http::{Method, Request};
 use crate::routing::PathState;
 
+pub use experiment::{Experiment, StickyKey, VariantFilter, Variants, VARIANTS_KEY};
 pub use others::*;
 pub use path::*;
 
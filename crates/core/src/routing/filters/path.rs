@@ -843,7 +843,7 @@ impl PathParser {
 
 /// Filter request by it's path information.
 pub struct PathFilter {
-    raw_value: String,
+    raw_value: Arc<str>,
     path_wisps: Vec<WispKind>,
 }
 
@@ -855,7 +855,12 @@ impl fmt::Debug for PathFilter {
 impl Filter for PathFilter {
     #[inline]
     fn filter(&self, _req: &mut Request, state: &mut PathState) -> bool {
-        self.detect(state)
+        if self.detect(state) {
+            state.matched_paths.push(self.raw_value.clone());
+            true
+        } else {
+            false
+        }
     }
 }
 impl PathFilter {
@@ -875,7 +880,10 @@ impl PathFilter {
                 panic!("{}, raw_value: {}", e, raw_value);
             }
         };
-        PathFilter { raw_value, path_wisps }
+        PathFilter {
+            raw_value: raw_value.into(),
+            path_wisps,
+        }
     }
     /// Register new path wisp builder.
     #[inline]
@@ -6,13 +6,16 @@ mod router;
 pub use filters::*;
 pub use router::{DetectMatched, Router};
 
+use std::any::TypeId;
 use std::borrow::Cow;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use indexmap::IndexMap;
 
+use crate::http::uri::{PathAndQuery, Uri};
 use crate::http::{Request, Response};
-use crate::{Depot, Handler};
+use crate::{Depot, Error, Handler};
 
 #[doc(hidden)]
 pub type PathParams = IndexMap<String, String>;
@@ -24,6 +27,7 @@ pub struct PathState {
     pub(crate) cursor: (usize, usize),
     pub(crate) params: PathParams,
     pub(crate) end_slash: bool, // For rest match, we want include the last slash.
+    pub(crate) matched_paths: Vec<Arc<str>>,
 }
 impl PathState {
     /// Create new `PathState`.
@@ -47,6 +51,7 @@ impl PathState {
             cursor: (0, 0),
             params: PathParams::new(),
             end_slash,
+            matched_paths: Vec::new(),
         }
     }
 
@@ -101,6 +106,22 @@ impl PathState {
         }
     }
 
+    /// Join the matched path filters to a path pattern.
+    pub(crate) fn matched_path(&self) -> String {
+        let mut pattern = String::new();
+        for path in &self.matched_paths {
+            let path = path.trim_matches('/');
+            if !path.is_empty() {
+                pattern.push('/');
+                pattern.push_str(path);
+            }
+        }
+        if pattern.is_empty() {
+            pattern.push('/');
+        }
+        pattern
+    }
+
     #[inline]
     pub fn is_ended(&self) -> bool {
         self.cursor.0 >= self.parts.len()
@@ -122,6 +143,45 @@ pub enum FlowCtrlStage {
     Catching,
 }
 
+/// Time spent in a handler called by [`FlowCtrl`], recorded in [`HandlerTimings`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct HandlerTiming {
+    /// Type name of the handler.
+    pub name: &'static str,
+    /// Whether the handler is the goal of the matched router, otherwise it is a hoop.
+    pub is_goal: bool,
+    /// Time spent in the handler itself, excluding the handlers it called with [`FlowCtrl::call_next`].
+    pub elapsed: Duration,
+}
+
+/// Timings of the handlers called by [`FlowCtrl`] while it is injected in the [`Depot`].
+///
+/// It is used by profiling middlewares, which inject it with [`Depot::inject`] before calling
+/// [`FlowCtrl::call_next`], then read it with [`Depot::obtain`]. The time of each handler is recorded excluding the
+/// time of the handlers it calls with [`FlowCtrl::call_next`]. Handlers are not timed when it is not injected.
+#[derive(Clone, Debug, Default)]
+pub struct HandlerTimings {
+    timings: Vec<HandlerTiming>,
+    nested_elapsed: Vec<Duration>,
+}
+impl HandlerTimings {
+    /// Create a new empty `HandlerTimings`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the timings of the handlers called since it is injected, in calling order.
+    #[inline]
+    pub fn timings(&self) -> &[HandlerTiming] {
+        &self.timings
+    }
+}
+
+/// Max number of times a request can be forwarded by [`FlowCtrl::forward`], to avoid forwarding loops.
+pub const MAX_FORWARDS: usize = 10;
+
 /// `FlowCtrl` is used to control the flow of execute handlers.
 ///
 /// When a request is coming, [`Router`] will detect it and get the matched one.
@@ -139,6 +199,12 @@ pub struct FlowCtrl {
     is_ceased: bool,
     cursor: usize,
     pub(crate) handlers: Vec<Arc<dyn Handler>>,
+    pub(crate) routing_duration: Option<Duration>,
+    aborted: Option<Error>,
+    pub(crate) router: Option<Arc<Router>>,
+    /// Index of the first handler of the route, the handlers before it are the hoops of the service.
+    pub(crate) route_start: usize,
+    forwards: usize,
 }
 
 impl FlowCtrl {
@@ -150,6 +216,11 @@ impl FlowCtrl {
             is_ceased: false,
             cursor: 0,
             handlers,
+            routing_duration: None,
+            aborted: None,
+            router: None,
+            route_start: 0,
+            forwards: 0,
         }
     }
     /// Has next handler.
@@ -176,7 +247,11 @@ impl FlowCtrl {
         } else {
             while let Some(h) = handler.take() {
                 self.cursor += 1;
-                h.handle(req, depot, res, self).await;
+                if depot.obtain::<HandlerTimings>().is_ok() {
+                    self.call_timed(h, req, depot, res).await;
+                } else {
+                    h.handle(req, depot, res, self).await;
+                }
                 if !self.catching.unwrap_or_default() && res.is_stamped() {
                     self.skip_rest();
                     return true;
@@ -188,12 +263,158 @@ impl FlowCtrl {
         }
     }
 
+    async fn call_timed(
+        &mut self,
+        handler: Arc<dyn Handler>,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) {
+        let index = match depot.obtain_mut::<HandlerTimings>() {
+            Ok(timings) => {
+                timings.timings.push(HandlerTiming {
+                    name: handler.type_name(),
+                    is_goal: self.cursor == self.handlers.len(),
+                    elapsed: Duration::ZERO,
+                });
+                timings.nested_elapsed.push(Duration::ZERO);
+                timings.timings.len() - 1
+            }
+            Err(_) => return handler.handle(req, depot, res, self).await,
+        };
+        let started = Instant::now();
+        handler.handle(req, depot, res, self).await;
+        let elapsed = started.elapsed();
+        // The timings may have been removed from the depot by the handler.
+        if let Ok(timings) = depot.obtain_mut::<HandlerTimings>() {
+            let nested = timings.nested_elapsed.pop().unwrap_or_default();
+            if let Some(parent) = timings.nested_elapsed.last_mut() {
+                *parent += elapsed;
+            }
+            if let Some(timing) = timings.timings.get_mut(index) {
+                timing.elapsed = elapsed.saturating_sub(nested);
+            }
+        }
+    }
+
+    /// Returns the time spent by the router to detect the handlers of the request.
+    #[inline]
+    pub fn routing_duration(&self) -> Option<Duration> {
+        self.routing_duration
+    }
+
     /// Skip all reset handlers.
     #[inline]
     pub fn skip_rest(&mut self) {
         self.cursor = self.handlers.len()
     }
 
+    /// Skip the rest handlers until a handler of type `H`, which will be called next.
+    ///
+    /// If there is no handler of type `H` in the rest handlers, all of them are skipped and returns `false`.
+    /// Handlers created by `#[handler]` on a function have the type of the function name, so a handler can be used
+    /// as a marker, like `ctrl.skip_rest_until::<audit>()`.
+    pub fn skip_rest_until<H: Handler>(&mut self) -> bool {
+        let position = self.handlers[self.cursor..]
+            .iter()
+            .position(|handler| Handler::type_id(&**handler) == TypeId::of::<H>());
+        match position {
+            Some(position) => {
+                self.cursor += position;
+                true
+            }
+            None => {
+                self.skip_rest();
+                false
+            }
+        }
+    }
+
+    /// Abort the flow with an error, which is rendered to the response and handled by the catcher when all the
+    /// called handlers return, so middlewares writing the response after calling the next handlers can't
+    /// replace it.
+    ///
+    /// The rest handlers are skipped and the flow is [ceased](FlowCtrl::cease).
+    #[inline]
+    pub fn abort(&mut self, error: impl Into<Error>) {
+        self.aborted = Some(error.into());
+        self.cease();
+    }
+
+    /// Returns the error of [`FlowCtrl::abort`], if the flow is aborted.
+    #[inline]
+    pub fn aborted(&self) -> Option<&Error> {
+        self.aborted.as_ref()
+    }
+
+    /// Takes the error of [`FlowCtrl::abort`] out, it is used by [`Service`](crate::Service) to render it.
+    #[inline]
+    pub(crate) fn take_aborted(&mut self) -> Option<Error> {
+        self.aborted.take()
+    }
+
+    /// Forward the request to another path internally, without sending a redirection to the client.
+    ///
+    /// The uri of the request is replaced by `path`, which can contain a query, and the router of the service
+    /// detects the handlers of the new path. The hoops and the goal of the router of the original path are
+    /// replaced by the ones of the new router. If it is called by a hoop added to the [`Service`](crate::Service),
+    /// the rest hoops of the service are still called before them, otherwise they are called next. Hoops of the
+    /// service which have been called are not called again.
+    ///
+    /// Returns `false` and leaves the request unchanged if `path` is invalid, if no router matches it, or if the
+    /// request has been forwarded [`MAX_FORWARDS`] times. It always returns `false` in the catcher.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use salvo_core::prelude::*;
+    ///
+    /// #[handler]
+    /// async fn legacy(req: &mut Request, ctrl: &mut FlowCtrl) {
+    ///     let path = format!("/users/{}", req.param::<u64>("id").unwrap_or_default());
+    ///     ctrl.forward(&path, req);
+    /// }
+    /// ```
+    pub fn forward(&mut self, path: &str, req: &mut Request) -> bool {
+        let Some(router) = self.router.clone() else {
+            return false;
+        };
+        if self.forwards >= MAX_FORWARDS {
+            tracing::warn!(path, "request is forwarded too many times");
+            return false;
+        }
+        let Ok(path_and_query) = path.parse::<PathAndQuery>() else {
+            return false;
+        };
+        let mut parts = req.uri().clone().into_parts();
+        parts.path_and_query = Some(path_and_query);
+        let Ok(uri) = Uri::from_parts(parts) else {
+            return false;
+        };
+
+        let original_uri = std::mem::replace(req.uri_mut(), uri);
+        let original_queries = std::mem::take(&mut req.queries);
+        let mut path_state = PathState::new(req.uri().path());
+        let Some(dm) = router.detect(req, &mut path_state) else {
+            *req.uri_mut() = original_uri;
+            req.queries = original_queries;
+            return false;
+        };
+        req.matched_path = Some(path_state.matched_path());
+        req.params = path_state.params;
+        self.forwards += 1;
+        if self.cursor <= self.route_start {
+            // Called by a hoop of the service, the rest hoops of the service are kept.
+            self.handlers.truncate(self.route_start);
+        } else {
+            self.handlers.truncate(self.cursor);
+            self.route_start = self.cursor;
+        }
+        self.handlers.extend(dm.hoops);
+        self.handlers.push(dm.goal);
+        true
+    }
+
     /// Check is `FlowCtrl` ceased.
     #[inline]
     pub fn is_ceased(&self) -> bool {
@@ -242,4 +463,161 @@ mod tests {
         assert!(access(&service, "127.0.0.1").await.contains("404: Not Found"));
         assert_eq!(access(&service, "localhost").await, "Hello World");
     }
+
+    #[tokio::test]
+    async fn test_flow_ctrl_timings() {
+        use std::time::Duration;
+
+        use crate::routing::HandlerTimings;
+
+        #[handler]
+        async fn profile(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+            depot.inject(HandlerTimings::new());
+            ctrl.call_next(req, depot, res).await;
+            let timings = depot
+                .obtain::<HandlerTimings>()
+                .unwrap()
+                .timings()
+                .iter()
+                .map(|timing| format!("{}:{}", timing.is_goal, timing.elapsed >= Duration::from_millis(20)))
+                .collect::<Vec<_>>();
+            res.render(format!("{} {}", ctrl.routing_duration().is_some(), timings.join(",")));
+        }
+        #[handler]
+        async fn wrap(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+            ctrl.call_next(req, depot, res).await;
+        }
+        #[handler]
+        async fn slow() {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+        }
+
+        let router = Router::new().hoop(profile).hoop(wrap).goal(slow);
+        let content = TestClient::get("http://127.0.0.1:5801")
+            .send(router)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert_eq!(content, "true false:false,true:true");
+    }
+
+    #[tokio::test]
+    async fn test_flow_ctrl_abort_skip_until_and_forward() {
+        #[handler]
+        async fn guard(req: &mut Request, ctrl: &mut FlowCtrl) {
+            if req.query::<bool>("deny").unwrap_or_default() {
+                ctrl.abort(StatusError::forbidden());
+            } else if req.query::<bool>("skip").unwrap_or_default() {
+                ctrl.skip_rest_until::<audit>();
+            }
+        }
+        #[handler]
+        async fn overwrite(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+            ctrl.call_next(req, depot, res).await;
+            if ctrl.aborted().is_some() {
+                res.render("overwritten");
+            }
+        }
+        #[handler]
+        async fn expensive(res: &mut Response) {
+            res.headers_mut().insert("x-expensive", "1".parse().unwrap());
+        }
+        #[handler]
+        async fn audit(res: &mut Response) {
+            res.headers_mut().insert("x-audit", "1".parse().unwrap());
+        }
+        #[handler]
+        async fn user(req: &mut Request) -> String {
+            format!("user {}", req.param::<u64>("id").unwrap_or_default())
+        }
+        #[handler]
+        async fn legacy(req: &mut Request, ctrl: &mut FlowCtrl) {
+            let path = format!("/users/{}", req.param::<u64>("id").unwrap_or_default());
+            ctrl.forward(&path, req);
+        }
+        #[handler]
+        async fn fallback(req: &mut Request, ctrl: &mut FlowCtrl) {
+            if req.uri().path().starts_with("/old/") {
+                ctrl.forward("/users/0?from=old", req);
+            }
+        }
+
+        let router = Router::new()
+            .push(Router::with_path("users/<id>").get(user))
+            .push(Router::with_path("legacy/<id>").get(legacy))
+            .push(
+                Router::with_path("guarded")
+                    .hoop(overwrite)
+                    .hoop(guard)
+                    .hoop(expensive)
+                    .hoop(audit)
+                    .get(user),
+            );
+        let service = Service::new(router).hoop(fallback);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/guarded?deny=true")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+        assert!(!res.take_string().await.unwrap().contains("overwritten"));
+
+        let res = TestClient::get("http://127.0.0.1:5801/guarded?skip=true")
+            .send(&service)
+            .await;
+        assert!(res.headers().get("x-expensive").is_none());
+        assert!(res.headers().get("x-audit").is_some());
+        let res = TestClient::get("http://127.0.0.1:5801/guarded").send(&service).await;
+        assert!(res.headers().get("x-expensive").is_some());
+
+        let content = TestClient::get("http://127.0.0.1:5801/legacy/7")
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert_eq!(content, "user 7");
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/old/page").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "user 0");
+
+        let res = TestClient::get("http://127.0.0.1:5801/missing").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_flow_ctrl_forward_keeps_service_hoops() {
+        #[handler]
+        async fn rename(req: &mut Request, ctrl: &mut FlowCtrl) {
+            if req.uri().path() == "/old" {
+                ctrl.forward("/new", req);
+            }
+        }
+        #[handler]
+        async fn stamp(res: &mut Response) {
+            res.headers_mut().insert("x-stamp", "1".parse().unwrap());
+        }
+        #[handler]
+        async fn old() -> &'static str {
+            "old"
+        }
+        #[handler]
+        async fn new() -> &'static str {
+            "new"
+        }
+
+        let router = Router::new()
+            .push(Router::with_path("old").get(old))
+            .push(Router::with_path("new").get(new));
+        let service = Service::new(router).hoop(rename).hoop(stamp);
+
+        for path in ["old", "new"] {
+            let mut res = TestClient::get(format!("http://127.0.0.1:5801/{path}"))
+                .send(&service)
+                .await;
+            assert!(res.headers().get("x-stamp").is_some());
+            assert_eq!(res.take_string().await.unwrap(), "new");
+        }
+    }
 }
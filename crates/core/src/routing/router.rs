@@ -4,9 +4,11 @@ use std::sync::Arc;
 
 use super::filters;
 use super::{Filter, FnFilter, PathFilter, PathState};
-use crate::handler::{Handler, WhenHoop};
+use futures_util::future::BoxFuture;
+
+use crate::handler::{FnHoop, Handler, Next, WhenHoop};
 use crate::http::uri::Scheme;
-use crate::{Depot, Request};
+use crate::{Depot, Request, Response};
 
 /// Router struct is used for route request to different handlers.
 ///
@@ -166,6 +168,7 @@ impl Router {
         }
         if !self.routers.is_empty() {
             let original_cursor = path_state.cursor;
+            let original_matched = path_state.matched_paths.len();
             for child in &self.routers {
                 if let Some(dm) = child.detect(req, path_state) {
                     return Some(DetectMatched {
@@ -174,6 +177,7 @@ impl Router {
                     });
                 } else {
                     path_state.cursor = original_cursor;
+                    path_state.matched_paths.truncate(original_matched);
                 }
             }
         }
@@ -252,6 +256,41 @@ impl Router {
         self
     }
 
+    /// Add a function as middleware, which wraps the rest of the handlers, so simple middlewares don't need to
+    /// implement [`Handler`] nor to manage [`FlowCtrl`](crate::FlowCtrl).
+    ///
+    /// The function runs the rest of the handlers with [`Next::run`], if it does not, the rest of the handlers are
+    /// skipped. It should return a boxed future, like `Box::pin(async move { ... })`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Instant;
+    ///
+    /// use salvo_core::prelude::*;
+    ///
+    /// let router = Router::new().hoop_fn(|req, depot, res, next| {
+    ///     Box::pin(async move {
+    ///         let started = Instant::now();
+    ///         next.run(req, depot, res).await;
+    ///         let elapsed = format!("{}", started.elapsed().as_millis());
+    ///         if let Ok(value) = elapsed.parse() {
+    ///             res.headers_mut().insert("x-elapsed-ms", value);
+    ///         }
+    ///     })
+    /// });
+    /// ```
+    #[inline]
+    pub fn hoop_fn<F>(self, f: F) -> Self
+    where
+        F: for<'a> Fn(&'a mut Request, &'a mut Depot, &'a mut Response, Next<'a>) -> BoxFuture<'a, ()>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.hoop(FnHoop { f })
+    }
+
     /// Create a new router and set path filter.
     ///
     /// # Panics
@@ -696,4 +735,22 @@ mod tests {
         assert!(matched.is_some());
         assert_eq!(path_state.params["p"], "a/b/c");
     }
+
+    #[test]
+    fn test_router_detect_matched_path() {
+        let router = Router::with_path("api").push(
+            Router::with_path("users")
+                .push(Router::with_path("<id:num>/emails").get(fake_handler))
+                .push(Router::with_path("<name>/profile").get(fake_handler)),
+        );
+        let mut req = TestClient::get("http://local.host/api/users/bob/profile").build();
+        let mut path_state = PathState::new(req.uri().path());
+        assert!(router.detect(&mut req, &mut path_state).is_some());
+        assert_eq!(path_state.matched_path(), "/api/users/<name>/profile");
+
+        let mut req = TestClient::get("http://local.host/api/users/7/emails").build();
+        let mut path_state = PathState::new(req.uri().path());
+        assert!(router.detect(&mut req, &mut path_state).is_some());
+        assert_eq!(path_state.matched_path(), "/api/users/<id:num>/emails");
+    }
 }
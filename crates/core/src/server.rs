@@ -1,6 +1,7 @@
 //! Server module
-use std::io::Result as IoResult;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::future::Future;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 #[cfg(not(any(feature = "http1", feature = "http2", feature = "quinn")))]
@@ -12,25 +13,75 @@ compile_error!(
 use hyper::server::conn::http1;
 #[cfg(feature = "http2")]
 use hyper::server::conn::http2;
+use tokio::runtime::Handle as RuntimeHandle;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-use tokio::sync::Notify;
+use tokio::sync::{Notify, Semaphore};
 use tokio::time::Duration;
 use tokio_util::sync::CancellationToken;
 
 #[cfg(feature = "quinn")]
 use crate::conn::quinn;
-use crate::conn::{Accepted, Acceptor, Holding, HttpBuilder};
+use crate::conn::{Accepted, Acceptor, Holding, HttpBuilder, Listener, SocketAddr};
 use crate::fuse::{ArcFuseFactory, FuseFactory, SteadyFusewire};
 use crate::http::{HeaderValue, HttpConnection, Version};
 use crate::Service;
 
-/// Server handle is used to stop server.
+mod config;
+pub use config::{ConfigAcceptor, ServerConfig, TlsConfig, DEFAULT_LISTEN};
+
+/// Smallest value accepted by [`Server::max_header_size`], 8 KiB.
+pub const MIN_MAX_HEADER_SIZE: usize = 8 * 1024;
+
+/// Server handle is used to stop server and inspect its state.
 #[derive(Clone)]
 pub struct ServerHandle {
     tx_cmd: UnboundedSender<ServerCommand>,
+    holdings: Arc<Vec<Holding>>,
+    alive_connections: Arc<AtomicUsize>,
+    accepted_connections: Arc<AtomicU64>,
+    handled_connections: Arc<AtomicU64>,
 }
 
 impl ServerHandle {
+    /// Get holding information of the server.
+    #[inline]
+    pub fn holdings(&self) -> &[Holding] {
+        &self.holdings
+    }
+
+    /// Local addresses the server is listening on.
+    ///
+    /// This can be useful, for example, when binding to port 0 to figure out which port was actually bound.
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.holdings.iter().map(|holding| holding.local_addr.clone()).collect()
+    }
+
+    /// Number of connections currently being served.
+    #[inline]
+    pub fn active_connections(&self) -> usize {
+        self.alive_connections.load(Ordering::Acquire)
+    }
+
+    /// Total number of connections accepted since the server started.
+    #[inline]
+    pub fn accepted_connections(&self) -> u64 {
+        self.accepted_connections.load(Ordering::Relaxed)
+    }
+
+    /// Total number of connections served and closed since the server started.
+    #[inline]
+    pub fn handled_connections(&self) -> u64 {
+        self.handled_connections.load(Ordering::Relaxed)
+    }
+
+    /// Shutdown server gracefully, `grace` is the time in-flight requests are given to complete.
+    ///
+    /// It is the same as [`ServerHandle::stop_graceful`].
+    #[inline]
+    pub fn shutdown(&self, grace: impl Into<Option<Duration>>) {
+        self.stop_graceful(grace);
+    }
+
     /// Force stop server.
     ///
     /// Call this function will stop server immediately.
@@ -87,6 +138,13 @@ pub struct Server<A> {
     acceptor: A,
     builder: HttpBuilder,
     fuse_factory: ArcFuseFactory,
+    alt_svc_max_age: Option<Duration>,
+    drain_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+    runtime: Option<RuntimeHandle>,
+    alive_connections: Arc<AtomicUsize>,
+    accepted_connections: Arc<AtomicU64>,
+    handled_connections: Arc<AtomicU64>,
     tx_cmd: UnboundedSender<ServerCommand>,
     rx_cmd: UnboundedReceiver<ServerCommand>,
 }
@@ -116,6 +174,13 @@ impl<A: Acceptor + Send> Server<A> {
             acceptor,
             builder,
             fuse_factory: Arc::new(SteadyFusewire),
+            alt_svc_max_age: Some(Duration::from_secs(2592000)),
+            drain_timeout: None,
+            max_connections: None,
+            runtime: None,
+            alive_connections: Arc::new(AtomicUsize::new(0)),
+            accepted_connections: Arc::new(AtomicU64::new(0)),
+            handled_connections: Arc::new(AtomicU64::new(0)),
             tx_cmd,
             rx_cmd,
         }
@@ -130,10 +195,31 @@ impl<A: Acceptor + Send> Server<A> {
         self
     }
 
-    /// Get a [`ServerHandle`] to stop server.
+    /// Sets the `max-age` of the `Alt-Svc` header used to advertise HTTP/3, `None` disables the header.
+    ///
+    /// When the acceptor holds an HTTP/3 listener, responses sent over HTTP/1 and HTTP/2 include an
+    /// `Alt-Svc` header, so clients can switch to HTTP/3. Defaults to 30 days.
+    pub fn alt_svc_max_age(mut self, max_age: impl Into<Option<Duration>>) -> Self {
+        self.alt_svc_max_age = max_age.into();
+        self
+    }
+
+    /// Sets how long in-flight requests may take to complete after the shutdown signal passed to
+    /// [`Server::serve_with_graceful_shutdown`] is received, the remaining connections are closed
+    /// forcibly afterwards. Defaults to `None`, which waits until all connections are closed.
+    pub fn drain_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.drain_timeout = timeout.into();
+        self
+    }
+
+    /// Get a [`ServerHandle`] to stop server and inspect its state.
     pub fn handle(&self) -> ServerHandle {
         ServerHandle {
             tx_cmd: self.tx_cmd.clone(),
+            holdings: Arc::new(self.acceptor.holdings().to_vec()),
+            alive_connections: self.alive_connections.clone(),
+            accepted_connections: self.accepted_connections.clone(),
+            handled_connections: self.handled_connections.clone(),
         }
     }
 
@@ -159,6 +245,120 @@ impl<A: Acceptor + Send> Server<A> {
         self.acceptor.holdings()
     }
 
+    /// Sets the maximum number of connections served concurrently.
+    ///
+    /// When the limit is reached, the server stops accepting new connections until one of the
+    /// existing connections is closed, pending connections wait in the listen backlog of the OS.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Serve connections on the given runtime, the accept loop keeps running on the current one.
+    ///
+    /// A dedicated runtime for the accept loop keeps accepting responsive, when the connection runtime is busy.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use salvo_core::prelude::*;
+    ///
+    /// fn main() {
+    ///     let workers = tokio::runtime::Builder::new_multi_thread()
+    ///         .worker_threads(8)
+    ///         .enable_all()
+    ///         .build()
+    ///         .unwrap();
+    ///     let acceptor = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    ///     acceptor.block_on(async {
+    ///         let listener = TcpListener::new("0.0.0.0:5800").bind().await;
+    ///         Server::new(listener).runtime(workers.handle().clone()).serve(Router::new()).await;
+    ///     });
+    /// }
+    /// ```
+    pub fn runtime(mut self, handle: RuntimeHandle) -> Self {
+        self.runtime = Some(handle);
+        self
+    }
+
+    /// Sets the maximum size of request headers, larger requests are rejected.
+    ///
+    /// For HTTP/1 this is the maximum size of the read buffer of the connection, which holds the request line and
+    /// the headers, and for HTTP/2 the maximum size of the header list. Values smaller than
+    /// [`MIN_MAX_HEADER_SIZE`] (8 KiB) are raised to it, as HTTP/1 connections need a read buffer of at least 8 KiB.
+    #[cfg(any(feature = "http1", feature = "http2"))]
+    pub fn max_header_size(mut self, size: usize) -> Self {
+        let size = size.max(MIN_MAX_HEADER_SIZE);
+        #[cfg(feature = "http1")]
+        self.builder.http1.max_buf_size(size);
+        #[cfg(feature = "http2")]
+        self.builder
+            .http2
+            .max_header_list_size(size.try_into().unwrap_or(u32::MAX));
+        self
+    }
+
+    /// Sets how long a read from a TCP connection may wait for data, the connection is closed when it elapses.
+    ///
+    /// It covers clients sending request heads slowly and idle keep-alive connections, as well as upgraded
+    /// connections like WebSocket, which should send heartbeats more often. It is not armed while a request is
+    /// handled, until its response is written, so slow handlers and request bodies read by handlers are not covered,
+    /// see `Timeout::read_timeout` of `salvo_extra` for the latter. There is no timeout by default.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.builder.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long a write to a TCP connection may wait for the client to receive data, the connection is closed
+    /// when it elapses, so clients which stop reading responses do not hold connections. There is no timeout by
+    /// default.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.builder.write_timeout = Some(timeout);
+        self
+    }
+
+    cfg_feature! {
+        #![feature = "http1"]
+        /// Sets the timeout for receiving the headers of a HTTP/1 request, slow clients are disconnected.
+        pub fn header_read_timeout(mut self, timeout: Duration) -> Self {
+            self.builder
+                .http1
+                .timer(crate::rt::tokio::TokioTimer::new())
+                .header_read_timeout(timeout);
+            self
+        }
+    }
+
+    cfg_feature! {
+        #![feature = "http2"]
+        /// Sets the maximum number of concurrent streams of each HTTP/2 connection.
+        pub fn max_concurrent_streams(mut self, max: u32) -> Self {
+            self.builder.http2.max_concurrent_streams(max);
+            self
+        }
+
+        /// Sets whether to accept HTTP/2 over cleartext with prior knowledge, defaults to `true`.
+        ///
+        /// Connections without TLS, which start with the HTTP/2 connection preface, are served as HTTP/2, as
+        /// gRPC and service mesh clients do without TLS. See [`Server::h2c_upgrade`] for clients upgrading
+        /// HTTP/1.1 connections. It has no effect on TLS connections, which negotiate HTTP/2 by ALPN.
+        pub fn h2c_prior_knowledge(mut self, enabled: bool) -> Self {
+            self.builder.h2c_prior_knowledge = enabled;
+            self
+        }
+
+        /// Sets whether to upgrade HTTP/1.1 connections without TLS to HTTP/2 when requests ask for it with
+        /// `Upgrade: h2c`, defaults to `false`.
+        ///
+        /// The request asking for the upgrade is answered with `101 Switching Protocols` and served as the first
+        /// stream of the HTTP/2 connection. Requests with a body are answered with HTTP/1.1, the upgrade is ignored.
+        /// It has no effect on TLS connections.
+        pub fn h2c_upgrade(mut self, enabled: bool) -> Self {
+            self.builder.h2c_upgrade = enabled;
+            self
+        }
+    }
+
     cfg_feature! {
         #![feature = "http1"]
         /// Use this function to set http1 protocol.
@@ -211,60 +411,88 @@ impl<A: Acceptor + Send> Server<A> {
     }
 
     /// Try to serve a [`Service`].
+    #[inline]
     pub async fn try_serve<S>(self, service: S) -> IoResult<()>
     where
         S: Into<Service> + Send,
+    {
+        self.try_serve_with_graceful_shutdown(service, std::future::pending())
+            .await
+    }
+
+    /// Serve a [`Service`] until `signal` completes, then stop the server gracefully.
+    ///
+    /// New connections are rejected once `signal` completes, in-flight requests are given
+    /// [`drain_timeout`](Server::drain_timeout) to finish. Use [`shutdown_signal`] to stop on
+    /// `SIGINT` or `SIGTERM`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use salvo_core::prelude::*;
+    /// use salvo_core::server::shutdown_signal;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let acceptor = TcpListener::new("0.0.0.0:5800").bind().await;
+    ///     Server::new(acceptor)
+    ///         .drain_timeout(Duration::from_secs(30))
+    ///         .serve_with_graceful_shutdown(Router::new(), shutdown_signal())
+    ///         .await;
+    /// }
+    /// ```
+    #[inline]
+    pub async fn serve_with_graceful_shutdown<S, G>(self, service: S, signal: G)
+    where
+        S: Into<Service> + Send,
+        G: Future<Output = ()> + Send,
+    {
+        self.try_serve_with_graceful_shutdown(service, signal)
+            .await
+            .expect("failed to call `Server::serve_with_graceful_shutdown`");
+    }
+
+    /// Try to serve a [`Service`] until `signal` completes, then stop the server gracefully.
+    pub async fn try_serve_with_graceful_shutdown<S, G>(self, service: S, signal: G) -> IoResult<()>
+    where
+        S: Into<Service> + Send,
+        G: Future<Output = ()> + Send,
     {
         let Self {
             mut acceptor,
             builder,
             fuse_factory,
+            alt_svc_max_age,
+            drain_timeout,
+            max_connections,
+            runtime,
+            alive_connections,
+            accepted_connections,
+            handled_connections,
             mut rx_cmd,
             ..
         } = self;
-        let alive_connections = Arc::new(AtomicUsize::new(0));
         let notify = Arc::new(Notify::new());
         let force_stop_token = CancellationToken::new();
         let graceful_stop_token = CancellationToken::new();
 
-        let mut alt_svc_h3 = None;
         for holding in acceptor.holdings() {
             tracing::info!("listening {}", holding);
-            if holding.http_versions.contains(&Version::HTTP_3) {
-                if let Some(addr) = holding.local_addr.clone().into_std() {
-                    let port = addr.port();
-                    alt_svc_h3 = Some(
-                        format!(r#"h3=":{port}"; ma=2592000,h3-29=":{port}"; ma=2592000"#)
-                            .parse::<HeaderValue>()
-                            .expect("Parse alt-svc header failed."),
-                    );
-                }
-            }
         }
+        let alt_svc_h3 = alt_svc_max_age.and_then(|max_age| alt_svc_h3(acceptor.holdings(), max_age));
 
         let service: Arc<Service> = Arc::new(service.into());
         let builder = Arc::new(builder);
+        let connection_limit = max_connections.map(|max| Arc::new(Semaphore::new(max)));
+        tokio::pin!(signal);
         loop {
             tokio::select! {
                 Some(cmd) = rx_cmd.recv() => {
                     match cmd {
                         ServerCommand::StopGraceful(timeout) => {
-                            let graceful_stop_token = graceful_stop_token.clone();
-                            graceful_stop_token.cancel();
-                            if let Some(timeout) = timeout {
-                                tracing::info!(
-                                    timeout_in_seconds = timeout.as_secs_f32(),
-                                    "initiate graceful stop server",
-                                );
-
-                                let force_stop_token = force_stop_token.clone();
-                                tokio::spawn(async move {
-                                    tokio::time::sleep(timeout).await;
-                                    force_stop_token.cancel();
-                                });
-                            } else {
-                                tracing::info!("initiate graceful stop server");
-                            }
+                            stop_graceful(&graceful_stop_token, &force_stop_token, timeout);
                         },
                         ServerCommand::StopForcible => {
                             tracing::info!("force stop server");
@@ -273,13 +501,26 @@ impl<A: Acceptor + Send> Server<A> {
                     }
                     break;
                 },
-                accepted = acceptor.accept(fuse_factory.clone()) => {
+                _ = &mut signal => {
+                    stop_graceful(&graceful_stop_token, &force_stop_token, drain_timeout);
+                    break;
+                },
+                (accepted, permit) = async {
+                    // Wait for a free slot before accepting, so that extra connections stay in the backlog.
+                    let permit = match &connection_limit {
+                        Some(limit) => limit.clone().acquire_owned().await.ok(),
+                        None => None,
+                    };
+                    (acceptor.accept(fuse_factory.clone()).await, permit)
+                } => {
                     match accepted {
                         Ok(Accepted { conn, local_addr, remote_addr, http_scheme, ..}) => {
                             alive_connections.fetch_add(1, Ordering::Release);
+                            accepted_connections.fetch_add(1, Ordering::Relaxed);
 
                             let service = service.clone();
                             let alive_connections = alive_connections.clone();
+                            let handled_connections = handled_connections.clone();
                             let notify = notify.clone();
                             let handler = service.hyper_handler(local_addr, remote_addr, http_scheme, conn.fusewire(), alt_svc_h3.clone());
                             let builder = builder.clone();
@@ -287,7 +528,7 @@ impl<A: Acceptor + Send> Server<A> {
                             let force_stop_token = force_stop_token.clone();
                             let graceful_stop_token = graceful_stop_token.clone();
 
-                            tokio::spawn(async move {
+                            let serve = async move {
                                 let conn = conn.serve(handler, builder, graceful_stop_token);
                                 tokio::select! {
                                     _ = conn => {
@@ -296,10 +537,16 @@ impl<A: Acceptor + Send> Server<A> {
                                     }
                                 }
 
+                                drop(permit);
+                                handled_connections.fetch_add(1, Ordering::Relaxed);
                                 if alive_connections.fetch_sub(1, Ordering::Acquire) == 1 {
                                     notify.notify_waiters();
                                 }
-                            });
+                            };
+                            match &runtime {
+                                Some(runtime) => runtime.spawn(serve),
+                                None => tokio::spawn(serve),
+                            };
                         },
                         Err(e) => {
                             tracing::error!(error = ?e, "accept connection failed");
@@ -309,9 +556,15 @@ impl<A: Acceptor + Send> Server<A> {
             }
         }
 
+        // Stop listening, so new connections are rejected while in-flight requests complete.
+        drop(acceptor);
+
+        let notified = notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
         if alive_connections.load(Ordering::Acquire) > 0 {
             tracing::info!("wait for all connections to close.");
-            notify.notified().await;
+            notified.await;
         }
 
         tracing::info!("server stopped");
@@ -319,13 +572,267 @@ impl<A: Acceptor + Send> Server<A> {
     }
 }
 
+/// Runs one [`Server`] per thread, each with its own single threaded runtime and listener.
+///
+/// The listeners should be bound with `SO_REUSEPORT` (see [`TcpListener::reuse_port`](crate::conn::TcpListener::reuse_port)),
+/// so the kernel distributes incoming connections among the threads. Compared to one multi-threaded runtime, tasks
+/// never move between threads, which reduces cross-core contention for high connection rates.
+///
+/// # Example
+///
+/// ```no_run
+/// use salvo_core::prelude::*;
+/// use salvo_core::server::ShardedServer;
+///
+/// fn main() {
+///     ShardedServer::new(|| TcpListener::new("0.0.0.0:5800").reuse_port(true))
+///         .threads(4)
+///         .serve(Router::new())
+///         .unwrap();
+/// }
+/// ```
+pub struct ShardedServer<F> {
+    make_listener: F,
+    threads: usize,
+    drain_timeout: Option<Duration>,
+    stop_token: CancellationToken,
+}
+
+impl<F, L> ShardedServer<F>
+where
+    F: Fn() -> L + Send + Sync + 'static,
+    L: Listener + Send + 'static,
+    L::Acceptor: Send + 'static,
+{
+    /// Create a new `ShardedServer`, `make_listener` is called once in each thread.
+    ///
+    /// It runs one thread per CPU core by default.
+    pub fn new(make_listener: F) -> Self {
+        Self {
+            make_listener,
+            threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            drain_timeout: None,
+            stop_token: CancellationToken::new(),
+        }
+    }
+
+    /// Sets the number of threads.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets how long in-flight requests may take to complete after the server is stopped gracefully.
+    pub fn drain_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.drain_timeout = timeout.into();
+        self
+    }
+
+    /// Get a [`ShardedServerHandle`] to stop all servers.
+    pub fn handle(&self) -> ShardedServerHandle {
+        ShardedServerHandle {
+            stop_token: self.stop_token.clone(),
+        }
+    }
+
+    /// Serve a [`Service`] on all threads, blocks until all servers are stopped.
+    ///
+    /// When a server fails, the others are stopped and the first error is returned.
+    pub fn serve<S>(self, service: S) -> IoResult<()>
+    where
+        S: Into<Service>,
+    {
+        let Self {
+            make_listener,
+            threads,
+            drain_timeout,
+            stop_token,
+        } = self;
+        let service = service.into();
+        let make_listener = Arc::new(make_listener);
+        let (result_tx, result_rx) = std::sync::mpsc::channel();
+        let mut workers = Vec::with_capacity(threads);
+        for idx in 0..threads {
+            let service = service.clone();
+            let make_listener = make_listener.clone();
+            let stop_token = stop_token.clone();
+            let result_tx = result_tx.clone();
+            let worker = std::thread::Builder::new()
+                .name(format!("salvo-server-{idx}"))
+                .spawn(move || {
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| -> IoResult<()> {
+                        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+                        runtime.block_on(async move {
+                            let acceptor = make_listener()
+                                .try_bind()
+                                .await
+                                .map_err(|e| IoError::new(ErrorKind::Other, e.to_string()))?;
+                            Server::new(acceptor)
+                                .drain_timeout(drain_timeout)
+                                .try_serve_with_graceful_shutdown(service, stop_token.cancelled_owned())
+                                .await
+                        })
+                    }))
+                    .unwrap_or_else(|_| Err(IoError::new(ErrorKind::Other, "server thread panicked")));
+                    result_tx.send(result).ok();
+                });
+            match worker {
+                Ok(worker) => workers.push(worker),
+                Err(e) => {
+                    stop_token.cancel();
+                    for worker in workers {
+                        worker.join().ok();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        drop(result_tx);
+
+        // Results come in the order the threads stop, so the first failure stops the others right away.
+        let mut result = Ok(());
+        for worker_result in result_rx {
+            if let Err(e) = worker_result {
+                tracing::error!(error = ?e, "server thread failed");
+                stop_token.cancel();
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        for worker in workers {
+            worker.join().ok();
+        }
+        result
+    }
+}
+
+/// Handle to stop a [`ShardedServer`].
+#[derive(Clone)]
+pub struct ShardedServerHandle {
+    stop_token: CancellationToken,
+}
+
+impl ShardedServerHandle {
+    /// Stop all servers gracefully.
+    pub fn stop_graceful(&self) {
+        self.stop_token.cancel();
+    }
+}
+
+fn stop_graceful(
+    graceful_stop_token: &CancellationToken,
+    force_stop_token: &CancellationToken,
+    timeout: Option<Duration>,
+) {
+    graceful_stop_token.cancel();
+    if let Some(timeout) = timeout {
+        tracing::info!(
+            timeout_in_seconds = timeout.as_secs_f32(),
+            "initiate graceful stop server",
+        );
+
+        let force_stop_token = force_stop_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            force_stop_token.cancel();
+        });
+    } else {
+        tracing::info!("initiate graceful stop server");
+    }
+}
+
+/// Completes when the process receives `SIGINT` (Ctrl+C) or, on unix, `SIGTERM`.
+///
+/// It is intended to be passed to [`Server::serve_with_graceful_shutdown`].
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = ?e, "failed to listen for ctrl_c signal");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to listen for terminate signal");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("ctrl_c signal received"),
+        _ = terminate => tracing::info!("terminate signal received"),
+    }
+}
+
+fn alt_svc_h3(holdings: &[Holding], max_age: Duration) -> Option<HeaderValue> {
+    let port = holdings
+        .iter()
+        .filter(|holding| holding.http_versions.contains(&Version::HTTP_3))
+        .find_map(|holding| holding.local_addr.clone().into_std())?
+        .port();
+    let max_age = max_age.as_secs();
+    Some(
+        format!(r#"h3=":{port}"; ma={max_age},h3-29=":{port}"; ma={max_age}"#)
+            .parse::<HeaderValue>()
+            .expect("Parse alt-svc header failed."),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use serde::Serialize;
 
+    use super::*;
     use crate::prelude::*;
     use crate::test::{ResponseExt, TestClient};
 
+    #[handler]
+    async fn hello() -> &'static str {
+        "Hello World"
+    }
+
+    async fn http_get(addr: std::net::SocketAddr) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_alt_svc_h3() {
+        let tcp = Holding {
+            local_addr: "127.0.0.1:443".parse::<std::net::SocketAddr>().unwrap().into(),
+            http_versions: vec![Version::HTTP_11, Version::HTTP_2],
+            http_scheme: http::uri::Scheme::HTTPS,
+        };
+        let quic = Holding {
+            local_addr: "127.0.0.1:8443".parse::<std::net::SocketAddr>().unwrap().into(),
+            http_versions: vec![Version::HTTP_3],
+            http_scheme: http::uri::Scheme::HTTPS,
+        };
+        assert!(alt_svc_h3(&[tcp.clone()], Duration::from_secs(60)).is_none());
+        assert_eq!(
+            alt_svc_h3(&[tcp, quic], Duration::from_secs(60)).unwrap(),
+            r#"h3=":8443"; ma=60,h3-29=":8443"; ma=60"#
+        );
+    }
+
     #[tokio::test]
     async fn test_server() {
         #[handler]
@@ -392,4 +899,354 @@ mod tests {
             .unwrap();
         assert!(result.contains("<code>404</code>"));
     }
+
+    #[tokio::test]
+    async fn test_serve_with_graceful_shutdown() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+
+        #[handler]
+        async fn slow() -> &'static str {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            "done"
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(
+            Server::new(acceptor)
+                .drain_timeout(Duration::from_secs(5))
+                .serve_with_graceful_shutdown(Router::new().get(slow), async move {
+                    rx.await.ok();
+                }),
+        );
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tx.send(()).unwrap();
+
+        // The in-flight request completes.
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("done"));
+
+        server.await.unwrap();
+        // New connections are rejected after shutdown.
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_handle() {
+        use tokio::io::AsyncWriteExt;
+        use tokio::net::TcpStream;
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let server = Server::new(acceptor);
+        let handle = server.handle();
+        let addr = handle.local_addrs()[0].clone().into_std().unwrap();
+        assert_ne!(addr.port(), 0);
+        let server = tokio::spawn(server.serve(Router::new()));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.active_connections(), 1);
+        assert_eq!(handle.accepted_connections(), 1);
+        assert_eq!(handle.handled_connections(), 0);
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.active_connections(), 0);
+        assert_eq!(handle.handled_connections(), 1);
+
+        handle.shutdown(Duration::from_secs(1));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_h2c_prior_knowledge() {
+        use crate::http::body::ReqBody;
+        use crate::rt::tokio::{TokioExecutor, TokioIo};
+        use tokio::net::TcpStream;
+
+        #[handler]
+        async fn version(req: &mut Request) -> String {
+            format!("{:?}", req.version())
+        }
+
+        for h2c in [true, false] {
+            let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+            let server = Server::new(acceptor).h2c_prior_knowledge(h2c);
+            let handle = server.handle();
+            let addr = handle.local_addrs()[0].clone().into_std().unwrap();
+            tokio::spawn(server.serve(Router::new().get(version)));
+
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let result = async {
+                let (mut sender, conn) =
+                    hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream)).await?;
+                tokio::spawn(conn);
+                let req = hyper::Request::get(format!("http://{addr}/"))
+                    .body(ReqBody::None)
+                    .unwrap();
+                sender.send_request(req).await
+            }
+            .await;
+            if h2c {
+                let mut res = Response::from(result.unwrap());
+                assert_eq!(res.take_string().await.unwrap(), "HTTP/2.0");
+            } else {
+                assert!(result.is_err());
+            }
+            handle.stop_forcible();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_h2c_upgrade() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+
+        #[handler]
+        async fn version(req: &mut Request) -> String {
+            format!("{:?} {}", req.version(), req.uri().path())
+        }
+
+        for upgrade in [true, false] {
+            let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+            let server = Server::new(acceptor).h2c_upgrade(upgrade);
+            let handle = server.handle();
+            let addr = handle.local_addrs()[0].clone().into_std().unwrap();
+            tokio::spawn(server.serve(Router::with_path("version").get(version)));
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(
+                    b"GET /version HTTP/1.1\r\nhost: localhost\r\nconnection: Upgrade, HTTP2-Settings\r\n\
+                    upgrade: h2c\r\nhttp2-settings: AAMAAABkAAQAAP__\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                head.push(stream.read_u8().await.unwrap());
+            }
+            if !upgrade {
+                assert!(head.starts_with(b"HTTP/1.1 200 OK"));
+                continue;
+            }
+            assert!(head.starts_with(b"HTTP/1.1 101 Switching Protocols"));
+
+            // Connection preface with an empty SETTINGS frame, then read the DATA frames of the stream 1.
+            stream.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").await.unwrap();
+            stream.write_all(&[0, 0, 0, 0x4, 0, 0, 0, 0, 0]).await.unwrap();
+            let mut body = Vec::new();
+            loop {
+                let mut header = [0; 9];
+                stream.read_exact(&mut header).await.unwrap();
+                let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+                let mut payload = vec![0; len];
+                stream.read_exact(&mut payload).await.unwrap();
+                let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+                if header[3] == 0 && stream_id == 1 {
+                    body.extend_from_slice(&payload);
+                    if header[4] & 0x1 != 0 {
+                        break;
+                    }
+                }
+            }
+            assert_eq!(body, b"HTTP/2.0 /version");
+            handle.stop_forcible();
+        }
+    }
+
+    #[test]
+    fn test_server_runtime() {
+        #[handler]
+        async fn thread_name() -> String {
+            std::thread::current().name().unwrap_or_default().to_owned()
+        }
+
+        let workers = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("conn-worker")
+            .enable_all()
+            .build()
+            .unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+            let server = Server::new(acceptor).runtime(workers.handle().clone());
+            let handle = server.handle();
+            let addr = handle.local_addrs()[0].clone().into_std().unwrap();
+            tokio::spawn(server.serve(Router::new().get(thread_name)));
+
+            assert!(http_get(addr).await.ends_with("conn-worker"));
+            handle.stop_forcible();
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sharded_server() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+        let server = ShardedServer::new(move || TcpListener::new(addr).reuse_port(true)).threads(2);
+        let handle = server.handle();
+        let server = std::thread::spawn(move || server.serve(Router::new().get(hello)));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            for _ in 0..4 {
+                assert!(http_get(addr).await.ends_with("Hello World"));
+            }
+        });
+        handle.stop_graceful();
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_sharded_server_stops_on_error() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken_addr = taken.local_addr().unwrap();
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let server = ShardedServer::new(move || {
+            if calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                TcpListener::new(std::net::SocketAddr::from(([127, 0, 0, 1], 0)))
+            } else {
+                TcpListener::new(taken_addr)
+            }
+        })
+        .threads(2);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || tx.send(server.serve(Router::new().get(hello))));
+        let result = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("the failed thread should stop the server");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_max_connections() {
+        use tokio::net::TcpStream;
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let server = Server::new(acceptor).max_connections(1);
+        let handle = server.handle();
+        let addr = handle.local_addrs()[0].clone().into_std().unwrap();
+        let server = tokio::spawn(server.serve(Router::new()));
+
+        let first = TcpStream::connect(addr).await.unwrap();
+        let _second = TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.active_connections(), 1);
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.active_connections(), 1);
+
+        handle.stop_forcible();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_read_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+
+        #[handler]
+        async fn slow() -> &'static str {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            "slow"
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let server = Server::new(acceptor)
+            .max_header_size(1024)
+            .read_timeout(Duration::from_millis(100));
+        let handle = server.handle();
+        let addr = handle.local_addrs()[0].clone().into_std().unwrap();
+        let server = tokio::spawn(server.serve(Router::new().get(hello).push(Router::with_path("slow").get(slow))));
+
+        assert!(http_get(addr).await.ends_with("Hello World"));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.ends_with("slow"), "slow handlers should not time out");
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n")
+            .await
+            .unwrap();
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut buf)).await;
+        assert!(read.is_ok(), "stalled connection should be closed");
+
+        handle.stop_forcible();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_take_io() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        #[handler]
+        async fn echo(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+            let io = req.take_io().ok_or_else(StatusError::bad_request)?;
+            res.status_code(StatusCode::SWITCHING_PROTOCOLS);
+            res.add_header(http::header::CONNECTION, "upgrade", true).unwrap();
+            res.add_header(http::header::UPGRADE, "echo", true).unwrap();
+            tokio::spawn(async move {
+                let io = io.await.unwrap();
+                let (mut reader, mut writer) = tokio::io::split(io);
+                tokio::io::copy(&mut reader, &mut writer).await.ok();
+            });
+            Ok(())
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::new().goal(echo)));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: upgrade\r\nupgrade: echo\r\n\r\n")
+            .await
+            .unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        assert!(head.starts_with(b"HTTP/1.1 101 Switching Protocols"));
+
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
 }
@@ -0,0 +1,413 @@
+use std::fmt::Display;
+use std::io::Result as IoResult;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use futures_util::future::select_all;
+use serde::{Deserialize, Serialize};
+
+use super::{Server, MIN_MAX_HEADER_SIZE};
+use crate::conn::tcp::TcpAcceptor;
+use crate::conn::{Accepted, Acceptor, Holding, Listener, TcpListener};
+use crate::fuse::ArcFuseFactory;
+use crate::Error;
+
+/// Address listened when [`ServerConfig::listen`] is empty.
+pub const DEFAULT_LISTEN: &str = "0.0.0.0:5800";
+
+/// Paths of the certificate and the private key used to serve HTTPS.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct TlsConfig {
+    /// Path of the PEM encoded certificate chain.
+    pub cert: PathBuf,
+    /// Path of the PEM encoded private key.
+    pub key: PathBuf,
+}
+impl TlsConfig {
+    /// Create a new `TlsConfig`.
+    #[inline]
+    pub fn new(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        TlsConfig {
+            cert: cert.into(),
+            key: key.into(),
+        }
+    }
+}
+
+/// Settings of a [`Server`], which can be loaded from a config file or environment variables, so deployments can
+/// tune the server without recompiling.
+///
+/// It is deserialized with serde, so any format is supported, like TOML or YAML, and durations are written in
+/// seconds:
+///
+/// ```toml
+/// listen = ["0.0.0.0:443", "[::]:443"]
+/// max_connections = 10000
+/// header_read_timeout = 10
+/// drain_timeout = 30
+/// compression = true
+/// log_level = "info"
+///
+/// [tls]
+/// cert = "/etc/app/cert.pem"
+/// key = "/etc/app/key.pem"
+/// ```
+///
+/// The server does not apply [`compression`](ServerConfig::compression) and [`log_level`](ServerConfig::log_level),
+/// they are read by the application to add the compression hoop to its service and to set up its tracing
+/// subscriber.
+///
+/// # Example
+///
+/// ```no_run
+/// use salvo_core::prelude::*;
+/// use salvo_core::server::ServerConfig;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let config: ServerConfig = serde_json::from_str(r#"{"listen": ["0.0.0.0:5800"], "drain_timeout": 30}"#)
+///         .unwrap()
+///         .merge_env("APP_")
+///         .unwrap();
+///     let server = Server::from_config(&config).await.unwrap();
+///     server.serve(Router::new()).await;
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct ServerConfig {
+    /// Addresses listened, defaults to [`DEFAULT_LISTEN`] when empty.
+    pub listen: Vec<String>,
+    /// Certificate and key used to serve HTTPS on all addresses, requires the `rustls` feature.
+    pub tls: Option<TlsConfig>,
+    /// Maximum number of connections served concurrently.
+    pub max_connections: Option<usize>,
+    /// Maximum size of request headers, at least [`MIN_MAX_HEADER_SIZE`].
+    pub max_header_size: Option<usize>,
+    /// Timeout for receiving the headers of HTTP/1 requests.
+    #[serde(with = "seconds")]
+    pub header_read_timeout: Option<Duration>,
+    /// How long in-flight requests may take to complete on graceful shutdown.
+    #[serde(with = "seconds")]
+    pub drain_timeout: Option<Duration>,
+    /// Whether responses should be compressed.
+    pub compression: bool,
+    /// Level of the logs, like `info` or `salvo=debug`.
+    pub log_level: Option<String>,
+}
+
+impl ServerConfig {
+    /// Create a new `ServerConfig` with default settings.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new `ServerConfig` from environment variables, see [`ServerConfig::merge_env`].
+    #[inline]
+    pub fn from_env(prefix: &str) -> crate::Result<Self> {
+        Self::default().merge_env(prefix)
+    }
+
+    /// Overrides settings with the environment variables set, whose names start with `prefix`:
+    ///
+    /// - `{prefix}LISTEN`: comma separated addresses.
+    /// - `{prefix}TLS_CERT` and `{prefix}TLS_KEY`: paths of the certificate and the private key.
+    /// - `{prefix}MAX_CONNECTIONS` and `{prefix}MAX_HEADER_SIZE`.
+    /// - `{prefix}HEADER_READ_TIMEOUT` and `{prefix}DRAIN_TIMEOUT`: durations in seconds.
+    /// - `{prefix}COMPRESSION`: `true` or `false`.
+    /// - `{prefix}LOG_LEVEL`.
+    pub fn merge_env(mut self, prefix: &str) -> crate::Result<Self> {
+        let var = |name: &str| {
+            let key = format!("{prefix}{name}");
+            std::env::var(&key).ok().map(|value| (key, value))
+        };
+        if let Some((_, listen)) = var("LISTEN") {
+            self.listen = listen
+                .split(',')
+                .map(str::trim)
+                .filter(|addr| !addr.is_empty())
+                .map(ToOwned::to_owned)
+                .collect();
+        }
+        match (var("TLS_CERT"), var("TLS_KEY")) {
+            (Some((_, cert)), Some((_, key))) => self.tls = Some(TlsConfig::new(cert, key)),
+            (None, None) => {}
+            _ => {
+                return Err(Error::other(format!(
+                    "both {prefix}TLS_CERT and {prefix}TLS_KEY should be set"
+                )))
+            }
+        }
+        if let Some((key, value)) = var("MAX_CONNECTIONS") {
+            self.max_connections = Some(parse_value(&key, &value)?);
+        }
+        if let Some((key, value)) = var("MAX_HEADER_SIZE") {
+            self.max_header_size = Some(check_max_header_size(&key, parse_value(&key, &value)?)?);
+        }
+        if let Some((key, value)) = var("HEADER_READ_TIMEOUT") {
+            self.header_read_timeout = Some(parse_seconds(&key, &value)?);
+        }
+        if let Some((key, value)) = var("DRAIN_TIMEOUT") {
+            self.drain_timeout = Some(parse_seconds(&key, &value)?);
+        }
+        if let Some((key, value)) = var("COMPRESSION") {
+            self.compression = parse_value(&key, &value)?;
+        }
+        if let Some((_, level)) = var("LOG_LEVEL") {
+            self.log_level = Some(level);
+        }
+        Ok(self)
+    }
+
+    /// Binds the listened addresses and returns the acceptor.
+    pub async fn bind(&self) -> crate::Result<ConfigAcceptor> {
+        let listen = if self.listen.is_empty() {
+            vec![DEFAULT_LISTEN.to_owned()]
+        } else {
+            self.listen.clone()
+        };
+        #[cfg(feature = "rustls")]
+        let rustls_config = match &self.tls {
+            Some(tls) => Some(crate::conn::rustls::RustlsConfig::new(
+                crate::conn::rustls::Keycert::new()
+                    .cert_from_path_async(&tls.cert)
+                    .await?
+                    .key_from_path_async(&tls.key)
+                    .await?,
+            )),
+            None => None,
+        };
+        #[cfg(not(feature = "rustls"))]
+        if self.tls.is_some() {
+            return Err(Error::other("serving tls requires the `rustls` feature"));
+        }
+
+        let mut acceptors = Vec::with_capacity(listen.len());
+        let mut holdings = vec![];
+        for addr in listen {
+            let listener = TcpListener::new(addr);
+            #[cfg(feature = "rustls")]
+            if let Some(rustls_config) = &rustls_config {
+                let acceptor = listener.rustls(rustls_config.clone()).try_bind().await?;
+                holdings.extend_from_slice(acceptor.holdings());
+                acceptors.push(InnerAcceptor::Rustls(acceptor));
+                continue;
+            }
+            let acceptor = listener.try_bind().await?;
+            holdings.extend_from_slice(acceptor.holdings());
+            acceptors.push(InnerAcceptor::Tcp(acceptor));
+        }
+        Ok(ConfigAcceptor { acceptors, holdings })
+    }
+}
+
+fn parse_value<T>(name: &str, value: &str) -> crate::Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e| Error::other(format!("invalid value of {name}: {e}")))
+}
+
+fn check_max_header_size(name: &str, size: usize) -> crate::Result<usize> {
+    if size < MIN_MAX_HEADER_SIZE {
+        return Err(Error::other(format!(
+            "invalid value of {name}: {size} is smaller than {MIN_MAX_HEADER_SIZE}"
+        )));
+    }
+    Ok(size)
+}
+
+fn parse_seconds(name: &str, value: &str) -> crate::Result<Duration> {
+    Duration::try_from_secs_f64(parse_value(name, value)?)
+        .map_err(|e| Error::other(format!("invalid value of {name}: {e}")))
+}
+
+/// Serializes durations as seconds.
+mod seconds {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|seconds| Duration::try_from_secs_f64(seconds).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(feature = "rustls")]
+type RustlsAcceptor = crate::conn::rustls::RustlsAcceptor<
+    futures_util::stream::BoxStream<'static, crate::conn::rustls::RustlsConfig>,
+    crate::conn::rustls::RustlsConfig,
+    TcpAcceptor,
+    std::io::Error,
+>;
+
+#[cfg(feature = "rustls")]
+type ConfigConn = crate::conn::JoinedStream<<TcpAcceptor as Acceptor>::Conn, <RustlsAcceptor as Acceptor>::Conn>;
+#[cfg(not(feature = "rustls"))]
+type ConfigConn = <TcpAcceptor as Acceptor>::Conn;
+
+enum InnerAcceptor {
+    Tcp(TcpAcceptor),
+    #[cfg(feature = "rustls")]
+    Rustls(RustlsAcceptor),
+}
+impl InnerAcceptor {
+    async fn accept(&mut self, fuse_factory: ArcFuseFactory) -> IoResult<Accepted<ConfigConn>> {
+        match self {
+            #[cfg(not(feature = "rustls"))]
+            Self::Tcp(acceptor) => acceptor.accept(fuse_factory).await,
+            #[cfg(feature = "rustls")]
+            Self::Tcp(acceptor) => Ok(acceptor
+                .accept(fuse_factory)
+                .await?
+                .map_conn(crate::conn::JoinedStream::A)),
+            #[cfg(feature = "rustls")]
+            Self::Rustls(acceptor) => Ok(acceptor
+                .accept(fuse_factory)
+                .await?
+                .map_conn(crate::conn::JoinedStream::B)),
+        }
+    }
+}
+
+/// Acceptor of the addresses listened by a [`ServerConfig`].
+pub struct ConfigAcceptor {
+    acceptors: Vec<InnerAcceptor>,
+    holdings: Vec<Holding>,
+}
+impl Acceptor for ConfigAcceptor {
+    type Conn = ConfigConn;
+
+    #[inline]
+    fn holdings(&self) -> &[Holding] {
+        &self.holdings
+    }
+
+    async fn accept(&mut self, fuse_factory: ArcFuseFactory) -> IoResult<Accepted<Self::Conn>> {
+        if let [acceptor] = &mut self.acceptors[..] {
+            return acceptor.accept(fuse_factory).await;
+        }
+        let accepts = self
+            .acceptors
+            .iter_mut()
+            .map(|acceptor| Box::pin(acceptor.accept(fuse_factory.clone())));
+        select_all(accepts).await.0
+    }
+}
+
+impl Server<ConfigAcceptor> {
+    /// Create a new `Server` listening the addresses of a [`ServerConfig`], with its limits and timeouts.
+    ///
+    /// Returns an error if [`ServerConfig::max_header_size`] is smaller than [`MIN_MAX_HEADER_SIZE`].
+    pub async fn from_config(config: &ServerConfig) -> crate::Result<Self> {
+        if let Some(size) = config.max_header_size {
+            check_max_header_size("max_header_size", size)?;
+        }
+        let mut server = Server::new(config.bind().await?).drain_timeout(config.drain_timeout);
+        if let Some(max) = config.max_connections {
+            server = server.max_connections(max);
+        }
+        #[cfg(any(feature = "http1", feature = "http2"))]
+        if let Some(size) = config.max_header_size {
+            server = server.max_header_size(size);
+        }
+        #[cfg(feature = "http1")]
+        if let Some(timeout) = config.header_read_timeout {
+            server = server.header_read_timeout(timeout);
+        }
+        Ok(server)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_config_from_json_and_env() {
+        let config: ServerConfig = serde_json::from_str(
+            r#"{"listen": ["127.0.0.1:5800"], "max_connections": 100, "drain_timeout": 1.5, "log_level": "info"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.listen, ["127.0.0.1:5800"]);
+        assert_eq!(config.max_connections, Some(100));
+        assert_eq!(config.drain_timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(config.header_read_timeout, None);
+        assert!(!config.compression);
+
+        std::env::set_var("TEST_SERVER_CONFIG_LISTEN", "127.0.0.1:5801, 127.0.0.1:5802");
+        std::env::set_var("TEST_SERVER_CONFIG_HEADER_READ_TIMEOUT", "10");
+        std::env::set_var("TEST_SERVER_CONFIG_COMPRESSION", "true");
+        let config = config.merge_env("TEST_SERVER_CONFIG_").unwrap();
+        assert_eq!(config.listen, ["127.0.0.1:5801", "127.0.0.1:5802"]);
+        assert_eq!(config.header_read_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(config.max_connections, Some(100));
+        assert!(config.compression);
+
+        std::env::set_var("TEST_SERVER_CONFIG_INVALID_MAX_CONNECTIONS", "many");
+        assert!(ServerConfig::from_env("TEST_SERVER_CONFIG_INVALID_").is_err());
+
+        std::env::set_var("TEST_SERVER_CONFIG_SMALL_MAX_HEADER_SIZE", "1024");
+        assert!(ServerConfig::from_env("TEST_SERVER_CONFIG_SMALL_").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_from_config() {
+        #[handler]
+        async fn hello() -> &'static str {
+            "hello"
+        }
+        let config = ServerConfig {
+            listen: vec!["127.0.0.1:0".into()],
+            max_header_size: Some(100),
+            ..Default::default()
+        };
+        assert!(Server::from_config(&config).await.is_err());
+
+        let config = ServerConfig {
+            listen: vec!["127.0.0.1:0".into(), "127.0.0.1:0".into()],
+            ..Default::default()
+        };
+        let server = Server::from_config(&config).await.unwrap();
+        let addrs: Vec<_> = server
+            .holdings()
+            .iter()
+            .map(|holding| holding.local_addr.clone().into_std().unwrap())
+            .collect();
+        assert_eq!(addrs.len(), 2);
+        tokio::spawn(server.serve(Router::new().get(hello)));
+
+        for addr in addrs {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"));
+            assert!(response.ends_with("hello"));
+        }
+    }
+}
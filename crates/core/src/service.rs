@@ -1,17 +1,21 @@
+use std::convert::Infallible;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Instant;
 
+use futures_util::future::BoxFuture;
 use headers::HeaderValue;
 use http::header::{ALT_SVC, CONTENT_TYPE};
 use http::uri::Scheme;
+use http::Extensions;
 use hyper::service::Service as HyperService;
 use hyper::{Method, Request as HyperRequest, Response as HyperResponse};
 
 use crate::catcher::{write_error_default, Catcher};
 use crate::conn::SocketAddr;
-use crate::fuse::ArcFusewire;
-use crate::handler::{Handler, WhenHoop};
+use crate::fuse::{ArcFusewire, SteadyFusewire};
+use crate::handler::{FnHoop, Handler, Next, WhenHoop};
 use crate::http::body::{ReqBody, ResBody};
 use crate::http::{Mime, Request, Response, StatusCode};
 use crate::routing::{FlowCtrl, PathState, Router};
@@ -19,6 +23,7 @@ use crate::Depot;
 
 /// Service http request.
 #[non_exhaustive]
+#[derive(Clone)]
 pub struct Service {
     /// The router of this service.
     pub router: Arc<Router>,
@@ -28,6 +33,8 @@ pub struct Service {
     pub hoops: Vec<Arc<dyn Handler>>,
     /// The allowed media types of this service.
     pub allowed_media_types: Arc<Vec<Mime>>,
+    /// The application states of this service, copied into the extensions of each request.
+    pub states: Arc<Extensions>,
 }
 
 impl Service {
@@ -42,6 +49,7 @@ impl Service {
             catcher: None,
             hoops: vec![],
             allowed_media_types: Arc::new(vec![]),
+            states: Arc::new(Extensions::new()),
         }
     }
 
@@ -99,6 +107,20 @@ impl Service {
         self
     }
 
+    /// Add a function as middleware, it will run the function when request received.
+    ///
+    /// See [`Router::hoop_fn`] for more details.
+    #[inline]
+    pub fn hoop_fn<F>(self, f: F) -> Self
+    where
+        F: for<'a> Fn(&'a mut Request, &'a mut Depot, &'a mut Response, Next<'a>) -> BoxFuture<'a, ()>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.hoop(FnHoop { f })
+    }
+
     /// Sets allowed media types list and returns `Self` for write code chained.
     ///
     /// # Example
@@ -120,6 +142,38 @@ impl Service {
         self
     }
 
+    /// Add an application state, like a database pool or a config, shared by all requests.
+    ///
+    /// The state is cloned into the extensions of each request, and can be extracted in handlers with
+    /// [`State`](crate::extract::State) or read with `req.extensions().get::<T>()`. It should be cheap to clone,
+    /// like a pool or an `Arc`. Only one state of each type is kept, adding a state of the same type replaces it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use salvo_core::prelude::*;
+    ///
+    /// #[derive(Default)]
+    /// struct Db;
+    ///
+    /// #[handler]
+    /// async fn list_users(db: State<Arc<Db>>) -> &'static str {
+    ///     "[]"
+    /// }
+    ///
+    /// let service = Service::new(Router::with_path("users").get(list_users)).with_state(Arc::new(Db));
+    /// ```
+    #[inline]
+    pub fn with_state<T>(mut self, state: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.states).insert(state);
+        self
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn hyper_handler(
@@ -138,10 +192,36 @@ impl Service {
             catcher: self.catcher.clone(),
             hoops: self.hoops.clone(),
             allowed_media_types: self.allowed_media_types.clone(),
+            states: self.states.clone(),
             fusewire,
             alt_svc_h3,
+            extensions: Extensions::new(),
         }
     }
+    /// Handles a hyper request with unknown local and remote addresses, used when the service is driven by other
+    /// servers.
+    pub(crate) fn call_hyper<B>(&self, req: HyperRequest<B>) -> BoxFuture<'static, HyperResponse<ResBody>>
+    where
+        B: Into<ReqBody>,
+    {
+        let scheme = req.uri().scheme().cloned().unwrap_or(Scheme::HTTP);
+        let handler = self.hyper_handler(
+            SocketAddr::Unknown,
+            SocketAddr::Unknown,
+            scheme.clone(),
+            Arc::new(SteadyFusewire),
+            None,
+        );
+        let request = Request::from_hyper(req, scheme);
+        let cancel_guard = request.cancel_token.clone().drop_guard();
+        let response = handler.handle(request);
+        Box::pin(async move {
+            let response = response.await;
+            cancel_guard.disarm();
+            response.into_hyper()
+        })
+    }
+
     /// Handle new request, this function only used for test.
     #[cfg(feature = "test")]
     #[inline]
@@ -179,16 +259,36 @@ pub struct HyperHandler {
     pub(crate) catcher: Option<Arc<Catcher>>,
     pub(crate) hoops: Vec<Arc<dyn Handler>>,
     pub(crate) allowed_media_types: Arc<Vec<Mime>>,
+    pub(crate) states: Arc<Extensions>,
     pub(crate) fusewire: ArcFusewire,
     pub(crate) alt_svc_h3: Option<HeaderValue>,
+    /// Connection level extensions, such as peer certificates, copied into each request.
+    pub(crate) extensions: Extensions,
 }
 impl HyperHandler {
+    /// Replace the router, catcher, hoops, allowed media types and states with the ones of `service`.
+    pub(crate) fn with_service(self, service: &Service) -> Self {
+        Self {
+            router: service.router.clone(),
+            catcher: service.catcher.clone(),
+            hoops: service.hoops.clone(),
+            allowed_media_types: service.allowed_media_types.clone(),
+            states: service.states.clone(),
+            ..self
+        }
+    }
     /// Handle [`Request`] and returns [`Response`].
     pub fn handle(&self, mut req: Request) -> impl Future<Output = Response> {
         let catcher = self.catcher.clone();
         let allowed_media_types = self.allowed_media_types.clone();
         req.local_addr = self.local_addr.clone();
         req.remote_addr = self.remote_addr.clone();
+        if !self.states.is_empty() {
+            req.extensions.extend((*self.states).clone());
+        }
+        if !self.extensions.is_empty() {
+            req.extensions.extend(self.extensions.clone());
+        }
         #[cfg(not(feature = "cookie"))]
         let mut res = Response::new();
         #[cfg(feature = "cookie")]
@@ -204,19 +304,39 @@ impl HyperHandler {
 
         let hoops = self.hoops.clone();
         async move {
+            let routing_started = Instant::now();
             if let Some(dm) = router.detect(&mut req, &mut path_state) {
+                req.matched_path = Some(path_state.matched_path());
                 req.params = path_state.params;
                 let mut ctrl = FlowCtrl::new([&hoops[..], &dm.hoops[..], &[dm.goal]].concat());
+                ctrl.routing_duration = Some(routing_started.elapsed());
+                ctrl.router = Some(router.clone());
+                ctrl.route_start = hoops.len();
                 ctrl.call_next(&mut req, &mut depot, &mut res).await;
+                if let Some(e) = ctrl.take_aborted() {
+                    res.render(e);
+                }
                 if res.status_code.is_none() {
                     res.status_code = Some(StatusCode::OK);
                 }
             } else if !hoops.is_empty() {
                 req.params = path_state.params;
+                let route_start = hoops.len();
                 let mut ctrl = FlowCtrl::new(hoops);
+                ctrl.routing_duration = Some(routing_started.elapsed());
+                ctrl.router = Some(router.clone());
+                ctrl.route_start = route_start;
                 ctrl.call_next(&mut req, &mut depot, &mut res).await;
+                if let Some(e) = ctrl.take_aborted() {
+                    res.render(e);
+                }
                 if res.status_code.is_none() {
-                    res.status_code = Some(StatusCode::NOT_FOUND);
+                    // The request is matched if a hoop forwarded it.
+                    if req.matched_path.is_some() {
+                        res.status_code = Some(StatusCode::OK);
+                    } else {
+                        res.status_code = Some(StatusCode::NOT_FOUND);
+                    }
                 }
             } else {
                 res.status_code(StatusCode::NOT_FOUND);
@@ -297,6 +417,51 @@ impl HyperHandler {
     }
 }
 
+/// Drives the service by a custom hyper server, like `hyper::server::conn::http1::Builder::serve_connection`.
+///
+/// The local and remote addresses of requests are unknown, and the scheme is `http` unless the uri of the request
+/// has one.
+///
+/// # Example
+///
+/// ```no_run
+/// use hyper::server::conn::http1;
+/// use hyper_util::rt::TokioIo;
+/// use salvo_core::prelude::*;
+///
+/// #[handler]
+/// async fn hello() -> &'static str {
+///     "Hello World"
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let service = Service::new(Router::new().get(hello));
+///     let listener = tokio::net::TcpListener::bind("127.0.0.1:5800").await.unwrap();
+///     loop {
+///         let (stream, _) = listener.accept().await.unwrap();
+///         let service = service.clone();
+///         tokio::spawn(async move {
+///             let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+///         });
+///     }
+/// }
+/// ```
+impl<B> HyperService<HyperRequest<B>> for Service
+where
+    B: Into<ReqBody>,
+{
+    type Response = HyperResponse<ResBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    #[inline]
+    fn call(&self, req: HyperRequest<B>) -> Self::Future {
+        let response = self.call_hyper(req);
+        Box::pin(async move { Ok(response.await) })
+    }
+}
+
 impl<B> HyperService<HyperRequest<B>> for HyperHandler
 where
     B: Into<ReqBody>,
@@ -331,8 +496,14 @@ where
         }
         let mut request = Request::from_hyper(req, scheme);
         request.body.fill_fusewire(self.fusewire.clone());
+        // Hyper drops the future when the client disconnects, which cancels the token of the request.
+        let cancel_guard = request.cancel_token.clone().drop_guard();
         let response = self.handle(request);
-        Box::pin(async move { Ok(response.await.into_hyper()) })
+        Box::pin(async move {
+            let response = response.await;
+            cancel_guard.disarm();
+            Ok(response.into_hyper())
+        })
     }
 }
 
@@ -398,4 +569,99 @@ mod tests {
         let content = access(&service, "3").await;
         assert_eq!(content, "before1before2before3");
     }
+
+    #[tokio::test]
+    async fn test_hoop_fn() {
+        #[handler]
+        async fn hello() -> &'static str {
+            "hello"
+        }
+        let router = Router::new()
+            .hoop_fn(|req, depot, res, next| {
+                Box::pin(async move {
+                    if req.query::<String>("deny").is_some() {
+                        res.status_code(StatusCode::FORBIDDEN);
+                        return;
+                    }
+                    next.run(req, depot, res).await;
+                    res.headers_mut().insert("x-router", "1".parse().unwrap());
+                })
+            })
+            .goal(hello);
+        let service = Service::new(router).hoop_fn(|req, depot, res, next| {
+            Box::pin(async move {
+                depot.insert("service", true);
+                next.run(req, depot, res).await;
+            })
+        });
+
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(&service).await;
+        assert_eq!(res.headers()["x-router"], "1");
+        assert_eq!(res.take_string().await.unwrap(), "hello");
+
+        let mut res = TestClient::get("http://127.0.0.1:5801?deny=1").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+        assert!(res.headers().get("x-router").is_none());
+        assert!(!res.take_string().await.unwrap().contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_hyper_service() {
+        use http_body_util::BodyExt;
+        use hyper::service::Service as _;
+
+        use crate::http::ReqBody;
+
+        #[handler]
+        async fn hello(req: &mut Request) -> String {
+            format!("Hello {}", req.query::<String>("name").unwrap_or_default())
+        }
+        let service = Service::new(Router::with_path("hello").get(hello));
+
+        let req = hyper::Request::get("http://127.0.0.1:5801/hello?name=hyper")
+            .body(ReqBody::None)
+            .unwrap();
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "Hello hyper");
+
+        let req = hyper::Request::get("/missing").body(ReqBody::None).unwrap();
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_on_disconnect() {
+        use hyper::service::Service as _;
+        use tokio::sync::mpsc;
+        use tokio_util::sync::CancellationToken;
+
+        use crate::http::ReqBody;
+
+        struct Slow {
+            tx: mpsc::UnboundedSender<CancellationToken>,
+        }
+        #[handler]
+        impl Slow {
+            async fn handle(&self, req: &mut Request) -> &'static str {
+                self.tx.send(req.cancellation_token()).unwrap();
+                req.cancelled().await;
+                "cancelled"
+            }
+        }
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let service = Service::new(Router::new().get(Slow { tx }));
+
+        let req = hyper::Request::get("http://127.0.0.1:5801/")
+            .body(ReqBody::None)
+            .unwrap();
+        let mut response = service.call(req);
+        let pending = tokio::time::timeout(std::time::Duration::from_millis(50), &mut response).await;
+        assert!(pending.is_err());
+        let token = rx.recv().await.unwrap();
+        assert!(!token.is_cancelled());
+        drop(response);
+        assert!(token.is_cancelled());
+    }
 }
@@ -1,4 +1,29 @@
 //! Test utils for unit tests.
+//!
+//! Requests built with [`TestClient`] are dispatched in-memory to a [`Router`](crate::Router),
+//! [`Service`](crate::Service) or [`Handler`](crate::Handler), without binding any socket:
+//!
+//! ```
+//! use salvo_core::prelude::*;
+//! use salvo_core::test::{ResponseExt, TestClient};
+//!
+//! #[handler]
+//! async fn hello(req: &mut Request) -> String {
+//!     format!("Hello, {}!", req.query::<&str>("name").unwrap_or("world"))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = Service::new(Router::new().get(hello));
+//! let mut res = TestClient::get("http://127.0.0.1:5800/")
+//!     .query("name", "salvo")
+//!     .add_header("x-request-id", "1", true)
+//!     .send(&service)
+//!     .await;
+//! assert_eq!(res.status_code, Some(StatusCode::OK));
+//! assert_eq!(res.take_string().await.unwrap(), "Hello, salvo!");
+//! # }
+//! ```
 
 mod client;
 mod request;
@@ -6,3 +31,9 @@ mod response;
 pub use client::TestClient;
 pub use request::{RequestBuilder, SendTarget};
 pub use response::ResponseExt;
+
+cfg_feature! {
+    #![feature = "cookie"]
+    mod session;
+    pub use session::TestSession;
+}
@@ -123,6 +123,17 @@ impl RequestBuilder {
         self.add_header(header::AUTHORIZATION, format!("Bearer {}", token.into()), true)
     }
 
+    /// Adds a cookie to the `Cookie` header of this request.
+    #[cfg(feature = "cookie")]
+    pub fn cookie(self, cookie: cookie::Cookie<'_>) -> Self {
+        let pair = cookie.encoded().stripped().to_string();
+        let value = match self.headers.get(header::COOKIE).and_then(|v| v.to_str().ok()) {
+            Some(cookies) if !cookies.is_empty() => format!("{cookies}; {pair}"),
+            _ => pair,
+        };
+        self.add_header(header::COOKIE, value, true)
+    }
+
     /// Sets the body of this request.
     pub fn body(mut self, body: impl Into<ReqBody>) -> Self {
         self.body = body.into();
@@ -242,7 +253,7 @@ impl RequestBuilder {
                 .filter_map(|c| c.encoded().to_string().parse().ok())
                 .collect::<Vec<_>>();
             for hv in values {
-                response.headers_mut().insert(header::SET_COOKIE, hv);
+                response.headers_mut().append(header::SET_COOKIE, hv);
             }
             response
         }
@@ -299,3 +310,37 @@ where
         SendTarget::call(handler, req).await
     }
 }
+
+#[cfg(all(test, feature = "cookie"))]
+mod tests {
+    use crate::http::cookie::Cookie;
+    use crate::prelude::*;
+    use crate::test::{ResponseExt, TestClient};
+
+    #[handler]
+    async fn login(req: &mut Request, res: &mut Response) {
+        let user = req.cookie("user").map(|c| c.value().to_owned()).unwrap_or_default();
+        let lang = req.cookie("lang").map(|c| c.value().to_owned()).unwrap_or_default();
+        res.add_cookie(Cookie::new("session", "1"));
+        res.add_cookie(Cookie::new("theme", "dark"));
+        res.render(format!("{user} {lang}"));
+    }
+
+    #[tokio::test]
+    async fn test_send_cookies() {
+        let mut res = TestClient::get("http://127.0.0.1:5801/")
+            .cookie(Cookie::new("user", "jane doe"))
+            .cookie(Cookie::new("lang", "en"))
+            .send(Router::new().get(login))
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "jane doe en");
+        let mut cookies = res
+            .headers()
+            .get_all("set-cookie")
+            .iter()
+            .map(|v| v.to_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        cookies.sort();
+        assert_eq!(cookies, ["session=1", "theme=dark"]);
+    }
+}
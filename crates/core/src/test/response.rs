@@ -8,6 +8,8 @@ use flate2::write::{GzDecoder, ZlibDecoder};
 use http_body_util::BodyExt;
 use mime::Mime;
 use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
 use tokio::io::{Error as IoError, ErrorKind};
 use zstd::stream::write::Decoder as ZstdDecoder;
 
@@ -58,6 +60,14 @@ pub trait ResponseExt {
     ) -> impl Future<Output = crate::Result<String>>;
     /// Take all body bytes. If body is none, it will creates and returns a new [`Bytes`].
     fn take_bytes(&mut self, content_type: Option<&Mime>) -> impl Future<Output = crate::Result<Bytes>> + Send;
+    /// Take body as data chunks, one for each frame of a streaming body, like events of server-sent events.
+    fn take_chunks(&mut self) -> impl Future<Output = crate::Result<Vec<Bytes>>> + Send;
+    /// Take body as JSON and asserts it is equal to `expected`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if body is not a valid JSON or is not equal to `expected`, with both values pretty printed.
+    fn assert_json_eq(&mut self, expected: impl Serialize + Send) -> impl Future<Output = ()> + Send;
 }
 
 impl ResponseExt for Response {
@@ -80,7 +90,15 @@ impl ResponseExt for Response {
             .await
     }
     async fn take_json<T: DeserializeOwned>(&mut self) -> crate::Result<T> {
-        let full = self.take_bytes(Some(&mime::APPLICATION_JSON)).await?;
+        let encoding = self
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_owned());
+        let mut full = self.take_bytes(Some(&mime::APPLICATION_JSON)).await?;
+        if let Some(algo) = encoding {
+            full = decompress(full, &algo)?;
+        }
         serde_json::from_slice(&full).map_err(Error::SerdeJson)
     }
     async fn take_string_with_charset(
@@ -92,35 +110,7 @@ impl ResponseExt for Response {
         let charset = Encoding::for_label(charset.as_bytes()).unwrap_or(UTF_8);
         let mut full = self.take_bytes(content_type).await?;
         if let Some(algo) = compress {
-            match algo {
-                "gzip" => {
-                    let mut decoder = GzDecoder::new(Writer::new());
-                    decoder.write_all(full.as_ref())?;
-                    decoder.flush()?;
-                    full = decoder.get_mut().take();
-                }
-                "deflate" => {
-                    let mut decoder = ZlibDecoder::new(Writer::new());
-                    decoder.write_all(full.as_ref())?;
-                    decoder.flush()?;
-                    full = decoder.get_mut().take();
-                }
-                "br" => {
-                    let mut decoder = brotli::DecompressorWriter::new(Writer::new(), 8_096);
-                    decoder.write_all(full.as_ref())?;
-                    decoder.flush()?;
-                    full = decoder.get_mut().take();
-                }
-                "zstd" => {
-                    let mut decoder = ZstdDecoder::new(Writer::new()).expect("failed to create zstd decoder");
-                    decoder.write_all(full.as_ref())?;
-                    decoder.flush()?;
-                    full = decoder.get_mut().take();
-                }
-                _ => {
-                    tracing::error!(algo, "unknown compress format");
-                }
-            }
+            full = decompress(full, algo)?;
         }
         let (text, _, _) = charset.decode(&full);
         if let Cow::Owned(s) = text {
@@ -144,4 +134,103 @@ impl ResponseExt for Response {
         };
         Ok(bytes)
     }
+    async fn take_chunks(&mut self) -> crate::Result<Vec<Bytes>> {
+        let mut body = self.take_body();
+        let mut chunks = Vec::new();
+        while let Some(frame) = body.frame().await {
+            if let Ok(data) = frame?.into_data() {
+                chunks.push(data);
+            }
+        }
+        Ok(chunks)
+    }
+    async fn assert_json_eq(&mut self, expected: impl Serialize + Send) {
+        let expected = serde_json::to_value(expected).expect("expected value should be serializable to json");
+        let actual = self
+            .take_json::<Value>()
+            .await
+            .unwrap_or_else(|e| panic!("response body is not a valid json: {e}"));
+        if actual != expected {
+            panic!(
+                "json of response body is not equal to expected\n  actual: {}\nexpected: {}",
+                serde_json::to_string_pretty(&actual).unwrap_or_default(),
+                serde_json::to_string_pretty(&expected).unwrap_or_default()
+            );
+        }
+    }
+}
+
+fn decompress(full: Bytes, algo: &str) -> crate::Result<Bytes> {
+    let full = match algo {
+        "gzip" => {
+            let mut decoder = GzDecoder::new(Writer::new());
+            decoder.write_all(full.as_ref())?;
+            decoder.flush()?;
+            decoder.get_mut().take()
+        }
+        "deflate" => {
+            let mut decoder = ZlibDecoder::new(Writer::new());
+            decoder.write_all(full.as_ref())?;
+            decoder.flush()?;
+            decoder.get_mut().take()
+        }
+        "br" => {
+            let mut decoder = brotli::DecompressorWriter::new(Writer::new(), 8_096);
+            decoder.write_all(full.as_ref())?;
+            decoder.flush()?;
+            decoder.get_mut().take()
+        }
+        "zstd" => {
+            let mut decoder = ZstdDecoder::new(Writer::new()).expect("failed to create zstd decoder");
+            decoder.write_all(full.as_ref())?;
+            decoder.flush()?;
+            decoder.get_mut().take()
+        }
+        _ => {
+            tracing::error!(algo, "unknown compress format");
+            full
+        }
+    };
+    Ok(full)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream;
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+    use crate::test::TestClient;
+
+    #[handler]
+    async fn events(res: &mut Response) {
+        let chunks = ["data: 1\n\n", "data: 2\n\n"].map(Ok::<_, std::io::Error>);
+        res.stream(stream::iter(chunks));
+    }
+
+    #[handler]
+    async fn user(res: &mut Response) {
+        res.render(crate::writing::Json(json!({"id": 1, "name": "alice"})));
+    }
+
+    #[tokio::test]
+    async fn test_take_chunks() {
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(events).await;
+        let chunks = res.take_chunks().await.unwrap();
+        assert_eq!(chunks, ["data: 1\n\n", "data: 2\n\n"]);
+    }
+
+    #[tokio::test]
+    async fn test_assert_json_eq() {
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(user).await;
+        res.assert_json_eq(json!({"name": "alice", "id": 1})).await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "json of response body is not equal to expected")]
+    async fn test_assert_json_eq_mismatch() {
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(user).await;
+        res.assert_json_eq(json!({"id": 2})).await;
+    }
 }
@@ -0,0 +1,322 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use cookie::time::OffsetDateTime;
+use cookie::{Cookie, CookieJar};
+use http::header::{self, HeaderValue};
+use http::Method;
+use url::Url;
+
+use super::request::{RequestBuilder, SendTarget};
+use crate::http::body::ReqBody;
+use crate::http::StatusCode;
+use crate::routing::Router;
+use crate::{Request, Response, Service};
+
+/// `TestSession` is a [`SendTarget`] which keeps cookies across requests, like a browser does.
+///
+/// Cookies set by responses are stored in the session and sent with the following requests, so login
+/// flows can be tested end-to-end. Redirects can also be followed with
+/// [`follow_redirects`](TestSession::follow_redirects).
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::http::cookie::Cookie;
+/// use salvo_core::prelude::*;
+/// use salvo_core::test::{ResponseExt, TestClient, TestSession};
+///
+/// #[handler]
+/// async fn login(res: &mut Response) {
+///     res.add_cookie(Cookie::new("user", "alice"));
+///     res.render(Redirect::other("/me"));
+/// }
+/// #[handler]
+/// async fn me(req: &mut Request) -> String {
+///     req.cookie("user").map(|c| c.value().to_owned()).unwrap_or_default()
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let router = Router::new()
+///     .push(Router::with_path("login").post(login))
+///     .push(Router::with_path("me").get(me));
+/// let session = TestSession::new(router).follow_redirects(5);
+/// let mut res = TestClient::post("http://127.0.0.1:5800/login").send(&session).await;
+/// assert_eq!(res.take_string().await.unwrap(), "alice");
+/// assert_eq!(session.cookie("user").unwrap().value(), "alice");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TestSession {
+    service: Arc<Service>,
+    cookies: Arc<Mutex<CookieJar>>,
+    max_redirects: usize,
+}
+
+impl TestSession {
+    /// Create a new `TestSession` sending requests to the given router.
+    pub fn new<T>(router: T) -> Self
+    where
+        T: Into<Arc<Router>>,
+    {
+        Self::with_service(Service::new(router))
+    }
+
+    /// Create a new `TestSession` sending requests to the given service.
+    pub fn with_service(service: impl Into<Arc<Service>>) -> Self {
+        Self {
+            service: service.into(),
+            cookies: Default::default(),
+            max_redirects: 0,
+        }
+    }
+
+    /// Sets the max number of redirects followed for a request, default is `0`, which means redirects are not
+    /// followed.
+    ///
+    /// `303 See Other` and `301`, `302` redirects of non `GET` requests are followed with a `GET` request without
+    /// body, `307` and `308` redirects with the same method and body.
+    #[inline]
+    pub fn follow_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Returns a cookie stored in this session.
+    pub fn cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        self.cookies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
+    }
+
+    /// Returns all cookies stored in this session.
+    pub fn cookies(&self) -> Vec<Cookie<'static>> {
+        self.cookies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Adds a cookie to this session, it will be sent with the following requests.
+    pub fn add_cookie(&self, cookie: Cookie<'static>) {
+        self.cookies.lock().unwrap_or_else(|e| e.into_inner()).add(cookie);
+    }
+
+    /// Removes a cookie from this session.
+    pub fn remove_cookie(&self, name: &str) {
+        self.cookies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(Cookie::from(name.to_owned()));
+    }
+
+    fn attach_cookies(&self, req: &mut Request) {
+        let jar = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+        let path = req.uri().path().to_owned();
+        for cookie in jar.iter() {
+            if req.cookie(cookie.name()).is_some() || !path_matches(cookie.path(), &path) {
+                continue;
+            }
+            let pair = cookie.encoded().stripped().to_string();
+            let value = match req.headers().get(header::COOKIE).and_then(|v| v.to_str().ok()) {
+                Some(cookies) if !cookies.is_empty() => format!("{cookies}; {pair}"),
+                _ => pair,
+            };
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                req.headers_mut().insert(header::COOKIE, value);
+                req.cookies_mut().add_original(cookie.clone());
+            }
+        }
+    }
+
+    fn store_cookies(&self, res: &Response) {
+        let mut jar = self.cookies.lock().unwrap_or_else(|e| e.into_inner());
+        let from_headers = res
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|v| Cookie::parse_encoded(v.to_owned()).ok());
+        for cookie in res.cookies.delta().cloned().chain(from_headers) {
+            let expired = cookie
+                .max_age()
+                .map(|age| age.is_zero() || age.is_negative())
+                .unwrap_or(false)
+                || cookie
+                    .expires_datetime()
+                    .map(|expires| expires <= OffsetDateTime::now_utc())
+                    .unwrap_or(false);
+            if expired {
+                jar.remove(Cookie::from(cookie.name().to_owned()));
+            } else {
+                jar.add(cookie);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for TestSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestSession")
+            .field("cookies", &self.cookies)
+            .field("max_redirects", &self.max_redirects)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SendTarget for &TestSession {
+    async fn call(self, mut req: Request) -> Response {
+        let mut redirects = 0;
+        loop {
+            self.attach_cookies(&mut req);
+            let method = req.method().clone();
+            let url = req.uri().to_string();
+            let mut headers = req.headers().clone();
+            let body = match &req.body {
+                ReqBody::None => Some(Bytes::new()),
+                ReqBody::Once(bytes) => Some(bytes.clone()),
+                _ => None,
+            };
+            let res = self.service.handle(req).await;
+            self.store_cookies(&res);
+            if redirects >= self.max_redirects {
+                return res;
+            }
+            let Some(location) = res
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|location| Url::parse(&url).ok()?.join(location).ok())
+            else {
+                return res;
+            };
+            let (method, body) = match res.status_code.unwrap_or(StatusCode::OK) {
+                StatusCode::SEE_OTHER | StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND
+                    if method != Method::GET && method != Method::HEAD =>
+                {
+                    headers.remove(header::CONTENT_TYPE);
+                    headers.remove(header::CONTENT_LENGTH);
+                    (Method::GET, Bytes::new())
+                }
+                StatusCode::SEE_OTHER | StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => (method, Bytes::new()),
+                StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => match body {
+                    Some(body) => (method, body),
+                    None => return res,
+                },
+                _ => return res,
+            };
+            headers.remove(header::COOKIE);
+            let mut builder = RequestBuilder::new(location, method);
+            for (name, value) in &headers {
+                builder = builder.add_header(name, value.clone(), false);
+            }
+            req = if body.is_empty() {
+                builder.build()
+            } else {
+                builder.body(body).build()
+            };
+            redirects += 1;
+        }
+    }
+}
+
+fn path_matches(cookie_path: Option<&str>, path: &str) -> bool {
+    match cookie_path {
+        None | Some("") | Some("/") => true,
+        Some(cookie_path) => {
+            let cookie_path = cookie_path.trim_end_matches('/');
+            path == cookie_path
+                || path
+                    .strip_prefix(cookie_path)
+                    .map(|rest| rest.starts_with('/'))
+                    .unwrap_or(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::test::{ResponseExt, TestClient};
+    use crate::writing::Redirect;
+
+    #[handler]
+    async fn login(req: &mut Request, res: &mut Response) {
+        let user = req.form::<String>("user").await.unwrap_or_default();
+        res.add_cookie(Cookie::build(("user", user)).path("/account").build());
+        res.add_cookie(Cookie::new("theme", "dark"));
+        res.render(Redirect::other("/account/me"));
+    }
+
+    #[handler]
+    async fn logout(res: &mut Response) {
+        res.remove_cookie("user");
+        res.render(Redirect::temporary("/account/me"));
+    }
+
+    #[handler]
+    async fn me(req: &mut Request) -> String {
+        let user = req.cookie("user").map(|c| c.value().to_owned());
+        format!("{} {}", req.method(), user.unwrap_or_else(|| "guest".into()))
+    }
+
+    #[handler]
+    async fn home(req: &mut Request) -> String {
+        req.cookie("user").map(|c| c.value().to_owned()).unwrap_or_default()
+    }
+
+    fn router() -> Router {
+        Router::new().get(home).push(
+            Router::with_path("account")
+                .push(Router::with_path("login").post(login))
+                .push(Router::with_path("logout").post(logout))
+                .push(Router::with_path("me").get(me).post(me)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_session_cookies_and_redirects() {
+        let session = TestSession::new(router()).follow_redirects(3);
+        let mut res = TestClient::post("http://127.0.0.1:5801/account/login")
+            .form(&[("user", "alice")])
+            .send(&session)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "GET alice");
+        assert_eq!(session.cookie("theme").unwrap().value(), "dark");
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/account/me").send(&session).await;
+        assert_eq!(res.take_string().await.unwrap(), "GET alice");
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(&session).await;
+        assert_eq!(res.take_string().await.unwrap(), "");
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/account/logout")
+            .send(&session)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "POST guest");
+        assert!(session.cookie("user").is_none());
+
+        let session = TestSession::new(router());
+        let res = TestClient::post("http://127.0.0.1:5801/account/login")
+            .form(&[("user", "alice")])
+            .send(&session)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::SEE_OTHER));
+        assert_eq!(session.cookie("user").unwrap().value(), "alice");
+    }
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches(None, "/a"));
+        assert!(path_matches(Some("/account"), "/account"));
+        assert!(path_matches(Some("/account/"), "/account/me"));
+        assert!(!path_matches(Some("/account"), "/accounts"));
+    }
+}
@@ -1,4 +1,10 @@
 //! Tower service compat.
+//!
+//! Tower services and layers can be converted to salvo handlers with [`TowerServiceCompat::compat`] and
+//! [`TowerLayerCompat::compat`], so existing tower middlewares, like timeouts, load shedding or retries, can be used
+//! as hoops. A salvo [`Service`](crate::Service) is a tower service itself, so it can be wrapped by tower layers or
+//! served by other tower based servers.
+use std::convert::Infallible;
 use std::error::Error as StdError;
 use std::fmt;
 use std::future::Future;
@@ -236,6 +242,29 @@ where
     }
 }
 
+/// Calls a salvo [`Service`](crate::Service) as a tower service.
+///
+/// The local and remote addresses of requests are unknown, and the scheme is `http` unless the uri of the request
+/// has one.
+impl<B> Service<hyper::Request<B>> for crate::Service
+where
+    B: Into<ReqBody>,
+{
+    type Response = hyper::Response<ResBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: hyper::Request<B>) -> Self::Future {
+        let response = self.call_hyper(req);
+        Box::pin(async move { Ok(response.await) })
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -291,4 +320,28 @@ mod tests {
             "Hello World"
         );
     }
+
+    #[tokio::test]
+    async fn test_service_as_tower_service() {
+        #[handler]
+        async fn hello(req: &mut Request) -> String {
+            format!("Hello {}", req.query::<String>("name").unwrap_or_default())
+        }
+        let service = crate::Service::new(Router::new().get(hello));
+        let service = tower::ServiceBuilder::new()
+            .map_response(|mut res: hyper::Response<ResBody>| {
+                res.headers_mut().insert("x-layer", "tower".parse().unwrap());
+                res
+            })
+            .service(service);
+
+        let req = hyper::Request::get("http://127.0.0.1:5800/?name=tower")
+            .body(ReqBody::None)
+            .unwrap();
+        let res = service.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), hyper::StatusCode::OK);
+        assert_eq!(res.headers()["x-layer"], "tower");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "Hello tower");
+    }
 }
@@ -0,0 +1,203 @@
+//! Zero-downtime binary upgrade.
+//!
+//! Listeners are bound with `SO_REUSEPORT` (see [`TcpListener::reuse_port`](crate::conn::TcpListener::reuse_port)),
+//! so the new process can listen on the same addresses while the old one is still running. When the old process
+//! receives `SIGUSR2`, it starts the new binary with the same arguments and waits until it reports to be ready by
+//! [`notify_ready`]. Then the old process stops accepting connections and drains in-flight requests.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use salvo_core::prelude::*;
+//! use salvo_core::server::shutdown_signal;
+//! use salvo_core::upgrade::{self, Upgrader};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let acceptor = TcpListener::new("0.0.0.0:5800").reuse_port(true).bind().await;
+//!     // Tell the old process, if any, that it can stop now.
+//!     upgrade::notify_ready().await.ok();
+//!
+//!     let signal = async {
+//!         tokio::select! {
+//!             _ = shutdown_signal() => {}
+//!             _ = Upgrader::new().upgraded() => {}
+//!         }
+//!     };
+//!     Server::new(acceptor)
+//!         .drain_timeout(Duration::from_secs(30))
+//!         .serve_with_graceful_shutdown(Router::new(), signal)
+//!         .await;
+//! }
+//! ```
+use std::ffi::OsString;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::net::{UnixListener, UnixStream};
+use tokio::process::Command;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// The environment variable which contains the socket path used by the new process to report readiness.
+pub const READY_ENV: &str = "SALVO_UPGRADE_READY";
+
+/// Whether [`notify_ready`] already reported readiness.
+static NOTIFIED: AtomicBool = AtomicBool::new(false);
+
+/// Starts the new process on `SIGUSR2` and waits until it is ready.
+#[derive(Clone, Debug)]
+pub struct Upgrader {
+    program: Option<PathBuf>,
+    args: Option<Vec<OsString>>,
+    ready_timeout: Duration,
+}
+
+impl Default for Upgrader {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Upgrader {
+    /// Create a new `Upgrader`.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            program: None,
+            args: None,
+            ready_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets the program of the new process, defaults to the current executable.
+    ///
+    /// The current executable may have been replaced on disk, which is usually the point of upgrading.
+    #[inline]
+    pub fn program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = Some(program.into());
+        self
+    }
+
+    /// Sets the arguments of the new process, defaults to the arguments of the current process.
+    #[inline]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args = Some(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets how long to wait for the new process to be ready, defaults to 30 seconds.
+    ///
+    /// The new process is killed if it is not ready in time, and the current process keeps serving.
+    #[inline]
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = timeout;
+        self
+    }
+
+    /// Completes after `SIGUSR2` is received and the new process is ready.
+    ///
+    /// If the upgrade fails, the error is logged and it waits for the next signal. It is intended to be passed
+    /// to [`Server::serve_with_graceful_shutdown`](crate::Server::serve_with_graceful_shutdown).
+    pub async fn upgraded(self) {
+        let mut signal = match signal(SignalKind::user_defined2()) {
+            Ok(signal) => signal,
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to listen for upgrade signal");
+                return std::future::pending().await;
+            }
+        };
+        loop {
+            signal.recv().await;
+            tracing::info!("upgrade signal received");
+            match self.upgrade().await {
+                Ok(()) => {
+                    tracing::info!("new process is ready");
+                    return;
+                }
+                Err(e) => {
+                    tracing::error!(error = ?e, "upgrade failed");
+                }
+            }
+        }
+    }
+
+    /// Starts the new process and waits until it is ready.
+    pub async fn upgrade(&self) -> IoResult<()> {
+        let program = match &self.program {
+            Some(program) => program.clone(),
+            None => std::env::current_exe()?,
+        };
+        let args = match &self.args {
+            Some(args) => args.clone(),
+            None => std::env::args_os().skip(1).collect(),
+        };
+
+        // The socket is created in a new directory only accessible by the current user, removed when it is dropped.
+        let ready_dir = tempfile::Builder::new().prefix("salvo-upgrade").tempdir()?;
+        let ready_path = ready_dir.path().join("ready.sock");
+        let ready_listener = UnixListener::bind(&ready_path)?;
+        let result = async {
+            let mut child = Command::new(&program).args(args).env(READY_ENV, &ready_path).spawn()?;
+            tracing::info!(pid = child.id(), program = ?program, "new process started");
+            tokio::select! {
+                accepted = ready_listener.accept() => accepted.map(|_| ()),
+                status = child.wait() => Err(IoError::new(
+                    ErrorKind::Other,
+                    format!("new process exited before ready: {}", status?),
+                )),
+                _ = tokio::time::sleep(self.ready_timeout) => {
+                    child.start_kill().ok();
+                    Err(IoError::new(ErrorKind::TimedOut, "new process is not ready in time"))
+                }
+            }
+        }
+        .await;
+        drop(ready_listener);
+        ready_dir.close().ok();
+        result
+    }
+}
+
+/// Reports to the old process that the current process is ready to serve.
+///
+/// Call it after all listeners are bound. It does nothing if the current process is not started by [`Upgrader`], or
+/// if it is already called.
+///
+/// The environment is left unchanged, the process started by the next upgrade gets its own socket path.
+pub async fn notify_ready() -> IoResult<()> {
+    let Some(path) = std::env::var_os(READY_ENV) else {
+        return Ok(());
+    };
+    if NOTIFIED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    UnixStream::connect(path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upgrade_failed() {
+        let upgrader = Upgrader::new().program("sh").args(["-c", "exit 1"]);
+        let err = upgrader.upgrade().await.unwrap_err();
+        assert!(err.to_string().contains("exited before ready"));
+
+        let upgrader = Upgrader::new()
+            .program("sleep")
+            .args(["10"])
+            .ready_timeout(Duration::from_millis(100));
+        assert_eq!(upgrader.upgrade().await.unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+}
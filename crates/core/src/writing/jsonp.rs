@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use serde::Serialize;
+
+use super::{Json, Writer};
+use crate::http::header::{HeaderValue, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS};
+use crate::http::{Request, Response, StatusError};
+use crate::Depot;
+
+/// Maximum length of a callback name.
+const MAX_CALLBACK_LEN: usize = 128;
+
+/// Write serializable content to response as JSONP content, for legacy clients loading JSON with `<script>` tags.
+///
+/// The callback name is read from the query parameter `callback_param` of the request, and the JSON content is
+/// written as `/**/ callback({...});` with `content-type` set to `text/javascript; charset=utf-8`. If the request
+/// has no callback, the content is written as [`Json`].
+///
+/// Callback names are checked to prevent script injection, they must be JavaScript identifiers, optionally separated
+/// by dots like `jQuery.callbacks.done`, of at most 128 characters, otherwise `400 Bad Request` is returned.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+///
+/// #[handler]
+/// async fn user() -> Jsonp<&'static str, serde_json::Value> {
+///     Jsonp("callback", serde_json::json!({"name": "jobs"}))
+/// }
+/// ```
+pub struct Jsonp<C, T>(pub C, pub T);
+
+fn is_valid_callback(callback: &str) -> bool {
+    callback.len() <= MAX_CALLBACK_LEN
+        && callback.split('.').all(|ident| {
+            let mut chars = ident.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        })
+}
+
+#[async_trait]
+impl<C, T> Writer for Jsonp<C, T>
+where
+    C: AsRef<str> + Send,
+    T: Serialize + Send,
+{
+    async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+        let Jsonp(callback_param, value) = self;
+        let Some(callback) = req.query::<String>(callback_param.as_ref()) else {
+            return Json(value).write(req, depot, res).await;
+        };
+        if !is_valid_callback(&callback) {
+            res.render(StatusError::bad_request().brief("Invalid JSONP callback name."));
+            return;
+        }
+        match serde_json::to_string(&value) {
+            Ok(json) => {
+                // U+2028 and U+2029 are valid in JSON strings, but are line terminators in older JavaScript engines.
+                let json = json.replace('\u{2028}', "\\u2028").replace('\u{2029}', "\\u2029");
+                // The leading comment prevents the Rosetta Flash attack.
+                let body = format!("/**/ {callback}({json});");
+                res.headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("text/javascript; charset=utf-8"));
+                res.headers_mut()
+                    .insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+                res.write_body(body).ok();
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "JsonpContent write error");
+                res.render(StatusError::internal_server_error());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use super::*;
+    use crate::test::{ResponseExt, TestClient};
+
+    #[test]
+    fn test_valid_callback() {
+        for callback in ["cb", "_cb1", "$", "jQuery.callbacks.done"] {
+            assert!(is_valid_callback(callback), "{callback}");
+        }
+        for callback in ["", "1cb", "cb.", "a..b", "alert(1)", "cb;x", "cb[0]", "é"] {
+            assert!(!is_valid_callback(callback), "{callback}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_jsonp_content() {
+        #[derive(Serialize, Debug)]
+        struct User {
+            name: String,
+        }
+        #[handler]
+        async fn test() -> Jsonp<&'static str, User> {
+            Jsonp(
+                "callback",
+                User {
+                    name: "jobs\u{2028}".into(),
+                },
+            )
+        }
+
+        let service = Service::new(Router::new().push(Router::with_path("test").get(test)));
+        let mut res = TestClient::get("http://127.0.0.1:5800/test?callback=app.done")
+            .send(&service)
+            .await;
+        assert_eq!(
+            res.take_string().await.unwrap(),
+            r#"/**/ app.done({"name":"jobs\u2028"});"#
+        );
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "text/javascript; charset=utf-8"
+        );
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/test").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), "{\"name\":\"jobs\u{2028}\"}");
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "application/json; charset=utf-8"
+        );
+
+        let res = TestClient::get("http://127.0.0.1:5800/test?callback=alert(1)")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::BAD_REQUEST));
+    }
+}
@@ -1,17 +1,21 @@
 //! Writer trait and it's implements.
 
 mod json;
+mod jsonp;
+mod ranged;
 mod redirect;
 mod seek;
 mod text;
 
 use http::StatusCode;
 pub use json::Json;
+pub use jsonp::Jsonp;
+pub use ranged::RangedStream;
 pub use redirect::Redirect;
 pub use seek::ReadSeeker;
 pub use text::Text;
 
-use crate::http::header::{HeaderValue, CONTENT_TYPE};
+use crate::http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use crate::{async_trait, Depot, Request, Response};
 
 /// `Writer` is a trait allows you to implement custom writing logic for different data types.
@@ -19,7 +23,27 @@ use crate::{async_trait, Depot, Request, Response};
 /// Implementing the `Writer` trait for your data type allows you to use it writing the data to the
 /// [`Response`] object.
 ///
-/// There are several built-in implementations of the `Writer` trait.
+/// There are several built-in implementations of the `Writer` trait, so handlers can return values instead of
+/// writing to [`Response`]:
+///
+/// - Any [`Scribe`], like `String`, [`Json`] or [`StatusCode`].
+/// - `Option<T>`, which writes `T` or sets status code to `404 Not Found` if it is `None`.
+/// - `Result<T, E>`, which writes `T` or `E`.
+/// - `(StatusCode, T)`, `(HeaderMap, T)` and `(StatusCode, HeaderMap, T)`, which write `T`, then set the status
+///   code and the headers of the response.
+///
+/// ```
+/// use salvo_core::http::header::{HeaderMap, LOCATION};
+/// use salvo_core::prelude::*;
+///
+/// #[handler]
+/// async fn create_user() -> Result<(StatusCode, HeaderMap, Json<u64>), StatusError> {
+///     let id = 7;
+///     let mut headers = HeaderMap::new();
+///     headers.insert(LOCATION, format!("/users/{id}").parse().map_err(|_| StatusError::internal_server_error())?);
+///     Ok((StatusCode::CREATED, headers, Json(id)))
+/// }
+/// ```
 #[async_trait]
 pub trait Writer {
     /// Write data to [`Response`].
@@ -48,12 +72,12 @@ where
 #[async_trait]
 impl<P> Writer for Option<P>
 where
-    P: Scribe + Sized + Send,
+    P: Writer + Sized + Send,
 {
     #[inline]
-    async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
+    async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
         match self {
-            Some(v) => v.render(res),
+            Some(v) => v.write(req, depot, res).await,
             None => {
                 res.status_code(StatusCode::NOT_FOUND);
             }
@@ -80,6 +104,46 @@ where
     }
 }
 
+#[async_trait]
+impl<T> Writer for (StatusCode, T)
+where
+    T: Writer + Send,
+{
+    #[inline]
+    async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+        let (status_code, writer) = self;
+        writer.write(req, depot, res).await;
+        res.status_code(status_code);
+    }
+}
+
+#[async_trait]
+impl<T> Writer for (HeaderMap, T)
+where
+    T: Writer + Send,
+{
+    #[inline]
+    async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+        let (headers, writer) = self;
+        writer.write(req, depot, res).await;
+        res.headers_mut().extend(headers);
+    }
+}
+
+#[async_trait]
+impl<T> Writer for (StatusCode, HeaderMap, T)
+where
+    T: Writer + Send,
+{
+    #[inline]
+    async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+        let (status_code, headers, writer) = self;
+        writer.write(req, depot, res).await;
+        res.status_code(status_code);
+        res.headers_mut().extend(headers);
+    }
+}
+
 #[allow(clippy::unit_arg)]
 impl Scribe for () {
     #[inline]
@@ -124,6 +188,7 @@ impl Scribe for std::convert::Infallible {
 
 #[cfg(test)]
 mod tests {
+    use crate::http::header::{HeaderMap, HeaderValue};
     use crate::prelude::*;
 
     use crate::test::{ResponseExt, TestClient};
@@ -154,4 +219,43 @@ mod tests {
         assert_eq!(res.take_string().await.unwrap(), "hello");
         assert_eq!(res.headers().get("content-type").unwrap(), "text/plain; charset=utf-8");
     }
+
+    #[tokio::test]
+    async fn test_write_option_and_tuples() {
+        #[handler]
+        async fn find(req: &mut Request) -> Option<Json<u32>> {
+            req.param::<u32>("id").filter(|id| *id < 10).map(Json)
+        }
+        #[handler]
+        async fn create() -> Result<(StatusCode, HeaderMap, &'static str), StatusError> {
+            let mut headers = HeaderMap::new();
+            headers.insert("location", HeaderValue::from_static("/items/1"));
+            Ok((StatusCode::CREATED, headers, "created"))
+        }
+        #[handler]
+        async fn teapot() -> (StatusCode, Option<&'static str>) {
+            (StatusCode::IM_A_TEAPOT, Some("tea"))
+        }
+
+        let router = Router::with_path("items")
+            .post(create)
+            .push(Router::with_path("tea").get(teapot))
+            .push(Router::with_path("<id>").get(find));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/items/1").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "1");
+        let res = TestClient::get("http://127.0.0.1:5800/items/42").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+
+        let mut res = TestClient::post("http://127.0.0.1:5800/items").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::CREATED));
+        assert_eq!(res.headers()["location"], "/items/1");
+        assert_eq!(res.take_string().await.unwrap(), "created");
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/items/tea").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::IM_A_TEAPOT));
+        assert_eq!(res.take_string().await.unwrap(), "tea");
+    }
 }
@@ -0,0 +1,421 @@
+use std::collections::VecDeque;
+use std::io::{self, SeekFrom};
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use headers::*;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use super::seek::{any_match, none_match};
+use crate::http::header::{HeaderValue, CONTENT_TYPE, IF_NONE_MATCH, RANGE};
+use crate::http::{HttpRange, Mime, Request, Response, StatusCode};
+use crate::{async_trait, Depot, Writer};
+
+/// Size of the chunks read from the source.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// `RangedStream` is used to write data to [`Response`] from a seekable source of a known length, serving the byte
+/// ranges requested by the `Range` header, so clients can resume downloads and video players can seek.
+///
+/// Unlike [`ReadSeeker`](super::ReadSeeker), requests for several ranges are answered with a
+/// `multipart/byteranges` response, and the `If-Range` header is supported: the full content is sent if it changed
+/// since the client got its part of it. Overlapping and adjacent ranges are coalesced, and the `Range` header is
+/// ignored if it asks for more than [`max_ranges`](RangedStream::max_ranges) ranges.
+///
+/// The source only has to implement [`AsyncRead`] and [`AsyncSeek`], so it can be a file or a reader of an object
+/// storage, seeking with ranged requests.
+///
+/// # Example
+/// ```
+/// use salvo_core::http::mime;
+/// use salvo_core::prelude::*;
+/// use salvo_core::writing::RangedStream;
+///
+/// #[handler]
+/// async fn video_stream() -> Result<RangedStream<tokio::fs::File>, StatusError> {
+///     let file = tokio::fs::File::open("video.mp4").await.map_err(|_| StatusError::not_found())?;
+///     let length = file.metadata().await.map_err(|_| StatusError::internal_server_error())?.len();
+///     Ok(RangedStream::new(file, length).content_type("video/mp4".parse::<mime::Mime>().unwrap()))
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RangedStream<R> {
+    reader: R,
+    length: u64,
+    content_type: Option<Mime>,
+    last_modified: Option<SystemTime>,
+    etag: Option<ETag>,
+    max_ranges: usize,
+}
+
+impl<R> RangedStream<R>
+where
+    R: AsyncSeek + AsyncRead + Unpin + Send + 'static,
+{
+    /// Create a new [`RangedStream`] from a source of `length` bytes.
+    pub fn new(reader: R, length: u64) -> Self {
+        RangedStream {
+            reader,
+            length,
+            content_type: None,
+            last_modified: None,
+            etag: None,
+            max_ranges: 32,
+        }
+    }
+
+    /// Set the content type of the content, it is also used for the parts of `multipart/byteranges` responses.
+    ///
+    /// If it is not set, the `Content-Type` header of the response is kept, or `application/octet-stream` is used.
+    pub fn content_type<T: Into<Mime>>(mut self, content_type: T) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Set the last modified time for the response.
+    pub fn last_modified(mut self, time: SystemTime) -> Self {
+        self.last_modified = Some(time);
+        self
+    }
+
+    /// Set the ETag header for the response.
+    pub fn etag(mut self, etag: ETag) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// Set the maximum number of ranges of a request, defaults to `32`.
+    pub fn max_ranges(mut self, max_ranges: usize) -> Self {
+        self.max_ranges = max_ranges.max(1);
+        self
+    }
+
+    /// Returns the ranges to send, `None` if the full content is sent.
+    fn ranges(&self, req_headers: &HeaderMap) -> Result<Option<Vec<HttpRange>>, StatusCode> {
+        let Some(range) = req_headers.get(RANGE) else {
+            return Ok(None);
+        };
+        if let Some(if_range) = req_headers.typed_get::<IfRange>() {
+            let last_modified = self.last_modified.map(LastModified::from);
+            if if_range.is_modified(self.etag.as_ref(), last_modified.as_ref()) {
+                return Ok(None);
+            }
+        }
+        let range = range.to_str().map_err(|_| StatusCode::BAD_REQUEST)?;
+        let mut ranges = HttpRange::parse(range, self.length).map_err(|_| StatusCode::RANGE_NOT_SATISFIABLE)?;
+        ranges.retain(|range| range.length > 0);
+        if ranges.is_empty() || ranges.len() > self.max_ranges {
+            return Ok(None);
+        }
+        Ok(Some(coalesce(ranges)))
+    }
+
+    /// Consume self and send content to [`Response`].
+    pub async fn send(self, req_headers: &HeaderMap, res: &mut Response) {
+        let precondition_failed = if !any_match(self.etag.as_ref(), req_headers) {
+            true
+        } else if let (Some(last_modified), Some(since)) =
+            (&self.last_modified, req_headers.typed_get::<IfUnmodifiedSince>())
+        {
+            !since.precondition_passes(*last_modified)
+        } else {
+            false
+        };
+        let not_modified = if !none_match(self.etag.as_ref(), req_headers) {
+            true
+        } else if req_headers.contains_key(IF_NONE_MATCH) {
+            false
+        } else if let (Some(last_modified), Some(since)) =
+            (&self.last_modified, req_headers.typed_get::<IfModifiedSince>())
+        {
+            !since.is_modified(*last_modified)
+        } else {
+            false
+        };
+
+        if let Some(lm) = self.last_modified {
+            res.headers_mut().typed_insert(LastModified::from(lm));
+        }
+        if let Some(etag) = &self.etag {
+            res.headers_mut().typed_insert(etag.clone());
+        }
+        res.headers_mut().typed_insert(AcceptRanges::bytes());
+
+        if precondition_failed {
+            res.status_code(StatusCode::PRECONDITION_FAILED);
+            return;
+        } else if not_modified {
+            res.status_code(StatusCode::NOT_MODIFIED);
+            return;
+        }
+
+        let ranges = match self.ranges(req_headers) {
+            Ok(ranges) => ranges,
+            Err(status_code) => {
+                if status_code == StatusCode::RANGE_NOT_SATISFIABLE {
+                    res.headers_mut()
+                        .typed_insert(ContentRange::unsatisfied_bytes(self.length));
+                }
+                res.status_code(status_code);
+                return;
+            }
+        };
+
+        let content_type = match self.content_type {
+            Some(content_type) => HeaderValue::from_str(content_type.as_ref()).ok(),
+            None => res.headers().get(CONTENT_TYPE).cloned(),
+        }
+        .unwrap_or_else(|| HeaderValue::from_static("application/octet-stream"));
+        let total = self.length;
+        let (parts, tail, content_length) = match ranges.as_deref() {
+            None => {
+                res.status_code(StatusCode::OK);
+                res.headers_mut().insert(CONTENT_TYPE, content_type);
+                (VecDeque::from([Part::new(Bytes::new(), 0, total)]), Bytes::new(), total)
+            }
+            Some([range]) => {
+                res.status_code(StatusCode::PARTIAL_CONTENT);
+                res.headers_mut().insert(CONTENT_TYPE, content_type);
+                if let Ok(content_range) = ContentRange::bytes(range.start..range.start + range.length, total) {
+                    res.headers_mut().typed_insert(content_range);
+                }
+                let part = Part::new(Bytes::new(), range.start, range.length);
+                (VecDeque::from([part]), Bytes::new(), range.length)
+            }
+            Some(ranges) => {
+                let boundary = format!("{:016x}", rand::random::<u64>());
+                let part_type = content_type.to_str().unwrap_or("application/octet-stream");
+                let parts = ranges
+                    .iter()
+                    .enumerate()
+                    .map(|(index, range)| {
+                        let header = format!(
+                            "{}--{boundary}\r\nContent-Type: {part_type}\r\n\
+                            Content-Range: bytes {}-{}/{total}\r\n\r\n",
+                            if index == 0 { "" } else { "\r\n" },
+                            range.start,
+                            range.start + range.length - 1,
+                        );
+                        Part::new(header.into(), range.start, range.length)
+                    })
+                    .collect::<VecDeque<_>>();
+                let tail = Bytes::from(format!("\r\n--{boundary}--\r\n"));
+                let content_length = parts
+                    .iter()
+                    .map(|part| part.header.len() as u64 + part.length)
+                    .sum::<u64>()
+                    + tail.len() as u64;
+                res.status_code(StatusCode::PARTIAL_CONTENT);
+                match HeaderValue::from_str(&format!("multipart/byteranges; boundary={boundary}")) {
+                    Ok(value) => {
+                        res.headers_mut().insert(CONTENT_TYPE, value);
+                    }
+                    Err(e) => {
+                        tracing::error!(error = ?e, "set multipart content type failed");
+                    }
+                }
+                (parts, tail, content_length)
+            }
+        };
+        res.headers_mut().typed_insert(ContentLength(content_length));
+
+        let state = StreamState {
+            reader: self.reader,
+            parts,
+            tail,
+            remaining: 0,
+            done: false,
+        };
+        res.stream(futures_util::stream::unfold(state, StreamState::next));
+    }
+}
+
+#[async_trait]
+impl<R> Writer for RangedStream<R>
+where
+    R: AsyncSeek + AsyncRead + Unpin + Send + 'static,
+{
+    #[inline]
+    async fn write(self, req: &mut Request, _depot: &mut Depot, res: &mut Response) {
+        self.send(req.headers(), res).await;
+    }
+}
+
+/// Sorts the ranges and merges the overlapping or adjacent ones.
+fn coalesce(mut ranges: Vec<HttpRange>) -> Vec<HttpRange> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<HttpRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.start + last.length => {
+                let end = (last.start + last.length).max(range.start + range.length);
+                last.length = end - last.start;
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// A range of the content, sent after its multipart header.
+struct Part {
+    header: Bytes,
+    start: u64,
+    length: u64,
+}
+impl Part {
+    fn new(header: Bytes, start: u64, length: u64) -> Self {
+        Self { header, start, length }
+    }
+}
+
+struct StreamState<R> {
+    reader: R,
+    parts: VecDeque<Part>,
+    tail: Bytes,
+    /// Bytes of the current part left to read.
+    remaining: u64,
+    done: bool,
+}
+impl<R> StreamState<R>
+where
+    R: AsyncSeek + AsyncRead + Unpin + Send + 'static,
+{
+    async fn next(mut self) -> Option<(io::Result<Bytes>, Self)> {
+        if self.done {
+            return None;
+        }
+        let item = self.read().await;
+        match item {
+            Some(Err(e)) => {
+                tracing::error!(error = ?e, "read ranged stream failed");
+                self.done = true;
+                Some((Err(e), self))
+            }
+            Some(Ok(chunk)) => Some((Ok(chunk), self)),
+            None => None,
+        }
+    }
+
+    async fn read(&mut self) -> Option<io::Result<Bytes>> {
+        loop {
+            if self.remaining > 0 {
+                let mut buf = vec![0; CHUNK_SIZE.min(self.remaining as usize)];
+                return match self.reader.read(&mut buf).await {
+                    Ok(0) => Some(Err(io::ErrorKind::UnexpectedEof.into())),
+                    Ok(read) => {
+                        self.remaining -= read as u64;
+                        buf.truncate(read);
+                        Some(Ok(buf.into()))
+                    }
+                    Err(e) => Some(Err(e)),
+                };
+            }
+            if let Some(part) = self.parts.pop_front() {
+                if let Err(e) = self.reader.seek(SeekFrom::Start(part.start)).await {
+                    return Some(Err(e));
+                }
+                self.remaining = part.length;
+                if !part.header.is_empty() {
+                    return Some(Ok(part.header));
+                }
+                // Single range or full content, without header.
+                continue;
+            }
+            if !self.tail.is_empty() {
+                return Some(Ok(std::mem::take(&mut self.tail)));
+            }
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::http::mime;
+    use crate::prelude::*;
+    use crate::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn content() -> RangedStream<Cursor<&'static [u8]>> {
+        RangedStream::new(Cursor::new(b"0123456789".as_slice()), 10)
+            .content_type(mime::TEXT_PLAIN)
+            .etag("\"v1\"".parse().unwrap())
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let ranges = coalesce(vec![
+            HttpRange { start: 6, length: 2 },
+            HttpRange { start: 0, length: 2 },
+            HttpRange { start: 1, length: 3 },
+            HttpRange { start: 4, length: 1 },
+        ]);
+        let ranges = ranges
+            .iter()
+            .map(|range| (range.start, range.length))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec![(0, 5), (6, 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_ranged_stream() {
+        let service = Service::new(Router::with_path("content").get(content));
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/content").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.headers()["accept-ranges"], "bytes");
+        assert_eq!(res.take_string().await.unwrap(), "0123456789");
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/content")
+            .add_header("range", "bytes=2-4", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::PARTIAL_CONTENT));
+        assert_eq!(res.headers()["content-range"], "bytes 2-4/10");
+        assert_eq!(res.headers()["content-length"], "3");
+        assert_eq!(res.take_string().await.unwrap(), "234");
+
+        let res = TestClient::get("http://127.0.0.1:5800/content")
+            .add_header("range", "bytes=20-", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::RANGE_NOT_SATISFIABLE));
+        assert_eq!(res.headers()["content-range"], "bytes */10");
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/content")
+            .add_header("range", "bytes=0-1", true)
+            .add_header("if-range", "\"v0\"", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_ranged_stream_multipart() {
+        let service = Service::new(Router::with_path("content").get(content));
+        let mut res = TestClient::get("http://127.0.0.1:5800/content")
+            .add_header("range", "bytes=0-1,-2", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::PARTIAL_CONTENT));
+        let content_type = res.headers()["content-type"].to_str().unwrap().to_owned();
+        let boundary = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap();
+        let content_length: usize = res.headers()["content-length"].to_str().unwrap().parse().unwrap();
+        let body = res.take_string().await.unwrap();
+        assert_eq!(body.len(), content_length);
+        assert_eq!(
+            body,
+            format!(
+                "--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+                \r\n--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\
+                \r\n--{boundary}--\r\n"
+            )
+        );
+    }
+}
@@ -159,7 +159,7 @@ where
 }
 
 /// Returns true if `req_headers` has no `If-Match` header or one which matches `etag`.
-fn any_match(etag: Option<&ETag>, req_headers: &HeaderMap) -> bool {
+pub(super) fn any_match(etag: Option<&ETag>, req_headers: &HeaderMap) -> bool {
     match req_headers.typed_get::<IfMatch>() {
         None => true,
         Some(if_match) => {
@@ -175,7 +175,7 @@ fn any_match(etag: Option<&ETag>, req_headers: &HeaderMap) -> bool {
 }
 
 /// Returns true if `req_headers` doesn't have an `If-None-Match` header matching `req`.
-fn none_match(etag: Option<&ETag>, req_headers: &HeaderMap) -> bool {
+pub(super) fn none_match(etag: Option<&ETag>, req_headers: &HeaderMap) -> bool {
     match req_headers.typed_get::<IfNoneMatch>() {
         None => true,
         Some(if_none_match) => {
@@ -1,5 +1,6 @@
 use std::{fmt, sync::Arc};
 
+use regex::Regex;
 use salvo_core::http::header::{self, HeaderName, HeaderValue};
 use salvo_core::{Depot, Request};
 
@@ -68,6 +69,35 @@ impl AllowOrigin {
         Self(OriginInner::Judge(Arc::new(f)))
     }
 
+    /// Set the allowed origins from a regular expression, for example `^https://.+\.salvo\.rs$`.
+    ///
+    /// The whole origin must be matched, so the pattern should usually be anchored with `^` and `$`.
+    ///
+    /// See [`Cors::allow_origin`] for more details.
+    ///
+    /// # Panics
+    ///
+    /// If the regular expression is invalid.
+    ///
+    /// [`Cors::allow_origin`]: super::Cors::allow_origin
+    pub fn regex(pattern: impl AsRef<str>) -> Self {
+        let regex = Regex::new(pattern.as_ref()).expect("invalid regular expression");
+        Self::judge(move |origin, _, _| origin.to_str().map(|origin| regex.is_match(origin)).unwrap_or(false))
+    }
+
+    /// Set the allowed origins from a pattern with wildcards, for example `https://*.salvo.rs`.
+    ///
+    /// Each `*` matches exactly one domain label, so `https://*.salvo.rs` allows `https://api.salvo.rs`
+    /// but neither `https://salvo.rs` nor `https://a.b.salvo.rs`.
+    ///
+    /// See [`Cors::allow_origin`] for more details.
+    ///
+    /// [`Cors::allow_origin`]: super::Cors::allow_origin
+    pub fn wildcard(pattern: impl AsRef<str>) -> Self {
+        let pattern = regex::escape(pattern.as_ref()).replace(r"\*", "[A-Za-z0-9-]+");
+        Self::regex(format!("^{pattern}$"))
+    }
+
     /// Allow any origin, by mirroring the request origin.
     ///
     /// See [`Cors::allow_origin`] for more details.
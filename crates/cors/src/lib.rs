@@ -13,7 +13,9 @@
 //!     .allow_origin("https://salvo.rs")
 //!     .allow_methods(vec![Method::GET, Method::POST, Method::DELETE]).into_handler();
 //!
-//! let router = Router::new().hoop(cors_handler).post(upload_file).options(upload_file);
+//! let router = Router::new().post(upload_file);
+//! // Hoops of the service run even if no router matches, so preflight requests are always answered.
+//! let service = Service::new(router).hoop(cors_handler);
 //! #[handler]
 //! async fn upload_file(res: &mut Response) {
 //! }
@@ -264,6 +266,10 @@ impl Cors {
 }
 
 /// CorsHandler
+///
+/// Preflight requests are answered with `204 No Content` directly, without calling the next handlers.
+/// A hoop only runs when its router matches the request, so add it to the [`Service`](salvo_core::Service)
+/// to answer preflight requests for routers without an `OPTIONS` handler.
 #[derive(Clone, Debug)]
 pub struct CorsHandler(Cors);
 
@@ -295,21 +301,29 @@ impl Handler for CorsHandler {
         }
 
         // Return results immediately upon preflight request
-        if req.method() == Method::OPTIONS {
+        if is_preflight(req) {
             // These headers are applied only to preflight requests
             headers.extend(self.0.allow_methods.to_header(origin, req, depot));
             headers.extend(self.0.allow_headers.to_header(origin, req, depot));
             headers.extend(self.0.max_age.to_header(origin, req, depot));
+            res.headers_mut().extend(headers);
             res.status_code = Some(StatusCode::NO_CONTENT);
+            ctrl.skip_rest();
         } else {
             // This header is applied only to non-preflight requests
             headers.extend(self.0.expose_headers.to_header(origin, req, depot));
+            res.headers_mut().extend(headers);
+            ctrl.call_next(req, depot, res).await;
         }
-        res.headers_mut().extend(headers);
-        ctrl.call_next(req, depot, res).await;
     }
 }
 
+/// A preflight request is an `OPTIONS` request with the `Access-Control-Request-Method` header,
+/// other `OPTIONS` requests are passed to the next handlers.
+fn is_preflight(req: &Request) -> bool {
+    req.method() == Method::OPTIONS && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
 /// Returns an iterator over the three request headers that may be involved in a CORS preflight request.
 ///
 /// This is the default set of header names returned in the `vary` header
@@ -326,7 +340,7 @@ pub fn preflight_request_headers() -> impl Iterator<Item = HeaderName> {
 mod tests {
     use salvo_core::http::header::*;
     use salvo_core::prelude::*;
-    use salvo_core::test::TestClient;
+    use salvo_core::test::{ResponseExt, TestClient};
 
     use super::*;
 
@@ -379,4 +393,45 @@ mod tests {
         );
         assert!(headers.get(ACCESS_CONTROL_ALLOW_HEADERS).is_none());
     }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        #[handler]
+        async fn hello() -> &'static str {
+            "hello"
+        }
+
+        let cors_handler = Cors::new()
+            .allow_origin(AllowOrigin::wildcard("https://*.salvo.rs"))
+            .allow_methods(vec![Method::GET, Method::POST])
+            .allow_credentials(true)
+            .max_age(600)
+            .into_handler();
+        let service = Service::new(Router::with_path("hello").post(hello)).hoop(cors_handler);
+
+        let mut res = TestClient::options("http://127.0.0.1:5801/hello")
+            .add_header(ORIGIN, "https://api.salvo.rs", true)
+            .add_header(ACCESS_CONTROL_REQUEST_METHOD, "POST", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::NO_CONTENT));
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "https://api.salvo.rs");
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(res.headers()[ACCESS_CONTROL_MAX_AGE], "600");
+        assert!(res.take_string().await.unwrap().is_empty());
+
+        let res = TestClient::options("http://127.0.0.1:5801/hello")
+            .add_header(ORIGIN, "https://a.b.salvo.rs", true)
+            .add_header(ACCESS_CONTROL_REQUEST_METHOD, "POST", true)
+            .send(&service)
+            .await;
+        assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/hello")
+            .add_header(ORIGIN, "https://api.salvo.rs", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "https://api.salvo.rs");
+        assert_eq!(res.take_string().await.unwrap(), "hello");
+    }
 }
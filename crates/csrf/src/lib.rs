@@ -14,8 +14,10 @@ use std::error::Error as StdError;
 use std::future::Future;
 
 mod finder;
+mod origin;
 
 pub use finder::{CsrfTokenFinder, FormFinder, HeaderFinder, JsonFinder};
+pub use origin::OriginCheck;
 
 use rand::distributions::Standard;
 use rand::Rng;
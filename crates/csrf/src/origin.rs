@@ -0,0 +1,264 @@
+//! Origin and Referer validation.
+use salvo_core::http::header::{HeaderName, HOST, ORIGIN, REFERER};
+use salvo_core::http::uri::{Scheme, Uri};
+use salvo_core::http::StatusError;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
+/// Returns the origin of `url` like `https://salvo.rs`, default ports are removed.
+fn parse_origin(url: &str) -> Option<String> {
+    let uri = url.trim().parse::<Uri>().ok()?;
+    let scheme = uri.scheme_str()?.to_ascii_lowercase();
+    let authority = uri.authority()?;
+    let host = authority.host().to_ascii_lowercase();
+    match authority.port_u16() {
+        Some(80) if scheme == "http" => Some(format!("{scheme}://{host}")),
+        Some(443) if scheme == "https" => Some(format!("{scheme}://{host}")),
+        Some(port) => Some(format!("{scheme}://{host}:{port}")),
+        None => Some(format!("{scheme}://{host}")),
+    }
+}
+
+/// Validates the `Origin` or `Referer` header of state-changing requests.
+///
+/// Browsers send the `Origin` header with cross-origin and `POST` requests, and can not be tricked to forge it.
+/// Requests with unsafe methods are accepted if their origin, read from `Origin` header or from `Referer` header
+/// when `Origin` is absent, is the origin of the server itself or one of the
+/// [`allowed_origins`](OriginCheck::allowed_origin). Others are rejected with `403 Forbidden`.
+///
+/// It is a lighter complement to CSRF tokens for JSON APIs authenticated by cookies. It should not be the only
+/// protection for clients not sending these headers, which are rejected unless
+/// [`allow_missing`](OriginCheck::allow_missing) is set.
+///
+/// Behind a reverse proxy terminating TLS, the server sees plain `http` requests, so either add the public origin
+/// with [`allowed_origin`](OriginCheck::allowed_origin) or enable
+/// [`trust_forwarded_headers`](OriginCheck::trust_forwarded_headers).
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_csrf::OriginCheck;
+///
+/// let check = OriginCheck::new().allowed_origin("https://app.salvo.rs");
+/// let router = Router::with_path("api").hoop(check);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OriginCheck {
+    allowed_origins: Vec<String>,
+    allow_missing: bool,
+    trust_forwarded_headers: bool,
+}
+
+impl OriginCheck {
+    /// Create a new `OriginCheck`, which only accepts the origin of the server.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an allowed origin like `https://app.salvo.rs`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `origin` is not a valid origin.
+    #[inline]
+    pub fn allowed_origin(mut self, origin: impl AsRef<str>) -> Self {
+        let origin = parse_origin(origin.as_ref()).expect("allowed origin should be like `https://salvo.rs`");
+        self.allowed_origins.push(origin);
+        self
+    }
+
+    /// Sets whether requests without `Origin` and `Referer` headers are accepted, defaults to `false`.
+    #[inline]
+    pub fn allow_missing(mut self, allow_missing: bool) -> Self {
+        self.allow_missing = allow_missing;
+        self
+    }
+
+    /// Sets whether the server origin is read from `X-Forwarded-Proto` and `X-Forwarded-Host` headers, defaults
+    /// to `false`.
+    ///
+    /// Only enable it when the server is reachable only through a reverse proxy which sets or overrides these
+    /// headers, otherwise clients can forge them.
+    #[inline]
+    pub fn trust_forwarded_headers(mut self, trust: bool) -> Self {
+        self.trust_forwarded_headers = trust;
+        self
+    }
+
+    /// Returns the first value of a forwarded header, proxies append their own values to the list.
+    fn forwarded(req: &Request, name: HeaderName) -> Option<String> {
+        let value = req.headers().get(name)?.to_str().ok()?;
+        let value = value.split(',').next()?.trim();
+        (!value.is_empty()).then(|| value.to_owned())
+    }
+
+    fn server_origin(&self, req: &Request) -> Option<String> {
+        let forwarded_host = self
+            .trust_forwarded_headers
+            .then(|| Self::forwarded(req, X_FORWARDED_HOST))
+            .flatten();
+        let host = match forwarded_host {
+            Some(host) => host,
+            None => req
+                .uri()
+                .authority()
+                .map(|authority| authority.as_str().to_owned())
+                .or_else(|| req.header::<String>(HOST))?,
+        };
+        let forwarded_proto = self
+            .trust_forwarded_headers
+            .then(|| Self::forwarded(req, X_FORWARDED_PROTO))
+            .flatten();
+        let scheme = match forwarded_proto {
+            Some(proto) if proto.eq_ignore_ascii_case("https") => "https",
+            Some(_) => "http",
+            None if req.scheme() == &Scheme::HTTPS => "https",
+            None => "http",
+        };
+        parse_origin(&format!("{scheme}://{host}"))
+    }
+
+    fn is_allowed(&self, req: &Request) -> bool {
+        let origin = match req.headers().get(ORIGIN) {
+            Some(origin) => origin.to_str().ok().and_then(parse_origin),
+            None => match req.headers().get(REFERER) {
+                Some(referer) => referer.to_str().ok().and_then(parse_origin),
+                None => return self.allow_missing,
+            },
+        };
+        let Some(origin) = origin else {
+            return false;
+        };
+        self.allowed_origins.contains(&origin) || self.server_origin(req).as_ref() == Some(&origin)
+    }
+}
+
+#[async_trait]
+impl Handler for OriginCheck {
+    async fn handle(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        if req.method().is_safe() || self.is_allowed(req) {
+            return;
+        }
+        tracing::debug!("rejecting request due to invalid origin");
+        res.render(StatusError::forbidden().brief("Origin is not allowed."));
+        ctrl.skip_rest();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[handler]
+    async fn hello() -> &'static str {
+        "hello"
+    }
+
+    #[test]
+    fn test_parse_origin() {
+        assert_eq!(
+            parse_origin("https://Salvo.rs:443/a?b").as_deref(),
+            Some("https://salvo.rs")
+        );
+        assert_eq!(
+            parse_origin("http://salvo.rs:8080").as_deref(),
+            Some("http://salvo.rs:8080")
+        );
+        assert_eq!(parse_origin("null"), None);
+        assert_eq!(parse_origin("/relative"), None);
+    }
+
+    #[tokio::test]
+    async fn test_origin_check() {
+        let router = Router::with_hoop(OriginCheck::new().allowed_origin("https://app.salvo.rs"))
+            .get(hello)
+            .post(hello);
+        let service = Service::new(router);
+
+        let send = |origin: Option<(&'static str, &'static str)>| {
+            let mut req = TestClient::post("http://api.salvo.rs/");
+            if let Some((name, value)) = origin {
+                req = req.add_header(name, value, true);
+            }
+            let service = &service;
+            async move { req.send(service).await.status_code }
+        };
+        assert_eq!(
+            send(Some(("origin", "https://app.salvo.rs"))).await,
+            Some(StatusCode::OK)
+        );
+        assert_eq!(
+            send(Some(("origin", "http://api.salvo.rs"))).await,
+            Some(StatusCode::OK)
+        );
+        assert_eq!(
+            send(Some(("referer", "https://app.salvo.rs/form"))).await,
+            Some(StatusCode::OK)
+        );
+        assert_eq!(
+            send(Some(("origin", "https://evil.example"))).await,
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(send(Some(("origin", "null"))).await, Some(StatusCode::FORBIDDEN));
+        assert_eq!(send(None).await, Some(StatusCode::FORBIDDEN));
+
+        let res = TestClient::get("http://api.salvo.rs/")
+            .add_header("origin", "https://evil.example", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_origin_check_forwarded_headers() {
+        let send = |check: OriginCheck, headers: &'static [(&'static str, &'static str)]| async move {
+            let service = Service::new(Router::with_hoop(check).post(hello));
+            let mut req = TestClient::post("http://127.0.0.1:5800/");
+            for (name, value) in headers {
+                req = req.add_header(*name, *value, true);
+            }
+            req.send(&service).await.status_code
+        };
+        let proxied = &[
+            ("origin", "https://salvo.rs"),
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "salvo.rs"),
+        ];
+
+        assert_eq!(send(OriginCheck::new(), proxied).await, Some(StatusCode::FORBIDDEN));
+        assert_eq!(
+            send(OriginCheck::new().trust_forwarded_headers(true), proxied).await,
+            Some(StatusCode::OK)
+        );
+        assert_eq!(
+            send(OriginCheck::new().allowed_origin("https://salvo.rs"), proxied).await,
+            Some(StatusCode::OK)
+        );
+        assert_eq!(
+            send(
+                OriginCheck::new().trust_forwarded_headers(true),
+                &[("origin", "https://salvo.rs"), ("x-forwarded-proto", "https, http")]
+            )
+            .await,
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            send(
+                OriginCheck::new().trust_forwarded_headers(true),
+                &[
+                    ("origin", "https://127.0.0.1:5800"),
+                    ("x-forwarded-proto", "https, http")
+                ]
+            )
+            .await,
+            Some(StatusCode::OK)
+        );
+    }
+}
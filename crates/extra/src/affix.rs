@@ -2,8 +2,6 @@
 //!
 //! Read more: <https://salvo.rs>
 
-use std::any::TypeId;
-
 use salvo_core::handler;
 use salvo_core::prelude::*;
 
@@ -25,10 +23,20 @@ where
     }
 }
 
+struct InjectCell<V>(V);
+impl<T> Affix for InjectCell<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    fn attach(&self, depot: &mut Depot) {
+        depot.inject(self.0.clone());
+    }
+}
+
 /// Inject a value into depot.
 #[inline]
 pub fn inject<V: Send + Sync + Clone + 'static>(value: V) -> AffixList {
-    insert(format!("{:?}", TypeId::of::<V>()), value)
+    AffixList::new().inject(value)
 }
 
 /// Insert a key-value pair into depot.
@@ -52,8 +60,9 @@ impl AffixList {
         AffixList(Vec::new())
     }
     /// Inject a value into depot.
-    pub fn inject<V: Send + Sync + Clone + 'static>(self, value: V) -> Self {
-        self.insert(format!("{:?}", TypeId::of::<V>()), value)
+    pub fn inject<V: Send + Sync + Clone + 'static>(mut self, value: V) -> Self {
+        self.0.push(Box::new(InjectCell(value)));
+        self
     }
 
     /// Insert a key-value pair into depot.
@@ -106,4 +115,25 @@ mod tests {
             .await;
         assert_eq!(content.unwrap(), "salvo:powerful");
     }
+
+    #[tokio::test]
+    async fn test_affix_list_inject() {
+        #[handler]
+        async fn count(depot: &mut Depot) -> String {
+            let count = depot.obtain::<u32>().copied().unwrap_or_default();
+            let names = depot
+                .obtain::<Vec<&'static str>>()
+                .map(|names| names.join(","))
+                .unwrap_or_default();
+            format!("{count}:{names}")
+        }
+        let affix = AffixList::new().inject(3u32).inject(vec!["a", "b"]);
+        let router = Router::with_hoop(affix).goal(count);
+        let content = TestClient::get("http://127.0.0.1:5800/")
+            .send(router)
+            .await
+            .take_string()
+            .await;
+        assert_eq!(content.unwrap(), "3:a,b");
+    }
 }
@@ -0,0 +1,297 @@
+//! API key authentication middleware.
+//!
+//! [`ApiKeyAuth`] reads the API key of a request from a header, `X-API-Key` by default, or optionally from a query
+//! parameter, and looks it up in a [`KeyStore`]. The store returns the [`ApiKeyInfo`] of the key, its owner and
+//! scopes, which is inserted into the [`Depot`] and read with [`ApiKeyDepotExt::api_key_info`].
+//!
+//! `KeyStore` is implemented for a `HashMap<String, ApiKeyInfo>` of static keys and for async closures, usually
+//! querying a database. Requests without a key or with an unknown key are rejected with `401 Unauthorized`, requests
+//! with a key missing one of the required scopes with `403 Forbidden`, and a failing store results in
+//! `500 Internal Server Error`.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! use salvo_core::prelude::*;
+//! use salvo_extra::api_key_auth::{ApiKeyAuth, ApiKeyDepotExt, ApiKeyInfo};
+//!
+//! #[handler]
+//! async fn hello(depot: &mut Depot) -> String {
+//!     format!("Hello {}", depot.api_key_info().unwrap().owner)
+//! }
+//!
+//! let keys = HashMap::from([("secret".to_owned(), ApiKeyInfo::new("alice").scopes(["read"]))]);
+//! let router = Router::with_path("reports").hoop(ApiKeyAuth::new(keys).required_scopes(["read"])).get(hello);
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+
+use salvo_core::http::header::HeaderName;
+use salvo_core::http::{Request, Response, StatusError};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// Key for the [`ApiKeyInfo`] of the request in depot.
+pub const API_KEY_INFO_KEY: &str = "::salvo::api_key_auth::info";
+
+/// Default header the API key is read from.
+pub const DEFAULT_HEADER_NAME: &str = "x-api-key";
+
+/// Metadata of an API key.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ApiKeyInfo {
+    /// Owner of the key, like a user name or a client id.
+    pub owner: String,
+    /// Scopes granted to the key.
+    pub scopes: Vec<String>,
+}
+
+impl ApiKeyInfo {
+    /// Create a new `ApiKeyInfo` without scopes.
+    #[inline]
+    pub fn new(owner: impl Into<String>) -> Self {
+        ApiKeyInfo {
+            owner: owner.into(),
+            scopes: vec![],
+        }
+    }
+
+    /// Sets the scopes granted to the key.
+    #[inline]
+    pub fn scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns `true` if the key is granted the scope.
+    #[inline]
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+/// Store looking up API keys.
+///
+/// It is implemented for `HashMap<String, ApiKeyInfo>` and for closures taking the key and returning a future of
+/// `Result<Option<ApiKeyInfo>, E>` where `E: Display`.
+#[async_trait]
+pub trait KeyStore: Send + Sync + 'static {
+    /// Finds the metadata of a key, returns `Ok(None)` if the key is unknown or revoked.
+    async fn find(&self, key: &str) -> Result<Option<ApiKeyInfo>, String>;
+}
+
+#[async_trait]
+impl KeyStore for HashMap<String, ApiKeyInfo> {
+    async fn find(&self, key: &str) -> Result<Option<ApiKeyInfo>, String> {
+        Ok(self.get(key).cloned())
+    }
+}
+
+#[async_trait]
+impl<F, Fut, E> KeyStore for F
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Option<ApiKeyInfo>, E>> + Send,
+    E: Display,
+{
+    async fn find(&self, key: &str) -> Result<Option<ApiKeyInfo>, String> {
+        self(key.to_owned()).await.map_err(|e| e.to_string())
+    }
+}
+
+/// Extension of [`Depot`] to get the API key metadata inserted by [`ApiKeyAuth`].
+pub trait ApiKeyDepotExt {
+    /// Get the metadata of the API key of the request.
+    fn api_key_info(&self) -> Option<&ApiKeyInfo>;
+}
+
+impl ApiKeyDepotExt for Depot {
+    #[inline]
+    fn api_key_info(&self) -> Option<&ApiKeyInfo> {
+        self.get(API_KEY_INFO_KEY).ok()
+    }
+}
+
+/// Middleware authenticating requests with API keys, see the [module documentation](self).
+pub struct ApiKeyAuth<S> {
+    store: S,
+    header_names: Vec<HeaderName>,
+    query_param: Option<String>,
+    required_scopes: Vec<String>,
+}
+
+impl<S: KeyStore> ApiKeyAuth<S> {
+    /// Create a new `ApiKeyAuth` looking up keys in `store`.
+    #[inline]
+    pub fn new(store: S) -> Self {
+        ApiKeyAuth {
+            store,
+            header_names: vec![HeaderName::from_static(DEFAULT_HEADER_NAME)],
+            query_param: None,
+            required_scopes: vec![],
+        }
+    }
+
+    /// Sets the headers the key is read from, defaults to `X-API-Key`.
+    #[inline]
+    pub fn header_names(mut self, header_names: impl Into<Vec<HeaderName>>) -> Self {
+        self.header_names = header_names.into();
+        self
+    }
+
+    /// Sets the query parameter the key is read from when no header contains it.
+    ///
+    /// Disabled by default, keys in urls are easily leaked in logs and browser histories.
+    #[inline]
+    pub fn query_param(mut self, name: impl Into<String>) -> Self {
+        self.query_param = Some(name.into());
+        self
+    }
+
+    /// Sets the scopes the key must be granted, requests with a key missing one of them are rejected with
+    /// `403 Forbidden`.
+    #[inline]
+    pub fn required_scopes<I, T>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.required_scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    fn find_key(&self, req: &Request) -> Option<String> {
+        self.header_names
+            .iter()
+            .filter_map(|name| req.headers().get(name))
+            .filter_map(|value| value.to_str().ok())
+            .map(str::trim)
+            .find(|key| !key.is_empty())
+            .map(ToOwned::to_owned)
+            .or_else(|| {
+                self.query_param
+                    .as_deref()
+                    .and_then(|name| req.query::<String>(name))
+                    .filter(|key| !key.is_empty())
+            })
+    }
+}
+
+#[async_trait]
+impl<S: KeyStore> Handler for ApiKeyAuth<S> {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let Some(key) = self.find_key(req) else {
+            res.render(StatusError::unauthorized().brief("Missing API key."));
+            ctrl.skip_rest();
+            return;
+        };
+        let info = match self.store.find(&key).await {
+            Ok(Some(info)) => info,
+            Ok(None) => {
+                res.render(StatusError::unauthorized().brief("Invalid API key."));
+                ctrl.skip_rest();
+                return;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "api key store failed");
+                res.render(StatusError::internal_server_error());
+                ctrl.skip_rest();
+                return;
+            }
+        };
+        if let Some(scope) = self.required_scopes.iter().find(|scope| !info.has_scope(scope)) {
+            res.render(StatusError::forbidden().brief(format!("API key is missing the required scope `{scope}`.")));
+            ctrl.skip_rest();
+            return;
+        }
+        depot.insert(API_KEY_INFO_KEY, info);
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn hello(depot: &mut Depot) -> String {
+        format!("Hello {}", depot.api_key_info().unwrap().owner)
+    }
+
+    #[tokio::test]
+    async fn test_api_key_auth() {
+        let keys = HashMap::from([
+            ("k1".to_owned(), ApiKeyInfo::new("alice").scopes(["read", "write"])),
+            ("k2".to_owned(), ApiKeyInfo::new("bob").scopes(["read"])),
+        ]);
+        let auth = ApiKeyAuth::new(keys).query_param("api_key").required_scopes(["write"]);
+        let service = Service::new(Router::new().hoop(auth).goal(hello));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-api-key", "k1", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "Hello alice");
+
+        let res = TestClient::get("http://127.0.0.1:5801?api_key=k1").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let res = TestClient::get("http://127.0.0.1:5801").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-api-key", "unknown", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-api-key", "k2", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_auth_callback() {
+        let store = |key: String| async move {
+            match &*key {
+                "k1" => Ok(Some(ApiKeyInfo::new("alice"))),
+                "broken" => Err("database unavailable"),
+                _ => Ok(None),
+            }
+        };
+        let service = Service::new(Router::new().hoop(ApiKeyAuth::new(store)).goal(hello));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-api-key", "k1", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "Hello alice");
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-api-key", "k2", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-api-key", "broken", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
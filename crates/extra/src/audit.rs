@@ -0,0 +1,424 @@
+//! Structured audit logging middleware.
+//!
+//! Routes are annotated with audit metadata by adding an [`audited`] hoop, naming the audited action and the path
+//! params identifying the affected resource. The [`AuditLog`] middleware, added to the [`Service`](salvo_core::Service)
+//! or a parent router, emits an [`AuditEvent`] to an [`AuditSink`] for every request handled by an annotated route,
+//! recording who did what on which resource and the result. Requests of routes without annotation are not
+//! recorded. Requests which do not complete, because they are cancelled or their handler panics, are still recorded
+//! with the [`Incomplete`](AuditOutcome::Incomplete) outcome.
+//!
+//! [`JsonFileSink`] appends events to a file as JSON lines, custom sinks can be implemented with the [`AuditSink`]
+//! trait, which is implemented for closures.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use salvo_core::prelude::*;
+//! use salvo_extra::audit::{audited, AuditLog, JsonFileSink};
+//!
+//! #[handler]
+//! async fn delete_user() {}
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let sink = JsonFileSink::open("audit.log").await.unwrap();
+//! let audit_log = AuditLog::new(sink).actor(|req: &Request, _depot: &Depot| req.header::<String>("x-user"));
+//! let router = Router::with_path("users/<id>")
+//!     .hoop(audited("user.delete").resource_param("id"))
+//!     .delete(delete_user);
+//! let service = Service::new(router).hoop(audit_log);
+//! # }
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::BTreeMap;
+use std::io::Result as IoResult;
+use std::path::Path;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use salvo_core::http::{Request, Response, StatusCode};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// Outcome of an audited request.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    /// The response status is not an error.
+    Success,
+    /// The response status is a client or server error.
+    Failure,
+    /// The request was cancelled, like when the client disconnected, or its handler panicked.
+    Incomplete,
+}
+
+/// An audit event, emitted by [`AuditLog`] for requests handled by an [`audited`] route.
+#[derive(Serialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct AuditEvent {
+    /// Milliseconds since the unix epoch when the request was received.
+    pub timestamp_ms: u64,
+    /// Who made the request, as returned by the [`actor`](AuditLog::actor) extractor, unknown for incomplete
+    /// requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// Audited action, like `user.delete`.
+    pub action: String,
+    /// Method of the request.
+    pub method: String,
+    /// Matched route pattern of the request, like `/users/<id>`.
+    pub route: String,
+    /// Path of the request.
+    pub path: String,
+    /// Path params identifying the affected resource.
+    pub resource: BTreeMap<String, String>,
+    /// Status code of the response, `499` if the request was cancelled and `500` if its handler panicked.
+    pub status: u16,
+    /// Outcome of the request.
+    pub outcome: AuditOutcome,
+    /// Remote address of the client.
+    pub remote_addr: String,
+}
+
+/// Destination of audit events.
+#[async_trait]
+pub trait AuditSink: Send + Sync + 'static {
+    /// Records an audit event.
+    async fn emit(&self, event: &AuditEvent);
+}
+
+#[async_trait]
+impl<F> AuditSink for F
+where
+    F: Fn(&AuditEvent) + Send + Sync + 'static,
+{
+    async fn emit(&self, event: &AuditEvent) {
+        self(event)
+    }
+}
+
+/// [`AuditSink`] appending events to a file, one JSON object per line.
+#[derive(Debug)]
+pub struct JsonFileSink {
+    file: Mutex<File>,
+}
+impl JsonFileSink {
+    /// Opens the file in append mode, creating it if it does not exist.
+    pub async fn open(path: impl AsRef<Path>) -> IoResult<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+}
+#[async_trait]
+impl AuditSink for JsonFileSink {
+    async fn emit(&self, event: &AuditEvent) {
+        let mut line = match serde_json::to_vec(event) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!(error = ?e, "serialize audit event failed");
+                return;
+            }
+        };
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        if let Err(e) = file.write_all(&line).await {
+            tracing::error!(error = ?e, "write audit event failed");
+        } else if let Err(e) = file.flush().await {
+            tracing::error!(error = ?e, "flush audit event failed");
+        }
+    }
+}
+
+/// Audit metadata of a route, created by [`audited`] and added to the route as a hoop.
+#[derive(Clone, Debug)]
+pub struct Audited {
+    action: String,
+    resource_params: Vec<String>,
+}
+impl Audited {
+    /// Adds a path param identifying the affected resource, like `id` for `/users/<id>`.
+    #[inline]
+    pub fn resource_param(mut self, name: impl Into<String>) -> Self {
+        self.resource_params.push(name.into());
+        self
+    }
+}
+
+/// Annotates a route with the audited `action`, see the [module documentation](self).
+#[inline]
+pub fn audited(action: impl Into<String>) -> Audited {
+    Audited {
+        action: action.into(),
+        resource_params: vec![],
+    }
+}
+
+#[async_trait]
+impl Handler for Audited {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, _res: &mut Response, _ctrl: &mut FlowCtrl) {
+        if let Ok(scope) = depot.obtain::<AuditScope>() {
+            let matched = MatchedRoute {
+                action: self.action.clone(),
+                route: req.matched_path().unwrap_or_default().to_owned(),
+                resource: self
+                    .resource_params
+                    .iter()
+                    .filter_map(|name| Some((name.clone(), req.params().get(name)?.clone())))
+                    .collect(),
+            };
+            *scope.0.lock().expect("lock should not be poisoned") = Some(matched);
+        }
+        depot.inject(self.clone());
+    }
+}
+
+/// Audit metadata of the route matching a request, recorded by the [`audited`] hoop.
+#[derive(Debug)]
+struct MatchedRoute {
+    action: String,
+    route: String,
+    resource: BTreeMap<String, String>,
+}
+
+/// Shared by [`AuditLog`] with the [`audited`] hoop, so the matched route is known when the request does not
+/// complete.
+#[derive(Clone, Default, Debug)]
+struct AuditScope(Arc<StdMutex<Option<MatchedRoute>>>);
+impl AuditScope {
+    fn take(&self) -> Option<MatchedRoute> {
+        self.0.lock().expect("lock should not be poisoned").take()
+    }
+}
+
+/// Parts of an [`AuditEvent`] known before the request is handled, the event is emitted with the
+/// [`Incomplete`](AuditOutcome::Incomplete) outcome if it is dropped before the request completes.
+struct PendingEvent<S: AuditSink> {
+    sink: Arc<S>,
+    scope: AuditScope,
+    timestamp_ms: u64,
+    method: String,
+    path: String,
+    remote_addr: String,
+    completed: bool,
+}
+impl<S: AuditSink> PendingEvent<S> {
+    fn event(&self, matched: MatchedRoute, actor: Option<String>, status: u16, outcome: AuditOutcome) -> AuditEvent {
+        AuditEvent {
+            timestamp_ms: self.timestamp_ms,
+            actor,
+            action: matched.action,
+            method: self.method.clone(),
+            route: matched.route,
+            path: self.path.clone(),
+            resource: matched.resource,
+            status,
+            outcome,
+            remote_addr: self.remote_addr.clone(),
+        }
+    }
+}
+impl<S: AuditSink> Drop for PendingEvent<S> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let Some(matched) = self.scope.take() else {
+            return;
+        };
+        let status = if std::thread::panicking() { 500 } else { 499 };
+        let event = self.event(matched, None, status, AuditOutcome::Incomplete);
+        let sink = self.sink.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move { sink.emit(&event).await });
+            }
+            Err(_) => tracing::error!(action = %event.action, "audit event not emitted, no runtime"),
+        }
+    }
+}
+
+/// Extracts who made a request, see [`AuditLog::actor`].
+pub trait ActorExtractor: Send + Sync + 'static {
+    /// Returns the actor of the request, like a user name.
+    fn extract(&self, req: &Request, depot: &Depot) -> Option<String>;
+}
+impl<F> ActorExtractor for F
+where
+    F: Fn(&Request, &Depot) -> Option<String> + Send + Sync + 'static,
+{
+    fn extract(&self, req: &Request, depot: &Depot) -> Option<String> {
+        self(req, depot)
+    }
+}
+
+/// Middleware emitting [`AuditEvent`]s for [`audited`] routes, see the [module documentation](self).
+pub struct AuditLog<S> {
+    sink: Arc<S>,
+    actor: Option<Box<dyn ActorExtractor>>,
+}
+impl<S: AuditSink> AuditLog<S> {
+    /// Create a new `AuditLog` emitting events to `sink`.
+    #[inline]
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            actor: None,
+        }
+    }
+
+    /// Sets the extractor of who made a request, it is called after the request is handled, so it can read the
+    /// depot values set by authentication middlewares.
+    #[inline]
+    pub fn actor(mut self, actor: impl ActorExtractor) -> Self {
+        self.actor = Some(Box::new(actor));
+        self
+    }
+}
+
+#[async_trait]
+impl<S: AuditSink> Handler for AuditLog<S> {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let scope = AuditScope::default();
+        depot.inject(scope.clone());
+        // The event is emitted by the guard if the request is cancelled or the handler panics.
+        let mut pending = PendingEvent {
+            sink: self.sink.clone(),
+            scope,
+            timestamp_ms,
+            method: req.method().to_string(),
+            path: req.uri().path().to_owned(),
+            remote_addr: req.remote_addr().to_string(),
+            completed: false,
+        };
+        ctrl.call_next(req, depot, res).await;
+        pending.completed = true;
+
+        let Some(matched) = pending.scope.take() else {
+            return;
+        };
+        let status = res.status_code.unwrap_or(StatusCode::OK);
+        let outcome = if status.is_client_error() || status.is_server_error() {
+            AuditOutcome::Failure
+        } else {
+            AuditOutcome::Success
+        };
+        let actor = self.actor.as_ref().and_then(|actor| actor.extract(req, depot));
+        let event = pending.event(matched, actor, status.as_u16(), outcome);
+        self.sink.emit(&event).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[handler]
+    async fn delete_user(req: &mut Request, res: &mut Response) {
+        if req.param::<String>("id").as_deref() == Some("0") {
+            res.render(StatusError::not_found());
+        }
+    }
+
+    #[handler]
+    async fn list_users() -> &'static str {
+        "[]"
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let events = Arc::new(StdMutex::new(Vec::<AuditEvent>::new()));
+        let sink = {
+            let events = events.clone();
+            move |event: &AuditEvent| events.lock().unwrap().push(event.clone())
+        };
+        let audit_log = AuditLog::new(sink).actor(|req: &Request, _depot: &Depot| req.header::<String>("x-user"));
+        let router = Router::with_path("users").get(list_users).push(
+            Router::with_path("<id>")
+                .hoop(audited("user.delete").resource_param("id"))
+                .delete(delete_user),
+        );
+        let service = Service::new(router).hoop(audit_log);
+
+        TestClient::get("http://127.0.0.1:5801/users").send(&service).await;
+        TestClient::delete("http://127.0.0.1:5801/users/42")
+            .add_header("x-user", "alice", true)
+            .send(&service)
+            .await;
+        TestClient::delete("http://127.0.0.1:5801/users/0").send(&service).await;
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].actor.as_deref(), Some("alice"));
+        assert_eq!(events[0].action, "user.delete");
+        assert_eq!(events[0].method, "DELETE");
+        assert_eq!(events[0].route, "/users/<id>");
+        assert_eq!(events[0].resource["id"], "42");
+        assert_eq!(events[0].outcome, AuditOutcome::Success);
+        assert_eq!(events[1].actor, None);
+        assert_eq!(events[1].status, 404);
+        assert_eq!(events[1].outcome, AuditOutcome::Failure);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_cancelled() {
+        #[handler]
+        async fn slow() {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+
+        let events = Arc::new(StdMutex::new(Vec::<AuditEvent>::new()));
+        let sink = {
+            let events = events.clone();
+            move |event: &AuditEvent| events.lock().unwrap().push(event.clone())
+        };
+        let router = Router::with_path("users/<id>")
+            .hoop(audited("user.delete").resource_param("id"))
+            .delete(slow);
+        let service = Service::new(router).hoop(AuditLog::new(sink));
+
+        let request = TestClient::delete("http://127.0.0.1:5801/users/42").send(&service);
+        assert!(tokio::time::timeout(Duration::from_millis(50), request).await.is_err());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].resource["id"], "42");
+        assert_eq!(events[0].status, 499);
+        assert_eq!(events[0].outcome, AuditOutcome::Incomplete);
+    }
+
+    #[tokio::test]
+    async fn test_json_file_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let router = Router::with_path("users/<id>")
+            .hoop(audited("user.delete").resource_param("id"))
+            .delete(delete_user);
+        let service = Service::new(router).hoop(AuditLog::new(JsonFileSink::open(&path).await.unwrap()));
+        TestClient::delete("http://127.0.0.1:5801/users/1").send(&service).await;
+        TestClient::delete("http://127.0.0.1:5801/users/2").send(&service).await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let event: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(event["action"], "user.delete");
+        assert_eq!(event["resource"]["id"], "2");
+        assert_eq!(event["outcome"], "success");
+        assert!(event.get("actor").is_none());
+    }
+}
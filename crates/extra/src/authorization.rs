@@ -0,0 +1,520 @@
+//! Permission based authorization middleware.
+//!
+//! Routes declare the permissions they require by adding a [`requires`] hoop. The permissions are checked against
+//! the authenticated [`Principal`] of the request by the [`PermissionChecker`] of the [`Authorization`] middleware,
+//! added to the [`Service`](salvo_core::Service) or a parent router. Requests without a principal are rejected with
+//! `401 Unauthorized`, and requests whose principal lacks a required permission with `403 Forbidden`.
+//!
+//! The principal is set in the [`Depot`] by authentication middlewares or hoops of the application with
+//! [`AuthorizationDepotExt::set_principal`], or built from depot values set by other middlewares with
+//! [`Authorization::principal`]. [`RolePermissions`] grants permissions to the roles of principals, custom checkers
+//! can be implemented with the [`PermissionChecker`] trait, which is implemented for async closures.
+//!
+//! Checks depending on the resource, like the ownership of a post, are done in handlers with [`ensure_owner`] and
+//! [`ensure_owner_or_permission`].
+//!
+//! # Example
+//!
+//! ```
+//! use salvo_core::prelude::*;
+//! use salvo_extra::authorization::{requires, Authorization, AuthorizationDepotExt, Principal, RolePermissions};
+//!
+//! #[handler]
+//! async fn authenticate(req: &mut Request, depot: &mut Depot) {
+//!     if let Some(user) = req.header::<String>("x-user") {
+//!         depot.set_principal(Principal::new(user).roles(["editor"]));
+//!     }
+//! }
+//!
+//! #[handler]
+//! async fn create_post() {}
+//!
+//! let checker = RolePermissions::new().role("editor", ["posts.*"]).role("admin", ["*"]);
+//! let router = Router::with_path("posts")
+//!     .hoop(authenticate)
+//!     .hoop(requires(["posts.create"]))
+//!     .post(create_post);
+//! let service = Service::new(router).hoop(Authorization::new(checker));
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+
+use salvo_core::http::{Request, Response, StatusError};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// Key for the [`Principal`] of the request in depot.
+pub const PRINCIPAL_KEY: &str = "::salvo::authorization::principal";
+
+/// The authenticated user or client of a request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Principal {
+    /// Identifier of the principal, compared to the owner of resources by [`ensure_owner`].
+    pub id: String,
+    /// Roles of the principal.
+    pub roles: Vec<String>,
+    /// Permissions granted directly to the principal.
+    pub permissions: Vec<String>,
+}
+
+impl Principal {
+    /// Create a new `Principal` without roles and permissions.
+    #[inline]
+    pub fn new(id: impl Into<String>) -> Self {
+        Principal {
+            id: id.into(),
+            roles: vec![],
+            permissions: vec![],
+        }
+    }
+
+    /// Sets the roles of the principal.
+    #[inline]
+    pub fn roles<I, S>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.roles = roles.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the permissions granted directly to the principal.
+    #[inline]
+    pub fn permissions<I, S>(mut self, permissions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.permissions = permissions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns `true` if the principal has the role.
+    #[inline]
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+/// Returns `true` if a granted permission covers the requested one.
+///
+/// A grant covers an equal permission, `*` covers every permission, and a grant ending with `.*` covers the
+/// permissions starting with its prefix, so `posts.*` covers `posts.create` and `posts.comments.delete`.
+pub fn permission_matches(grant: &str, permission: &str) -> bool {
+    if grant == "*" || grant == permission {
+        return true;
+    }
+    match grant.strip_suffix('*') {
+        Some(prefix) if prefix.ends_with('.') => permission.starts_with(prefix),
+        _ => false,
+    }
+}
+
+/// Checks the permissions of principals.
+///
+/// It is implemented for closures taking the principal and the permission and returning a future of
+/// `Result<bool, E>` where `E: Display`.
+#[async_trait]
+pub trait PermissionChecker: Send + Sync + 'static {
+    /// Returns `true` if the principal is granted the permission.
+    async fn has_permission(&self, principal: &Principal, permission: &str) -> Result<bool, String>;
+}
+
+#[async_trait]
+impl<F, Fut, E> PermissionChecker for F
+where
+    F: Fn(Principal, String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<bool, E>> + Send,
+    E: Display,
+{
+    async fn has_permission(&self, principal: &Principal, permission: &str) -> Result<bool, String> {
+        self(principal.clone(), permission.to_owned())
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// [`PermissionChecker`] granting permissions to roles.
+///
+/// The permissions granted directly to principals are also checked. Grants are matched with
+/// [`permission_matches`].
+#[derive(Clone, Debug, Default)]
+pub struct RolePermissions {
+    roles: HashMap<String, Vec<String>>,
+}
+
+impl RolePermissions {
+    /// Create a new `RolePermissions` without roles.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grants permissions to a role.
+    #[inline]
+    pub fn role<I, S>(mut self, role: impl Into<String>, permissions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.roles
+            .entry(role.into())
+            .or_default()
+            .extend(permissions.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns `true` if the principal is granted the permission.
+    pub fn grants(&self, principal: &Principal, permission: &str) -> bool {
+        principal
+            .roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+            .chain(&principal.permissions)
+            .any(|grant| permission_matches(grant, permission))
+    }
+}
+
+#[async_trait]
+impl PermissionChecker for RolePermissions {
+    async fn has_permission(&self, principal: &Principal, permission: &str) -> Result<bool, String> {
+        Ok(self.grants(principal, permission))
+    }
+}
+
+/// Extension of [`Depot`] to get and set the [`Principal`] of the request.
+pub trait AuthorizationDepotExt {
+    /// Get the principal of the request.
+    fn principal(&self) -> Option<&Principal>;
+    /// Sets the principal of the request, usually called by authentication hoops.
+    fn set_principal(&mut self, principal: Principal) -> &mut Self;
+}
+
+impl AuthorizationDepotExt for Depot {
+    #[inline]
+    fn principal(&self) -> Option<&Principal> {
+        self.get(PRINCIPAL_KEY).ok()
+    }
+    #[inline]
+    fn set_principal(&mut self, principal: Principal) -> &mut Self {
+        self.insert(PRINCIPAL_KEY, principal)
+    }
+}
+
+type PrincipalExtractor = dyn Fn(&Depot) -> Option<Principal> + Send + Sync;
+
+struct AuthorizationInner {
+    checker: Box<dyn PermissionChecker>,
+    extractor: Option<Box<PrincipalExtractor>>,
+}
+
+/// Middleware providing the [`PermissionChecker`] to [`requires`] hoops and the ensure functions, see the
+/// [module documentation](self).
+#[derive(Clone)]
+pub struct Authorization {
+    inner: Arc<AuthorizationInner>,
+}
+
+impl Authorization {
+    /// Create a new `Authorization` checking permissions with `checker`.
+    #[inline]
+    pub fn new(checker: impl PermissionChecker) -> Self {
+        Authorization {
+            inner: Arc::new(AuthorizationInner {
+                checker: Box::new(checker),
+                extractor: None,
+            }),
+        }
+    }
+
+    /// Sets the function building the principal from depot values set by authentication middlewares, like the
+    /// claims of a JWT, when no principal is set with [`AuthorizationDepotExt::set_principal`].
+    ///
+    /// It is called when permissions are checked, after the authentication hoops of the route are run.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Authorization` is cloned.
+    #[inline]
+    pub fn principal(mut self, extractor: impl Fn(&Depot) -> Option<Principal> + Send + Sync + 'static) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("principal extractor should be set before `Authorization` is cloned")
+            .extractor = Some(Box::new(extractor));
+        self
+    }
+}
+
+#[async_trait]
+impl Handler for Authorization {
+    async fn handle(&self, _req: &mut Request, depot: &mut Depot, _res: &mut Response, _ctrl: &mut FlowCtrl) {
+        depot.inject(self.clone());
+    }
+}
+
+fn resolve_principal(depot: &mut Depot) -> Option<Principal> {
+    if let Some(principal) = depot.principal() {
+        return Some(principal.clone());
+    }
+    let principal = depot
+        .obtain::<Authorization>()
+        .ok()
+        .and_then(|authorization| authorization.inner.extractor.as_ref())
+        .and_then(|extractor| extractor(depot))?;
+    depot.set_principal(principal.clone());
+    Some(principal)
+}
+
+async fn check_permission(depot: &Depot, principal: &Principal, permission: &str) -> Result<bool, StatusError> {
+    let Ok(authorization) = depot.obtain::<Authorization>() else {
+        tracing::error!("`Authorization` middleware is not added, permissions can not be checked");
+        return Err(StatusError::internal_server_error());
+    };
+    authorization
+        .inner
+        .checker
+        .has_permission(principal, permission)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, permission, "permission check failed");
+            StatusError::internal_server_error()
+        })
+}
+
+fn unauthenticated() -> StatusError {
+    StatusError::unauthorized().brief("Authentication required.")
+}
+
+fn forbidden(permission: &str) -> StatusError {
+    StatusError::forbidden().brief(format!("Missing the required permission `{permission}`."))
+}
+
+/// Ensures the principal of the request is granted the permission.
+pub async fn ensure_permission(depot: &mut Depot, permission: &str) -> Result<(), StatusError> {
+    let principal = resolve_principal(depot).ok_or_else(unauthenticated)?;
+    if check_permission(depot, &principal, permission).await? {
+        Ok(())
+    } else {
+        Err(forbidden(permission))
+    }
+}
+
+/// Ensures the principal of the request is the owner of a resource, identified by its id.
+pub fn ensure_owner(depot: &mut Depot, owner: &str) -> Result<(), StatusError> {
+    let principal = resolve_principal(depot).ok_or_else(unauthenticated)?;
+    if principal.id == owner {
+        Ok(())
+    } else {
+        Err(StatusError::forbidden().brief("Only the owner can access this resource."))
+    }
+}
+
+/// Ensures the principal of the request is the owner of a resource or is granted the permission, like moderators
+/// editing the posts of other users.
+pub async fn ensure_owner_or_permission(depot: &mut Depot, owner: &str, permission: &str) -> Result<(), StatusError> {
+    let principal = resolve_principal(depot).ok_or_else(unauthenticated)?;
+    if principal.id == owner || check_permission(depot, &principal, permission).await? {
+        Ok(())
+    } else {
+        Err(forbidden(permission))
+    }
+}
+
+/// Permissions required by a route, created by [`requires`] and added to the route as a hoop.
+#[derive(Clone, Debug)]
+pub struct Requires {
+    permissions: Vec<String>,
+    any: bool,
+}
+
+impl Requires {
+    /// Requires only one of the permissions instead of all of them.
+    #[inline]
+    pub fn any(mut self) -> Self {
+        self.any = true;
+        self
+    }
+
+    async fn check(&self, depot: &mut Depot) -> Result<(), StatusError> {
+        let principal = resolve_principal(depot).ok_or_else(unauthenticated)?;
+        for permission in &self.permissions {
+            let granted = check_permission(depot, &principal, permission).await?;
+            if granted && self.any {
+                return Ok(());
+            } else if !granted && !self.any {
+                return Err(forbidden(permission));
+            }
+        }
+        if self.any && !self.permissions.is_empty() {
+            Err(StatusError::forbidden().brief(format!(
+                "Missing one of the required permissions `{}`.",
+                self.permissions.join("`, `")
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Declares the permissions required by a route, see the [module documentation](self).
+#[inline]
+pub fn requires<I, S>(permissions: I) -> Requires
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    Requires {
+        permissions: permissions.into_iter().map(Into::into).collect(),
+        any: false,
+    }
+}
+
+#[async_trait]
+impl Handler for Requires {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        match self.check(depot).await {
+            Ok(()) => ctrl.call_next(req, depot, res).await,
+            Err(e) => {
+                res.render(e);
+                ctrl.skip_rest();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[handler]
+    async fn authenticate(req: &mut Request, depot: &mut Depot) {
+        if let Some(user) = req.header::<String>("x-user") {
+            let role = req.header::<String>("x-role").unwrap_or_default();
+            depot.set_principal(Principal::new(user).roles([role]));
+        }
+    }
+
+    #[handler]
+    async fn create_post() -> &'static str {
+        "created"
+    }
+
+    #[handler]
+    async fn edit_post(req: &mut Request, depot: &mut Depot) -> Result<&'static str, StatusError> {
+        let author = req.param::<String>("author").unwrap_or_default();
+        ensure_owner_or_permission(depot, &author, "posts.moderate").await?;
+        Ok("edited")
+    }
+
+    #[test]
+    fn test_permission_matches() {
+        assert!(permission_matches("*", "posts.create"));
+        assert!(permission_matches("posts.create", "posts.create"));
+        assert!(permission_matches("posts.*", "posts.comments.delete"));
+        assert!(!permission_matches("posts.*", "postsx.create"));
+        assert!(!permission_matches("posts*", "posts.create"));
+        assert!(!permission_matches("posts.create", "posts.delete"));
+    }
+
+    #[tokio::test]
+    async fn test_requires() {
+        let checker = RolePermissions::new()
+            .role("editor", ["posts.create"])
+            .role("admin", ["*"]);
+        let router = Router::new().hoop(authenticate).push(
+            Router::with_path("posts")
+                .hoop(requires(["posts.create", "posts.publish"]).any())
+                .post(create_post)
+                .push(
+                    Router::with_path("drafts")
+                        .hoop(requires(["posts.create", "posts.drafts"]))
+                        .get(edit_post),
+                ),
+        );
+        let service = Service::new(router).hoop(Authorization::new(checker));
+
+        let res = TestClient::post("http://127.0.0.1:5801/posts").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+
+        let res = TestClient::post("http://127.0.0.1:5801/posts")
+            .add_header("x-user", "alice", true)
+            .add_header("x-role", "viewer", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        let res = TestClient::post("http://127.0.0.1:5801/posts")
+            .add_header("x-user", "alice", true)
+            .add_header("x-role", "editor", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let res = TestClient::get("http://127.0.0.1:5801/posts/drafts")
+            .add_header("x-user", "alice", true)
+            .add_header("x-role", "editor", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        let res = TestClient::get("http://127.0.0.1:5801/posts/drafts")
+            .add_header("x-user", "root", true)
+            .add_header("x-role", "admin", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_ownership() {
+        #[handler]
+        async fn set_user(req: &mut Request, depot: &mut Depot) {
+            if let Some(user) = req.header::<String>("x-user") {
+                depot.insert("user", user);
+            }
+        }
+        let checker = |principal: Principal, permission: String| async move {
+            Ok::<_, String>(principal.has_role("moderator") && permission == "posts.moderate")
+        };
+        let router = Router::with_path("posts/<author>").put(edit_post);
+        let service = Service::new(router)
+            .hoop(Authorization::new(checker).principal(|depot: &Depot| {
+                depot
+                    .get::<String>("user")
+                    .ok()
+                    .map(|user| Principal::new(user.clone()).roles([user.clone()]))
+            }))
+            .hoop(set_user);
+
+        let res = TestClient::put("http://127.0.0.1:5801/posts/alice")
+            .add_header("x-user", "alice", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let res = TestClient::put("http://127.0.0.1:5801/posts/alice")
+            .add_header("x-user", "bob", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        let res = TestClient::put("http://127.0.0.1:5801/posts/alice")
+            .add_header("x-user", "moderator", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let res = TestClient::put("http://127.0.0.1:5801/posts/alice")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -49,6 +49,13 @@ where
         }
     }
 
+    /// Sets the realm of the `WWW-Authenticate` challenge.
+    #[inline]
+    pub fn realm(mut self, realm: impl Into<String>) -> Self {
+        self.realm = realm.into();
+        self
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn set_header_names(mut self, header_names: impl Into<Vec<HeaderName>>) -> Self {
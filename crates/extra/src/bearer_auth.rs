@@ -0,0 +1,177 @@
+//! bearer auth middleware.
+//!
+//! Read more: <https://salvo.rs>
+use std::future::Future;
+
+use salvo_core::http::header::{HeaderName, AUTHORIZATION, WWW_AUTHENTICATE};
+use salvo_core::http::{HeaderValue, Request, Response, StatusCode};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// key used when insert into depot.
+pub const TOKEN_KEY: &str = "::salvo::bearer_auth::token";
+
+/// BearerAuthValidator
+pub trait BearerAuthValidator: Send + Sync {
+    /// Validate is that token is right.
+    fn validate(&self, token: &str, depot: &mut Depot) -> impl Future<Output = bool> + Send;
+}
+/// BearerAuthDepotExt
+pub trait BearerAuthDepotExt {
+    /// Get bearer auth token reference.
+    fn bearer_auth_token(&self) -> Option<&String>;
+}
+
+impl BearerAuthDepotExt for Depot {
+    fn bearer_auth_token(&self) -> Option<&String> {
+        self.get(TOKEN_KEY).ok()
+    }
+}
+
+/// BearerAuth
+///
+/// Validates the token in `Authorization: Bearer <token>` header (RFC 6750). Requests without a token
+/// or with an invalid token are rejected with `401 Unauthorized` and a `WWW-Authenticate` challenge.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::bearer_auth::{BearerAuth, BearerAuthValidator};
+///
+/// struct Validator;
+/// impl BearerAuthValidator for Validator {
+///     async fn validate(&self, token: &str, _depot: &mut Depot) -> bool {
+///         token == "secret"
+///     }
+/// }
+///
+/// let router = Router::with_path("health").hoop(BearerAuth::new(Validator).realm("internal"));
+/// ```
+pub struct BearerAuth<V: BearerAuthValidator> {
+    realm: String,
+    header_names: Vec<HeaderName>,
+    validator: V,
+}
+
+impl<V> BearerAuth<V>
+where
+    V: BearerAuthValidator,
+{
+    /// Create new `BearerAuth`.
+    #[inline]
+    pub fn new(validator: V) -> Self {
+        BearerAuth {
+            realm: "realm".to_owned(),
+            header_names: vec![AUTHORIZATION],
+            validator,
+        }
+    }
+
+    /// Sets the realm of the `WWW-Authenticate` challenge.
+    #[inline]
+    pub fn realm(mut self, realm: impl Into<String>) -> Self {
+        self.realm = realm.into();
+        self
+    }
+
+    /// Sets the headers the token is read from, defaults to `Authorization`.
+    #[inline]
+    pub fn header_names(mut self, header_names: impl Into<Vec<HeaderName>>) -> Self {
+        self.header_names = header_names.into();
+        self
+    }
+
+    fn find_token(&self, req: &Request) -> Option<String> {
+        self.header_names
+            .iter()
+            .filter_map(|name| req.headers().get(name))
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|value| {
+                let (scheme, token) = value.split_once(' ')?;
+                let token = token.trim();
+                (scheme.eq_ignore_ascii_case("Bearer") && !token.is_empty()).then(|| token.to_owned())
+            })
+    }
+
+    fn ask_token(&self, res: &mut Response, invalid: bool) {
+        let mut challenge = format!("Bearer realm={:?}", self.realm);
+        if invalid {
+            challenge.push_str(r#", error="invalid_token""#);
+        }
+        if let Ok(challenge) = HeaderValue::from_str(&challenge) {
+            res.headers_mut().insert(WWW_AUTHENTICATE, challenge);
+        }
+        res.status_code(StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[async_trait]
+impl<V> Handler for BearerAuth<V>
+where
+    V: BearerAuthValidator + 'static,
+{
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let Some(token) = self.find_token(req) else {
+            self.ask_token(res, false);
+            ctrl.skip_rest();
+            return;
+        };
+        if self.validator.validate(&token, depot).await {
+            depot.insert(TOKEN_KEY, token);
+            ctrl.call_next(req, depot, res).await;
+        } else {
+            self.ask_token(res, true);
+            ctrl.skip_rest();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn hello(depot: &mut Depot) -> String {
+        format!("Hello {}", depot.bearer_auth_token().unwrap())
+    }
+
+    struct Validator;
+    impl BearerAuthValidator for Validator {
+        async fn validate(&self, token: &str, _depot: &mut Depot) -> bool {
+            token == "secret"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bearer_auth() {
+        let auth_handler = BearerAuth::new(Validator).realm("internal");
+        let router = Router::with_hoop(auth_handler).goal(hello);
+        let service = Service::new(router);
+
+        let content = TestClient::get("http://127.0.0.1:5800/")
+            .bearer_auth("secret")
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert_eq!(content, "Hello secret");
+
+        let res = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(res.headers()[WWW_AUTHENTICATE], r#"Bearer realm="internal""#);
+
+        let res = TestClient::get("http://127.0.0.1:5800/")
+            .bearer_auth("wrong")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(
+            res.headers()[WWW_AUTHENTICATE],
+            r#"Bearer realm="internal", error="invalid_token""#
+        );
+    }
+}
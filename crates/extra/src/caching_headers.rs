@@ -1,219 +1,479 @@
-/*!
-# Salvo handlers for etag and last-modified-since headers.
-This crate provides three handlers: [`ETag`], [`Modified`], and
-[`CachingHeaders`].
-Unless you are sure that you _don't_ want either etag or last-modified
-behavior, please use the combined [`CachingHeaders`] handler.
- */
-
-use etag::EntityTag;
-use salvo_core::http::header::{ETAG, IF_NONE_MATCH};
-use salvo_core::http::headers::{self, HeaderMapExt};
-use salvo_core::http::{ResBody, StatusCode};
-use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
-
-/**
-# Etag and If-None-Match header handler
-
-Salvo handler that provides an outbound [`etag
-header`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag)
-after other handlers have been run, and if the request includes an
-[`if-none-match`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/If-None-Match)
-header, compares these values and sends a
-[`304 not modified`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/304) status,
-omitting the response body.
-
-## Streamed bodies
-
-Note that this handler does not currently provide an etag trailer for
-streamed bodies, but may do so in the future.
-
-## Strong vs weak comparison
-
-Etags can be compared using a strong method or a weak
-method. By default, this handler allows weak comparison. To change
-this setting, construct your handler with `Etag::new().strong()`.
-See [`etag::EntityTag`](https://docs.rs/etag/3.0.0/etag/struct.EntityTag.html#comparison)
-for further documentation.
-
-Read more: <https://salvo.rs>
-*/
-#[derive(Default, Clone, Copy, Debug)]
-pub struct ETag {
-    strong: bool,
-}
-
-impl ETag {
-    /// constructs a new Etag handler
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    /// Configures this handler to use strong content-based etag comparison only. See
-    /// [`etag::EntityTag`](https://docs.rs/etag/3.0.0/etag/struct.EntityTag.html#comparison)
-    /// for further documentation on the differences between strong
-    /// and weak etag comparison.
-    pub fn strong(mut self) -> Self {
-        self.strong = true;
-        self
-    }
-}
-
-#[async_trait]
-impl Handler for ETag {
-    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
-        ctrl.call_next(req, depot, res).await;
-        if ctrl.is_ceased() {
-            return;
-        }
-
-        let if_none_match = req
-            .headers()
-            .get(IF_NONE_MATCH)
-            .and_then(|etag| etag.to_str().ok())
-            .and_then(|etag| etag.parse::<EntityTag>().ok());
-
-        let etag = req
-            .headers()
-            .get(ETAG)
-            .and_then(|etag| etag.to_str().ok())
-            .and_then(|etag| etag.parse().ok())
-            .or_else(|| {
-                let etag = match &res.body {
-                    ResBody::Once(bytes) => Some(EntityTag::from_data(bytes)),
-                    ResBody::Chunks(bytes) => {
-                        let tags = bytes
-                            .iter()
-                            .map(|item| EntityTag::from_data(item).tag().to_owned())
-                            .collect::<Vec<_>>()
-                            .concat();
-                        Some(EntityTag::from_data(tags.as_bytes()))
-                    }
-                    ResBody::Stream(_) => {
-                        tracing::debug!("etag not supported for streaming body");
-                        None
-                    }
-                    ResBody::None => {
-                        tracing::debug!("etag not supported for empty body");
-                        None
-                    }
-                    _ => None,
-                };
-
-                if let Some(etag) = &etag {
-                    match etag.to_string().parse::<headers::ETag>() {
-                        Ok(etag) => res.headers_mut().typed_insert(etag),
-                        Err(e) => {
-                            tracing::error!(error = ?e, "failed to parse etag");
-                        }
-                    }
-                }
-                etag
-            });
-
-        if let (Some(etag), Some(if_none_match)) = (etag, if_none_match) {
-            let eq = if self.strong {
-                etag.strong_eq(&if_none_match)
-            } else {
-                etag.weak_eq(&if_none_match)
-            };
-
-            if eq {
-                res.body(ResBody::None);
-                res.status_code(StatusCode::NOT_MODIFIED);
-            }
-        }
-    }
-}
-
-/**
-# A handler for the `Last-Modified` and `If-Modified-Since` header interaction.
-
-This handler does not set a `Last-Modified` header on its own, but
-relies on other handlers doing so.
-*/
-#[derive(Clone, Debug, Copy, Default)]
-pub struct Modified {
-    _private: (),
-}
-
-impl Modified {
-    /// Constructs a new Modified handler
-    pub fn new() -> Self {
-        Self { _private: () }
-    }
-}
-
-#[async_trait]
-impl Handler for Modified {
-    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
-        ctrl.call_next(req, depot, res).await;
-        if ctrl.is_ceased() {
-            return;
-        }
-
-        if let (Some(if_modified_since), Some(last_modified)) = (
-            req.headers().typed_get::<headers::IfModifiedSince>(),
-            res.headers().typed_get::<headers::LastModified>(),
-        ) {
-            if !if_modified_since.is_modified(last_modified.into()) {
-                res.body(ResBody::None);
-                res.status_code(StatusCode::NOT_MODIFIED);
-            }
-        }
-    }
-}
-
-/**
-A combined handler that provides both [`ETag`] and [`Modified`] behavior.
-*/
-#[derive(Clone, Debug, Copy, Default)]
-pub struct CachingHeaders(Modified, ETag);
-
-impl CachingHeaders {
-    /// Constructs a new combination modified and etag handler
-    pub fn new() -> Self {
-        Self::default()
-    }
-}
-
-#[async_trait]
-impl Handler for CachingHeaders {
-    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
-        self.0.handle(req, depot, res, ctrl).await;
-        if res.status_code != Some(StatusCode::NOT_MODIFIED) {
-            self.1.handle(req, depot, res, ctrl).await;
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use salvo_core::http::header::*;
-    use salvo_core::prelude::*;
-    use salvo_core::test::TestClient;
-
-    use super::*;
-
-    #[handler]
-    async fn hello() -> &'static str {
-        "Hello World"
-    }
-
-    #[tokio::test]
-    async fn test_affix() {
-        let router = Router::with_hoop(CachingHeaders::new()).get(hello);
-        let service = Service::new(router);
-
-        let respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
-        assert_eq!(respone.status_code, Some(StatusCode::OK));
-
-        let etag = respone.headers().get(ETAG).unwrap();
-        let respone = TestClient::get("http://127.0.0.1:5800/")
-            .add_header(IF_NONE_MATCH, etag, true)
-            .send(&service)
-            .await;
-        assert_eq!(respone.status_code, Some(StatusCode::NOT_MODIFIED));
-        assert!(respone.body.is_none());
-    }
-}
+/*!
+# Salvo handlers for etag and last-modified-since headers.
+This crate provides three handlers: [`ETag`], [`Modified`], and
+[`CachingHeaders`], and a [`CacheControl`] handler assigning `cache-control` policies.
+Unless you are sure that you _don't_ want either etag or last-modified
+behavior, please use the combined [`CachingHeaders`] handler.
+ */
+
+use etag::EntityTag;
+use salvo_core::http::header::{HeaderValue, CACHE_CONTROL, ETAG, IF_NONE_MATCH, LAST_MODIFIED};
+use salvo_core::http::headers::{self, HeaderMapExt};
+use salvo_core::http::{ResBody, StatusCode};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+
+/**
+# Etag and If-None-Match header handler
+
+Salvo handler that provides an outbound [`etag
+header`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag)
+after other handlers have been run, and if the request includes an
+[`if-none-match`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/If-None-Match)
+header, compares these values and sends a
+[`304 not modified`](https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/304) status,
+omitting the response body.
+
+## Streamed bodies
+
+Note that this handler does not currently provide an etag trailer for
+streamed bodies, but may do so in the future.
+
+## Strong vs weak comparison
+
+Etags can be compared using a strong method or a weak
+method. By default, this handler allows weak comparison. To change
+this setting, construct your handler with `Etag::new().strong()`.
+See [`etag::EntityTag`](https://docs.rs/etag/3.0.0/etag/struct.EntityTag.html#comparison)
+for further documentation.
+
+Read more: <https://salvo.rs>
+*/
+#[derive(Default, Clone, Copy, Debug)]
+pub struct ETag {
+    strong: bool,
+}
+
+impl ETag {
+    /// constructs a new Etag handler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures this handler to use strong content-based etag comparison only. See
+    /// [`etag::EntityTag`](https://docs.rs/etag/3.0.0/etag/struct.EntityTag.html#comparison)
+    /// for further documentation on the differences between strong
+    /// and weak etag comparison.
+    pub fn strong(mut self) -> Self {
+        self.strong = true;
+        self
+    }
+}
+
+#[async_trait]
+impl Handler for ETag {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        ctrl.call_next(req, depot, res).await;
+        if ctrl.is_ceased() {
+            return;
+        }
+
+        let if_none_match = req
+            .headers()
+            .get(IF_NONE_MATCH)
+            .and_then(|etag| etag.to_str().ok())
+            .and_then(|etag| etag.parse::<EntityTag>().ok());
+
+        let etag = res
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .and_then(|etag| etag.parse().ok())
+            .or_else(|| {
+                let etag = match &res.body {
+                    ResBody::Once(bytes) => Some(EntityTag::from_data(bytes)),
+                    ResBody::Chunks(bytes) => {
+                        let tags = bytes
+                            .iter()
+                            .map(|item| EntityTag::from_data(item).tag().to_owned())
+                            .collect::<Vec<_>>()
+                            .concat();
+                        Some(EntityTag::from_data(tags.as_bytes()))
+                    }
+                    ResBody::Stream(_) => {
+                        tracing::debug!("etag not supported for streaming body");
+                        None
+                    }
+                    ResBody::None => {
+                        tracing::debug!("etag not supported for empty body");
+                        None
+                    }
+                    _ => None,
+                };
+
+                if let Some(etag) = &etag {
+                    match etag.to_string().parse::<headers::ETag>() {
+                        Ok(etag) => res.headers_mut().typed_insert(etag),
+                        Err(e) => {
+                            tracing::error!(error = ?e, "failed to parse etag");
+                        }
+                    }
+                }
+                etag
+            });
+
+        if let (Some(etag), Some(if_none_match)) = (etag, if_none_match) {
+            let eq = if self.strong {
+                etag.strong_eq(&if_none_match)
+            } else {
+                etag.weak_eq(&if_none_match)
+            };
+
+            if eq {
+                res.body(ResBody::None);
+                res.status_code(StatusCode::NOT_MODIFIED);
+            }
+        }
+    }
+}
+
+/**
+# A handler for the `Last-Modified` and `If-Modified-Since` header interaction.
+
+This handler does not set a `Last-Modified` header on its own, but
+relies on other handlers doing so.
+*/
+#[derive(Clone, Debug, Copy, Default)]
+pub struct Modified {
+    _private: (),
+}
+
+impl Modified {
+    /// Constructs a new Modified handler
+    pub fn new() -> Self {
+        Self { _private: () }
+    }
+}
+
+#[async_trait]
+impl Handler for Modified {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        ctrl.call_next(req, depot, res).await;
+        if ctrl.is_ceased() {
+            return;
+        }
+
+        if let (Some(if_modified_since), Some(last_modified)) = (
+            req.headers().typed_get::<headers::IfModifiedSince>(),
+            res.headers().typed_get::<headers::LastModified>(),
+        ) {
+            if !if_modified_since.is_modified(last_modified.into()) {
+                res.body(ResBody::None);
+                res.status_code(StatusCode::NOT_MODIFIED);
+            }
+        }
+    }
+}
+
+/**
+A combined handler that provides both [`ETag`] and [`Modified`] behavior.
+*/
+#[derive(Clone, Debug, Copy, Default)]
+pub struct CachingHeaders(Modified, ETag);
+
+impl CachingHeaders {
+    /// Constructs a new combination modified and etag handler
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Handler for CachingHeaders {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        self.0.handle(req, depot, res, ctrl).await;
+        if res.status_code != Some(StatusCode::NOT_MODIFIED) {
+            self.1.handle(req, depot, res, ctrl).await;
+        }
+    }
+}
+
+/// `cache-control` policy for fingerprinted assets which never change.
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// `cache-control` policy for responses which must not be stored.
+pub const NO_STORE: &str = "no-store";
+/// `cache-control` policy for responses which must be revalidated before use.
+pub const NO_CACHE: &str = "no-cache";
+
+#[derive(Clone, Debug)]
+enum CacheRule {
+    Path(String),
+    ContentType(String),
+}
+impl CacheRule {
+    fn is_match(&self, req: &Request, res: &Response) -> bool {
+        match self {
+            CacheRule::Path(pattern) => glob_match(pattern.as_bytes(), req.uri().path().as_bytes()),
+            CacheRule::ContentType(pattern) => res.content_type().is_some_and(|mime| {
+                let essence = mime.essence_str();
+                match pattern.strip_suffix("/*") {
+                    Some(kind) => mime.type_() == kind,
+                    None => essence == pattern,
+                }
+            }),
+        }
+    }
+}
+
+/// Matches `path` against `pattern`, `*` matches any characters except `/`, and `**` matches any characters.
+///
+/// On a mismatch, the last `*` consumes one more character, or when it can not cross a `/`, the last `**` does.
+/// A `**` makes the stars before it irrelevant, so the match takes at most `pattern.len() * path.len()` steps.
+fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Pattern index after the star and path index where the star match ends.
+    let mut star: Option<(usize, usize)> = None;
+    let mut globstar: Option<(usize, usize)> = None;
+    loop {
+        match pattern.get(p) {
+            Some(b'*') if pattern.get(p + 1) == Some(&b'*') => {
+                p += 2;
+                globstar = Some((p, i));
+                star = None;
+                continue;
+            }
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, i));
+                continue;
+            }
+            Some(c) if path.get(i) == Some(c) => {
+                p += 1;
+                i += 1;
+                continue;
+            }
+            None if i == path.len() => return true,
+            _ => {}
+        }
+        match (star, globstar) {
+            (Some((star_p, star_i)), _) if star_i < path.len() && path[star_i] != b'/' => {
+                star = Some((star_p, star_i + 1));
+                (p, i) = (star_p, star_i + 1);
+            }
+            (_, Some((star_p, star_i))) if star_i < path.len() => {
+                globstar = Some((star_p, star_i + 1));
+                star = None;
+                (p, i) = (star_p, star_i + 1);
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// # A handler assigning `cache-control` policies centrally.
+///
+/// Rules are checked in the order they are added, and the policy of the first matching rule is set on
+/// successful and `304 not modified` responses. Path rules are glob patterns matched against the request
+/// path, `*` matches a single path segment part and `**` matches anything. Content type rules match the
+/// response content type, `image/*` matches all images.
+///
+/// When no rule matches, responses carrying an `etag` or `last-modified` header get the
+/// [`revalidate`](CacheControl::revalidate) policy, `no-cache` by default, so clients always check for
+/// fresh content. A `cache-control` header set by the handlers is kept.
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::caching_headers::{CacheControl, CachingHeaders, IMMUTABLE, NO_STORE};
+///
+/// let cache_control = CacheControl::new()
+///     .path("/assets/**", IMMUTABLE)
+///     .path("/api/**", NO_STORE)
+///     .content_type("text/html", "no-cache");
+/// let router = Router::new().hoop(cache_control).hoop(CachingHeaders::new());
+/// ```
+#[derive(Clone, Debug)]
+pub struct CacheControl {
+    rules: Vec<(CacheRule, HeaderValue)>,
+    revalidate: Option<HeaderValue>,
+}
+impl Default for CacheControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CacheControl {
+    /// Constructs a new cache control handler without rules.
+    pub fn new() -> Self {
+        Self {
+            rules: vec![],
+            revalidate: Some(HeaderValue::from_static(NO_CACHE)),
+        }
+    }
+
+    /// Adds a rule setting `policy` for requests whose path matches the glob `pattern`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `policy` is not a valid header value.
+    pub fn path(mut self, pattern: impl Into<String>, policy: impl AsRef<str>) -> Self {
+        self.rules
+            .push((CacheRule::Path(pattern.into()), to_header_value(policy)));
+        self
+    }
+
+    /// Adds a rule setting `policy` for responses whose content type matches `content_type`,
+    /// for example `application/json` or `image/*`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `policy` is not a valid header value.
+    pub fn content_type(mut self, content_type: impl Into<String>, policy: impl AsRef<str>) -> Self {
+        self.rules
+            .push((CacheRule::ContentType(content_type.into()), to_header_value(policy)));
+        self
+    }
+
+    /// Sets the policy for unmatched responses with `etag` or `last-modified` header, `None` disables it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `policy` is not a valid header value.
+    pub fn revalidate(mut self, policy: Option<&str>) -> Self {
+        self.revalidate = policy.map(to_header_value);
+        self
+    }
+
+    fn policy(&self, req: &Request, res: &Response) -> Option<HeaderValue> {
+        self.rules
+            .iter()
+            .find(|(rule, _)| rule.is_match(req, res))
+            .map(|(_, policy)| policy.clone())
+            .or_else(|| {
+                let validated = res.headers().contains_key(ETAG) || res.headers().contains_key(LAST_MODIFIED);
+                self.revalidate.clone().filter(|_| validated)
+            })
+    }
+}
+
+fn to_header_value(policy: impl AsRef<str>) -> HeaderValue {
+    HeaderValue::from_str(policy.as_ref()).expect("cache control policy should be a valid header value")
+}
+
+#[async_trait]
+impl Handler for CacheControl {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        ctrl.call_next(req, depot, res).await;
+        if res.headers().contains_key(CACHE_CONTROL) {
+            return;
+        }
+        let status = res.status_code.unwrap_or(StatusCode::OK);
+        if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+            return;
+        }
+        if let Some(policy) = self.policy(req, res) {
+            res.headers_mut().insert(CACHE_CONTROL, policy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::http::header::*;
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[handler]
+    async fn hello() -> &'static str {
+        "Hello World"
+    }
+
+    #[tokio::test]
+    async fn test_affix() {
+        let router = Router::with_hoop(CachingHeaders::new()).get(hello);
+        let service = Service::new(router);
+
+        let respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        assert_eq!(respone.status_code, Some(StatusCode::OK));
+
+        let etag = respone.headers().get(ETAG).unwrap();
+        let respone = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(IF_NONE_MATCH, etag, true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::NOT_MODIFIED));
+        assert!(respone.body.is_none());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"/assets/**", b"/assets/js/app.1234.js"));
+        assert!(glob_match(b"/assets/*.js", b"/assets/app.js"));
+        assert!(!glob_match(b"/assets/*.js", b"/assets/js/app.js"));
+        assert!(glob_match(b"/api/*/items", b"/api/v1/items"));
+        assert!(!glob_match(b"/api/*", b"/apis"));
+        assert!(glob_match(b"/**/*.js", b"/assets/js/app.js"));
+        assert!(!glob_match(b"/**/*.js", b"/assets/app.js/index.html"));
+        assert!(glob_match(b"/a/**/b/*", b"/a/x/b/y/b/z"));
+
+        let path = format!("/{}", "a".repeat(64));
+        assert!(!glob_match(b"/*a*a*a*a*a*a*a*a*a*a*b", path.as_bytes()));
+        assert!(!glob_match(b"/**a**a**a**a**a**a**a**a**a**a**b", path.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_etag_of_response() {
+        #[handler]
+        async fn tagged(res: &mut Response) {
+            res.headers_mut().insert(ETAG, HeaderValue::from_static("\"v2\""));
+            res.render("tagged");
+        }
+        let service = Service::new(Router::with_hoop(ETag::new()).get(tagged));
+
+        // The `etag` sent by the client must not be taken for the etag of the response.
+        let res = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(ETAG, "\"v1\"", true)
+            .add_header(IF_NONE_MATCH, "\"v1\"", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.headers()[ETAG], "\"v2\"");
+
+        let res = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(IF_NONE_MATCH, "\"v2\"", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_MODIFIED));
+    }
+
+    #[tokio::test]
+    async fn test_cache_control() {
+        #[handler]
+        async fn json(res: &mut Response) {
+            res.render(Json("{}"));
+        }
+        #[handler]
+        async fn custom(res: &mut Response) {
+            res.headers_mut()
+                .insert(CACHE_CONTROL, HeaderValue::from_static("private"));
+            res.render("custom");
+        }
+
+        let router = Router::new()
+            .hoop(
+                CacheControl::new()
+                    .path("/assets/**", IMMUTABLE)
+                    .content_type("application/json", NO_STORE),
+            )
+            .hoop(CachingHeaders::new())
+            .push(Router::with_path("assets/<**>").get(hello))
+            .push(Router::with_path("json").get(json))
+            .push(Router::with_path("custom").get(custom))
+            .push(Router::with_path("hello").get(hello));
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5800/assets/app.js")
+            .send(&service)
+            .await;
+        assert_eq!(res.headers()[CACHE_CONTROL], IMMUTABLE);
+        let res = TestClient::get("http://127.0.0.1:5800/json").send(&service).await;
+        assert_eq!(res.headers()[CACHE_CONTROL], NO_STORE);
+        let res = TestClient::get("http://127.0.0.1:5800/custom").send(&service).await;
+        assert_eq!(res.headers()[CACHE_CONTROL], "private");
+        let res = TestClient::get("http://127.0.0.1:5800/hello").send(&service).await;
+        assert_eq!(res.headers()[CACHE_CONTROL], NO_CACHE);
+        let res = TestClient::get("http://127.0.0.1:5800/assets2").send(&service).await;
+        assert!(res.headers().get(CACHE_CONTROL).is_none());
+    }
+}
@@ -1,22 +1,72 @@
 //! Catch panic middleware.
 //!
 //! Read more: <https://salvo.rs>
+use std::any::Any;
+use std::fmt::{self, Debug, Formatter};
 use std::panic::AssertUnwindSafe;
 
 use futures_util::FutureExt;
 
 use salvo_core::http::{Request, Response, StatusError};
-use salvo_core::{async_trait, Depot, FlowCtrl, Error, Handler};
+use salvo_core::{async_trait, Depot, Error, FlowCtrl, Handler};
+
+type PanicCallback = Box<dyn Fn(&Request, &str) + Send + Sync>;
 
 /// This middleware catches panics and write `500 INTERNAL SERVER ERROR`
 /// into response. This middleware should be used as the first middleware.
-#[derive(Default, Debug)]
-pub struct CatchPanic {}
+///
+/// The panic message is logged with the request method and uri, and the error is rendered by the
+/// [`Catcher`](salvo_core::catcher::Catcher), so the connection is kept alive and the response looks
+/// like other server errors.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::catch_panic::CatchPanic;
+///
+/// let catch_panic = CatchPanic::new().on_panic(|req, message| {
+///     eprintln!("alert: {} {} panicked: {message}", req.method(), req.uri());
+/// });
+/// let service = Service::new(Router::new()).hoop(catch_panic);
+/// ```
+#[derive(Default)]
+pub struct CatchPanic {
+    on_panic: Option<PanicCallback>,
+}
+impl Debug for CatchPanic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchPanic")
+            .field("on_panic", &self.on_panic.is_some())
+            .finish()
+    }
+}
 impl CatchPanic {
     /// Create new `CatchPanic` middleware.
     #[inline]
     pub fn new() -> Self {
-        CatchPanic {}
+        CatchPanic { on_panic: None }
+    }
+
+    /// Sets a callback called with the request and the panic message after a panic is caught,
+    /// it can be used to send alerts.
+    #[inline]
+    pub fn on_panic<F>(mut self, on_panic: F) -> Self
+    where
+        F: Fn(&Request, &str) + Send + Sync + 'static,
+    {
+        self.on_panic = Some(Box::new(on_panic));
+        self
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic payload"
     }
 }
 
@@ -24,18 +74,32 @@ impl CatchPanic {
 impl Handler for CatchPanic {
     async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
         if let Err(e) = AssertUnwindSafe(ctrl.call_next(req, depot, res)).catch_unwind().await {
-            tracing::error!(error = ?e, "panic occurred");
+            let message = panic_message(&*e);
+            tracing::error!(
+                method = %req.method(),
+                uri = %req.uri(),
+                remote_addr = %req.remote_addr(),
+                payload = message,
+                "panic occurred"
+            );
+            if let Some(on_panic) = &self.on_panic {
+                on_panic(req, message);
+            }
             res.render(
                 StatusError::internal_server_error()
                     .brief("panic occurred on server")
-                    .cause(Error::other(format!("{e:#?}"))),
+                    .cause(Error::other(message.to_owned())),
             );
+            ctrl.skip_rest();
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
     use salvo_core::prelude::*;
     use salvo_core::test::{ResponseExt, TestClient};
     use tracing_test::traced_test;
@@ -62,4 +126,28 @@ mod tests {
             .unwrap();
         assert!(logs_contain("panic occurred"));
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_catch_panic_callback() {
+        #[handler]
+        async fn hello(req: &mut Request) -> &'static str {
+            panic!("user {} not found", req.param::<u32>("id").unwrap());
+        }
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let catch_panic = CatchPanic::new().on_panic(move |req, message| {
+            assert_eq!(req.uri().path(), "/users/7");
+            assert_eq!(message, "user 7 not found");
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let service = Service::new(Router::with_path("users/<id>").get(hello)).hoop(catch_panic);
+
+        let res = TestClient::get("http://127.0.0.1:5801/users/7").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::INTERNAL_SERVER_ERROR));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(logs_contain("payload=\"user 7 not found\""));
+        assert!(logs_contain("uri=http://127.0.0.1:5801/users/7"));
+    }
 }
@@ -1,31 +1,123 @@
 //! concurrency limiter middleware.
 //!
 //! Read more: <https://salvo.rs>
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
-use tokio::sync::Semaphore;
+use tokio::sync::{Semaphore, SemaphorePermit, TryAcquireError};
 
+use salvo_core::http::header::RETRY_AFTER;
 use salvo_core::http::StatusError;
 use salvo_core::http::{Request, Response};
 use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
 
 /// MaxConcurrency
+///
+/// Limits the number of requests handled concurrently. Requests exceeding the limit wait in a queue,
+/// when the queue is full or the wait times out, they are rejected with `503 Service Unavailable`.
+///
+/// The limit applies to the router which the hoop is added to, so different route subtrees can have
+/// different limits.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use salvo_core::prelude::*;
+/// use salvo_extra::concurrency_limiter::max_concurrency;
+///
+/// let limiter = max_concurrency(64)
+///     .max_queued(128)
+///     .queue_timeout(Duration::from_secs(5))
+///     .retry_after(Duration::from_secs(1));
+/// let router = Router::with_path("api").hoop(limiter);
+/// ```
 pub struct MaxConcurrency {
     semaphore: Semaphore,
+    max_queued: Option<usize>,
+    queued: AtomicUsize,
+    queue_timeout: Option<Duration>,
+    retry_after: Option<Duration>,
 }
+impl MaxConcurrency {
+    /// Create a new `MaxConcurrency`, by default the queue is unbounded and requests wait without timeout.
+    #[inline]
+    pub fn new(size: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(size),
+            max_queued: None,
+            queued: AtomicUsize::new(0),
+            queue_timeout: None,
+            retry_after: None,
+        }
+    }
+
+    /// Sets the maximum number of requests waiting for a free slot, `0` rejects requests immediately
+    /// when the limit is reached.
+    #[inline]
+    pub fn max_queued(mut self, max_queued: usize) -> Self {
+        self.max_queued = Some(max_queued);
+        self
+    }
+
+    /// Sets the maximum time a request waits in the queue.
+    #[inline]
+    pub fn queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the `Retry-After` header of rejected responses.
+    #[inline]
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        match self.semaphore.try_acquire() {
+            Ok(permit) => return Some(permit),
+            Err(TryAcquireError::Closed) => return None,
+            Err(TryAcquireError::NoPermits) => {}
+        }
+        let queued = self.queued.fetch_add(1, Ordering::AcqRel);
+        let _guard = QueuedGuard(&self.queued);
+        if self.max_queued.map(|max| queued >= max).unwrap_or(false) {
+            return None;
+        }
+        match self.queue_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.semaphore.acquire())
+                .await
+                .ok()
+                .and_then(Result::ok),
+            None => self.semaphore.acquire().await.ok(),
+        }
+    }
+}
+
+struct QueuedGuard<'a>(&'a AtomicUsize);
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
 #[async_trait]
 impl Handler for MaxConcurrency {
     #[inline]
     async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
-        match self.semaphore.acquire().await {
-            Ok(_) => {
+        match self.acquire().await {
+            Some(_permit) => {
                 ctrl.call_next(req, depot, res).await;
             }
-            Err(e) => {
-                tracing::error!(
-                    "Max concurrency semaphore is never closed, acquire should never fail: {}",
-                    e
-                );
-                res.render(StatusError::payload_too_large().brief("Max concurrency reached."));
+            None => {
+                tracing::debug!("max concurrency reached, request rejected");
+                if let Some(retry_after) = self.retry_after {
+                    res.add_header(RETRY_AFTER, retry_after.as_secs().max(1), true).ok();
+                }
+                res.render(StatusError::service_unavailable().brief("Max concurrency reached."));
+                ctrl.skip_rest();
             }
         }
     }
@@ -33,7 +125,59 @@ impl Handler for MaxConcurrency {
 /// Create a new `MaxConcurrency`.
 #[inline]
 pub fn max_concurrency(size: usize) -> MaxConcurrency {
-    MaxConcurrency {
-        semaphore: Semaphore::new(size),
+    MaxConcurrency::new(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[handler]
+    async fn slow() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        "done"
+    }
+
+    async fn statuses(limiter: MaxConcurrency, count: usize) -> Vec<Response> {
+        let service = Arc::new(Service::new(Router::new().hoop(limiter).get(slow)));
+        let tasks: Vec<_> = (0..count)
+            .map(|_| {
+                let service = service.clone();
+                tokio::spawn(async move { TestClient::get("http://127.0.0.1:5800/").send(&*service).await })
+            })
+            .collect();
+        let mut responses = vec![];
+        for task in tasks {
+            responses.push(task.await.unwrap());
+        }
+        responses
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency_queue() {
+        let responses = statuses(max_concurrency(1), 3).await;
+        assert!(responses.iter().all(|res| res.status_code == Some(StatusCode::OK)));
+
+        let responses = statuses(max_concurrency(1).max_queued(1).retry_after(Duration::from_secs(3)), 3).await;
+        let rejected: Vec<_> = responses
+            .iter()
+            .filter(|res| res.status_code == Some(StatusCode::SERVICE_UNAVAILABLE))
+            .collect();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].headers().get(RETRY_AFTER).unwrap(), "3");
+
+        let responses = statuses(max_concurrency(1).queue_timeout(Duration::from_millis(50)), 2).await;
+        assert_eq!(
+            responses
+                .iter()
+                .filter(|res| res.status_code == Some(StatusCode::SERVICE_UNAVAILABLE))
+                .count(),
+            1
+        );
     }
 }
@@ -1,5 +1,7 @@
 //! Force https middleware.
 //!
+//! It can be used as middleware, or as the service of the plain HTTP listener, see [`ForceHttps::into_service`].
+//!
 //! Read more: <https://salvo.rs>
 use std::borrow::Cow;
 
@@ -7,8 +9,9 @@ use salvo_core::handler::Skipper;
 use salvo_core::http::header;
 use salvo_core::http::uri::{Scheme, Uri};
 use salvo_core::http::{Request, ResBody, Response};
+use salvo_core::routing::Router;
 use salvo_core::writing::Redirect;
-use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Service};
 
 /// Middleware for force redirect to http uri.
 #[derive(Default)]
@@ -37,6 +40,22 @@ impl ForceHttps {
             ..self
         }
     }
+
+    /// Creates a [`Service`] which redirects all requests to the HTTPS origin.
+    ///
+    /// It is intended for the plain HTTP listener, see
+    /// [`Listener::with_service`](salvo_core::conn::Listener::with_service).
+    pub fn into_service(self) -> Service {
+        self.into_service_with(Router::new())
+    }
+
+    /// Creates a [`Service`] which serves requests matched by `router`, and redirects the others to the HTTPS origin.
+    ///
+    /// It is usually used to serve ACME HTTP-01 challenges on the plain HTTP listener, pass the router given to
+    /// `AcmeListener::http01_challege`.
+    pub fn into_service_with(self, router: Router) -> Service {
+        Service::new(router.push(Router::with_path("<**>").goal(self)))
+    }
 }
 
 #[async_trait]
@@ -79,7 +98,7 @@ fn redirect_host(host: &str, https_port: Option<u16>) -> Cow<'_, str> {
 mod tests {
     use salvo_core::http::header::{HOST, LOCATION};
     use salvo_core::prelude::*;
-    use salvo_core::test::TestClient;
+    use salvo_core::test::{ResponseExt, TestClient};
 
     use super::*;
 
@@ -109,4 +128,28 @@ mod tests {
             Some(&"https://127.0.0.1:1234/".parse().unwrap())
         );
     }
+
+    #[tokio::test]
+    async fn test_redirect_service() {
+        let service = ForceHttps::new()
+            .into_service_with(Router::new().push(Router::with_path(".well-known/acme-challenge/<token>").goal(hello)));
+        for path in ["/", "/users/1?tab=posts"] {
+            let response = TestClient::get(format!("http://127.0.0.1:5800{path}"))
+                .add_header(HOST, "example.com", true)
+                .send(&service)
+                .await;
+            assert_eq!(response.status_code, Some(StatusCode::PERMANENT_REDIRECT));
+            assert_eq!(
+                response.headers().get(LOCATION),
+                Some(&format!("https://example.com{path}").parse().unwrap())
+            );
+        }
+
+        let mut response = TestClient::get("http://127.0.0.1:5800/.well-known/acme-challenge/token")
+            .add_header(HOST, "example.com", true)
+            .send(&service)
+            .await;
+        assert_eq!(response.status_code, Some(StatusCode::OK));
+        assert_eq!(response.take_string().await.unwrap(), "Hello World");
+    }
 }
@@ -0,0 +1,368 @@
+//! Health check handlers for liveness and readiness probes.
+//!
+//! Components register async checks, like a database or cache ping, in a [`HealthCheck`]. Its
+//! [`liveness`](HealthCheck::liveness) and [`readiness`](HealthCheck::readiness) handlers, usually mounted at
+//! `/healthz` and `/readyz`, run the checks concurrently and report the aggregate status and the detail of every
+//! check as JSON, with `200 OK` when all checks pass and `503 Service Unavailable` otherwise:
+//!
+//! ```json
+//! {"status":"fail","checks":{"db":{"status":"ok","duration_ms":2},"cache":{"status":"fail","duration_ms":5000,"error":"timeout"}}}
+//! ```
+//!
+//! During a graceful shutdown, the readiness probe fails without running the checks, so load balancers stop sending
+//! new requests while in-flight requests complete. See [`HealthCheck::drain_signal`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use salvo_core::prelude::*;
+//! use salvo_core::server::shutdown_signal;
+//! use salvo_extra::health_check::HealthCheck;
+//!
+//! async fn ping_db() -> Result<(), String> {
+//!     Ok(())
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let health = HealthCheck::new()
+//!         .check("db", ping_db)
+//!         .drain_delay(Duration::from_secs(5));
+//!     let router = Router::new().push(health.router());
+//!
+//!     let acceptor = TcpListener::new("0.0.0.0:5800").bind().await;
+//!     Server::new(acceptor)
+//!         .serve_with_graceful_shutdown(router, health.drain_signal(shutdown_signal()))
+//!         .await;
+//! }
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use serde::Serialize;
+
+use salvo_core::http::{Request, Response, StatusCode};
+use salvo_core::writing::Json;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Router};
+
+/// An async health check of a component.
+///
+/// It is implemented for closures returning a future of `Result<(), E>` where `E: Display`.
+#[async_trait]
+pub trait Check: Send + Sync + 'static {
+    /// Checks the component, returns an error message if it is unhealthy.
+    async fn check(&self) -> Result<(), String>;
+}
+
+#[async_trait]
+impl<F, Fut, E> Check for F
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send,
+    E: Display,
+{
+    async fn check(&self) -> Result<(), String> {
+        self().await.map_err(|e| e.to_string())
+    }
+}
+
+/// Status of a health report or a check.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// All checks pass.
+    Ok,
+    /// At least one check fails.
+    Fail,
+    /// The application is shutting down, only reported by the readiness probe.
+    Draining,
+}
+
+/// Result of a check in a [`HealthReport`].
+#[derive(Serialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct CheckReport {
+    /// Status of the check.
+    pub status: HealthStatus,
+    /// Time spent running the check, in milliseconds.
+    pub duration_ms: u64,
+    /// Error message of the check, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Report rendered by the health check handlers.
+#[derive(Serialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct HealthReport {
+    /// Aggregate status of the checks.
+    pub status: HealthStatus,
+    /// Results of the checks, by name.
+    pub checks: BTreeMap<String, CheckReport>,
+}
+
+struct NamedCheck {
+    name: String,
+    liveness: bool,
+    check: Box<dyn Check>,
+}
+
+/// Registry of health checks, see the [module documentation](self).
+///
+/// Cloned `HealthCheck`s share the same readiness state.
+#[derive(Clone)]
+pub struct HealthCheck {
+    checks: Vec<Arc<NamedCheck>>,
+    timeout: Duration,
+    drain_delay: Duration,
+    ready: Arc<AtomicBool>,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthCheck {
+    /// Create a new `HealthCheck` without checks, which is ready.
+    pub fn new() -> Self {
+        Self {
+            checks: vec![],
+            timeout: Duration::from_secs(5),
+            drain_delay: Duration::ZERO,
+            ready: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Registers a readiness check, like a database ping.
+    ///
+    /// Readiness checks only fail the readiness probe, so the application is not restarted when a dependency is
+    /// down.
+    #[inline]
+    pub fn check(mut self, name: impl Into<String>, check: impl Check) -> Self {
+        self.checks.push(Arc::new(NamedCheck {
+            name: name.into(),
+            liveness: false,
+            check: Box::new(check),
+        }));
+        self
+    }
+
+    /// Registers a liveness check, which fails both probes. Failing the liveness probe usually makes the
+    /// orchestrator restart the application, so it should only check the application itself, like a deadlock.
+    #[inline]
+    pub fn liveness_check(mut self, name: impl Into<String>, check: impl Check) -> Self {
+        self.checks.push(Arc::new(NamedCheck {
+            name: name.into(),
+            liveness: true,
+            check: Box::new(check),
+        }));
+        self
+    }
+
+    /// Sets the time a check is given before it fails, default is 5 seconds.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the time waited by [`drain_signal`](HealthCheck::drain_signal) between failing the readiness probe and
+    /// stopping the server, so load balancers can notice it. Default is zero.
+    #[inline]
+    pub fn drain_delay(mut self, delay: Duration) -> Self {
+        self.drain_delay = delay;
+        self
+    }
+
+    /// Returns `true` if the readiness probe is not failed by [`set_ready`](HealthCheck::set_ready).
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether the application is ready, the readiness probe fails without running the checks when it is not.
+    #[inline]
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Relaxed);
+    }
+
+    /// Wraps a shutdown signal, like [`shutdown_signal`](salvo_core::server::shutdown_signal), to be passed to
+    /// [`Server::serve_with_graceful_shutdown`](salvo_core::Server::serve_with_graceful_shutdown).
+    ///
+    /// When `signal` completes, the readiness probe starts failing, then the returned future completes after the
+    /// [`drain_delay`](HealthCheck::drain_delay), which stops the server gracefully.
+    pub fn drain_signal<G>(&self, signal: G) -> impl Future<Output = ()> + Send + 'static
+    where
+        G: Future<Output = ()> + Send + 'static,
+    {
+        let ready = self.ready.clone();
+        let drain_delay = self.drain_delay;
+        async move {
+            signal.await;
+            ready.store(false, Ordering::Relaxed);
+            if !drain_delay.is_zero() {
+                tokio::time::sleep(drain_delay).await;
+            }
+        }
+    }
+
+    /// Runs the checks concurrently, only the liveness checks if `liveness` is `true`.
+    pub async fn report(&self, liveness: bool) -> HealthReport {
+        let checks = self
+            .checks
+            .iter()
+            .filter(|check| !liveness || check.liveness)
+            .map(|check| async move {
+                let started = Instant::now();
+                let result = match tokio::time::timeout(self.timeout, check.check.check()).await {
+                    Ok(result) => result,
+                    Err(_) => Err("timeout".to_owned()),
+                };
+                let report = CheckReport {
+                    status: if result.is_ok() {
+                        HealthStatus::Ok
+                    } else {
+                        HealthStatus::Fail
+                    },
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    error: result.err(),
+                };
+                (check.name.clone(), report)
+            });
+        let checks: BTreeMap<_, _> = join_all(checks).await.into_iter().collect();
+        let status = if checks.values().all(|check| check.status == HealthStatus::Ok) {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Fail
+        };
+        HealthReport { status, checks }
+    }
+
+    /// Returns the handler of the liveness probe, running the liveness checks.
+    #[inline]
+    pub fn liveness(&self) -> HealthHandler {
+        HealthHandler {
+            health: self.clone(),
+            readiness: false,
+        }
+    }
+
+    /// Returns the handler of the readiness probe, running all the checks.
+    #[inline]
+    pub fn readiness(&self) -> HealthHandler {
+        HealthHandler {
+            health: self.clone(),
+            readiness: true,
+        }
+    }
+
+    /// Returns a router serving the liveness probe at `/healthz` and the readiness probe at `/readyz`.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .push(Router::with_path("healthz").get(self.liveness()))
+            .push(Router::with_path("readyz").get(self.readiness()))
+    }
+}
+
+/// Handler of a liveness or readiness probe, created by [`HealthCheck::liveness`] or [`HealthCheck::readiness`].
+pub struct HealthHandler {
+    health: HealthCheck,
+    readiness: bool,
+}
+
+#[async_trait]
+impl Handler for HealthHandler {
+    async fn handle(&self, _req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+        let report = if self.readiness && !self.health.is_ready() {
+            HealthReport {
+                status: HealthStatus::Draining,
+                checks: BTreeMap::new(),
+            }
+        } else {
+            self.health.report(!self.readiness).await
+        };
+        if report.status == HealthStatus::Ok {
+            res.status_code(StatusCode::OK);
+        } else {
+            res.status_code(StatusCode::SERVICE_UNAVAILABLE);
+        }
+        res.render(Json(report));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+    use serde_json::Value;
+
+    use super::*;
+
+    async fn get(service: &Service, path: &str) -> (StatusCode, Value) {
+        let mut res = TestClient::get(format!("http://127.0.0.1:5801/{path}"))
+            .send(service)
+            .await;
+        (res.status_code.unwrap(), res.take_json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let db_up = Arc::new(AtomicBool::new(true));
+        let db = db_up.clone();
+        let health = HealthCheck::new()
+            .liveness_check("self", || async { Ok::<_, String>(()) })
+            .check("db", move || {
+                let up = db.load(Ordering::Relaxed);
+                async move {
+                    if up {
+                        Ok(())
+                    } else {
+                        Err("connection refused")
+                    }
+                }
+            })
+            .check("cache", || async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok::<_, String>(())
+            })
+            .timeout(Duration::from_millis(50));
+        let service = Service::new(health.router());
+
+        let (status, report) = get(&service, "healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["status"], "ok");
+        assert_eq!(report["checks"].as_object().unwrap().len(), 1);
+
+        let (status, report) = get(&service, "readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(report["status"], "fail");
+        assert_eq!(report["checks"]["self"]["status"], "ok");
+        assert_eq!(report["checks"]["db"]["status"], "ok");
+        assert_eq!(report["checks"]["cache"]["error"], "timeout");
+
+        db_up.store(false, Ordering::Relaxed);
+        let (_, report) = get(&service, "readyz").await;
+        assert_eq!(report["checks"]["db"]["error"], "connection refused");
+
+        health.drain_signal(async {}).await;
+        assert!(!health.is_ready());
+        let (status, report) = get(&service, "readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(report["status"], "draining");
+        let (status, _) = get(&service, "healthz").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
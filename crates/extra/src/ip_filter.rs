@@ -0,0 +1,308 @@
+//! IP filter middleware.
+//!
+//! Read more: <https://salvo.rs>
+use std::fmt::{self, Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use salvo_core::http::header::HeaderName;
+use salvo_core::http::{Request, Response, StatusError};
+use salvo_core::{async_trait, Depot, Error, FlowCtrl, Handler};
+
+/// A CIDR block, like `10.0.0.0/8` or `fd00::/8`, a single IP address is parsed as a block with full prefix.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+impl IpCidr {
+    /// Create a new `IpCidr`, returns error if `prefix` is longer than the address.
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, Error> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(Error::other(format!("invalid prefix length `{prefix}` for `{addr}`")));
+        }
+        Ok(IpCidr { addr, prefix })
+    }
+
+    /// Returns `true` if `ip` is in this block.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+impl FromStr for IpCidr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|e| Error::other(format!("invalid ip address `{addr}`: {e}")))?
+            .to_canonical();
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .map_err(|e| Error::other(format!("invalid prefix length `{prefix}`: {e}")))?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        IpCidr::new(addr, prefix)
+    }
+}
+impl Display for IpCidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+/// A shared list of CIDR blocks.
+///
+/// Cloned lists share the same blocks, so a list kept by the application can be updated by
+/// [`IpList::replace`] while the server is running, for example after reloading a config file.
+#[derive(Clone, Debug, Default)]
+pub struct IpList {
+    cidrs: Arc<RwLock<Vec<IpCidr>>>,
+}
+impl IpList {
+    /// Create a new `IpList` with `cidrs`.
+    pub fn new(cidrs: impl IntoIterator<Item = IpCidr>) -> Self {
+        IpList {
+            cidrs: Arc::new(RwLock::new(cidrs.into_iter().collect())),
+        }
+    }
+
+    /// Parses CIDR blocks like `["10.0.0.0/8", "192.168.1.1"]` into a new `IpList`.
+    pub fn parse<I, S>(cidrs: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cidrs = cidrs
+            .into_iter()
+            .map(|cidr| cidr.as_ref().parse())
+            .collect::<Result<Vec<IpCidr>, _>>()?;
+        Ok(Self::new(cidrs))
+    }
+
+    /// Replaces all blocks of the list.
+    pub fn replace(&self, cidrs: impl IntoIterator<Item = IpCidr>) {
+        let cidrs = cidrs.into_iter().collect();
+        *self.cidrs.write().unwrap_or_else(|e| e.into_inner()) = cidrs;
+    }
+
+    /// Returns `true` if `ip` is in any block of the list.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let cidrs = self.cidrs.read().unwrap_or_else(|e| e.into_inner());
+        cidrs.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+/// IpFilter
+///
+/// Filters requests by client IP, requests denied are rejected with `403 Forbidden`. The deny list is checked
+/// first, and if an allow list is set, only IPs in it are accepted.
+///
+/// The client IP is the peer address of the connection. If the peer is one of the
+/// [`trusted_proxies`](IpFilter::trusted_proxies), the `X-Forwarded-For` header is read from right to left
+/// and the first address not trusted is used, so clients can not spoof their IP through the header.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::ip_filter::{IpFilter, IpList};
+///
+/// let allowed = IpList::parse(["10.0.0.0/8", "192.168.0.0/16"]).unwrap();
+/// let filter = IpFilter::new()
+///     .allow(allowed.clone())
+///     .trusted_proxies(IpList::parse(["127.0.0.1"]).unwrap());
+/// let router = Router::with_path("admin").hoop(filter);
+///
+/// // Later, after the config is reloaded.
+/// allowed.replace(["10.1.0.0/16".parse().unwrap()]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct IpFilter {
+    allow: Option<IpList>,
+    deny: IpList,
+    trusted_proxies: IpList,
+    forwarded_header: HeaderName,
+}
+impl Default for IpFilter {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl IpFilter {
+    /// Create new `IpFilter`, which accepts all requests.
+    #[inline]
+    pub fn new() -> Self {
+        IpFilter {
+            allow: None,
+            deny: IpList::default(),
+            trusted_proxies: IpList::default(),
+            forwarded_header: HeaderName::from_static("x-forwarded-for"),
+        }
+    }
+
+    /// Sets the allow list, only IPs in it are accepted.
+    #[inline]
+    pub fn allow(mut self, allow: IpList) -> Self {
+        self.allow = Some(allow);
+        self
+    }
+
+    /// Sets the deny list, IPs in it are rejected.
+    #[inline]
+    pub fn deny(mut self, deny: IpList) -> Self {
+        self.deny = deny;
+        self
+    }
+
+    /// Sets the reverse proxies whose forwarded header is trusted.
+    #[inline]
+    pub fn trusted_proxies(mut self, trusted_proxies: IpList) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Sets the header containing the forwarded client IPs, defaults to `X-Forwarded-For`.
+    #[inline]
+    pub fn forwarded_header(mut self, forwarded_header: HeaderName) -> Self {
+        self.forwarded_header = forwarded_header;
+        self
+    }
+
+    /// Resolves the client IP of the request.
+    pub fn client_ip(&self, req: &Request) -> Option<IpAddr> {
+        let mut ip = req.remote_addr().clone().into_std()?.ip().to_canonical();
+        if !self.trusted_proxies.contains(ip) {
+            return Some(ip);
+        }
+        let forwarded = req
+            .headers()
+            .get_all(&self.forwarded_header)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        for addr in forwarded.into_iter().rev() {
+            match addr.trim().parse::<IpAddr>() {
+                Ok(addr) => {
+                    ip = addr.to_canonical();
+                    if !self.trusted_proxies.contains(ip) {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        Some(ip)
+    }
+
+    fn is_allowed(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => !self.deny.contains(ip) && self.allow.as_ref().map_or(true, |allow| allow.contains(ip)),
+            None => self.allow.is_none(),
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for IpFilter {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let ip = self.client_ip(req);
+        if self.is_allowed(ip) {
+            ctrl.call_next(req, depot, res).await;
+        } else {
+            tracing::debug!(ip = ?ip, "ip address is not allowed");
+            res.render(StatusError::forbidden().brief("Your IP address is not allowed."));
+            ctrl.skip_rest();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::conn::SocketAddr;
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[handler]
+    async fn hello() -> &'static str {
+        "hello"
+    }
+
+    #[test]
+    fn test_ip_cidr() {
+        let cidr: IpCidr = "10.1.0.0/16".parse().unwrap();
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(cidr.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("10.2.0.1".parse().unwrap()));
+        let cidr: IpCidr = "fd00::/8".parse().unwrap();
+        assert!(cidr.contains("fd12::1".parse().unwrap()));
+        assert!(!cidr.contains("fe80::1".parse().unwrap()));
+        assert!("0.0.0.0/0"
+            .parse::<IpCidr>()
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("10.0.0".parse::<IpCidr>().is_err());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let filter = IpFilter::new().trusted_proxies(IpList::parse(["127.0.0.1", "10.0.0.0/8"]).unwrap());
+        let mut req = Request::default();
+        *req.remote_addr_mut() = SocketAddr::from(std::net::SocketAddr::from(([127, 0, 0, 1], 80)));
+        req.headers_mut()
+            .insert("x-forwarded-for", "1.1.1.1, 2.2.2.2, 10.0.0.2".parse().unwrap());
+        assert_eq!(filter.client_ip(&req), Some("2.2.2.2".parse().unwrap()));
+
+        *req.remote_addr_mut() = SocketAddr::from(std::net::SocketAddr::from(([3, 3, 3, 3], 80)));
+        assert_eq!(filter.client_ip(&req), Some("3.3.3.3".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_ip_filter() {
+        let allowed = IpList::parse(["10.0.0.0/8"]).unwrap();
+        let filter = IpFilter::new()
+            .allow(allowed.clone())
+            .deny(IpList::parse(["10.0.0.13"]).unwrap())
+            .trusted_proxies(IpList::parse(["127.0.0.1"]).unwrap());
+        let service = Service::new(Router::new().hoop(filter).get(hello));
+
+        let send = |ip: &'static str| {
+            let mut req = TestClient::get("http://127.0.0.1:5801/")
+                .add_header("x-forwarded-for", ip, true)
+                .build();
+            *req.remote_addr_mut() = SocketAddr::from(std::net::SocketAddr::from(([127, 0, 0, 1], 80)));
+            let service = &service;
+            async move { service.handle(req).await.status_code }
+        };
+        assert_eq!(send("10.0.0.1").await, Some(StatusCode::OK));
+        assert_eq!(send("10.0.0.13").await, Some(StatusCode::FORBIDDEN));
+        assert_eq!(send("192.168.0.1").await, Some(StatusCode::FORBIDDEN));
+
+        allowed.replace(["192.168.0.0/16".parse().unwrap()]);
+        assert_eq!(send("192.168.0.1").await, Some(StatusCode::OK));
+        assert_eq!(send("10.0.0.1").await, Some(StatusCode::FORBIDDEN));
+    }
+}
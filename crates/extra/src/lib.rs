@@ -6,11 +6,26 @@
 #[macro_use]
 mod cfg;
 
+cfg_feature! {
+    #![feature = "api-key-auth"]
+    pub mod api_key_auth;
+}
+
+cfg_feature! {
+    #![feature = "authorization"]
+    pub mod authorization;
+}
+
 cfg_feature! {
     #![feature = "basic-auth"]
     pub mod basic_auth;
 }
 
+cfg_feature! {
+    #![feature = "bearer-auth"]
+    pub mod bearer_auth;
+}
+
 cfg_feature! {
     #![feature = "affix"]
     pub mod affix;
@@ -21,6 +36,11 @@ cfg_feature! {
     pub mod force_https;
 }
 
+cfg_feature! {
+    #![feature = "ip-filter"]
+    pub mod ip_filter;
+}
+
 cfg_feature! {
     #![feature = "jwt-auth"]
     pub mod jwt_auth;
@@ -35,10 +55,22 @@ cfg_feature! {
     #![feature = "compression"]
     pub mod compression;
 }
+cfg_feature! {
+    #![feature = "locale"]
+    pub mod locale;
+}
 cfg_feature! {
     #![feature = "logging"]
     pub mod logging;
 }
+cfg_feature! {
+    #![feature = "long-poll"]
+    pub mod long_poll;
+}
+cfg_feature! {
+    #![feature = "maintenance"]
+    pub mod maintenance;
+}
 cfg_feature! {
     #![feature = "sse"]
     pub mod sse;
@@ -71,3 +103,43 @@ cfg_feature! {
     #![feature = "request-id"]
     pub mod request_id;
 }
+cfg_feature! {
+    #![feature = "secure-headers"]
+    pub mod secure_headers;
+}
+cfg_feature! {
+    #![feature = "prometheus"]
+    pub mod prometheus;
+}
+cfg_feature! {
+    #![feature = "health-check"]
+    pub mod health_check;
+}
+cfg_feature! {
+    #![feature = "audit"]
+    pub mod audit;
+}
+cfg_feature! {
+    #![feature = "slow-request"]
+    pub mod slow_request;
+}
+cfg_feature! {
+    #![feature = "server-stats"]
+    pub mod server_stats;
+}
+cfg_feature! {
+    #![feature = "rewrite"]
+    pub mod rewrite;
+}
+cfg_feature! {
+    #![feature = "tus"]
+    pub mod tus;
+}
+cfg_feature! {
+    #![feature = "webhook-signature"]
+    pub mod webhook_signature;
+}
+cfg_feature! {
+    #![feature = "response-transform"]
+    pub mod response_transform;
+}
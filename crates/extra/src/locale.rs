@@ -0,0 +1,316 @@
+//! Locale negotiation middleware.
+//!
+//! [`Locale`] selects the language of a request among the supported locales of the application. The locale is
+//! read, in order, from a query parameter and a cookie, which let users override their browser settings, then
+//! negotiated with the `Accept-Language` header, and falls back to the default locale.
+//!
+//! The selected locale is stored in the [`Depot`], read with [`LocaleDepotExt::locale`], and written in the
+//! `Content-Language` header of the response. `Accept-Language`, and `Cookie` when a cookie is read, are appended to
+//! the `Vary` header of the response, so caches do not serve a response in the wrong language.
+//!
+//! A [`Translator`] holds the messages of the application, as plain text or, with the `fluent` feature, as
+//! [Fluent](https://projectfluent.org) resources, and translates them in the locale of the request. Errors rendered
+//! by handlers and error pages of the catcher are translated by [`LocalizeErrors`].
+//!
+//! # Example
+//!
+//! ```
+//! use salvo_core::prelude::*;
+//! use salvo_extra::locale::{Locale, LocaleDepotExt};
+//!
+//! #[handler]
+//! async fn hello(depot: &mut Depot) -> &'static str {
+//!     match depot.locale() {
+//!         Some("fr") => "Bonjour",
+//!         _ => "Hello",
+//!     }
+//! }
+//!
+//! let locale = Locale::new(["en", "fr"]).query_param("lang").cookie_name("lang");
+//! let router = Router::new().hoop(locale).get(hello);
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::cmp::Ordering;
+
+use salvo_core::http::header::{HeaderValue, ACCEPT_LANGUAGE, CONTENT_LANGUAGE, COOKIE, VARY};
+use salvo_core::http::{Request, Response};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+mod translator;
+pub use translator::{
+    LocalizeErrors, Translator, TranslatorBuilder, TranslatorDepotExt, TranslatorError, TRANSLATOR_KEY,
+};
+
+/// Key for the locale of the request in depot.
+pub const LOCALE_KEY: &str = "::salvo::locale::locale";
+
+/// Extension of [`Depot`] to get the locale selected by [`Locale`].
+pub trait LocaleDepotExt {
+    /// Get the locale of the request.
+    fn locale(&self) -> Option<&str>;
+}
+
+impl LocaleDepotExt for Depot {
+    #[inline]
+    fn locale(&self) -> Option<&str> {
+        self.get::<String>(LOCALE_KEY).map(|locale| &**locale).ok()
+    }
+}
+
+/// A language range of the `Accept-Language` header, with its quality.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LanguageRange {
+    /// The language tag, like `en-US`, or `*` for any language.
+    pub tag: String,
+    /// The quality, from 0 to 1.
+    pub quality: f32,
+}
+
+/// Parses the value of an `Accept-Language` header.
+///
+/// The ranges are sorted by quality, the ones with the same quality keep their order, and the ones with a quality of
+/// 0, which are not acceptable, or an invalid quality are dropped.
+pub fn parse_accept_language(value: &str) -> Vec<LanguageRange> {
+    let mut ranges: Vec<LanguageRange> = value
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+            if tag.is_empty() {
+                return None;
+            }
+            let mut quality = 1.0;
+            for param in parts {
+                if let Some((name, value)) = param.trim().split_once('=') {
+                    if name.trim().eq_ignore_ascii_case("q") {
+                        quality = value.trim().parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+                    }
+                }
+            }
+            (quality > 0.0).then(|| LanguageRange {
+                tag: tag.to_owned(),
+                quality,
+            })
+        })
+        .collect();
+    ranges.sort_by(|a, b| b.quality.partial_cmp(&a.quality).unwrap_or(Ordering::Equal));
+    ranges
+}
+
+/// Finds the supported locale matching a language tag.
+///
+/// The tag matches a locale equal to it, ignoring case, then it is truncated, so `zh-Hant-TW` matches `zh-Hant` and
+/// `zh`, and at last a less specific tag matches a more specific locale, so `en` matches `en-US`.
+pub fn lookup<'a, S: AsRef<str>>(tag: &str, supported: &'a [S]) -> Option<&'a str> {
+    let locales = || supported.iter().map(AsRef::as_ref);
+    if tag == "*" {
+        return locales().next();
+    }
+    let mut range = tag;
+    loop {
+        if let Some(locale) = locales().find(|locale| locale.eq_ignore_ascii_case(range)) {
+            return Some(locale);
+        }
+        match range.rfind('-') {
+            Some(index) => range = &range[..index],
+            None => break,
+        }
+    }
+    locales().find(|locale| {
+        locale.len() > tag.len()
+            && locale.as_bytes()[tag.len()] == b'-'
+            && locale[..tag.len()].eq_ignore_ascii_case(tag)
+    })
+}
+
+/// Negotiates the locale of language ranges parsed by [`parse_accept_language`] among supported locales.
+///
+/// Ranges are tried by quality with [`lookup`], `None` is returned if no supported locale matches.
+pub fn negotiate<'a, S: AsRef<str>>(ranges: &[LanguageRange], supported: &'a [S]) -> Option<&'a str> {
+    ranges.iter().find_map(|range| lookup(&range.tag, supported))
+}
+
+/// Middleware selecting the locale of requests.
+pub struct Locale {
+    supported: Vec<String>,
+    default_locale: String,
+    query_param: Option<String>,
+    cookie_name: Option<String>,
+    content_language: bool,
+}
+
+impl Locale {
+    /// Create a new `Locale` with the supported locales, the first one is the default locale.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `supported` is empty.
+    pub fn new<I, S>(supported: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let supported: Vec<String> = supported.into_iter().map(Into::into).collect();
+        let default_locale = supported.first().expect("supported locales should not be empty").clone();
+        Locale {
+            supported,
+            default_locale,
+            query_param: None,
+            cookie_name: None,
+            content_language: true,
+        }
+    }
+
+    /// Sets the locale used when no supported locale matches the request, defaults to the first supported locale.
+    #[inline]
+    pub fn default_locale(mut self, locale: impl Into<String>) -> Self {
+        self.default_locale = locale.into();
+        self
+    }
+
+    /// Sets the query parameter overriding the `Accept-Language` header, like `lang` in `?lang=fr`.
+    #[inline]
+    pub fn query_param(mut self, name: impl Into<String>) -> Self {
+        self.query_param = Some(name.into());
+        self
+    }
+
+    /// Sets the cookie overriding the `Accept-Language` header, usually set when users choose their language.
+    #[inline]
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.cookie_name = Some(name.into());
+        self
+    }
+
+    /// Sets if the locale is written in the `Content-Language` header of responses, defaults to `true`.
+    #[inline]
+    pub fn content_language(mut self, content_language: bool) -> Self {
+        self.content_language = content_language;
+        self
+    }
+
+    /// Get the supported locales.
+    #[inline]
+    pub fn supported(&self) -> &[String] {
+        &self.supported
+    }
+
+    /// Selects the locale of a request.
+    pub fn select(&self, req: &Request) -> &str {
+        if let Some(locale) = self
+            .query_param
+            .as_deref()
+            .and_then(|name| req.query::<String>(name))
+            .and_then(|tag| lookup(&tag, &self.supported))
+        {
+            return locale;
+        }
+        if let Some(locale) = self.cookie_name.as_deref().and_then(|name| {
+            req.headers()
+                .get_all(COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(';'))
+                .filter_map(|pair| pair.trim().split_once('='))
+                .find_map(|(key, tag)| (key == name).then(|| lookup(tag.trim(), &self.supported)).flatten())
+        }) {
+            return locale;
+        }
+        req.headers()
+            .get_all(ACCEPT_LANGUAGE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|value| negotiate(&parse_accept_language(value), &self.supported))
+            .unwrap_or(&self.default_locale)
+    }
+}
+
+#[async_trait]
+impl Handler for Locale {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+        let locale = self.select(req).to_owned();
+        if self.content_language {
+            if let Ok(value) = HeaderValue::from_str(&locale) {
+                res.headers_mut().insert(CONTENT_LANGUAGE, value);
+            }
+        }
+        let headers = res.headers_mut();
+        headers.append(VARY, HeaderValue::from_static("accept-language"));
+        if self.cookie_name.is_some() {
+            headers.append(VARY, HeaderValue::from_static("cookie"));
+        }
+        depot.insert(LOCALE_KEY, locale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_accept_language() {
+        let ranges = parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5, it;q=0");
+        let tags: Vec<&str> = ranges.iter().map(|range| &*range.tag).collect();
+        assert_eq!(tags, ["fr-CH", "fr", "en", "de", "*"]);
+        assert_eq!(ranges[1].quality, 0.9);
+
+        let ranges = parse_accept_language("en;q=0.5, de, es;q=2");
+        let tags: Vec<&str> = ranges.iter().map(|range| &*range.tag).collect();
+        assert_eq!(tags, ["de", "en"]);
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let supported = ["en-US", "fr", "zh-Hant"];
+        assert_eq!(lookup("fr-CA", &supported), Some("fr"));
+        assert_eq!(lookup("EN", &supported), Some("en-US"));
+        assert_eq!(lookup("zh-hant-tw", &supported), Some("zh-Hant"));
+        assert_eq!(lookup("e", &supported), None);
+        assert_eq!(negotiate(&parse_accept_language("de, fr;q=0.5, en;q=0.8"), &supported), Some("en-US"));
+        assert_eq!(negotiate(&parse_accept_language("de, *;q=0.1"), &supported), Some("en-US"));
+        assert_eq!(negotiate(&parse_accept_language("de"), &supported), None);
+    }
+
+    #[tokio::test]
+    async fn test_locale() {
+        #[handler]
+        async fn hello(depot: &mut Depot) -> String {
+            depot.locale().unwrap().to_owned()
+        }
+        let locale = Locale::new(["en", "fr", "de"]).query_param("lang").cookie_name("lang");
+        let service = Service::new(Router::new().hoop(locale).get(hello));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("accept-language", "fr-FR,fr;q=0.9,en;q=0.8", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.headers()["content-language"], "fr");
+        let vary: Vec<_> = res.headers().get_all("vary").iter().collect();
+        assert_eq!(vary, ["accept-language", "cookie"]);
+        assert_eq!(res.take_string().await.unwrap(), "fr");
+
+        let mut res = TestClient::get("http://127.0.0.1:5801?lang=de")
+            .add_header("accept-language", "fr", true)
+            .add_header("cookie", "lang=en", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "de");
+
+        let mut res = TestClient::get("http://127.0.0.1:5801?lang=es")
+            .add_header("accept-language", "fr", true)
+            .add_header("cookie", "theme=dark; lang=en", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "en");
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("accept-language", "ja", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "en");
+    }
+}
@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::Arc;
+
+use salvo_core::http::{ResBody, Response, StatusError};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request};
+
+use super::LocaleDepotExt;
+
+/// Key for the [`Translator`] in depot.
+pub const TRANSLATOR_KEY: &str = "::salvo::locale::translator";
+
+/// Extension of [`Depot`] to translate messages in the locale of the request.
+pub trait TranslatorDepotExt {
+    /// Get the translator stored in depot by the [`Translator`] hoop.
+    fn translator(&self) -> Option<&Translator>;
+    /// Translate a message in the locale of the request, returns the key if the message is not found.
+    fn tr(&self, key: &str) -> String {
+        self.tr_args(key, &[])
+    }
+    /// Translate a message with arguments in the locale of the request, returns the key if the message is not found.
+    fn tr_args(&self, key: &str, args: &[(&str, &str)]) -> String;
+}
+
+impl TranslatorDepotExt for Depot {
+    #[inline]
+    fn translator(&self) -> Option<&Translator> {
+        self.get::<Translator>(TRANSLATOR_KEY).ok()
+    }
+    fn tr_args(&self, key: &str, args: &[(&str, &str)]) -> String {
+        match self.translator() {
+            Some(translator) => {
+                let locale = self.locale().unwrap_or(translator.fallback_locale());
+                translator.translate(locale, key, args)
+            }
+            None => key.to_owned(),
+        }
+    }
+}
+
+/// Error happened when loading messages of a [`Translator`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TranslatorError {
+    /// The locale is not a valid language identifier.
+    InvalidLocale(String),
+    /// A Fluent resource could not be parsed or added.
+    Fluent(String),
+    /// Messages could not be read.
+    Io(std::io::Error),
+}
+impl Display for TranslatorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLocale(locale) => write!(f, "invalid locale: {locale}"),
+            Self::Fluent(e) => write!(f, "fluent error: {e}"),
+            Self::Io(e) => write!(f, "io error: {e}"),
+        }
+    }
+}
+impl std::error::Error for TranslatorError {}
+impl From<std::io::Error> for TranslatorError {
+    #[inline]
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Default)]
+struct Catalog {
+    messages: HashMap<String, String>,
+    #[cfg(feature = "fluent")]
+    bundle: Option<fluent_bundle::concurrent::FluentBundle<fluent_bundle::FluentResource>>,
+}
+impl Catalog {
+    fn message(&self, key: &str, args: &[(&str, &str)]) -> Option<String> {
+        if let Some(message) = self.messages.get(key) {
+            let mut message = message.clone();
+            for (name, value) in args {
+                message = message.replace(&format!("{{{name}}}"), value);
+            }
+            return Some(message);
+        }
+        #[cfg(feature = "fluent")]
+        if let Some(bundle) = &self.bundle {
+            let pattern = bundle.get_message(key)?.value()?;
+            let mut fluent_args = fluent_bundle::FluentArgs::new();
+            for (name, value) in args {
+                fluent_args.set(*name, *value);
+            }
+            let mut errors = vec![];
+            let message = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+            if !errors.is_empty() {
+                tracing::debug!(key, ?errors, "format fluent message failed");
+            }
+            return Some(message.into_owned());
+        }
+        None
+    }
+}
+
+/// Builder of [`Translator`].
+pub struct TranslatorBuilder {
+    fallback_locale: String,
+    catalogs: HashMap<String, Catalog>,
+}
+impl TranslatorBuilder {
+    /// Adds messages of a locale, where `{name}` is replaced by the argument `name` when they are translated.
+    pub fn add_messages<I, K, V>(mut self, locale: impl Into<String>, messages: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.catalogs
+            .entry(locale.into())
+            .or_default()
+            .messages
+            .extend(messages.into_iter().map(|(key, message)| (key.into(), message.into())));
+        self
+    }
+
+    /// Adds a Fluent resource of a locale, from the content of a `.ftl` file.
+    #[cfg(feature = "fluent")]
+    pub fn add_ftl(mut self, locale: &str, source: impl Into<String>) -> Result<Self, TranslatorError> {
+        use fluent_bundle::concurrent::FluentBundle;
+        use fluent_bundle::FluentResource;
+
+        let langid: unic_langid::LanguageIdentifier = locale
+            .parse()
+            .map_err(|_| TranslatorError::InvalidLocale(locale.to_owned()))?;
+        let resource = FluentResource::try_new(source.into())
+            .map_err(|(_, errors)| TranslatorError::Fluent(format!("{errors:?}")))?;
+        let catalog = self.catalogs.entry(locale.to_owned()).or_default();
+        let bundle = catalog.bundle.get_or_insert_with(|| {
+            let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+            bundle.set_use_isolating(false);
+            bundle
+        });
+        bundle
+            .add_resource(resource)
+            .map_err(|errors| TranslatorError::Fluent(format!("{errors:?}")))?;
+        Ok(self)
+    }
+
+    /// Loads the Fluent resources of a directory, either named with their locale, like `fr.ftl`, or in a
+    /// subdirectory named with their locale, like `fr/errors.ftl`.
+    #[cfg(feature = "fluent")]
+    pub fn load_dir(mut self, dir: impl AsRef<std::path::Path>) -> Result<Self, TranslatorError> {
+        let is_ftl = |path: &std::path::Path| path.extension().is_some_and(|ext| ext == "ftl");
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                let Some(locale) = path.file_name().and_then(|name| name.to_str()).map(ToOwned::to_owned) else {
+                    continue;
+                };
+                for entry in std::fs::read_dir(&path)? {
+                    let path = entry?.path();
+                    if is_ftl(&path) {
+                        self = self.add_ftl(&locale, std::fs::read_to_string(&path)?)?;
+                    }
+                }
+            } else if is_ftl(&path) {
+                if let Some(locale) = path.file_stem().and_then(|name| name.to_str()).map(ToOwned::to_owned) {
+                    self = self.add_ftl(&locale, std::fs::read_to_string(&path)?)?;
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Build the [`Translator`].
+    #[inline]
+    pub fn build(self) -> Translator {
+        Translator {
+            inner: Arc::new(self),
+        }
+    }
+}
+
+/// Translates messages in the locales of an application.
+///
+/// A message is looked up in the requested locale, then in less specific locales, so `fr-CA` falls back to `fr`,
+/// and at last in the fallback locale. Messages are added as plain text with `{name}` placeholders, or, with the
+/// `fluent` feature, loaded from [Fluent](https://projectfluent.org) resources.
+///
+/// Used as a hoop, the translator is stored in the depot, so handlers translate messages in the locale selected by
+/// [`Locale`](super::Locale) with [`TranslatorDepotExt::tr`].
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::locale::{Locale, Translator, TranslatorDepotExt};
+///
+/// #[handler]
+/// async fn hello(depot: &mut Depot) -> String {
+///     depot.tr_args("hello", &[("name", "Salvo")])
+/// }
+///
+/// let translator = Translator::builder("en")
+///     .add_messages("en", [("hello", "Hello {name}!")])
+///     .add_messages("fr", [("hello", "Bonjour {name} !")])
+///     .build();
+/// let router = Router::new()
+///     .hoop(Locale::new(["en", "fr"]))
+///     .hoop(translator)
+///     .get(hello);
+/// ```
+#[derive(Clone)]
+pub struct Translator {
+    inner: Arc<TranslatorBuilder>,
+}
+impl Debug for Translator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Translator")
+            .field("fallback_locale", &self.inner.fallback_locale)
+            .field("locales", &self.locales().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Translator {
+    /// Create a [`TranslatorBuilder`] with the locale used when a message is not found in the requested one.
+    #[inline]
+    pub fn builder(fallback_locale: impl Into<String>) -> TranslatorBuilder {
+        TranslatorBuilder {
+            fallback_locale: fallback_locale.into(),
+            catalogs: HashMap::new(),
+        }
+    }
+
+    /// Get the fallback locale.
+    #[inline]
+    pub fn fallback_locale(&self) -> &str {
+        &self.inner.fallback_locale
+    }
+
+    /// Iterate over the locales which have messages.
+    #[inline]
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.inner.catalogs.keys().map(|locale| &**locale)
+    }
+
+    /// Get a message translated in a locale, `None` if it is found neither in the locale nor in the fallback locale.
+    pub fn message(&self, locale: &str, key: &str, args: &[(&str, &str)]) -> Option<String> {
+        let mut range = locale;
+        loop {
+            if let Some(message) = self.inner.catalogs.get(range).and_then(|catalog| catalog.message(key, args)) {
+                return Some(message);
+            }
+            match range.rfind('-') {
+                Some(index) => range = &range[..index],
+                None => break,
+            }
+        }
+        self.inner
+            .catalogs
+            .get(&self.inner.fallback_locale)
+            .and_then(|catalog| catalog.message(key, args))
+    }
+
+    /// Translate a message in a locale, returns the key if the message is not found.
+    #[inline]
+    pub fn translate(&self, locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+        self.message(locale, key, args).unwrap_or_else(|| key.to_owned())
+    }
+
+    /// Translate the name, the brief and the detail of a [`StatusError`].
+    ///
+    /// The name is translated with the key `status-{code}`, like `status-404`. The brief and the detail are used as
+    /// keys, so handlers can render errors like `StatusError::bad_request().brief("invalid-email")`, and the default
+    /// brief of the status code is translated with the key `status-{code}-brief`.
+    pub fn localize_error(&self, locale: &str, error: &mut StatusError) {
+        let code = error.code.as_u16();
+        if let Some(name) = self.message(locale, &format!("status-{code}"), &[]) {
+            error.name = name;
+        }
+        let is_default_brief = StatusError::from_code(error.code).is_some_and(|default| default.brief == error.brief);
+        let brief = if is_default_brief {
+            self.message(locale, &format!("status-{code}-brief"), &[])
+        } else {
+            self.message(locale, &error.brief, &[])
+        };
+        if let Some(brief) = brief {
+            error.brief = brief;
+        }
+        if let Some(detail) = error.detail.as_deref().and_then(|detail| self.message(locale, detail, &[])) {
+            error.detail = Some(detail);
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for Translator {
+    async fn handle(&self, _req: &mut Request, depot: &mut Depot, _res: &mut Response, _ctrl: &mut FlowCtrl) {
+        depot.insert(TRANSLATOR_KEY, self.clone());
+    }
+}
+
+/// Middleware translating the [`StatusError`]s rendered in responses with the [`Translator`] in depot.
+///
+/// It can be added to the hoops of a router, to translate errors rendered by its handlers, like validation errors,
+/// and to the hoops of a [`Catcher`](salvo_core::catcher::Catcher), to translate error pages.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::catcher::Catcher;
+/// use salvo_core::prelude::*;
+/// use salvo_extra::locale::{Locale, LocalizeErrors, Translator};
+///
+/// let translator = Translator::builder("en")
+///     .add_messages("fr", [("status-404", "Introuvable")])
+///     .build();
+/// let service = Service::new(Router::new())
+///     .hoop(Locale::new(["en", "fr"]))
+///     .hoop(translator)
+///     .catcher(Catcher::default().hoop(LocalizeErrors));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalizeErrors;
+
+impl LocalizeErrors {
+    fn localize(depot: &Depot, res: &mut Response) {
+        let Some(translator) = depot.translator() else {
+            return;
+        };
+        let locale = depot.locale().unwrap_or(translator.fallback_locale());
+        if res.body.is_none() {
+            if let Some(mut error) = res.status_code.and_then(StatusError::from_code) {
+                translator.localize_error(locale, &mut error);
+                res.render(error);
+            }
+        } else if let ResBody::Error(error) = &mut res.body {
+            translator.localize_error(locale, error);
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for LocalizeErrors {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        // In a catcher, the error is localized before the error page is written by the next handlers.
+        Self::localize(depot, res);
+        ctrl.call_next(req, depot, res).await;
+        Self::localize(depot, res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::catcher::Catcher;
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::locale::Locale;
+
+    fn translator() -> Translator {
+        Translator::builder("en")
+            .add_messages("en", [("hello", "Hello {name}!"), ("invalid-email", "Invalid email.")])
+            .add_messages(
+                "fr",
+                [
+                    ("hello", "Bonjour {name} !"),
+                    ("invalid-email", "Email invalide."),
+                    ("status-404", "Introuvable"),
+                    ("status-404-brief", "La ressource est introuvable."),
+                ],
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_translate() {
+        let translator = translator();
+        assert_eq!(translator.translate("fr-CA", "hello", &[("name", "Salvo")]), "Bonjour Salvo !");
+        assert_eq!(translator.translate("de", "hello", &[("name", "Salvo")]), "Hello Salvo!");
+        assert_eq!(translator.translate("fr", "missing", &[]), "missing");
+
+        let mut error = StatusError::not_found();
+        translator.localize_error("fr", &mut error);
+        assert_eq!(error.name, "Introuvable");
+        assert_eq!(error.brief, "La ressource est introuvable.");
+        let mut error = StatusError::bad_request().brief("invalid-email");
+        translator.localize_error("en", &mut error);
+        assert_eq!(error.brief, "Invalid email.");
+    }
+
+    #[tokio::test]
+    async fn test_localize_errors() {
+        #[handler]
+        async fn hello(depot: &mut Depot) -> String {
+            depot.tr_args("hello", &[("name", "Salvo")])
+        }
+        #[handler]
+        async fn signup(res: &mut Response) {
+            res.render(StatusError::bad_request().brief("invalid-email"));
+        }
+        let router = Router::new()
+            .hoop(LocalizeErrors)
+            .get(hello)
+            .push(Router::with_path("signup").post(signup));
+        let service = Service::new(router)
+            .hoop(Locale::new(["en", "fr"]))
+            .hoop(translator())
+            .catcher(Catcher::default().hoop(LocalizeErrors));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("accept-language", "fr", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "Bonjour Salvo !");
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/signup")
+            .add_header("accept-language", "fr", true)
+            .add_header("accept", "text/plain", true)
+            .send(&service)
+            .await;
+        assert!(res.take_string().await.unwrap().contains("Email invalide."));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/missing")
+            .add_header("accept-language", "fr", true)
+            .add_header("accept", "text/plain", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::NOT_FOUND);
+        assert!(res.take_string().await.unwrap().contains("Introuvable"));
+    }
+
+    #[cfg(feature = "fluent")]
+    #[test]
+    fn test_fluent() {
+        let translator = Translator::builder("en")
+            .add_ftl("en", "hello = Hello { $name }!\n")
+            .unwrap()
+            .add_ftl("fr", "hello = Bonjour { $name } !\n")
+            .unwrap()
+            .build();
+        assert_eq!(translator.translate("fr", "hello", &[("name", "Salvo")]), "Bonjour Salvo !");
+        assert_eq!(translator.translate("en-US", "hello", &[("name", "Salvo")]), "Hello Salvo!");
+    }
+}
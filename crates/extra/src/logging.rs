@@ -1,24 +1,118 @@
 //! Simple logging middleware.
 //!
 //! Read more: <https://salvo.rs>
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 
 use tracing::{Instrument, Level};
 
+use salvo_core::http::header::{HeaderName, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE};
 use salvo_core::http::{Request, ResBody, Response, StatusCode};
 use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
 
+const REDACTED: &str = "[REDACTED]";
+
 /// A simple logger middleware.
-#[derive(Default, Debug)]
-pub struct Logger {}
+///
+/// Every response is logged as a structured `tracing` event with `method`, `path`, `route` (the matched
+/// path pattern), `status`, `duration`, `bytes`, `remote_addr` and `request_id` fields. The `bytes` field is
+/// skipped if the size of the body is not known, like streaming bodies.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::http::header::HeaderName;
+/// use salvo_core::prelude::*;
+/// use salvo_extra::logging::Logger;
+///
+/// let logger = Logger::new()
+///     .log_headers(true)
+///     .redact_header(HeaderName::from_static("x-api-key"))
+///     .sample_rate(0.1);
+/// let router = Router::new().hoop(logger);
+/// ```
+#[derive(Debug)]
+pub struct Logger {
+    log_headers: bool,
+    redacted_headers: Vec<HeaderName>,
+    sample_rate: f64,
+    counter: AtomicU64,
+}
+impl Default for Logger {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Logger {
     /// Create new `Logger` middleware.
     #[inline]
     pub fn new() -> Self {
-        Logger {}
+        Logger {
+            log_headers: false,
+            redacted_headers: vec![AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE],
+            sample_rate: 1.0,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets whether request headers are logged, defaults to `false`.
+    #[inline]
+    pub fn log_headers(mut self, log_headers: bool) -> Self {
+        self.log_headers = log_headers;
+        self
+    }
+
+    /// Adds a header whose value is replaced by `[REDACTED]` in logs.
+    ///
+    /// `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie` are redacted by default.
+    #[inline]
+    pub fn redact_header(mut self, name: HeaderName) -> Self {
+        self.redacted_headers.push(name);
+        self
+    }
+
+    /// Sets the fraction of successful responses to log, in range `0.0..=1.0`, defaults to `1.0`.
+    ///
+    /// Responses with client or server error status are always logged.
+    #[inline]
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    fn sampled(&self) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        // Deterministic sampling, logs exactly `sample_rate` of responses over time.
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.sample_rate).floor() > (n * self.sample_rate).floor()
+    }
+
+    fn headers(&self, req: &Request) -> String {
+        let headers = req.headers().iter().map(|(name, value)| {
+            let value = if self.redacted_headers.contains(name) {
+                REDACTED
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{name}: {value}")
+        });
+        headers.collect::<Vec<_>>().join(", ")
     }
 }
 
+fn request_id(req: &Request, depot: &Depot) -> Option<String> {
+    #[cfg(feature = "request-id")]
+    if let Ok(id) = depot.get::<String>(crate::request_id::REQUST_ID_KEY) {
+        return Some(id.clone());
+    }
+    #[cfg(not(feature = "request-id"))]
+    let _ = depot;
+    req.header::<String>("x-request-id")
+}
+
 #[async_trait]
 impl Handler for Logger {
     async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
@@ -29,7 +123,11 @@ impl Handler for Logger {
             version = ?req.version(),
             method = %req.method(),
             path = %req.uri(),
+            route = req.matched_path().unwrap_or_default(),
         );
+        if self.log_headers {
+            span.in_scope(|| tracing::info!(headers = %self.headers(req), "Request headers"));
+        }
 
         async move {
             let now = Instant::now();
@@ -41,11 +139,15 @@ impl Handler for Logger {
                 ResBody::Error(e) => e.code,
                 _ => StatusCode::OK,
             });
-            tracing::info!(
-                %status,
-                ?duration,
-                "Response"
-            );
+            if status.is_client_error() || status.is_server_error() || self.sampled() {
+                tracing::info!(
+                    %status,
+                    ?duration,
+                    bytes = res.body.size(),
+                    request_id = request_id(req, depot),
+                    "Response"
+                );
+            }
         }
         .instrument(span)
         .await
@@ -80,4 +182,56 @@ mod tests {
             .unwrap();
         assert!(logs_contain("duration"));
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_log_fields() {
+        #[handler]
+        async fn hello() -> &'static str {
+            "hello"
+        }
+
+        let router = Router::new()
+            .hoop(Logger::new().log_headers(true))
+            .push(Router::with_path("users/<id>").get(hello));
+
+        TestClient::get("http://127.0.0.1:5801/users/7")
+            .add_header("authorization", "Bearer secret", true)
+            .add_header("x-request-id", "abc", true)
+            .send(router)
+            .await;
+        assert!(logs_contain("route=\"/users/<id>\""));
+        assert!(logs_contain("bytes=5"));
+        assert!(logs_contain("request_id=\"abc\""));
+        assert!(logs_contain("authorization: [REDACTED]"));
+        assert!(!logs_contain("secret"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_log_streaming_body() {
+        #[handler]
+        async fn hello(res: &mut Response) {
+            res.stream(tokio_stream::iter(vec![Ok::<_, std::io::Error>("hello")]));
+        }
+
+        let router = Router::new().hoop(Logger::new()).get(hello);
+        let content = TestClient::get("http://127.0.0.1:5801/")
+            .send(router)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert_eq!(content, "hello");
+        assert!(logs_contain("duration"));
+        assert!(!logs_contain("bytes="));
+    }
+
+    #[test]
+    fn test_sample_rate() {
+        let logger = Logger::new().sample_rate(0.25);
+        assert_eq!((0..100).filter(|_| logger.sampled()).count(), 25);
+        let logger = Logger::new().sample_rate(0.0);
+        assert!(!(0..100).any(|_| logger.sampled()));
+    }
 }
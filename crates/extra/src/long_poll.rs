@@ -0,0 +1,251 @@
+//! Long polling support.
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::HashMap;
+use std::fmt::{self, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use salvo_core::http::{Response, StatusCode};
+use salvo_core::writing::Scribe;
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+
+/// Waiters of long polling requests, grouped by topics.
+///
+/// Requests [waiting](LongPoll::wait) on the same topic share one channel, so a value
+/// [published](LongPoll::publish) to a topic wakes all of them at once. Requests not woken before their timeout
+/// get `None`, which [`respond`](LongPoll::respond) renders as `204 No Content` so clients poll again. Topics are
+/// removed when they have no waiters left.
+///
+/// Cloned `LongPoll`s share the same topics.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::long_poll::LongPoll;
+///
+/// struct Messages {
+///     long_poll: LongPoll<String>,
+/// }
+///
+/// #[handler]
+/// impl Messages {
+///     async fn handle(&self, req: &mut Request, res: &mut Response) {
+///         let room = req.param::<String>("room").unwrap_or_default();
+///         self.long_poll.respond(&room, res).await;
+///     }
+/// }
+///
+/// let long_poll = LongPoll::new();
+/// let router = Router::with_path("rooms/<room>/messages").get(Messages {
+///     long_poll: long_poll.clone(),
+/// });
+///
+/// // When a message is posted.
+/// long_poll.publish("lobby", "hello".to_owned());
+/// ```
+pub struct LongPoll<T> {
+    topics: Arc<Mutex<HashMap<String, Sender<T>>>>,
+    timeout: Duration,
+}
+impl<T> Clone for LongPoll<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        LongPoll {
+            topics: self.topics.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+impl<T> fmt::Debug for LongPoll<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LongPoll").field("timeout", &self.timeout).finish()
+    }
+}
+impl<T> Default for LongPoll<T>
+where
+    T: Clone + Send + 'static,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> LongPoll<T>
+where
+    T: Clone + Send + 'static,
+{
+    /// Create a new `LongPoll`.
+    #[inline]
+    pub fn new() -> Self {
+        LongPoll {
+            topics: Default::default(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets how long requests wait for a value, defaults to 30 seconds.
+    ///
+    /// It should be shorter than the timeouts of clients and proxies.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Waits for the next value published to `topic`, returns `None` after the default timeout.
+    #[inline]
+    pub async fn wait(&self, topic: &str) -> Option<T> {
+        self.wait_timeout(topic, self.timeout).await
+    }
+
+    /// Waits for the next value published to `topic`, returns `None` after `timeout`.
+    pub async fn wait_timeout(&self, topic: &str, timeout: Duration) -> Option<T> {
+        let receiver = {
+            let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+            topics
+                .entry(topic.to_owned())
+                .or_insert_with(|| broadcast::channel(1).0)
+                .subscribe()
+        };
+        // The topic is removed by the guard, also when the waiting request is cancelled.
+        let mut waiter = Waiter {
+            long_poll: self,
+            topic,
+            receiver: Some(receiver),
+        };
+        let receiver = waiter.receiver.as_mut().expect("receiver should be set");
+        let value = tokio::time::timeout(timeout, async {
+            loop {
+                match receiver.recv().await {
+                    Ok(value) => return Some(value),
+                    // Only the latest value is kept for slow waiters.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .await
+        .ok()
+        .flatten();
+        value
+    }
+
+    /// Waits for the next value published to `topic` and writes it to the response, or sets the status code
+    /// to `204 No Content` after the default timeout.
+    pub async fn respond(&self, topic: &str, res: &mut Response)
+    where
+        T: Scribe,
+    {
+        match self.wait(topic).await {
+            Some(value) => res.render(value),
+            None => {
+                res.status_code(StatusCode::NO_CONTENT);
+            }
+        }
+    }
+
+    /// Wakes all requests waiting on `topic` with `value`.
+    ///
+    /// Returns the number of requests woken, the value is dropped if there is none.
+    pub fn publish(&self, topic: &str, value: T) -> usize {
+        let topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        topics
+            .get(topic)
+            .and_then(|sender| sender.send(value).ok())
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of requests waiting on `topic`.
+    pub fn waiter_count(&self, topic: &str) -> usize {
+        let topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        topics.get(topic).map(Sender::receiver_count).unwrap_or(0)
+    }
+}
+
+/// Subscription of a waiting request, the topic is removed when its last waiter is dropped.
+struct Waiter<'a, T> {
+    long_poll: &'a LongPoll<T>,
+    topic: &'a str,
+    receiver: Option<Receiver<T>>,
+}
+impl<T> Drop for Waiter<'_, T> {
+    fn drop(&mut self) {
+        // The receiver is dropped first, so it is not counted as a waiter.
+        drop(self.receiver.take());
+        let mut topics = self.long_poll.topics.lock().unwrap_or_else(|e| e.into_inner());
+        if topics
+            .get(self.topic)
+            .is_some_and(|sender| sender.receiver_count() == 0)
+        {
+            topics.remove(self.topic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_long_poll() {
+        let long_poll = LongPoll::<String>::new().timeout(Duration::from_millis(100));
+        let first = tokio::spawn({
+            let long_poll = long_poll.clone();
+            async move { long_poll.wait("lobby").await }
+        });
+        let second = tokio::spawn({
+            let long_poll = long_poll.clone();
+            async move { long_poll.wait_timeout("lobby", Duration::from_secs(5)).await }
+        });
+        while long_poll.waiter_count("lobby") < 2 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(long_poll.publish("other", "ignored".into()), 0);
+        assert_eq!(long_poll.publish("lobby", "hello".into()), 2);
+        assert_eq!(first.await.unwrap().as_deref(), Some("hello"));
+        assert_eq!(second.await.unwrap().as_deref(), Some("hello"));
+        assert_eq!(long_poll.wait("lobby").await, None);
+        assert!(long_poll.topics.lock().unwrap().is_empty());
+
+        let cancelled = tokio::time::timeout(Duration::from_millis(10), long_poll.wait("lobby")).await;
+        assert!(cancelled.is_err());
+        assert!(long_poll.topics.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_long_poll_respond() {
+        struct Messages {
+            long_poll: LongPoll<String>,
+        }
+        #[handler]
+        impl Messages {
+            async fn handle(&self, res: &mut Response) {
+                self.long_poll.respond("lobby", res).await;
+            }
+        }
+
+        let long_poll = LongPoll::new().timeout(Duration::from_millis(100));
+        let service = Service::new(Router::new().get(Messages {
+            long_poll: long_poll.clone(),
+        }));
+
+        let res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::NO_CONTENT));
+
+        let publisher = async {
+            while long_poll.waiter_count("lobby") == 0 {
+                tokio::task::yield_now().await;
+            }
+            long_poll.publish("lobby", "hello".to_owned());
+        };
+        let (mut res, _) = tokio::join!(TestClient::get("http://127.0.0.1:5801/").send(&service), publisher);
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "hello");
+    }
+}
@@ -0,0 +1,254 @@
+//! Maintenance mode middleware.
+//!
+//! Read more: <https://salvo.rs>
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use salvo_core::handler::Skipper;
+use salvo_core::http::header::{HeaderName, COOKIE, RETRY_AFTER};
+use salvo_core::http::{HeaderValue, Request, Response, StatusCode, StatusError};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// A switch turning maintenance mode on and off at runtime.
+///
+/// Cloned switches share the same state, keep one in the application and pass a clone to [`Maintenance`].
+#[derive(Clone, Debug, Default)]
+pub struct MaintenanceSwitch {
+    enabled: Arc<AtomicBool>,
+}
+impl MaintenanceSwitch {
+    /// Create a new `MaintenanceSwitch`.
+    #[inline]
+    pub fn new(enabled: bool) -> Self {
+        MaintenanceSwitch {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    /// Turns maintenance mode on.
+    #[inline]
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Turns maintenance mode off.
+    #[inline]
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if maintenance mode is on.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+}
+
+/// Maintenance
+///
+/// Rejects all requests with `503 Service Unavailable` while the [`MaintenanceSwitch`] is on. Requests carrying a
+/// bypass token in the [`bypass_header`](Maintenance::bypass_header) header or the
+/// [`bypass_cookie`](Maintenance::bypass_cookie) cookie, and requests skipped by the
+/// [`skipper`](Maintenance::skipper) are passed through, so the team can check the site before it is reopened.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use salvo_core::prelude::*;
+/// use salvo_extra::maintenance::{Maintenance, MaintenanceSwitch};
+///
+/// let switch = MaintenanceSwitch::new(false);
+/// let maintenance = Maintenance::new(switch.clone())
+///     .retry_after(Duration::from_secs(600))
+///     .bypass_token("secret-token")
+///     .skipper(|req: &mut Request, _depot: &Depot| req.uri().path() == "/health");
+/// let service = Service::new(Router::new()).hoop(maintenance);
+///
+/// // Later, when the maintenance window starts.
+/// switch.enable();
+/// ```
+pub struct Maintenance {
+    switch: MaintenanceSwitch,
+    retry_after: Option<Duration>,
+    bypass_tokens: Vec<String>,
+    bypass_header: HeaderName,
+    bypass_cookie: String,
+    skipper: Option<Box<dyn Skipper>>,
+    handler: Option<Box<dyn Handler>>,
+}
+impl Maintenance {
+    /// Create new `Maintenance` middleware.
+    #[inline]
+    pub fn new(switch: MaintenanceSwitch) -> Self {
+        Maintenance {
+            switch,
+            retry_after: None,
+            bypass_tokens: vec![],
+            bypass_header: HeaderName::from_static("x-maintenance-bypass"),
+            bypass_cookie: "maintenance_bypass".into(),
+            skipper: None,
+            handler: None,
+        }
+    }
+
+    /// Sets the `Retry-After` header of rejected responses.
+    #[inline]
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// Adds a token which lets requests bypass maintenance mode.
+    #[inline]
+    pub fn bypass_token(mut self, token: impl Into<String>) -> Self {
+        self.bypass_tokens.push(token.into());
+        self
+    }
+
+    /// Sets the header carrying the bypass token, defaults to `X-Maintenance-Bypass`.
+    #[inline]
+    pub fn bypass_header(mut self, name: HeaderName) -> Self {
+        self.bypass_header = name;
+        self
+    }
+
+    /// Sets the cookie carrying the bypass token, defaults to `maintenance_bypass`.
+    #[inline]
+    pub fn bypass_cookie(mut self, name: impl Into<String>) -> Self {
+        self.bypass_cookie = name.into();
+        self
+    }
+
+    /// Uses a closure to determine if a request is passed through, for example health checks or an IP allowlist.
+    #[inline]
+    pub fn skipper(mut self, skipper: impl Skipper) -> Self {
+        self.skipper = Some(Box::new(skipper));
+        self
+    }
+
+    /// Sets a handler rendering the maintenance page, the status code is set to `503 Service Unavailable`
+    /// if the handler does not set it.
+    #[inline]
+    pub fn handler(mut self, handler: impl Handler) -> Self {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    fn has_bypass_token(&self, req: &Request) -> bool {
+        if self.bypass_tokens.is_empty() {
+            return false;
+        }
+        let is_token = |value: &str| self.bypass_tokens.iter().any(|token| token == value.trim());
+        if let Some(value) = req.headers().get(&self.bypass_header) {
+            if value.to_str().is_ok_and(is_token) {
+                return true;
+            }
+        }
+        req.headers()
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .any(|(name, value)| name == self.bypass_cookie && is_token(value))
+    }
+}
+
+#[async_trait]
+impl Handler for Maintenance {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        if !self.switch.is_enabled()
+            || self.has_bypass_token(req)
+            || self.skipper.as_ref().is_some_and(|skipper| skipper.skipped(req, depot))
+        {
+            return;
+        }
+        if let Some(handler) = &self.handler {
+            handler.handle(req, depot, res, ctrl).await;
+            if res.status_code.is_none() {
+                res.status_code(StatusCode::SERVICE_UNAVAILABLE);
+            }
+        } else {
+            res.render(StatusError::service_unavailable().brief("The service is under maintenance."));
+        }
+        if let Some(retry_after) = self.retry_after {
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs()));
+        }
+        ctrl.skip_rest();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn hello() -> &'static str {
+        "hello"
+    }
+
+    #[tokio::test]
+    async fn test_maintenance() {
+        let switch = MaintenanceSwitch::new(false);
+        let maintenance = Maintenance::new(switch.clone())
+            .retry_after(Duration::from_secs(600))
+            .bypass_token("secret")
+            .skipper(|req: &mut Request, _depot: &Depot| req.uri().path() == "/health");
+        let router = Router::new().get(hello).push(Router::with_path("health").get(hello));
+        let service = Service::new(router).hoop(maintenance);
+
+        let res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        switch.enable();
+        let res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(res.headers()[RETRY_AFTER], "600");
+        let res = TestClient::get("http://127.0.0.1:5801/missing").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+
+        let res = TestClient::get("http://127.0.0.1:5801/health").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let res = TestClient::get("http://127.0.0.1:5801/")
+            .add_header("x-maintenance-bypass", "secret", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let res = TestClient::get("http://127.0.0.1:5801/")
+            .add_header("cookie", "theme=dark; maintenance_bypass=secret", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let res = TestClient::get("http://127.0.0.1:5801/")
+            .add_header("x-maintenance-bypass", "wrong", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+
+        switch.disable();
+        let res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_page() {
+        #[handler]
+        async fn page(res: &mut Response) {
+            res.render(Text::Html("<h1>Back soon</h1>"));
+        }
+
+        let maintenance = Maintenance::new(MaintenanceSwitch::new(true)).handler(page);
+        let service = Service::new(Router::new().get(hello)).hoop(maintenance);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(res.take_string().await.unwrap(), "<h1>Back soon</h1>");
+    }
+}
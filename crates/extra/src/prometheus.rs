@@ -0,0 +1,498 @@
+//! Prometheus metrics middleware.
+//!
+//! [`PrometheusMetrics`] records for every request:
+//!
+//! - `http_requests_total`: counter of finished requests.
+//! - `http_request_duration_seconds`: histogram of request latencies.
+//! - `http_response_size_bytes`: histogram of response body sizes, when the size is known.
+//! - `http_requests_in_flight`: gauge of requests being handled.
+//!
+//! Requests are labelled by `method`, matched route pattern (`route`, like `/users/<id>`) and status class
+//! (`status`, like `2xx`), so the number of series does not grow with the number of distinct paths. Requests
+//! which match no route are labelled with route `unmatched`.
+//!
+//! The metrics are recorded in a [`MetricsRegistry`], which is [`PrometheusRegistry`] by default, and rendered in
+//! the Prometheus text format by [`metrics_handler`].
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use salvo_core::prelude::*;
+//! use salvo_extra::prometheus::{metrics_handler, PrometheusMetrics, PrometheusRegistry};
+//!
+//! #[handler]
+//! async fn hello() -> &'static str {
+//!     "Hello World"
+//! }
+//!
+//! let registry = Arc::new(PrometheusRegistry::new());
+//! let router = Router::new()
+//!     .push(Router::with_path("metrics").get(metrics_handler(registry.clone())))
+//!     .push(Router::with_path("hello").get(hello));
+//! let service = Service::new(router).hoop(PrometheusMetrics::with_registry(registry));
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use salvo_core::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use salvo_core::http::{HeaderValue, Method, Request, Response, StatusCode};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// Content type of the Prometheus text format.
+pub const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Route label of requests which match no route.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Default buckets of the request duration histogram, in seconds.
+pub const DEFAULT_DURATION_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Default buckets of the response size histogram, in bytes.
+pub const DEFAULT_SIZE_BUCKETS: &[f64] = &[100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0, 10_000_000.0];
+
+/// A finished request, recorded by [`MetricsRegistry::request_finished`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RequestRecord<'a> {
+    /// Method of the request.
+    pub method: &'a Method,
+    /// Matched route pattern of the request, or [`UNMATCHED_ROUTE`].
+    pub route: &'a str,
+    /// Status code of the response.
+    pub status_code: StatusCode,
+    /// Time spent handling the request.
+    pub duration: Duration,
+    /// Size of the response body, if known.
+    pub response_size: Option<u64>,
+}
+
+/// Storage of the metrics recorded by [`PrometheusMetrics`].
+///
+/// Implement it to export metrics to another system, or to add metrics to the ones recorded by
+/// [`PrometheusRegistry`].
+pub trait MetricsRegistry: Send + Sync + 'static {
+    /// Called when a request starts to be handled.
+    fn request_started(&self, method: &Method, route: &str);
+    /// Called when a request is handled, or dropped before.
+    fn request_finished(&self, record: &RequestRecord<'_>);
+    /// Renders the metrics in the Prometheus text format.
+    fn render(&self) -> String;
+}
+
+impl<R: MetricsRegistry + ?Sized> MetricsRegistry for Arc<R> {
+    #[inline]
+    fn request_started(&self, method: &Method, route: &str) {
+        (**self).request_started(method, route)
+    }
+    #[inline]
+    fn request_finished(&self, record: &RequestRecord<'_>) {
+        (**self).request_finished(record)
+    }
+    #[inline]
+    fn render(&self) -> String {
+        (**self).render()
+    }
+}
+
+/// Returns the status class of a status code, like `2xx`.
+pub fn status_class(status_code: StatusCode) -> &'static str {
+    match status_code.as_u16() / 100 {
+        1 => "1xx",
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        _ => "5xx",
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+impl Histogram {
+    fn new(buckets: usize) -> Self {
+        Self {
+            counts: vec![0; buckets],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+    fn observe(&mut self, bounds: &[f64], value: f64) {
+        for (count, bound) in self.counts.iter_mut().zip(bounds) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+type RequestLabels = (String, String, &'static str);
+
+#[derive(Default, Debug)]
+struct Series {
+    requests: BTreeMap<RequestLabels, u64>,
+    durations: BTreeMap<RequestLabels, Histogram>,
+    sizes: BTreeMap<RequestLabels, Histogram>,
+    in_flight: BTreeMap<(String, String), i64>,
+}
+
+/// Default [`MetricsRegistry`], keeping the metrics in memory.
+#[derive(Debug)]
+pub struct PrometheusRegistry {
+    prefix: String,
+    duration_buckets: Vec<f64>,
+    size_buckets: Vec<f64>,
+    series: Mutex<Series>,
+}
+
+impl Default for PrometheusRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrometheusRegistry {
+    /// Create a new `PrometheusRegistry` with the default buckets.
+    pub fn new() -> Self {
+        Self {
+            prefix: String::new(),
+            duration_buckets: DEFAULT_DURATION_BUCKETS.to_vec(),
+            size_buckets: DEFAULT_SIZE_BUCKETS.to_vec(),
+            series: Mutex::new(Series::default()),
+        }
+    }
+
+    /// Sets the prefix of the metric names, like `myapp_` for `myapp_http_requests_total`.
+    #[inline]
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets the buckets of the request duration histogram, in seconds.
+    #[inline]
+    pub fn duration_buckets(mut self, buckets: impl Into<Vec<f64>>) -> Self {
+        self.duration_buckets = sorted(buckets.into());
+        self
+    }
+
+    /// Sets the buckets of the response size histogram, in bytes.
+    #[inline]
+    pub fn size_buckets(mut self, buckets: impl Into<Vec<f64>>) -> Self {
+        self.size_buckets = sorted(buckets.into());
+        self
+    }
+
+    fn series(&self) -> std::sync::MutexGuard<'_, Series> {
+        self.series.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn render_histograms(
+        &self,
+        out: &mut String,
+        name: &str,
+        help: &str,
+        bounds: &[f64],
+        histograms: &BTreeMap<RequestLabels, Histogram>,
+    ) -> fmt::Result {
+        writeln!(out, "# HELP {}{name} {help}", self.prefix)?;
+        writeln!(out, "# TYPE {}{name} histogram", self.prefix)?;
+        for ((method, route, status), histogram) in histograms {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\",status=\"{status}\"",
+                escape(method),
+                escape(route)
+            );
+            for (count, bound) in histogram.counts.iter().zip(bounds) {
+                writeln!(out, "{}{name}_bucket{{{labels},le=\"{bound}\"}} {count}", self.prefix)?;
+            }
+            writeln!(
+                out,
+                "{}{name}_bucket{{{labels},le=\"+Inf\"}} {}",
+                self.prefix, histogram.count
+            )?;
+            writeln!(out, "{}{name}_sum{{{labels}}} {}", self.prefix, histogram.sum)?;
+            writeln!(out, "{}{name}_count{{{labels}}} {}", self.prefix, histogram.count)?;
+        }
+        Ok(())
+    }
+
+    fn write_metrics(&self, out: &mut String) -> fmt::Result {
+        let series = self.series();
+        let prefix = &self.prefix;
+
+        writeln!(
+            out,
+            "# HELP {prefix}http_requests_total Total number of finished HTTP requests."
+        )?;
+        writeln!(out, "# TYPE {prefix}http_requests_total counter")?;
+        for ((method, route, status), count) in &series.requests {
+            writeln!(
+                out,
+                "{prefix}http_requests_total{{method=\"{}\",route=\"{}\",status=\"{status}\"}} {count}",
+                escape(method),
+                escape(route)
+            )?;
+        }
+        self.render_histograms(
+            out,
+            "http_request_duration_seconds",
+            "HTTP request latencies in seconds.",
+            &self.duration_buckets,
+            &series.durations,
+        )?;
+        self.render_histograms(
+            out,
+            "http_response_size_bytes",
+            "HTTP response body sizes in bytes.",
+            &self.size_buckets,
+            &series.sizes,
+        )?;
+        writeln!(
+            out,
+            "# HELP {prefix}http_requests_in_flight Number of HTTP requests being handled."
+        )?;
+        writeln!(out, "# TYPE {prefix}http_requests_in_flight gauge")?;
+        for ((method, route), count) in &series.in_flight {
+            writeln!(
+                out,
+                "{prefix}http_requests_in_flight{{method=\"{}\",route=\"{}\"}} {count}",
+                escape(method),
+                escape(route)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl MetricsRegistry for PrometheusRegistry {
+    fn request_started(&self, method: &Method, route: &str) {
+        *self
+            .series()
+            .in_flight
+            .entry((method.to_string(), route.to_owned()))
+            .or_default() += 1;
+    }
+
+    fn request_finished(&self, record: &RequestRecord<'_>) {
+        let mut series = self.series();
+        if let Some(in_flight) = series
+            .in_flight
+            .get_mut(&(record.method.to_string(), record.route.to_owned()))
+        {
+            *in_flight -= 1;
+        }
+        let labels = (
+            record.method.to_string(),
+            record.route.to_owned(),
+            status_class(record.status_code),
+        );
+        *series.requests.entry(labels.clone()).or_default() += 1;
+        series
+            .durations
+            .entry(labels.clone())
+            .or_insert_with(|| Histogram::new(self.duration_buckets.len()))
+            .observe(&self.duration_buckets, record.duration.as_secs_f64());
+        if let Some(size) = record.response_size {
+            series
+                .sizes
+                .entry(labels)
+                .or_insert_with(|| Histogram::new(self.size_buckets.len()))
+                .observe(&self.size_buckets, size as f64);
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        // Writing to a `String` never fails.
+        let _ = self.write_metrics(&mut out);
+        out
+    }
+}
+
+fn sorted(mut buckets: Vec<f64>) -> Vec<f64> {
+    buckets.retain(|bound| bound.is_finite());
+    buckets.sort_by(|a, b| a.total_cmp(b));
+    buckets.dedup();
+    buckets
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Middleware recording Prometheus metrics of requests, see the [module documentation](self).
+///
+/// It should be added to the [`Service`](salvo_core::Service), so requests which match no route are recorded as well.
+/// Added to a router, only the requests matching the router are recorded.
+pub struct PrometheusMetrics {
+    registry: Arc<dyn MetricsRegistry>,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrometheusMetrics {
+    /// Create a new `PrometheusMetrics` with a new [`PrometheusRegistry`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_registry(Arc::new(PrometheusRegistry::new()))
+    }
+
+    /// Create a new `PrometheusMetrics` recording metrics in the given registry.
+    #[inline]
+    pub fn with_registry(registry: Arc<dyn MetricsRegistry>) -> Self {
+        Self { registry }
+    }
+
+    /// Returns the registry of this middleware, to be passed to [`metrics_handler`].
+    #[inline]
+    pub fn registry(&self) -> Arc<dyn MetricsRegistry> {
+        self.registry.clone()
+    }
+}
+
+/// Records the request as finished when dropped, so requests cancelled by the client are recorded as well.
+struct InFlight<'a> {
+    registry: &'a dyn MetricsRegistry,
+    method: Method,
+    route: String,
+    started: Instant,
+    status_code: StatusCode,
+    response_size: Option<u64>,
+}
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.registry.request_finished(&RequestRecord {
+            method: &self.method,
+            route: &self.route,
+            status_code: self.status_code,
+            duration: self.started.elapsed(),
+            response_size: self.response_size,
+        });
+    }
+}
+
+#[async_trait]
+impl Handler for PrometheusMetrics {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let method = req.method().clone();
+        let matched = req.matched_path().is_some();
+        let route = req.matched_path().unwrap_or(UNMATCHED_ROUTE).to_owned();
+        self.registry.request_started(&method, &route);
+        let mut in_flight = InFlight {
+            registry: &*self.registry,
+            method,
+            route,
+            started: Instant::now(),
+            // Recorded when the request is dropped before a response is written, like nginx does.
+            status_code: StatusCode::from_u16(499).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            response_size: None,
+        };
+
+        ctrl.call_next(req, depot, res).await;
+
+        in_flight.status_code = res
+            .status_code
+            .unwrap_or(if matched { StatusCode::OK } else { StatusCode::NOT_FOUND });
+        in_flight.response_size = res.body.size().or_else(|| {
+            res.headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+        });
+    }
+}
+
+/// Handler rendering the metrics of a [`MetricsRegistry`] in the Prometheus text format.
+pub struct MetricsHandler {
+    registry: Arc<dyn MetricsRegistry>,
+}
+
+/// Create a [`MetricsHandler`] rendering the metrics of `registry`, usually mounted at `/metrics`.
+#[inline]
+pub fn metrics_handler(registry: Arc<dyn MetricsRegistry>) -> MetricsHandler {
+    MetricsHandler { registry }
+}
+
+#[async_trait]
+impl Handler for MetricsHandler {
+    async fn handle(&self, _req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+        res.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(TEXT_FORMAT));
+        res.write_body(self.registry.render()).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn user() -> &'static str {
+        "alice"
+    }
+
+    #[handler]
+    async fn fail(res: &mut Response) {
+        res.render(StatusError::bad_request());
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_metrics() {
+        let registry = Arc::new(PrometheusRegistry::new().prefix("app_"));
+        let router = Router::new()
+            .push(Router::with_path("metrics").get(metrics_handler(registry.clone())))
+            .push(Router::with_path("users/<id>").get(user).post(fail));
+        let service = Service::new(router).hoop(PrometheusMetrics::with_registry(registry));
+
+        for id in 1..=3 {
+            TestClient::get(format!("http://127.0.0.1:5801/users/{id}"))
+                .send(&service)
+                .await;
+        }
+        TestClient::post("http://127.0.0.1:5801/users/1").send(&service).await;
+        TestClient::get("http://127.0.0.1:5801/other").send(&service).await;
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/metrics").send(&service).await;
+        assert_eq!(res.headers()[CONTENT_TYPE], TEXT_FORMAT);
+        let body = res.take_string().await.unwrap();
+        assert!(body.contains("# TYPE app_http_requests_total counter"));
+        assert!(body.contains(r#"app_http_requests_total{method="GET",route="/users/<id>",status="2xx"} 3"#));
+        assert!(body.contains(r#"app_http_requests_total{method="POST",route="/users/<id>",status="4xx"} 1"#));
+        assert!(body.contains(r#"app_http_requests_total{method="GET",route="unmatched",status="4xx"} 1"#));
+        assert!(body
+            .contains(r#"app_http_request_duration_seconds_count{method="GET",route="/users/<id>",status="2xx"} 3"#));
+        assert!(body.contains(
+            r#"app_http_response_size_bytes_bucket{method="GET",route="/users/<id>",status="2xx",le="100"} 3"#
+        ));
+        assert!(body.contains(r#"app_http_response_size_bytes_sum{method="GET",route="/users/<id>",status="2xx"} 15"#));
+        assert!(body.contains(r#"app_http_requests_in_flight{method="GET",route="/users/<id>"} 0"#));
+        assert!(body.contains(r#"app_http_requests_in_flight{method="GET",route="/metrics"} 1"#));
+    }
+
+    #[test]
+    fn test_escape_and_buckets() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+        let registry = PrometheusRegistry::new().duration_buckets(vec![1.0, f64::INFINITY, 0.5, 1.0]);
+        assert_eq!(registry.duration_buckets, vec![0.5, 1.0]);
+        assert_eq!(status_class(StatusCode::CONTINUE), "1xx");
+        assert_eq!(status_class(StatusCode::SERVICE_UNAVAILABLE), "5xx");
+    }
+}
@@ -0,0 +1,208 @@
+//! Response transform middleware.
+//!
+//! [`ResponseTransform`] runs after the handler and the hoops following it, and transforms the body of the response,
+//! like wrapping all JSON responses of an API in an envelope or adding hypermedia links. The body is passed to a
+//! [`BodyTransformer`] as bytes, or, with [`ResponseTransform::json`], as a [`serde_json::Value`] parsed from the
+//! body, which is serialized again after the transform.
+//!
+//! Only bodies written at once, by [`Response::render`] or [`Response::write_body`], are transformed. Streamed
+//! bodies, error bodies rendered later by the catcher and encoded bodies, with a `Content-Encoding` header, are
+//! left unchanged.
+//!
+//! # Example
+//!
+//! ```
+//! use salvo_core::prelude::*;
+//! use salvo_extra::response_transform::ResponseTransform;
+//! use serde_json::{json, Value};
+//!
+//! #[handler]
+//! async fn user() -> Json<Value> {
+//!     Json(json!({"id": 1, "name": "alice"}))
+//! }
+//!
+//! let envelope = ResponseTransform::json(|_req: &Request, _depot: &Depot, res: &mut Response, data: Value| {
+//!     if res.status_code.unwrap_or(StatusCode::OK).is_success() {
+//!         json!({"data": data, "links": {"self": "/users/1"}})
+//!     } else {
+//!         json!({"error": data})
+//!     }
+//! });
+//! let router = Router::with_path("users/<id>").hoop(envelope).get(user);
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use salvo_core::http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+use salvo_core::http::{Request, ResBody, Response};
+use salvo_core::hyper::body::Bytes;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, IntoVecString};
+use serde_json::Value;
+
+/// Transforms the body of a response.
+///
+/// It is implemented for closures `Fn(&Request, &Depot, &mut Response, Bytes) -> Bytes`, the response can be used to
+/// read the status code and to change the headers.
+pub trait BodyTransformer: Send + Sync + 'static {
+    /// Transforms the body, returns the new body.
+    fn transform(&self, req: &Request, depot: &Depot, res: &mut Response, body: Bytes) -> Bytes;
+}
+
+impl<F> BodyTransformer for F
+where
+    F: Fn(&Request, &Depot, &mut Response, Bytes) -> Bytes + Send + Sync + 'static,
+{
+    #[inline]
+    fn transform(&self, req: &Request, depot: &Depot, res: &mut Response, body: Bytes) -> Bytes {
+        self(req, depot, res, body)
+    }
+}
+
+/// [`BodyTransformer`] of JSON bodies, created by [`ResponseTransform::json`].
+///
+/// Bodies which are not valid JSON are left unchanged.
+pub struct JsonTransformer<F>(F);
+
+impl<F> BodyTransformer for JsonTransformer<F>
+where
+    F: Fn(&Request, &Depot, &mut Response, Value) -> Value + Send + Sync + 'static,
+{
+    fn transform(&self, req: &Request, depot: &Depot, res: &mut Response, body: Bytes) -> Bytes {
+        let value = match serde_json::from_slice(&body) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::debug!(error = ?e, "response body is not valid json, it is not transformed");
+                return body;
+            }
+        };
+        match serde_json::to_vec(&(self.0)(req, depot, res, value)) {
+            Ok(body) => body.into(),
+            Err(e) => {
+                tracing::error!(error = ?e, "serialize transformed json failed");
+                body
+            }
+        }
+    }
+}
+
+/// Middleware transforming the body of responses, see the [module documentation](self).
+pub struct ResponseTransform {
+    transformer: Box<dyn BodyTransformer>,
+    content_types: Vec<String>,
+}
+
+impl ResponseTransform {
+    /// Create a new `ResponseTransform` transforming the bodies of all content types.
+    #[inline]
+    pub fn new(transformer: impl BodyTransformer) -> Self {
+        ResponseTransform {
+            transformer: Box::new(transformer),
+            content_types: vec![],
+        }
+    }
+
+    /// Create a new `ResponseTransform` transforming the bodies of `application/json` responses as JSON values.
+    #[inline]
+    pub fn json<F>(transformer: F) -> Self
+    where
+        F: Fn(&Request, &Depot, &mut Response, Value) -> Value + Send + Sync + 'static,
+    {
+        Self::new(JsonTransformer(transformer)).content_types("application/json")
+    }
+
+    /// Sets the content types of the transformed responses, like `text/html`, all content types are transformed if
+    /// it is empty.
+    #[inline]
+    pub fn content_types(mut self, content_types: impl IntoVecString) -> Self {
+        self.content_types = content_types.into_vec_string();
+        self
+    }
+
+    fn is_transformable(&self, res: &Response) -> bool {
+        if !matches!(res.body, ResBody::Once(_) | ResBody::Chunks(_)) || res.headers().contains_key(CONTENT_ENCODING) {
+            return false;
+        }
+        if self.content_types.is_empty() {
+            return true;
+        }
+        res.content_type().is_some_and(|content_type| {
+            self.content_types
+                .iter()
+                .any(|essence| essence.eq_ignore_ascii_case(content_type.essence_str()))
+        })
+    }
+}
+
+#[async_trait]
+impl Handler for ResponseTransform {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        ctrl.call_next(req, depot, res).await;
+        if !self.is_transformable(res) {
+            return;
+        }
+        let body = match res.take_body() {
+            ResBody::Once(bytes) => bytes,
+            ResBody::Chunks(chunks) => chunks.into_iter().flatten().collect::<Vec<u8>>().into(),
+            _ => unreachable!("body is checked by `is_transformable`"),
+        };
+        let body = self.transformer.transform(req, depot, res, body);
+        res.headers_mut().remove(CONTENT_LENGTH);
+        res.body(ResBody::Once(body));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+    use serde_json::json;
+
+    use super::*;
+
+    #[handler]
+    async fn user() -> Json<Value> {
+        Json(json!({"id": 1}))
+    }
+
+    #[handler]
+    async fn text() -> &'static str {
+        "hello"
+    }
+
+    #[tokio::test]
+    async fn test_json_transform() {
+        let envelope = ResponseTransform::json(|req: &Request, _depot: &Depot, res: &mut Response, data: Value| {
+            res.headers_mut().insert("x-enveloped", "true".parse().unwrap());
+            json!({"data": data, "links": {"self": req.uri().path()}})
+        });
+        let router = Router::new()
+            .hoop(envelope)
+            .push(Router::with_path("users/1").get(user))
+            .push(Router::with_path("text").get(text));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/users/1").send(&service).await;
+        assert_eq!(res.headers()["x-enveloped"], "true");
+        let body: Value = res.take_json().await.unwrap();
+        assert_eq!(body, json!({"data": {"id": 1}, "links": {"self": "/users/1"}}));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/text").send(&service).await;
+        assert!(res.headers().get("x-enveloped").is_none());
+        assert_eq!(res.take_string().await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_bytes_transform() {
+        let upper =
+            |_req: &Request, _depot: &Depot, _res: &mut Response, body: Bytes| Bytes::from(body.to_ascii_uppercase());
+        let router = Router::new()
+            .hoop(ResponseTransform::new(upper).content_types("text/plain"))
+            .push(Router::with_path("users/1").get(user))
+            .push(Router::with_path("text").get(text));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/text").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), "HELLO");
+        let mut res = TestClient::get("http://127.0.0.1:5801/users/1").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), r#"{"id":1}"#);
+    }
+}
@@ -0,0 +1,411 @@
+//! Request rewrite middleware.
+//!
+//! [`Rewrite`] applies declarative rules to requests before they are handled, to adapt legacy clients without
+//! changing the handlers: strip or add path prefixes, rename, copy, set or remove headers, and add default query
+//! parameters. Rules are grouped in [`RuleSet`]s, which can be limited to a path prefix, and all of them can be
+//! deserialized from a config file.
+//!
+//! When the path or the query of a request is changed, it is routed again with [`FlowCtrl::forward`], so the hoop
+//! can be added to the [`Service`](salvo_core::Service) to rewrite paths no route matches.
+//!
+//! # Example
+//!
+//! ```
+//! use salvo_core::prelude::*;
+//! use salvo_extra::rewrite::{Rewrite, RewriteRule, RuleSet};
+//!
+//! #[handler]
+//! async fn users() -> &'static str {
+//!     "users"
+//! }
+//!
+//! let rewrite = Rewrite::new().rule_set(
+//!     RuleSet::with_path_prefix("/legacy")
+//!         .rule(RewriteRule::StripPrefix { prefix: "/legacy".into() })
+//!         .rule(RewriteRule::RenameHeader {
+//!             from: "x-auth".into(),
+//!             to: "authorization".into(),
+//!         }),
+//! );
+//! let service = Service::new(Router::with_path("users").get(users)).hoop(rewrite);
+//! ```
+//!
+//! The same rules loaded from JSON:
+//!
+//! ```json
+//! [
+//!     {
+//!         "path_prefix": "/legacy",
+//!         "rules": [
+//!             { "type": "strip_prefix", "prefix": "/legacy" },
+//!             { "type": "rename_header", "from": "x-auth", "to": "authorization" }
+//!         ]
+//!     }
+//! ]
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use salvo_core::http::header::{Entry, HeaderName, HeaderValue};
+use salvo_core::http::{Request, Response};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+use serde::{Deserialize, Serialize};
+
+/// A rule rewriting a part of requests.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RewriteRule {
+    /// Removes a prefix from the path, like `/v1` from `/v1/users`. Paths not starting with the prefix are kept.
+    StripPrefix {
+        /// The removed prefix.
+        prefix: String,
+    },
+    /// Adds a prefix to the path, like `/api` to `/users`.
+    AddPrefix {
+        /// The added prefix.
+        prefix: String,
+    },
+    /// Moves the values of a header to another header, replacing its values.
+    RenameHeader {
+        /// The name of the renamed header.
+        from: String,
+        /// The new name of the header.
+        to: String,
+    },
+    /// Copies the values of a header to another header, replacing its values.
+    CopyHeader {
+        /// The name of the copied header.
+        from: String,
+        /// The name of the header receiving the values.
+        to: String,
+    },
+    /// Sets the value of a header, replacing its values.
+    SetHeader {
+        /// The name of the header.
+        name: String,
+        /// The value of the header.
+        value: String,
+    },
+    /// Removes a header.
+    RemoveHeader {
+        /// The name of the header.
+        name: String,
+    },
+    /// Adds a query parameter if the request does not have it.
+    ///
+    /// The name and the value are appended to the query as is, so they should be percent-encoded.
+    DefaultQuery {
+        /// The name of the parameter.
+        name: String,
+        /// The value of the parameter.
+        value: String,
+    },
+}
+
+/// Rules applied in order to the requests whose path starts with the path prefix, or to all requests without it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RuleSet {
+    /// The path prefix of the requests the rules are applied to.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// The rules of the set.
+    #[serde(default)]
+    pub rules: Vec<RewriteRule>,
+}
+
+impl RuleSet {
+    /// Create a new `RuleSet` applied to all requests.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create a new `RuleSet` applied to the requests whose path starts with `prefix`.
+    #[inline]
+    pub fn with_path_prefix(prefix: impl Into<String>) -> Self {
+        RuleSet {
+            path_prefix: Some(prefix.into()),
+            rules: vec![],
+        }
+    }
+    /// Adds a rule to the set.
+    #[inline]
+    pub fn rule(mut self, rule: RewriteRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    fn is_matched(&self, path: &str) -> bool {
+        self.path_prefix
+            .as_deref()
+            .map_or(true, |prefix| strip_path_prefix(path, prefix).is_some())
+    }
+}
+
+/// Middleware rewriting the path, the headers and the query of requests with [`RuleSet`]s.
+///
+/// A rule set is applied if the path rewritten by the previous sets matches its prefix. A request is rewritten only
+/// once, even if it is forwarded to a route with the same hoop. If no route matches the rewritten path, the request
+/// is handled with its original path and query.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Rewrite {
+    rule_sets: Vec<RuleSet>,
+}
+
+impl Rewrite {
+    /// Create a new `Rewrite` without rules.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create a new `Rewrite` from rule sets, like the ones loaded from a config file.
+    #[inline]
+    pub fn from_rule_sets(rule_sets: impl IntoIterator<Item = RuleSet>) -> Self {
+        Rewrite {
+            rule_sets: rule_sets.into_iter().collect(),
+        }
+    }
+    /// Adds a rule set.
+    #[inline]
+    pub fn rule_set(mut self, rule_set: RuleSet) -> Self {
+        self.rule_sets.push(rule_set);
+        self
+    }
+    /// Adds a rule applied to all requests.
+    #[inline]
+    pub fn rule(self, rule: RewriteRule) -> Self {
+        self.rule_set(RuleSet::new().rule(rule))
+    }
+    /// Get the rule sets.
+    #[inline]
+    pub fn rule_sets(&self) -> &[RuleSet] {
+        &self.rule_sets
+    }
+
+    /// Applies the rules to the headers of the request, and returns the rewritten path and query.
+    fn apply(&self, req: &mut Request) -> String {
+        let mut path = req.uri().path().to_owned();
+        let mut query = req.uri().query().unwrap_or_default().to_owned();
+        for rule_set in &self.rule_sets {
+            if !rule_set.is_matched(&path) {
+                continue;
+            }
+            for rule in &rule_set.rules {
+                match rule {
+                    RewriteRule::StripPrefix { prefix } => {
+                        if let Some(rest) = strip_path_prefix(&path, prefix) {
+                            path = if rest.is_empty() {
+                                "/".to_owned()
+                            } else {
+                                rest.to_owned()
+                            };
+                        }
+                    }
+                    RewriteRule::AddPrefix { prefix } => {
+                        let prefix = prefix.trim_end_matches('/');
+                        path = if path == "/" {
+                            format!("/{}", prefix.trim_start_matches('/'))
+                        } else {
+                            format!("/{}{path}", prefix.trim_start_matches('/'))
+                        };
+                    }
+                    RewriteRule::RenameHeader { from, to } => {
+                        if let (Some(from), Some(to)) = (header_name(from), header_name(to)) {
+                            move_header(req, from, to, false);
+                        }
+                    }
+                    RewriteRule::CopyHeader { from, to } => {
+                        if let (Some(from), Some(to)) = (header_name(from), header_name(to)) {
+                            move_header(req, from, to, true);
+                        }
+                    }
+                    RewriteRule::SetHeader { name, value } => match (header_name(name), HeaderValue::from_str(value)) {
+                        (Some(name), Ok(value)) => {
+                            req.headers_mut().insert(name, value);
+                        }
+                        (_, Err(e)) => {
+                            tracing::warn!(error = ?e, name, "invalid header value in rewrite rule");
+                        }
+                        _ => {}
+                    },
+                    RewriteRule::RemoveHeader { name } => {
+                        if let Some(name) = header_name(name) {
+                            req.headers_mut().remove(name);
+                        }
+                    }
+                    RewriteRule::DefaultQuery { name, value } => {
+                        let exists = query
+                            .split('&')
+                            .any(|pair| pair.split_once('=').map_or(pair, |(key, _)| key) == name);
+                        if !exists {
+                            if !query.is_empty() {
+                                query.push('&');
+                            }
+                            query.push_str(&format!("{name}={value}"));
+                        }
+                    }
+                }
+            }
+        }
+        if query.is_empty() {
+            path
+        } else {
+            format!("{path}?{query}")
+        }
+    }
+}
+
+/// Returns the rest of the path after the prefix if the path starts with the prefix at a segment boundary.
+fn strip_path_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let prefix = prefix.trim_end_matches('/');
+    let rest = path.strip_prefix(prefix)?;
+    (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+}
+
+fn header_name(name: &str) -> Option<HeaderName> {
+    match HeaderName::from_bytes(name.as_bytes()) {
+        Ok(name) => Some(name),
+        Err(e) => {
+            tracing::warn!(error = ?e, name, "invalid header name in rewrite rule");
+            None
+        }
+    }
+}
+
+fn move_header(req: &mut Request, from: HeaderName, to: HeaderName, keep: bool) {
+    let headers = req.headers_mut();
+    let values: Vec<HeaderValue> = if keep {
+        headers.get_all(&from).iter().cloned().collect()
+    } else {
+        match headers.entry(&from) {
+            Entry::Occupied(entry) => entry.remove_entry_mult().1.collect(),
+            Entry::Vacant(_) => vec![],
+        }
+    };
+    if values.is_empty() {
+        return;
+    }
+    headers.remove(&to);
+    for value in values {
+        headers.append(to.clone(), value);
+    }
+}
+
+/// Marks a request already rewritten, so it is not rewritten again when forwarded.
+#[derive(Clone, Copy, Debug)]
+struct Rewritten;
+
+#[async_trait]
+impl Handler for Rewrite {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        if req.extensions().get::<Rewritten>().is_none() {
+            req.extensions_mut().insert(Rewritten);
+            let original = req
+                .uri()
+                .path_and_query()
+                .map(|pq| pq.as_str())
+                .unwrap_or("/")
+                .to_owned();
+            let rewritten = self.apply(req);
+            if rewritten != original && !ctrl.forward(&rewritten, req) {
+                tracing::debug!(original, rewritten, "no route matched the rewritten path");
+            }
+        }
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn echo(req: &mut Request) -> String {
+        format!(
+            "{} {} {}",
+            req.uri().path_and_query().unwrap(),
+            req.header::<String>("authorization").unwrap_or_default(),
+            req.query::<String>("lang").unwrap_or_default()
+        )
+    }
+
+    #[handler]
+    async fn stamp(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        ctrl.call_next(req, depot, res).await;
+        res.headers_mut().insert("x-stamp", "1".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rewrite() {
+        let rewrite = Rewrite::new()
+            .rule_set(
+                RuleSet::with_path_prefix("/legacy")
+                    .rule(RewriteRule::StripPrefix {
+                        prefix: "/legacy".into(),
+                    })
+                    .rule(RewriteRule::AddPrefix { prefix: "/api/".into() })
+                    .rule(RewriteRule::RenameHeader {
+                        from: "x-auth".into(),
+                        to: "authorization".into(),
+                    }),
+            )
+            .rule(RewriteRule::DefaultQuery {
+                name: "lang".into(),
+                value: "en".into(),
+            });
+        let router = Router::new().push(Router::with_path("api/users").hoop(rewrite.clone()).get(echo));
+        let service = Service::new(router).hoop(rewrite).hoop(stamp);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/legacy/users")
+            .add_header("x-auth", "token", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.headers()["x-stamp"], "1");
+        assert_eq!(res.take_string().await.unwrap(), "/api/users?lang=en token en");
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/api/users?lang=fr")
+            .send(&service)
+            .await;
+        assert_eq!(res.headers()["x-stamp"], "1");
+        assert_eq!(res.take_string().await.unwrap(), "/api/users?lang=fr  fr");
+
+        let res = TestClient::get("http://127.0.0.1:5801/legacyusers")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_load_rules() {
+        let rewrite: Rewrite = serde_json::from_str(
+            r#"[
+                {
+                    "path_prefix": "/v1",
+                    "rules": [
+                        { "type": "strip_prefix", "prefix": "/v1" },
+                        { "type": "copy_header", "from": "x-token", "to": "authorization" },
+                        { "type": "set_header", "name": "x-legacy", "value": "1" },
+                        { "type": "remove_header", "name": "x-token" }
+                    ]
+                }
+            ]"#,
+        )
+        .unwrap();
+        let mut req = Request::new();
+        *req.uri_mut() = "http://127.0.0.1/v1/users?page=2".parse().unwrap();
+        req.headers_mut().insert("x-token", "token".parse().unwrap());
+        assert_eq!(rewrite.apply(&mut req), "/users?page=2");
+        assert_eq!(req.headers()["authorization"], "token");
+        assert_eq!(req.headers()["x-legacy"], "1");
+        assert!(req.headers().get("x-token").is_none());
+
+        *req.uri_mut() = "http://127.0.0.1/v1".parse().unwrap();
+        assert_eq!(rewrite.apply(&mut req), "/");
+        *req.uri_mut() = "http://127.0.0.1/v10/users".parse().unwrap();
+        assert_eq!(rewrite.apply(&mut req), "/v10/users");
+    }
+}
@@ -0,0 +1,400 @@
+//! Security headers middleware.
+//!
+//! Read more: <https://salvo.rs>
+use std::time::Duration;
+
+use base64::engine::{general_purpose, Engine};
+use salvo_core::http::header::{
+    HeaderName, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY, REFERRER_POLICY,
+    STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+use salvo_core::http::{HeaderValue, Request, Response};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// key used when insert into depot.
+pub const NONCE_KEY: &str = "::salvo::secure_headers::nonce";
+
+/// SecureHeadersDepotExt
+pub trait SecureHeadersDepotExt {
+    /// Get the nonce of the `Content-Security-Policy` header for current request.
+    fn csp_nonce(&self) -> Option<&str>;
+}
+
+impl SecureHeadersDepotExt for Depot {
+    #[inline]
+    fn csp_nonce(&self) -> Option<&str> {
+        self.get::<String>(NONCE_KEY).ok().map(|s| &**s)
+    }
+}
+
+/// `Strict-Transport-Security` header options.
+#[derive(Clone, Debug)]
+pub struct Hsts {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+impl Hsts {
+    /// Create a new `Hsts` with `max_age`.
+    #[inline]
+    pub fn new(max_age: Duration) -> Self {
+        Hsts {
+            max_age,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// Sets whether the policy applies to all subdomains.
+    #[inline]
+    pub fn include_subdomains(mut self, include_subdomains: bool) -> Self {
+        self.include_subdomains = include_subdomains;
+        self
+    }
+
+    /// Sets whether the `preload` directive is sent.
+    #[inline]
+    pub fn preload(mut self, preload: bool) -> Self {
+        self.preload = preload;
+        self
+    }
+
+    fn to_header_value(&self) -> HeaderValue {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        HeaderValue::from_str(&value).expect("hsts header value should be valid")
+    }
+}
+impl Default for Hsts {
+    /// One year with `includeSubDomains`.
+    #[inline]
+    fn default() -> Self {
+        Hsts::new(Duration::from_secs(31_536_000)).include_subdomains(true)
+    }
+}
+
+/// `X-Frame-Options` header values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameOptions {
+    /// The page can not be displayed in a frame.
+    Deny,
+    /// The page can only be displayed in a frame on the same origin.
+    SameOrigin,
+}
+impl FrameOptions {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "DENY",
+            FrameOptions::SameOrigin => "SAMEORIGIN",
+        }
+    }
+}
+
+/// `Referrer-Policy` header values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReferrerPolicy {
+    /// `no-referrer`
+    NoReferrer,
+    /// `no-referrer-when-downgrade`
+    NoReferrerWhenDowngrade,
+    /// `origin`
+    Origin,
+    /// `origin-when-cross-origin`
+    OriginWhenCrossOrigin,
+    /// `same-origin`
+    SameOrigin,
+    /// `strict-origin`
+    StrictOrigin,
+    /// `strict-origin-when-cross-origin`
+    StrictOriginWhenCrossOrigin,
+    /// `unsafe-url`
+    UnsafeUrl,
+}
+impl ReferrerPolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReferrerPolicy::NoReferrer => "no-referrer",
+            ReferrerPolicy::NoReferrerWhenDowngrade => "no-referrer-when-downgrade",
+            ReferrerPolicy::Origin => "origin",
+            ReferrerPolicy::OriginWhenCrossOrigin => "origin-when-cross-origin",
+            ReferrerPolicy::SameOrigin => "same-origin",
+            ReferrerPolicy::StrictOrigin => "strict-origin",
+            ReferrerPolicy::StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+            ReferrerPolicy::UnsafeUrl => "unsafe-url",
+        }
+    }
+}
+
+/// `Content-Security-Policy` header builder.
+///
+/// Directives listed in [`ContentSecurityPolicy::nonce`] get a `'nonce-<value>'` source, the value is generated
+/// for every request and can be read by [`SecureHeadersDepotExt::csp_nonce`] to render inline scripts or styles.
+#[derive(Clone, Debug, Default)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<String>)>,
+    nonce_directives: Vec<String>,
+    report_only: bool,
+}
+impl ContentSecurityPolicy {
+    /// Create a new empty `ContentSecurityPolicy`.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a directive with its sources, for example `directive("script-src", ["'self'"])`.
+    ///
+    /// Sources are appended if the directive is already added.
+    pub fn directive<I, S>(mut self, name: impl Into<String>, sources: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let name = name.into();
+        let sources = sources.into_iter().map(Into::into);
+        if let Some((_, values)) = self.directives.iter_mut().find(|(n, _)| *n == name) {
+            values.extend(sources);
+        } else {
+            self.directives.push((name, sources.collect()));
+        }
+        self
+    }
+
+    /// Adds a per-request nonce source to the directive, for example `nonce("script-src")`.
+    pub fn nonce(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !self.directives.iter().any(|(n, _)| *n == name) {
+            self.directives.push((name.clone(), vec![]));
+        }
+        self.nonce_directives.push(name);
+        self
+    }
+
+    /// Sends the policy by `Content-Security-Policy-Report-Only` header, violations are reported
+    /// but not enforced.
+    #[inline]
+    pub fn report_only(mut self, report_only: bool) -> Self {
+        self.report_only = report_only;
+        self
+    }
+
+    fn header_name(&self) -> HeaderName {
+        if self.report_only {
+            CONTENT_SECURITY_POLICY_REPORT_ONLY
+        } else {
+            CONTENT_SECURITY_POLICY
+        }
+    }
+
+    fn to_header_value(&self, nonce: Option<&str>) -> Option<HeaderValue> {
+        let directives = self.directives.iter().map(|(name, sources)| {
+            let mut directive = name.clone();
+            for source in sources {
+                directive.push(' ');
+                directive.push_str(source);
+            }
+            if let Some(nonce) = nonce.filter(|_| self.nonce_directives.contains(name)) {
+                directive.push_str(&format!(" 'nonce-{nonce}'"));
+            }
+            directive
+        });
+        HeaderValue::from_str(&directives.collect::<Vec<_>>().join("; ")).ok()
+    }
+}
+
+/// SecureHeaders
+///
+/// Sets security related response headers, the defaults are:
+///
+/// - `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// - `X-Content-Type-Options: nosniff`
+/// - `X-Frame-Options: DENY`
+/// - `Referrer-Policy: strict-origin-when-cross-origin`
+///
+/// `Content-Security-Policy` is not sent unless [`SecureHeaders::csp`] is set. Headers already set by the
+/// handlers are kept.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::secure_headers::{ContentSecurityPolicy, SecureHeaders, SecureHeadersDepotExt};
+///
+/// #[handler]
+/// async fn index(depot: &mut Depot) -> Text<String> {
+///     let nonce = depot.csp_nonce().unwrap_or_default();
+///     Text::Html(format!(r#"<script nonce="{nonce}">console.log("hello")</script>"#))
+/// }
+///
+/// let secure_headers = SecureHeaders::new().csp(
+///     ContentSecurityPolicy::new()
+///         .directive("default-src", ["'self'"])
+///         .nonce("script-src"),
+/// );
+/// let service = Service::new(Router::new().get(index)).hoop(secure_headers);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SecureHeaders {
+    hsts: Option<Hsts>,
+    content_type_options: bool,
+    frame_options: Option<FrameOptions>,
+    referrer_policy: Option<ReferrerPolicy>,
+    csp: Option<ContentSecurityPolicy>,
+}
+impl Default for SecureHeaders {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SecureHeaders {
+    /// Create new `SecureHeaders` with safe defaults.
+    #[inline]
+    pub fn new() -> Self {
+        SecureHeaders {
+            hsts: Some(Hsts::default()),
+            content_type_options: true,
+            frame_options: Some(FrameOptions::Deny),
+            referrer_policy: Some(ReferrerPolicy::StrictOriginWhenCrossOrigin),
+            csp: None,
+        }
+    }
+
+    /// Sets the `Strict-Transport-Security` header, `None` disables it.
+    #[inline]
+    pub fn hsts(mut self, hsts: impl Into<Option<Hsts>>) -> Self {
+        self.hsts = hsts.into();
+        self
+    }
+
+    /// Sets whether `X-Content-Type-Options: nosniff` header is sent.
+    #[inline]
+    pub fn content_type_options(mut self, content_type_options: bool) -> Self {
+        self.content_type_options = content_type_options;
+        self
+    }
+
+    /// Sets the `X-Frame-Options` header, `None` disables it.
+    #[inline]
+    pub fn frame_options(mut self, frame_options: impl Into<Option<FrameOptions>>) -> Self {
+        self.frame_options = frame_options.into();
+        self
+    }
+
+    /// Sets the `Referrer-Policy` header, `None` disables it.
+    #[inline]
+    pub fn referrer_policy(mut self, referrer_policy: impl Into<Option<ReferrerPolicy>>) -> Self {
+        self.referrer_policy = referrer_policy.into();
+        self
+    }
+
+    /// Sets the `Content-Security-Policy` header, `None` disables it.
+    #[inline]
+    pub fn csp(mut self, csp: impl Into<Option<ContentSecurityPolicy>>) -> Self {
+        self.csp = csp.into();
+        self
+    }
+}
+
+fn generate_nonce() -> String {
+    general_purpose::STANDARD.encode(rand::random::<[u8; 16]>())
+}
+
+#[async_trait]
+impl Handler for SecureHeaders {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let nonce = self
+            .csp
+            .as_ref()
+            .filter(|csp| !csp.nonce_directives.is_empty())
+            .map(|_| generate_nonce());
+        if let Some(nonce) = &nonce {
+            depot.insert(NONCE_KEY, nonce.clone());
+        }
+        ctrl.call_next(req, depot, res).await;
+
+        let headers = res.headers_mut();
+        if let Some(hsts) = &self.hsts {
+            headers
+                .entry(STRICT_TRANSPORT_SECURITY)
+                .or_insert_with(|| hsts.to_header_value());
+        }
+        if self.content_type_options {
+            headers
+                .entry(X_CONTENT_TYPE_OPTIONS)
+                .or_insert(HeaderValue::from_static("nosniff"));
+        }
+        if let Some(frame_options) = self.frame_options {
+            headers
+                .entry(X_FRAME_OPTIONS)
+                .or_insert(HeaderValue::from_static(frame_options.as_str()));
+        }
+        if let Some(referrer_policy) = self.referrer_policy {
+            headers
+                .entry(REFERRER_POLICY)
+                .or_insert(HeaderValue::from_static(referrer_policy.as_str()));
+        }
+        if let Some(csp) = &self.csp {
+            if let Some(value) = csp.to_header_value(nonce.as_deref()) {
+                headers.entry(csp.header_name()).or_insert(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn index(depot: &mut Depot) -> String {
+        depot.csp_nonce().unwrap_or_default().to_owned()
+    }
+
+    #[tokio::test]
+    async fn test_secure_headers() {
+        let router = Router::new().get(index);
+        let service = Service::new(router).hoop(SecureHeaders::new().frame_options(FrameOptions::SameOrigin));
+
+        let res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(
+            res.headers()[STRICT_TRANSPORT_SECURITY],
+            "max-age=31536000; includeSubDomains"
+        );
+        assert_eq!(res.headers()[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(res.headers()[X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert_eq!(res.headers()[REFERRER_POLICY], "strict-origin-when-cross-origin");
+        assert!(res.headers().get(CONTENT_SECURITY_POLICY).is_none());
+
+        let res = TestClient::get("http://127.0.0.1:5801/missing").send(&service).await;
+        assert_eq!(res.headers()[X_CONTENT_TYPE_OPTIONS], "nosniff");
+    }
+
+    #[tokio::test]
+    async fn test_secure_headers_csp_nonce() {
+        let csp = ContentSecurityPolicy::new()
+            .directive("default-src", ["'self'"])
+            .directive("script-src", ["'self'"])
+            .nonce("script-src");
+        let router = Router::new().get(index);
+        let service = Service::new(router).hoop(SecureHeaders::new().hsts(None).csp(csp));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert!(res.headers().get(STRICT_TRANSPORT_SECURITY).is_none());
+        let policy = res.headers()[CONTENT_SECURITY_POLICY].to_str().unwrap().to_owned();
+        let nonce = res.take_string().await.unwrap();
+        assert!(!nonce.is_empty());
+        assert_eq!(policy, format!("default-src 'self'; script-src 'self' 'nonce-{nonce}'"));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_ne!(res.take_string().await.unwrap(), nonce);
+    }
+}
@@ -0,0 +1,307 @@
+//! Server statistics middleware and introspection handler.
+//!
+//! [`ServerStats`] counts the requests handled by the service, in total and by matched route pattern, and
+//! measures the health of the event loop: a background task sleeps for a fixed interval and records how late it
+//! is woken up, a high lag means the runtime is blocked by long synchronous work. With a
+//! [`ServerHandle`](salvo_core::server::ServerHandle), the active, accepted and handled connections are reported
+//! as well.
+//!
+//! The statistics are rendered as JSON by the [`handler`](ServerStats::handler), which should be protected, for
+//! example by an authentication hoop or by listening on a private address:
+//!
+//! ```json
+//! {
+//!   "uptime_secs": 3600,
+//!   "connections": {"active": 3, "accepted": 1200, "handled": 1197},
+//!   "requests": {"total": 5000, "in_flight": 2, "routes": {"/users/<id>": 4000, "unmatched": 12}},
+//!   "event_loop": {"lag_ms": 0, "max_lag_ms": 35}
+//! }
+//! ```
+//!
+//! # Example
+//!
+//! ```no_run
+//! use salvo_core::prelude::*;
+//! use salvo_extra::server_stats::ServerStats;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let acceptor = TcpListener::new("0.0.0.0:5800").bind().await;
+//!     let server = Server::new(acceptor);
+//!     let stats = ServerStats::new().server_handle(server.handle());
+//!     let router = Router::new().push(Router::with_path("admin/stats").get(stats.handler()));
+//!     server.serve(Service::new(router).hoop(stats)).await;
+//! }
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use salvo_core::http::{Request, Response};
+use salvo_core::server::ServerHandle;
+use salvo_core::writing::Json;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+
+/// Route of requests which match no route.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Statistics of the connections of the server.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct ConnectionStats {
+    /// Number of connections being served.
+    pub active: usize,
+    /// Total number of accepted connections.
+    pub accepted: u64,
+    /// Total number of served and closed connections.
+    pub handled: u64,
+}
+
+/// Statistics of the requests handled by the service.
+#[derive(Serialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct RequestStats {
+    /// Total number of requests.
+    pub total: u64,
+    /// Number of requests being handled.
+    pub in_flight: i64,
+    /// Number of requests by matched route pattern.
+    pub routes: BTreeMap<String, u64>,
+}
+
+/// Health of the event loop.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct EventLoopStats {
+    /// Lag of the last wake up of the monitor task, in milliseconds.
+    pub lag_ms: u64,
+    /// Max lag of the monitor task since it started, in milliseconds.
+    pub max_lag_ms: u64,
+}
+
+/// Snapshot of the statistics, rendered by the handler of [`ServerStats`].
+#[derive(Serialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct StatsSnapshot {
+    /// Seconds since the [`ServerStats`] is created.
+    pub uptime_secs: u64,
+    /// Statistics of the connections, if a [`ServerHandle`] is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connections: Option<ConnectionStats>,
+    /// Statistics of the requests.
+    pub requests: RequestStats,
+    /// Health of the event loop, once the monitor task is started by the first request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_loop: Option<EventLoopStats>,
+}
+
+struct Inner {
+    started: Instant,
+    server: Option<ServerHandle>,
+    monitor_interval: Duration,
+    monitor_started: AtomicBool,
+    requests: AtomicU64,
+    in_flight: AtomicI64,
+    routes: Mutex<BTreeMap<String, u64>>,
+    lag_ms: AtomicU64,
+    max_lag_ms: AtomicU64,
+}
+
+/// Middleware collecting the statistics of the server, see the [module documentation](self).
+///
+/// It should be added to the [`Service`](salvo_core::Service), so requests which match no route are counted as
+/// well. Cloned `ServerStats` share the same statistics.
+#[derive(Clone)]
+pub struct ServerStats {
+    inner: Arc<Inner>,
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerStats {
+    /// Create a new `ServerStats`.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                started: Instant::now(),
+                server: None,
+                monitor_interval: Duration::from_secs(1),
+                monitor_started: AtomicBool::new(false),
+                requests: AtomicU64::new(0),
+                in_flight: AtomicI64::new(0),
+                routes: Mutex::new(BTreeMap::new()),
+                lag_ms: AtomicU64::new(0),
+                max_lag_ms: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("`ServerStats` should be configured before it is cloned")
+    }
+
+    /// Sets the handle of the server, to report its connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this `ServerStats` is already cloned.
+    #[inline]
+    pub fn server_handle(mut self, handle: ServerHandle) -> Self {
+        self.inner_mut().server = Some(handle);
+        self
+    }
+
+    /// Sets the interval of the event loop monitor task, default is 1 second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this `ServerStats` is already cloned.
+    #[inline]
+    pub fn monitor_interval(mut self, interval: Duration) -> Self {
+        self.inner_mut().monitor_interval = interval;
+        self
+    }
+
+    /// Returns a snapshot of the statistics.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let inner = &self.inner;
+        StatsSnapshot {
+            uptime_secs: inner.started.elapsed().as_secs(),
+            connections: inner.server.as_ref().map(|server| ConnectionStats {
+                active: server.active_connections(),
+                accepted: server.accepted_connections(),
+                handled: server.handled_connections(),
+            }),
+            requests: RequestStats {
+                total: inner.requests.load(Ordering::Relaxed),
+                in_flight: inner.in_flight.load(Ordering::Relaxed),
+                routes: inner.routes.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            },
+            event_loop: inner.monitor_started.load(Ordering::Relaxed).then(|| EventLoopStats {
+                lag_ms: inner.lag_ms.load(Ordering::Relaxed),
+                max_lag_ms: inner.max_lag_ms.load(Ordering::Relaxed),
+            }),
+        }
+    }
+
+    /// Returns the handler rendering the statistics as JSON.
+    #[inline]
+    pub fn handler(&self) -> StatsHandler {
+        StatsHandler { stats: self.clone() }
+    }
+
+    fn start_monitor(&self) {
+        if self.inner.monitor_started.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let inner = Arc::downgrade(&self.inner);
+        let interval = self.inner.monitor_interval;
+        tokio::spawn(monitor_event_loop(inner, interval));
+    }
+}
+
+/// Sleeps for `interval` in a loop and records how late it is woken up, until the `ServerStats` is dropped.
+async fn monitor_event_loop(inner: Weak<Inner>, interval: Duration) {
+    loop {
+        let slept = Instant::now();
+        tokio::time::sleep(interval).await;
+        let Some(inner) = inner.upgrade() else {
+            break;
+        };
+        let lag_ms = slept.elapsed().saturating_sub(interval).as_millis() as u64;
+        inner.lag_ms.store(lag_ms, Ordering::Relaxed);
+        inner.max_lag_ms.fetch_max(lag_ms, Ordering::Relaxed);
+    }
+}
+
+/// Decrements the in-flight requests when dropped, so cancelled requests are not counted.
+struct InFlight<'a>(&'a AtomicI64);
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl Handler for ServerStats {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        self.start_monitor();
+        let inner = &self.inner;
+        inner.requests.fetch_add(1, Ordering::Relaxed);
+        let route = req.matched_path().unwrap_or(UNMATCHED_ROUTE);
+        *inner
+            .routes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(route.to_owned())
+            .or_default() += 1;
+        inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        let _in_flight = InFlight(&inner.in_flight);
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
+/// Handler rendering the statistics of a [`ServerStats`] as JSON, created by [`ServerStats::handler`].
+pub struct StatsHandler {
+    stats: ServerStats,
+}
+
+#[async_trait]
+impl Handler for StatsHandler {
+    async fn handle(&self, _req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+        res.render(Json(self.stats.snapshot()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn user() -> &'static str {
+        "alice"
+    }
+
+    #[tokio::test]
+    async fn test_server_stats() {
+        let stats = ServerStats::new().monitor_interval(Duration::from_millis(10));
+        let router = Router::new()
+            .push(Router::with_path("users/<id>").get(user))
+            .push(Router::with_path("stats").get(stats.handler()));
+        let service = Service::new(router).hoop(stats.clone());
+
+        for path in ["users/1", "users/2", "other"] {
+            TestClient::get(format!("http://127.0.0.1:5801/{path}"))
+                .send(&service)
+                .await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let body = TestClient::get("http://127.0.0.1:5801/stats")
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert!(body.contains(r#""routes":{"/stats":1,"/users/<id>":2,"unmatched":1}"#));
+        assert!(body.contains(r#""in_flight":1"#));
+        assert!(body.contains(r#""event_loop":{"#));
+        assert!(!body.contains("connections"));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests.total, 4);
+        assert_eq!(snapshot.requests.in_flight, 0);
+        assert!(snapshot.connections.is_none());
+    }
+}
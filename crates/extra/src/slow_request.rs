@@ -0,0 +1,363 @@
+//! Slow request detector middleware.
+//!
+//! [`SlowRequest`] records the time spent routing the request, in every hoop and in the handler, and, when
+//! [`SlowRequest::time_body`] is enabled, writing the response body. Requests taking longer than a threshold are reported to a [`SlowRequestReporter`], which logs
+//! them by default, with their route, path params, status and timing breakdown. The values of path params are
+//! redacted, unless they are revealed by [`SlowRequest::reveal_param`].
+//!
+//! The timings of routing, hoops and handler are also stored in the [`Depot`] for every request, and can be read
+//! with [`SlowRequestDepotExt::request_timings`] by hoops added before this middleware, to export them.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use salvo_core::prelude::*;
+//! use salvo_extra::slow_request::SlowRequest;
+//!
+//! let slow_request = SlowRequest::new(Duration::from_millis(500)).reveal_param("id");
+//! let service = Service::new(Router::with_path("users/<id>")).hoop(slow_request);
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::io::Error as IoError;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use salvo_core::http::body::{Body, Frame, ResBody, SizeHint};
+use salvo_core::http::{Method, Request, Response, StatusCode};
+use salvo_core::routing::{HandlerTiming, HandlerTimings};
+use salvo_core::{async_trait, BoxedError, Depot, FlowCtrl, Handler};
+
+/// Key for the [`RequestTimings`] of the current request in depot.
+pub const TIMINGS_KEY: &str = "::salvo::slow_request::timings";
+
+/// Value of redacted path params in reports.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Time spent in the phases of a request before its response body is written.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct RequestTimings {
+    /// Time spent by the router to detect the handlers of the request.
+    pub routing: Duration,
+    /// Time spent in the hoops called after [`SlowRequest`].
+    pub middleware: Duration,
+    /// Time spent in the handler.
+    pub handler: Duration,
+    /// Time spent in each hoop and handler called after [`SlowRequest`], in calling order.
+    pub handlers: Vec<HandlerTiming>,
+}
+
+/// Extension of [`Depot`] to get the [`RequestTimings`] recorded by [`SlowRequest`].
+pub trait SlowRequestDepotExt {
+    /// Get the timings of the current request.
+    fn request_timings(&self) -> Option<&RequestTimings>;
+}
+
+impl SlowRequestDepotExt for Depot {
+    #[inline]
+    fn request_timings(&self) -> Option<&RequestTimings> {
+        self.get::<RequestTimings>(TIMINGS_KEY).ok()
+    }
+}
+
+/// A request slower than the threshold of [`SlowRequest`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SlowRequestReport {
+    /// Method of the request.
+    pub method: Method,
+    /// Path of the request.
+    pub path: String,
+    /// Matched route pattern of the request, like `/users/<id>`.
+    pub route: Option<String>,
+    /// Path params of the request, with redacted values.
+    pub params: Vec<(String, String)>,
+    /// Status code of the response.
+    pub status_code: StatusCode,
+    /// Time spent in routing, hoops and handler.
+    pub timings: RequestTimings,
+    /// Time spent writing the response body, since [`SlowRequest`] returned, zero unless
+    /// [`SlowRequest::time_body`] is enabled.
+    pub write: Duration,
+    /// Total time spent handling the request.
+    pub total: Duration,
+}
+
+/// Receives the reports of slow requests.
+pub trait SlowRequestReporter: Send + Sync + 'static {
+    /// Reports a slow request.
+    fn report(&self, report: &SlowRequestReport);
+}
+
+impl<F> SlowRequestReporter for F
+where
+    F: Fn(&SlowRequestReport) + Send + Sync + 'static,
+{
+    fn report(&self, report: &SlowRequestReport) {
+        self(report)
+    }
+}
+
+/// Default [`SlowRequestReporter`], logging slow requests with `tracing` at warn level.
+#[derive(Default, Debug)]
+pub struct TracingReporter;
+
+impl SlowRequestReporter for TracingReporter {
+    fn report(&self, report: &SlowRequestReport) {
+        let handlers = report
+            .timings
+            .handlers
+            .iter()
+            .map(|timing| format!("{}={:?}", timing.name, timing.elapsed))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tracing::warn!(
+            method = %report.method,
+            path = %report.path,
+            route = report.route.as_deref().unwrap_or_default(),
+            params = ?report.params,
+            status = %report.status_code,
+            total = ?report.total,
+            routing = ?report.timings.routing,
+            middleware = ?report.timings.middleware,
+            handler = ?report.timings.handler,
+            write = ?report.write,
+            handlers = %handlers,
+            "slow request"
+        );
+    }
+}
+
+/// Middleware detecting slow requests, see the [module documentation](self).
+///
+/// It should be added to the [`Service`](salvo_core::Service) as the first hoop, so all the other hoops are
+/// profiled.
+pub struct SlowRequest {
+    threshold: Duration,
+    revealed_params: Vec<String>,
+    reporter: Arc<dyn SlowRequestReporter>,
+    time_body: bool,
+}
+
+impl SlowRequest {
+    /// Create a new `SlowRequest` reporting requests slower than `threshold` with [`TracingReporter`].
+    #[inline]
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            revealed_params: vec![],
+            reporter: Arc::new(TracingReporter),
+            time_body: false,
+        }
+    }
+
+    /// Reveals the value of a path param in reports, like `id`, the values of other params are redacted.
+    #[inline]
+    pub fn reveal_param(mut self, name: impl Into<String>) -> Self {
+        self.revealed_params.push(name.into());
+        self
+    }
+
+    /// Sets the reporter of slow requests.
+    #[inline]
+    pub fn reporter(mut self, reporter: impl SlowRequestReporter) -> Self {
+        self.reporter = Arc::new(reporter);
+        self
+    }
+
+    /// Sets if the time spent writing the response body is recorded, defaults to `false`.
+    ///
+    /// The body is wrapped to detect when it is written, and requests are reported after their body is written.
+    #[inline]
+    pub fn time_body(mut self, time_body: bool) -> Self {
+        self.time_body = time_body;
+        self
+    }
+}
+
+#[async_trait]
+impl Handler for SlowRequest {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let started = Instant::now();
+        depot.inject(HandlerTimings::new());
+        ctrl.call_next(req, depot, res).await;
+        let elapsed = started.elapsed();
+
+        let handlers = depot
+            .scrape::<HandlerTimings>()
+            .map(|timings| timings.timings().to_vec())
+            .unwrap_or_default();
+        let handler = handlers
+            .iter()
+            .filter(|timing| timing.is_goal)
+            .map(|timing| timing.elapsed)
+            .sum();
+        let timings = RequestTimings {
+            routing: ctrl.routing_duration().unwrap_or_default(),
+            middleware: elapsed.saturating_sub(handler),
+            handler,
+            handlers,
+        };
+        depot.insert(TIMINGS_KEY, timings.clone());
+
+        let report = SlowRequestReport {
+            method: req.method().clone(),
+            path: req.uri().path().to_owned(),
+            route: req.matched_path().map(ToOwned::to_owned),
+            params: req
+                .params()
+                .iter()
+                .map(|(name, value)| {
+                    if self.revealed_params.contains(name) {
+                        (name.clone(), value.clone())
+                    } else {
+                        (name.clone(), REDACTED.to_owned())
+                    }
+                })
+                .collect(),
+            status_code: res.status_code.unwrap_or(StatusCode::OK),
+            total: timings.routing + elapsed,
+            timings,
+            write: Duration::ZERO,
+        };
+        let pending = PendingReport {
+            report,
+            threshold: self.threshold,
+            reporter: self.reporter.clone(),
+            write_started: Instant::now(),
+        };
+        if !self.time_body || res.body.is_none() || res.body.is_error() {
+            pending.finish();
+        } else {
+            res.body = ResBody::Boxed(Box::pin(TimedBody {
+                inner: res.body.take(),
+                pending: Some(pending),
+            }));
+        }
+    }
+}
+
+struct PendingReport {
+    report: SlowRequestReport,
+    threshold: Duration,
+    reporter: Arc<dyn SlowRequestReporter>,
+    write_started: Instant,
+}
+impl PendingReport {
+    fn finish(mut self) {
+        self.report.write = self.write_started.elapsed();
+        self.report.total += self.report.write;
+        if self.report.total >= self.threshold {
+            self.reporter.report(&self.report);
+        }
+    }
+}
+
+/// Response body reporting the request when it is written, or dropped because the client is gone.
+struct TimedBody {
+    inner: ResBody,
+    pending: Option<PendingReport>,
+}
+impl Body for TimedBody {
+    type Data = <ResBody as Body>::Data;
+    type Error = BoxedError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(None) = poll {
+            if let Some(pending) = self.pending.take() {
+                pending.finish();
+            }
+        }
+        poll.map_err(|e: IoError| e.into())
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+impl Drop for TimedBody {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            pending.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn user(req: &mut Request) -> String {
+        let delay = req.query::<u64>("delay").unwrap_or_default();
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        req.param::<String>("id").unwrap_or_default()
+    }
+
+    #[handler]
+    async fn export(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        ctrl.call_next(req, depot, res).await;
+        let timings = depot.request_timings().unwrap();
+        res.headers_mut()
+            .insert("x-handlers", timings.handlers.len().to_string().parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_slow_request() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let slow_request = SlowRequest::new(Duration::from_millis(50))
+            .reveal_param("id")
+            .time_body(true)
+            .reporter({
+                let reports = reports.clone();
+                move |report: &SlowRequestReport| reports.lock().unwrap().push(report.clone())
+            });
+        let router = Router::with_path("users/<id>/<token>").get(user);
+        let service = Service::new(router).hoop(export).hoop(slow_request);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/users/7/secret")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "7");
+        assert_eq!(res.headers()["x-handlers"], "1");
+        assert!(reports.lock().unwrap().is_empty());
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/users/8/secret?delay=60")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "8");
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.route.as_deref(), Some("/users/<id>/<token>"));
+        assert_eq!(
+            report.params,
+            vec![
+                ("id".to_owned(), "8".to_owned()),
+                ("token".to_owned(), REDACTED.to_owned())
+            ]
+        );
+        assert_eq!(report.status_code, StatusCode::OK);
+        assert!(report.timings.handler >= Duration::from_millis(60));
+        assert!(report.timings.handlers[0].is_goal);
+        assert!(report.total >= report.timings.handler + report.write);
+    }
+}
@@ -50,20 +50,28 @@
 
 use serde::Serialize;
 use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::convert::Infallible;
 use std::error::Error as StdError;
 use std::fmt::{self, Display, Formatter, Write};
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 use std::time::Duration;
 
 use futures_util::future;
-use futures_util::stream::{Stream, TryStream, TryStreamExt};
+use futures_util::stream::{self, BoxStream, Stream, StreamExt, TryStream, TryStreamExt};
 use pin_project::pin_project;
-use salvo_core::http::header::{HeaderValue, CACHE_CONTROL, CONTENT_TYPE};
+use salvo_core::http::header::{HeaderName, HeaderValue, CACHE_CONTROL, CONTENT_TYPE};
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::time::{self, Sleep};
 
-use salvo_core::http::Response;
+use salvo_core::http::{Request, Response};
+
+/// Header sent by reconnecting clients with the id of the last event they received.
+pub const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
 
 /// Server-sent event data type
 #[derive(Clone, Debug)]
@@ -319,11 +327,187 @@ where
     }
 }
 
+/// Fans out events to all connected SSE clients.
+///
+/// Every event [sent](SseBroadcaster::send) is given an increasing numeric id and kept in a bounded replay
+/// buffer. Clients reconnecting with a `Last-Event-ID` header receive the buffered events they missed before
+/// the live ones. Clients too slow to keep up with the channel capacity skip the oldest events, the number of
+/// dropped events is tracked per client and in total.
+///
+/// Cloned broadcasters share the same channel and buffer.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::sse::{SseBroadcaster, SseEvent};
+///
+/// struct Notifications {
+///     broadcaster: SseBroadcaster,
+/// }
+///
+/// #[handler]
+/// impl Notifications {
+///     async fn handle(&self, req: &mut Request, res: &mut Response) {
+///         self.broadcaster.stream(req, res);
+///     }
+/// }
+///
+/// let broadcaster = SseBroadcaster::new(64).replay_size(100);
+/// let router = Router::with_path("notifications").get(Notifications {
+///     broadcaster: broadcaster.clone(),
+/// });
+///
+/// // Anywhere else in the application.
+/// broadcaster.send(SseEvent::default().name("order").text("shipped"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SseBroadcaster {
+    sender: broadcast::Sender<SseEvent>,
+    history: Arc<Mutex<VecDeque<(u64, SseEvent)>>>,
+    replay_size: usize,
+    next_id: Arc<AtomicU64>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl SseBroadcaster {
+    /// Create a new `SseBroadcaster`, each client can fall behind by `capacity` events before events are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is `0`.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        SseBroadcaster {
+            sender: broadcast::channel(capacity).0,
+            history: Default::default(),
+            replay_size: 0,
+            next_id: Arc::new(AtomicU64::new(1)),
+            dropped: Default::default(),
+        }
+    }
+
+    /// Sets how many recent events are kept for clients reconnecting with `Last-Event-ID`, defaults to `0`.
+    #[inline]
+    pub fn replay_size(mut self, size: usize) -> Self {
+        self.replay_size = size;
+        self
+    }
+
+    /// Sends an event to all connected clients, its id is replaced by the next id of the broadcaster.
+    ///
+    /// Returns the number of clients the event was sent to.
+    pub fn send(&self, event: SseEvent) -> usize {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let event = event.id(id.to_string());
+        if self.replay_size > 0 {
+            if history.len() >= self.replay_size {
+                history.pop_front();
+            }
+            history.push_back((id, event.clone()));
+        }
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    /// Returns the number of connected clients.
+    #[inline]
+    pub fn client_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Returns the total number of events dropped for clients lagging behind.
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Subscribes a new client, events after `last_event_id` still in the replay buffer are sent first.
+    pub fn subscribe(&self, last_event_id: Option<&str>) -> SseSubscription {
+        // Hold the lock, so no event is sent between the replay and the subscription.
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let replay = match last_event_id.and_then(|id| id.trim().parse::<u64>().ok()) {
+            Some(last_id) => history
+                .iter()
+                .filter(|(id, _)| *id > last_id)
+                .map(|(_, event)| event.clone())
+                .collect(),
+            None => Vec::new(),
+        };
+        let receiver = self.sender.subscribe();
+        drop(history);
+
+        let dropped = Arc::new(AtomicU64::new(0));
+        let total_dropped = self.dropped.clone();
+        let client_dropped = dropped.clone();
+        let live = stream::unfold(receiver, move |mut receiver| {
+            let total_dropped = total_dropped.clone();
+            let client_dropped = client_dropped.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => return Some((Ok(event), receiver)),
+                        Err(RecvError::Lagged(count)) => {
+                            tracing::debug!(count, "sse client lagged, events dropped");
+                            client_dropped.fetch_add(count, Ordering::Relaxed);
+                            total_dropped.fetch_add(count, Ordering::Relaxed);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        SseSubscription {
+            inner: stream::iter(replay.into_iter().map(Ok)).chain(live).boxed(),
+            dropped,
+        }
+    }
+
+    /// Streams events to the client as the response, reading `Last-Event-ID` header from the request.
+    pub fn stream(&self, req: &Request, res: &mut Response) {
+        let last_event_id = req.headers().get(LAST_EVENT_ID).and_then(|value| value.to_str().ok());
+        stream(res, self.subscribe(last_event_id));
+    }
+}
+
+/// Events of a client subscribed to a [`SseBroadcaster`].
+pub struct SseSubscription {
+    inner: BoxStream<'static, Result<SseEvent, Infallible>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl SseSubscription {
+    /// Returns a counter of the events dropped for this client, which can be read after the subscription is
+    /// moved into the response.
+    #[inline]
+    pub fn dropped(&self) -> Arc<AtomicU64> {
+        self.dropped.clone()
+    }
+}
+
+impl fmt::Debug for SseSubscription {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("SseSubscription")
+            .field("dropped", &self.dropped.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl Stream for SseSubscription {
+    type Item = Result<SseEvent, Infallible>;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::convert::Infallible;
     use std::time::Duration;
 
+    use futures_util::FutureExt;
     use salvo_core::prelude::*;
     use salvo_core::test::ResponseExt;
     use tokio_stream;
@@ -407,6 +591,48 @@ mod tests {
         assert!(text.contains("retry:1001"));
     }
 
+    #[tokio::test]
+    async fn test_sse_broadcaster() {
+        let broadcaster = SseBroadcaster::new(2).replay_size(2);
+        assert_eq!(broadcaster.send(SseEvent::default().text("1")), 0);
+        broadcaster.send(SseEvent::default().text("2"));
+        broadcaster.send(SseEvent::default().text("3"));
+
+        let mut replayed = broadcaster.subscribe(Some("1"));
+        let mut live = broadcaster.subscribe(None);
+        assert_eq!(broadcaster.client_count(), 2);
+        assert_eq!(broadcaster.send(SseEvent::default().text("4")), 2);
+        let next = |sub: &mut SseSubscription| {
+            let event = sub
+                .next()
+                .now_or_never()
+                .flatten()
+                .map(|event| event.unwrap().to_string());
+            event.unwrap_or_default()
+        };
+        assert_eq!(next(&mut replayed), "data:2\nid:2\n\n");
+        assert_eq!(next(&mut replayed), "data:3\nid:3\n\n");
+        assert_eq!(next(&mut replayed), "data:4\nid:4\n\n");
+        assert_eq!(next(&mut live), "data:4\nid:4\n\n");
+        assert_eq!(next(&mut live), "");
+
+        for i in 5..=8 {
+            broadcaster.send(SseEvent::default().text(i.to_string()));
+        }
+        assert_eq!(next(&mut live), "data:7\nid:7\n\n");
+        assert_eq!(live.dropped().load(Ordering::Relaxed), 2);
+        assert_eq!(broadcaster.dropped(), 2);
+
+        let mut req = Request::new();
+        req.headers_mut().insert(LAST_EVENT_ID, HeaderValue::from_static("7"));
+        let mut res = Response::new();
+        broadcaster.stream(&req, &mut res);
+        drop(broadcaster);
+        drop((replayed, live));
+        let text = res.take_string().await.unwrap();
+        assert_eq!(text, "data:8\nid:8\n\n");
+    }
+
     #[tokio::test]
     async fn test_sse_id() {
         let event_stream = tokio_stream::iter(vec![Ok::<_, Infallible>(SseEvent::default().id("jobs"))]);
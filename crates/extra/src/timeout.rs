@@ -1,33 +1,175 @@
-//! basic auth middleware.
+//! Timeout middleware.
 //!
 //! Read more: <https://salvo.rs>
-use std::time::Duration;
+use std::future::Future;
+use std::io::{Error as IoError, ErrorKind};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
+use salvo_core::fuse::SteadyFusewire;
+use salvo_core::http::body::{Body, Frame, ReqBody, SizeHint};
 use salvo_core::http::{Request, Response, StatusError};
-use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+use salvo_core::hyper::body::Bytes;
+use salvo_core::{async_trait, BoxedError, Depot, FlowCtrl, Handler};
+use tokio::time::Sleep;
+
+type ErrorBuilder = Box<dyn Fn() -> StatusError + Send + Sync>;
 
 /// Timeout
+///
+/// Cancels the rest of the handlers if they do not complete in time, the handler future is dropped,
+/// so all resources held by it are released. Add it to [`Service`](salvo_core::Service) for a global
+/// timeout or to a [`Router`](salvo_core::Router) for the routes under it.
+///
+/// Two phases can be limited separately:
+///
+/// - Reading the request body, enabled by [`Timeout::read_timeout`]. The body is not read in advance, reading it
+///   fails once the timeout is elapsed, and `408 Request Timeout` is rendered if the client is too slow.
+/// - Handler execution, `504 Gateway Timeout` is rendered if the handlers are too slow.
+///
+/// Both phases are also limited by the [deadline](Request::deadline) of the request, set by the client with the
+/// `X-Request-Timeout` or `grpc-timeout` header. The deadline of the request is shortened to the timeout of the
+/// handlers, so the handlers and proxies after this hoop can read the time they have left.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use salvo_core::prelude::*;
+/// use salvo_extra::timeout::Timeout;
+///
+/// let timeout = Timeout::new(Duration::from_secs(30))
+///     .read_timeout(Duration::from_secs(10))
+///     .error(|| StatusError::service_unavailable().brief("Try again later."));
+/// let router = Router::with_path("upload").hoop(timeout);
+/// ```
 pub struct Timeout {
     value: Duration,
+    read_value: Option<Duration>,
+    error: ErrorBuilder,
+    read_error: ErrorBuilder,
 }
 impl Timeout {
     /// Create a new `Timeout`.
     #[inline]
     pub fn new(value: Duration) -> Self {
-        Timeout { value }
+        Timeout {
+            value,
+            read_value: None,
+            error: Box::new(|| StatusError::gateway_timeout().brief("Server process the request timeout.")),
+            read_error: Box::new(|| StatusError::request_timeout().brief("Read the request body timeout.")),
+        }
+    }
+
+    /// Sets the timeout for reading the request body, counted from the call of this hoop, disabled by default.
+    ///
+    /// The body is streamed to the handlers as usual, so it is not limited in size and can be read by
+    /// [`Request::payload`], [`Request::form_data`] or as a stream. Once the timeout is elapsed, reading the
+    /// body fails and the response is replaced by the [`read_error`](Self::read_error).
+    #[inline]
+    pub fn read_timeout(mut self, value: Duration) -> Self {
+        self.read_value = Some(value);
+        self
+    }
+
+    /// Sets the error rendered when the handlers time out, defaults to `504 Gateway Timeout`.
+    #[inline]
+    pub fn error<F>(mut self, error: F) -> Self
+    where
+        F: Fn() -> StatusError + Send + Sync + 'static,
+    {
+        self.error = Box::new(error);
+        self
+    }
+
+    /// Sets the error rendered when reading the request body times out, defaults to `408 Request Timeout`.
+    #[inline]
+    pub fn read_error<F>(mut self, error: F) -> Self
+    where
+        F: Fn() -> StatusError + Send + Sync + 'static,
+    {
+        self.read_error = Box::new(error);
+        self
     }
 }
 #[async_trait]
 impl Handler for Timeout {
     #[inline]
     async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
-        tokio::select! {
-            _ = ctrl.call_next(req, depot, res) => {},
-            _ = tokio::time::sleep(self.value) => {
-                res.render(StatusError::internal_server_error().brief("Server process the request timeout."));
-                ctrl.skip_rest();
-            }
+        let remaining = req.remaining_time();
+        if remaining == Some(Duration::ZERO) {
+            res.render((self.error)());
+            ctrl.skip_rest();
+            return;
+        }
+        let read_timed_out = self.read_value.map(|read_value| {
+            let read_value = remaining.map_or(read_value, |remaining| read_value.min(remaining));
+            let timed_out = Arc::new(AtomicBool::new(false));
+            let body = ReadTimeoutBody {
+                inner: req.take_body(),
+                sleep: Box::pin(tokio::time::sleep(read_value)),
+                timed_out: timed_out.clone(),
+            };
+            req.replace_body(ReqBody::Boxed {
+                inner: Box::pin(body),
+                fusewire: Arc::new(SteadyFusewire),
+            });
+            timed_out
+        });
+        let value = req
+            .remaining_time()
+            .map_or(self.value, |remaining| self.value.min(remaining));
+        req.set_deadline(Instant::now().checked_add(value));
+        if tokio::time::timeout(value, ctrl.call_next(req, depot, res))
+            .await
+            .is_err()
+        {
+            res.render((self.error)());
+            ctrl.skip_rest();
+        }
+        if read_timed_out.is_some_and(|timed_out| timed_out.load(Ordering::Acquire)) {
+            res.render((self.read_error)());
+            ctrl.skip_rest();
+        }
+    }
+}
+
+/// Request body failing with a `TimedOut` error once the read timeout is elapsed.
+struct ReadTimeoutBody {
+    inner: ReqBody,
+    sleep: Pin<Box<Sleep>>,
+    timed_out: Arc<AtomicBool>,
+}
+
+impl Body for ReadTimeoutBody {
+    type Data = Bytes;
+    type Error = BoxedError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if let Poll::Ready(frame) = Pin::new(&mut self.inner).poll_frame(cx) {
+            return Poll::Ready(frame.map(|frame| frame.map_err(BoxedError::from)));
+        }
+        if self.sleep.as_mut().poll(cx).is_ready() {
+            self.timed_out.store(true, Ordering::Release);
+            let error = IoError::new(ErrorKind::TimedOut, "read the request body timeout");
+            return Poll::Ready(Some(Err(error.into())));
         }
+        Poll::Pending
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
     }
 }
 
@@ -72,4 +214,158 @@ mod tests {
             .unwrap();
         assert!(content.contains("hello"));
     }
+
+    #[tokio::test]
+    async fn test_timeout_phases() {
+        #[handler]
+        async fn echo(req: &mut Request) -> String {
+            String::from_utf8_lossy(req.payload().await.unwrap()).into_owned()
+        }
+        #[handler]
+        async fn slow() {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        let router = Router::new()
+            .hoop(
+                Timeout::new(Duration::from_millis(100))
+                    .read_timeout(Duration::from_secs(1))
+                    .error(StatusError::service_unavailable),
+            )
+            .push(Router::with_path("echo").post(echo))
+            .push(Router::with_path("slow").get(slow));
+        let service = Service::new(router);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/echo")
+            .body("payload")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "payload");
+
+        let res = TestClient::get("http://127.0.0.1:5801/slow").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_stalled_body() {
+        use salvo_core::conn::{Acceptor, Listener};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        #[handler]
+        async fn echo(req: &mut Request, res: &mut Response) {
+            match req.payload().await {
+                Ok(payload) => res.render(String::from_utf8_lossy(payload).into_owned()),
+                Err(e) => res.render(StatusError::bad_request().cause(e)),
+            }
+        }
+
+        let router = Router::new()
+            .hoop(Timeout::new(Duration::from_secs(5)).read_timeout(Duration::from_millis(200)))
+            .post(echo);
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(async move {
+            Server::new(acceptor).serve(router).await;
+        });
+
+        // Only a part of the declared body is sent, then the client stalls.
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"POST / HTTP/1.1\r\nhost: localhost\r\ncontent-length: 100\r\n\r\npartial")
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut buf = [0; 1024];
+            while !response.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                response.extend_from_slice(&buf[..n]);
+            }
+        })
+        .await;
+        assert!(read.is_ok(), "the stalled request should be answered");
+        assert!(String::from_utf8_lossy(&response).starts_with("HTTP/1.1 408"));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_large_and_multipart_bodies() {
+        #[handler]
+        async fn size(req: &mut Request) -> String {
+            req.payload_with_max_size(usize::MAX).await.unwrap().len().to_string()
+        }
+        #[handler]
+        async fn upload(req: &mut Request) -> String {
+            let file = req.file("file").await.unwrap();
+            format!("{}:{}", file.name().unwrap(), file.size())
+        }
+
+        let router = Router::new()
+            .hoop(Timeout::new(Duration::from_secs(5)).read_timeout(Duration::from_secs(5)))
+            .push(Router::with_path("size").post(size))
+            .push(Router::with_path("upload").post(upload));
+        let service = Service::new(router);
+
+        // Larger than the default max size of `Request::payload`, the body is not read by the hoop.
+        let content = TestClient::post("http://127.0.0.1:5801/size")
+            .bytes(vec![b'a'; 200 * 1024])
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert_eq!(content, (200 * 1024).to_string());
+
+        let mut body = Vec::new();
+        body.extend_from_slice(b"--BOUNDARY\r\n");
+        body.extend_from_slice(b"content-disposition: form-data; name=\"file\"; filename=\"data.bin\"\r\n");
+        body.extend_from_slice(b"content-type: application/octet-stream\r\n\r\n");
+        body.extend_from_slice(&[7; 100 * 1024]);
+        body.extend_from_slice(b"\r\n--BOUNDARY--\r\n");
+        let content = TestClient::post("http://127.0.0.1:5801/upload")
+            .add_header("content-type", "multipart/form-data; boundary=BOUNDARY", true)
+            .bytes(body)
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert_eq!(content, format!("data.bin:{}", 100 * 1024));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_deadline() {
+        #[handler]
+        async fn slow(req: &mut Request) -> String {
+            let remaining = req.remaining_time().unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            remaining.as_millis().to_string()
+        }
+
+        let router = Router::new().hoop(Timeout::new(Duration::from_secs(1))).get(slow);
+        let service = Service::new(router);
+
+        let remaining = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        let remaining = remaining.parse::<u64>().unwrap();
+        assert!(remaining > 500 && remaining <= 1000);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-request-timeout", "100", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::GATEWAY_TIMEOUT));
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("grpc-timeout", "0m", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::GATEWAY_TIMEOUT));
+    }
 }
@@ -0,0 +1,462 @@
+//! Resumable uploads with the [tus protocol](https://tus.io/protocols/resumable-upload).
+//!
+//! [`Tus`] implements the core protocol and the `creation`, `creation-defer-length`, `termination` and
+//! `expiration` extensions. A client creates an upload with a `POST` request, then sends its content with `PATCH`
+//! requests. When the connection is lost, the client asks the offset of the upload with a `HEAD` request, and
+//! resumes from it. The state and the data of uploads are saved by a [`TusStore`], like [`DiskStore`].
+//!
+//! The handler must be the goal of a router whose path ends with a rest param, which holds the id of the upload.
+//!
+//! `PATCH` and `DELETE` requests of an upload are handled one at a time, a request sent while another one is writing
+//! the same upload waits for it, then fails with `409 Conflict` if its offset is outdated.
+//!
+//! Expired uploads are deleted when they are requested again, call [`Tus::purge_expired`] periodically to delete
+//! the uploads which are never resumed.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use salvo_core::prelude::*;
+//! use salvo_extra::tus::{DiskStore, Tus};
+//!
+//! let tus = Tus::new(DiskStore::new("./uploads"))
+//!     .max_size(1024 * 1024 * 1024)
+//!     .expiration(Duration::from_secs(24 * 3600));
+//! let router = Router::with_path("files/<**id>").goal(tus);
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use futures_util::StreamExt;
+use salvo_core::http::header::{HeaderName, CACHE_CONTROL, CONTENT_TYPE, LOCATION};
+use salvo_core::http::headers::{Date, Header};
+use salvo_core::http::{HeaderValue, Method, Request, Response, StatusCode, StatusError};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use ulid::Ulid;
+
+mod store;
+pub use store::{DiskStore, MemoryStore, TusStore, UploadInfo};
+
+/// Version of the tus protocol implemented.
+pub const TUS_VERSION: &str = "1.0.0";
+/// Extensions of the tus protocol implemented, `expiration` is added when an expiration is set.
+pub const TUS_EXTENSIONS: &str = "creation,creation-defer-length,termination";
+
+/// Header `Tus-Resumable`.
+pub const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+/// Header `Tus-Version`.
+pub const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+/// Header `Tus-Extension`.
+pub const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+/// Header `Tus-Max-Size`.
+pub const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+/// Header `Upload-Offset`.
+pub const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+/// Header `Upload-Length`.
+pub const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+/// Header `Upload-Defer-Length`.
+pub const UPLOAD_DEFER_LENGTH: HeaderName = HeaderName::from_static("upload-defer-length");
+/// Header `Upload-Metadata`.
+pub const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+/// Header `Upload-Expires`.
+pub const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+
+/// Content type of the body of `PATCH` requests.
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+/// Handler of the tus resumable upload protocol.
+pub struct Tus<S> {
+    store: S,
+    max_size: Option<u64>,
+    expiration: Option<Duration>,
+    locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+/// Lock of an upload, removed from the locks of [`Tus`] when no request is waiting for it.
+struct UploadLock<'a> {
+    locks: &'a Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+    id: &'a str,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+impl Drop for UploadLock<'_> {
+    fn drop(&mut self) {
+        self.guard.take();
+        let mut locks = self.locks.lock().expect("lock should not be poisoned");
+        if locks.get(self.id).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+            locks.remove(self.id);
+        }
+    }
+}
+
+impl<S> Tus<S>
+where
+    S: TusStore,
+{
+    /// Create a new `Tus` saving uploads in `store`.
+    #[inline]
+    pub fn new(store: S) -> Self {
+        Tus {
+            store,
+            max_size: None,
+            expiration: None,
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the store of uploads, to read completed uploads.
+    #[inline]
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Sets the max size of uploads.
+    #[inline]
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets the time after which uploads not updated can not be resumed.
+    ///
+    /// Expired uploads are deleted when they are requested, or by [`Tus::purge_expired`].
+    #[inline]
+    pub fn expiration(mut self, expiration: Duration) -> Self {
+        self.expiration = Some(expiration);
+        self
+    }
+
+    /// Delete the expired uploads of the store, returns the number of uploads deleted.
+    ///
+    /// It should be called periodically, like in a task spawned with the server, when an expiration is set.
+    pub async fn purge_expired(&self) -> Result<usize, S::Error> {
+        self.store.purge_expired().await
+    }
+
+    async fn lock<'a>(&'a self, id: &'a str) -> UploadLock<'a> {
+        let lock = self
+            .locks
+            .lock()
+            .expect("lock should not be poisoned")
+            .entry(id.to_owned())
+            .or_default()
+            .clone();
+        UploadLock {
+            locks: &self.locks,
+            id,
+            guard: Some(lock.lock_owned().await),
+        }
+    }
+
+    fn expires(&self) -> Option<SystemTime> {
+        self.expiration.and_then(|expiration| SystemTime::now().checked_add(expiration))
+    }
+
+    fn write_options(&self, res: &mut Response) {
+        let headers = res.headers_mut();
+        headers.insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+        headers.insert(
+            TUS_EXTENSION,
+            HeaderValue::from_static(if self.expiration.is_some() {
+                "creation,creation-defer-length,termination,expiration"
+            } else {
+                TUS_EXTENSIONS
+            }),
+        );
+        if let Some(max_size) = self.max_size {
+            headers.insert(TUS_MAX_SIZE, max_size.into());
+        }
+        res.status_code(StatusCode::NO_CONTENT);
+    }
+
+    async fn create(&self, req: &mut Request, res: &mut Response) {
+        let mut info = UploadInfo::new(Ulid::new().to_string().to_lowercase());
+        info.length = req.header::<u64>(UPLOAD_LENGTH);
+        if info.length.is_none() && req.header::<String>(UPLOAD_DEFER_LENGTH).as_deref() != Some("1") {
+            res.render(StatusError::bad_request().brief("Missing Upload-Length header."));
+            return;
+        }
+        if info.length.zip(self.max_size).is_some_and(|(length, max)| length > max) {
+            res.render(StatusError::payload_too_large());
+            return;
+        }
+        info.metadata = req.header::<String>(UPLOAD_METADATA);
+        info.expires = self.expires();
+        if let Err(e) = self.store.save(&info).await {
+            tracing::error!(error = ?e, "failed to create upload");
+            res.render(StatusError::internal_server_error());
+            return;
+        }
+        let location = format!("{}/{}", req.uri().path().trim_end_matches('/'), info.id);
+        if let Ok(location) = HeaderValue::from_str(&location) {
+            res.headers_mut().insert(LOCATION, location);
+        }
+        write_expires(res, &info);
+        res.status_code(StatusCode::CREATED);
+    }
+
+    async fn load(&self, id: &str, res: &mut Response) -> Option<UploadInfo> {
+        let is_valid_id = !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !is_valid_id {
+            res.render(StatusError::not_found());
+            return None;
+        }
+        match self.store.load(id).await {
+            Ok(Some(info)) if info.is_expired() => {
+                if let Err(e) = self.store.delete(id).await {
+                    tracing::error!(error = ?e, "failed to delete expired upload");
+                }
+                res.render(StatusError::gone());
+                None
+            }
+            Ok(Some(info)) => Some(info),
+            Ok(None) => {
+                res.render(StatusError::not_found());
+                None
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to load upload");
+                res.render(StatusError::internal_server_error());
+                None
+            }
+        }
+    }
+
+    async fn append(&self, req: &mut Request, res: &mut Response, mut info: UploadInfo) {
+        if req.header::<String>(CONTENT_TYPE).as_deref() != Some(OFFSET_OCTET_STREAM) {
+            res.render(StatusError::unsupported_media_type());
+            return;
+        }
+        if req.header::<u64>(UPLOAD_OFFSET) != Some(info.offset) {
+            res.render(StatusError::conflict().brief("Upload-Offset does not match the offset of the upload."));
+            return;
+        }
+        if info.length.is_none() {
+            info.length = req.header::<u64>(UPLOAD_LENGTH);
+            if info.length.zip(self.max_size).is_some_and(|(length, max)| length > max) {
+                res.render(StatusError::payload_too_large());
+                return;
+            }
+        }
+        let max_length = info.length.or(self.max_size);
+
+        let mut body = req.take_body();
+        let mut too_large = false;
+        let mut failed = false;
+        while let Some(frame) = body.next().await {
+            // The client is disconnected, the chunks received are kept to be resumed.
+            let Ok(Ok(data)) = frame.map(|frame| frame.into_data()) else {
+                break;
+            };
+            if max_length.is_some_and(|max| info.offset + data.len() as u64 > max) {
+                too_large = true;
+                break;
+            }
+            let len = data.len() as u64;
+            if let Err(e) = self.store.write(&info.id, info.offset, data).await {
+                tracing::error!(error = ?e, "failed to write upload");
+                failed = true;
+                break;
+            }
+            info.offset += len;
+        }
+        info.expires = self.expires();
+        if let Err(e) = self.store.save(&info).await {
+            tracing::error!(error = ?e, "failed to save upload");
+            failed = true;
+        }
+        if failed {
+            res.render(StatusError::internal_server_error());
+        } else if too_large {
+            res.render(StatusError::payload_too_large());
+        } else {
+            res.headers_mut().insert(UPLOAD_OFFSET, info.offset.into());
+            write_expires(res, &info);
+            res.status_code(StatusCode::NO_CONTENT);
+        }
+    }
+}
+
+fn write_expires(res: &mut Response, info: &UploadInfo) {
+    if let Some(expires) = info.expires {
+        let mut values = Vec::with_capacity(1);
+        Date::from(expires).encode(&mut values);
+        if let Some(value) = values.pop() {
+            res.headers_mut().insert(UPLOAD_EXPIRES, value);
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Handler for Tus<S>
+where
+    S: TusStore,
+{
+    async fn handle(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        res.headers_mut()
+            .insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+        ctrl.skip_rest();
+        if req.method() == Method::OPTIONS {
+            self.write_options(res);
+            return;
+        }
+        if req.header::<String>(TUS_RESUMABLE).as_deref() != Some(TUS_VERSION) {
+            res.headers_mut()
+                .insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+            res.render(StatusError::precondition_failed().brief("Unsupported tus version."));
+            return;
+        }
+        let id = req
+            .params()
+            .iter()
+            .find(|(key, _)| key.starts_with('*'))
+            .map(|(_, value)| value.trim_matches('/').to_owned())
+            .unwrap_or_default();
+        match *req.method() {
+            Method::POST if id.is_empty() => self.create(req, res).await,
+            Method::HEAD => {
+                if let Some(info) = self.load(&id, res).await {
+                    let headers = res.headers_mut();
+                    headers.insert(UPLOAD_OFFSET, info.offset.into());
+                    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+                    match info.length {
+                        Some(length) => headers.insert(UPLOAD_LENGTH, length.into()),
+                        None => headers.insert(UPLOAD_DEFER_LENGTH, HeaderValue::from_static("1")),
+                    };
+                    if let Some(metadata) = info.metadata.as_deref().and_then(|m| HeaderValue::from_str(m).ok()) {
+                        headers.insert(UPLOAD_METADATA, metadata);
+                    }
+                    write_expires(res, &info);
+                    res.status_code(StatusCode::OK);
+                }
+            }
+            Method::PATCH => {
+                let _lock = self.lock(&id).await;
+                if let Some(info) = self.load(&id, res).await {
+                    self.append(req, res, info).await;
+                }
+            }
+            Method::DELETE => {
+                let _lock = self.lock(&id).await;
+                if self.load(&id, res).await.is_some() {
+                    if let Err(e) = self.store.delete(&id).await {
+                        tracing::error!(error = ?e, "failed to delete upload");
+                        res.render(StatusError::internal_server_error());
+                    } else {
+                        res.status_code(StatusCode::NO_CONTENT);
+                    }
+                }
+            }
+            _ => {
+                res.render(StatusError::method_not_allowed());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tus_upload() {
+        struct Shared(Arc<Tus<MemoryStore>>);
+        #[async_trait]
+        impl Handler for Shared {
+            async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+                self.0.handle(req, depot, res, ctrl).await
+            }
+        }
+        let tus = Arc::new(Tus::new(MemoryStore::new()).max_size(10).expiration(Duration::from_secs(60)));
+        let service = Service::new(Router::with_path("files/<**id>").goal(Shared(tus.clone())));
+
+        let res = TestClient::post("http://127.0.0.1:5801/files")
+            .add_header("upload-length", "6", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::PRECONDITION_FAILED));
+
+        let res = TestClient::post("http://127.0.0.1:5801/files")
+            .add_header("tus-resumable", "1.0.0", true)
+            .add_header("upload-length", "6", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::CREATED));
+        assert!(res.headers().contains_key(UPLOAD_EXPIRES));
+        let location = res.headers()[LOCATION].to_str().unwrap().to_owned();
+        let id = location.strip_prefix("/files/").unwrap().to_owned();
+        let url = format!("http://127.0.0.1:5801{location}");
+
+        let res = TestClient::patch(&url)
+            .add_header("tus-resumable", "1.0.0", true)
+            .add_header("upload-offset", "0", true)
+            .add_header("content-type", OFFSET_OCTET_STREAM, true)
+            .body("abc")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::NO_CONTENT));
+        assert_eq!(res.headers()[UPLOAD_OFFSET], "3");
+
+        let res = TestClient::patch(&url)
+            .add_header("tus-resumable", "1.0.0", true)
+            .add_header("upload-offset", "0", true)
+            .add_header("content-type", OFFSET_OCTET_STREAM, true)
+            .body("abc")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::CONFLICT));
+
+        let res = TestClient::head(&url)
+            .add_header("tus-resumable", "1.0.0", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.headers()[UPLOAD_OFFSET], "3");
+        assert_eq!(res.headers()[UPLOAD_LENGTH], "6");
+
+        let res = TestClient::patch(&url)
+            .add_header("tus-resumable", "1.0.0", true)
+            .add_header("upload-offset", "3", true)
+            .add_header("content-type", OFFSET_OCTET_STREAM, true)
+            .body("def")
+            .send(&service)
+            .await;
+        assert_eq!(res.headers()[UPLOAD_OFFSET], "6");
+        assert!(tus.store().load(&id).await.unwrap().unwrap().is_complete());
+        assert_eq!(tus.store().data(&id).unwrap(), b"abcdef");
+
+        let res = TestClient::delete(&url)
+            .add_header("tus-resumable", "1.0.0", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::NO_CONTENT));
+        let res = TestClient::head(&url)
+            .add_header("tus-resumable", "1.0.0", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_tus_purge_expired() {
+        let tus = Tus::new(MemoryStore::new()).expiration(Duration::ZERO);
+        let mut info = UploadInfo::new("expired");
+        info.expires = tus.expires();
+        tus.store().save(&info).await.unwrap();
+        tus.store().save(&UploadInfo::new("kept")).await.unwrap();
+
+        assert_eq!(tus.purge_expired().await.unwrap(), 1);
+        assert!(tus.store().load("expired").await.unwrap().is_none());
+        assert!(tus.store().load("kept").await.unwrap().is_some());
+        assert!(tus.locks.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::io::{Error as IoError, ErrorKind, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use salvo_core::hyper::body::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+/// State of an upload, saved by a [`TusStore`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct UploadInfo {
+    /// The id of the upload, used in its url.
+    pub id: String,
+    /// The number of bytes received.
+    pub offset: u64,
+    /// The size of the upload, `None` until it is sent by the client if its length is deferred.
+    pub length: Option<u64>,
+    /// The raw value of the `Upload-Metadata` header sent when the upload is created.
+    pub metadata: Option<String>,
+    /// The time after which the upload can not be resumed.
+    pub expires: Option<SystemTime>,
+}
+impl UploadInfo {
+    /// Create a new empty upload.
+    #[inline]
+    pub fn new(id: impl Into<String>) -> Self {
+        UploadInfo {
+            id: id.into(),
+            offset: 0,
+            length: None,
+            metadata: None,
+            expires: None,
+        }
+    }
+    /// Check if all the bytes of the upload are received.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.length == Some(self.offset)
+    }
+    /// Check if the upload is expired.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= SystemTime::now())
+    }
+}
+
+/// Storage of the state and the data of uploads.
+pub trait TusStore: Send + Sync + 'static {
+    /// Error type for TusStore.
+    type Error: StdError + Sync + Send + 'static;
+    /// Load the state of an upload, `None` if it does not exist.
+    fn load(&self, id: &str) -> impl Future<Output = Result<Option<UploadInfo>, Self::Error>> + Send;
+    /// Save the state of an upload, creating it if it does not exist.
+    fn save(&self, info: &UploadInfo) -> impl Future<Output = Result<(), Self::Error>> + Send;
+    /// Write a chunk of data of an upload at `offset`, which is the number of bytes already written.
+    fn write(&self, id: &str, offset: u64, data: Bytes) -> impl Future<Output = Result<(), Self::Error>> + Send;
+    /// Delete the state and the data of an upload.
+    fn delete(&self, id: &str) -> impl Future<Output = Result<(), Self::Error>> + Send;
+    /// Delete the state and the data of all expired uploads, returns the number of uploads deleted.
+    fn purge_expired(&self) -> impl Future<Output = Result<usize, Self::Error>> + Send;
+}
+
+/// A [`TusStore`] keeping uploads in memory, for tests and small files.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    uploads: Mutex<HashMap<String, (UploadInfo, Vec<u8>)>>,
+}
+impl MemoryStore {
+    /// Create a new `MemoryStore`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Get the data received of an upload.
+    pub fn data(&self, id: &str) -> Option<Vec<u8>> {
+        self.uploads
+            .lock()
+            .expect("lock should not be poisoned")
+            .get(id)
+            .map(|(_, data)| data.clone())
+    }
+}
+impl TusStore for MemoryStore {
+    type Error = Infallible;
+
+    async fn load(&self, id: &str) -> Result<Option<UploadInfo>, Self::Error> {
+        let uploads = self.uploads.lock().expect("lock should not be poisoned");
+        Ok(uploads.get(id).map(|(info, _)| info.clone()))
+    }
+    async fn save(&self, info: &UploadInfo) -> Result<(), Self::Error> {
+        let mut uploads = self.uploads.lock().expect("lock should not be poisoned");
+        uploads
+            .entry(info.id.clone())
+            .and_modify(|(saved, _)| *saved = info.clone())
+            .or_insert_with(|| (info.clone(), Vec::new()));
+        Ok(())
+    }
+    async fn write(&self, id: &str, offset: u64, data: Bytes) -> Result<(), Self::Error> {
+        let mut uploads = self.uploads.lock().expect("lock should not be poisoned");
+        if let Some((_, content)) = uploads.get_mut(id) {
+            content.truncate(offset as usize);
+            content.extend_from_slice(&data);
+        }
+        Ok(())
+    }
+    async fn delete(&self, id: &str) -> Result<(), Self::Error> {
+        self.uploads.lock().expect("lock should not be poisoned").remove(id);
+        Ok(())
+    }
+    async fn purge_expired(&self) -> Result<usize, Self::Error> {
+        let mut uploads = self.uploads.lock().expect("lock should not be poisoned");
+        let count = uploads.len();
+        uploads.retain(|_, (info, _)| !info.is_expired());
+        Ok(count - uploads.len())
+    }
+}
+
+/// A [`TusStore`] writing uploads in a directory.
+///
+/// The data of an upload is written in a file named with its id, and its state in a JSON file with the `.info`
+/// extension.
+#[derive(Clone, Debug)]
+pub struct DiskStore {
+    dir: PathBuf,
+}
+impl DiskStore {
+    /// Create a new `DiskStore` writing uploads in `dir`, which is created if it does not exist.
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskStore { dir: dir.into() }
+    }
+    /// Get the path of the file with the data of an upload.
+    #[inline]
+    pub fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+    fn info_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.info"))
+    }
+}
+impl TusStore for DiskStore {
+    type Error = IoError;
+
+    async fn load(&self, id: &str) -> Result<Option<UploadInfo>, Self::Error> {
+        match fs::read(self.info_path(id)).await {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    async fn save(&self, info: &UploadInfo) -> Result<(), Self::Error> {
+        fs::create_dir_all(&self.dir).await?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.data_path(&info.id))
+            .await?;
+        fs::write(self.info_path(&info.id), serde_json::to_vec(info)?).await
+    }
+    async fn write(&self, id: &str, offset: u64, data: Bytes) -> Result<(), Self::Error> {
+        let mut file = OpenOptions::new().write(true).open(self.data_path(id)).await?;
+        file.set_len(offset).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.write_all(&data).await?;
+        file.flush().await
+    }
+    async fn delete(&self, id: &str) -> Result<(), Self::Error> {
+        for path in [self.data_path(id), self.info_path(id)] {
+            match fs::remove_file(path).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    async fn purge_expired(&self) -> Result<usize, Self::Error> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut count = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let Some(id) = file_name.to_str().and_then(|name| name.strip_suffix(".info")) else {
+                continue;
+            };
+            if self.load(id).await?.is_some_and(|info| info.is_expired()) {
+                self.delete(id).await?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,382 @@
+//! Webhook signature verification middleware.
+//!
+//! [`WebhookSignature`] verifies the HMAC signature of the body of requests sent by webhooks, like the ones of GitHub,
+//! Stripe or Slack. The body is read up to a max size, the signature is computed from the secret, the optional
+//! timestamp of the request and the raw body, and compared in constant time with the signatures of the request.
+//! Requests with a missing or wrong signature, or a timestamp out of the tolerance, are rejected with
+//! `401 Unauthorized`.
+//!
+//! The verified body is kept in the request, so handlers can parse it with `req.parse_json()` or read the raw bytes
+//! with `req.payload()`, and it is also stored in the [`Depot`], read with
+//! [`WebhookSignatureDepotExt::verified_body`].
+//!
+//! # Example
+//!
+//! ```
+//! use salvo_core::prelude::*;
+//! use salvo_extra::webhook_signature::WebhookSignature;
+//!
+//! #[handler]
+//! async fn push(req: &mut Request) -> StatusCode {
+//!     let _event = req.payload().await;
+//!     StatusCode::NO_CONTENT
+//! }
+//!
+//! let router = Router::with_path("webhooks/github")
+//!     .hoop(WebhookSignature::github("my secret"))
+//!     .post(push);
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use salvo_core::http::header::HeaderName;
+use salvo_core::http::request::secure_max_size;
+use salvo_core::http::{Request, Response, StatusError};
+use salvo_core::hyper::body::Bytes;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+use sha2::{Sha256, Sha512};
+
+/// Key for the verified body of the request in depot.
+pub const VERIFIED_BODY_KEY: &str = "::salvo::webhook_signature::verified_body";
+
+/// Extension of [`Depot`] to get the body verified by [`WebhookSignature`].
+pub trait WebhookSignatureDepotExt {
+    /// Get the raw body of the request whose signature is verified.
+    fn verified_body(&self) -> Option<&Bytes>;
+}
+
+impl WebhookSignatureDepotExt for Depot {
+    #[inline]
+    fn verified_body(&self) -> Option<&Bytes> {
+        self.get::<Bytes>(VERIFIED_BODY_KEY).ok()
+    }
+}
+
+/// HMAC algorithm of signatures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignatureAlgorithm {
+    /// HMAC with SHA-256.
+    #[default]
+    HmacSha256,
+    /// HMAC with SHA-512.
+    HmacSha512,
+}
+
+/// Encoding of signatures in headers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignatureEncoding {
+    /// Lowercase or uppercase hexadecimal.
+    #[default]
+    Hex,
+    /// Standard base64, with padding.
+    Base64,
+}
+
+/// Where the timestamp of a signed request is read from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimestampSource {
+    /// A header with the timestamp in seconds since the Unix epoch, like `X-Slack-Request-Timestamp`.
+    Header(HeaderName),
+    /// A field of the signature header, like `t` in `Stripe-Signature: t=1492774577,v1=5257a869...`.
+    SignatureField(String),
+}
+
+/// Middleware verifying HMAC signatures of request bodies.
+///
+/// The signature header can hold several comma separated signatures, the request is accepted if one of the ones
+/// starting with the signature prefix matches, so secrets can be rotated by the sender.
+///
+/// When a timestamp source is set, the signed payload is the payload prefix, with `{timestamp}` replaced by the
+/// timestamp, followed by the body, and requests whose timestamp is older or newer than the tolerance are rejected
+/// to prevent replay attacks.
+pub struct WebhookSignature {
+    secrets: Vec<Vec<u8>>,
+    algorithm: SignatureAlgorithm,
+    encoding: SignatureEncoding,
+    signature_header: HeaderName,
+    signature_prefix: String,
+    timestamp_source: Option<TimestampSource>,
+    payload_prefix: String,
+    tolerance: Duration,
+    max_size: usize,
+}
+
+impl WebhookSignature {
+    /// Create a new `WebhookSignature` verifying HMAC-SHA256 signatures, hex encoded in `signature_header`.
+    #[inline]
+    pub fn new(secret: impl Into<Vec<u8>>, signature_header: HeaderName) -> Self {
+        WebhookSignature {
+            secrets: vec![secret.into()],
+            algorithm: SignatureAlgorithm::default(),
+            encoding: SignatureEncoding::default(),
+            signature_header,
+            signature_prefix: String::new(),
+            timestamp_source: None,
+            payload_prefix: String::new(),
+            tolerance: Duration::from_secs(300),
+            max_size: secure_max_size(),
+        }
+    }
+
+    /// Create a `WebhookSignature` for GitHub webhooks, signed in `X-Hub-Signature-256: sha256=<signature>`.
+    pub fn github(secret: impl Into<Vec<u8>>) -> Self {
+        Self::new(secret, HeaderName::from_static("x-hub-signature-256")).signature_prefix("sha256=")
+    }
+
+    /// Create a `WebhookSignature` for Stripe webhooks, signed in `Stripe-Signature: t=<timestamp>,v1=<signature>`.
+    pub fn stripe(secret: impl Into<Vec<u8>>) -> Self {
+        Self::new(secret, HeaderName::from_static("stripe-signature"))
+            .signature_prefix("v1=")
+            .timestamp_source(TimestampSource::SignatureField("t".into()))
+            .payload_prefix("{timestamp}.")
+    }
+
+    /// Create a `WebhookSignature` for Slack requests, signed in `X-Slack-Signature: v0=<signature>` with the
+    /// timestamp in `X-Slack-Request-Timestamp`.
+    pub fn slack(secret: impl Into<Vec<u8>>) -> Self {
+        Self::new(secret, HeaderName::from_static("x-slack-signature"))
+            .signature_prefix("v0=")
+            .timestamp_source(TimestampSource::Header(HeaderName::from_static(
+                "x-slack-request-timestamp",
+            )))
+            .payload_prefix("v0:{timestamp}:")
+    }
+
+    /// Adds a secret, a signature made with any of the secrets is accepted.
+    #[inline]
+    pub fn add_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secrets.push(secret.into());
+        self
+    }
+
+    /// Sets the HMAC algorithm, defaults to HMAC-SHA256.
+    #[inline]
+    pub fn algorithm(mut self, algorithm: SignatureAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the encoding of signatures, defaults to hex.
+    #[inline]
+    pub fn encoding(mut self, encoding: SignatureEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the prefix of signatures in the signature header, like `sha256=`.
+    #[inline]
+    pub fn signature_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.signature_prefix = prefix.into();
+        self
+    }
+
+    /// Sets where the timestamp of requests is read from, requests without a timestamp are rejected.
+    #[inline]
+    pub fn timestamp_source(mut self, source: TimestampSource) -> Self {
+        self.timestamp_source = Some(source);
+        self
+    }
+
+    /// Sets the text signed before the body, where `{timestamp}` is replaced by the timestamp of the request.
+    #[inline]
+    pub fn payload_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.payload_prefix = prefix.into();
+        self
+    }
+
+    /// Sets the max difference between the timestamp of requests and the current time, defaults to 5 minutes.
+    #[inline]
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the max size of bodies, defaults to [`secure_max_size`].
+    #[inline]
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    fn find_timestamp(&self, req: &Request) -> Option<Option<String>> {
+        let Some(source) = &self.timestamp_source else {
+            return Some(None);
+        };
+        let timestamp = match source {
+            TimestampSource::Header(name) => req.header::<String>(name)?,
+            TimestampSource::SignatureField(field) => self
+                .header_fields(req)
+                .find_map(|(key, value)| (key == field).then(|| value.to_owned()))?,
+        };
+        let seconds = timestamp.trim().parse::<u64>().ok()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        (now.abs_diff(seconds) <= self.tolerance.as_secs()).then_some(Some(timestamp))
+    }
+
+    fn header_fields<'a>(&self, req: &'a Request) -> impl Iterator<Item = (&'a str, &'a str)> {
+        req.headers()
+            .get_all(&self.signature_header)
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|field| field.trim().split_once('='))
+    }
+
+    fn signatures(&self, req: &Request) -> Vec<Vec<u8>> {
+        req.headers()
+            .get_all(&self.signature_header)
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|signature| signature.trim().strip_prefix(&*self.signature_prefix))
+            .filter_map(|signature| match self.encoding {
+                SignatureEncoding::Hex => hex::decode(signature).ok(),
+                SignatureEncoding::Base64 => STANDARD.decode(signature).ok(),
+            })
+            .collect()
+    }
+
+    fn verify(&self, secret: &[u8], prefix: &[u8], body: &[u8], signature: &[u8]) -> bool {
+        match self.algorithm {
+            SignatureAlgorithm::HmacSha256 => verify_mac::<Hmac<Sha256>>(secret, prefix, body, signature),
+            SignatureAlgorithm::HmacSha512 => verify_mac::<Hmac<Sha512>>(secret, prefix, body, signature),
+        }
+    }
+}
+
+/// Computes the MAC of the payload and compares it with the signature in constant time.
+fn verify_mac<M: Mac + KeyInit>(secret: &[u8], prefix: &[u8], body: &[u8], signature: &[u8]) -> bool {
+    let Ok(mut mac) = <M as Mac>::new_from_slice(secret) else {
+        return false;
+    };
+    mac.update(prefix);
+    mac.update(body);
+    mac.verify_slice(signature).is_ok()
+}
+
+#[async_trait]
+impl Handler for WebhookSignature {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let signatures = self.signatures(req);
+        if signatures.is_empty() {
+            res.render(StatusError::unauthorized().brief("Missing webhook signature."));
+            ctrl.skip_rest();
+            return;
+        }
+        let Some(timestamp) = self.find_timestamp(req) else {
+            res.render(StatusError::unauthorized().brief("Missing or expired webhook timestamp."));
+            ctrl.skip_rest();
+            return;
+        };
+        let prefix = match &timestamp {
+            Some(timestamp) => self.payload_prefix.replace("{timestamp}", timestamp),
+            None => self.payload_prefix.clone(),
+        };
+        let body = match req.payload_with_max_size(self.max_size).await {
+            Ok(body) => body.clone(),
+            Err(e) => {
+                tracing::debug!(error = ?e, "failed to read webhook body");
+                res.render(StatusError::payload_too_large());
+                ctrl.skip_rest();
+                return;
+            }
+        };
+        let verified = self.secrets.iter().any(|secret| {
+            signatures
+                .iter()
+                .any(|signature| self.verify(secret, prefix.as_bytes(), &body, signature))
+        });
+        if verified {
+            depot.insert(VERIFIED_BODY_KEY, body);
+            ctrl.call_next(req, depot, res).await;
+        } else {
+            res.render(StatusError::unauthorized().brief("Invalid webhook signature."));
+            ctrl.skip_rest();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    fn sign(secret: &str, payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(payload.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[handler]
+    async fn echo(req: &mut Request, depot: &mut Depot) -> String {
+        let verified = depot.verified_body().cloned().unwrap();
+        let payload = req.payload().await.unwrap();
+        assert_eq!(&verified, payload);
+        String::from_utf8(payload.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_github_signature() {
+        let router = Router::new().hoop(WebhookSignature::github("secret")).post(echo);
+        let service = Service::new(router);
+
+        let signature = format!("sha256={}", sign("secret", "payload"));
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-hub-signature-256", signature, true)
+            .text("payload")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "payload");
+
+        let signature = format!("sha256={}", sign("other", "payload"));
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-hub-signature-256", signature, true)
+            .text("payload")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::UNAUTHORIZED);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .text("payload")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_stripe_signature() {
+        let router = Router::new()
+            .hoop(WebhookSignature::stripe("old").add_secret("new"))
+            .post(echo);
+        let service = Service::new(router);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let signature = format!("t={now},v1={}", sign("new", &format!("{now}.payload")));
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("stripe-signature", signature, true)
+            .text("payload")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let expired = now - 3600;
+        let signature = format!("t={expired},v1={}", sign("new", &format!("{expired}.payload")));
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("stripe-signature", signature, true)
+            .text("payload")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -11,21 +11,30 @@ use std::fmt::{self, Formatter};
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll, ready};
+use std::time::Duration;
 
 use futures_util::sink::{Sink, SinkExt};
-use futures_util::stream::{Stream, StreamExt};
+use futures_util::stream::{SplitSink, SplitStream, Stream, StreamExt};
 use futures_util::{future, FutureExt, TryFutureExt};
 use hyper::upgrade::OnUpgrade;
-use salvo_core::http::header::{SEC_WEBSOCKET_VERSION, UPGRADE};
+use salvo_core::http::header::{SEC_WEBSOCKET_EXTENSIONS, SEC_WEBSOCKET_VERSION, UPGRADE};
 use salvo_core::http::headers::{Connection, HeaderMapExt, SecWebsocketAccept, SecWebsocketKey, Upgrade};
 use salvo_core::http::{StatusCode, StatusError};
 use salvo_core::rt::tokio::TokioIo;
 use salvo_core::{Error, Request, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
 use tokio_tungstenite::{
     tungstenite::protocol::{self, WebSocketConfig},
     WebSocketStream,
 };
 
+mod deflate;
+mod hub;
+use deflate::{DeflateParams, DeflateStream};
+pub use hub::{ClientId, Hub, HubClient, Overflow};
+
 /// Creates a WebSocket Handler.
 /// Request:
 /// - Method must be `GET`
@@ -39,9 +48,18 @@ use tokio_tungstenite::{
 /// - Header `connection: upgrade`
 /// - Header `upgrade: websocket`
 /// - Header `sec-websocket-accept` with the hash value of the received key.
+///
+/// - Header `sec-websocket-extensions: permessage-deflate` if it is [enabled](WebSocketUpgrade::permessage_deflate)
+///   and offered by the client.
+///
+/// Other extensions requested by `sec-websocket-extensions` header are not accepted, so clients fall back to
+/// uncompressed messages as required by RFC 6455.
 #[allow(missing_debug_implementations)]
 pub struct WebSocketUpgrade {
     config: Option<WebSocketConfig>,
+    heartbeat: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    permessage_deflate: bool,
 }
 
 impl Default for WebSocketUpgrade {
@@ -55,13 +73,23 @@ impl WebSocketUpgrade {
     /// Create new `WebSocketUpgrade`.
     #[inline]
     pub fn new() -> Self {
-        WebSocketUpgrade { config: None }
+        WebSocketUpgrade {
+            config: None,
+            heartbeat: None,
+            idle_timeout: None,
+            permessage_deflate: false,
+        }
     }
 
     /// Create new `WebSocketUpgrade` with config.
     #[inline]
     pub fn with_config(config: WebSocketConfig) -> Self {
-        WebSocketUpgrade { config: Some(config) }
+        WebSocketUpgrade {
+            config: Some(config),
+            heartbeat: None,
+            idle_timeout: None,
+            permessage_deflate: false,
+        }
     }
 
     /// The target minimum size of the write buffer to reach before writing the data
@@ -124,6 +152,37 @@ impl WebSocketUpgrade {
         self
     }
 
+    /// Sends a Ping message every `interval`, so connections kept idle by NATs and proxies stay open and
+    /// clients reply with Pong messages. Disabled by default.
+    ///
+    /// Pings are sent while the [`WebSocket`] is polled for messages.
+    #[inline]
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = Some(interval);
+        self
+    }
+
+    /// Yields an error from the [`WebSocket`] if no message, including Pong messages, is received from the
+    /// client within `timeout`, so dead connections can be dropped. Disabled by default.
+    ///
+    /// It should be longer than the [`heartbeat`](Self::heartbeat) interval, so live clients have time to reply.
+    #[inline]
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Accepts the `permessage-deflate` extension of RFC 7692 if the client offers it, so messages are
+    /// compressed in both directions. Disabled by default.
+    ///
+    /// Offers limiting the window of the server with `server_max_window_bits` are declined, and the context
+    /// takeover parameters offered by the client are accepted. Compressed messages are limited by
+    /// [`max_message_size`](Self::max_message_size) once decompressed.
+    #[inline]
+    pub fn permessage_deflate(mut self, enabled: bool) -> Self {
+        self.permessage_deflate = enabled;
+        self
+    }
 
     /// Upgrade websocket request.
     pub async fn upgrade<F, Fut>(&self, req: &mut Request, res: &mut Response, callback: F) -> Result<(), StatusError>
@@ -170,17 +229,27 @@ impl WebSocketUpgrade {
         res.headers_mut().typed_insert(Connection::upgrade());
         res.headers_mut().typed_insert(Upgrade::websocket());
         res.headers_mut().typed_insert(SecWebsocketAccept::from(sec_ws_key));
+        let deflate = if self.permessage_deflate {
+            DeflateParams::negotiate(req.headers().get_all(SEC_WEBSOCKET_EXTENSIONS))
+        } else {
+            None
+        };
+        if let Some(deflate) = &deflate {
+            res.headers_mut().insert(SEC_WEBSOCKET_EXTENSIONS, deflate.response());
+        }
 
         if let Some(on_upgrade) = req.extensions_mut().remove::<OnUpgrade>() {
             let config = self.config;
+            let (heartbeat, idle_timeout) = (self.heartbeat, self.idle_timeout);
             tokio::spawn(async move {
                 let socket = on_upgrade
                     .and_then(move |upgraded| {
                         tracing::debug!("websocket upgrade complete");
-                        WebSocket::from_raw_socket(upgraded, protocol::Role::Server, config).map(Ok)
+                        WebSocket::from_raw_socket(upgraded, protocol::Role::Server, config, deflate).map(Ok)
                     })
                     .await
-                    .expect("connection upgrade failed");
+                    .expect("connection upgrade failed")
+                    .keepalive(heartbeat, idle_timeout);
                 callback(socket).await;
             });
             Ok(())
@@ -197,7 +266,10 @@ impl WebSocketUpgrade {
 /// Close messages need to be handled explicitly: usually by closing the `Sink` end of the
 /// `WebSocket`.
 pub struct WebSocket {
-    inner: WebSocketStream<TokioIo<hyper::upgrade::Upgraded>>,
+    inner: WebSocketStream<DeflateStream<TokioIo<hyper::upgrade::Upgraded>>>,
+    heartbeat: Option<Interval>,
+    ping_pending: bool,
+    idle_timeout: Option<(Duration, Pin<Box<Sleep>>)>,
 }
 
 impl WebSocket {
@@ -206,12 +278,60 @@ impl WebSocket {
         upgraded: hyper::upgrade::Upgraded,
         role: protocol::Role,
         config: Option<protocol::WebSocketConfig>,
+        deflate: Option<DeflateParams>,
     ) -> Self {
-        WebSocketStream::from_raw_socket(TokioIo::new(upgraded), role, config)
-            .map(|inner| WebSocket { inner })
+        let stream = DeflateStream::new(TokioIo::new(upgraded), deflate, config);
+        WebSocketStream::from_raw_socket(stream, role, config)
+            .map(|inner| WebSocket {
+                inner,
+                heartbeat: None,
+                ping_pending: false,
+                idle_timeout: None,
+            })
             .await
     }
 
+    fn keepalive(mut self, heartbeat: Option<Duration>, idle_timeout: Option<Duration>) -> Self {
+        self.heartbeat = heartbeat.map(|period| {
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        self.idle_timeout = idle_timeout.map(|timeout| (timeout, Box::pin(tokio::time::sleep(timeout))));
+        self
+    }
+
+    fn poll_keepalive(&mut self, cx: &mut Context) -> Poll<Error> {
+        if let Some(heartbeat) = &mut self.heartbeat {
+            if heartbeat.poll_tick(cx).is_ready() {
+                self.ping_pending = true;
+            }
+        }
+        if self.ping_pending {
+            match Pin::new(&mut self.inner).poll_ready(cx) {
+                Poll::Ready(Ok(())) => {
+                    self.ping_pending = false;
+                    if let Err(e) = Pin::new(&mut self.inner).start_send(protocol::Message::Ping(vec![])) {
+                        return Poll::Ready(Error::other(e));
+                    }
+                    if let Poll::Ready(Err(e)) = Pin::new(&mut self.inner).poll_flush(cx) {
+                        return Poll::Ready(Error::other(e));
+                    }
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Error::other(e)),
+                Poll::Pending => {}
+            }
+        }
+        if let Some((_, sleep)) = &mut self.idle_timeout {
+            if sleep.as_mut().poll(cx).is_ready() {
+                tracing::debug!("websocket idle timeout");
+                self.idle_timeout = None;
+                return Poll::Ready(Error::other("websocket idle timeout"));
+            }
+        }
+        Poll::Pending
+    }
+
     /// Receive another message.
     ///
     /// Returns `None` if the stream has closed.
@@ -229,6 +349,12 @@ impl WebSocket {
     pub async fn close(mut self) -> Result<(), Error> {
         future::poll_fn(|cx| Pin::new(&mut self).poll_close(cx)).await
     }
+
+    /// Splits this websocket into a sink and a stream, so messages can be sent and received in different tasks.
+    #[inline]
+    pub fn split(self) -> (SplitSink<WebSocket, Message>, SplitStream<WebSocket>) {
+        StreamExt::split(self)
+    }
 }
 
 impl Stream for WebSocket {
@@ -236,8 +362,16 @@ impl Stream for WebSocket {
 
     #[inline]
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if let Poll::Ready(e) = self.poll_keepalive(cx) {
+            return Poll::Ready(Some(Err(e)));
+        }
         match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
-            Some(Ok(item)) => Poll::Ready(Some(Ok(Message { inner: item }))),
+            Some(Ok(item)) => {
+                if let Some((timeout, sleep)) = &mut self.idle_timeout {
+                    sleep.as_mut().reset(Instant::now() + *timeout);
+                }
+                Poll::Ready(Some(Ok(Message { inner: item })))
+            }
             Some(Err(e)) => {
                 tracing::debug!("websocket poll error: {}", e);
                 Poll::Ready(Some(Err(Error::other(e))))
@@ -342,6 +476,12 @@ impl Message {
         }
     }
 
+    /// Construct a new Text `Message` containing `value` serialized as JSON.
+    #[inline]
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Result<Message, Error> {
+        serde_json::to_string(value).map(Message::text).map_err(Error::other)
+    }
+
     /// Returns true if this message is a Text message.
     #[inline]
     pub fn is_text(&self) -> bool {
@@ -391,6 +531,17 @@ impl Message {
         }
     }
 
+    /// Deserializes the data of a Text or Binary message as JSON.
+    #[inline]
+    pub fn parse_json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        match self.inner {
+            protocol::Message::Text(_) | protocol::Message::Binary(_) => {
+                serde_json::from_slice(self.as_bytes()).map_err(Error::other)
+            }
+            _ => Err(Error::Other("not a text or binary message".into())),
+        }
+    }
+
     /// Returns the bytes of this message, if the message can contain data.
     #[inline]
     pub fn as_bytes(&self) -> &[u8] {
@@ -487,4 +638,73 @@ mod tests {
 
         assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
     }
+
+    #[tokio::test]
+    async fn test_websocket_heartbeat() {
+        #[handler]
+        async fn connect(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+            WebSocketUpgrade::new()
+                .heartbeat(Duration::from_millis(50))
+                .idle_timeout(Duration::from_millis(200))
+                .upgrade(req, res, |mut ws| async move {
+                    while let Some(Ok(_)) = ws.recv().await {}
+                })
+                .await
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(async move {
+            Server::new(acceptor).serve(Router::new().goal(connect)).await;
+        });
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await.unwrap();
+        tokio::task::spawn(conn.with_upgrades());
+        let req = hyper::Request::builder()
+            .uri(format!("http://{}", addr))
+            .header(UPGRADE, "websocket")
+            .header(CONNECTION, "Upgrade")
+            .header(SEC_WEBSOCKET_KEY, "6D69KGBOr4Re+Nj6zx9aQA==")
+            .header(SEC_WEBSOCKET_VERSION, "13")
+            .body(http_body_util::Empty::<hyper::body::Bytes>::new())
+            .unwrap();
+        let res = sender.send_request(req).await.unwrap();
+        let upgraded = hyper::upgrade::on(res).await.unwrap();
+        let mut client = WebSocketStream::from_raw_socket(TokioIo::new(upgraded), protocol::Role::Client, None).await;
+
+        // Pongs are sent by the client while it is reading, so the connection is kept alive.
+        for _ in 0..6 {
+            let msg = client.next().await.unwrap().unwrap();
+            assert!(msg.is_ping());
+        }
+
+        // The server drops the connection once the client stops replying.
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let closed = tokio::time::timeout(Duration::from_secs(2), async {
+            while let Some(Ok(_)) = client.next().await {}
+        })
+        .await;
+        assert!(closed.is_ok());
+    }
+
+    #[test]
+    fn test_json_message() {
+        #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Chat {
+            user: String,
+            text: String,
+        }
+
+        let chat = Chat {
+            user: "salvo".into(),
+            text: "hello".into(),
+        };
+        let msg = Message::json(&chat).unwrap();
+        assert!(msg.is_text());
+        assert_eq!(msg.parse_json::<Chat>().unwrap(), chat);
+        assert_eq!(Message::binary(msg.as_bytes()).parse_json::<Chat>().unwrap(), chat);
+        assert!(Message::ping(vec![]).parse_json::<Chat>().is_err());
+        assert!(Message::text("not json").parse_json::<Chat>().is_err());
+    }
 }
@@ -0,0 +1,547 @@
+//! The `permessage-deflate` extension of [RFC 7692](https://www.rfc-editor.org/rfc/rfc7692).
+//!
+//! `tokio-tungstenite` does not support extensions, so messages are compressed and decompressed by
+//! [`DeflateStream`], which sits between the upgraded connection and the WebSocket protocol: frames of compressed
+//! messages read from the client are inflated into plain frames, and data frames written by the protocol are
+//! deflated into frames with the `RSV1` bit set.
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use salvo_core::http::HeaderValue;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+
+const EXTENSION: &str = "permessage-deflate";
+const SERVER_NO_CONTEXT_TAKEOVER: &str = "server_no_context_takeover";
+const CLIENT_NO_CONTEXT_TAKEOVER: &str = "client_no_context_takeover";
+const SERVER_MAX_WINDOW_BITS: &str = "server_max_window_bits";
+const CLIENT_MAX_WINDOW_BITS: &str = "client_max_window_bits";
+/// Trailer removed from and appended to the payload of compressed messages.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+/// Maximum payload size of the frames of inflated messages.
+const MAX_INFLATED_FRAME: usize = 64 * 1024;
+
+const OP_CONTINUE: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+
+/// Negotiated parameters of `permessage-deflate`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct DeflateParams {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+}
+
+impl DeflateParams {
+    /// Accepts the first acceptable offer of `permessage-deflate` in the `sec-websocket-extensions` headers.
+    ///
+    /// Offers limiting the window of the server with `server_max_window_bits` below 15 are declined, since the
+    /// window of the compressor can't be changed.
+    pub(crate) fn negotiate<'a>(headers: impl IntoIterator<Item = &'a HeaderValue>) -> Option<Self> {
+        headers
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(Self::accept)
+    }
+
+    fn accept(offer: &str) -> Option<Self> {
+        let mut parts = offer.split(';').map(str::trim);
+        if !parts.next()?.eq_ignore_ascii_case(EXTENSION) {
+            return None;
+        }
+        let mut params = DeflateParams::default();
+        let mut seen = Vec::new();
+        for part in parts {
+            let (name, value) = match part.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (part, None),
+            };
+            let name = name.to_ascii_lowercase();
+            if seen.contains(&name) {
+                return None;
+            }
+            match (name.as_str(), value) {
+                (SERVER_NO_CONTEXT_TAKEOVER, None) => params.server_no_context_takeover = true,
+                (CLIENT_NO_CONTEXT_TAKEOVER, None) => params.client_no_context_takeover = true,
+                (SERVER_MAX_WINDOW_BITS, Some("15")) => {}
+                // The inflater uses the largest window, so any window of the client is fine.
+                (CLIENT_MAX_WINDOW_BITS, None) => {}
+                (CLIENT_MAX_WINDOW_BITS, Some(bits)) if matches!(bits.parse::<u8>(), Ok(8..=15)) => {}
+                _ => return None,
+            }
+            seen.push(name);
+        }
+        Some(params)
+    }
+
+    /// Value of the `sec-websocket-extensions` response header.
+    pub(crate) fn response(&self) -> HeaderValue {
+        let mut value = EXTENSION.to_owned();
+        if self.server_no_context_takeover {
+            value.push_str("; ");
+            value.push_str(SERVER_NO_CONTEXT_TAKEOVER);
+        }
+        if self.client_no_context_takeover {
+            value.push_str("; ");
+            value.push_str(CLIENT_NO_CONTEXT_TAKEOVER);
+        }
+        HeaderValue::from_str(&value).expect("extension should be a valid header value")
+    }
+}
+
+/// Header of a WebSocket frame.
+#[derive(Clone, Copy, Debug)]
+struct FrameHeader {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    mask: Option<[u8; 4]>,
+    /// Size of the header in bytes.
+    size: usize,
+    payload_len: usize,
+}
+
+impl FrameHeader {
+    /// Parses the header of the frame at the start of `buf`, returns `None` if the frame is incomplete.
+    fn parse(buf: &[u8], max_frame_size: Option<usize>) -> IoResult<Option<Self>> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let (first, second) = (buf[0], buf[1]);
+        let (payload_len, mut size) = match second & 0x7f {
+            126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4),
+            127 if buf.len() >= 10 => (
+                u64::from_be_bytes(buf[2..10].try_into().expect("slice should be 8 bytes")),
+                10,
+            ),
+            126 | 127 => return Ok(None),
+            len => (len as u64, 2),
+        };
+        let payload_len = usize::try_from(payload_len).map_err(|_| invalid_data("frame is too large"))?;
+        if max_frame_size.is_some_and(|max| payload_len > max) {
+            return Err(invalid_data("frame is too large"));
+        }
+        let mask = if second & 0x80 != 0 {
+            if buf.len() < size + 4 {
+                return Ok(None);
+            }
+            let mask = buf[size..size + 4].try_into().expect("slice should be 4 bytes");
+            size += 4;
+            Some(mask)
+        } else {
+            None
+        };
+        if buf.len() < size + payload_len {
+            return Ok(None);
+        }
+        Ok(Some(FrameHeader {
+            fin: first & 0x80 != 0,
+            rsv1: first & 0x40 != 0,
+            opcode: first & 0x0f,
+            mask,
+            size,
+            payload_len,
+        }))
+    }
+
+    fn is_data(&self) -> bool {
+        matches!(self.opcode, OP_TEXT | OP_BINARY)
+    }
+
+    fn is_control(&self) -> bool {
+        self.opcode & 0x08 != 0
+    }
+
+    /// Returns the unmasked payload of the frame starting at the start of `buf`.
+    fn payload<'a>(&self, buf: &'a [u8]) -> impl Iterator<Item = u8> + 'a {
+        let mask = self.mask.unwrap_or_default();
+        buf[self.size..self.size + self.payload_len]
+            .iter()
+            .enumerate()
+            .map(move |(i, byte)| byte ^ mask[i % 4])
+    }
+}
+
+/// Writes a frame. Masked frames are written with a zero mask, so the payload is not changed.
+fn write_frame(out: &mut Vec<u8>, fin: bool, rsv1: bool, opcode: u8, masked: bool, payload: &[u8]) {
+    out.push(((fin as u8) << 7) | ((rsv1 as u8) << 6) | opcode);
+    let mask_bit = (masked as u8) << 7;
+    match payload.len() {
+        len @ 0..=125 => out.push(mask_bit | len as u8),
+        len @ 126..=0xffff => {
+            out.push(mask_bit | 126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(mask_bit | 127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    if masked {
+        out.extend_from_slice(&[0; 4]);
+    }
+    out.extend_from_slice(payload);
+}
+
+fn invalid_data(msg: &'static str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg)
+}
+
+/// Compressed message being read or written.
+#[derive(Debug)]
+struct Pending {
+    opcode: u8,
+    masked: bool,
+    payload: Vec<u8>,
+}
+
+struct Codec {
+    params: DeflateParams,
+    max_message_size: Option<usize>,
+    max_frame_size: Option<usize>,
+    inflater: Decompress,
+    deflater: Compress,
+    read_in: Vec<u8>,
+    read_out: Vec<u8>,
+    read_pos: usize,
+    reading: Option<Pending>,
+    write_in: Vec<u8>,
+    write_out: Vec<u8>,
+    writing: Option<Pending>,
+}
+
+impl Codec {
+    /// Converts the complete frames read from the client, frames of compressed messages are inflated.
+    fn decode(&mut self) -> IoResult<()> {
+        let mut offset = 0;
+        while let Some(header) = FrameHeader::parse(&self.read_in[offset..], self.max_frame_size)? {
+            let frame = &self.read_in[offset..offset + header.size + header.payload_len];
+            offset += frame.len();
+            let compressed = match (&self.reading, header.opcode) {
+                (None, _) if header.is_data() && header.rsv1 => true,
+                (Some(_), OP_CONTINUE) if !header.rsv1 => true,
+                (Some(_), _) if header.is_data() => return Err(invalid_data("expected a continuation frame")),
+                _ => false,
+            };
+            if !compressed {
+                // Control frames and uncompressed messages are passed through.
+                self.read_out.extend_from_slice(frame);
+                continue;
+            }
+            let reading = self.reading.get_or_insert_with(|| Pending {
+                opcode: header.opcode,
+                masked: header.mask.is_some(),
+                payload: Vec::new(),
+            });
+            reading.payload.extend(header.payload(frame));
+            if self.max_message_size.is_some_and(|max| reading.payload.len() > max) {
+                return Err(invalid_data("message is too large"));
+            }
+            if header.fin {
+                let Pending {
+                    opcode,
+                    masked,
+                    mut payload,
+                } = self.reading.take().expect("message should be read");
+                payload.extend_from_slice(&TRAILER);
+                let message = self.inflate(&payload)?;
+                let chunk_size = self.max_frame_size.unwrap_or(usize::MAX).clamp(1, MAX_INFLATED_FRAME);
+                let mut chunks = message.chunks(chunk_size).peekable();
+                if chunks.peek().is_none() {
+                    write_frame(&mut self.read_out, true, false, opcode, masked, &[]);
+                }
+                let mut opcode = opcode;
+                while let Some(chunk) = chunks.next() {
+                    write_frame(
+                        &mut self.read_out,
+                        chunks.peek().is_none(),
+                        false,
+                        opcode,
+                        masked,
+                        chunk,
+                    );
+                    opcode = OP_CONTINUE;
+                }
+            }
+        }
+        self.read_in.drain(..offset);
+        Ok(())
+    }
+
+    fn inflate(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+        let mut output = Vec::with_capacity(input.len() * 2);
+        let mut consumed = 0;
+        loop {
+            if output.len() == output.capacity() {
+                output.reserve(output.capacity().max(1024));
+            }
+            let (total_in, total_out) = (self.inflater.total_in(), self.inflater.total_out());
+            let status = self
+                .inflater
+                .decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+            consumed += (self.inflater.total_in() - total_in) as usize;
+            if self.max_message_size.is_some_and(|max| output.len() > max) {
+                return Err(invalid_data("message is too large"));
+            }
+            let progressed = self.inflater.total_in() != total_in || self.inflater.total_out() != total_out;
+            if status == Status::StreamEnd {
+                // The client ended the stream with a final block, so the next message starts a new one.
+                self.inflater.reset(false);
+                return Ok(output);
+            }
+            if consumed == input.len() && output.len() < output.capacity() {
+                break;
+            }
+            if !progressed && output.len() < output.capacity() {
+                return Err(invalid_data("compressed message is truncated"));
+            }
+        }
+        if self.params.client_no_context_takeover {
+            self.inflater.reset(false);
+        }
+        Ok(output)
+    }
+
+    /// Converts the complete frames written by the protocol, data messages are deflated.
+    fn encode(&mut self) -> IoResult<()> {
+        let mut offset = 0;
+        while let Some(header) = FrameHeader::parse(&self.write_in[offset..], None)? {
+            let frame = &self.write_in[offset..offset + header.size + header.payload_len];
+            offset += frame.len();
+            if header.is_control() || (self.writing.is_none() && !header.is_data()) {
+                self.write_out.extend_from_slice(frame);
+                continue;
+            }
+            let writing = self.writing.get_or_insert_with(|| Pending {
+                opcode: header.opcode,
+                masked: header.mask.is_some(),
+                payload: Vec::new(),
+            });
+            writing.payload.extend(header.payload(frame));
+            if header.fin {
+                let Pending {
+                    opcode,
+                    masked,
+                    payload,
+                } = self.writing.take().expect("message should be written");
+                let mut message = self.deflate(&payload)?;
+                if message.ends_with(&TRAILER) {
+                    message.truncate(message.len() - TRAILER.len());
+                }
+                write_frame(&mut self.write_out, true, true, opcode, masked, &message);
+            }
+        }
+        self.write_in.drain(..offset);
+        Ok(())
+    }
+
+    fn deflate(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+        let mut output = Vec::with_capacity(input.len() / 2 + 64);
+        let mut consumed = 0;
+        loop {
+            if output.len() == output.capacity() {
+                output.reserve(output.capacity().max(1024));
+            }
+            let total_in = self.deflater.total_in();
+            self.deflater
+                .compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+                .map_err(IoError::other)?;
+            consumed += (self.deflater.total_in() - total_in) as usize;
+            if consumed == input.len() && output.len() < output.capacity() {
+                break;
+            }
+        }
+        if self.params.server_no_context_takeover {
+            self.deflater.reset();
+        }
+        Ok(output)
+    }
+}
+
+/// Stream between the upgraded connection and the WebSocket protocol, applying `permessage-deflate` if it is
+/// negotiated.
+pub(crate) struct DeflateStream<S> {
+    inner: S,
+    codec: Option<Box<Codec>>,
+}
+
+impl<S> DeflateStream<S> {
+    pub(crate) fn new(inner: S, params: Option<DeflateParams>, config: Option<WebSocketConfig>) -> Self {
+        let config = config.unwrap_or_default();
+        let codec = params.map(|params| {
+            Box::new(Codec {
+                params,
+                max_message_size: config.max_message_size,
+                max_frame_size: config.max_frame_size,
+                inflater: Decompress::new(false),
+                deflater: Compress::new(Compression::default(), false),
+                read_in: Vec::new(),
+                read_out: Vec::new(),
+                read_pos: 0,
+                reading: None,
+                write_in: Vec::new(),
+                write_out: Vec::new(),
+                writing: None,
+            })
+        });
+        DeflateStream { inner, codec }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> DeflateStream<S> {
+    /// Writes the deflated frames to the connection.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        if let Some(codec) = &mut self.codec {
+            while !codec.write_out.is_empty() {
+                let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &codec.write_out))?;
+                if n == 0 {
+                    return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+                }
+                codec.write_out.drain(..n);
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        let Some(codec) = &mut this.codec else {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        };
+        loop {
+            if codec.read_pos < codec.read_out.len() {
+                let len = buf.remaining().min(codec.read_out.len() - codec.read_pos);
+                buf.put_slice(&codec.read_out[codec.read_pos..codec.read_pos + len]);
+                codec.read_pos += len;
+                return Poll::Ready(Ok(()));
+            }
+            codec.read_out.clear();
+            codec.read_pos = 0;
+            codec.decode()?;
+            if !codec.read_out.is_empty() {
+                continue;
+            }
+            let mut data = [0; 8 * 1024];
+            let mut chunk = ReadBuf::new(&mut data);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk))?;
+            if chunk.filled().is_empty() {
+                // Incomplete frames are left to the protocol to report.
+                codec.read_out.append(&mut codec.read_in);
+                if codec.read_out.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                continue;
+            }
+            codec.read_in.extend_from_slice(chunk.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        if this.codec.is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        ready!(this.poll_drain(cx))?;
+        let codec = this.codec.as_mut().expect("codec should be set");
+        codec.write_in.extend_from_slice(buf);
+        codec.encode()?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_tungstenite::tungstenite::protocol::{Message, Role};
+    use tokio_tungstenite::WebSocketStream;
+
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let offer = |value: &'static str| DeflateParams::negotiate([&HeaderValue::from_static(value)]);
+        assert_eq!(
+            offer("permessage-deflate; client_max_window_bits").unwrap().response(),
+            "permessage-deflate"
+        );
+        assert_eq!(
+            offer("permessage-deflate; server_no_context_takeover; client_no_context_takeover")
+                .unwrap()
+                .response(),
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover"
+        );
+        assert_eq!(
+            offer("permessage-deflate; server_max_window_bits=10, permessage-deflate; client_max_window_bits=10")
+                .unwrap()
+                .response(),
+            "permessage-deflate"
+        );
+        assert!(offer("permessage-deflate; server_max_window_bits=10").is_none());
+        assert!(offer("permessage-deflate; server_no_context_takeover; server_no_context_takeover").is_none());
+        assert!(offer("x-webkit-deflate-frame").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deflate_stream() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let stream = DeflateStream::new(server, Some(DeflateParams::default()), None);
+        let mut server = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+
+        // A compressed message of the client, in two fragments.
+        let mut deflater = Compress::new(Compression::default(), false);
+        let mut payload = Vec::with_capacity(128);
+        deflater
+            .compress_vec(b"hello hello hello", &mut payload, FlushCompress::Sync)
+            .unwrap();
+        assert!(payload.ends_with(&TRAILER));
+        payload.truncate(payload.len() - TRAILER.len());
+        let (first, second) = payload.split_at(payload.len() / 2);
+        let mut frames = Vec::new();
+        write_frame(&mut frames, false, true, OP_TEXT, true, first);
+        write_frame(&mut frames, true, false, OP_CONTINUE, true, second);
+        client.write_all(&frames).await.unwrap();
+        assert_eq!(
+            server.next().await.unwrap().unwrap(),
+            Message::text("hello hello hello")
+        );
+
+        server.send(Message::binary(vec![7; 1000])).await.unwrap();
+        let mut frame = vec![0; 2];
+        client.read_exact(&mut frame).await.unwrap();
+        let len = (frame[1] & 0x7f) as usize;
+        assert!(len < 126);
+        frame.resize(2 + len, 0);
+        client.read_exact(&mut frame[2..]).await.unwrap();
+        let header = FrameHeader::parse(&frame, None).unwrap().unwrap();
+        assert!(header.fin && header.rsv1);
+        assert_eq!(header.opcode, OP_BINARY);
+        let mut payload: Vec<u8> = header.payload(&frame).collect();
+        payload.extend_from_slice(&TRAILER);
+        let mut message = Vec::with_capacity(2000);
+        Decompress::new(false)
+            .decompress_vec(&payload, &mut message, FlushDecompress::Sync)
+            .unwrap();
+        assert_eq!(message, vec![7; 1000]);
+    }
+}
@@ -0,0 +1,395 @@
+//! A registry of connected websocket clients, grouped by rooms.
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use futures_util::sink::SinkExt;
+use futures_util::stream::{SplitStream, StreamExt};
+use salvo_core::Error;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Sender};
+
+use super::{Message, WebSocket};
+
+/// Id of a client registered in a [`Hub`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ClientId(u64);
+impl Display for ClientId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// What a [`Hub`] does when the buffer of a client is full, because it reads messages slower than they are sent.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Overflow {
+    /// The client is disconnected, after the buffered messages are written.
+    #[default]
+    Disconnect,
+    /// The message is dropped, and the client stays connected.
+    DropMessage,
+}
+
+#[derive(Default)]
+struct HubInner {
+    next_id: AtomicU64,
+    clients: RwLock<HashMap<ClientId, Sender<Message>>>,
+    rooms: RwLock<HashMap<String, HashSet<ClientId>>>,
+}
+
+/// A registry of connected websocket clients, grouped by rooms.
+///
+/// Each [registered](Hub::register) websocket gets a [`ClientId`] and a task writing the messages sent to it,
+/// so messages can be sent to a client, a room or all clients from any task. Clients are removed from the hub
+/// and all their rooms when their [`HubClient`] is dropped, or when writing to them fails.
+///
+/// At most [`buffer_size`](Hub::buffer_size) messages are buffered for each client, messages sent to a client with
+/// a full buffer are handled by the [`overflow`](Hub::overflow) policy, so a slow client can not exhaust memory.
+///
+/// Cloned hubs share the same clients and rooms, and have the settings of the hub when it is cloned.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_extra::websocket::{Hub, WebSocketUpgrade};
+///
+/// struct Chat {
+///     hub: Hub,
+/// }
+///
+/// #[handler]
+/// impl Chat {
+///     async fn handle(&self, req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+///         let hub = self.hub.clone();
+///         WebSocketUpgrade::new()
+///             .upgrade(req, res, |ws| async move {
+///                 let mut client = hub.register(ws);
+///                 hub.join(client.id(), "lobby");
+///                 while let Some(Ok(msg)) = client.recv().await {
+///                     if msg.is_text() {
+///                         hub.broadcast_to("lobby", msg);
+///                     }
+///                 }
+///             })
+///             .await
+///     }
+/// }
+///
+/// let router = Router::with_path("chat").goal(Chat { hub: Hub::new() });
+/// ```
+#[derive(Clone)]
+pub struct Hub {
+    inner: Arc<HubInner>,
+    buffer_size: usize,
+    overflow: Overflow,
+}
+impl Default for Hub {
+    fn default() -> Self {
+        Hub {
+            inner: Default::default(),
+            buffer_size: 64,
+            overflow: Overflow::default(),
+        }
+    }
+}
+impl fmt::Debug for Hub {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hub")
+            .field("clients", &self.client_count())
+            .field("rooms", &self.rooms().len())
+            .finish()
+    }
+}
+
+impl Hub {
+    /// Create a new empty `Hub`.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the number of messages buffered for each client, defaults to `64`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is `0`.
+    #[inline]
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "buffer size should be greater than 0");
+        self.buffer_size = size;
+        self
+    }
+
+    /// Sets what is done when the buffer of a client is full, defaults to [`Overflow::Disconnect`].
+    #[inline]
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Registers a websocket, spawns a task writing the messages sent to it, and returns the receiving half.
+    pub fn register(&self, ws: WebSocket) -> HubClient {
+        let (mut sink, stream) = ws.split();
+        let (tx, mut rx) = mpsc::channel(self.buffer_size);
+        let id = self.insert(tx);
+        let hub = self.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let Err(e) = sink.send(msg).await {
+                    tracing::debug!(client = %id, error = ?e, "websocket send failed");
+                    break;
+                }
+            }
+            hub.disconnect(id);
+            let _ = sink.close().await;
+        });
+        HubClient {
+            id,
+            hub: self.clone(),
+            stream,
+        }
+    }
+
+    fn insert(&self, tx: Sender<Message>) -> ClientId {
+        let id = ClientId(self.inner.next_id.fetch_add(1, Ordering::Relaxed));
+        self.inner
+            .clients
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, tx);
+        id
+    }
+
+    /// Removes a client from the hub and all its rooms, its websocket is closed after pending messages are written.
+    pub fn disconnect(&self, id: ClientId) {
+        self.inner
+            .clients
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+        let mut rooms = self.inner.rooms.write().unwrap_or_else(|e| e.into_inner());
+        rooms.retain(|_, members| {
+            members.remove(&id);
+            !members.is_empty()
+        });
+    }
+
+    /// Adds a client to a room, rooms are created on demand.
+    ///
+    /// Returns `false` if the client is not connected.
+    pub fn join(&self, id: ClientId, room: impl Into<String>) -> bool {
+        if !self.is_connected(id) {
+            return false;
+        }
+        let mut rooms = self.inner.rooms.write().unwrap_or_else(|e| e.into_inner());
+        rooms.entry(room.into()).or_default().insert(id);
+        true
+    }
+
+    /// Removes a client from a room, empty rooms are removed.
+    pub fn leave(&self, id: ClientId, room: &str) {
+        let mut rooms = self.inner.rooms.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(members) = rooms.get_mut(room) {
+            members.remove(&id);
+            if members.is_empty() {
+                rooms.remove(room);
+            }
+        }
+    }
+
+    /// Sends a message to a client, without waiting for it to be written.
+    ///
+    /// Returns `false` if the client is not connected, or if its buffer is full and the message is dropped.
+    pub fn send(&self, id: ClientId, msg: Message) -> bool {
+        let result = match self.inner.clients.read().unwrap_or_else(|e| e.into_inner()).get(&id) {
+            Some(tx) => tx.try_send(msg),
+            None => return false,
+        };
+        match result {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) if self.overflow == Overflow::DropMessage => {
+                tracing::debug!(client = %id, "websocket buffer is full, message dropped");
+                false
+            }
+            Err(TrySendError::Full(_)) => {
+                tracing::debug!(client = %id, "websocket buffer is full, client disconnected");
+                self.disconnect(id);
+                false
+            }
+            Err(TrySendError::Closed(_)) => {
+                self.disconnect(id);
+                false
+            }
+        }
+    }
+
+    /// Sends a message to all connected clients.
+    pub fn broadcast(&self, msg: Message) {
+        let ids = self.client_ids();
+        self.send_all(ids, msg, None);
+    }
+
+    /// Sends a message to all clients in a room.
+    pub fn broadcast_to(&self, room: &str, msg: Message) {
+        self.send_all(self.members(room), msg, None);
+    }
+
+    /// Sends a message to all clients in a room except `sender`.
+    pub fn broadcast_to_others(&self, room: &str, sender: ClientId, msg: Message) {
+        self.send_all(self.members(room), msg, Some(sender));
+    }
+
+    fn send_all(&self, ids: Vec<ClientId>, msg: Message, except: Option<ClientId>) {
+        for id in ids {
+            if Some(id) != except {
+                self.send(id, msg.clone());
+            }
+        }
+    }
+
+    /// Returns `true` if the client is connected.
+    pub fn is_connected(&self, id: ClientId) -> bool {
+        self.inner
+            .clients
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(&id)
+    }
+
+    /// Returns the number of connected clients.
+    pub fn client_count(&self) -> usize {
+        self.inner.clients.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns the ids of all connected clients.
+    pub fn client_ids(&self) -> Vec<ClientId> {
+        let clients = self.inner.clients.read().unwrap_or_else(|e| e.into_inner());
+        clients.keys().copied().collect()
+    }
+
+    /// Returns the names of all rooms with at least one client.
+    pub fn rooms(&self) -> Vec<String> {
+        let rooms = self.inner.rooms.read().unwrap_or_else(|e| e.into_inner());
+        rooms.keys().cloned().collect()
+    }
+
+    /// Returns the ids of the clients in a room.
+    pub fn members(&self, room: &str) -> Vec<ClientId> {
+        let rooms = self.inner.rooms.read().unwrap_or_else(|e| e.into_inner());
+        rooms
+            .get(room)
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// The receiving half of a websocket [registered](Hub::register) in a [`Hub`].
+///
+/// The client is disconnected from the hub when it is dropped.
+pub struct HubClient {
+    id: ClientId,
+    hub: Hub,
+    stream: SplitStream<WebSocket>,
+}
+impl fmt::Debug for HubClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HubClient").field("id", &self.id).finish()
+    }
+}
+
+impl HubClient {
+    /// Returns the id of this client.
+    #[inline]
+    pub fn id(&self) -> ClientId {
+        self.id
+    }
+
+    /// Returns the hub this client is registered in.
+    #[inline]
+    pub fn hub(&self) -> &Hub {
+        &self.hub
+    }
+
+    /// Receive another message.
+    ///
+    /// Returns `None` if the stream has closed.
+    pub async fn recv(&mut self) -> Option<Result<Message, Error>> {
+        self.stream.next().await
+    }
+}
+
+impl Drop for HubClient {
+    fn drop(&mut self) {
+        self.hub.disconnect(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hub() {
+        let hub = Hub::new();
+        let (tx1, mut rx1) = mpsc::channel(8);
+        let (tx2, mut rx2) = mpsc::channel(8);
+        let (tx3, rx3) = mpsc::channel(8);
+        let id1 = hub.insert(tx1);
+        let id2 = hub.insert(tx2);
+        let id3 = hub.insert(tx3);
+        assert_eq!(hub.client_count(), 3);
+
+        assert!(hub.join(id1, "room"));
+        assert!(hub.join(id2, "room"));
+        hub.broadcast_to("room", Message::text("room"));
+        hub.broadcast_to_others("room", id1, Message::text("others"));
+        assert!(hub.send(id1, Message::text("direct")));
+        hub.broadcast(Message::text("all"));
+        let texts = |rx: &mut mpsc::Receiver<Message>| {
+            let mut texts = vec![];
+            while let Ok(msg) = rx.try_recv() {
+                texts.push(msg.to_str().unwrap().to_owned());
+            }
+            texts
+        };
+        assert_eq!(texts(&mut rx1), ["room", "direct", "all"]);
+        assert_eq!(texts(&mut rx2), ["room", "others", "all"]);
+
+        drop(rx3);
+        assert!(hub.join(id3, "room"));
+        hub.broadcast_to("room", Message::text("gone"));
+        assert!(!hub.is_connected(id3));
+        assert_eq!(hub.members("room").len(), 2);
+
+        hub.leave(id2, "room");
+        hub.disconnect(id1);
+        assert_eq!(hub.client_count(), 1);
+        assert!(hub.rooms().is_empty());
+        assert!(!hub.join(id1, "room"));
+        assert!(!hub.send(id1, Message::text("closed")));
+    }
+
+    #[test]
+    fn test_hub_overflow() {
+        let hub = Hub::new().overflow(Overflow::DropMessage);
+        let (tx, mut rx) = mpsc::channel(1);
+        let id = hub.insert(tx);
+        assert!(hub.send(id, Message::text("first")));
+        assert!(!hub.send(id, Message::text("dropped")));
+        assert!(hub.is_connected(id));
+        assert_eq!(rx.try_recv().unwrap().to_str().unwrap(), "first");
+        assert!(rx.try_recv().is_err());
+
+        let hub = Hub::new();
+        let (tx, _rx) = mpsc::channel(1);
+        let id = hub.insert(tx);
+        assert!(hub.join(id, "room"));
+        assert!(hub.send(id, Message::text("first")));
+        assert!(!hub.send(id, Message::text("overflow")));
+        assert!(!hub.is_connected(id));
+        assert!(hub.rooms().is_empty());
+    }
+}
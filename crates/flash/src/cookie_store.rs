@@ -1,10 +1,13 @@
 use salvo_core::http::cookie::time::Duration;
-use salvo_core::http::cookie::{Cookie, SameSite};
+use salvo_core::http::cookie::{Cookie, Key, SameSite};
 use salvo_core::{Depot, Request, Response};
 
 use super::{Flash, FlashHandler, FlashStore};
 
 /// CookieStore is a `FlashStore` implementation that stores the flash messages in a cookie.
+///
+/// The cookie is not signed by default, use [`CookieStore::signed`] to prevent clients from forging
+/// flash messages.
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct CookieStore {
@@ -18,6 +21,8 @@ pub struct CookieStore {
     pub path: String,
     /// The cookie name.
     pub name: String,
+    /// The key used to sign the cookie.
+    pub key: Option<Key>,
 }
 impl Default for CookieStore {
     fn default() -> Self {
@@ -34,6 +39,7 @@ impl CookieStore {
             http_only: true,
             path: "/".into(),
             name: "salvo.flash".into(),
+            key: None,
         }
     }
 
@@ -67,6 +73,12 @@ impl CookieStore {
         self
     }
 
+    /// Signs the cookie with `key`, cookies with an invalid signature are ignored.
+    pub fn signed(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
     /// Into `FlashHandler`.
     pub fn into_handler(self) -> FlashHandler<CookieStore> {
         FlashHandler::new(self)
@@ -74,7 +86,11 @@ impl CookieStore {
 }
 impl FlashStore for CookieStore {
     async fn load_flash(&self, req: &mut Request, _depot: &mut Depot) -> Option<Flash> {
-        match req.cookie(&self.name) {
+        let cookie = match &self.key {
+            Some(key) => req.cookies().signed(key).get(&self.name),
+            None => req.cookie(&self.name).cloned(),
+        };
+        match cookie {
             None => None,
             Some(cookie) => match serde_json::from_str(cookie.value()) {
                 Ok(flash) => Some(flash),
@@ -86,14 +102,18 @@ impl FlashStore for CookieStore {
         }
     }
     async fn save_flash(&self, _req: &mut Request, _depot: &mut Depot, res: &mut Response, flash: Flash) {
-        res.add_cookie(
-            Cookie::build((self.name.clone(), serde_json::to_string(&flash).unwrap_or_default()))
-                .max_age(self.max_age)
-                .path(self.path.clone())
-                .same_site(self.same_site)
-                .http_only(self.http_only)
-                .build(),
-        );
+        let cookie = Cookie::build((self.name.clone(), serde_json::to_string(&flash).unwrap_or_default()))
+            .max_age(self.max_age)
+            .path(self.path.clone())
+            .same_site(self.same_site)
+            .http_only(self.http_only)
+            .build();
+        match &self.key {
+            Some(key) => res.cookies_mut().signed_mut(key).add(cookie),
+            None => {
+                res.add_cookie(cookie);
+            }
+        }
     }
     async fn clear_flash(&self, _depot: &mut Depot, res: &mut Response) {
         res.add_cookie(
@@ -203,6 +203,14 @@ pub trait FlashDepotExt {
     fn outgoing_flash(&self) -> &Flash;
     /// Get mutable outgoing flash.
     fn outgoing_flash_mut(&mut self) -> &mut Flash;
+    /// Get mutable outgoing flash, messages added are shown on the next request.
+    ///
+    /// This is a shortcut of [`outgoing_flash_mut`](FlashDepotExt::outgoing_flash_mut), for example
+    /// `depot.flash().success("Saved!")`.
+    #[inline]
+    fn flash(&mut self) -> &mut Flash {
+        self.outgoing_flash_mut()
+    }
 }
 
 impl FlashDepotExt for Depot {
@@ -345,6 +353,39 @@ mod tests {
         assert!(respone.take_string().await.unwrap().is_empty());
     }
 
+    #[cfg(feature = "cookie-store")]
+    #[tokio::test]
+    async fn test_signed_cookie_store() {
+        #[handler]
+        pub async fn save(depot: &mut Depot, res: &mut Response) {
+            depot.flash().success("Saved!");
+            res.render(Redirect::other("/get"));
+        }
+
+        let key = salvo_core::http::cookie::Key::generate();
+        let router = Router::new()
+            .hoop(CookieStore::new().signed(key).into_handler())
+            .push(Router::with_path("get").get(get_flash))
+            .push(Router::with_path("save").post(save));
+        let service = Service::new(router);
+
+        let respone = TestClient::post("http://127.0.0.1:5800/save").send(&service).await;
+        let cookie = respone.headers().get(SET_COOKIE).unwrap().to_str().unwrap().to_owned();
+
+        let mut respone = TestClient::get("http://127.0.0.1:5800/get")
+            .add_header(COOKIE, &cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.take_string().await.unwrap(), "Saved! - success\n");
+
+        let forged = r#"salvo.flash=[{"level":"Error","value":"Forged"}]"#;
+        let mut respone = TestClient::get("http://127.0.0.1:5800/get")
+            .add_header(COOKIE, forged, true)
+            .send(&service)
+            .await;
+        assert!(respone.take_string().await.unwrap().is_empty());
+    }
+
     #[cfg(feature = "session-store")]
     #[tokio::test]
     async fn test_session_store() {
@@ -5,6 +5,7 @@
 #![doc(html_logo_url = "https://salvo.rs/images/logo.svg")]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+use std::borrow::Cow;
 use std::marker::PhantomData;
 
 pub use jsonwebtoken::errors::Error as JwtError;
@@ -162,6 +163,9 @@ pub struct JwtAuth<C, D> {
     pub decoder: D,
     /// The finders list.
     pub finders: Vec<Box<dyn JwtTokenFinder>>,
+    /// The handler used to write response when auth failed, the default response is `401 Unauthorized`
+    /// or `403 Forbidden`.
+    pub failure_handler: Option<Box<dyn Handler>>,
 }
 
 impl<C, D> JwtAuth<C, D>
@@ -177,6 +181,7 @@ where
             decoder,
             _claims: PhantomData::<C>,
             finders: vec![Box::new(HeaderFinder::new())],
+            failure_handler: None,
         }
     }
     /// Sets force_passed value and return Self.
@@ -203,6 +208,48 @@ where
         self.finders = finders;
         self
     }
+    /// Adds a finder to the end of the finders list and return Self.
+    #[inline]
+    pub fn add_finder(mut self, finder: impl JwtTokenFinder + 'static) -> Self {
+        self.finders.push(Box::new(finder));
+        self
+    }
+    /// Adds a [`CookieFinder`] finding the token from the cookie named `cookie_name` and return Self.
+    #[inline]
+    pub fn cookie_finder(self, cookie_name: impl Into<Cow<'static, str>>) -> Self {
+        self.add_finder(CookieFinder::new(cookie_name))
+    }
+    /// Adds a [`QueryFinder`] finding the token from the query parameter named `query_name` and return Self.
+    #[inline]
+    pub fn query_finder(self, query_name: impl Into<Cow<'static, str>>) -> Self {
+        self.add_finder(QueryFinder::new(query_name))
+    }
+
+    /// Sets the handler used to write response when auth failed and return Self.
+    ///
+    /// The auth state and error are inserted into depot before it is called, see [`JwtAuthDepotExt`].
+    /// It is not called if `force_passed` is `true`.
+    #[inline]
+    pub fn failure_handler(mut self, handler: impl Handler) -> Self {
+        self.failure_handler = Some(Box::new(handler));
+        self
+    }
+
+    async fn fail(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+        error: StatusError,
+    ) {
+        if let Some(handler) = &self.failure_handler {
+            handler.handle(req, depot, res, ctrl).await;
+        } else {
+            res.render(error);
+        }
+        ctrl.skip_rest();
+    }
 
     async fn find_token(&self, req: &mut Request) -> Option<String> {
         for finder in &self.finders {
@@ -234,16 +281,14 @@ where
                     depot.insert(JWT_AUTH_STATE_KEY, JwtAuthState::Forbidden);
                     depot.insert(JWT_AUTH_ERROR_KEY, e);
                     if !self.force_passed {
-                        res.render(StatusError::forbidden());
-                        ctrl.skip_rest();
+                        self.fail(req, depot, res, ctrl, StatusError::forbidden()).await;
                     }
                 }
             }
         } else {
             depot.insert(JWT_AUTH_STATE_KEY, JwtAuthState::Unauthorized);
             if !self.force_passed {
-                res.render(StatusError::unauthorized());
-                ctrl.skip_rest();
+                self.fail(req, depot, res, ctrl, StatusError::unauthorized()).await;
             }
         }
     }
@@ -332,4 +377,73 @@ mod tests {
         let content = access(&service, &token).await;
         assert!(content.contains("Forbidden"));
     }
+
+    #[tokio::test]
+    async fn test_jwt_auth_failure_handler() {
+        #[handler]
+        async fn failure(depot: &mut Depot, res: &mut Response) {
+            res.status_code(StatusCode::UNAUTHORIZED);
+            res.render(Json(
+                serde_json::json!({ "state": format!("{:?}", depot.jwt_auth_state()) }),
+            ));
+        }
+        #[handler]
+        async fn hello() -> &'static str {
+            "hello"
+        }
+
+        let auth_handler: JwtAuth<JwtClaims, ConstDecoder> =
+            JwtAuth::new(ConstDecoder::from_secret(b"ABCDEF")).failure_handler(failure);
+        let service = Service::new(Router::new().hoop(auth_handler).get(hello));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(res.take_string().await.unwrap(), r#"{"state":"Unauthorized"}"#);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/")
+            .add_header("Authorization", "Bearer invalid", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(res.take_string().await.unwrap(), r#"{"state":"Forbidden"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_cookie_and_query_finders() {
+        #[handler]
+        async fn hello() -> &'static str {
+            "hello"
+        }
+
+        let auth_handler: JwtAuth<JwtClaims, ConstDecoder> = JwtAuth::new(ConstDecoder::from_secret(b"ABCDEF"))
+            .cookie_finder("session")
+            .query_finder("access_token");
+        assert_eq!(auth_handler.finders.len(), 3);
+        let service = Service::new(Router::new().hoop(auth_handler).get(hello));
+
+        let claim = JwtClaims {
+            user: "root".into(),
+            exp: (OffsetDateTime::now_utc() + Duration::days(1)).unix_timestamp(),
+        };
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claim,
+            &EncodingKey::from_secret(b"ABCDEF"),
+        )
+        .unwrap();
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/")
+            .add_header("Cookie", format!("session={token}"), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "hello");
+        let mut res = TestClient::get(format!("http://127.0.0.1:5801/?access_token={token}"))
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "hello");
+        let res = TestClient::get(format!("http://127.0.0.1:5801/?jwt_token={token}"))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+    }
 }
@@ -33,6 +33,7 @@ pub(super) type HyperClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>
 #[derive(Clone)]
 pub struct OidcDecoder {
     issuer: String,
+    jwks_uri: Option<String>,
     http_client: HyperClient,
     cache: Arc<RwLock<JwkSetStore>>,
     cache_state: Arc<CacheState>,
@@ -94,6 +95,19 @@ where
 
     /// Build a `OidcDecoder`.
     pub fn build(self) -> impl Future<Output = Result<OidcDecoder, JwtAuthError>> {
+        self.build_decoder(None)
+    }
+
+    /// Build a `OidcDecoder` which fetches the keys from the JWKS URL directly, instead of the `jwks_uri`
+    /// in the OIDC configuration of the issuer.
+    pub fn build_with_jwks_uri(
+        self,
+        jwks_uri: impl Into<String>,
+    ) -> impl Future<Output = Result<OidcDecoder, JwtAuthError>> {
+        self.build_decoder(Some(jwks_uri.into()))
+    }
+
+    fn build_decoder(self, jwks_uri: Option<String>) -> impl Future<Output = Result<OidcDecoder, JwtAuthError>> {
         let Self {
             issuer,
             http_client,
@@ -117,6 +131,7 @@ where
         let http_client = http_client.unwrap_or_else(|| Client::builder(TokioExecutor::new()).build(https));
         let decoder = OidcDecoder {
             issuer,
+            jwks_uri,
             http_client,
             cache,
             cache_state,
@@ -153,6 +168,9 @@ impl OidcDecoder {
         Ok(config)
     }
     async fn jwks_uri(&self) -> Result<String, JwtAuthError> {
+        if let Some(jwks_uri) = &self.jwks_uri {
+            return Ok(jwks_uri.clone());
+        }
         Ok(self.get_config().await?.jwks_uri)
     }
 
@@ -0,0 +1,317 @@
+//! Conversion of Lambda events to requests and of responses to Lambda results.
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+use base64::engine::{general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use serde::{Deserialize, Serialize};
+
+use salvo_core::http::header::{HeaderName, HeaderValue, CONTENT_TYPE, COOKIE, HOST, SET_COOKIE};
+use salvo_core::http::uri::Scheme;
+use salvo_core::http::{HeaderMap, Method, ReqBody, Response};
+use salvo_core::hyper;
+
+use crate::Error;
+
+/// Payload format of the event, the response is sent in the same format.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PayloadFormat {
+    /// API Gateway REST API, or HTTP API with payload format version 1.0.
+    ApiGatewayV1,
+    /// API Gateway HTTP API with payload format version 2.0, or Lambda Function URL.
+    ApiGatewayV2,
+    /// Application Load Balancer, `multi_value` is `true` if multi-value headers are enabled on the target group.
+    Alb { multi_value: bool },
+}
+
+/// Fields of the events of all the supported payload formats.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct LambdaEvent {
+    version: Option<String>,
+    http_method: Option<String>,
+    path: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    query_string_parameters: Option<HashMap<String, String>>,
+    multi_value_query_string_parameters: Option<HashMap<String, Vec<String>>>,
+    raw_path: Option<String>,
+    raw_query_string: Option<String>,
+    cookies: Option<Vec<String>>,
+    request_context: RequestContext,
+    body: Option<String>,
+    is_base64_encoded: bool,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase", default)]
+struct RequestContext {
+    http: Option<HttpContext>,
+    identity: Option<Identity>,
+    elb: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase", default)]
+struct HttpContext {
+    method: String,
+    source_ip: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase", default)]
+struct Identity {
+    source_ip: Option<String>,
+}
+
+impl LambdaEvent {
+    pub(crate) fn format(&self) -> PayloadFormat {
+        if self.version.as_deref() == Some("2.0") {
+            PayloadFormat::ApiGatewayV2
+        } else if self.request_context.elb.is_some() {
+            PayloadFormat::Alb {
+                multi_value: self.multi_value_headers.is_some(),
+            }
+        } else {
+            PayloadFormat::ApiGatewayV1
+        }
+    }
+
+    /// Converts the event to a hyper request.
+    ///
+    /// The scheme is read from the `x-forwarded-proto` header. The remote address is the source ip reported by API
+    /// Gateway, or the last address of `x-forwarded-for` added by the load balancer.
+    pub(crate) fn into_request(self) -> Result<LambdaRequest, Error> {
+        let format = self.format();
+        let method = match format {
+            PayloadFormat::ApiGatewayV2 => self.request_context.http.as_ref().map(|http| http.method.clone()),
+            _ => self.http_method.clone(),
+        }
+        .ok_or_else(|| Error::InvalidEvent("http method is missing".into()))?;
+        let method = Method::from_bytes(method.as_bytes()).map_err(|e| Error::InvalidEvent(e.to_string()))?;
+
+        let mut headers = HeaderMap::new();
+        if let Some(multi_value_headers) = self.multi_value_headers {
+            for (name, values) in multi_value_headers {
+                for value in values {
+                    append_header(&mut headers, &name, &value)?;
+                }
+            }
+        } else if let Some(single_headers) = self.headers {
+            for (name, value) in single_headers {
+                append_header(&mut headers, &name, &value)?;
+            }
+        }
+        if let Some(cookies) = self.cookies.filter(|cookies| !cookies.is_empty()) {
+            append_header(&mut headers, COOKIE.as_str(), &cookies.join("; "))?;
+        }
+
+        let (path, query) = match format {
+            PayloadFormat::ApiGatewayV2 => (
+                self.raw_path.unwrap_or_default(),
+                self.raw_query_string.unwrap_or_default(),
+            ),
+            PayloadFormat::ApiGatewayV1 => {
+                // API Gateway decodes the query string, so it is encoded again.
+                let mut serializer = form_urlencoded::Serializer::new(String::new());
+                if let Some(queries) = self.multi_value_query_string_parameters {
+                    for (name, values) in queries {
+                        for value in values {
+                            serializer.append_pair(&name, &value);
+                        }
+                    }
+                } else if let Some(queries) = self.query_string_parameters {
+                    for (name, value) in queries {
+                        serializer.append_pair(&name, &value);
+                    }
+                }
+                (self.path.unwrap_or_default(), serializer.finish())
+            }
+            PayloadFormat::Alb { .. } => {
+                // Load balancers send the query string as received, it is already encoded.
+                let mut pairs = Vec::new();
+                if let Some(queries) = self.multi_value_query_string_parameters {
+                    for (name, values) in queries {
+                        for value in values {
+                            pairs.push(format!("{name}={value}"));
+                        }
+                    }
+                } else if let Some(queries) = self.query_string_parameters {
+                    for (name, value) in queries {
+                        pairs.push(format!("{name}={value}"));
+                    }
+                }
+                (self.path.unwrap_or_default(), pairs.join("&"))
+            }
+        };
+        let path = if path.starts_with('/') {
+            path
+        } else {
+            format!("/{path}")
+        };
+
+        let remote_addr = self.request_context.source_ip(format, &headers);
+        let scheme = match headers.get("x-forwarded-proto").and_then(|value| value.to_str().ok()) {
+            Some("http") => Scheme::HTTP,
+            _ => Scheme::HTTPS,
+        };
+        let mut uri = match headers.get(HOST).and_then(|value| value.to_str().ok()) {
+            Some(host) => format!("{scheme}://{host}{path}"),
+            None => path,
+        };
+        if !query.is_empty() {
+            uri.push('?');
+            uri.push_str(&query);
+        }
+
+        let body = match self.body {
+            Some(body) if self.is_base64_encoded => ReqBody::Once(
+                STANDARD
+                    .decode(body)
+                    .map_err(|e| Error::InvalidEvent(e.to_string()))?
+                    .into(),
+            ),
+            Some(body) if !body.is_empty() => ReqBody::Once(body.into()),
+            _ => ReqBody::None,
+        };
+
+        let mut builder = hyper::Request::builder().method(method).uri(uri);
+        if let Some(request_headers) = builder.headers_mut() {
+            *request_headers = headers;
+        }
+        let request = builder.body(body).map_err(|e| Error::InvalidEvent(e.to_string()))?;
+        Ok(LambdaRequest {
+            request,
+            scheme,
+            remote_addr,
+        })
+    }
+}
+
+impl RequestContext {
+    fn source_ip(&self, format: PayloadFormat, headers: &HeaderMap) -> Option<SocketAddr> {
+        let source_ip = match format {
+            PayloadFormat::ApiGatewayV1 => self.identity.as_ref()?.source_ip.clone(),
+            PayloadFormat::ApiGatewayV2 => self.http.as_ref()?.source_ip.clone(),
+            PayloadFormat::Alb { .. } => headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .last()
+                .map(|ip| ip.trim().to_owned()),
+        };
+        let ip = source_ip?.parse::<IpAddr>().ok()?;
+        Some(SocketAddr::new(ip, 0))
+    }
+}
+
+/// Request converted from a [`LambdaEvent`].
+pub(crate) struct LambdaRequest {
+    pub(crate) request: hyper::Request<ReqBody>,
+    pub(crate) scheme: Scheme,
+    pub(crate) remote_addr: Option<SocketAddr>,
+}
+
+fn append_header(headers: &mut HeaderMap, name: &str, value: &str) -> Result<(), Error> {
+    let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| Error::InvalidEvent(e.to_string()))?;
+    let value = HeaderValue::from_str(value).map_err(|e| Error::InvalidEvent(e.to_string()))?;
+    headers.append(name, value);
+    Ok(())
+}
+
+/// Result of the function, in the payload format of the event.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LambdaResponse {
+    status_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cookies: Option<Vec<String>>,
+    body: String,
+    is_base64_encoded: bool,
+}
+
+impl LambdaResponse {
+    /// Converts a response to the payload format of the event, the body is encoded with base64 unless it is text.
+    pub(crate) async fn from_response(res: Response, format: PayloadFormat) -> Result<Self, Error> {
+        let (parts, body) = res.into_hyper().into_parts();
+        let body = body
+            .collect()
+            .await
+            .map_err(|e| Error::Response(e.to_string()))?
+            .to_bytes();
+        let (body, is_base64_encoded) = encode_body(&parts.headers, body);
+
+        let mut headers = HashMap::<String, Vec<String>>::new();
+        let mut cookies = Vec::new();
+        for (name, value) in &parts.headers {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            if format == PayloadFormat::ApiGatewayV2 && name == SET_COOKIE {
+                cookies.push(value);
+            } else {
+                headers.entry(name.as_str().to_owned()).or_default().push(value);
+            }
+        }
+
+        let mut response = LambdaResponse {
+            status_code: parts.status.as_u16(),
+            status_description: None,
+            headers: None,
+            multi_value_headers: None,
+            cookies: None,
+            body,
+            is_base64_encoded,
+        };
+        match format {
+            PayloadFormat::ApiGatewayV1 | PayloadFormat::Alb { multi_value: true } => {
+                response.multi_value_headers = Some(headers);
+            }
+            PayloadFormat::ApiGatewayV2 | PayloadFormat::Alb { multi_value: false } => {
+                response.headers = Some(
+                    headers
+                        .into_iter()
+                        .map(|(name, values)| (name, values.join(",")))
+                        .collect(),
+                );
+            }
+        }
+        if let PayloadFormat::Alb { .. } = format {
+            response.status_description = Some(parts.status.to_string());
+        }
+        if format == PayloadFormat::ApiGatewayV2 {
+            response.cookies = Some(cookies);
+        }
+        Ok(response)
+    }
+}
+
+/// Returns the body as a string if its content type is text, otherwise encoded with base64.
+fn encode_body(headers: &HeaderMap, body: Bytes) -> (String, bool) {
+    if body.is_empty() {
+        return (String::new(), false);
+    }
+    let is_text = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|ctype| {
+            let ctype = ctype.to_ascii_lowercase();
+            ctype.starts_with("text/")
+                || ["json", "xml", "javascript", "x-www-form-urlencoded"]
+                    .iter()
+                    .any(|text| ctype.contains(text))
+        })
+        .unwrap_or(false);
+    if is_text {
+        if let Ok(body) = std::str::from_utf8(&body) {
+            return (body.to_owned(), false);
+        }
+    }
+    (STANDARD.encode(&body), true)
+}
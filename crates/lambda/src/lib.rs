@@ -0,0 +1,350 @@
+//! AWS Lambda support for Salvo web server framework.
+//!
+//! [`run`] serves a [`Service`] as a Lambda function with the Lambda runtime API, handling the events of API
+//! Gateway REST and HTTP APIs, Application Load Balancers and Lambda Function URLs. Events are converted to
+//! requests, handled by the same router and hoops as in a server, and the responses are converted back to the
+//! payload format of the event. Binary response bodies are encoded with base64.
+//!
+//! So the same `Router` can be served by a binary and deployed to Lambda, for example by checking the
+//! `AWS_LAMBDA_RUNTIME_API` environment variable set by Lambda:
+//!
+//! ```no_run
+//! use salvo_core::prelude::*;
+//!
+//! #[handler]
+//! async fn hello() -> &'static str {
+//!     "Hello World"
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let router = Router::new().get(hello);
+//!     if std::env::var("AWS_LAMBDA_RUNTIME_API").is_ok() {
+//!         salvo_lambda::run(router).await.unwrap();
+//!     } else {
+//!         let acceptor = TcpListener::new("0.0.0.0:5800").bind().await;
+//!         Server::new(acceptor).serve(router).await;
+//!     }
+//! }
+//! ```
+//!
+//! Events can also be handled by other Lambda runtimes with [`handle_event`].
+//!
+//! The X-Ray trace id of an invocation is inserted in the extensions of the request as a [`TraceId`], instead of
+//! the `_X_AMZN_TRACE_ID` environment variable set by other runtimes.
+//!
+//! Read more: <https://salvo.rs>
+#![doc(html_favicon_url = "https://salvo.rs/favicon-32x32.png")]
+#![doc(html_logo_url = "https://salvo.rs/images/logo.svg")]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::Serialize;
+
+use salvo_core::fuse::SteadyFusewire;
+use salvo_core::http::Request;
+use salvo_core::hyper;
+use salvo_core::Service;
+
+mod event;
+
+use event::{LambdaEvent, LambdaRequest, LambdaResponse};
+
+/// Header of the id of an invocation, sent by the Lambda runtime API.
+const REQUEST_ID_HEADER: &str = "lambda-runtime-aws-request-id";
+/// Header of the X-Ray trace id of an invocation, sent by the Lambda runtime API.
+const TRACE_ID_HEADER: &str = "lambda-runtime-trace-id";
+
+/// Errors of the Lambda adapter.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The `AWS_LAMBDA_RUNTIME_API` environment variable is not set, the function is not running in Lambda.
+    #[error("environment variable `AWS_LAMBDA_RUNTIME_API` is not set")]
+    MissingRuntimeApi,
+    /// The event is not an event of API Gateway, Application Load Balancer or Function URL.
+    #[error("invalid lambda event: {0}")]
+    InvalidEvent(String),
+    /// Reading the body of the response failed.
+    #[error("response error: {0}")]
+    Response(String),
+    /// Calling the Lambda runtime API failed.
+    #[error("lambda runtime api error: {0}")]
+    Runtime(String),
+    /// Json error.
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Io error.
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    /// Type of the error reported to the Lambda runtime API.
+    fn error_type(&self) -> &'static str {
+        match self {
+            Error::InvalidEvent(_) | Error::Json(_) => "InvalidEvent",
+            Error::Response(_) => "ResponseError",
+            Error::MissingRuntimeApi | Error::Runtime(_) | Error::Io(_) => "RuntimeError",
+        }
+    }
+}
+
+/// X-Ray trace id of an invocation, inserted in the extensions of the request by [`run`].
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_lambda::TraceId;
+///
+/// #[handler]
+/// async fn hello(req: &mut Request) -> String {
+///     let trace_id = req.extensions().get::<TraceId>().map(|id| id.0.as_str());
+///     format!("trace id: {trace_id:?}")
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceId(pub String);
+
+/// Handles a Lambda event with the service, and returns the result of the function.
+///
+/// The event should be the JSON payload of an API Gateway, Application Load Balancer or Function URL event, the
+/// result is the JSON payload of the response, in the same payload format.
+pub async fn handle_event(service: &Service, event: &[u8]) -> Result<Vec<u8>, Error> {
+    handle_invocation(service, event, None).await
+}
+
+async fn handle_invocation(service: &Service, event: &[u8], trace_id: Option<TraceId>) -> Result<Vec<u8>, Error> {
+    let event: LambdaEvent = serde_json::from_slice(event)?;
+    let format = event.format();
+    let LambdaRequest {
+        mut request,
+        scheme,
+        remote_addr,
+    } = event.into_request()?;
+    let local_addr = salvo_core::conn::SocketAddr::Unknown;
+    let remote_addr = remote_addr
+        .map(Into::into)
+        .unwrap_or(salvo_core::conn::SocketAddr::Unknown);
+    if let Some(trace_id) = trace_id {
+        request.extensions_mut().insert(trace_id);
+    }
+    let handler = service.hyper_handler(local_addr, remote_addr, scheme.clone(), Arc::new(SteadyFusewire), None);
+    let res = handler.handle(Request::from_hyper(request, scheme)).await;
+    let response = LambdaResponse::from_response(res, format).await?;
+    Ok(serde_json::to_vec(&response)?)
+}
+
+/// Error reported to the Lambda runtime API when an invocation fails.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct InvocationError {
+    error_message: String,
+    error_type: &'static str,
+}
+
+/// Runs the service as a Lambda function, see the [crate documentation](crate).
+///
+/// Invocations are handled one by one, until the runtime API fails. Invalid events and responses whose body can not
+/// be read are reported to the runtime API as invocation errors.
+pub async fn run(service: impl Into<Service>) -> Result<(), Error> {
+    let runtime_api = std::env::var("AWS_LAMBDA_RUNTIME_API").map_err(|_| Error::MissingRuntimeApi)?;
+    let service = service.into();
+    let client = Client::builder(TokioExecutor::new()).build_http::<Full<Bytes>>();
+    let base_url = format!("http://{runtime_api}/2018-06-01/runtime/invocation");
+    loop {
+        let next = client
+            .get(parse_uri(&format!("{base_url}/next"))?)
+            .await
+            .map_err(|e| Error::Runtime(e.to_string()))?;
+        let request_id = next
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| Error::Runtime("request id of the invocation is missing".into()))?
+            .to_owned();
+        let trace_id = next
+            .headers()
+            .get(TRACE_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| TraceId(value.to_owned()));
+        let event = next
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| Error::Runtime(e.to_string()))?
+            .to_bytes();
+
+        let (url, body) = match handle_invocation(&service, &event, trace_id).await {
+            Ok(body) => (format!("{base_url}/{request_id}/response"), body),
+            Err(e) => {
+                tracing::error!(error = ?e, request_id, "handle lambda event failed");
+                let error = InvocationError {
+                    error_message: e.to_string(),
+                    error_type: e.error_type(),
+                };
+                (format!("{base_url}/{request_id}/error"), serde_json::to_vec(&error)?)
+            }
+        };
+        let request = hyper::Request::post(parse_uri(&url)?)
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| Error::Runtime(e.to_string()))?;
+        client
+            .request(request)
+            .await
+            .map_err(|e| Error::Runtime(e.to_string()))?;
+    }
+}
+
+fn parse_uri(url: &str) -> Result<hyper::Uri, Error> {
+    url.parse()
+        .map_err(|e: hyper::http::uri::InvalidUri| Error::Runtime(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+
+    use super::*;
+
+    #[handler]
+    async fn user(req: &mut Request, res: &mut Response) {
+        res.add_cookie(salvo_core::http::cookie::Cookie::new("seen", "1"));
+        res.render(format!(
+            "{} {} {} {}",
+            req.method(),
+            req.param::<String>("id").unwrap_or_default(),
+            req.query::<String>("q").unwrap_or_default(),
+            req.remote_addr()
+        ));
+    }
+
+    #[handler]
+    async fn trace(req: &mut Request) -> String {
+        req.extensions()
+            .get::<TraceId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_default()
+    }
+
+    #[handler]
+    async fn upload(req: &mut Request, res: &mut Response) {
+        let body = req.payload().await.unwrap().clone();
+        res.headers_mut()
+            .insert("content-type", "application/octet-stream".parse().unwrap());
+        res.write_body(body).unwrap();
+    }
+
+    fn service() -> Service {
+        Service::new(
+            Router::new()
+                .push(Router::with_path("users/<id>").get(user))
+                .push(Router::with_path("trace").get(trace))
+                .push(Router::with_path("upload").post(upload)),
+        )
+    }
+
+    async fn call(event: serde_json::Value) -> serde_json::Value {
+        let result = handle_event(&service(), &serde_json::to_vec(&event).unwrap())
+            .await
+            .unwrap();
+        serde_json::from_slice(&result).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_gateway_v1() {
+        let result = call(serde_json::json!({
+            "httpMethod": "GET",
+            "path": "/users/7",
+            "headers": {"host": "example.com"},
+            "multiValueHeaders": {"host": ["example.com"]},
+            "queryStringParameters": {"q": "a b"},
+            "multiValueQueryStringParameters": {"q": ["a b"]},
+            "requestContext": {"identity": {"sourceIp": "10.0.0.1"}},
+            "body": null,
+            "isBase64Encoded": false
+        }))
+        .await;
+        assert_eq!(result["statusCode"], 200);
+        assert_eq!(result["body"], "GET 7 a b socket://10.0.0.1:0");
+        assert_eq!(result["isBase64Encoded"], false);
+        assert_eq!(result["multiValueHeaders"]["set-cookie"][0], "seen=1");
+    }
+
+    #[tokio::test]
+    async fn test_api_gateway_v2() {
+        let result = call(serde_json::json!({
+            "version": "2.0",
+            "rawPath": "/upload",
+            "rawQueryString": "",
+            "cookies": ["a=1"],
+            "headers": {"content-type": "application/octet-stream"},
+            "requestContext": {"http": {"method": "POST", "sourceIp": "10.0.0.2"}},
+            "body": "AAEC",
+            "isBase64Encoded": true
+        }))
+        .await;
+        assert_eq!(result["statusCode"], 200);
+        assert_eq!(result["body"], "AAEC");
+        assert_eq!(result["isBase64Encoded"], true);
+        assert_eq!(result["cookies"], serde_json::json!([]));
+
+        let result = call(serde_json::json!({
+            "version": "2.0",
+            "rawPath": "/missing",
+            "rawQueryString": "q=1",
+            "requestContext": {"http": {"method": "GET"}}
+        }))
+        .await;
+        assert_eq!(result["statusCode"], 404);
+    }
+
+    #[tokio::test]
+    async fn test_alb() {
+        let result = call(serde_json::json!({
+            "requestContext": {"elb": {"targetGroupArn": "arn"}},
+            "httpMethod": "GET",
+            "path": "/users/8",
+            "queryStringParameters": {"q": "x%20y"},
+            "headers": {"x-forwarded-for": "10.0.0.3, 10.0.0.4"},
+            "body": "",
+            "isBase64Encoded": false
+        }))
+        .await;
+        assert_eq!(result["statusCode"], 200);
+        assert_eq!(result["statusDescription"], "200 OK");
+        assert_eq!(result["body"], "GET 8 x y socket://10.0.0.4:0");
+        assert_eq!(result["headers"]["set-cookie"], "seen=1");
+        assert!(result.get("multiValueHeaders").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_trace_id() {
+        let event = serde_json::json!({
+            "version": "2.0",
+            "rawPath": "/trace",
+            "rawQueryString": "",
+            "requestContext": {"http": {"method": "GET"}}
+        });
+        let trace_id = TraceId("Root=1-5759e988-bd862e3fe1be46a994272793".into());
+        let result = handle_invocation(&service(), &serde_json::to_vec(&event).unwrap(), Some(trace_id.clone()))
+            .await
+            .unwrap();
+        let result: serde_json::Value = serde_json::from_slice(&result).unwrap();
+        assert_eq!(result["body"], trace_id.0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_event() {
+        let result = handle_event(&service(), br#"{"path": "/users/1"}"#).await;
+        assert!(matches!(&result, Err(Error::InvalidEvent(_))));
+        assert_eq!(result.unwrap_err().error_type(), "InvalidEvent");
+        assert!(matches!(run(service()).await, Err(Error::MissingRuntimeApi)));
+    }
+}
@@ -0,0 +1,143 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Attribute, Ident, ImplItem, ItemImpl, LitStr, Meta, Type};
+
+use crate::handler::handle_fn_with;
+use crate::shared::*;
+
+/// Attributes mapping a method of a controller to a route, named after the methods of `Router`.
+const ROUTE_METHODS: [&str; 7] = ["get", "post", "put", "delete", "patch", "head", "options"];
+
+/// Parses a route attribute like `#[get("<id>")]` or `#[post]`, returns the method and the path.
+fn parse_route(attr: &Attribute) -> syn::Result<Option<(Ident, String)>> {
+    let Some(method) = ROUTE_METHODS.iter().find(|method| attr.path().is_ident(method)) else {
+        return Ok(None);
+    };
+    let path = match &attr.meta {
+        Meta::Path(_) => String::new(),
+        Meta::List(_) => attr.parse_args::<LitStr>()?.value(),
+        Meta::NameValue(_) => {
+            return Err(syn::Error::new_spanned(
+                attr,
+                format!("route attribute should be `#[{method}]` or `#[{method}(\"path\")]`"),
+            ))
+        }
+    };
+    Ok(Some((Ident::new(method, Span::call_site()), path)))
+}
+
+pub(crate) fn generate(mut item_impl: ItemImpl) -> syn::Result<TokenStream> {
+    let salvo = salvo_crate();
+    if let Some((_, path, _)) = &item_impl.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "#[controller] must be added to an inherent `impl`",
+        ));
+    }
+    let ty = item_impl.self_ty.clone();
+    let Type::Path(ty_path) = &*ty else {
+        return Err(syn::Error::new_spanned(
+            ty,
+            "#[controller] must be added to `impl` of a struct",
+        ));
+    };
+    let ty_name = &ty_path.path.segments.last().expect("path segment should exists").ident;
+    let generics = item_impl.generics.clone();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut handlers = Vec::new();
+    // Routes grouped by path, in declaration order.
+    let mut routes: Vec<(String, Vec<(Ident, Ident)>)> = Vec::new();
+    for item in &mut item_impl.items {
+        let ImplItem::Fn(method) = item else {
+            continue;
+        };
+        let mut method_routes = Vec::new();
+        let mut attrs = Vec::with_capacity(method.attrs.len());
+        for attr in method.attrs.drain(..) {
+            match parse_route(&attr)? {
+                Some(route) => method_routes.push(route),
+                None => attrs.push(attr),
+            }
+        }
+        method.attrs = attrs;
+        if method_routes.is_empty() {
+            continue;
+        }
+
+        let sig = &method.sig;
+        if let Some(receiver) = sig.receiver() {
+            if receiver.reference.is_none() || receiver.mutability.is_some() || receiver.colon_token.is_some() {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "controller methods should take `&self`",
+                ));
+            }
+        }
+        let name = &sig.ident;
+        let handler_name = format_ident!("__salvo_controller_{}_{}", ty_name, name);
+        let hfn = handle_fn_with(&salvo, sig, quote!(<#ty>::#name), quote!(&*self.controller))?;
+        handlers.push(quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            struct #handler_name #generics #where_clause {
+                controller: ::std::sync::Arc<#ty>,
+            }
+            impl #impl_generics ::std::clone::Clone for #handler_name #ty_generics #where_clause {
+                fn clone(&self) -> Self {
+                    Self {
+                        controller: self.controller.clone(),
+                    }
+                }
+            }
+            #[#salvo::async_trait]
+            impl #impl_generics #salvo::Handler for #handler_name #ty_generics #where_clause {
+                #hfn
+            }
+        });
+        for (route_method, path) in method_routes {
+            match routes.iter_mut().find(|(route_path, _)| *route_path == path) {
+                Some((_, goals)) => goals.push((route_method, handler_name.clone())),
+                None => routes.push((path, vec![(route_method, handler_name.clone())])),
+            }
+        }
+    }
+    if routes.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item_impl.self_ty,
+            "#[controller] requires at least one method with a route attribute, like `#[get]`",
+        ));
+    }
+
+    let routers = routes.iter().map(|(path, goals)| {
+        let goals = goals.iter().map(|(route_method, handler_name)| {
+            quote! {
+                .#route_method(#handler_name {
+                    controller: controller.clone(),
+                })
+            }
+        });
+        if path.is_empty() {
+            quote! {
+                router = router #(#goals)*;
+            }
+        } else {
+            quote! {
+                router = router.push(#salvo::Router::with_path(#path) #(#goals)*);
+            }
+        }
+    });
+
+    Ok(quote! {
+        #item_impl
+        #(#handlers)*
+        impl #impl_generics ::std::convert::From<#ty> for #salvo::Router #where_clause {
+            fn from(controller: #ty) -> Self {
+                let controller = ::std::sync::Arc::new(controller);
+                let mut router = #salvo::Router::new();
+                #(#routers)*
+                router
+            }
+        }
+    })
+}
@@ -75,21 +75,31 @@ pub(crate) fn generate(input: Item) -> syn::Result<TokenStream> {
 
 fn handle_fn(salvo: &Ident, sig: &Signature) -> syn::Result<TokenStream> {
     let name = &sig.ident;
+    handle_fn_with(salvo, sig, quote!(Self::#name), quote!(self))
+}
+
+/// Generates the `handle` function of `Handler`, calling `callee` with the receiver replaced by `receiver`.
+pub(crate) fn handle_fn_with(
+    salvo: &Ident,
+    sig: &Signature,
+    callee: TokenStream,
+    receiver: TokenStream,
+) -> syn::Result<TokenStream> {
     let mut extract_ts = Vec::with_capacity(sig.inputs.len());
-    let mut call_args: Vec<Ident> = Vec::with_capacity(sig.inputs.len());
+    let mut call_args: Vec<TokenStream> = Vec::with_capacity(sig.inputs.len());
     for input in &sig.inputs {
         match parse_input_type(input) {
             InputType::Request(_pat) => {
-                call_args.push(Ident::new("__macro_gen_req", Span::call_site()));
+                call_args.push(quote!(__macro_gen_req));
             }
             InputType::Depot(_pat) => {
-                call_args.push(Ident::new("__macro_gen_depot", Span::call_site()));
+                call_args.push(quote!(__macro_gen_depot));
             }
             InputType::Response(_pat) => {
-                call_args.push(Ident::new("__macro_gen_res", Span::call_site()));
+                call_args.push(quote!(__macro_gen_res));
             }
             InputType::FlowCtrl(_pat) => {
-                call_args.push(Ident::new("__macro_gen_ctrl", Span::call_site()));
+                call_args.push(quote!(__macro_gen_ctrl));
             }
             InputType::Unknown => {
                 return Err(syn::Error::new_spanned(
@@ -99,7 +109,7 @@ fn handle_fn(salvo: &Ident, sig: &Signature) -> syn::Result<TokenStream> {
             }
             InputType::NoReference(pat) => {
                 if let (Pat::Ident(ident), Type::Path(ty)) = (&*pat.pat, &*pat.ty) {
-                    call_args.push(ident.ident.clone());
+                    call_args.push(ident.ident.to_token_stream());
                     let ty = omit_type_path_lifetimes(ty);
                     let idv = pat.pat.to_token_stream().to_string();
                     let idv = idv.rsplit_once(' ').map(|(_, v)| v.to_owned()).unwrap_or(idv);
@@ -125,7 +135,7 @@ fn handle_fn(salvo: &Ident, sig: &Signature) -> syn::Result<TokenStream> {
                 }
             }
             InputType::Receiver(_) => {
-                call_args.push(Ident::new("self", Span::call_site()));
+                call_args.push(receiver.clone());
             }
         }
     }
@@ -136,14 +146,14 @@ fn handle_fn(salvo: &Ident, sig: &Signature) -> syn::Result<TokenStream> {
                 Ok(quote! {
                     async fn handle(&self, __macro_gen_req: &mut #salvo::Request, __macro_gen_depot: &mut #salvo::Depot, __macro_gen_res: &mut #salvo::Response, __macro_gen_ctrl: &mut #salvo::FlowCtrl) {
                         #(#extract_ts)*
-                        Self::#name(#(#call_args),*)
+                        #callee(#(#call_args),*)
                     }
                 })
             } else {
                 Ok(quote! {
                     async fn handle(&self, __macro_gen_req: &mut #salvo::Request, __macro_gen_depot: &mut #salvo::Depot, __macro_gen_res: &mut #salvo::Response, __macro_gen_ctrl: &mut #salvo::FlowCtrl) {
                         #(#extract_ts)*
-                        Self::#name(#(#call_args),*).await
+                        #callee(#(#call_args),*).await
                     }
                 })
             }
@@ -153,14 +163,14 @@ fn handle_fn(salvo: &Ident, sig: &Signature) -> syn::Result<TokenStream> {
                 Ok(quote! {
                     async fn handle(&self, __macro_gen_req: &mut #salvo::Request, __macro_gen_depot: &mut #salvo::Depot, __macro_gen_res: &mut #salvo::Response, __macro_gen_ctrl: &mut #salvo::FlowCtrl) {
                         #(#extract_ts)*
-                        #salvo::Writer::write(Self::#name(#(#call_args),*), __macro_gen_req, __macro_gen_depot, __macro_gen_res).await;
+                        #salvo::Writer::write(#callee(#(#call_args),*), __macro_gen_req, __macro_gen_depot, __macro_gen_res).await;
                     }
                 })
             } else {
                 Ok(quote! {
                     async fn handle(&self, __macro_gen_req: &mut #salvo::Request, __macro_gen_depot: &mut #salvo::Depot, __macro_gen_res: &mut #salvo::Response, __macro_gen_ctrl: &mut #salvo::FlowCtrl) {
                         #(#extract_ts)*
-                        #salvo::Writer::write(Self::#name(#(#call_args),*).await, __macro_gen_req, __macro_gen_depot, __macro_gen_res).await;
+                        #salvo::Writer::write(#callee(#(#call_args),*).await, __macro_gen_req, __macro_gen_depot, __macro_gen_res).await;
                     }
                 })
             }
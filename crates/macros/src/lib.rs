@@ -6,9 +6,10 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, DeriveInput, Item};
+use syn::{parse_macro_input, DeriveInput, Item, ItemImpl};
 
 mod attribute;
+mod controller;
 mod extract;
 mod handler;
 mod shared;
@@ -31,6 +32,26 @@ pub fn handler(_args: TokenStream, input: TokenStream) -> TokenStream {
     }
 }
 
+/// `controller` is a macro to map the methods of a struct to routes, so they can access the fields of the struct,
+/// like a database pool or a config, with `&self`.
+///
+/// Methods are mapped to routes by the attributes `#[get]`, `#[post]`, `#[put]`, `#[delete]`, `#[patch]`,
+/// `#[head]` and `#[options]`, with an optional path relative to the router of the controller, like
+/// `#[get("<id>")]`. Their arguments and return value are handled like with `#[handler]`. Methods without route
+/// attribute are left as is.
+///
+/// The struct is converted to a `Router` with `Router::from`, and is shared by its routes in an `Arc`.
+///
+/// View `salvo_core::handler` for more details.
+#[proc_macro_attribute]
+pub fn controller(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as ItemImpl);
+    match controller::generate(item) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 /// Generate code for extractible type.
 #[proc_macro_derive(Extractible, attributes(salvo))]
 pub fn derive_extractible(input: TokenStream) -> TokenStream {
@@ -164,6 +185,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_controller() {
+        let input = quote! {
+            impl UserController {
+                #[get("<id>")]
+                #[head("<id>")]
+                async fn show(&self, id: PathParam<u64>) -> String {
+                    format!("{}", *id)
+                }
+                fn helper(&self) {}
+            }
+        };
+        let output = controller::generate(parse2(input).unwrap()).unwrap().to_string();
+        assert!(!output.contains("# [get"));
+        assert!(output.contains("struct __salvo_controller_UserController_show"));
+        assert!(output.contains(
+            &quote! {
+                router = router.push(salvo::Router::with_path("<id>")
+                    .get(__salvo_controller_UserController_show { controller: controller.clone(), })
+                    .head(__salvo_controller_UserController_show { controller: controller.clone(), }));
+            }
+            .to_string()
+        ));
+        assert!(output.contains(&quote!(<UserController>::show(&*self.controller, id)).to_string()));
+
+        let input = quote! {
+            impl UserController {
+                #[post]
+                async fn create(&mut self) {}
+            }
+        };
+        let error = controller::generate(parse2(input).unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "controller methods should take `&self`");
+    }
+
     #[test]
     fn test_extract_simple() {
         let input = quote! {
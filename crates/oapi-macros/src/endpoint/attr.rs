@@ -15,6 +15,8 @@ pub(crate) struct EndpointAttr<'p> {
     pub(crate) tags: Option<Vec<String>>,
     pub(crate) parameters: Vec<Parameter<'p>>,
     pub(crate) security: Option<Array<'p, SecurityRequirementsAttr>>,
+    pub(crate) summary: Option<Expr>,
+    pub(crate) description: Option<Expr>,
 
     pub(crate) doc_comments: Option<Vec<String>>,
     pub(crate) deprecated: Option<bool>,
@@ -22,8 +24,8 @@ pub(crate) struct EndpointAttr<'p> {
 
 impl Parse for EndpointAttr<'_> {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        const EXPECTED_ATTRIBUTE_MESSAGE: &str =
-            "unexpected identifier, expected any of: operation_id, request_body, responses, parameters, tags, security";
+        const EXPECTED_ATTRIBUTE_MESSAGE: &str = "unexpected identifier, expected any of: operation_id, request_body, \
+            responses, parameters, tags, security, summary, description";
         let mut attr = EndpointAttr::default();
 
         while !input.is_empty() {
@@ -34,6 +36,12 @@ impl Parse for EndpointAttr<'_> {
                 "operation_id" => {
                     attr.operation_id = Some(parse_utils::parse_next(input, || Expr::parse(input))?);
                 }
+                "summary" => {
+                    attr.summary = Some(parse_utils::parse_next(input, || Expr::parse(input))?);
+                }
+                "description" => {
+                    attr.description = Some(parse_utils::parse_next(input, || Expr::parse(input))?);
+                }
                 "request_body" => {
                     attr.request_body = Some(input.parse::<RequestBodyAttr>()?);
                 }
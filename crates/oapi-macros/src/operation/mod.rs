@@ -24,6 +24,8 @@ pub(crate) struct Operation<'a> {
     tags: &'a Option<Vec<String>>,
     summary: Option<&'a String>,
     description: Option<&'a Vec<String>>,
+    summary_expr: Option<&'a Expr>,
+    description_expr: Option<&'a Expr>,
     parameters: &'a Vec<Parameter<'a>>,
     request_body: Option<&'a RequestBodyAttr<'a>>,
     responses: &'a Vec<Response<'a>>,
@@ -38,6 +40,8 @@ impl<'a> Operation<'a> {
             tags: &attr.tags,
             summary: attr.doc_comments.as_ref().and_then(|comments| comments.iter().next()),
             description: attr.doc_comments.as_ref(),
+            summary_expr: attr.summary.as_ref(),
+            description_expr: attr.description.as_ref(),
             parameters: attr.parameters.as_ref(),
             request_body: attr.request_body.as_ref(),
             responses: attr.responses.as_ref(),
@@ -104,6 +108,18 @@ impl<'a> Operation<'a> {
             }
         }
 
+        // Explicit attributes take precedence over doc comments.
+        if let Some(summary) = self.summary_expr {
+            modifiers.push(quote! {
+                operation.summary = Some(#summary.into());
+            })
+        }
+        if let Some(description) = self.description_expr {
+            modifiers.push(quote! {
+                operation.description = Some(#description.into());
+            })
+        }
+
         self.parameters.iter().for_each(|parameter| {
             modifiers.push(quote! {
                 #parameter
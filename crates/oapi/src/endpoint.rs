@@ -1,5 +1,6 @@
 use std::any::TypeId;
 
+use salvo_core::http::header::HeaderMap;
 use salvo_core::http::StatusCode;
 use salvo_core::{prelude::StatusError, writing};
 
@@ -27,6 +28,11 @@ pub trait EndpointArgRegister {
     /// Modify the OpenApi components section or current operation information with given argument. This function is called by macros internal.
     fn register(components: &mut Components, operation: &mut Operation, arg: &str);
 }
+impl<T> EndpointArgRegister for salvo_core::extract::State<T> {
+    #[inline]
+    fn register(_components: &mut Components, _operation: &mut Operation, _arg: &str) {}
+}
+
 /// A trait for endpoint return type register.
 pub trait EndpointOutRegister {
     /// Modify the OpenApi components section or current operation information with given argument. This function is called by macros internal.
@@ -64,6 +70,47 @@ where
     }
 }
 
+impl<T> EndpointOutRegister for Option<T>
+where
+    T: EndpointOutRegister + Send,
+{
+    #[inline]
+    fn register(components: &mut Components, operation: &mut Operation) {
+        T::register(components, operation);
+        operation.responses.insert(
+            StatusCode::NOT_FOUND.as_str(),
+            Response::new(StatusCode::NOT_FOUND.canonical_reason().unwrap_or_default()),
+        );
+    }
+}
+impl<T> EndpointOutRegister for (StatusCode, T)
+where
+    T: EndpointOutRegister + Send,
+{
+    #[inline]
+    fn register(components: &mut Components, operation: &mut Operation) {
+        T::register(components, operation);
+    }
+}
+impl<T> EndpointOutRegister for (HeaderMap, T)
+where
+    T: EndpointOutRegister + Send,
+{
+    #[inline]
+    fn register(components: &mut Components, operation: &mut Operation) {
+        T::register(components, operation);
+    }
+}
+impl<T> EndpointOutRegister for (StatusCode, HeaderMap, T)
+where
+    T: EndpointOutRegister + Send,
+{
+    #[inline]
+    fn register(components: &mut Components, operation: &mut Operation) {
+        T::register(components, operation);
+    }
+}
+
 impl EndpointOutRegister for StatusError {
     #[inline]
     fn register(components: &mut Components, operation: &mut Operation) {
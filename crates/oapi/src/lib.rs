@@ -15,6 +15,8 @@ pub use endpoint::{Endpoint, EndpointArgRegister, EndpointOutRegister, EndpointR
 pub mod extract;
 mod routing;
 pub use routing::RouterExt;
+pub mod validation;
+pub use validation::SchemaValidator;
 
 cfg_feature! {
     #![feature ="swagger-ui"]
@@ -336,6 +336,9 @@ impl OpenApi {
     }
 
     /// Consusmes the [`OpenApi`] and returns [`Router`] with the [`OpenApi`] as handler.
+    ///
+    /// With `yaml` feature, the document is served as YAML if the path ends with `.yaml` or `.yml`, like
+    /// `/api-doc/openapi.yaml`, and as JSON otherwise.
     pub fn into_router(self, path: impl Into<String>) -> Router {
         Router::with_path(path.into()).goal(self)
     }
@@ -441,6 +444,23 @@ impl Handler for OpenApi {
         res: &mut salvo_core::Response,
         _ctrl: &mut FlowCtrl,
     ) {
+        #[cfg(feature = "yaml")]
+        if req.uri().path().ends_with(".yaml") || req.uri().path().ends_with(".yml") {
+            match self.to_yaml() {
+                Ok(content) => {
+                    res.headers_mut().insert(
+                        salvo_core::http::header::CONTENT_TYPE,
+                        salvo_core::http::HeaderValue::from_static("application/yaml; charset=utf-8"),
+                    );
+                    res.write_body(content).ok();
+                }
+                Err(e) => {
+                    tracing::error!(error = ?e, "serialize openapi to yaml failed");
+                    res.render(salvo_core::http::StatusError::internal_server_error());
+                }
+            }
+            return;
+        }
         let pretty = req.queries().get("pretty").map(|v| &**v != "false").unwrap_or(false);
         let content = if pretty {
             self.to_pretty_json().unwrap_or_default()
@@ -1140,4 +1160,61 @@ mod tests {
             Bytes::from_static(b"{\n  \"openapi\": \"3.1.0\",\n  \"info\": {\n    \"title\": \"pet api\",\n    \"version\": \"0.1.0\"\n  },\n  \"paths\": {}\n}")
         );
     }
+
+    #[test]
+    fn test_endpoint_summary_and_description() {
+        /// Get pet
+        ///
+        /// Doc comment of the operation.
+        #[salvo_oapi::endpoint(summary = "Find a pet", tags("pets"))]
+        async fn find_pet() {}
+
+        /// Get pet
+        #[salvo_oapi::endpoint(description = concat!("Get a pet", " by id."))]
+        async fn get_pet() {}
+
+        let router = Router::new()
+            .push(Router::with_path("find").get(find_pet))
+            .push(Router::with_path("get").get(get_pet));
+        let doc = OpenApi::new("pet api", "0.1.0").merge_router(&router);
+
+        let find = &doc.paths["/find"].operations[&PathItemType::Get];
+        assert_eq!(find.summary.as_deref(), Some("Find a pet"));
+        assert_eq!(
+            find.description.as_deref(),
+            Some("Get pet\n\nDoc comment of the operation.")
+        );
+        assert_eq!(find.tags, ["pets"]);
+        let get = &doc.paths["/get"].operations[&PathItemType::Get];
+        assert_eq!(get.summary.as_deref(), Some("Get pet"));
+        assert_eq!(get.description.as_deref(), Some("Get a pet by id."));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn test_openapi_handle_yaml() {
+        let doc = OpenApi::new("pet api", "0.1.0");
+
+        let mut req = Request::new();
+        *req.uri_mut() = "http://127.0.0.1:5801/api-doc/openapi.yaml".parse().unwrap();
+
+        let mut depot = Depot::new();
+        let mut res = salvo_core::Response::new();
+        let mut ctrl = FlowCtrl::default();
+        doc.handle(&mut req, &mut depot, &mut res, &mut ctrl).await;
+
+        let bytes = match res.body.take() {
+            ResBody::Once(bytes) => bytes,
+            _ => Bytes::new(),
+        };
+
+        assert_eq!(
+            res.content_type().unwrap().to_string(),
+            "application/yaml; charset=utf-8".to_string()
+        );
+        assert_eq!(
+            bytes,
+            Bytes::from_static(b"openapi: 3.1.0\ninfo:\n  title: pet api\n  version: 0.1.0\npaths: {}\n")
+        );
+    }
 }
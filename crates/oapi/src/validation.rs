@@ -0,0 +1,906 @@
+//! Validation of requests and responses against an [`OpenApi`] document at runtime.
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use regex::Regex;
+use salvo_core::http::body::Body;
+use salvo_core::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use salvo_core::http::{Method, ResBody, StatusCode, StatusError};
+use salvo_core::writing::Json;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::schema::AdditionalProperties;
+use crate::{
+    Components, Content, OpenApi, Operation, ParameterIn, PathItem, PathItemType, RefOr, Required, Schema, SchemaType,
+};
+
+/// Max depth of schema references followed while validating a value.
+const MAX_DEPTH: usize = 64;
+/// Default max size of the JSON request bodies read for validation.
+const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Location of an invalid value in a request.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum ValidationTarget {
+    /// Path parameter.
+    Path,
+    /// Query parameter.
+    Query,
+    /// Header parameter.
+    Header,
+    /// Cookie parameter.
+    Cookie,
+    /// Request or response body.
+    Body,
+}
+
+impl From<&ParameterIn> for ValidationTarget {
+    fn from(parameter_in: &ParameterIn) -> Self {
+        match parameter_in {
+            ParameterIn::Path => Self::Path,
+            ParameterIn::Query => Self::Query,
+            ParameterIn::Header => Self::Header,
+            ParameterIn::Cookie => Self::Cookie,
+        }
+    }
+}
+
+/// A value which does not match the [`OpenApi`] document.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub struct ValidationError {
+    /// Location of the value.
+    #[serde(rename = "in")]
+    pub target: ValidationTarget,
+    /// Name of the parameter, or JSON pointer of the value in the body, like `/tags/0`.
+    pub name: String,
+    /// Description of the error.
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ValidationErrors {
+    errors: Vec<ValidationError>,
+}
+
+struct Route {
+    regex: Regex,
+    literal_len: usize,
+    path: String,
+}
+
+/// Middleware validating requests against an [`OpenApi`] document.
+///
+/// The operation matching the path and method of the request is looked up in the document, then its path, query,
+/// header and cookie parameters and its JSON request body are validated against their schemas. Invalid requests are
+/// rejected with `422 Unprocessable Entity` and a JSON body listing all errors:
+///
+/// ```json
+/// {"errors": [{"in": "query", "name": "limit", "message": "expected integer"}]}
+/// ```
+///
+/// Requests without matching operation are passed through unchecked.
+///
+/// The request body is only read when the operation declares a JSON request body and the request is JSON, up to
+/// [`max_body_size`](SchemaValidator::max_body_size). Other bodies, like multipart forms, are left unread for the
+/// handlers and only checked to be present if they are required.
+///
+/// The `pattern`s of string schemas are compiled once and cached. Values of schemas whose pattern is not a valid
+/// regex are rejected, and the invalid pattern is logged.
+///
+/// With [`validate_responses`](SchemaValidator::validate_responses), JSON responses are also validated against the
+/// schema of their status code, and replaced with `500 Internal Server Error` when they do not match. It is meant to
+/// catch drift between handlers and the document in test and integration environments. Only responses with a body
+/// in memory are validated, streamed responses, like files or server-sent events, are not.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::prelude::*;
+/// use salvo_oapi::extract::QueryParam;
+/// use salvo_oapi::{OpenApi, SchemaValidator};
+///
+/// #[salvo_oapi::endpoint]
+/// async fn hello(name: QueryParam<String, true>) -> String {
+///     format!("Hello, {}!", name.into_inner())
+/// }
+///
+/// let router = Router::new().push(Router::with_path("hello").get(hello));
+/// let doc = OpenApi::new("test api", "0.0.1").merge_router(&router);
+/// let router = router
+///     .hoop(SchemaValidator::new(doc.clone()).validate_responses(cfg!(debug_assertions)))
+///     .push(doc.into_router("/api-doc/openapi.json"));
+/// ```
+pub struct SchemaValidator {
+    openapi: Arc<OpenApi>,
+    routes: Vec<Route>,
+    patterns: PatternCache,
+    validate_responses: bool,
+    max_body_size: usize,
+}
+
+impl SchemaValidator {
+    /// Create new `SchemaValidator` for the given document.
+    pub fn new(openapi: impl Into<Arc<OpenApi>>) -> Self {
+        let openapi = openapi.into();
+        let mut routes = openapi
+            .paths
+            .keys()
+            .filter_map(|path| {
+                let (regex, literal_len) = path_regex(path)?;
+                Some(Route {
+                    regex,
+                    literal_len,
+                    path: path.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        routes.sort_by_key(|route| Reverse(route.literal_len));
+        Self {
+            openapi,
+            routes,
+            patterns: PatternCache::default(),
+            validate_responses: false,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Sets the max size of the JSON request bodies read for validation, default is 16 MiB.
+    ///
+    /// Larger bodies are rejected.
+    #[inline]
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets whether JSON responses are validated against the document, default is `false`.
+    #[inline]
+    pub fn validate_responses(mut self, validate_responses: bool) -> Self {
+        self.validate_responses = validate_responses;
+        self
+    }
+
+    fn find_operation(&self, req: &Request) -> Option<(&PathItem, &Operation)> {
+        let item_type = match *req.method() {
+            Method::GET => PathItemType::Get,
+            Method::POST => PathItemType::Post,
+            Method::PUT => PathItemType::Put,
+            Method::DELETE => PathItemType::Delete,
+            Method::OPTIONS => PathItemType::Options,
+            Method::HEAD => PathItemType::Head,
+            Method::PATCH => PathItemType::Patch,
+            Method::TRACE => PathItemType::Trace,
+            Method::CONNECT => PathItemType::Connect,
+            _ => return None,
+        };
+        let path = req.uri().path();
+        self.routes
+            .iter()
+            .filter(|route| route.regex.is_match(path))
+            .find_map(|route| {
+                let item = self.openapi.paths.get(&route.path)?;
+                item.operations.get(&item_type).map(|operation| (item, operation))
+            })
+    }
+
+    async fn validate_request(
+        &self,
+        item: &PathItem,
+        operation: &Operation,
+        req: &mut Request,
+    ) -> Vec<ValidationError> {
+        let checker = Checker {
+            components: &self.openapi.components,
+            patterns: &self.patterns,
+        };
+        let mut errors = Vec::new();
+        let overridden = operation
+            .parameters
+            .0
+            .iter()
+            .map(|p| (p.name.as_str(), ValidationTarget::from(&p.parameter_in)))
+            .collect::<HashSet<_>>();
+        let parameters = item
+            .parameters
+            .0
+            .iter()
+            .filter(|p| !overridden.contains(&(p.name.as_str(), ValidationTarget::from(&p.parameter_in))))
+            .chain(operation.parameters.0.iter());
+        for parameter in parameters {
+            let target = ValidationTarget::from(&parameter.parameter_in);
+            let raw: Vec<&str> = match parameter.parameter_in {
+                ParameterIn::Path => req
+                    .params()
+                    .get(parameter.name.trim_start_matches(['*', '+', '?']))
+                    .map(|v| v.split(',').collect())
+                    .unwrap_or_default(),
+                ParameterIn::Query => req
+                    .queries()
+                    .get_vec(&parameter.name)
+                    .map(|values| values.iter().map(|v| v.as_str()).collect())
+                    .unwrap_or_default(),
+                ParameterIn::Header => req
+                    .headers()
+                    .get_all(&parameter.name)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .flat_map(|v| v.split(','))
+                    .map(|v| v.trim())
+                    .collect(),
+                ParameterIn::Cookie => req.cookie(&parameter.name).map(|c| vec![c.value()]).unwrap_or_default(),
+            };
+            if raw.is_empty() {
+                if parameter.parameter_in == ParameterIn::Path || parameter.required == Required::True {
+                    errors.push(ValidationError {
+                        target,
+                        name: parameter.name.clone(),
+                        message: "missing required parameter".into(),
+                    });
+                }
+                continue;
+            }
+            if let Some(schema) = &parameter.schema {
+                let value = checker.coerce(&raw, schema);
+                checker.check(&value, schema, target, parameter.name.clone(), 0, &mut errors);
+            }
+        }
+
+        if let Some(request_body) = &operation.request_body {
+            let required = request_body.required == Some(Required::True);
+            let is_json = req
+                .content_type()
+                .map(|ctype| is_json(ctype.essence_str()))
+                .unwrap_or(false);
+            let content = json_content(&request_body.contents).filter(|_| is_json);
+            if req.body().size_hint().exact() == Some(0) {
+                if required {
+                    errors.push(body_error("", "missing required body"));
+                }
+            } else if let Some(content) = content {
+                let payload = match req.payload_with_max_size(self.max_body_size).await {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        errors.push(body_error("", format!("read body failed: {e}")));
+                        return errors;
+                    }
+                };
+                if payload.is_empty() {
+                    if required {
+                        errors.push(body_error("", "missing required body"));
+                    }
+                } else {
+                    match serde_json::from_slice::<Value>(payload) {
+                        Ok(value) => checker.check(
+                            &value,
+                            &content.schema,
+                            ValidationTarget::Body,
+                            "".into(),
+                            0,
+                            &mut errors,
+                        ),
+                        Err(e) => errors.push(body_error("", format!("invalid json: {e}"))),
+                    }
+                }
+            }
+        }
+        errors
+    }
+
+    fn validate_response(&self, operation: &Operation, res: &Response) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let bytes = match &res.body {
+            ResBody::Once(bytes) => Cow::Borrowed(&bytes[..]),
+            ResBody::Chunks(chunks) => Cow::Owned(chunks.iter().flat_map(|chunk| chunk.iter().copied()).collect()),
+            _ => return errors,
+        };
+        if !res
+            .content_type()
+            .map(|ctype| is_json(ctype.essence_str()))
+            .unwrap_or(false)
+        {
+            return errors;
+        }
+        let status = res.status_code.unwrap_or(StatusCode::OK);
+        let code = status.as_str();
+        let range = format!("{}XX", &code[..1]);
+        let response = operation
+            .responses
+            .get(code)
+            .or_else(|| operation.responses.get(&range))
+            .or_else(|| operation.responses.get(&range.to_lowercase()))
+            .or_else(|| operation.responses.get("default"));
+        let response = match response {
+            Some(RefOr::T(response)) => Some(response),
+            Some(RefOr::Ref(reference)) => reference
+                .ref_location
+                .strip_prefix("#/components/responses/")
+                .and_then(|name| self.openapi.components.responses.get(name))
+                .and_then(|response| match response {
+                    RefOr::T(response) => Some(response),
+                    RefOr::Ref(_) => None,
+                }),
+            None => {
+                errors.push(body_error("", format!("status code {code} is not documented")));
+                return errors;
+            }
+        };
+        let Some(content) = response.and_then(|response| json_content(&response.contents)) else {
+            return errors;
+        };
+        match serde_json::from_slice::<Value>(&bytes) {
+            Ok(value) => {
+                let checker = Checker {
+                    components: &self.openapi.components,
+                    patterns: &self.patterns,
+                };
+                checker.check(
+                    &value,
+                    &content.schema,
+                    ValidationTarget::Body,
+                    "".into(),
+                    0,
+                    &mut errors,
+                );
+            }
+            Err(e) => errors.push(body_error("", format!("invalid json: {e}"))),
+        }
+        errors
+    }
+}
+
+#[async_trait]
+impl Handler for SchemaValidator {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let Some((item, operation)) = self.find_operation(req) else {
+            return;
+        };
+        let errors = self.validate_request(item, operation, req).await;
+        if !errors.is_empty() {
+            res.status_code(StatusCode::UNPROCESSABLE_ENTITY);
+            res.render(Json(ValidationErrors { errors }));
+            ctrl.skip_rest();
+            return;
+        }
+        if self.validate_responses {
+            ctrl.call_next(req, depot, res).await;
+            let errors = self.validate_response(operation, res);
+            if !errors.is_empty() {
+                tracing::error!(
+                    method = %req.method(),
+                    path = %req.uri().path(),
+                    errors = ?errors,
+                    "response does not match the openapi document"
+                );
+                let detail = errors
+                    .iter()
+                    .map(|e| format!("{}: {}", e.name, e.message))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                res.headers_mut().remove(CONTENT_TYPE);
+                res.headers_mut().remove(CONTENT_LENGTH);
+                res.body(ResBody::None);
+                res.render(
+                    StatusError::internal_server_error()
+                        .brief("Response does not match the OpenAPI document.")
+                        .detail(detail),
+                );
+            }
+        }
+    }
+}
+
+/// Compiled `pattern`s of string schemas, `None` for invalid patterns.
+#[derive(Default)]
+struct PatternCache(RwLock<HashMap<String, Option<Regex>>>);
+impl PatternCache {
+    fn get(&self, pattern: &str) -> Option<Regex> {
+        if let Some(regex) = self.0.read().unwrap_or_else(|e| e.into_inner()).get(pattern) {
+            return regex.clone();
+        }
+        let regex = Regex::new(pattern)
+            .map_err(|e| tracing::error!(pattern, error = ?e, "invalid pattern in the OpenAPI document"))
+            .ok();
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(pattern.to_owned(), regex.clone());
+        regex
+    }
+}
+
+struct Checker<'a> {
+    components: &'a Components,
+    patterns: &'a PatternCache,
+}
+
+impl Checker<'_> {
+    fn resolve<'s>(&'s self, mut schema: &'s RefOr<Schema>) -> Option<&'s Schema> {
+        for _ in 0..MAX_DEPTH {
+            match schema {
+                RefOr::T(schema) => return Some(schema),
+                RefOr::Ref(reference) => {
+                    let name = reference.ref_location.strip_prefix("#/components/schemas/")?;
+                    schema = self.components.schemas.get(name)?;
+                }
+            }
+        }
+        None
+    }
+
+    /// Converts raw parameter values to the JSON value expected by the schema.
+    fn coerce(&self, raw: &[&str], schema: &RefOr<Schema>) -> Value {
+        match self.resolve(schema) {
+            Some(Schema::Array(array)) => Value::Array(raw.iter().map(|v| self.coerce(&[v], &array.items)).collect()),
+            Some(Schema::Object(object)) => {
+                let raw = raw.join(",");
+                match object.schema_type {
+                    SchemaType::Integer => raw.parse::<i64>().map(Value::from).unwrap_or(Value::String(raw)),
+                    SchemaType::Number => raw
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(serde_json::Number::from_f64)
+                        .map(Value::Number)
+                        .unwrap_or(Value::String(raw)),
+                    SchemaType::Boolean => raw.parse::<bool>().map(Value::Bool).unwrap_or(Value::String(raw)),
+                    _ => Value::String(raw),
+                }
+            }
+            _ => Value::String(raw.join(",")),
+        }
+    }
+
+    fn check(
+        &self,
+        value: &Value,
+        schema: &RefOr<Schema>,
+        target: ValidationTarget,
+        name: String,
+        depth: usize,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        // Unresolvable references are not validated.
+        let Some(schema) = self.resolve(schema) else {
+            return;
+        };
+        let mut error = |message: String| {
+            errors.push(ValidationError {
+                target,
+                name: name.clone(),
+                message,
+            })
+        };
+        match schema {
+            Schema::Array(array) => {
+                if value.is_null() && array.nullable {
+                    return;
+                }
+                let Some(items) = value.as_array() else {
+                    error("expected array".into());
+                    return;
+                };
+                if let Some(min_items) = array.min_items {
+                    if items.len() < min_items {
+                        error(format!("expected at least {min_items} items"));
+                    }
+                }
+                if let Some(max_items) = array.max_items {
+                    if items.len() > max_items {
+                        error(format!("expected at most {max_items} items"));
+                    }
+                }
+                if array.unique_items && items.iter().enumerate().any(|(i, item)| items[..i].contains(item)) {
+                    error("expected unique items".into());
+                }
+                for (i, item) in items.iter().enumerate() {
+                    self.check(item, &array.items, target, format!("{name}/{i}"), depth + 1, errors);
+                }
+            }
+            Schema::Object(object) => {
+                if value.is_null() {
+                    if !object.nullable && !is_any(object) {
+                        error("unexpected null".into());
+                    }
+                    return;
+                }
+                if let Some(enum_values) = &object.enum_values {
+                    if !enum_values.contains(value) {
+                        error("value is not one of the allowed values".into());
+                        return;
+                    }
+                }
+                match object.schema_type {
+                    SchemaType::String => {
+                        let Some(value) = value.as_str() else {
+                            error("expected string".into());
+                            return;
+                        };
+                        let len = value.chars().count();
+                        if let Some(min_length) = object.min_length {
+                            if len < min_length {
+                                error(format!("expected at least {min_length} characters"));
+                            }
+                        }
+                        if let Some(max_length) = object.max_length {
+                            if len > max_length {
+                                error(format!("expected at most {max_length} characters"));
+                            }
+                        }
+                        if let Some(pattern) = &object.pattern {
+                            match self.patterns.get(pattern) {
+                                Some(regex) if regex.is_match(value) => {}
+                                Some(_) => error(format!("expected to match pattern `{pattern}`")),
+                                None => error(format!("pattern `{pattern}` of the schema is invalid")),
+                            }
+                        }
+                    }
+                    SchemaType::Integer | SchemaType::Number => {
+                        let number = match value.as_f64() {
+                            Some(number) if object.schema_type == SchemaType::Number || is_integer(value) => number,
+                            _ => {
+                                let expected = if object.schema_type == SchemaType::Integer {
+                                    "integer"
+                                } else {
+                                    "number"
+                                };
+                                error(format!("expected {expected}"));
+                                return;
+                            }
+                        };
+                        if let Some(minimum) = object.minimum {
+                            if number < minimum {
+                                error(format!("expected a value greater than or equal to {minimum}"));
+                            }
+                        }
+                        if let Some(maximum) = object.maximum {
+                            if number > maximum {
+                                error(format!("expected a value less than or equal to {maximum}"));
+                            }
+                        }
+                        if let Some(minimum) = object.exclusive_minimum {
+                            if number <= minimum {
+                                error(format!("expected a value greater than {minimum}"));
+                            }
+                        }
+                        if let Some(maximum) = object.exclusive_maximum {
+                            if number >= maximum {
+                                error(format!("expected a value less than {maximum}"));
+                            }
+                        }
+                        if let Some(multiple_of) = object.multiple_of {
+                            if multiple_of > 0.0 && (number / multiple_of).fract().abs() > f64::EPSILON {
+                                error(format!("expected a multiple of {multiple_of}"));
+                            }
+                        }
+                    }
+                    SchemaType::Boolean => {
+                        if !value.is_boolean() {
+                            error("expected boolean".into());
+                        }
+                    }
+                    SchemaType::Array => {
+                        if !value.is_array() {
+                            error("expected array".into());
+                        }
+                    }
+                    SchemaType::Object => {
+                        // Schemas of `serde_json::Value` and alike accept any value.
+                        if is_any(object) {
+                            return;
+                        }
+                        let Some(map) = value.as_object() else {
+                            error("expected object".into());
+                            return;
+                        };
+                        if let Some(min_properties) = object.min_properties {
+                            if map.len() < min_properties {
+                                error(format!("expected at least {min_properties} properties"));
+                            }
+                        }
+                        if let Some(max_properties) = object.max_properties {
+                            if map.len() > max_properties {
+                                error(format!("expected at most {max_properties} properties"));
+                            }
+                        }
+                        for key in &object.required {
+                            if !map.contains_key(key) {
+                                errors.push(ValidationError {
+                                    target,
+                                    name: format!("{name}/{key}"),
+                                    message: "missing required property".into(),
+                                });
+                            }
+                        }
+                        for (key, value) in map {
+                            let name = format!("{name}/{key}");
+                            if let Some(schema) = object.properties.get(key) {
+                                self.check(value, schema, target, name, depth + 1, errors);
+                                continue;
+                            }
+                            match object.additional_properties.as_deref() {
+                                Some(AdditionalProperties::FreeForm(false)) => errors.push(ValidationError {
+                                    target,
+                                    name,
+                                    message: "unknown property".into(),
+                                }),
+                                Some(AdditionalProperties::RefOr(schema)) => {
+                                    self.check(value, schema, target, name, depth + 1, errors)
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+            Schema::AllOf(all_of) => {
+                if value.is_null() && all_of.nullable {
+                    return;
+                }
+                for item in &all_of.items {
+                    self.check(value, item, target, name.clone(), depth + 1, errors);
+                }
+            }
+            // Serde enums often produce overlapping `oneOf` schemas, so both are satisfied by any matching schema.
+            Schema::OneOf(one_of) => {
+                if !(value.is_null() && one_of.nullable || self.matches_any(value, &one_of.items, target, depth)) {
+                    error("value does not match any of the schemas".into());
+                }
+            }
+            Schema::AnyOf(any_of) => {
+                if !(value.is_null() && any_of.nullable || self.matches_any(value, &any_of.items, target, depth)) {
+                    error("value does not match any of the schemas".into());
+                }
+            }
+        }
+    }
+
+    fn matches_any(&self, value: &Value, items: &[RefOr<Schema>], target: ValidationTarget, depth: usize) -> bool {
+        items.is_empty()
+            || items.iter().any(|item| {
+                let mut errors = Vec::new();
+                self.check(value, item, target, String::new(), depth + 1, &mut errors);
+                errors.is_empty()
+            })
+    }
+}
+
+fn is_any(object: &crate::Object) -> bool {
+    object.schema_type == SchemaType::Object
+        && object.properties.is_empty()
+        && object.required.is_empty()
+        && object.additional_properties.is_none()
+        && object.enum_values.is_none()
+}
+
+fn is_integer(value: &Value) -> bool {
+    value.is_i64() || value.is_u64() || value.as_f64().map(|v| v.fract() == 0.0).unwrap_or(false)
+}
+
+fn is_json(essence: &str) -> bool {
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+fn json_content(contents: &indexmap::IndexMap<String, Content>) -> Option<&Content> {
+    contents
+        .iter()
+        .find(|(ctype, _)| is_json(ctype))
+        .map(|(_, content)| content)
+}
+
+fn body_error(name: &str, message: impl Into<String>) -> ValidationError {
+    ValidationError {
+        target: ValidationTarget::Body,
+        name: name.into(),
+        message: message.into(),
+    }
+}
+
+/// Builds a regex matching the request paths of an OpenAPI path template, and counts its literal characters.
+fn path_regex(path: &str) -> Option<(Regex, usize)> {
+    let mut pattern = String::from("^");
+    let mut literal_len = 0;
+    let mut rest = path.trim_end_matches('/');
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        literal_len += start;
+        pattern.push_str(&regex::escape(&rest[..start]));
+        let name = &rest[start + 1..end];
+        pattern.push_str(if name.starts_with("**") {
+            ".*"
+        } else if name.starts_with("*+") {
+            ".+"
+        } else if name.starts_with("*?") {
+            "[^/]*"
+        } else if name.starts_with('*') {
+            ".*"
+        } else {
+            "[^/]+"
+        });
+        rest = &rest[end + 1..];
+    }
+    literal_len += rest.len();
+    pattern.push_str(&regex::escape(rest));
+    pattern.push_str("/?$");
+    Regex::new(&pattern).ok().map(|regex| (regex, literal_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::extract::{JsonBody, PathParam, QueryParam};
+    use crate::ToSchema;
+
+    #[derive(Serialize, Deserialize, ToSchema, Debug)]
+    struct Pet {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[salvo_oapi::endpoint]
+    async fn get_pet(id: PathParam<u64>, verbose: QueryParam<bool, false>) -> Json<Pet> {
+        let _ = (id, verbose);
+        Json(Pet {
+            name: "kitty".into(),
+            tags: vec![],
+        })
+    }
+
+    #[salvo_oapi::endpoint]
+    async fn create_pet(pet: JsonBody<Pet>) -> Json<Pet> {
+        Json(pet.into_inner())
+    }
+
+    /// Returns a body which does not match the document.
+    #[salvo_oapi::endpoint(responses((status_code = 200, description = "success", body = Pet)))]
+    async fn drift(res: &mut Response) {
+        res.render(Json(json!({"name": 1})));
+    }
+
+    fn service(validate_responses: bool) -> Service {
+        let router = Router::with_path("pets")
+            .post(create_pet)
+            .push(Router::with_path("drift").get(drift))
+            .push(Router::with_path("<id>").get(get_pet));
+        let doc = OpenApi::new("pet api", "0.1.0").merge_router(&router);
+        let validator = SchemaValidator::new(doc).validate_responses(validate_responses);
+        Service::new(Router::new().hoop(validator).push(router))
+    }
+
+    #[tokio::test]
+    async fn test_validate_request() {
+        let service = service(false);
+
+        let res = TestClient::get("http://127.0.0.1:5801/pets/1?verbose=true")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/pets/kitty?verbose=yes")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNPROCESSABLE_ENTITY));
+        let body: Value = serde_json::from_str(&res.take_string().await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({"errors": [
+                {"in": "path", "name": "id", "message": "expected integer"},
+                {"in": "query", "name": "verbose", "message": "expected boolean"},
+            ]})
+        );
+
+        let res = TestClient::post("http://127.0.0.1:5801/pets")
+            .json(&json!({"name": "kitty", "tags": ["cute"]}))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/pets")
+            .json(&json!({"tags": ["cute", 1]}))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNPROCESSABLE_ENTITY));
+        let body: Value = serde_json::from_str(&res.take_string().await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({"errors": [
+                {"in": "body", "name": "/name", "message": "missing required property"},
+                {"in": "body", "name": "/tags/1", "message": "expected string"},
+            ]})
+        );
+
+        let res = TestClient::get("http://127.0.0.1:5801/unknown").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_validate_request_body_size() {
+        let tags = vec!["cute".repeat(64); 512];
+        let res = TestClient::post("http://127.0.0.1:5801/pets")
+            .json(&json!({"name": "kitty", "tags": tags}))
+            .send(&service(false))
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let router = Router::with_path("pets").post(create_pet);
+        let doc = OpenApi::new("pet api", "0.1.0").merge_router(&router);
+        let service = Service::new(
+            Router::new()
+                .hoop(SchemaValidator::new(doc).max_body_size(1024))
+                .push(router),
+        );
+        let mut res = TestClient::post("http://127.0.0.1:5801/pets")
+            .json(&json!({"name": "kitty", "tags": tags}))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(res.take_string().await.unwrap().contains("read body failed"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_response() {
+        let res = TestClient::get("http://127.0.0.1:5801/pets/drift")
+            .send(&service(false))
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let service = service(true);
+        let res = TestClient::get("http://127.0.0.1:5801/pets/drift").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::INTERNAL_SERVER_ERROR));
+        let res = TestClient::get("http://127.0.0.1:5801/pets/1").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+
+    #[test]
+    fn test_check_pattern() {
+        let components = Components::default();
+        let patterns = PatternCache::default();
+        let checker = Checker {
+            components: &components,
+            patterns: &patterns,
+        };
+        let check = |pattern: &str, value: &str| {
+            let schema = RefOr::T(Schema::Object(
+                crate::Object::new().schema_type(SchemaType::String).pattern(pattern),
+            ));
+            let mut errors = vec![];
+            checker.check(
+                &json!(value),
+                &schema,
+                ValidationTarget::Body,
+                "".into(),
+                0,
+                &mut errors,
+            );
+            errors.into_iter().map(|e| e.message).collect::<Vec<_>>()
+        };
+        assert!(check("^[a-z]+$", "kitty").is_empty());
+        assert_eq!(check("^[a-z]+$", "Kitty"), ["expected to match pattern `^[a-z]+$`"]);
+        assert_eq!(check("[", "kitty"), ["pattern `[` of the schema is invalid"]);
+        assert_eq!(patterns.0.read().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_path_regex() {
+        let (regex, literal_len) = path_regex("/pets/{id}/").unwrap();
+        assert_eq!(literal_len, 6);
+        assert!(regex.is_match("/pets/1"));
+        assert!(regex.is_match("/pets/1/"));
+        assert!(!regex.is_match("/pets/1/toys"));
+        let (regex, _) = path_regex("/files/{**rest}").unwrap();
+        assert!(regex.is_match("/files/a/b.txt"));
+    }
+}
@@ -0,0 +1,818 @@
+//! OAuth2 and OpenID Connect client middleware for Savlo web server framework.
+//!
+//! [`OAuth`] implements the authorization code flow with PKCE: the [`Login`] handler redirects users to the
+//! provider, the [`Callback`] handler checks the state, exchanges the authorization code for tokens and saves the
+//! [`Claims`] of the user in the session, and the [`OAuthGuard`] hoop protects routes requiring a logged in user.
+//!
+//! [`Provider`] has presets for Google and GitHub, and generic OpenID Connect providers can be discovered from
+//! their issuer.
+//!
+//! The middleware keeps its state in the session, so the session handler of `salvo-session` must be added before
+//! it.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use salvo_core::prelude::*;
+//! use salvo_oauth::{OAuth, OAuthDepotExt, Provider};
+//! use salvo_session::{MemoryStore, SessionHandler};
+//!
+//! #[handler]
+//! async fn profile(depot: &mut Depot) -> String {
+//!     let claims = depot.oauth_claims().unwrap();
+//!     format!("Hello {}", claims.name.unwrap_or(claims.subject))
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let secret = b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab";
+//!     let session_handler = SessionHandler::builder(MemoryStore::new(), secret).build().unwrap();
+//!     let oauth = OAuth::new(Provider::google(), "client-id", "https://example.com/oauth/callback")
+//!         .client_secret("client-secret");
+//!     let router = Router::new()
+//!         .hoop(session_handler)
+//!         .push(Router::with_path("oauth/login").get(oauth.login()))
+//!         .push(Router::with_path("oauth/callback").get(oauth.callback()))
+//!         .push(Router::with_path("profile").hoop(oauth.guard()).get(profile));
+//!     let acceptor = TcpListener::new("0.0.0.0:5800").bind().await;
+//!     Server::new(acceptor).serve(router).await;
+//! }
+//! ```
+//!
+//! Read more: <https://salvo.rs>
+#![doc(html_favicon_url = "https://salvo.rs/favicon-32x32.png")]
+#![doc(html_logo_url = "https://salvo.rs/images/logo.svg")]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::TokioExecutor;
+use rand::RngCore;
+use salvo_core::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
+use salvo_core::http::{Method, Request, Response, StatusCode, StatusError};
+use salvo_core::writing::Redirect;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
+use salvo_session::SessionDepotExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+mod provider;
+pub use provider::Provider;
+
+/// Session key of the [`Claims`] of the logged in user.
+pub const OAUTH_CLAIMS_KEY: &str = "salvo.oauth.claims";
+/// Session key of the [`Tokens`] of the logged in user.
+pub const OAUTH_TOKENS_KEY: &str = "salvo.oauth.tokens";
+/// Session key of the state of a login in progress.
+const OAUTH_PENDING_KEY: &str = "salvo.oauth.pending";
+
+/// HTTP client used to call the provider.
+pub type HttpClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+/// Create the default [`HttpClient`], which only connects with HTTPS and trusts the native root certificates.
+pub fn default_http_client() -> HttpClient {
+    let https = HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .expect("no native root CA certificates found")
+        .https_only()
+        .enable_http1()
+        .build();
+    Client::builder(TokioExecutor::new()).build(https)
+}
+
+/// OAuthError
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum OAuthError {
+    /// HTTP client error.
+    #[error("http client error: {0}")]
+    Client(#[from] hyper_util::client::legacy::Error),
+    /// Error happened in hyper.
+    #[error("hyper error: {0}")]
+    Hyper(#[from] salvo_core::hyper::Error),
+    /// Error building a request.
+    #[error("http error: {0}")]
+    Http(#[from] salvo_core::hyper::http::Error),
+    /// Invalid URI.
+    #[error("invalid uri: {0}")]
+    InvalidUri(#[from] salvo_core::http::uri::InvalidUri),
+    /// Serde error.
+    #[error("serde error: {0}")]
+    Serde(#[from] serde_json::Error),
+    /// Base64 decoding error.
+    #[error("base64 decode error: {0}")]
+    Base64(#[from] base64::DecodeError),
+    /// Failed to discover the OpenID Connect configuration.
+    #[error("failed to discover openid connect configuration")]
+    Discovery,
+    /// The provider returned an error.
+    #[error("provider error: {error}")]
+    Provider {
+        /// Error code, like `invalid_grant`.
+        error: String,
+        /// Human readable description of the error.
+        description: Option<String>,
+    },
+    /// The provider responded with an unexpected status code.
+    #[error("provider responded with status {0}")]
+    Status(StatusCode),
+    /// The ID token is invalid.
+    #[error("invalid id token: {0}")]
+    InvalidIdToken(&'static str),
+    /// Neither an ID token nor a userinfo endpoint is available to get the claims.
+    #[error("no claims available from the provider")]
+    MissingClaims,
+}
+
+/// Claims of the logged in user.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[non_exhaustive]
+pub struct Claims {
+    /// Name of the provider.
+    pub provider: String,
+    /// Identifier of the user at the provider, the `sub` claim, or the `id` of the user for OAuth2 providers.
+    pub subject: String,
+    /// Email of the user.
+    pub email: Option<String>,
+    /// Display name of the user.
+    pub name: Option<String>,
+    /// All the claims returned by the provider.
+    pub raw: Map<String, Value>,
+}
+
+impl Claims {
+    fn from_raw(provider: &str, raw: Map<String, Value>) -> Result<Self, OAuthError> {
+        let subject = match raw.get("sub").or_else(|| raw.get("id")) {
+            Some(Value::String(subject)) => subject.clone(),
+            Some(Value::Number(subject)) => subject.to_string(),
+            _ => return Err(OAuthError::MissingClaims),
+        };
+        let string = |key: &str| raw.get(key).and_then(Value::as_str).map(ToOwned::to_owned);
+        Ok(Self {
+            provider: provider.to_owned(),
+            subject,
+            email: string("email"),
+            name: string("name"),
+            raw,
+        })
+    }
+}
+
+/// Tokens returned by the provider.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Tokens {
+    /// Access token, used to call the APIs of the provider.
+    pub access_token: String,
+    /// Refresh token.
+    pub refresh_token: Option<String>,
+    /// ID token of OpenID Connect providers.
+    pub id_token: Option<String>,
+    /// Expiration time of the access token, in seconds since the Unix epoch.
+    pub expires_at: Option<u64>,
+    /// Granted scopes, when they differ from the requested ones.
+    pub scope: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    id_token: Option<String>,
+    expires_in: Option<u64>,
+    scope: Option<String>,
+}
+
+/// State of a login in progress, saved in the session between the login and the callback.
+#[derive(Serialize, Deserialize)]
+struct Pending {
+    state: String,
+    nonce: Option<String>,
+    verifier: String,
+    return_to: Option<String>,
+}
+
+/// Extension of [`Depot`] to get the logged in user, it requires the session handler.
+pub trait OAuthDepotExt {
+    /// Get the claims of the logged in user.
+    fn oauth_claims(&self) -> Option<Claims>;
+    /// Get the tokens of the logged in user.
+    fn oauth_tokens(&self) -> Option<Tokens>;
+}
+
+impl OAuthDepotExt for Depot {
+    #[inline]
+    fn oauth_claims(&self) -> Option<Claims> {
+        self.session()?.get(OAUTH_CLAIMS_KEY)
+    }
+    #[inline]
+    fn oauth_tokens(&self) -> Option<Tokens> {
+        self.session()?.get(OAUTH_TOKENS_KEY)
+    }
+}
+
+/// OAuth2 client of a provider, creating the login and callback handlers and the guard.
+#[derive(Clone)]
+pub struct OAuth {
+    provider: Provider,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    login_path: String,
+    default_return_to: String,
+    http_client: HttpClient,
+}
+
+impl std::fmt::Debug for OAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuth")
+            .field("provider", &self.provider)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &self.client_secret.as_ref().map(|_| ".."))
+            .field("redirect_uri", &self.redirect_uri)
+            .field("scopes", &self.scopes)
+            .field("login_path", &self.login_path)
+            .field("default_return_to", &self.default_return_to)
+            .finish()
+    }
+}
+
+impl OAuth {
+    /// Create a new `OAuth` for a provider, `redirect_uri` is the URL of the [`Callback`] handler, registered at
+    /// the provider.
+    pub fn new(provider: Provider, client_id: impl Into<String>, redirect_uri: impl Into<String>) -> Self {
+        let scopes = provider.scopes.clone();
+        Self {
+            provider,
+            client_id: client_id.into(),
+            client_secret: None,
+            redirect_uri: redirect_uri.into(),
+            scopes,
+            login_path: "/oauth/login".into(),
+            default_return_to: "/".into(),
+            http_client: default_http_client(),
+        }
+    }
+
+    /// Sets the client secret, public clients like mobile or desktop applications only rely on PKCE.
+    #[inline]
+    pub fn client_secret(mut self, client_secret: impl Into<String>) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    /// Sets the requested scopes, defaults to the scopes of the provider.
+    #[inline]
+    pub fn scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the path of the [`Login`] handler, where the [`OAuthGuard`] redirects users who are not logged in.
+    ///
+    /// The default value is `/oauth/login`.
+    #[inline]
+    pub fn login_path(mut self, login_path: impl Into<String>) -> Self {
+        self.login_path = login_path.into();
+        self
+    }
+
+    /// Sets the path users are redirected to after logging in, when the login did not come from the guard.
+    ///
+    /// The default value is `/`.
+    #[inline]
+    pub fn default_return_to(mut self, path: impl Into<String>) -> Self {
+        self.default_return_to = path.into();
+        self
+    }
+
+    /// Sets the HTTP client used to call the provider.
+    #[inline]
+    pub fn http_client(mut self, http_client: HttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Get the provider.
+    #[inline]
+    pub fn provider(&self) -> &Provider {
+        &self.provider
+    }
+
+    /// Create the handler starting the login, it redirects users to the provider.
+    ///
+    /// A local path can be given in the `return_to` query parameter, users are redirected to it once logged in.
+    #[inline]
+    pub fn login(&self) -> Login {
+        Login(Arc::new(self.clone()))
+    }
+
+    /// Create the handler of the redirect URI, it completes the login.
+    #[inline]
+    pub fn callback(&self) -> Callback {
+        Callback(Arc::new(self.clone()))
+    }
+
+    /// Create the hoop protecting routes, `GET` requests of users who are not logged in are redirected to the login,
+    /// other requests are rejected with `401 Unauthorized`.
+    #[inline]
+    pub fn guard(&self) -> OAuthGuard {
+        OAuthGuard {
+            login_path: self.login_path.clone(),
+        }
+    }
+
+    fn authorization_url(&self, pending: &Pending) -> String {
+        let mut scopes = self.scopes.clone();
+        if self.provider.is_oidc() && !scopes.iter().any(|scope| scope == "openid") {
+            scopes.insert(0, "openid".into());
+        }
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(pending.verifier.as_bytes()));
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("state", &pending.state)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+        if !scopes.is_empty() {
+            query.append_pair("scope", &scopes.join(" "));
+        }
+        if let Some(nonce) = &pending.nonce {
+            query.append_pair("nonce", nonce);
+        }
+        let separator = if self.provider.authorization_url.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        format!("{}{separator}{}", self.provider.authorization_url, query.finish())
+    }
+
+    /// Exchanges the authorization code for tokens, and gets the claims of the user.
+    async fn exchange(&self, code: &str, pending: &Pending) -> Result<(Tokens, Claims), OAuthError> {
+        let mut form = form_urlencoded::Serializer::new(String::new());
+        form.append_pair("grant_type", "authorization_code")
+            .append_pair("code", code)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("client_id", &self.client_id)
+            .append_pair("code_verifier", &pending.verifier);
+        if let Some(client_secret) = &self.client_secret {
+            form.append_pair("client_secret", client_secret);
+        }
+        let req = salvo_core::hyper::Request::builder()
+            .method(Method::POST)
+            .uri(&self.provider.token_url)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(ACCEPT, "application/json")
+            .header(USER_AGENT, "salvo-oauth")
+            .body(Full::new(Bytes::from(form.finish())))?;
+        let token: TokenResponse = serde_json::from_value(self.send(req).await?)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let raw = match (&token.id_token, &self.provider.userinfo_url) {
+            (Some(id_token), _) if self.provider.is_oidc() => self.verify_id_token(id_token, pending, now)?,
+            (_, Some(userinfo_url)) => {
+                let req = salvo_core::hyper::Request::builder()
+                    .uri(userinfo_url)
+                    .header(AUTHORIZATION, format!("Bearer {}", token.access_token))
+                    .header(ACCEPT, "application/json")
+                    .header(USER_AGENT, "salvo-oauth")
+                    .body(Full::new(Bytes::new()))?;
+                match self.send(req).await? {
+                    Value::Object(raw) => raw,
+                    _ => return Err(OAuthError::MissingClaims),
+                }
+            }
+            _ => return Err(OAuthError::MissingClaims),
+        };
+        let claims = Claims::from_raw(&self.provider.name, raw)?;
+        let tokens = Tokens {
+            access_token: token.access_token,
+            refresh_token: token.refresh_token,
+            id_token: token.id_token,
+            expires_at: token.expires_in.map(|expires_in| now + expires_in),
+            scope: token.scope,
+        };
+        Ok((tokens, claims))
+    }
+
+    async fn send(&self, req: salvo_core::hyper::Request<Full<Bytes>>) -> Result<Value, OAuthError> {
+        let res = self.http_client.request(req).await?;
+        let status = res.status();
+        let body = res.into_body().collect().await?.to_bytes();
+        let value: Value = serde_json::from_slice(&body).map_err(|e| {
+            if status.is_success() {
+                OAuthError::Serde(e)
+            } else {
+                OAuthError::Status(status)
+            }
+        })?;
+        // Some providers, like GitHub, report errors with a success status.
+        if let Some(error) = value.get("error").and_then(Value::as_str) {
+            return Err(OAuthError::Provider {
+                error: error.to_owned(),
+                description: value
+                    .get("error_description")
+                    .and_then(Value::as_str)
+                    .map(ToOwned::to_owned),
+            });
+        }
+        if !status.is_success() {
+            return Err(OAuthError::Status(status));
+        }
+        Ok(value)
+    }
+
+    /// Verifies the claims of an ID token.
+    ///
+    /// The signature is not checked: the token is received directly from the token endpoint of the provider over
+    /// TLS, which is allowed by OpenID Connect Core, section 3.1.3.7.
+    fn verify_id_token(&self, id_token: &str, pending: &Pending, now: u64) -> Result<Map<String, Value>, OAuthError> {
+        let payload = id_token
+            .split('.')
+            .nth(1)
+            .ok_or(OAuthError::InvalidIdToken("malformed token"))?;
+        let claims: Map<String, Value> = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)?;
+        if claims.get("iss").and_then(Value::as_str) != self.provider.issuer.as_deref() {
+            return Err(OAuthError::InvalidIdToken("issuer mismatch"));
+        }
+        let audience_matched = match claims.get("aud") {
+            Some(Value::String(aud)) => *aud == self.client_id,
+            Some(Value::Array(aud)) => aud.iter().any(|aud| aud.as_str() == Some(&self.client_id)),
+            _ => false,
+        };
+        if !audience_matched {
+            return Err(OAuthError::InvalidIdToken("audience mismatch"));
+        }
+        if claims.get("exp").and_then(Value::as_u64).unwrap_or_default() <= now {
+            return Err(OAuthError::InvalidIdToken("token expired"));
+        }
+        if claims.get("nonce").and_then(Value::as_str) != pending.nonce.as_deref() {
+            return Err(OAuthError::InvalidIdToken("nonce mismatch"));
+        }
+        Ok(claims)
+    }
+}
+
+/// Handler starting the login, created by [`OAuth::login`].
+#[derive(Clone, Debug)]
+pub struct Login(Arc<OAuth>);
+
+#[async_trait]
+impl Handler for Login {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+        let pending = Pending {
+            state: random_token(),
+            nonce: self.0.provider.is_oidc().then(random_token),
+            verifier: random_token(),
+            return_to: req.query::<String>("return_to").filter(|path| is_local_path(path)),
+        };
+        let Some(session) = depot.session_mut() else {
+            tracing::error!("oauth login requires the session handler");
+            res.render(StatusError::internal_server_error());
+            return;
+        };
+        if let Err(e) = session.insert(OAUTH_PENDING_KEY, &pending) {
+            tracing::error!(error = ?e, "failed to save oauth state in session");
+            res.render(StatusError::internal_server_error());
+            return;
+        }
+        match Redirect::with_status_code(StatusCode::FOUND, self.0.authorization_url(&pending)) {
+            Ok(redirect) => res.render(redirect),
+            Err(e) => {
+                tracing::error!(error = ?e, "invalid oauth authorization url");
+                res.render(StatusError::internal_server_error());
+            }
+        }
+    }
+}
+
+/// Handler of the redirect URI completing the login, created by [`OAuth::callback`].
+#[derive(Clone, Debug)]
+pub struct Callback(Arc<OAuth>);
+
+#[async_trait]
+impl Handler for Callback {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+        let Some(session) = depot.session_mut() else {
+            tracing::error!("oauth callback requires the session handler");
+            res.render(StatusError::internal_server_error());
+            return;
+        };
+        // The state can only be used once.
+        let pending = session.get::<Pending>(OAUTH_PENDING_KEY);
+        session.remove(OAUTH_PENDING_KEY);
+
+        if let Some(error) = req.query::<String>("error") {
+            tracing::info!(error = %error, "oauth login refused by the provider");
+            res.render(StatusError::unauthorized().brief("Login refused by the provider."));
+            return;
+        }
+        let (Some(pending), Some(state), Some(code)) =
+            (pending, req.query::<String>("state"), req.query::<String>("code"))
+        else {
+            res.render(StatusError::bad_request().brief("Missing oauth state or code."));
+            return;
+        };
+        if pending.state != state {
+            res.render(StatusError::bad_request().brief("Invalid oauth state."));
+            return;
+        }
+
+        let (tokens, claims) = match self.0.exchange(&code, &pending).await {
+            Ok(result) => result,
+            Err(e @ OAuthError::InvalidIdToken(_)) => {
+                tracing::warn!(error = ?e, "oauth login rejected");
+                res.render(StatusError::unauthorized());
+                return;
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "oauth token exchange failed");
+                res.render(StatusError::bad_gateway());
+                return;
+            }
+        };
+        let Some(session) = depot.session_mut() else {
+            return;
+        };
+        if let Err(e) = session
+            .insert(OAUTH_CLAIMS_KEY, &claims)
+            .and_then(|_| session.insert(OAUTH_TOKENS_KEY, &tokens))
+        {
+            tracing::error!(error = ?e, "failed to save oauth claims in session");
+            res.render(StatusError::internal_server_error());
+            return;
+        }
+        // The user is now logged in, a new session id prevents session fixation.
+        session.regenerate();
+        let return_to = pending.return_to.as_deref().unwrap_or(&self.0.default_return_to);
+        match Redirect::with_status_code(StatusCode::SEE_OTHER, return_to) {
+            Ok(redirect) => res.render(redirect),
+            Err(_) => res.render(Redirect::other("/")),
+        }
+    }
+}
+
+/// Hoop protecting routes from users who are not logged in, created by [`OAuth::guard`].
+#[derive(Clone, Debug)]
+pub struct OAuthGuard {
+    login_path: String,
+}
+
+#[async_trait]
+impl Handler for OAuthGuard {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let logged_in = depot
+            .session()
+            .map(|session| session.get_raw(OAUTH_CLAIMS_KEY).is_some())
+            .unwrap_or(false);
+        if logged_in {
+            return;
+        }
+        ctrl.skip_rest();
+        if matches!(*req.method(), Method::GET | Method::HEAD) {
+            let return_to = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+            let location = format!(
+                "{}?{}",
+                self.login_path,
+                form_urlencoded::Serializer::new(String::new())
+                    .append_pair("return_to", return_to)
+                    .finish()
+            );
+            if let Ok(redirect) = Redirect::with_status_code(StatusCode::FOUND, location) {
+                res.render(redirect);
+                return;
+            }
+        }
+        res.render(StatusError::unauthorized());
+    }
+}
+
+/// Random token for the state, the nonce and the PKCE verifier.
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Returns `true` if the path is local to the site, so it can not be used for open redirects.
+fn is_local_path(path: &str) -> bool {
+    path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::http::header::{COOKIE, LOCATION, SET_COOKIE};
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+    use salvo_session::{MemoryStore, SessionHandler};
+
+    use super::*;
+
+    fn test_http_client() -> HttpClient {
+        let https = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .unwrap()
+            .https_or_http()
+            .enable_http1()
+            .build();
+        Client::builder(TokioExecutor::new()).build(https)
+    }
+
+    #[test]
+    fn test_is_local_path() {
+        assert!(is_local_path("/profile?tab=1"));
+        assert!(!is_local_path("//evil.com"));
+        assert!(!is_local_path("/\\evil.com"));
+        assert!(!is_local_path("https://evil.com"));
+    }
+
+    #[tokio::test]
+    async fn test_oauth_flow() {
+        #[handler]
+        async fn token(req: &mut Request) -> Json<Value> {
+            assert_eq!(req.form::<String>("code").await.unwrap(), "the-code");
+            assert!(req.form::<String>("code_verifier").await.is_some());
+            Json(serde_json::json!({ "access_token": "the-token", "token_type": "bearer", "expires_in": 3600 }))
+        }
+        #[handler]
+        async fn user(req: &mut Request) -> Json<Value> {
+            assert_eq!(req.header::<String>("authorization").unwrap(), "Bearer the-token");
+            Json(serde_json::json!({ "id": 42, "name": "Salvo", "email": "salvo@example.com" }))
+        }
+        let provider_acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let provider_addr = provider_acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(async move {
+            let router = Router::new()
+                .push(Router::with_path("token").post(token))
+                .push(Router::with_path("user").get(user));
+            Server::new(provider_acceptor).serve(router).await;
+        });
+
+        #[handler]
+        async fn profile(depot: &mut Depot) -> String {
+            let claims = depot.oauth_claims().unwrap();
+            format!("{}:{}:{}", claims.provider, claims.subject, claims.name.unwrap())
+        }
+        let provider = Provider::new(
+            "test",
+            "https://provider.example.com/authorize",
+            format!("http://{provider_addr}/token"),
+        )
+        .userinfo_url(format!("http://{provider_addr}/user"));
+        let oauth = OAuth::new(provider, "client-id", "http://127.0.0.1:5800/oauth/callback")
+            .client_secret("secret")
+            .http_client(test_http_client());
+        let session_handler = SessionHandler::builder(
+            MemoryStore::new(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .build()
+        .unwrap();
+        let router = Router::new()
+            .hoop(session_handler)
+            .push(Router::with_path("oauth/login").get(oauth.login()))
+            .push(Router::with_path("oauth/callback").get(oauth.callback()))
+            .push(Router::with_path("profile").hoop(oauth.guard()).get(profile));
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5800/profile").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FOUND));
+        assert_eq!(res.headers()[LOCATION], "/oauth/login?return_to=%2Fprofile");
+        let res = TestClient::post("http://127.0.0.1:5800/profile").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+
+        let res = TestClient::get("http://127.0.0.1:5800/oauth/login?return_to=/profile")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FOUND));
+        let cookie = res.headers().get(SET_COOKIE).unwrap().clone();
+        let location = res.headers()[LOCATION].to_str().unwrap();
+        assert!(location.starts_with("https://provider.example.com/authorize?response_type=code&client_id=client-id"));
+        assert!(location.contains("code_challenge_method=S256"));
+
+        // The state of the login is consumed by a forged callback, a new login is needed.
+        let res = TestClient::get("http://127.0.0.1:5800/oauth/callback?state=forged&code=the-code")
+            .add_header(COOKIE, cookie.clone(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::BAD_REQUEST));
+
+        let res = TestClient::get("http://127.0.0.1:5800/oauth/login?return_to=/profile")
+            .add_header(COOKIE, cookie.clone(), true)
+            .send(&service)
+            .await;
+        let state = res.headers()[LOCATION]
+            .to_str()
+            .unwrap()
+            .split(['?', '&'])
+            .find_map(|pair| pair.strip_prefix("state="))
+            .unwrap()
+            .to_owned();
+        let res = TestClient::get(format!(
+            "http://127.0.0.1:5800/oauth/callback?state={state}&code=the-code"
+        ))
+        .add_header(COOKIE, cookie.clone(), true)
+        .send(&service)
+        .await;
+        assert_eq!(res.status_code, Some(StatusCode::SEE_OTHER));
+        assert_eq!(res.headers()[LOCATION], "/profile");
+        let logged_in_cookie = res.headers().get(SET_COOKIE).unwrap().clone();
+        assert_ne!(logged_in_cookie, cookie);
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/profile")
+            .add_header(COOKIE, logged_in_cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "test:42:Salvo");
+    }
+
+    #[tokio::test]
+    async fn test_discover() {
+        #[handler]
+        async fn configuration(req: &mut Request) -> Json<Value> {
+            let host = req.header::<String>("host").unwrap();
+            Json(serde_json::json!({
+                "issuer": format!("http://{host}"),
+                "authorization_endpoint": format!("http://{host}/authorize"),
+                "token_endpoint": format!("http://{host}/token"),
+            }))
+        }
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(async move {
+            let router = Router::new()
+                .push(Router::with_path(".well-known/openid-configuration").get(configuration))
+                .push(Router::with_path("other/.well-known/openid-configuration").get(configuration));
+            Server::new(acceptor).serve(router).await;
+        });
+
+        let http_client = test_http_client();
+        let provider = Provider::discover("test", format!("http://{addr}/"), &http_client)
+            .await
+            .unwrap();
+        assert_eq!(provider.issuer.as_deref(), Some(format!("http://{addr}").as_str()));
+        assert_eq!(provider.token_url, format!("http://{addr}/token"));
+
+        // The document of another issuer is rejected.
+        let result = Provider::discover("test", format!("http://{addr}/other"), &http_client).await;
+        assert!(matches!(result, Err(OAuthError::Discovery)));
+    }
+
+    #[test]
+    fn test_verify_id_token() {
+        let oauth = OAuth::new(
+            Provider::new(
+                "oidc",
+                "https://id.example.com/authorize",
+                "https://id.example.com/token",
+            )
+            .issuer("https://id.example.com"),
+            "client-id",
+            "https://example.com/callback",
+        );
+        let pending = Pending {
+            state: "state".into(),
+            nonce: Some("nonce".into()),
+            verifier: "verifier".into(),
+            return_to: None,
+        };
+        let token = |claims: Value| format!("e30.{}.sig", URL_SAFE_NO_PAD.encode(claims.to_string()));
+        let claims = serde_json::json!({
+            "iss": "https://id.example.com",
+            "aud": ["client-id"],
+            "sub": "user",
+            "exp": 2000,
+            "nonce": "nonce",
+        });
+        let verified = oauth.verify_id_token(&token(claims.clone()), &pending, 1000).unwrap();
+        assert_eq!(verified["sub"], "user");
+        assert!(oauth.verify_id_token(&token(claims.clone()), &pending, 3000).is_err());
+
+        let mut forged = claims.clone();
+        forged["nonce"] = "other".into();
+        assert!(oauth.verify_id_token(&token(forged), &pending, 1000).is_err());
+        let mut forged = claims;
+        forged["aud"] = "other-client".into();
+        assert!(oauth.verify_id_token(&token(forged), &pending, 1000).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! OAuth2 and OpenID Connect providers.
+use http_body_util::BodyExt;
+use salvo_core::http::uri::Uri;
+use serde::Deserialize;
+
+use crate::{HttpClient, OAuthError};
+
+/// Endpoints and default scopes of an OAuth2 or OpenID Connect provider.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Provider {
+    /// Name of the provider, like `google`, saved in the [`Claims`](crate::Claims).
+    pub name: String,
+    /// URL of the authorization endpoint, where users are redirected to log in.
+    pub authorization_url: String,
+    /// URL of the token endpoint, where the authorization code is exchanged.
+    pub token_url: String,
+    /// URL of the userinfo endpoint, used to get the claims when the provider returns no ID token.
+    pub userinfo_url: Option<String>,
+    /// Issuer of the ID tokens, set for OpenID Connect providers.
+    pub issuer: Option<String>,
+    /// Scopes requested by default.
+    pub scopes: Vec<String>,
+}
+
+impl Provider {
+    /// Create a new OAuth2 `Provider`.
+    #[inline]
+    pub fn new(name: impl Into<String>, authorization_url: impl Into<String>, token_url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            authorization_url: authorization_url.into(),
+            token_url: token_url.into(),
+            userinfo_url: None,
+            issuer: None,
+            scopes: vec![],
+        }
+    }
+
+    /// Sets the URL of the userinfo endpoint.
+    #[inline]
+    pub fn userinfo_url(mut self, userinfo_url: impl Into<String>) -> Self {
+        self.userinfo_url = Some(userinfo_url.into());
+        self
+    }
+
+    /// Sets the issuer, the provider is then treated as an OpenID Connect provider: the `openid` scope and a nonce
+    /// are requested, and the claims are read from the ID token.
+    #[inline]
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// Sets the scopes requested by default.
+    #[inline]
+    pub fn scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns `true` if it is an OpenID Connect provider.
+    #[inline]
+    pub fn is_oidc(&self) -> bool {
+        self.issuer.is_some()
+    }
+
+    /// Google, an OpenID Connect provider.
+    pub fn google() -> Self {
+        Self::new(
+            "google",
+            "https://accounts.google.com/o/oauth2/v2/auth",
+            "https://oauth2.googleapis.com/token",
+        )
+        .userinfo_url("https://openidconnect.googleapis.com/v1/userinfo")
+        .issuer("https://accounts.google.com")
+        .scopes(["openid", "email", "profile"])
+    }
+
+    /// GitHub, an OAuth2 provider, the claims are read from its user API.
+    pub fn github() -> Self {
+        Self::new(
+            "github",
+            "https://github.com/login/oauth/authorize",
+            "https://github.com/login/oauth/access_token",
+        )
+        .userinfo_url("https://api.github.com/user")
+        .scopes(["read:user", "user:email"])
+    }
+
+    /// Discovers a generic OpenID Connect provider from the `.well-known/openid-configuration` document of its
+    /// issuer, like `https://accounts.example.com`.
+    ///
+    /// [`OAuthError::Discovery`] is returned if the `issuer` of the document is not the requested issuer, as
+    /// required by OpenID Connect Discovery, so a document served for another issuer is not trusted.
+    pub async fn discover(
+        name: impl Into<String>,
+        issuer: impl AsRef<str>,
+        http_client: &HttpClient,
+    ) -> Result<Self, OAuthError> {
+        let issuer = issuer.as_ref().trim_end_matches('/');
+        let url = format!("{issuer}/.well-known/openid-configuration");
+        let res = http_client.get(url.parse::<Uri>()?).await?;
+        if !res.status().is_success() {
+            return Err(OAuthError::Discovery);
+        }
+        let body = res.into_body().collect().await?.to_bytes();
+        let document: DiscoveryDocument = serde_json::from_slice(&body)?;
+        if document.issuer.trim_end_matches('/') != issuer {
+            tracing::warn!(issuer, document_issuer = %document.issuer, "openid connect discovery issuer mismatch");
+            return Err(OAuthError::Discovery);
+        }
+        let mut provider = Self::new(name, document.authorization_endpoint, document.token_endpoint)
+            .issuer(document.issuer)
+            .scopes(["openid", "email", "profile"]);
+        provider.userinfo_url = document.userinfo_endpoint;
+        Ok(provider)
+    }
+}
+
+#[derive(Deserialize)]
+struct DiscoveryDocument {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+}
@@ -6,7 +6,9 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 mod metrics;
+mod propagation;
 mod tracing;
 
 pub use metrics::Metrics;
-pub use tracing::Tracing;
+pub use propagation::{HeaderExtractor, HeaderInjector};
+pub use tracing::{OtelDepotExt, Tracing, CONTEXT_KEY};
@@ -0,0 +1,56 @@
+use opentelemetry::propagation::{Extractor, Injector};
+use salvo_core::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// Helper for extracting OpenTelemetry context from the headers of a request.
+pub struct HeaderExtractor<'a>(pub &'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    /// Get a value for a key from the `HeaderMap`.
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    /// Collect all the keys from the `HeaderMap`.
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+/// Helper for injecting OpenTelemetry context into the headers of an outgoing request.
+pub struct HeaderInjector<'a>(pub &'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    /// Set a key and value in the `HeaderMap`, invalid keys or values are ignored.
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_extractor_and_injector() {
+        let mut headers = HeaderMap::new();
+        let mut injector = HeaderInjector(&mut headers);
+        injector.set(
+            "traceparent",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".into(),
+        );
+        injector.set("baggage", "user=alice".into());
+        injector.set("invalid key", "value".into());
+
+        let extractor = HeaderExtractor(&headers);
+        assert_eq!(extractor.get("baggage"), Some("user=alice"));
+        assert_eq!(
+            extractor.get("traceparent"),
+            Some("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01")
+        );
+        let mut keys = extractor.keys();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["baggage", "traceparent"]);
+    }
+}
@@ -1,12 +1,49 @@
-use headers03::{HeaderMap, HeaderName, HeaderValue};
-use opentelemetry::trace::{FutureExt, Span, SpanKind, TraceContextExt, Tracer};
+use opentelemetry::trace::{FutureExt, Span, SpanKind, Status, TraceContextExt, Tracer};
 use opentelemetry::{global, Context, KeyValue};
-use opentelemetry_http::HeaderExtractor;
 use opentelemetry_semantic_conventions::{resource, trace};
+use salvo_core::http::header::{HeaderMap, HOST, USER_AGENT};
 use salvo_core::http::headers::{self, HeaderMapExt};
 use salvo_core::prelude::*;
 
+use crate::propagation::{HeaderExtractor, HeaderInjector};
+
+/// Key for the OpenTelemetry context of the current request in depot.
+pub const CONTEXT_KEY: &str = "::salvo::otel::context";
+
+/// Extension of [`Depot`] to get the OpenTelemetry context stored by [`Tracing`].
+pub trait OtelDepotExt {
+    /// Get the OpenTelemetry context of the current request, with the server span as its active span.
+    fn otel_context(&self) -> Option<&Context>;
+
+    /// Inject the OpenTelemetry context of the current request into the headers of an outgoing request, with the
+    /// global propagator, so the downstream service continues the trace.
+    fn inject_otel_context(&self, headers: &mut HeaderMap);
+}
+
+impl OtelDepotExt for Depot {
+    #[inline]
+    fn otel_context(&self) -> Option<&Context> {
+        self.get::<Context>(CONTEXT_KEY).ok()
+    }
+
+    fn inject_otel_context(&self, headers: &mut HeaderMap) {
+        if let Some(cx) = self.otel_context() {
+            global::get_text_map_propagator(|propagator| {
+                propagator.inject_context(cx, &mut HeaderInjector(headers));
+            });
+        }
+    }
+}
+
 /// Middleware for tracing with OpenTelemetry.
+///
+/// The parent context is extracted from the request headers with the global propagator, so the W3C `traceparent`
+/// and `baggage` headers are honored when it is set to a propagator handling them, like a composite of
+/// `TraceContextPropagator` and `BaggagePropagator`.
+///
+/// A server span named after the method and matched route pattern is started with the HTTP semantic convention
+/// attributes, and its status is set to error for `5xx` responses. The context is stored in the [`Depot`] and can
+/// be read by [`OtelDepotExt`] to propagate it to downstream calls.
 pub struct Tracing<T> {
     tracer: T,
 }
@@ -26,39 +63,55 @@ where
 {
     async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
         let remote_addr = req.remote_addr().to_string();
+        let parent_cx =
+            global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(req.headers())));
 
-        //TODO: Will remove after opentelemetry_http updated
-        let mut headers = HeaderMap::with_capacity(req.headers().len());
-        headers.extend(req.headers().into_iter().map(|(name, value)| {
-            let name = HeaderName::from_bytes(name.as_ref()).expect("Invalid header name");
-            let value = HeaderValue::from_bytes(value.as_ref()).expect("Invalid header value");
-            (name, value)
-        }));
-
-        let parent_cx = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(&headers)));
-
-        let mut attributes = Vec::new();
-        attributes.push(KeyValue::new(resource::TELEMETRY_SDK_NAME, env!("CARGO_CRATE_NAME")));
-        attributes.push(KeyValue::new(
-            resource::TELEMETRY_SDK_VERSION,
-            env!("CARGO_PKG_VERSION"),
-        ));
-        attributes.push(KeyValue::new(resource::TELEMETRY_SDK_LANGUAGE, "rust"));
-        attributes.push(KeyValue::new(trace::HTTP_REQUEST_METHOD, req.method().to_string()));
-        attributes.push(KeyValue::new(trace::URL_FULL, req.uri().to_string()));
+        let mut attributes = vec![
+            KeyValue::new(resource::TELEMETRY_SDK_NAME, env!("CARGO_CRATE_NAME")),
+            KeyValue::new(resource::TELEMETRY_SDK_VERSION, env!("CARGO_PKG_VERSION")),
+            KeyValue::new(resource::TELEMETRY_SDK_LANGUAGE, "rust"),
+            KeyValue::new(trace::HTTP_REQUEST_METHOD, req.method().to_string()),
+            KeyValue::new(trace::URL_FULL, req.uri().to_string()),
+        ];
+        attributes.push(KeyValue::new(trace::URL_PATH, req.uri().path().to_owned()));
+        if let Some(query) = req.uri().query() {
+            attributes.push(KeyValue::new(trace::URL_QUERY, query.to_owned()));
+        }
+        if let Some(scheme) = req.uri().scheme_str() {
+            attributes.push(KeyValue::new(trace::URL_SCHEME, scheme.to_owned()));
+        }
+        let host = req
+            .uri()
+            .host()
+            .or_else(|| req.headers().get(HOST).and_then(|host| host.to_str().ok()));
+        if let Some(host) = host {
+            attributes.push(KeyValue::new(trace::SERVER_ADDRESS, host.to_owned()));
+        }
+        if let Some(route) = req.matched_path() {
+            attributes.push(KeyValue::new(trace::HTTP_ROUTE, route.to_owned()));
+        }
+        if let Some(user_agent) = req.headers().get(USER_AGENT).and_then(|ua| ua.to_str().ok()) {
+            attributes.push(KeyValue::new(trace::USER_AGENT_ORIGINAL, user_agent.to_owned()));
+        }
         attributes.push(KeyValue::new(trace::CLIENT_ADDRESS, remote_addr));
         attributes.push(KeyValue::new(
             trace::NETWORK_PROTOCOL_VERSION,
             format!("{:?}", req.version()),
         ));
+        let span_name = match req.matched_path() {
+            Some(route) => format!("{} {route}", req.method()),
+            None => req.method().to_string(),
+        };
         let mut span = self
             .tracer
-            .span_builder(format!("{} {}", req.method(), req.uri()))
+            .span_builder(span_name)
             .with_kind(SpanKind::Server)
             .with_attributes(attributes)
             .start_with_context(&self.tracer, &parent_cx);
 
         span.add_event("request.started".to_string(), vec![]);
+        let cx = parent_cx.with_span(span);
+        depot.insert(CONTEXT_KEY, cx.clone());
 
         async move {
             ctrl.call_next(req, depot, res).await;
@@ -73,11 +126,46 @@ where
             };
             span.add_event(event.to_string(), vec![]);
             span.set_attribute(KeyValue::new(trace::HTTP_RESPONSE_STATUS_CODE, status.as_u16() as i64));
+            if status.is_server_error() {
+                span.set_status(Status::error(status.to_string()));
+            }
             if let Some(content_length) = res.headers().typed_get::<headers::ContentLength>() {
                 span.set_attribute(KeyValue::new(trace::HTTP_RESPONSE_BODY_SIZE, content_length.0 as i64));
             }
         }
-        .with_context(Context::current_with_span(span))
+        .with_context(cx)
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::noop::NoopTracer;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn downstream(depot: &mut Depot) -> String {
+        let mut headers = HeaderMap::new();
+        depot.inject_otel_context(&mut headers);
+        format!("{}", depot.otel_context().is_some())
+    }
+
+    #[tokio::test]
+    async fn test_tracing_context_in_depot() {
+        let router = Router::new()
+            .hoop(Tracing::new(NoopTracer::new()))
+            .push(Router::with_path("users/<id>").get(downstream));
+        let mut res = TestClient::get("http://127.0.0.1:5801/users/1")
+            .add_header(
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+                true,
+            )
+            .send(router)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "true");
+    }
+}
@@ -0,0 +1,476 @@
+use std::fmt::{self, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use hyper_rustls::HttpsConnectorBuilder;
+use hyper_util::client::legacy::Client as HyperUtilClient;
+use hyper_util::rt::TokioExecutor;
+use salvo_core::http::ReqBody;
+use salvo_core::Error;
+use tokio::task::JoinHandle;
+
+use super::{Lease, LeaseTracker, Upstreams};
+
+/// Strategy electing an upstream in a [`LoadBalancer`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Strategy {
+    /// Upstreams are elected in turn.
+    #[default]
+    RoundRobin,
+    /// The upstream with the fewest requests in flight is elected.
+    LeastConnections,
+    /// Upstreams are elected randomly, in proportion to their weights.
+    Weighted,
+}
+
+struct Upstream {
+    url: String,
+    weight: u32,
+    in_flight: AtomicUsize,
+    fails: AtomicU32,
+    down_until: Mutex<Option<Instant>>,
+    healthy: AtomicBool,
+}
+impl Upstream {
+    fn is_available(&self, now: Instant) -> bool {
+        if !self.healthy.load(Ordering::Relaxed) {
+            return false;
+        }
+        let down_until = self.down_until.lock().unwrap_or_else(|e| e.into_inner());
+        down_until.map_or(true, |until| until <= now)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct HealthCheck {
+    path: String,
+    interval: Duration,
+    timeout: Duration,
+}
+
+struct Inner {
+    upstreams: Vec<Upstream>,
+    strategy: Strategy,
+    next: AtomicUsize,
+    max_fails: u32,
+    fail_timeout: Duration,
+    health_check: Option<HealthCheck>,
+}
+
+/// [`Upstreams`] balancing requests between several upstreams, created by [`LoadBalancer::builder`].
+///
+/// Upstreams failing [`max_fails`](LoadBalancerBuilder::max_fails) requests in a row, with connection errors or
+/// `502`, `503` and `504` responses, are not elected during [`fail_timeout`](LoadBalancerBuilder::fail_timeout).
+/// With [`health_check`](LoadBalancerBuilder::health_check), upstreams are also probed periodically by
+/// [`spawn_health_check`](LoadBalancer::spawn_health_check), and are not elected while their probes fail.
+///
+/// A request is in flight, for [`Strategy::LeastConnections`], until the body of its response is sent or it is
+/// abandoned.
+///
+/// Cloned balancers share the same upstreams and states.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use salvo_core::prelude::*;
+/// use salvo_proxy::{LoadBalancer, Proxy, Strategy};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let balancer = LoadBalancer::builder(Strategy::LeastConnections)
+///     .upstream("http://10.0.0.1:8080")
+///     .upstream("http://10.0.0.2:8080")
+///     .health_check("/health", Duration::from_secs(5))
+///     .build();
+/// balancer.spawn_health_check();
+/// let router = Router::with_path("<**rest>").goal(Proxy::default_hyper_client(balancer));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LoadBalancer {
+    inner: Arc<Inner>,
+}
+impl fmt::Debug for LoadBalancer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadBalancer")
+            .field(
+                "upstreams",
+                &self.inner.upstreams.iter().map(|u| &u.url).collect::<Vec<_>>(),
+            )
+            .field("strategy", &self.inner.strategy)
+            .finish()
+    }
+}
+
+/// A builder for [`LoadBalancer`].
+pub struct LoadBalancerBuilder {
+    inner: Inner,
+}
+impl fmt::Debug for LoadBalancerBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadBalancerBuilder")
+            .field(
+                "upstreams",
+                &self.inner.upstreams.iter().map(|u| &u.url).collect::<Vec<_>>(),
+            )
+            .field("strategy", &self.inner.strategy)
+            .finish()
+    }
+}
+
+impl LoadBalancerBuilder {
+    /// Create a new `LoadBalancerBuilder` without upstreams.
+    #[inline]
+    pub fn new(strategy: Strategy) -> Self {
+        LoadBalancerBuilder {
+            inner: Inner {
+                upstreams: vec![],
+                strategy,
+                next: AtomicUsize::new(0),
+                max_fails: 3,
+                fail_timeout: Duration::from_secs(10),
+                health_check: None,
+            },
+        }
+    }
+
+    /// Adds an upstream with weight `1`.
+    #[inline]
+    pub fn upstream(self, url: impl Into<String>) -> Self {
+        self.weighted_upstream(url, 1)
+    }
+
+    /// Adds an upstream with a weight, which is only used by [`Strategy::Weighted`].
+    ///
+    /// Upstreams with weight `0` are only elected when no other upstream is available.
+    pub fn weighted_upstream(mut self, url: impl Into<String>, weight: u32) -> Self {
+        self.inner.upstreams.push(Upstream {
+            url: url.into(),
+            weight,
+            in_flight: AtomicUsize::new(0),
+            fails: AtomicU32::new(0),
+            down_until: Mutex::new(None),
+            healthy: AtomicBool::new(true),
+        });
+        self
+    }
+
+    /// Sets the number of failures in a row marking an upstream as down, defaults to `3`.
+    #[inline]
+    pub fn max_fails(mut self, max_fails: u32) -> Self {
+        self.inner.max_fails = max_fails.max(1);
+        self
+    }
+
+    /// Sets how long an upstream marked as down is not elected, defaults to 10 seconds.
+    #[inline]
+    pub fn fail_timeout(mut self, fail_timeout: Duration) -> Self {
+        self.inner.fail_timeout = fail_timeout;
+        self
+    }
+
+    /// Probes `path` of each upstream every `interval`, upstreams are healthy if it responds with a
+    /// `2xx` status code within `interval`.
+    #[inline]
+    pub fn health_check(mut self, path: impl Into<String>, interval: Duration) -> Self {
+        self.inner.health_check = Some(HealthCheck {
+            path: path.into(),
+            interval,
+            timeout: interval,
+        });
+        self
+    }
+
+    /// Build a [`LoadBalancer`].
+    #[inline]
+    pub fn build(self) -> LoadBalancer {
+        LoadBalancer {
+            inner: Arc::new(self.inner),
+        }
+    }
+}
+
+impl LoadBalancer {
+    /// Returns a [`LoadBalancerBuilder`] to configure the upstreams.
+    #[inline]
+    pub fn builder(strategy: Strategy) -> LoadBalancerBuilder {
+        LoadBalancerBuilder::new(strategy)
+    }
+
+    /// Spawns a task running the [`health_check`](LoadBalancer::health_check) probes.
+    ///
+    /// Returns `None` if no health check is configured. The task stops when all clones of the balancer are dropped.
+    pub fn spawn_health_check(&self) -> Option<JoinHandle<()>> {
+        let check = self.inner.health_check.clone()?;
+        let inner = Arc::downgrade(&self.inner);
+        Some(tokio::spawn(async move {
+            let https = HttpsConnectorBuilder::new()
+                .with_native_roots()
+                .expect("no native root CA certificates found")
+                .https_or_http()
+                .enable_http1()
+                .build();
+            let client = HyperUtilClient::builder(TokioExecutor::new()).build(https);
+            let mut interval = tokio::time::interval(check.interval);
+            loop {
+                interval.tick().await;
+                let Some(inner) = Weak::upgrade(&inner) else {
+                    break;
+                };
+                let probes = inner.upstreams.iter().map(|upstream| {
+                    let url = format!(
+                        "{}/{}",
+                        upstream.url.trim_end_matches('/'),
+                        check.path.trim_start_matches('/')
+                    );
+                    let client = &client;
+                    async move {
+                        let request = hyper::Request::get(url).body(ReqBody::None).map_err(Error::other)?;
+                        let response = tokio::time::timeout(check.timeout, client.request(request))
+                            .await
+                            .map_err(Error::other)?
+                            .map_err(Error::other)?;
+                        Ok::<_, Error>(response.status().is_success())
+                    }
+                });
+                let results = futures_util::future::join_all(probes).await;
+                for (upstream, result) in inner.upstreams.iter().zip(results) {
+                    let healthy = result.unwrap_or_else(|e| {
+                        tracing::debug!(upstream = %upstream.url, error = ?e, "health check failed");
+                        false
+                    });
+                    if upstream.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+                        tracing::info!(upstream = %upstream.url, healthy, "upstream health changed");
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Returns the urls of the upstreams which can be elected.
+    pub fn available_upstreams(&self) -> Vec<&str> {
+        let now = Instant::now();
+        self.inner
+            .upstreams
+            .iter()
+            .filter(|upstream| upstream.is_available(now))
+            .map(|upstream| upstream.url.as_str())
+            .collect()
+    }
+
+    fn pick(&self, candidates: &[usize]) -> Option<usize> {
+        if candidates.is_empty() {
+            return None;
+        }
+        let upstreams = &self.inner.upstreams;
+        let index = match self.inner.strategy {
+            Strategy::RoundRobin => candidates[self.inner.next.fetch_add(1, Ordering::Relaxed) % candidates.len()],
+            Strategy::LeastConnections => {
+                // Ties are broken in turn, so idle upstreams share the load.
+                let start = self.inner.next.fetch_add(1, Ordering::Relaxed);
+                (0..candidates.len())
+                    .map(|offset| candidates[(start + offset) % candidates.len()])
+                    .min_by_key(|index| upstreams[*index].in_flight.load(Ordering::Relaxed))?
+            }
+            Strategy::Weighted => {
+                let total: u64 = candidates.iter().map(|index| upstreams[*index].weight as u64).sum();
+                if total == 0 {
+                    candidates[fastrand::usize(..candidates.len())]
+                } else {
+                    let mut point = fastrand::u64(..total);
+                    candidates
+                        .iter()
+                        .copied()
+                        .find(|index| {
+                            let weight = upstreams[*index].weight as u64;
+                            if point < weight {
+                                true
+                            } else {
+                                point -= weight;
+                                false
+                            }
+                        })
+                        .unwrap_or(candidates[0])
+                }
+            }
+        };
+        Some(index)
+    }
+
+    fn elect_index(&self) -> Result<usize, Error> {
+        let now = Instant::now();
+        let upstreams = &self.inner.upstreams;
+        let available: Vec<_> = (0..upstreams.len())
+            .filter(|index| upstreams[*index].is_available(now))
+            .collect();
+        let weighted: Vec<_> = available
+            .iter()
+            .copied()
+            .filter(|index| upstreams[*index].weight > 0)
+            .collect();
+        let candidates = if weighted.is_empty() { available } else { weighted };
+        self.pick(&candidates)
+            .ok_or_else(|| Error::other("no upstream is available"))
+    }
+}
+
+impl Upstreams for LoadBalancer {
+    type Error = Error;
+
+    async fn elect(&self) -> Result<&str, Self::Error> {
+        let index = self.elect_index()?;
+        Ok(&self.inner.upstreams[index].url)
+    }
+
+    async fn lease(&self) -> Result<(&str, Lease), Self::Error> {
+        let index = self.elect_index()?;
+        let upstream = &self.inner.upstreams[index];
+        upstream.in_flight.fetch_add(1, Ordering::Relaxed);
+        let lease = Lease::new(UpstreamLease {
+            inner: self.inner.clone(),
+            index,
+        });
+        Ok((&upstream.url, lease))
+    }
+
+    fn count(&self) -> usize {
+        self.inner.upstreams.len()
+    }
+}
+
+/// Lease of an upstream of a [`LoadBalancer`], the request is in flight until it is dropped.
+struct UpstreamLease {
+    inner: Arc<Inner>,
+    index: usize,
+}
+impl LeaseTracker for UpstreamLease {
+    fn report(&self, success: bool) {
+        let upstream = &self.inner.upstreams[self.index];
+        if success {
+            upstream.fails.store(0, Ordering::Relaxed);
+        } else if upstream.fails.fetch_add(1, Ordering::Relaxed) + 1 >= self.inner.max_fails {
+            upstream.fails.store(0, Ordering::Relaxed);
+            tracing::warn!(upstream = %upstream.url, "upstream marked as down");
+            let mut down_until = upstream.down_until.lock().unwrap_or_else(|e| e.into_inner());
+            *down_until = Some(Instant::now() + self.inner.fail_timeout);
+        }
+    }
+}
+impl Drop for UpstreamLease {
+    fn drop(&mut self) {
+        self.inner.upstreams[self.index]
+            .in_flight
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::conn::{Acceptor, TcpListener};
+    use salvo_core::prelude::*;
+
+    use super::*;
+
+    async fn elect_all(balancer: &LoadBalancer, count: usize) -> Vec<String> {
+        let mut elected = vec![];
+        for _ in 0..count {
+            let (upstream, lease) = balancer.lease().await.unwrap();
+            lease.report(true);
+            elected.push(upstream.to_owned());
+        }
+        elected
+    }
+
+    #[tokio::test]
+    async fn test_strategies() {
+        let balancer = LoadBalancer::builder(Strategy::RoundRobin)
+            .upstream("a")
+            .upstream("b")
+            .build();
+        assert_eq!(elect_all(&balancer, 4).await, ["a", "b", "a", "b"]);
+
+        let balancer = LoadBalancer::builder(Strategy::LeastConnections)
+            .upstream("a")
+            .upstream("b")
+            .build();
+        let (first, first_lease) = balancer.lease().await.unwrap();
+        for _ in 0..3 {
+            let (upstream, lease) = balancer.lease().await.unwrap();
+            assert_ne!(upstream, first);
+            // Reporting does not end the request, only dropping the lease does.
+            lease.report(true);
+        }
+        drop(first_lease);
+        let elected = elect_all(&balancer, 2).await;
+        assert!(elected.iter().any(|upstream| upstream == "a"));
+        assert!(elected.iter().any(|upstream| upstream == "b"));
+
+        let balancer = LoadBalancer::builder(Strategy::Weighted)
+            .weighted_upstream("a", 3)
+            .weighted_upstream("b", 1)
+            .weighted_upstream("backup", 0)
+            .build();
+        let elected = elect_all(&balancer, 400).await;
+        let count = elected.iter().filter(|upstream| *upstream == "a").count();
+        assert!(count > 200 && count < 400, "{count}");
+        assert!(!elected.iter().any(|upstream| upstream == "backup"));
+    }
+
+    #[tokio::test]
+    async fn test_passive_failures() {
+        let balancer = LoadBalancer::builder(Strategy::RoundRobin)
+            .upstream("a")
+            .upstream("b")
+            .max_fails(2)
+            .fail_timeout(Duration::from_millis(50))
+            .build();
+        for _ in 0..2 {
+            let (upstream, lease) = balancer.lease().await.unwrap();
+            assert_eq!(upstream, "a");
+            lease.report(false);
+            balancer.lease().await.unwrap().1.report(true);
+        }
+        assert_eq!(balancer.available_upstreams(), ["b"]);
+        assert_eq!(elect_all(&balancer, 2).await, ["b", "b"]);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(balancer.available_upstreams(), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        #[handler]
+        async fn health(res: &mut Response) {
+            res.status_code(StatusCode::SERVICE_UNAVAILABLE);
+        }
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("health").get(health)));
+
+        let down = format!("http://{addr}");
+        let balancer = LoadBalancer::builder(Strategy::RoundRobin)
+            .upstream(&down)
+            .upstream("http://127.0.0.1:1")
+            .health_check("/health", Duration::from_millis(20))
+            .build();
+        let task = balancer.spawn_health_check().unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !balancer.available_upstreams().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(balancer.elect().await.is_err());
+
+        drop(balancer);
+        tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use salvo_core::Error;
+
+use super::{Lease, LeaseTracker, Upstreams};
+
+/// Elections per upstream before giving up, random strategies may elect the same upstream several times before all
+/// upstreams are tried.
+const ELECT_ROUNDS: usize = 8;
+
+/// State of the circuit of an upstream in a [`CircuitBreaker`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum CircuitState {
+    /// Requests are sent to the upstream and their results are recorded.
+    Closed,
+    /// The upstream is considered unhealthy, requests are not sent to it.
+    Open,
+    /// The open duration elapsed, a few probe requests are sent to the upstream to decide if the circuit is closed
+    /// or opened again.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed,
+    Open {
+        until: Instant,
+    },
+    HalfOpen {
+        /// Identifies the probes of this half-open state, so results of older probes are ignored.
+        round: u64,
+        probing: u32,
+        succeeded: u32,
+        probed_at: Instant,
+    },
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: State,
+    window_start: Instant,
+    successes: u32,
+    failures: u32,
+}
+impl Circuit {
+    fn new(now: Instant) -> Self {
+        Self {
+            state: State::Closed,
+            window_start: now,
+            successes: 0,
+            failures: 0,
+        }
+    }
+
+    fn close(&mut self, now: Instant) {
+        *self = Self::new(now);
+    }
+}
+
+struct Inner {
+    failure_ratio: f64,
+    min_requests: u32,
+    window: Duration,
+    open_duration: Duration,
+    half_open_probes: u32,
+    circuits: Mutex<HashMap<String, Circuit>>,
+    rounds: AtomicU64,
+}
+impl Inner {
+    fn next_round(&self) -> u64 {
+        self.rounds.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Records the result of a request, `probe` is the round of the half-open state it probes.
+    fn record(&self, upstream: &str, probe: Option<u64>, success: bool, now: Instant) {
+        let mut circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let circuit = circuits.entry(upstream.to_owned()).or_insert_with(|| Circuit::new(now));
+        match &mut circuit.state {
+            State::Closed => {
+                if now.duration_since(circuit.window_start) > self.window {
+                    circuit.close(now);
+                }
+                if success {
+                    circuit.successes += 1;
+                } else {
+                    circuit.failures += 1;
+                }
+                let total = circuit.successes + circuit.failures;
+                if total >= self.min_requests && circuit.failures as f64 >= total as f64 * self.failure_ratio {
+                    tracing::warn!(upstream, failures = circuit.failures, total, "circuit opened");
+                    circuit.state = State::Open {
+                        until: now + self.open_duration,
+                    };
+                }
+            }
+            State::HalfOpen {
+                round,
+                probing,
+                succeeded,
+                ..
+            } => {
+                // Results of requests sent before the circuit was half-open, or of given up probes.
+                if probe != Some(*round) {
+                    return;
+                }
+                *probing = probing.saturating_sub(1);
+                if !success {
+                    tracing::warn!(upstream, "probe failed, circuit opened again");
+                    circuit.state = State::Open {
+                        until: now + self.open_duration,
+                    };
+                } else {
+                    *succeeded += 1;
+                    if *succeeded >= self.half_open_probes {
+                        tracing::info!(upstream, "circuit closed");
+                        circuit.close(now);
+                    }
+                }
+            }
+            // Results of requests sent before the circuit was opened.
+            State::Open { .. } => {}
+        }
+    }
+
+    /// Gives back the slot of a probe whose result is never recorded.
+    fn release(&self, upstream: &str, probe: u64) {
+        let mut circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(Circuit {
+            state: State::HalfOpen { round, probing, .. },
+            ..
+        }) = circuits.get_mut(upstream)
+        {
+            if *round == probe {
+                *probing = probing.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// [`Upstreams`] wrapper stopping requests to unhealthy upstreams, so a failing upstream is given time to recover
+/// and clients get a fast `503 Service Unavailable` instead of waiting for it.
+///
+/// Each upstream has its own circuit. While it is closed, the results of the requests are recorded, a request fails
+/// with a connection error or a `502`, `503` or `504` response. When the ratio of failed requests in a
+/// [`window`](CircuitBreaker::window) reaches the [`failure_ratio`](CircuitBreaker::failure_ratio), the circuit is
+/// opened, and the upstream is not elected during the [`open_duration`](CircuitBreaker::open_duration). Then the
+/// circuit is half-open: [`half_open_probes`](CircuitBreaker::half_open_probes) requests are sent to the upstream,
+/// the circuit is closed if they all succeed, and opened again if one of them fails. Probes whose requests are
+/// cancelled are replaced by new ones, and probes whose results are never recorded are given up after the open
+/// duration.
+///
+/// If the circuit of the elected upstream is open, another upstream is elected until every upstream is tried, which
+/// is useful with a [`LoadBalancer`](crate::LoadBalancer). The proxy responds with `503 Service Unavailable` if no
+/// upstream can be elected.
+///
+/// Cloned circuit breakers share the same circuits.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use salvo_core::prelude::*;
+/// use salvo_proxy::{CircuitBreaker, Proxy};
+///
+/// let upstreams = CircuitBreaker::new("http://10.0.0.1:8080")
+///     .failure_ratio(0.5)
+///     .open_duration(Duration::from_secs(30));
+/// let router = Router::with_path("<**rest>").goal(Proxy::default_hyper_client(upstreams));
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker<U> {
+    upstreams: U,
+    inner: Arc<Inner>,
+}
+impl<U> fmt::Debug for CircuitBreaker<U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("failure_ratio", &self.inner.failure_ratio)
+            .field("min_requests", &self.inner.min_requests)
+            .field("window", &self.inner.window)
+            .field("open_duration", &self.inner.open_duration)
+            .field("half_open_probes", &self.inner.half_open_probes)
+            .finish()
+    }
+}
+
+impl<U: Upstreams> CircuitBreaker<U> {
+    /// Create a new `CircuitBreaker` wrapping `upstreams`.
+    #[inline]
+    pub fn new(upstreams: U) -> Self {
+        CircuitBreaker {
+            upstreams,
+            inner: Arc::new(Inner {
+                failure_ratio: 0.5,
+                min_requests: 10,
+                window: Duration::from_secs(10),
+                open_duration: Duration::from_secs(30),
+                half_open_probes: 1,
+                circuits: Mutex::new(HashMap::new()),
+                rounds: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("circuit breaker should be configured before it is cloned")
+    }
+
+    /// Sets the ratio of failed requests opening the circuit, from `0` to `1`, defaults to `0.5`.
+    #[inline]
+    pub fn failure_ratio(mut self, failure_ratio: f64) -> Self {
+        self.inner_mut().failure_ratio = failure_ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the minimum number of requests in a window before the circuit can be opened, defaults to `10`.
+    #[inline]
+    pub fn min_requests(mut self, min_requests: u32) -> Self {
+        self.inner_mut().min_requests = min_requests.max(1);
+        self
+    }
+
+    /// Sets the window in which the results of the requests are counted, defaults to 10 seconds.
+    #[inline]
+    pub fn window(mut self, window: Duration) -> Self {
+        self.inner_mut().window = window;
+        self
+    }
+
+    /// Sets how long the circuit stays open before probe requests are sent, defaults to 30 seconds.
+    #[inline]
+    pub fn open_duration(mut self, open_duration: Duration) -> Self {
+        self.inner_mut().open_duration = open_duration;
+        self
+    }
+
+    /// Sets the number of probe requests of a half-open circuit, defaults to `1`.
+    #[inline]
+    pub fn half_open_probes(mut self, half_open_probes: u32) -> Self {
+        self.inner_mut().half_open_probes = half_open_probes.max(1);
+        self
+    }
+
+    /// Get the wrapped upstreams.
+    #[inline]
+    pub fn upstreams(&self) -> &U {
+        &self.upstreams
+    }
+
+    /// Returns the state of the circuit of an upstream.
+    pub fn state(&self, upstream: &str) -> CircuitState {
+        let circuits = self.inner.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        match circuits.get(upstream).map(|circuit| &circuit.state) {
+            None | Some(State::Closed) => CircuitState::Closed,
+            Some(State::Open { until }) if *until > Instant::now() => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Returns `None` if the circuit of the upstream is open, or half-open without probe left.
+    ///
+    /// The request is allowed with `Some(None)` if the circuit is closed, and with the round of the probe if it is
+    /// half-open.
+    fn acquire(&self, upstream: &str, now: Instant) -> Option<Option<u64>> {
+        let mut circuits = self.inner.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(circuit) = circuits.get_mut(upstream) else {
+            return Some(None);
+        };
+        match &mut circuit.state {
+            State::Closed => Some(None),
+            State::Open { until } if *until > now => None,
+            State::Open { .. } => {
+                tracing::info!(upstream, "circuit half-open");
+                let round = self.inner.next_round();
+                circuit.state = State::HalfOpen {
+                    round,
+                    probing: 1,
+                    succeeded: 0,
+                    probed_at: now,
+                };
+                Some(Some(round))
+            }
+            State::HalfOpen {
+                round,
+                probing,
+                succeeded,
+                probed_at,
+            } => {
+                if *probing > 0 && now.duration_since(*probed_at) >= self.inner.open_duration {
+                    tracing::debug!(upstream, probing = *probing, "stale probes given up");
+                    *round = self.inner.next_round();
+                    *probing = 0;
+                    *succeeded = 0;
+                }
+                if *probing + *succeeded < self.inner.half_open_probes {
+                    *probing += 1;
+                    *probed_at = now;
+                    Some(Some(*round))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl<U: Upstreams> Upstreams for CircuitBreaker<U> {
+    type Error = Error;
+
+    async fn elect(&self) -> Result<&str, Self::Error> {
+        let (upstream, _lease) = self.lease().await?;
+        Ok(upstream)
+    }
+
+    async fn lease(&self) -> Result<(&str, Lease), Self::Error> {
+        let count = self.upstreams.count().max(1);
+        let mut tried = HashSet::new();
+        for _ in 0..count * ELECT_ROUNDS {
+            let (upstream, lease) = self.upstreams.lease().await.map_err(Error::other)?;
+            if let Some(probe) = self.acquire(upstream, Instant::now()) {
+                let lease = Lease::new(CircuitLease {
+                    inner: self.inner.clone(),
+                    upstream: upstream.to_owned(),
+                    probe,
+                    reported: AtomicBool::new(false),
+                    lease,
+                });
+                return Ok((upstream, lease));
+            }
+            // The upstream is unhealthy, so a load balancer can mark it as down.
+            lease.report(false);
+            tried.insert(upstream);
+            if tried.len() >= count {
+                break;
+            }
+        }
+        Err(Error::other("circuits of the elected upstreams are open"))
+    }
+
+    #[inline]
+    fn count(&self) -> usize {
+        self.upstreams.count()
+    }
+}
+
+/// Lease recording the result of a request in the circuit of its upstream, and reporting it to the lease of the
+/// wrapped upstreams.
+struct CircuitLease {
+    inner: Arc<Inner>,
+    upstream: String,
+    probe: Option<u64>,
+    reported: AtomicBool,
+    lease: Lease,
+}
+impl LeaseTracker for CircuitLease {
+    fn report(&self, success: bool) {
+        self.reported.store(true, Ordering::Relaxed);
+        self.inner.record(&self.upstream, self.probe, success, Instant::now());
+        self.lease.report(success);
+    }
+}
+impl Drop for CircuitLease {
+    fn drop(&mut self) {
+        // The request of the probe is abandoned, another request can probe the upstream.
+        if let Some(probe) = self.probe {
+            if !self.reported.load(Ordering::Relaxed) {
+                self.inner.release(&self.upstream, probe);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+    use crate::{LoadBalancer, Proxy, Strategy};
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new("a")
+            .min_requests(4)
+            .failure_ratio(0.5)
+            .open_duration(Duration::from_millis(50))
+            .half_open_probes(2);
+        for success in [true, false, true] {
+            breaker.lease().await.unwrap().1.report(success);
+        }
+        assert_eq!(breaker.state("a"), CircuitState::Closed);
+        breaker.lease().await.unwrap().1.report(false);
+        assert_eq!(breaker.state("a"), CircuitState::Open);
+        assert!(breaker.lease().await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(breaker.state("a"), CircuitState::HalfOpen);
+        let (_, first) = breaker.lease().await.unwrap();
+        let (_, second) = breaker.lease().await.unwrap();
+        assert!(breaker.lease().await.is_err());
+        first.report(true);
+        second.report(false);
+        assert_eq!(breaker.state("a"), CircuitState::Open);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        for _ in 0..2 {
+            breaker.lease().await.unwrap().1.report(true);
+        }
+        assert_eq!(breaker.state("a"), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_stale_probe() {
+        let breaker = CircuitBreaker::new("a")
+            .min_requests(1)
+            .open_duration(Duration::from_millis(50));
+        breaker.lease().await.unwrap().1.report(false);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        // The result of this probe is never recorded, like when the lease is leaked.
+        let (_, stale) = breaker.lease().await.unwrap();
+        assert!(breaker.lease().await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let (_, probe) = breaker.lease().await.unwrap();
+        stale.report(false);
+        assert_eq!(breaker.state("a"), CircuitState::HalfOpen);
+        probe.report(true);
+        assert_eq!(breaker.state("a"), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_dropped_probe() {
+        let breaker = CircuitBreaker::new("a")
+            .min_requests(1)
+            .open_duration(Duration::from_millis(50));
+        breaker.lease().await.unwrap().1.report(false);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        // The request of this probe is cancelled, so it is never reported.
+        drop(breaker.lease().await.unwrap());
+        breaker.lease().await.unwrap().1.report(true);
+        assert_eq!(breaker.state("a"), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_balancer() {
+        let balancer = LoadBalancer::builder(Strategy::RoundRobin)
+            .upstream("a")
+            .upstream("b")
+            .max_fails(100)
+            .build();
+        let breaker = CircuitBreaker::new(balancer).min_requests(1);
+        let (upstream, lease) = breaker.lease().await.unwrap();
+        assert_eq!(upstream, "a");
+        lease.report(false);
+        assert_eq!(breaker.state("a"), CircuitState::Open);
+        for _ in 0..4 {
+            let (upstream, lease) = breaker.lease().await.unwrap();
+            assert_eq!(upstream, "b");
+            lease.report(true);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_tries_all_upstreams() {
+        let balancer = LoadBalancer::builder(Strategy::RoundRobin)
+            .upstream("a")
+            .upstream("b")
+            .upstream("c")
+            .upstream("d")
+            .max_fails(100)
+            .build();
+        let breaker = CircuitBreaker::new(balancer).min_requests(1);
+        for (upstream, success) in [("a", false), ("b", false), ("c", false), ("d", true)] {
+            let (elected, lease) = breaker.lease().await.unwrap();
+            assert_eq!(elected, upstream);
+            lease.report(success);
+        }
+        // `a`, `b` and `c` are elected first, but their circuits are open.
+        let (upstream, _) = breaker.lease().await.unwrap();
+        assert_eq!(upstream, "d");
+
+        breaker.lease().await.unwrap().1.report(false);
+        assert!(breaker.lease().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_circuit_open() {
+        let breaker = CircuitBreaker::new("http://127.0.0.1:1").min_requests(1);
+        let router = Router::with_path("<**rest>").goal(Proxy::default_hyper_client(breaker));
+        let service = Service::new(router);
+        let res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::BAD_GATEWAY));
+        let res = TestClient::get("http://127.0.0.1:5801/").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+    }
+}
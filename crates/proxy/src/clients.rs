@@ -10,6 +10,9 @@ use tokio::io::copy_bidirectional;
 use super::{Client, HyperRequest, HyperResponse};
 
 /// A [`Client`] implementation based on [`hyper_util::client::legacy::Client`].
+///
+/// Connections to upstreams are pooled and reused, both `http` and `https` upstreams are supported, TLS
+/// certificates of upstreams are verified with the native root certificates.
 pub struct HyperClient {
     inner: HyperUtilClient<HttpsConnector<HttpConnector>, ReqBody>,
 }
@@ -18,7 +21,7 @@ impl Default for HyperClient {
         let https = HttpsConnectorBuilder::new()
             .with_native_roots()
             .expect("no native root CA certificates found")
-            .https_only()
+            .https_or_http()
             .enable_http1()
             .build();
         Self {
@@ -42,6 +45,10 @@ impl Client for HyperClient {
         request_upgraded: Option<OnUpgrade>,
     ) -> Result<HyperResponse, Self::Error> {
         let request_upgrade_type = crate::get_upgrade_type(proxied_request.headers()).map(|s| s.to_owned());
+        if request_upgrade_type.is_some() && request_upgraded.is_none() {
+            // Upgrades are not supported by the connection, like HTTP/2 connections.
+            return Err(Error::other("request does not have an upgrade extension."));
+        }
 
         let mut response = self.inner.request(proxied_request).await.map_err(Error::other)?;
 
@@ -57,7 +64,7 @@ impl Client for HyperClient {
                                 let mut response_upgraded = TokioIo::new(response_upgraded);
                                 if let Err(e) = copy_bidirectional(&mut response_upgraded, &mut request_upgraded).await
                                 {
-                                    tracing::error!(error = ?e, "coping between upgraded connections failed.");
+                                    tracing::error!(error = ?e, "copying between upgraded connections failed.");
                                 }
                             }
                             Err(e) => {
@@ -0,0 +1,239 @@
+use salvo_core::http::header::{HeaderName, AUTHORIZATION, COOKIE};
+use salvo_core::http::uri::Uri;
+use salvo_core::http::{HeaderValue, ReqBody, StatusCode, StatusError};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+
+use super::{add_forwarded_headers, remove_hop_by_hop_headers, Client, HyperClient};
+
+const X_FORWARDED_METHOD: HeaderName = HeaderName::from_static("x-forwarded-method");
+const X_FORWARDED_URI: HeaderName = HeaderName::from_static("x-forwarded-uri");
+
+/// Middleware delegating authorization of requests to an external service, like `auth_request` of nginx.
+///
+/// For each request, a `GET` request is sent to the authorization service with the
+/// [`request_headers`](ForwardAuth::request_header) of the original request, which default to `Authorization` and
+/// `Cookie`, and `X-Forwarded-Method`, `X-Forwarded-Uri`, `X-Forwarded-Proto`, `X-Forwarded-Host` and
+/// `X-Forwarded-For` headers describing it.
+///
+/// - With a `2xx` response, the request is passed through, with the
+///   [`response_headers`](ForwardAuth::response_header) of the response, like `X-Auth-User`, copied into it. These
+///   headers are always removed from the original request, so clients can not forge them.
+/// - With a `3xx`, `401` or `403` response, the response is sent to the client, so it can be redirected to a
+///   login page or asked for credentials.
+/// - Other responses and errors are rejected with `500 Internal Server Error`.
+///
+/// # Example
+///
+/// ```no_run
+/// use salvo_core::http::header::HeaderName;
+/// use salvo_core::prelude::*;
+/// use salvo_proxy::ForwardAuth;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let auth = ForwardAuth::new("http://auth.internal/verify").response_header(HeaderName::from_static("x-auth-user"));
+/// let router = Router::with_path("admin").hoop(auth);
+/// # }
+/// ```
+#[non_exhaustive]
+pub struct ForwardAuth<C = HyperClient> {
+    /// Url of the authorization service.
+    pub url: Uri,
+    /// [`Client`] calling the authorization service.
+    pub client: C,
+    /// Headers of the original request sent to the authorization service.
+    pub request_headers: Vec<HeaderName>,
+    /// Headers of the authorization response copied into the original request.
+    pub response_headers: Vec<HeaderName>,
+}
+
+impl ForwardAuth<HyperClient> {
+    /// Create new `ForwardAuth` which use default hyper util client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `url` is not a valid url.
+    #[inline]
+    pub fn new(url: impl AsRef<str>) -> Self {
+        ForwardAuth::with_client(url, HyperClient::default())
+    }
+}
+
+impl<C> ForwardAuth<C>
+where
+    C: Client,
+{
+    /// Create new `ForwardAuth` with a [`Client`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `url` is not a valid url.
+    pub fn with_client(url: impl AsRef<str>, client: C) -> Self {
+        ForwardAuth {
+            url: url.as_ref().parse().expect("authorization service url should be valid"),
+            client,
+            request_headers: vec![AUTHORIZATION, COOKIE],
+            response_headers: vec![],
+        }
+    }
+
+    /// Adds a header of the original request sent to the authorization service.
+    #[inline]
+    pub fn request_header(mut self, name: HeaderName) -> Self {
+        self.request_headers.push(name);
+        self
+    }
+
+    /// Adds a header of the authorization response copied into the original request, like `X-Auth-User`.
+    #[inline]
+    pub fn response_header(mut self, name: HeaderName) -> Self {
+        self.response_headers.push(name);
+        self
+    }
+
+    fn build_auth_request(&self, req: &Request) -> Result<hyper::Request<ReqBody>, hyper::http::Error> {
+        let mut builder = hyper::Request::get(self.url.clone());
+        if let Some(headers) = builder.headers_mut() {
+            for name in &self.request_headers {
+                for value in req.headers().get_all(name) {
+                    headers.append(name, value.clone());
+                }
+            }
+            add_forwarded_headers(headers, req);
+            headers.insert(X_FORWARDED_METHOD, HeaderValue::from_str(req.method().as_str())?);
+            let uri = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+            headers.insert(X_FORWARDED_URI, HeaderValue::from_str(uri)?);
+        }
+        builder.body(ReqBody::None)
+    }
+}
+
+#[async_trait]
+impl<C> Handler for ForwardAuth<C>
+where
+    C: Client,
+{
+    async fn handle(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        for name in &self.response_headers {
+            req.headers_mut().remove(name);
+        }
+        let auth_request = match self.build_auth_request(req) {
+            Ok(auth_request) => auth_request,
+            Err(e) => {
+                tracing::error!(error = ?e, "build authorization request failed");
+                reject(res, ctrl);
+                return;
+            }
+        };
+        let auth_response = match self.client.execute(auth_request, None).await {
+            Ok(auth_response) => auth_response,
+            Err(e) => {
+                tracing::error!(error = ?e, "call authorization service failed");
+                reject(res, ctrl);
+                return;
+            }
+        };
+        let status = auth_response.status();
+        if status.is_success() {
+            for name in &self.response_headers {
+                for value in auth_response.headers().get_all(name) {
+                    req.headers_mut().append(name, value.clone());
+                }
+            }
+        } else if status.is_redirection() || status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            let (mut parts, body) = auth_response.into_parts();
+            remove_hop_by_hop_headers(&mut parts.headers);
+            res.status_code(status);
+            res.headers_mut().extend(parts.headers);
+            res.body(body);
+            ctrl.skip_rest();
+        } else {
+            tracing::error!(status = %status, "unexpected authorization response");
+            reject(res, ctrl);
+        }
+    }
+}
+
+fn reject(res: &mut Response, ctrl: &mut FlowCtrl) {
+    res.render(StatusError::internal_server_error().brief("Authorization service failed."));
+    ctrl.skip_rest();
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::conn::{Acceptor, TcpListener};
+    use salvo_core::http::header::{LOCATION, WWW_AUTHENTICATE};
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+
+    #[handler]
+    async fn verify(req: &mut Request, res: &mut Response) {
+        let uri = req.header::<String>("x-forwarded-uri").unwrap_or_default();
+        match req.header::<String>("authorization").as_deref() {
+            Some("Bearer alice") if uri == "/admin?page=1" => {
+                res.add_header("x-auth-user", "alice", true).unwrap();
+            }
+            Some("Bearer alice") => {
+                res.status_code(StatusCode::FORBIDDEN);
+            }
+            Some(_) => {
+                res.render(Redirect::found("/login"));
+            }
+            None => {
+                res.status_code(StatusCode::UNAUTHORIZED);
+                res.add_header(WWW_AUTHENTICATE, "Bearer", true).unwrap();
+            }
+        }
+    }
+
+    #[handler]
+    async fn admin(req: &mut Request) -> String {
+        req.headers()
+            .get_all("x-auth-user")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    #[tokio::test]
+    async fn test_forward_auth() {
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("verify").get(verify)));
+
+        let auth =
+            ForwardAuth::new(format!("http://{addr}/verify")).response_header(HeaderName::from_static("x-auth-user"));
+        let service = Service::new(Router::with_path("admin").hoop(auth).get(admin).post(admin));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/admin?page=1")
+            .add_header("authorization", "Bearer alice", true)
+            .add_header("x-auth-user", "root", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "alice");
+
+        let res = TestClient::get("http://127.0.0.1:5801/admin").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(res.headers()[WWW_AUTHENTICATE], "Bearer");
+        let res = TestClient::post("http://127.0.0.1:5801/admin?page=2")
+            .add_header("authorization", "Bearer alice", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+        let res = TestClient::get("http://127.0.0.1:5801/admin")
+            .add_header("authorization", "Bearer bob", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::FOUND));
+        assert_eq!(res.headers()[LOCATION], "/login");
+
+        let auth = ForwardAuth::new("http://127.0.0.1:1/verify");
+        let service = Service::new(Router::with_path("admin").hoop(auth).get(admin));
+        let res = TestClient::get("http://127.0.0.1:5801/admin").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
@@ -7,21 +7,57 @@
 
 use std::convert::{Infallible, TryFrom};
 use std::error::Error as StdError;
+use std::fmt::{self, Formatter};
 use std::future::Future;
+use std::io::Error as IoError;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
 
+use futures_util::stream::{self, StreamExt};
+use http_body_util::BodyExt;
+use hyper::body::{Body, Bytes, Frame, SizeHint};
 use hyper::upgrade::OnUpgrade;
 use percent_encoding::{utf8_percent_encode, CONTROLS};
-use salvo_core::http::header::{HeaderMap, HeaderName, HeaderValue, CONNECTION, HOST, UPGRADE};
+use salvo_core::http::body::BytesFrame;
+use salvo_core::http::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH,
+    CONTENT_TYPE, ETAG, HOST, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE,
+};
 use salvo_core::http::uri::Uri;
-use salvo_core::http::{ReqBody, ResBody, StatusCode};
-use salvo_core::{async_trait, BoxedError, Depot, Error, FlowCtrl, Handler, Request, Response};
+use salvo_core::http::{deadline, ReqBody, ResBody, StatusCode, StatusError};
+use salvo_core::{async_trait, BoxedError, Depot, Error, FlowCtrl, Handler, IntoVecString, Request, Response};
 
+mod balancer;
+mod circuit_breaker;
 mod clients;
+mod forward_auth;
+mod mirror;
+mod retry;
+pub use balancer::*;
+pub use circuit_breaker::{CircuitBreaker, CircuitState};
 pub use clients::*;
+pub use forward_auth::ForwardAuth;
+pub use mirror::{Mirror, X_SHADOW_REQUEST};
+pub use retry::{RetryBudget, RetryPolicy};
 
 type HyperRequest = hyper::Request<ReqBody>;
 type HyperResponse = hyper::Response<ResBody>;
 
+/// Headers meaningful only for a single connection, they are not forwarded.
+const HOP_BY_HOP_HEADERS: [HeaderName; 7] = [
+    CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    PROXY_AUTHENTICATE,
+    PROXY_AUTHORIZATION,
+    TE,
+    TRAILER,
+    TRANSFER_ENCODING,
+];
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
 /// Encode url path. This can be used when build your custom url path getter.
 #[inline]
 pub(crate) fn encode_url_path(path: &str) -> String {
@@ -49,6 +85,84 @@ pub trait Upstreams: Send + Sync + 'static {
     type Error: StdError + Send + Sync + 'static;
     /// Elect a upstream to process current request.
     fn elect(&self) -> impl Future<Output = Result<&str, Self::Error>> + Send;
+
+    /// Elect a upstream like [`elect`](Upstreams::elect), with a [`Lease`] tracking the request sent to it.
+    ///
+    /// The proxy reports the outcome of the request to the lease, and drops it when the response body ends or the
+    /// request is abandoned. Defaults to a lease tracking nothing.
+    fn lease(&self) -> impl Future<Output = Result<(&str, Lease), Self::Error>> + Send {
+        async move { Ok((self.elect().await?, Lease::default())) }
+    }
+
+    /// Returns the number of upstreams, so wrappers like [`CircuitBreaker`] can elect again until all of them are
+    /// tried. Defaults to `1`.
+    fn count(&self) -> usize {
+        1
+    }
+}
+
+/// Receives the outcome of a request sent to an upstream, see [`Lease`].
+pub trait LeaseTracker: Send + Sync + 'static {
+    /// Called when the response head of the upstream is received, or the request to it failed.
+    ///
+    /// `success` is `false` for connection errors and `502`, `503` and `504` responses, so upstreams can be
+    /// marked as down.
+    fn report(&self, success: bool);
+}
+
+/// Tracks a request sent to an upstream elected by [`Upstreams::lease`].
+///
+/// The request ends when the lease is dropped, which happens when the response body ends, or when the request is
+/// abandoned, like when the client disconnects.
+#[derive(Default)]
+pub struct Lease {
+    tracker: Option<Box<dyn LeaseTracker>>,
+}
+impl Lease {
+    /// Create a new `Lease` reporting to `tracker`, which is dropped when the request ends.
+    #[inline]
+    pub fn new(tracker: impl LeaseTracker) -> Self {
+        Lease {
+            tracker: Some(Box::new(tracker)),
+        }
+    }
+
+    /// Reports the outcome of the request.
+    #[inline]
+    pub fn report(&self, success: bool) {
+        if let Some(tracker) = &self.tracker {
+            tracker.report(success);
+        }
+    }
+}
+impl fmt::Debug for Lease {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lease")
+            .field("tracked", &self.tracker.is_some())
+            .finish()
+    }
+}
+
+/// Response body holding the [`Lease`] of its upstream until it ends.
+struct LeasedBody {
+    body: ResBody,
+    _lease: Lease,
+}
+impl Body for LeasedBody {
+    type Data = Bytes;
+    type Error = BoxedError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, BoxedError>>> {
+        Pin::new(&mut self.body).poll_frame(cx).map_err(BoxedError::from)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
 }
 impl Upstreams for &'static str {
     type Error = Infallible;
@@ -73,6 +187,9 @@ impl<const N: usize> Upstreams for [&'static str; N] {
         let index = fastrand::usize(..self.len());
         Ok(self[index])
     }
+    fn count(&self) -> usize {
+        N
+    }
 }
 
 impl<T> Upstreams for Vec<T>
@@ -87,11 +204,20 @@ where
         let index = fastrand::usize(..self.len());
         Ok(self[index].as_ref())
     }
+    fn count(&self) -> usize {
+        self.len()
+    }
 }
 
 /// Url part getter. You can use this to get the proxied url path or query.
 pub type UrlPartGetter = Box<dyn Fn(&Request, &Depot) -> Option<String> + Send + Sync + 'static>;
 
+/// Headers rewriter. You can use this to add, change or remove headers of proxied responses.
+pub type HeadersRewriter = Box<dyn Fn(&Request, &mut HeaderMap) + Send + Sync + 'static>;
+
+/// Body rewriter. You can use this to change bodies of proxied responses, it returns the new body.
+pub type BodyRewriter = Box<dyn Fn(&Request, Bytes) -> Bytes + Send + Sync + 'static>;
+
 /// Default url path getter. This getter will get the url path from request wildcard param, like `<**rest>`, `<*+rest>`.
 pub fn default_url_path_getter(req: &Request, _depot: &Depot) -> Option<String> {
     let param = req.params().iter().find(|(key, _)| key.starts_with('*'));
@@ -107,6 +233,15 @@ pub fn default_url_query_getter(req: &Request, _depot: &Depot) -> Option<String>
 }
 
 /// Handler that can proxy request to other server.
+///
+/// Upgrade requests, like WebSocket handshakes, are forwarded with their `Connection` and `Upgrade` headers. When
+/// the upstream accepts them with `101 Switching Protocols`, the data of both connections is copied in both directions
+/// until one of them is closed.
+///
+/// If the request has a [deadline](Request::deadline), the remaining time is sent to the upstream with the
+/// `X-Request-Timeout` header, and `504 Gateway Timeout` is rendered if the upstream does not respond in time.
+///
+/// With a [`RetryPolicy`], requests failing with transient errors are retried.
 #[non_exhaustive]
 pub struct Proxy<U, C>
 where
@@ -121,6 +256,18 @@ where
     pub url_path_getter: UrlPartGetter,
     /// Url query getter.
     pub url_query_getter: UrlPartGetter,
+    /// Whether `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers are added.
+    pub forwarded_headers: bool,
+    /// Response headers rewriter.
+    pub headers_rewriter: Option<HeadersRewriter>,
+    /// Response body rewriter.
+    pub body_rewriter: Option<BodyRewriter>,
+    /// Content types of the response bodies passed to the body rewriter.
+    pub rewrite_content_types: Vec<String>,
+    /// Maximum size of the response bodies passed to the body rewriter.
+    pub rewrite_max_size: usize,
+    /// Retries of failed requests.
+    pub retry_policy: Option<RetryPolicy>,
 }
 impl<U> Proxy<U, HyperClient>
 where
@@ -146,6 +293,12 @@ where
             client,
             url_path_getter: Box::new(default_url_path_getter),
             url_query_getter: Box::new(default_url_query_getter),
+            forwarded_headers: true,
+            headers_rewriter: None,
+            body_rewriter: None,
+            rewrite_content_types: vec!["text/html".into()],
+            rewrite_max_size: 1024 * 1024,
+            retry_policy: None,
         }
     }
 
@@ -169,6 +322,69 @@ where
         self
     }
 
+    /// Sets whether `X-Forwarded-*` headers are added to proxied requests, defaults to `true`.
+    ///
+    /// The client address is appended to `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` are
+    /// set to the scheme and host of the request received by this server.
+    #[inline]
+    pub fn forwarded_headers(mut self, forwarded_headers: bool) -> Self {
+        self.forwarded_headers = forwarded_headers;
+        self
+    }
+
+    /// Set response headers rewriter, it is called after hop-by-hop headers are removed.
+    #[inline]
+    pub fn headers_rewriter<R>(mut self, headers_rewriter: R) -> Self
+    where
+        R: Fn(&Request, &mut HeaderMap) + Send + Sync + 'static,
+    {
+        self.headers_rewriter = Some(Box::new(headers_rewriter));
+        self
+    }
+
+    /// Set response body rewriter, like rewriting absolute urls in HTML pages or injecting a script.
+    ///
+    /// Only bodies of the [`rewrite_content_types`](Proxy::rewrite_content_types) are buffered and rewritten. Bodies
+    /// larger than [`rewrite_max_size`](Proxy::rewrite_max_size) are streamed unchanged, which is logged at debug
+    /// level.
+    ///
+    /// The rewriter gets the whole body at once: nothing is sent to the client before the upstream body ends, and
+    /// each response being rewritten holds up to `rewrite_max_size` bytes in memory. Do not list content types of
+    /// long lived streams like `text/event-stream` in `rewrite_content_types`, they would be held back. Requests whose `Accept` header matches one of the rewritten content types ask for uncompressed
+    /// responses, so the rewriter gets plain bodies. The `ETag` of rewritten responses is made weak, as their bodies
+    /// are no longer the ones of the upstream.
+    #[inline]
+    pub fn body_rewriter<R>(mut self, body_rewriter: R) -> Self
+    where
+        R: Fn(&Request, Bytes) -> Bytes + Send + Sync + 'static,
+    {
+        self.body_rewriter = Some(Box::new(body_rewriter));
+        self
+    }
+
+    /// Sets content types of the response bodies passed to the body rewriter, defaults to `text/html`.
+    #[inline]
+    pub fn rewrite_content_types(mut self, content_types: impl IntoVecString) -> Self {
+        self.rewrite_content_types = content_types.into_vec_string();
+        self
+    }
+
+    /// Sets maximum size of the response bodies passed to the body rewriter, defaults to 1 MiB.
+    ///
+    /// It bounds the memory buffered for each rewritten response, larger bodies are streamed unchanged.
+    #[inline]
+    pub fn rewrite_max_size(mut self, size: usize) -> Self {
+        self.rewrite_max_size = size;
+        self
+    }
+
+    /// Sets the [`RetryPolicy`] of failed requests, requests are not retried by default.
+    #[inline]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
     /// Get upstreams list.
     #[inline]
     pub fn upstreams(&self) -> &U {
@@ -191,8 +407,13 @@ where
         &mut self.client
     }
 
-    async fn build_proxied_request(&self, req: &mut Request, depot: &Depot) -> Result<HyperRequest, Error> {
-        let upstream = self.upstreams.elect().await.map_err(Error::other)?;
+    async fn build_proxied_request(
+        &self,
+        upstream: &str,
+        req: &Request,
+        depot: &Depot,
+        body: ReqBody,
+    ) -> Result<HyperRequest, Error> {
         if upstream.is_empty() {
             tracing::error!("upstreams is empty");
             return Err(Error::other("upstreams is empty"));
@@ -219,34 +440,139 @@ where
             format!("{}/{}", upstream, rest)
         };
         let forward_url: Uri = TryFrom::try_from(forward_url).map_err(Error::other)?;
+        let mut headers = req.headers().clone();
+        headers.remove(HOST);
+        remove_hop_by_hop_headers(&mut headers);
+        if self.forwarded_headers {
+            add_forwarded_headers(&mut headers, req);
+        }
+        if self.body_rewriter.is_some() && self.accepts_rewritable(&headers) {
+            headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        }
+        if let Some(remaining) = req.remaining_time() {
+            deadline::write_timeout_headers(&mut headers, remaining);
+        }
+        // The port is part of `Host` header unless it is the default one.
+        if let Some(host) = forward_url
+            .authority()
+            .and_then(|authority| HeaderValue::from_str(authority.as_str()).ok())
+        {
+            headers.insert(HOST, host);
+        }
         let mut build = hyper::Request::builder().method(req.method()).uri(&forward_url);
-        for (key, value) in req.headers() {
-            if key != HOST {
-                build = build.header(key, value);
+        if let Some(build_headers) = build.headers_mut() {
+            *build_headers = headers;
+        }
+        build.body(body).map_err(Error::other)
+    }
+
+    /// Checks if the `Accept` header of a request allows one of the rewritten content types, it is allowed when
+    /// the header is missing.
+    fn accepts_rewritable(&self, headers: &HeaderMap) -> bool {
+        let accepts: Vec<&str> = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|value| value.split(';').next().unwrap_or_default().trim())
+            .collect();
+        if accepts.is_empty() {
+            return true;
+        }
+        accepts.iter().any(|accept| {
+            *accept == "*/*"
+                || self.rewrite_content_types.iter().any(|content_type| {
+                    content_type.eq_ignore_ascii_case(accept)
+                        || accept.strip_suffix("/*").is_some_and(|type_| {
+                            content_type
+                                .split('/')
+                                .next()
+                                .is_some_and(|content_type| content_type.eq_ignore_ascii_case(type_))
+                        })
+                })
+        })
+    }
+}
+
+impl<U, C> Proxy<U, C>
+where
+    U: Upstreams,
+    C: Client,
+{
+    fn is_rewritable(&self, status: StatusCode, headers: &HeaderMap) -> bool {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        if status == StatusCode::SWITCHING_PROTOCOLS
+            || header(CONTENT_ENCODING).is_some_and(|encoding| !encoding.eq_ignore_ascii_case("identity"))
+        {
+            return false;
+        }
+        let Some(content_type) = header(CONTENT_TYPE) else {
+            return false;
+        };
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        if !self
+            .rewrite_content_types
+            .iter()
+            .any(|content_type| content_type.eq_ignore_ascii_case(essence))
+        {
+            return false;
+        }
+        match header(CONTENT_LENGTH).and_then(|length| length.parse::<usize>().ok()) {
+            Some(length) if length > self.rewrite_max_size => {
+                tracing::debug!(
+                    length,
+                    max_size = self.rewrite_max_size,
+                    "response body too large to be rewritten"
+                );
+                false
+            }
+            _ => true,
+        }
+    }
+
+    async fn rewrite_body(&self, body_rewriter: &BodyRewriter, req: &Request, mut body: ResBody, res: &mut Response) {
+        let mut data = Vec::new();
+        while let Some(frame) = body.frame().await {
+            match frame {
+                Ok(frame) => {
+                    let Ok(chunk) = frame.into_data() else {
+                        continue;
+                    };
+                    data.extend_from_slice(&chunk);
+                    if data.len() > self.rewrite_max_size {
+                        // Too large to be buffered, the read part and the rest are streamed unchanged.
+                        tracing::debug!(
+                            max_size = self.rewrite_max_size,
+                            "response body too large to be rewritten"
+                        );
+                        let head = stream::once(async move { Ok::<_, IoError>(BytesFrame::from(data)) });
+                        let rest = stream::unfold(body, |mut body| async move {
+                            let frame = body.frame().await?;
+                            Some((frame.map(BytesFrame), body))
+                        });
+                        res.stream(head.chain(rest));
+                        return;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!(error = ?e, "read response body failed");
+                    res.render(StatusError::bad_gateway());
+                    return;
+                }
+            }
+        }
+        let data = body_rewriter(req, Bytes::from(data));
+        let headers = res.headers_mut();
+        headers.insert(CONTENT_LENGTH, data.len().into());
+        if let Some(etag) = headers.get(ETAG).and_then(|etag| etag.to_str().ok()) {
+            if !etag.starts_with("W/") {
+                match HeaderValue::from_str(&format!("W/{etag}")) {
+                    Ok(etag) => headers.insert(ETAG, etag),
+                    Err(_) => headers.remove(ETAG),
+                };
             }
         }
-        if let Some(host) = forward_url.host().and_then(|host| HeaderValue::from_str(host).ok()) {
-            build = build.header(HeaderName::from_static("host"), host);
-        }
-        // let x_forwarded_for_header_name = "x-forwarded-for";
-        // // Add forwarding information in the headers
-        // match request.headers_mut().entry(x_forwarded_for_header_name) {
-        //     Ok(header_entry) => {
-        //         match header_entry {
-        //             hyper::header::Entry::Vacant(entry) => {
-        //                 let addr = format!("{}", client_ip);
-        //                 entry.insert(addr.parse().unwrap());
-        //             },
-        //             hyper::header::Entry::Occupied(mut entry) => {
-        //                 let addr = format!("{}, {}", entry.get().to_str().unwrap(), client_ip);
-        //                 entry.insert(addr.parse().unwrap());
-        //             }
-        //         }
-        //     }
-        //     // shouldn't happen...
-        //     Err(_) => panic!("Invalid header name: {}", x_forwarded_for_header_name),
-        // }
-        build.body(req.take_body()).map_err(Error::other)
+        res.body(ResBody::Once(data));
     }
 }
 
@@ -258,44 +584,204 @@ where
     C: Client,
 {
     async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
-        match self.build_proxied_request(req, depot).await {
-            Ok(proxied_request) => {
-                match self
-                    .client
-                    .execute(proxied_request, req.extensions_mut().remove())
-                    .await
-                {
-                    Ok(response) => {
-                        let (
-                            salvo_core::http::response::Parts {
-                                status,
-                                // version,
-                                headers,
-                                // extensions,
-                                ..
-                            },
-                            body,
-                        ) = response.into_parts();
-                        res.status_code(status);
-                        for (name, value) in headers {
-                            if let Some(name) = name {
-                                res.headers.insert(name, value);
-                            }
-                        }
-                        res.body(body);
-                    }
+        let mut upgraded: Option<OnUpgrade> = req.extensions_mut().remove();
+        // The body of retryable requests is buffered, so it can be sent again.
+        let retry = match &self.retry_policy {
+            Some(policy) if upgraded.is_none() && policy.is_retryable(req) => {
+                match req.payload_with_max_size(policy.max_body_size).await {
+                    Ok(payload) => Some((policy, payload.clone())),
                     Err(e) => {
-                        tracing::error!( error = ?e, uri = ?req.uri(), "get response data failed: {}", e);
-                        res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+                        tracing::error!(error = ?e, "read request body failed");
+                        res.render(StatusError::bad_request());
+                        return;
                     }
                 }
             }
-            Err(e) => {
-                tracing::error!(error = ?e, "build proxied request failed");
+            _ => None,
+        };
+        if let Some((policy, _)) = &retry {
+            policy.budget.deposit();
+        }
+        let deadline = req.remaining_time().map(|remaining| Instant::now() + remaining);
+        let mut retries = 0;
+        loop {
+            let (upstream, lease) = match self.upstreams.lease().await {
+                Ok(elected) => elected,
+                Err(e) => {
+                    let e = e.into();
+                    tracing::error!(error = ?e, "elect upstream failed");
+                    res.render(StatusError::service_unavailable());
+                    return;
+                }
+            };
+            let body = match &retry {
+                Some((_, payload)) => ReqBody::Once(payload.clone()),
+                None => req.take_body(),
+            };
+            let proxied_request = match self.build_proxied_request(upstream, req, depot, body).await {
+                Ok(proxied_request) => proxied_request,
+                Err(e) => {
+                    tracing::error!(error = ?e, "build proxied request failed");
+                    res.render(StatusError::internal_server_error());
+                    return;
+                }
+            };
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let per_try_timeout = retry.as_ref().and_then(|(policy, _)| policy.per_try_timeout);
+            let timeout = match (remaining, per_try_timeout) {
+                (Some(remaining), Some(per_try_timeout)) => Some(remaining.min(per_try_timeout)),
+                (remaining, per_try_timeout) => remaining.or(per_try_timeout),
+            };
+            let response = self.client.execute(proxied_request, upgraded.take());
+            // The upstream is not waited for after the deadline of the request.
+            let response = match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, response).await {
+                    Ok(response) => Some(response),
+                    Err(_) => None,
+                },
+                None => Some(response.await),
+            };
+            let (status, headers) = match &response {
+                Some(Ok(response)) => (Some(response.status()), Some(response.headers())),
+                _ => (None, None),
+            };
+            let failed = match status {
+                Some(status) => matches!(
+                    status,
+                    StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+                ),
+                None => true,
+            };
+            lease.report(!failed);
+
+            let delay = retry
+                .as_ref()
+                .filter(|(policy, _)| retries < policy.max_retries)
+                .and_then(|(policy, _)| policy.delay(retries, status, headers).map(|delay| (policy, delay)))
+                .filter(|(_, delay)| deadline.map_or(true, |deadline| Instant::now() + *delay < deadline))
+                .filter(|(policy, _)| policy.budget.withdraw());
+            if let Some((_, delay)) = delay {
+                retries += 1;
+                tracing::debug!(uri = ?req.uri(), upstream, retries, ?delay, "retry proxied request");
+                drop(response);
+                drop(lease);
+                tokio::time::sleep(delay).await;
+                continue;
             }
+
+            match response {
+                Some(Ok(response)) => {
+                    self.write_response(req, res, response).await;
+                    // The upstream is busy until the body is sent, bodies already read end the lease now.
+                    if !matches!(
+                        res.body,
+                        ResBody::None | ResBody::Once(_) | ResBody::Chunks(_) | ResBody::Error(_)
+                    ) {
+                        let body = res.take_body();
+                        res.body(ResBody::Boxed(Box::pin(LeasedBody { body, _lease: lease })));
+                    }
+                }
+                Some(Err(e)) => {
+                    tracing::error!( error = ?e, uri = ?req.uri(), "get response data failed: {}", e);
+                    res.render(StatusError::bad_gateway());
+                }
+                None => {
+                    if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                        tracing::warn!(uri = ?req.uri(), "deadline of the request exceeded");
+                    } else {
+                        tracing::warn!(uri = ?req.uri(), "try of the proxied request timed out");
+                    }
+                    res.render(StatusError::gateway_timeout());
+                }
+            }
+            return;
         }
     }
 }
+
+impl<U, C> Proxy<U, C>
+where
+    U: Upstreams,
+    C: Client,
+{
+    async fn write_response(&self, req: &Request, res: &mut Response, response: HyperResponse) {
+        let (
+            salvo_core::http::response::Parts {
+                status,
+                // version,
+                headers,
+                // extensions,
+                ..
+            },
+            body,
+        ) = response.into_parts();
+        let mut headers = headers;
+        remove_hop_by_hop_headers(&mut headers);
+        if let Some(headers_rewriter) = &self.headers_rewriter {
+            headers_rewriter(req, &mut headers);
+        }
+        res.status_code(status);
+        // Extended by the whole map, so headers with multiple values like `Set-Cookie` are kept.
+        res.headers_mut().extend(headers);
+        match &self.body_rewriter {
+            Some(body_rewriter) if self.is_rewritable(status, res.headers()) => {
+                self.rewrite_body(body_rewriter, req, body, res).await;
+            }
+            _ => {
+                res.body(body);
+            }
+        }
+    }
+}
+
+/// Removes hop-by-hop headers, and the headers listed in `Connection` header.
+///
+/// `Connection: upgrade` and `Upgrade` headers are kept for upgrade requests and responses, like websocket.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let upgrade = get_upgrade_type(headers)
+        .is_some()
+        .then(|| headers.get(UPGRADE).cloned())
+        .flatten();
+    let listed: Vec<HeaderName> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in listed.iter().chain(&HOP_BY_HOP_HEADERS) {
+        headers.remove(name);
+    }
+    headers.remove(UPGRADE);
+    if let Some(upgrade) = upgrade {
+        headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(UPGRADE, upgrade);
+    }
+}
+
+fn add_forwarded_headers(headers: &mut HeaderMap, req: &Request) {
+    if let Some(addr) = req.remote_addr().clone().into_std() {
+        let value = match headers.get(X_FORWARDED_FOR).and_then(|value| value.to_str().ok()) {
+            Some(forwarded_for) => format!("{forwarded_for}, {}", addr.ip()),
+            None => addr.ip().to_string(),
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(X_FORWARDED_FOR, value);
+        }
+    }
+    if let Ok(proto) = HeaderValue::from_str(req.scheme().as_str()) {
+        headers.insert(X_FORWARDED_PROTO, proto);
+    }
+    let host = req
+        .headers()
+        .get(HOST)
+        .cloned()
+        .or_else(|| HeaderValue::from_str(req.uri().authority()?.as_str()).ok());
+    if let Some(host) = host {
+        headers.insert(X_FORWARDED_HOST, host);
+    }
+}
+
 #[inline]
 fn get_upgrade_type(headers: &HeaderMap) -> Option<&str> {
     if headers
@@ -321,6 +807,7 @@ fn get_upgrade_type(headers: &HeaderMap) -> Option<&str> {
 // Unit tests for Proxy
 #[cfg(test)]
 mod tests {
+    use salvo_core::conn::Acceptor;
     use salvo_core::prelude::*;
     use salvo_core::test::*;
 
@@ -364,6 +851,303 @@ mod tests {
             .unwrap();
         assert!(content.contains("Install Rust"));
     }
+    #[tokio::test]
+    async fn test_proxy_headers() {
+        #[handler]
+        async fn upstream(req: &mut Request, res: &mut Response) {
+            let header = |name: &str| req.header::<String>(name).unwrap_or_default();
+            let lines = [
+                req.uri().to_string(),
+                header("x-forwarded-for"),
+                header("x-forwarded-proto"),
+                header("x-forwarded-host"),
+                header("host"),
+                header("x-secret"),
+                header("proxy-authorization"),
+            ];
+            res.add_header("set-cookie", "a=1", false).unwrap();
+            res.add_header("set-cookie", "b=2", false).unwrap();
+            res.add_header("keep-alive", "timeout=5", true).unwrap();
+            res.render(lines.join("\n"));
+        }
+        #[handler]
+        async fn client_addr(req: &mut Request) {
+            *req.remote_addr_mut() = std::net::SocketAddr::from(([10, 0, 0, 1], 1234)).into();
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("<**>").goal(upstream)));
+
+        let proxy = Proxy::default_hyper_client(format!("http://{addr}/v1"));
+        let router = Router::with_hoop(client_addr).push(Router::with_path("api/<**rest>").goal(proxy));
+        let mut res = TestClient::get("http://gateway.local/api/users?page=2")
+            .add_header("x-forwarded-for", "192.168.1.1", true)
+            .add_header("connection", "x-secret", true)
+            .add_header("x-secret", "hidden", true)
+            .add_header("proxy-authorization", "Basic abc", true)
+            .send(router)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let cookies: Vec<_> = res.headers().get_all("set-cookie").iter().collect();
+        assert_eq!(cookies, ["a=1", "b=2"]);
+        assert!(res.headers().get("keep-alive").is_none());
+        let content = res.take_string().await.unwrap();
+        let lines: Vec<_> = content.split('\n').collect();
+        assert_eq!(lines[0], "/v1/users?page=2");
+        assert_eq!(lines[1], "192.168.1.1, 10.0.0.1");
+        assert_eq!(lines[2], "http");
+        assert_eq!(lines[3], "gateway.local");
+        assert_eq!(lines[4], addr.to_string());
+        assert_eq!(lines[5], "");
+        assert_eq!(lines[6], "");
+    }
+
+    #[tokio::test]
+    async fn test_proxy_deadline() {
+        #[handler]
+        async fn upstream(req: &mut Request) -> String {
+            let timeout = req.header::<u64>("x-request-timeout").unwrap_or_default();
+            if req.query::<bool>("slow").unwrap_or_default() {
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+            timeout.to_string()
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("<**>").goal(upstream)));
+
+        let service =
+            Service::new(Router::with_path("<**rest>").goal(Proxy::default_hyper_client(format!("http://{addr}"))));
+        let timeout = TestClient::get("http://127.0.0.1:5801/")
+            .add_header("x-request-timeout", "5000", true)
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        let timeout = timeout.parse::<u64>().unwrap();
+        assert!(timeout > 4000 && timeout <= 5000);
+
+        let res = TestClient::get("http://127.0.0.1:5801/?slow=true")
+            .add_header("x-request-timeout", "100", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::GATEWAY_TIMEOUT));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_upgrade() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        #[handler]
+        async fn echo(req: &mut Request, res: &mut Response) -> Result<(), StatusError> {
+            let io = req.take_io().ok_or_else(StatusError::bad_request)?;
+            res.status_code(StatusCode::SWITCHING_PROTOCOLS);
+            res.add_header(CONNECTION, "upgrade", true).unwrap();
+            res.add_header(UPGRADE, "echo", true).unwrap();
+            tokio::spawn(async move {
+                let (mut reader, mut writer) = tokio::io::split(io.await.unwrap());
+                tokio::io::copy(&mut reader, &mut writer).await.ok();
+            });
+            Ok(())
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let upstream = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("ws").goal(echo)));
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        let proxy = Proxy::default_hyper_client(format!("http://{upstream}"));
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("<**rest>").goal(proxy)));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET /ws HTTP/1.1\r\nhost: localhost\r\nconnection: keep-alive, Upgrade\r\nupgrade: echo\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        assert!(head.starts_with(b"HTTP/1.1 101 Switching Protocols"));
+
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_proxy_rewrite() {
+        #[handler]
+        async fn page(req: &mut Request, res: &mut Response) {
+            let encoding = req.header::<String>("accept-encoding").unwrap_or_default();
+            res.add_header("x-powered-by", "internal", true).unwrap();
+            res.add_header("etag", "\"v1\"", true).unwrap();
+            res.render(Text::Html(format!(
+                "<html><body><a href=\"http://internal/docs\">{encoding}</a></body></html>"
+            )));
+        }
+        #[handler]
+        async fn data() -> Json<&'static str> {
+            Json("http://internal/docs")
+        }
+
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        let upstream = Router::new()
+            .push(Router::with_path("page").get(page))
+            .push(Router::with_path("data").get(data));
+        tokio::spawn(Server::new(acceptor).serve(upstream));
+
+        let rewrite = |req: &Request, body: Bytes| {
+            let body = String::from_utf8_lossy(&body)
+                .replace("http://internal", "https://public")
+                .replace(
+                    "</body>",
+                    &format!("<script src=\"{}.js\"></script></body>", req.uri().path()),
+                );
+            Bytes::from(body)
+        };
+        let proxy = Proxy::default_hyper_client(format!("http://{addr}"))
+            .headers_rewriter(|_req: &Request, headers: &mut HeaderMap| {
+                headers.remove("x-powered-by");
+            })
+            .body_rewriter(rewrite);
+        let small = Proxy::default_hyper_client(format!("http://{addr}"))
+            .body_rewriter(rewrite)
+            .rewrite_max_size(16);
+        let router = Router::new()
+            .push(Router::with_path("small/<**rest>").goal(small))
+            .push(Router::with_path("<**rest>").goal(proxy));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/page")
+            .add_header("accept-encoding", "gzip", true)
+            .send(&service)
+            .await;
+        assert!(res.headers().get("x-powered-by").is_none());
+        assert_eq!(res.headers()["etag"], "W/\"v1\"");
+        assert_eq!(
+            res.take_string().await.unwrap(),
+            "<html><body><a href=\"https://public/docs\">identity</a><script src=\"/page.js\"></script></body></html>"
+        );
+        let mut res = TestClient::get("http://127.0.0.1:5801/page")
+            .add_header("accept", "image/webp,image/*", true)
+            .add_header("accept-encoding", "gzip", true)
+            .send(&service)
+            .await;
+        assert!(res.take_string().await.unwrap().contains(">gzip<"));
+        let mut res = TestClient::get("http://127.0.0.1:5801/data").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), "\"http://internal/docs\"");
+        let mut res = TestClient::get("http://127.0.0.1:5801/small/page").send(&service).await;
+        assert!(res.take_string().await.unwrap().contains("http://internal/docs"));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_retry() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        struct Flaky(Arc<AtomicUsize>);
+        #[async_trait]
+        impl Handler for Flaky {
+            async fn handle(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+                let tries = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+                if tries < 3 {
+                    res.add_header("retry-after", "0", true).unwrap();
+                    res.render(StatusError::service_unavailable());
+                } else {
+                    res.render(String::from_utf8(req.payload().await.unwrap().to_vec()).unwrap());
+                }
+            }
+        }
+
+        let tries = Arc::new(AtomicUsize::new(0));
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("<**>").goal(Flaky(tries.clone()))));
+
+        let proxy = Proxy::default_hyper_client(format!("http://{addr}"))
+            .retry_policy(RetryPolicy::new().backoff(Duration::from_millis(1), Duration::from_millis(5)));
+        let service = Service::new(Router::with_path("<**rest>").goal(proxy));
+
+        let mut res = TestClient::put("http://127.0.0.1:5801/items/1")
+            .text("data")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "data");
+        assert_eq!(tries.load(Ordering::SeqCst), 3);
+
+        tries.store(0, Ordering::SeqCst);
+        let res = TestClient::post("http://127.0.0.1:5801/items")
+            .text("data")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(tries.load(Ordering::SeqCst), 1);
+
+        tries.store(0, Ordering::SeqCst);
+        let proxy = Proxy::default_hyper_client(format!("http://{addr}")).retry_policy(
+            RetryPolicy::new()
+                .max_retries(1)
+                .backoff(Duration::ZERO, Duration::ZERO),
+        );
+        let res = TestClient::get("http://127.0.0.1:5801/")
+            .send(Service::new(Router::with_path("<**rest>").goal(proxy)))
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(tries.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_retry_per_try_timeout() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        struct Stalled(Arc<AtomicUsize>);
+        #[async_trait]
+        impl Handler for Stalled {
+            async fn handle(&self, _req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+                if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                res.render("done");
+            }
+        }
+
+        let tries = Arc::new(AtomicUsize::new(0));
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("<**>").goal(Stalled(tries.clone()))));
+
+        let proxy = Proxy::default_hyper_client(format!("http://{addr}")).retry_policy(
+            RetryPolicy::new()
+                .per_try_timeout(Duration::from_millis(200))
+                .backoff(Duration::ZERO, Duration::ZERO),
+        );
+        let mut res = TestClient::get("http://127.0.0.1:5801/")
+            .send(Service::new(Router::with_path("<**rest>").goal(proxy)))
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), "done");
+        assert_eq!(tries.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_bad_gateway() {
+        let router = Router::with_path("<**rest>").goal(Proxy::default_hyper_client("http://127.0.0.1:1"));
+        let res = TestClient::get("http://127.0.0.1:5801/").send(router).await;
+        assert_eq!(res.status_code, Some(StatusCode::BAD_GATEWAY));
+    }
+
     #[test]
     fn test_others() {
         let mut handler = Proxy::default_hyper_client(["https://www.bing.com"]);
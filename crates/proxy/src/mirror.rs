@@ -0,0 +1,334 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::stream::{self, StreamExt};
+use http_body_util::{BodyExt, BodyStream, StreamBody};
+use salvo_core::fuse::SteadyFusewire;
+use salvo_core::http::header::{HeaderName, HeaderValue, HOST};
+use salvo_core::http::uri::Uri;
+use salvo_core::http::{Body, ReqBody};
+use salvo_core::hyper::body::{Bytes, Frame};
+use salvo_core::rng::{Rng, SystemRng};
+use salvo_core::{async_trait, BoxedError, Depot, FlowCtrl, Handler, Request, Response};
+use tokio::sync::Semaphore;
+
+use super::{remove_hop_by_hop_headers, Client, HyperClient};
+
+/// Header added to mirrored requests, so the shadow upstream can tell them from real ones.
+pub const X_SHADOW_REQUEST: HeaderName = HeaderName::from_static("x-shadow-request");
+
+/// Middleware duplicating a part of requests to a shadow upstream, to test a new version of a service with
+/// production traffic.
+///
+/// A sampled request is sent to the shadow upstream in a background task, with the same method, path, query, headers
+/// and body, and the `X-Shadow-Request: 1` header. The response of the shadow upstream is discarded, and its
+/// failures are only logged, so the original request is never affected.
+///
+/// Bodies are buffered to be sent twice, so only requests whose body has a known size up to
+/// [`max_body_size`](Mirror::max_body_size) are mirrored. If the body is larger than its size or can not be read,
+/// the request is not mirrored, and the handler reads the body as it is received.
+///
+/// At most [`max_concurrency`](Mirror::max_concurrency) mirrored requests are in flight, requests sampled while
+/// the limit is reached are not mirrored, so a slow shadow upstream does not pile up tasks.
+///
+/// # Example
+///
+/// ```no_run
+/// use salvo_core::prelude::*;
+/// use salvo_proxy::{Mirror, Proxy};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let mirror = Mirror::new("http://api-next.internal:8080").percentage(10.0);
+/// let router = Router::with_path("api/<**rest>")
+///     .hoop(mirror)
+///     .goal(Proxy::default_hyper_client("http://api.internal:8080"));
+/// # }
+/// ```
+pub struct Mirror<C = HyperClient> {
+    upstream: Uri,
+    client: Arc<C>,
+    percentage: f64,
+    max_body_size: u64,
+    timeout: Option<Duration>,
+    in_flight: Arc<Semaphore>,
+    rng: Arc<dyn Rng>,
+}
+
+impl Mirror<HyperClient> {
+    /// Create new `Mirror` which use default hyper util client, mirroring all requests to `upstream`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `upstream` is not a valid url.
+    #[inline]
+    pub fn new(upstream: impl AsRef<str>) -> Self {
+        Mirror::with_client(upstream, HyperClient::default())
+    }
+}
+
+impl<C> Mirror<C>
+where
+    C: Client,
+{
+    /// Create new `Mirror` with a [`Client`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `upstream` is not a valid url.
+    pub fn with_client(upstream: impl AsRef<str>, client: C) -> Self {
+        Mirror {
+            upstream: upstream.as_ref().parse().expect("shadow upstream url should be valid"),
+            client: Arc::new(client),
+            percentage: 100.0,
+            max_body_size: 64 * 1024,
+            timeout: Some(Duration::from_secs(30)),
+            in_flight: Arc::new(Semaphore::new(100)),
+            rng: Arc::new(SystemRng),
+        }
+    }
+
+    /// Sets the percentage of requests mirrored, from 0 to 100, defaults to 100.
+    #[inline]
+    pub fn percentage(mut self, percentage: f64) -> Self {
+        self.percentage = percentage.clamp(0.0, 100.0);
+        self
+    }
+
+    /// Sets the max size of bodies buffered to be mirrored, defaults to 64KB.
+    #[inline]
+    pub fn max_body_size(mut self, size: u64) -> Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Sets the time after which mirrored requests are abandoned, defaults to 30 seconds.
+    #[inline]
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the max number of mirrored requests in flight, defaults to 100.
+    #[inline]
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.in_flight = Arc::new(Semaphore::new(max_concurrency));
+        self
+    }
+
+    /// Sets the [`Rng`] used to sample requests, defaults to [`SystemRng`].
+    ///
+    /// A [`MockRng`](salvo_core::rng::MockRng) can be used in tests, so sampling is deterministic.
+    #[inline]
+    pub fn rng(mut self, rng: impl Rng) -> Self {
+        self.rng = Arc::new(rng);
+        self
+    }
+
+    fn is_sampled(&self, req: &Request) -> bool {
+        let is_body_small = req
+            .body()
+            .size_hint()
+            .upper()
+            .is_some_and(|upper| upper <= self.max_body_size);
+        is_body_small && self.rng.next_f64() * 100.0 < self.percentage
+    }
+
+    fn build_shadow_request(
+        &self,
+        req: &Request,
+        body: ReqBody,
+    ) -> Result<hyper::Request<ReqBody>, hyper::http::Error> {
+        let mut parts = self.upstream.clone().into_parts();
+        let base = parts
+            .path_and_query
+            .as_ref()
+            .map(|pq| pq.path().trim_end_matches('/'))
+            .unwrap_or_default();
+        let path_and_query = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+        parts.path_and_query = Some(format!("{base}{path_and_query}").parse()?);
+        let uri = Uri::from_parts(parts)?;
+
+        let mut headers = req.headers().clone();
+        remove_hop_by_hop_headers(&mut headers);
+        headers.remove(HOST);
+        if let Some(host) = uri
+            .authority()
+            .and_then(|authority| HeaderValue::from_str(authority.as_str()).ok())
+        {
+            headers.insert(HOST, host);
+        }
+        headers.insert(X_SHADOW_REQUEST, HeaderValue::from_static("1"));
+        let mut builder = hyper::Request::builder().method(req.method()).uri(uri);
+        if let Some(builder_headers) = builder.headers_mut() {
+            *builder_headers = headers;
+        }
+        builder.body(body)
+    }
+}
+
+#[async_trait]
+impl<C> Handler for Mirror<C>
+where
+    C: Client,
+{
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        if self.is_sampled(req) {
+            mirror(self, req).await;
+        }
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
+async fn mirror<C: Client>(mirror: &Mirror<C>, req: &mut Request) {
+    let Ok(permit) = mirror.in_flight.clone().try_acquire_owned() else {
+        tracing::debug!("too many mirrored requests in flight, request not mirrored");
+        return;
+    };
+    let body = match buffer_body(req.take_body(), mirror.max_body_size).await {
+        Ok(body) => body,
+        Err(body) => {
+            tracing::debug!("body of the request is too large or can not be read, request not mirrored");
+            req.replace_body(body);
+            return;
+        }
+    };
+    req.replace_body(ReqBody::Once(body.clone()));
+    match mirror.build_shadow_request(req, ReqBody::Once(body)) {
+        Ok(shadow_request) => {
+            let client = mirror.client.clone();
+            let timeout = mirror.timeout;
+            tokio::spawn(async move {
+                let _permit = permit;
+                let response = client.execute(shadow_request, None);
+                let result = match timeout {
+                    Some(timeout) => match tokio::time::timeout(timeout, response).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            tracing::debug!("mirrored request timed out");
+                            return;
+                        }
+                    },
+                    None => response.await,
+                };
+                if let Err(e) = result {
+                    tracing::debug!(error = ?e, "mirrored request failed");
+                }
+            });
+        }
+        Err(e) => {
+            tracing::warn!(error = ?e, "build mirrored request failed");
+        }
+    }
+}
+
+/// Reads the body up to `max_size` bytes, or returns a body with the same content if it is larger or can not be
+/// read.
+async fn buffer_body(mut body: ReqBody, max_size: u64) -> Result<Bytes, ReqBody> {
+    let mut frames = Vec::new();
+    let mut size = 0;
+    while let Some(frame) = body.frame().await {
+        let is_err = frame.is_err();
+        if let Some(data) = frame.as_ref().ok().and_then(Frame::data_ref) {
+            size += data.len() as u64;
+        }
+        frames.push(frame);
+        if is_err || size > max_size {
+            let rest = stream::iter(frames).chain(BodyStream::new(body));
+            let inner = StreamBody::new(rest).map_err(BoxedError::from);
+            return Err(ReqBody::Boxed {
+                inner: Box::pin(inner),
+                fusewire: Arc::new(SteadyFusewire),
+            });
+        }
+    }
+    let mut buffer = Vec::with_capacity(size as usize);
+    for frame in frames.into_iter().flatten() {
+        if let Ok(data) = frame.into_data() {
+            buffer.extend_from_slice(&data);
+        }
+    }
+    Ok(buffer.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::conn::{Acceptor, TcpListener};
+    use salvo_core::prelude::*;
+    use salvo_core::rng::MockRng;
+    use salvo_core::test::{ResponseExt, TestClient};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    struct Shadow {
+        tx: mpsc::UnboundedSender<String>,
+    }
+    #[handler]
+    impl Shadow {
+        async fn handle(&self, req: &mut Request) -> &'static str {
+            let body = String::from_utf8_lossy(req.payload().await.unwrap()).into_owned();
+            let line = format!(
+                "{} {} {} {body}",
+                req.method(),
+                req.uri().path_and_query().unwrap(),
+                req.header::<String>("x-shadow-request").unwrap_or_default(),
+            );
+            self.tx.send(line).unwrap();
+            "shadow"
+        }
+    }
+
+    #[handler]
+    async fn echo(req: &mut Request) -> String {
+        String::from_utf8_lossy(req.payload().await.unwrap()).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_mirror() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+        let addr = acceptor.holdings()[0].local_addr.clone().into_std().unwrap();
+        tokio::spawn(Server::new(acceptor).serve(Router::with_path("next/<**>").goal(Shadow { tx })));
+
+        let mirror = Mirror::new(format!("http://{addr}/next")).max_body_size(8);
+        let service = Service::new(Router::with_path("api/<**>").hoop(mirror).goal(echo));
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/api/users?page=1")
+            .text("hello")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "hello");
+        assert_eq!(rx.recv().await.unwrap(), "POST /next/api/users?page=1 1 hello");
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/api/users")
+            .text("too large body")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "too large body");
+
+        let body = ReqBody::Once(Bytes::from_static(b"too large body"));
+        let body = buffer_body(body, 8).await.unwrap_err();
+        assert_eq!(&body.collect().await.unwrap().to_bytes()[..], b"too large body");
+
+        let mirror = Mirror::new(format!("http://{addr}/next")).percentage(0.0);
+        let service = Service::new(Router::with_path("api/<**>").hoop(mirror).goal(echo));
+        TestClient::get("http://127.0.0.1:5801/api/users").send(&service).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(rx.try_recv().is_err());
+
+        let rng = MockRng::with_f64(0.6);
+        let mirror = Mirror::new(format!("http://{addr}/next"))
+            .percentage(50.0)
+            .rng(rng.clone());
+        let service = Service::new(Router::with_path("api/<**>").hoop(mirror).goal(echo));
+        TestClient::get("http://127.0.0.1:5801/api/skipped")
+            .send(&service)
+            .await;
+        rng.set_f64([0.4]);
+        TestClient::get("http://127.0.0.1:5801/api/sampled")
+            .send(&service)
+            .await;
+        assert_eq!(rx.recv().await.unwrap(), "GET /next/api/sampled 1 ");
+    }
+}
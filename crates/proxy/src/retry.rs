@@ -0,0 +1,320 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use hyper::body::Body as _;
+use salvo_core::http::header::{HeaderMap, RETRY_AFTER};
+use salvo_core::http::headers::{Date, Header};
+use salvo_core::http::{Method, StatusCode};
+use salvo_core::Request;
+
+/// Limits the retries of a [`Proxy`](crate::Proxy) to a part of the requests, so retries do not overload upstreams
+/// which are already failing.
+///
+/// Retries are allowed while they are fewer than [`ratio`](RetryBudget::ratio) of the requests plus
+/// [`min_per_second`](RetryBudget::min_per_second) per second, counted in windows of 10 seconds. Cloned budgets
+/// are shared.
+#[derive(Clone, Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_per_second: u32,
+    window: Duration,
+    state: Arc<Mutex<BudgetState>>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    window_start: Instant,
+    requests: u32,
+    retries: u32,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(0.2, 10)
+    }
+}
+
+impl RetryBudget {
+    /// Create a new `RetryBudget`.
+    pub fn new(ratio: f64, min_per_second: u32) -> Self {
+        Self {
+            ratio: ratio.max(0.0),
+            min_per_second,
+            window: Duration::from_secs(10),
+            state: Arc::new(Mutex::new(BudgetState {
+                window_start: Instant::now(),
+                requests: 0,
+                retries: 0,
+            })),
+        }
+    }
+
+    /// Get the ratio of the requests which can be retried.
+    #[inline]
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Get the number of retries allowed per second regardless of the number of requests.
+    #[inline]
+    pub fn min_per_second(&self) -> u32 {
+        self.min_per_second
+    }
+
+    fn lock(&self, now: Instant) -> std::sync::MutexGuard<'_, BudgetState> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if now.duration_since(state.window_start) >= self.window {
+            *state = BudgetState {
+                window_start: now,
+                requests: 0,
+                retries: 0,
+            };
+        }
+        state
+    }
+
+    pub(crate) fn deposit(&self) {
+        let mut state = self.lock(Instant::now());
+        state.requests = state.requests.saturating_add(1);
+    }
+
+    pub(crate) fn withdraw(&self) -> bool {
+        let mut state = self.lock(Instant::now());
+        let allowed = self.min_per_second as f64 * self.window.as_secs_f64() + self.ratio * state.requests as f64;
+        if (state.retries as f64) < allowed {
+            state.retries += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Retries of the requests of a [`Proxy`](crate::Proxy) failing with transient errors.
+///
+/// Only requests with idempotent methods (`GET`, `HEAD`, `OPTIONS`, `TRACE`, `PUT` and `DELETE`) and a body of a
+/// known size smaller than [`max_body_size`](RetryPolicy::max_body_size) are retried, their bodies are buffered to
+/// be sent again. Upgrade requests are never retried.
+///
+/// A try fails with a connection error, when its [`per_try_timeout`](RetryPolicy::per_try_timeout) elapses, or
+/// with one of the [`retry_statuses`](RetryPolicy::retry_statuses). Each retry elects an upstream again, waits for
+/// an exponential backoff with jitter, or for the `Retry-After` delay of the failed response if it is not longer
+/// than [`max_retry_after`](RetryPolicy::max_retry_after), and consumes the [`RetryBudget`]. The response of the last
+/// try is sent to the client, and requests are not retried after their [deadline](Request::deadline).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use salvo_core::prelude::*;
+/// use salvo_proxy::{Proxy, RetryPolicy};
+///
+/// let proxy = Proxy::default_hyper_client(vec!["http://10.0.0.1:8080", "http://10.0.0.2:8080"])
+///     .retry_policy(RetryPolicy::new().max_retries(2).per_try_timeout(Duration::from_secs(2)));
+/// let router = Router::with_path("<**rest>").goal(proxy);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub(crate) max_retries: u32,
+    pub(crate) per_try_timeout: Option<Duration>,
+    backoff: Duration,
+    max_backoff: Duration,
+    retry_statuses: Vec<StatusCode>,
+    max_retry_after: Duration,
+    pub(crate) max_body_size: usize,
+    pub(crate) budget: RetryBudget,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+    /// Create a new `RetryPolicy` retrying requests twice.
+    pub fn new() -> Self {
+        Self {
+            max_retries: 2,
+            per_try_timeout: None,
+            backoff: Duration::from_millis(25),
+            max_backoff: Duration::from_millis(250),
+            retry_statuses: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            max_retry_after: Duration::from_secs(1),
+            max_body_size: 64 * 1024,
+            budget: RetryBudget::default(),
+        }
+    }
+
+    /// Sets the maximum number of retries of a request, defaults to `2`.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the timeout of each try, there is no timeout by default.
+    pub fn per_try_timeout(mut self, timeout: Duration) -> Self {
+        self.per_try_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the base and maximum delays of the exponential backoff between tries, defaults to 25 and 250
+    /// milliseconds.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.backoff = base;
+        self.max_backoff = max.max(base);
+        self
+    }
+
+    /// Sets the response status codes retried, defaults to `502`, `503` and `504`.
+    pub fn retry_statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.retry_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Sets the longest `Retry-After` delay waited for, defaults to 1 second.
+    ///
+    /// `Retry-After` is either a number of seconds or a date. Responses asking to retry later are sent to the
+    /// client.
+    pub fn max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Sets the maximum size of the request bodies buffered to be retried, defaults to 64 KiB.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets the [`RetryBudget`], defaults to 20% of the requests plus 10 retries per second.
+    pub fn budget(mut self, budget: RetryBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub(crate) fn is_retryable(&self, req: &Request) -> bool {
+        let idempotent = [
+            Method::GET,
+            Method::HEAD,
+            Method::OPTIONS,
+            Method::TRACE,
+            Method::PUT,
+            Method::DELETE,
+        ]
+        .contains(req.method());
+        idempotent
+            && self.max_retries > 0
+            && req
+                .body()
+                .size_hint()
+                .upper()
+                .is_some_and(|size| size <= self.max_body_size as u64)
+    }
+
+    /// Returns the delay before retrying, or `None` if the failed response should not be retried.
+    ///
+    /// `status` is `None` if the try failed without response.
+    pub(crate) fn delay(
+        &self,
+        retry: u32,
+        status: Option<StatusCode>,
+        headers: Option<&HeaderMap>,
+    ) -> Option<Duration> {
+        if let Some(status) = status {
+            if !self.retry_statuses.contains(&status) {
+                return None;
+            }
+        }
+        let retry_after = headers.and_then(|headers| headers.get(RETRY_AFTER)).and_then(|value| {
+            match value.to_str().ok()?.trim().parse::<u64>() {
+                Ok(seconds) => Some(Duration::from_secs(seconds)),
+                Err(_) => {
+                    // A date in the past asks to retry now.
+                    let date = SystemTime::from(Date::decode(&mut std::iter::once(value)).ok()?);
+                    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+                }
+            }
+        });
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_retry_after => None,
+            Some(retry_after) => Some(retry_after),
+            None => {
+                let max = self
+                    .backoff
+                    .saturating_mul(2u32.saturating_pow(retry))
+                    .min(self.max_backoff);
+                // Full jitter, so retries of concurrent requests are spread.
+                Some(max.mul_f64(fastrand::f64()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(0.5, 0);
+        assert!(!budget.withdraw());
+        for _ in 0..4 {
+            budget.deposit();
+        }
+        assert!(budget.withdraw());
+        assert!(budget.withdraw());
+        assert!(!budget.withdraw());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::new().backoff(Duration::from_millis(10), Duration::from_millis(30));
+        assert_eq!(policy.delay(0, Some(StatusCode::INTERNAL_SERVER_ERROR), None), None);
+        assert!(policy.delay(5, None, None).unwrap() <= Duration::from_millis(30));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "1".parse().unwrap());
+        assert_eq!(
+            policy.delay(0, Some(StatusCode::SERVICE_UNAVAILABLE), Some(&headers)),
+            Some(Duration::from_secs(1))
+        );
+        headers.insert(RETRY_AFTER, "60".parse().unwrap());
+        assert_eq!(
+            policy.delay(0, Some(StatusCode::SERVICE_UNAVAILABLE), Some(&headers)),
+            None
+        );
+        headers.insert(RETRY_AFTER, "invalid".parse().unwrap());
+        assert!(
+            policy
+                .delay(0, Some(StatusCode::SERVICE_UNAVAILABLE), Some(&headers))
+                .unwrap()
+                <= Duration::from_millis(10)
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_date() {
+        let policy = RetryPolicy::new().max_retry_after(Duration::from_secs(10));
+        let retry_after = |time: SystemTime| {
+            let mut values = Vec::new();
+            Date::from(time).encode(&mut values);
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, values.remove(0));
+            policy.delay(0, Some(StatusCode::SERVICE_UNAVAILABLE), Some(&headers))
+        };
+
+        let delay = retry_after(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert!(delay > Duration::from_secs(3) && delay <= Duration::from_secs(5));
+        assert_eq!(
+            retry_after(SystemTime::now() - Duration::from_secs(5)),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(SystemTime::now() + Duration::from_secs(60)), None);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+use super::{BasicQuota, RateGuard};
+
+/// Token bucket implement.
+///
+/// The bucket holds at most `quota.limit` tokens and is refilled continuously, a full bucket is
+/// refilled in `quota.period`. Every request takes one token, or as many tokens as its cost, so bursts up to
+/// `quota.limit` requests are allowed while the average rate is limited to `quota.limit` per `quota.period`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BucketGuard {
+    updated: OffsetDateTime,
+    tokens: f64,
+    quota: Option<BasicQuota>,
+}
+
+impl Default for BucketGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BucketGuard {
+    /// Create a new `BucketGuard`.
+    pub fn new() -> Self {
+        Self {
+            updated: OffsetDateTime::now_utc(),
+            tokens: 0.0,
+            quota: None,
+        }
+    }
+
+    /// Time needed to refill one token.
+    fn refill_span(quota: &BasicQuota) -> Duration {
+        quota.period / (quota.limit.max(1) as f64)
+    }
+}
+
+impl RateGuard for BucketGuard {
+    type Quota = BasicQuota;
+    async fn verify(&mut self, quota: &Self::Quota) -> bool {
+        self.verify_at(quota, OffsetDateTime::now_utc()).await
+    }
+
+    async fn verify_at(&mut self, quota: &Self::Quota, now: OffsetDateTime) -> bool {
+        self.verify_cost_at(quota, now, 1).await
+    }
+
+    async fn verify_cost_at(&mut self, quota: &Self::Quota, now: OffsetDateTime, cost: usize) -> bool {
+        let capacity = quota.limit.max(1) as f64;
+        if self.quota.as_ref() != Some(quota) {
+            self.quota = Some(quota.clone());
+            self.tokens = capacity;
+        } else if quota.period <= Duration::ZERO {
+            self.tokens = capacity;
+        } else {
+            let refilled = (now - self.updated) / Self::refill_span(quota);
+            self.tokens = (self.tokens + refilled).min(capacity);
+        }
+        self.updated = now;
+        let cost = cost as f64;
+        if self.tokens >= cost {
+            self.tokens -= cost;
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn remaining(&self, _quota: &Self::Quota) -> usize {
+        self.tokens as usize
+    }
+
+    async fn reset(&self, quota: &Self::Quota) -> i64 {
+        if self.tokens >= 1.0 {
+            return self.updated.unix_timestamp();
+        }
+        let available = self.updated + Self::refill_span(quota) * (1.0 - self.tokens);
+        if available.nanosecond() > 0 {
+            available.unix_timestamp() + 1
+        } else {
+            available.unix_timestamp()
+        }
+    }
+
+    async fn limit(&self, quota: &Self::Quota) -> usize {
+        quota.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bucket_guard() {
+        let quota = BasicQuota::set_seconds(2, 1);
+        let mut guard = BucketGuard::new();
+        assert!(guard.verify(&quota).await);
+        assert!(guard.verify(&quota).await);
+        assert!(!guard.verify(&quota).await);
+        assert_eq!(guard.remaining(&quota).await, 0);
+        assert!(guard.reset(&quota).await >= OffsetDateTime::now_utc().unix_timestamp());
+
+        tokio::time::sleep(std::time::Duration::from_millis(550)).await;
+        assert!(guard.verify(&quota).await);
+        assert!(!guard.verify(&quota).await);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use salvo_core::http::{Method, Request};
+use salvo_core::Depot;
+
+/// Used to get the cost of a request, the units of the quota it consumes.
+///
+/// It is implemented for `usize`, a constant cost, and for closures `Fn(&Request, &Depot) -> usize`.
+pub trait CostGetter: Send + Sync + 'static {
+    /// Get the cost of the request.
+    fn cost(&self, req: &Request, depot: &Depot) -> usize;
+}
+
+impl CostGetter for usize {
+    #[inline]
+    fn cost(&self, _req: &Request, _depot: &Depot) -> usize {
+        *self
+    }
+}
+
+impl<F> CostGetter for F
+where
+    F: Fn(&Request, &Depot) -> usize + Send + Sync + 'static,
+{
+    #[inline]
+    fn cost(&self, req: &Request, depot: &Depot) -> usize {
+        self(req, depot)
+    }
+}
+
+/// Costs declared per route.
+///
+/// Routes are identified by their pattern, as returned by [`Request::matched_path`], like `/search` or
+/// `/users/<id>`, so the costs can be declared once for a [`RateLimiter`](crate::RateLimiter) added to the
+/// [`Service`](salvo_core::Service). Requests of undeclared routes cost the default cost.
+///
+/// # Example
+///
+/// ```
+/// use salvo_core::http::Method;
+/// use salvo_rate_limiter::RouteCosts;
+///
+/// let costs = RouteCosts::new()
+///     .route("/search", 10)
+///     .route_method(Method::POST, "/users", 5);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RouteCosts {
+    routes: HashMap<String, usize>,
+    route_methods: HashMap<(Method, String), usize>,
+    default_cost: usize,
+}
+
+impl Default for RouteCosts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RouteCosts {
+    /// Create a new `RouteCosts`, the default cost is 1.
+    pub fn new() -> Self {
+        Self {
+            routes: HashMap::new(),
+            route_methods: HashMap::new(),
+            default_cost: 1,
+        }
+    }
+
+    /// Sets the cost of requests of routes not declared.
+    pub fn default_cost(mut self, cost: usize) -> Self {
+        self.default_cost = cost;
+        self
+    }
+
+    /// Declares the cost of a route, for all methods.
+    pub fn route(mut self, pattern: impl Into<String>, cost: usize) -> Self {
+        self.routes.insert(pattern.into(), cost);
+        self
+    }
+
+    /// Declares the cost of a route for a method, it overrides the cost declared for all methods.
+    pub fn route_method(mut self, method: Method, pattern: impl Into<String>, cost: usize) -> Self {
+        self.route_methods.insert((method, pattern.into()), cost);
+        self
+    }
+}
+
+impl CostGetter for RouteCosts {
+    fn cost(&self, req: &Request, _depot: &Depot) -> usize {
+        let Some(pattern) = req.matched_path() else {
+            return self.default_cost;
+        };
+        self.route_methods
+            .get(&(req.method().clone(), pattern.to_owned()))
+            .or_else(|| self.routes.get(pattern))
+            .copied()
+            .unwrap_or(self.default_cost)
+    }
+}
@@ -31,7 +31,15 @@ impl FixedGuard {
 impl RateGuard for FixedGuard {
     type Quota = BasicQuota;
     async fn verify(&mut self, quota: &Self::Quota) -> bool {
-        if self.quota.is_none() || OffsetDateTime::now_utc() > self.reset || self.quota.as_ref() != Some(quota) {
+        self.verify_at(quota, OffsetDateTime::now_utc()).await
+    }
+
+    async fn verify_at(&mut self, quota: &Self::Quota, now: OffsetDateTime) -> bool {
+        self.verify_cost_at(quota, now, 1).await
+    }
+
+    async fn verify_cost_at(&mut self, quota: &Self::Quota, now: OffsetDateTime, cost: usize) -> bool {
+        if self.quota.is_none() || now > self.reset || self.quota.as_ref() != Some(quota) {
             if self.quota.as_ref() != Some(quota) {
                 let mut quota = quota.clone();
                 if quota.limit == 0 {
@@ -39,11 +47,11 @@ impl RateGuard for FixedGuard {
                 }
                 self.quota = Some(quota);
             }
-            self.reset = OffsetDateTime::now_utc() + quota.period;
-            self.count = 1;
-            true
-        } else if self.count < quota.limit {
-            self.count += 1;
+            self.reset = now + quota.period;
+            self.count = 0;
+        }
+        if self.count + cost <= quota.limit.max(1) {
+            self.count += cost;
             true
         } else {
             false
@@ -51,7 +59,7 @@ impl RateGuard for FixedGuard {
     }
 
     async fn remaining(&self, quota: &Self::Quota) -> usize {
-        quota.limit - self.count
+        quota.limit.saturating_sub(self.count)
     }
 
     async fn reset(&self, _: &Self::Quota) -> i64 {
@@ -8,7 +8,15 @@
 //!
 //! [`QuotaGetter`] is used to get quota for every key.
 //!
-//! [`RateGuard`] is strategy to verify is the request exceeded quota.
+//! [`CostGetter`] is used to get the cost of every request, the units of the quota it consumes, so expensive
+//! routes like a search can consume more of the quota than a simple get. [`RouteCosts`] declares the costs per
+//! route.
+//!
+//! [`RateGuard`] is strategy to verify is the request exceeded quota, `FixedGuard` (fixed window),
+//! `SlidingGuard` (sliding window) and `BucketGuard` (token bucket) are provided.
+//!
+//! [`RateStore`] is used to store guards, `MokaStore` keeps them in memory and `RedisStore` (`redis-store`
+//! feature) shares them between server instances.
 //!
 //! Read more: <https://salvo.rs>
 #![doc(html_favicon_url = "https://salvo.rs/favicon-32x32.png")]
@@ -20,11 +28,17 @@ use std::error::Error as StdError;
 use std::future::Future;
 use std::hash::Hash;
 
+use time::OffsetDateTime;
+
+use salvo_core::clock::{Clock, SystemClock};
 use salvo_core::conn::SocketAddr;
 use salvo_core::handler::{none_skipper, Skipper};
+use salvo_core::http::header::RETRY_AFTER;
 use salvo_core::http::{HeaderValue, Request, Response, StatusCode, StatusError};
 use salvo_core::{async_trait, Depot, FlowCtrl, Handler};
 
+mod cost;
+pub use cost::{CostGetter, RouteCosts};
 mod quota;
 pub use quota::{BasicQuota, CelledQuota, QuotaGetter};
 #[macro_use]
@@ -37,6 +51,13 @@ cfg_feature! {
     pub use moka_store::MokaStore;
 }
 
+cfg_feature! {
+    #![feature = "redis-store"]
+
+    mod redis_store;
+    pub use redis_store::RedisStore;
+}
+
 cfg_feature! {
     #![feature = "fixed-guard"]
 
@@ -51,6 +72,13 @@ cfg_feature! {
     pub use sliding_guard::SlidingGuard;
 }
 
+cfg_feature! {
+    #![feature = "bucket-guard"]
+
+    mod bucket_guard;
+    pub use bucket_guard::BucketGuard;
+}
+
 /// Issuer is used to identify every request.
 pub trait RateIssuer: Send + Sync + 'static {
     /// The key is used to identify the rate limit.
@@ -89,6 +117,36 @@ pub trait RateGuard: Clone + Send + Sync + 'static {
     /// Verify is current request exceed the quota.
     fn verify(&mut self, quota: &Self::Quota) -> impl Future<Output = bool> + Send;
 
+    /// Verify is current request exceed the quota, with `now` as the current time.
+    ///
+    /// [`RateLimiter`] calls this method with the time of its [`Clock`], the default implementation ignores `now`
+    /// and calls [`verify`](RateGuard::verify).
+    fn verify_at(&mut self, quota: &Self::Quota, now: OffsetDateTime) -> impl Future<Output = bool> + Send {
+        let _ = now;
+        self.verify(quota)
+    }
+
+    /// Verify is current request exceed the quota, with `now` as the current time and `cost` as the units of the
+    /// quota consumed by the request.
+    ///
+    /// [`RateLimiter`] calls this method with the cost of its [`CostGetter`], the default implementation calls
+    /// [`verify_at`](RateGuard::verify_at) `cost` times, so a rejected request may consume a part of the quota.
+    fn verify_cost_at(
+        &mut self,
+        quota: &Self::Quota,
+        now: OffsetDateTime,
+        cost: usize,
+    ) -> impl Future<Output = bool> + Send {
+        async move {
+            for _ in 0..cost {
+                if !self.verify_at(quota, now).await {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+
     /// Returns the remaining quota.
     fn remaining(&self, quota: &Self::Quota) -> impl Future<Output = usize> + Send;
 
@@ -118,16 +176,36 @@ pub trait RateStore: Send + Sync + 'static {
         Q: Hash + Eq + Sync;
     /// Save the guard from the store.
     fn save_guard(&self, key: Self::Key, guard: Self::Guard) -> impl Future<Output = Result<(), Self::Error>> + Send;
+    /// Save the guard if the stored guard is still `loaded`, returns `false` without saving it otherwise, then the
+    /// guard is loaded and verified again.
+    ///
+    /// Stores shared by server instances should replace guards atomically, the default implementation always saves
+    /// the guard.
+    fn replace_guard(
+        &self,
+        key: Self::Key,
+        loaded: &Self::Guard,
+        guard: Self::Guard,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+        let _ = loaded;
+        let save = self.save_guard(key, guard);
+        async move { save.await.map(|()| true) }
+    }
 }
 
+/// Max attempts to verify a request while its guard is replaced by concurrent requests.
+const REPLACE_ATTEMPTS: usize = 8;
+
 /// `RateLimiter` is the main struct to used limit user request.
 pub struct RateLimiter<G, S, I, Q> {
     guard: G,
     store: S,
     issuer: I,
     quota_getter: Q,
+    cost_getter: Box<dyn CostGetter>,
     add_headers: bool,
     skipper: Box<dyn Skipper>,
+    clock: Box<dyn Clock>,
 }
 
 impl<G: RateGuard, S: RateStore, I: RateIssuer, P: QuotaGetter<I::Key>> RateLimiter<G, S, I, P> {
@@ -139,11 +217,33 @@ impl<G: RateGuard, S: RateStore, I: RateIssuer, P: QuotaGetter<I::Key>> RateLimi
             store,
             issuer,
             quota_getter,
+            cost_getter: Box::new(1),
             add_headers: false,
             skipper: Box::new(none_skipper),
+            clock: Box::new(SystemClock),
         }
     }
 
+    /// Sets the [`Clock`] giving the current time to guards, default is [`SystemClock`].
+    ///
+    /// A [`MockClock`](salvo_core::clock::MockClock) can be used in tests, so quotas can be verified without
+    /// sleeping.
+    #[inline]
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Sets the [`CostGetter`] giving the units of the quota consumed by requests, every request costs 1 by
+    /// default.
+    ///
+    /// Requests costing more than the remaining quota are rejected without consuming it.
+    #[inline]
+    pub fn with_cost(mut self, cost_getter: impl CostGetter) -> Self {
+        self.cost_getter = Box::new(cost_getter);
+        self
+    }
+
     /// Sets skipper and returns new `RateLimiter`.
     #[inline]
     pub fn with_skipper(mut self, skipper: impl Skipper) -> Self {
@@ -153,6 +253,11 @@ impl<G: RateGuard, S: RateStore, I: RateIssuer, P: QuotaGetter<I::Key>> RateLimi
 
     /// Sets `add_headers` and returns new `RateLimiter`.
     /// If `add_headers` is true, the rate limit headers will be added to the response.
+    ///
+    /// Both the [standard](https://datatracker.ietf.org/doc/draft-ietf-httpapi-ratelimit-headers/) `RateLimit-Limit`,
+    /// `RateLimit-Remaining`, `RateLimit-Reset` (seconds until reset) headers and the legacy `X-RateLimit-*`
+    /// headers (`X-RateLimit-Reset` is a unix timestamp) are added. `X-RateLimit-Used` is the part of the limit
+    /// already consumed and `X-RateLimit-Cost` the cost of the request.
     #[inline]
     pub fn add_headers(mut self, add_headers: bool) -> Self {
         self.add_headers = add_headers;
@@ -189,38 +294,60 @@ where
                 return;
             }
         };
-        let mut guard = match self.store.load_guard(&key, &self.guard).await {
-            Ok(guard) => guard,
-            Err(e) => {
-                tracing::error!(error = ?e, "RateLimiter error: {}", e);
-                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
-                ctrl.skip_rest();
-                return;
+        let cost = self.cost_getter.cost(req, depot);
+        let mut attempts = 0;
+        let (guard, verified, now) = loop {
+            let loaded = match self.store.load_guard(&key, &self.guard).await {
+                Ok(guard) => guard,
+                Err(e) => {
+                    tracing::error!(error = ?e, "RateLimiter error: {}", e);
+                    res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+                    ctrl.skip_rest();
+                    return;
+                }
+            };
+            let mut guard = loaded.clone();
+            let now = OffsetDateTime::from(self.clock.now());
+            let verified = guard.verify_cost_at(&quota, now, cost).await;
+            attempts += 1;
+            match self.store.replace_guard(key.clone(), &loaded, guard.clone()).await {
+                Ok(true) => break (guard, verified, now),
+                Ok(false) if attempts < REPLACE_ATTEMPTS => {}
+                Ok(false) => {
+                    tracing::warn!("RateLimiter guard replaced by concurrent requests, it is not saved");
+                    break (guard, verified, now);
+                }
+                Err(e) => {
+                    tracing::error!(error = ?e, "RateLimiter save guard failed");
+                    break (guard, verified, now);
+                }
             }
         };
-        let verified = guard.verify(&quota).await;
 
+        let reset = guard.reset(&quota).await;
+        let reset_after = (reset - now.unix_timestamp()).max(0);
         if self.add_headers {
-            res.headers_mut().insert(
-                "X-RateLimit-Limit",
-                HeaderValue::from_str(&guard.limit(&quota).await.to_string()).expect("Invalid header value"),
-            );
-            res.headers_mut().insert(
-                "X-RateLimit-Remaining",
-                HeaderValue::from_str(&(guard.remaining(&quota).await).to_string()).expect("Invalid header value"),
-            );
-            res.headers_mut().insert(
-                "X-RateLimit-Reset",
-                HeaderValue::from_str(&guard.reset(&quota).await.to_string()).expect("Invalid header value"),
-            );
+            let limit = guard.limit(&quota).await;
+            let remaining = guard.remaining(&quota).await;
+            let used = HeaderValue::from(limit.saturating_sub(remaining));
+            let limit = HeaderValue::from(limit);
+            let remaining = HeaderValue::from(remaining);
+            let headers = res.headers_mut();
+            headers.insert("X-RateLimit-Limit", limit.clone());
+            headers.insert("X-RateLimit-Remaining", remaining.clone());
+            headers.insert("X-RateLimit-Reset", HeaderValue::from(reset));
+            headers.insert("X-RateLimit-Used", used);
+            headers.insert("X-RateLimit-Cost", HeaderValue::from(cost));
+            headers.insert("RateLimit-Limit", limit);
+            headers.insert("RateLimit-Remaining", remaining);
+            headers.insert("RateLimit-Reset", HeaderValue::from(reset_after));
         }
         if !verified {
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(reset_after.max(1)));
             res.status_code(StatusCode::TOO_MANY_REQUESTS);
             ctrl.skip_rest();
         }
-        if let Err(e) = self.store.save_guard(key, guard).await {
-            tracing::error!(error = ?e, "RateLimiter save guard failed");
-        }
     }
 }
 
@@ -407,4 +534,182 @@ mod tests {
         assert_eq!(respone.status_code, Some(StatusCode::OK));
         assert_eq!(respone.take_string().await.unwrap(), "Limited page");
     }
+
+    #[tokio::test]
+    async fn test_bucket_headers() {
+        let limiter = RateLimiter::new(
+            BucketGuard::default(),
+            MokaStore::default(),
+            UserIssuer,
+            BasicQuota::set_seconds(2, 10),
+        )
+        .add_headers(true);
+        let router = Router::new().push(Router::with_path("limited").hoop(limiter).get(limited));
+        let service = Service::new(router);
+
+        let respone = TestClient::get("http://127.0.0.1:5800/limited?user=user1")
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::OK));
+        assert_eq!(respone.headers()["RateLimit-Limit"], "2");
+        assert_eq!(respone.headers()["RateLimit-Remaining"], "1");
+        assert_eq!(respone.headers()["RateLimit-Reset"], "0");
+
+        let respone = TestClient::get("http://127.0.0.1:5800/limited?user=user1")
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::OK));
+        assert_eq!(respone.headers()["RateLimit-Remaining"], "0");
+
+        let respone = TestClient::get("http://127.0.0.1:5800/limited?user=user1")
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::TOO_MANY_REQUESTS));
+        let retry_after: i64 = respone.headers()[RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((4..=6).contains(&retry_after));
+    }
+
+    #[tokio::test]
+    async fn test_route_costs() {
+        let limiter = RateLimiter::new(
+            FixedGuard::default(),
+            MokaStore::default(),
+            UserIssuer,
+            BasicQuota::set_seconds(12, 60),
+        )
+        .with_cost(RouteCosts::new().route("/search", 10))
+        .add_headers(true);
+        let router = Router::new()
+            .push(Router::with_path("search").get(limited))
+            .push(Router::with_path("limited").get(limited));
+        let service = Service::new(router).hoop(limiter);
+
+        let respone = TestClient::get("http://127.0.0.1:5800/search?user=user1")
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::OK));
+        assert_eq!(respone.headers()["X-RateLimit-Remaining"], "2");
+        assert_eq!(respone.headers()["X-RateLimit-Used"], "10");
+        assert_eq!(respone.headers()["X-RateLimit-Cost"], "10");
+
+        let respone = TestClient::get("http://127.0.0.1:5800/search?user=user1")
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(respone.headers()["X-RateLimit-Remaining"], "2");
+
+        for remaining in ["1", "0"] {
+            let respone = TestClient::get("http://127.0.0.1:5800/limited?user=user1")
+                .send(&service)
+                .await;
+            assert_eq!(respone.status_code, Some(StatusCode::OK));
+            assert_eq!(respone.headers()["X-RateLimit-Remaining"], remaining);
+            assert_eq!(respone.headers()["X-RateLimit-Cost"], "1");
+        }
+        let respone = TestClient::get("http://127.0.0.1:5800/limited?user=user1")
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    #[tokio::test]
+    async fn test_bucket_cost() {
+        let quota = BasicQuota::set_seconds(10, 10);
+        let mut guard = BucketGuard::default();
+        let now = OffsetDateTime::now_utc();
+        assert!(guard.verify_cost_at(&quota, now, 8).await);
+        assert!(!guard.verify_cost_at(&quota, now, 3).await);
+        assert_eq!(guard.remaining(&quota).await, 2);
+        assert!(guard.verify_cost_at(&quota, now + time::Duration::seconds(1), 3).await);
+        assert_eq!(guard.remaining(&quota).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_mock_clock() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        use salvo_core::clock::MockClock;
+
+        let clock = MockClock::with_time(UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let limiter = RateLimiter::new(
+            BucketGuard::default(),
+            MokaStore::default(),
+            UserIssuer,
+            BasicQuota::set_seconds(2, 10),
+        )
+        .with_clock(clock.clone());
+        let service = Service::new(Router::new().hoop(limiter).get(limited));
+        let url = "http://127.0.0.1:5800/?user=user1";
+
+        for _ in 0..2 {
+            let respone = TestClient::get(url).send(&service).await;
+            assert_eq!(respone.status_code, Some(StatusCode::OK));
+        }
+        let respone = TestClient::get(url).send(&service).await;
+        assert_eq!(respone.status_code, Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(respone.headers()[RETRY_AFTER], "5");
+
+        clock.advance(Duration::from_secs(4));
+        let respone = TestClient::get(url).send(&service).await;
+        assert_eq!(respone.status_code, Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(respone.headers()[RETRY_AFTER], "1");
+
+        clock.advance(Duration::from_secs(1));
+        let respone = TestClient::get(url).send(&service).await;
+        assert_eq!(respone.status_code, Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_replace_guard_conflict() {
+        /// A store where a concurrent request consumes the quota while the first request is verified.
+        struct RacingStore {
+            inner: MokaStore<String, FixedGuard>,
+            raced: std::sync::atomic::AtomicBool,
+        }
+        impl RateStore for RacingStore {
+            type Error = std::convert::Infallible;
+            type Key = String;
+            type Guard = FixedGuard;
+
+            async fn load_guard<Q>(&self, key: &Q, refer: &Self::Guard) -> Result<Self::Guard, Self::Error>
+            where
+                String: Borrow<Q>,
+                Q: Hash + Eq + Sync,
+            {
+                self.inner.load_guard(key, refer).await
+            }
+
+            async fn save_guard(&self, key: Self::Key, guard: Self::Guard) -> Result<(), Self::Error> {
+                self.inner.save_guard(key, guard).await
+            }
+
+            async fn replace_guard(
+                &self,
+                key: Self::Key,
+                loaded: &Self::Guard,
+                guard: Self::Guard,
+            ) -> Result<bool, Self::Error> {
+                if self.raced.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                    return self.inner.save_guard(key, guard).await.map(|()| true);
+                }
+                let mut concurrent = loaded.clone();
+                concurrent.verify(&BasicQuota::per_minute(1)).await;
+                self.inner.save_guard(key, concurrent).await?;
+                Ok(false)
+            }
+        }
+
+        let store = RacingStore {
+            inner: MokaStore::default(),
+            raced: Default::default(),
+        };
+        let limiter = RateLimiter::new(FixedGuard::default(), store, UserIssuer, BasicQuota::per_minute(1));
+        let router = Router::new().push(Router::with_path("limited").hoop(limiter).get(limited));
+        let service = Service::new(router);
+
+        let respone = TestClient::get("http://127.0.0.1:5800/limited?user=user1")
+            .send(&service)
+            .await;
+        assert_eq!(respone.status_code, Some(StatusCode::TOO_MANY_REQUESTS));
+    }
 }
@@ -0,0 +1,189 @@
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ErrorKind, RedisError, Script};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{RateGuard, RateStore};
+
+/// A store keeps rate limit data in Redis, so the quota is shared by all server instances.
+///
+/// Guards are saved as JSON, the Redis key is `prefix` followed by the FNV-1a hash of the rate key, which does not
+/// depend on the process or on the pointer width, so all instances use the same Redis keys.
+///
+/// Guards are replaced with a Lua script which only saves them if the stored guard is still the loaded one, so
+/// concurrent requests of all instances are verified one after the other. Guards must be serialized to the same JSON
+/// after being deserialized, which is the case of the provided guards.
+pub struct RedisStore<K, G> {
+    conn: ConnectionManager,
+    prefix: String,
+    ttl: Option<Duration>,
+    load_script: Script,
+    replace_script: Script,
+    _phantom: PhantomData<fn() -> (K, G)>,
+}
+
+/// Gets the guard, the reference guard is saved first if there is none, so it can be replaced.
+const LOAD_SCRIPT: &str = r"
+local value = redis.call('GET', KEYS[1])
+if value then
+    return value
+end
+if ARGV[2] == '0' then
+    redis.call('SET', KEYS[1], ARGV[1])
+else
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+end
+return ARGV[1]
+";
+
+/// Saves the guard in `ARGV[2]` if the stored guard is still `ARGV[1]`, returns whether it was saved.
+const REPLACE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+    return 0
+end
+if ARGV[3] == '0' then
+    redis.call('SET', KEYS[1], ARGV[2])
+else
+    redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+end
+return 1
+";
+
+impl<K, G> RedisStore<K, G> {
+    /// Create a new `RedisStore`.
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            prefix: "salvo:rate_limiter:".into(),
+            ttl: None,
+            load_script: Script::new(LOAD_SCRIPT),
+            replace_script: Script::new(REPLACE_SCRIPT),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Sets the prefix of Redis keys, defaults to `salvo:rate_limiter:`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets the expiry of Redis keys, it should be longer than the period of quotas.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the expiry of Redis keys in seconds, `0` if they do not expire.
+    fn ttl_secs(&self) -> u64 {
+        self.ttl.map(|ttl| ttl.as_secs().max(1)).unwrap_or(0)
+    }
+
+    fn redis_key<Q: Hash + ?Sized>(&self, key: &Q) -> String {
+        let mut hasher = FnvHasher::default();
+        key.hash(&mut hasher);
+        format!("{}{:016x}", self.prefix, hasher.finish())
+    }
+}
+
+/// 64-bit FNV-1a hasher, stable unlike `DefaultHasher`, whose algorithm may change between Rust versions.
+struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        // Lengths are hashed as 64 bits, so 32 and 64 bits targets have the same hashes.
+        self.write(&(i as u64).to_le_bytes());
+    }
+}
+
+impl<K, G> RateStore for RedisStore<K, G>
+where
+    K: Hash + Eq + Send + Sync + Clone + 'static,
+    G: RateGuard + Serialize + DeserializeOwned,
+{
+    type Error = RedisError;
+    type Key = K;
+    type Guard = G;
+
+    async fn load_guard<Q>(&self, key: &Q, refer: &Self::Guard) -> Result<Self::Guard, Self::Error>
+    where
+        Self::Key: Borrow<Q>,
+        Q: Hash + Eq + Sync,
+    {
+        let mut conn = self.conn.clone();
+        let value: String = self
+            .load_script
+            .key(self.redis_key(key))
+            .arg(to_json(refer)?)
+            .arg(self.ttl_secs())
+            .invoke_async(&mut conn)
+            .await?;
+        serde_json::from_str(&value)
+            .map_err(|e| RedisError::from((ErrorKind::TypeError, "invalid rate guard", e.to_string())))
+    }
+
+    async fn save_guard(&self, key: Self::Key, guard: Self::Guard) -> Result<(), Self::Error> {
+        let value = to_json(&guard)?;
+        let mut conn = self.conn.clone();
+        let redis_key = self.redis_key(&key);
+        match self.ttl {
+            Some(ttl) => conn.set_ex(redis_key, value, ttl.as_secs().max(1)).await,
+            None => conn.set(redis_key, value).await,
+        }
+    }
+
+    async fn replace_guard(
+        &self,
+        key: Self::Key,
+        loaded: &Self::Guard,
+        guard: Self::Guard,
+    ) -> Result<bool, Self::Error> {
+        let mut conn = self.conn.clone();
+        self.replace_script
+            .key(self.redis_key(&key))
+            .arg(to_json(loaded)?)
+            .arg(to_json(&guard)?)
+            .arg(self.ttl_secs())
+            .invoke_async(&mut conn)
+            .await
+    }
+}
+
+fn to_json<G: Serialize>(guard: &G) -> Result<String, RedisError> {
+    serde_json::to_string(guard)
+        .map_err(|e| RedisError::from((ErrorKind::TypeError, "invalid rate guard", e.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv_hasher() {
+        let mut hasher = FnvHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+}
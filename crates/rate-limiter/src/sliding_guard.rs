@@ -1,9 +1,10 @@
+use serde::{Deserialize, Serialize};
 use time::{Duration, OffsetDateTime};
 
 use super::{CelledQuota, RateGuard};
 
 /// Sliding window implement.
-#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct SlidingGuard {
     cell_inst: OffsetDateTime,
     cell_span: Duration,
@@ -34,32 +35,39 @@ impl SlidingGuard {
 impl RateGuard for SlidingGuard {
     type Quota = CelledQuota;
     async fn verify(&mut self, quota: &Self::Quota) -> bool {
+        self.verify_at(quota, OffsetDateTime::now_utc()).await
+    }
+
+    async fn verify_at(&mut self, quota: &Self::Quota, now: OffsetDateTime) -> bool {
+        self.verify_cost_at(quota, now, 1).await
+    }
+
+    async fn verify_cost_at(&mut self, quota: &Self::Quota, now: OffsetDateTime, cost: usize) -> bool {
+        // A limit of 0 is handled as a limit of 1, like the number of cells.
+        let limit = quota.limit.max(1);
         if self.quota.is_none() || self.quota.as_ref() != Some(quota) {
-            let mut quota = quota.clone();
-            if quota.limit == 0 {
-                quota.limit = 1;
-            }
-            if quota.cells == 0 {
-                quota.cells = 1;
-            }
-            if quota.cells > quota.limit {
-                quota.cells = quota.limit;
-            }
-            self.cell_inst = OffsetDateTime::now_utc();
-            self.cell_span = quota.period / (quota.cells as u32);
-            self.counts = vec![0; quota.cells];
+            let cells = quota.cells.clamp(1, limit);
+            self.cell_inst = now;
+            self.cell_span = quota.period / (cells as u32);
+            self.counts = vec![0; cells];
             self.head = 0;
-            self.counts[0] = 1;
-            self.quota = Some(quota);
-            return true;
+            let verified = cost <= limit;
+            if verified {
+                self.counts[0] = cost;
+            }
+            self.quota = Some(quota.clone());
+            return verified;
         }
-        let mut delta = OffsetDateTime::now_utc() - self.cell_inst;
+        let mut delta = now - self.cell_inst;
         if delta > quota.period {
-            self.counts = vec![0; quota.cells];
+            self.counts.fill(0);
             self.head = 0;
-            self.counts[0] = 1;
-            self.cell_inst = OffsetDateTime::now_utc();
-            return true;
+            self.cell_inst = now;
+            let verified = cost <= limit;
+            if verified {
+                self.counts[0] = cost;
+            }
+            return verified;
         } else {
             while delta > self.cell_span {
                 delta -= self.cell_span;
@@ -67,10 +75,14 @@ impl RateGuard for SlidingGuard {
                 self.counts[self.head] = 0;
             }
             self.head = (self.head + 1) % self.counts.len();
-            self.counts[self.head] += 1;
-            self.cell_inst = OffsetDateTime::now_utc();
+            self.cell_inst = now;
+        }
+        if self.counts.iter().sum::<usize>() + cost <= limit {
+            self.counts[self.head] += cost;
+            true
+        } else {
+            false
         }
-        self.counts.iter().cloned().sum::<usize>() <= quota.limit
     }
 
     async fn remaining(&self, quota: &Self::Quota) -> usize {
@@ -18,11 +18,26 @@ cfg_feature! {
     #[doc(no_inline)]
     pub use salvo_extra::affix;
 }
+cfg_feature! {
+    #![feature ="api-key-auth"]
+    #[doc(no_inline)]
+    pub use salvo_extra::api_key_auth;
+}
+cfg_feature! {
+    #![feature ="authorization"]
+    #[doc(no_inline)]
+    pub use salvo_extra::authorization;
+}
 cfg_feature! {
     #![feature ="basic-auth"]
     #[doc(no_inline)]
     pub use salvo_extra::basic_auth;
 }
+cfg_feature! {
+    #![feature ="bearer-auth"]
+    #[doc(no_inline)]
+    pub use salvo_extra::bearer_auth;
+}
 cfg_feature! {
     #![feature ="caching-headers"]
     #[doc(no_inline)]
@@ -43,16 +58,36 @@ cfg_feature! {
     #[doc(no_inline)]
     pub use salvo_extra::force_https;
 }
+cfg_feature! {
+    #![feature ="ip-filter"]
+    #[doc(no_inline)]
+    pub use salvo_extra::ip_filter;
+}
 cfg_feature! {
     #![feature ="jwt-auth"]
     #[doc(no_inline)]
     pub use salvo_jwt_auth as jwt_auth;
 }
+cfg_feature! {
+    #![feature ="locale"]
+    #[doc(no_inline)]
+    pub use salvo_extra::locale;
+}
 cfg_feature! {
     #![feature ="logging"]
     #[doc(no_inline)]
     pub use salvo_extra::logging;
 }
+cfg_feature! {
+    #![feature ="long-poll"]
+    #[doc(no_inline)]
+    pub use salvo_extra::long_poll;
+}
+cfg_feature! {
+    #![feature ="maintenance"]
+    #[doc(no_inline)]
+    pub use salvo_extra::maintenance;
+}
 cfg_feature! {
     #![feature ="concurrency-limiter"]
     #[doc(no_inline)]
@@ -88,6 +123,56 @@ cfg_feature! {
     #[doc(no_inline)]
     pub use salvo_extra::request_id;
 }
+cfg_feature! {
+    #![feature ="secure-headers"]
+    #[doc(no_inline)]
+    pub use salvo_extra::secure_headers;
+}
+cfg_feature! {
+    #![feature ="prometheus"]
+    #[doc(no_inline)]
+    pub use salvo_extra::prometheus;
+}
+cfg_feature! {
+    #![feature ="health-check"]
+    #[doc(no_inline)]
+    pub use salvo_extra::health_check;
+}
+cfg_feature! {
+    #![feature ="audit"]
+    #[doc(no_inline)]
+    pub use salvo_extra::audit;
+}
+cfg_feature! {
+    #![feature ="slow-request"]
+    #[doc(no_inline)]
+    pub use salvo_extra::slow_request;
+}
+cfg_feature! {
+    #![feature ="server-stats"]
+    #[doc(no_inline)]
+    pub use salvo_extra::server_stats;
+}
+cfg_feature! {
+    #![feature ="rewrite"]
+    #[doc(no_inline)]
+    pub use salvo_extra::rewrite;
+}
+cfg_feature! {
+    #![feature ="tus"]
+    #[doc(no_inline)]
+    pub use salvo_extra::tus;
+}
+cfg_feature! {
+    #![feature ="webhook-signature"]
+    #[doc(no_inline)]
+    pub use salvo_extra::webhook_signature;
+}
+cfg_feature! {
+    #![feature ="response-transform"]
+    #[doc(no_inline)]
+    pub use salvo_extra::response_transform;
+}
 cfg_feature! {
     #![feature ="cache"]
     #[doc(no_inline)]
@@ -118,6 +203,11 @@ cfg_feature! {
     #[doc(no_inline)]
     pub use salvo_rate_limiter as rate_limiter;
 }
+cfg_feature! {
+    #![feature ="oauth"]
+    #[doc(no_inline)]
+    pub use salvo_oauth as oauth;
+}
 cfg_feature! {
     #![feature ="session"]
     #[doc(no_inline)]
@@ -133,6 +223,11 @@ cfg_feature! {
     #[doc(no_inline)]
     pub use salvo_otel as otel;
 }
+cfg_feature! {
+    #![feature ="lambda"]
+    #[doc(no_inline)]
+    pub use salvo_lambda as lambda;
+}
 cfg_feature! {
     #![feature ="oapi"]
     #[doc(no_inline)]
@@ -146,13 +241,27 @@ pub mod prelude {
         #![feature ="affix"]
         pub use salvo_extra::affix;
     }
+    cfg_feature! {
+        #![feature ="api-key-auth"]
+        pub use salvo_extra::api_key_auth::{ApiKeyAuth, ApiKeyDepotExt, ApiKeyInfo, KeyStore};
+    }
+    cfg_feature! {
+        #![feature ="authorization"]
+        pub use salvo_extra::authorization::{
+            requires, Authorization, AuthorizationDepotExt, PermissionChecker, Principal,
+        };
+    }
     cfg_feature! {
         #![feature ="basic-auth"]
         pub use salvo_extra::basic_auth::{BasicAuth, BasicAuthDepotExt, BasicAuthValidator};
     }
+    cfg_feature! {
+        #![feature ="bearer-auth"]
+        pub use salvo_extra::bearer_auth::{BearerAuth, BearerAuthDepotExt, BearerAuthValidator};
+    }
     cfg_feature! {
         #![feature ="caching-headers"]
-        pub use salvo_extra::caching_headers::CachingHeaders;
+        pub use salvo_extra::caching_headers::{CacheControl, CachingHeaders};
     }
     cfg_feature! {
         #![feature ="catch-panic"]
@@ -160,16 +269,20 @@ pub mod prelude {
     }
     cfg_feature! {
         #![feature ="compression"]
-        pub use salvo_compression::{Compression, CompressionAlgo, CompressionLevel};
+        pub use salvo_compression::{Compression, CompressionAlgo, CompressionLevel, Decompression};
     }
     cfg_feature! {
         #![feature ="csrf"]
-        pub use salvo_csrf::CsrfDepotExt;
+        pub use salvo_csrf::{CsrfDepotExt, OriginCheck};
     }
     cfg_feature! {
         #![feature ="force-https"]
         pub use salvo_extra::force_https::ForceHttps;
     }
+    cfg_feature! {
+        #![feature ="ip-filter"]
+        pub use salvo_extra::ip_filter::IpFilter;
+    }
     cfg_feature! {
         #![feature ="jwt-auth"]
         pub use salvo_jwt_auth::{JwtAuthDepotExt, JwtAuth, JwtAuthState};
@@ -178,9 +291,21 @@ pub mod prelude {
         #![feature ="logging"]
         pub use salvo_extra::logging::Logger;
     }
+    cfg_feature! {
+        #![feature ="long-poll"]
+        pub use salvo_extra::long_poll::LongPoll;
+    }
+    cfg_feature! {
+        #![feature ="maintenance"]
+        pub use salvo_extra::maintenance::{Maintenance, MaintenanceSwitch};
+    }
+    cfg_feature! {
+        #![feature ="oauth"]
+        pub use salvo_oauth::{OAuth, OAuthDepotExt};
+    }
     cfg_feature! {
         #![feature ="proxy"]
-        pub use salvo_proxy::Proxy;
+        pub use salvo_proxy::{ForwardAuth, Proxy};
     }
     cfg_feature! {
         #![feature ="session"]
@@ -214,6 +339,22 @@ pub mod prelude {
         #![feature ="request-id"]
         pub use salvo_extra::request_id::RequestId;
     }
+    cfg_feature! {
+        #![feature ="secure-headers"]
+        pub use salvo_extra::secure_headers::{SecureHeaders, SecureHeadersDepotExt};
+    }
+    cfg_feature! {
+        #![feature ="prometheus"]
+        pub use salvo_extra::prometheus::{metrics_handler, PrometheusMetrics, PrometheusRegistry};
+    }
+    cfg_feature! {
+        #![feature ="health-check"]
+        pub use salvo_extra::health_check::HealthCheck;
+    }
+    cfg_feature! {
+        #![feature ="response-transform"]
+        pub use salvo_extra::response_transform::{BodyTransformer, ResponseTransform};
+    }
     cfg_feature! {
         #![feature ="serve-static"]
         pub use salvo_serve_static::{StaticFile, StaticDir};
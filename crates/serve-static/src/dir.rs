@@ -1,6 +1,6 @@
 //! serve static dir
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt::{self, Display, Write};
 use std::fs::Metadata;
@@ -9,8 +9,8 @@ use std::str::FromStr;
 use std::time::SystemTime;
 
 use salvo_core::fs::NamedFile;
-use salvo_core::http::header::ACCEPT_ENCODING;
-use salvo_core::http::{self, HeaderValue, Request, Response, StatusCode, StatusError};
+use salvo_core::http::header::{ACCEPT_ENCODING, CACHE_CONTROL, VARY};
+use salvo_core::http::{self, HeaderValue, Method, Request, Response, StatusCode, StatusError};
 use salvo_core::writing::Text;
 use salvo_core::{async_trait, Depot, FlowCtrl, Handler, IntoVecString};
 use serde::{Deserialize, Serialize};
@@ -146,6 +146,14 @@ pub struct StaticDir {
     pub defaults: Vec<String>,
     /// Fallback file name. This is used when the requested file is not found.
     pub fallback: Option<String>,
+    /// Index file of a single page application, served for unknown routes.
+    pub spa: Option<String>,
+    /// Path prefixes which are never answered with the single page application index.
+    pub spa_excludes: Vec<String>,
+    /// `Cache-Control` header of HTML documents.
+    pub index_cache_control: Option<HeaderValue>,
+    /// `Cache-Control` header of other files.
+    pub assets_cache_control: Option<HeaderValue>,
 }
 impl StaticDir {
     /// Create new `StaticDir`.
@@ -166,6 +174,10 @@ impl StaticDir {
             compressed_variations,
             defaults: vec![],
             fallback: None,
+            spa: None,
+            spa_excludes: vec![],
+            index_cache_control: None,
+            assets_cache_control: None,
         }
     }
 
@@ -219,6 +231,39 @@ impl StaticDir {
         self
     }
 
+    /// Enables single page application mode, `index` is served with `200 OK` for unknown routes.
+    ///
+    /// Only `GET` and `HEAD` requests whose last path segment has no extension are unknown routes, so missing
+    /// assets like `/assets/app.js` are still `404 Not Found`. Existing files are served as usual.
+    #[inline]
+    pub fn spa(mut self, index: impl Into<String>) -> Self {
+        self.spa = Some(index.into());
+        self
+    }
+
+    /// Excludes a path prefix like `/api` from single page application mode, unknown paths under it
+    /// are `404 Not Found`.
+    #[inline]
+    pub fn spa_exclude(mut self, prefix: impl Into<String>) -> Self {
+        self.spa_excludes.push(prefix.into());
+        self
+    }
+
+    /// Sets the `Cache-Control` header of HTML documents, like `no-cache` so new releases are seen at once.
+    #[inline]
+    pub fn index_cache_control(mut self, value: HeaderValue) -> Self {
+        self.index_cache_control = Some(value);
+        self
+    }
+
+    /// Sets the `Cache-Control` header of files other than HTML documents, like
+    /// `public, max-age=31536000, immutable` for assets with hashed file names.
+    #[inline]
+    pub fn assets_cache_control(mut self, value: HeaderValue) -> Self {
+        self.assets_cache_control = Some(value);
+        self
+    }
+
     /// During the file chunk read, the maximum read size at one time will affect the
     /// access experience and the demand for server memory.
     ///
@@ -231,6 +276,25 @@ impl StaticDir {
         self
     }
 
+    #[inline]
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude_filters.iter().any(|filter| filter(path))
+    }
+
+    fn is_spa_route(&self, req: &Request, rel_path: &str) -> bool {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return false;
+        }
+        let path = req.uri().path();
+        let excluded = self.spa_excludes.iter().any(|prefix| {
+            let prefix = prefix.trim_end_matches('/');
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        });
+        let last = rel_path.rsplit('/').next().unwrap_or_default();
+        !excluded && !last.contains('.')
+    }
+
     #[inline]
     fn is_compressed_ext(&self, ext: &str) -> bool {
         for exts in self.compressed_variations.values() {
@@ -297,19 +361,14 @@ impl Handler for StaticDir {
         let rel_path = format_url_path_safely(&rel_path);
         let mut files: HashMap<String, Metadata> = HashMap::new();
         let mut dirs: HashMap<String, Metadata> = HashMap::new();
-        let is_dot_file = Path::new(&rel_path)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .map(|s| s.starts_with('.'))
-            .unwrap_or(false);
+        // Files in dot directories, like `.git/config`, are dot files too.
+        let is_dot_file = rel_path.split('/').any(|part| part.starts_with('.'));
         let mut abs_path = None;
         if self.include_dot_files || !is_dot_file {
             for root in &self.roots {
                 let raw_path = join_path!(root, &rel_path);
-                for filter in &self.exclude_filters {
-                    if filter(&raw_path) {
-                        continue;
-                    }
+                if self.is_excluded(&raw_path) {
+                    continue;
                 }
                 let path = Path::new(&raw_path);
                 if path.is_dir() {
@@ -337,14 +396,21 @@ impl Handler for StaticDir {
                 }
             }
         }
+        if let Some(index) = self.spa.as_deref() {
+            if abs_path.is_none() && self.is_spa_route(req, &rel_path) {
+                abs_path = self
+                    .roots
+                    .iter()
+                    .map(|root| PathBuf::from(join_path!(root, index)))
+                    .find(|path| path.is_file());
+            }
+        }
         let fallback = self.fallback.as_deref().unwrap_or_default();
         if abs_path.is_none() && !fallback.is_empty() {
             for root in &self.roots {
                 let raw_path = join_path!(root, fallback);
-                for filter in &self.exclude_filters {
-                    if filter(&raw_path) {
-                        continue;
-                    }
+                if self.is_excluded(&raw_path) {
+                    continue;
                 }
                 let path = Path::new(&raw_path);
                 if path.is_file() {
@@ -374,29 +440,24 @@ impl Handler for StaticDir {
                         .get(ACCEPT_ENCODING)
                         .and_then(|v| v.to_str().ok())
                         .unwrap_or_default();
+                    // Algorithms are sorted by the preference of the client.
                     let accept_algos = http::parse_accept_encoding(header)
                         .into_iter()
-                        .filter_map(|(algo, _level)| {
-                            if let Ok(algo) = algo.parse::<CompressionAlgo>() {
-                                Some(algo)
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<HashSet<_>>();
-                    for (algo, exts) in &self.compressed_variations {
-                        if accept_algos.contains(algo) {
-                            for zip_ext in exts {
-                                let mut path = abs_path.clone();
-                                path.as_mut_os_string().push(&*format!(".{}", zip_ext));
-                                if path.is_file() {
-                                    new_abs_path = Some(path);
-                                    content_encoding = Some(algo.to_string());
-                                    break;
-                                }
+                        .filter(|(_, level)| *level > 0)
+                        .filter_map(|(algo, _)| algo.parse::<CompressionAlgo>().ok());
+                    'algos: for algo in accept_algos {
+                        for zip_ext in self.compressed_variations.get(&algo).into_iter().flatten() {
+                            let mut path = abs_path.clone();
+                            path.as_mut_os_string().push(&*format!(".{}", zip_ext));
+                            if path.is_file() {
+                                new_abs_path = Some(path);
+                                content_encoding = Some(algo.to_string());
+                                break 'algos;
                             }
                         }
                     }
+                    res.headers_mut()
+                        .append(VARY, HeaderValue::from_static("accept-encoding"));
                     new_abs_path.unwrap_or(abs_path)
                 } else {
                     abs_path
@@ -417,6 +478,15 @@ impl Handler for StaticDir {
                 builder
             };
             if let Ok(named_file) = builder.build().await {
+                let is_html = matches!(ext.as_deref(), Some("html" | "htm"));
+                let cache_control = if is_html {
+                    &self.index_cache_control
+                } else {
+                    &self.assets_cache_control
+                };
+                if let Some(cache_control) = cache_control {
+                    res.headers_mut().insert(CACHE_CONTROL, cache_control.clone());
+                }
                 let headers = req.headers();
                 named_file.send(headers, res).await;
             } else {
@@ -429,10 +499,8 @@ impl Handler for StaticDir {
                     let file_name = entry.file_name().to_string_lossy().to_string();
                     if self.include_dot_files || !file_name.starts_with('.') {
                         let raw_path = join_path!(&abs_path, &file_name);
-                        for filter in &self.exclude_filters {
-                            if filter(&raw_path) {
-                                continue;
-                            }
+                        if self.is_excluded(&raw_path) {
+                            continue;
                         }
                         if let Ok(metadata) = entry.metadata().await {
                             if metadata.is_dir() {
@@ -468,6 +536,22 @@ impl Handler for StaticDir {
     }
 }
 
+/// Escapes file names written in HTML and XML listings.
+fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[inline]
 fn list_json(current: &CurrentInfo) -> String {
     json!(current).to_string()
@@ -482,9 +566,9 @@ fn list_xml(current: &CurrentInfo) -> String {
             write!(
                 ftxt,
                 "<dir><name>{}</name><modified>{}</modified><link>{}</link></dir>",
-                dir.name,
+                escape_markup(&dir.name),
                 dir.modified.format(&format).expect("format time failed"),
-                encode_url_path(&dir.name),
+                escape_markup(&encode_url_path(&dir.name)),
             )
             .ok();
         }
@@ -492,10 +576,10 @@ fn list_xml(current: &CurrentInfo) -> String {
             write!(
                 ftxt,
                 "<file><name>{}</name><modified>{}</modified><size>{}</size><link>{}</link></file>",
-                file.name,
+                escape_markup(&file.name),
                 file.modified.format(&format).expect("format time failed"),
                 file.size,
-                encode_url_path(&file.name),
+                escape_markup(&encode_url_path(&file.name)),
             )
             .ok();
         }
@@ -535,8 +619,8 @@ fn list_html(current: &CurrentInfo) -> String {
             HOME_ICON,
             segments
                 .map(|seg| {
-                    link = format!("{link}/{seg}");
-                    format!("/<a href=\"{link}\">{seg}</a>")
+                    link = format!("{link}/{}", encode_url_path(seg));
+                    format!("/<a href=\"{}\">{}</a>", escape_markup(&link), escape_markup(seg))
                 })
                 .collect::<Vec<_>>()
                 .join("")
@@ -548,7 +632,7 @@ fn list_html(current: &CurrentInfo) -> String {
         <meta name="viewport" content="width=device-width">
         <title>{}</title>
         <style>{}</style></head><body><header><h3>Index of: {}</h3></header><hr/>"#,
-        current.path,
+        escape_markup(&current.path),
         HTML_STYLE,
         header_links(&current.path)
     );
@@ -566,8 +650,8 @@ fn list_html(current: &CurrentInfo) -> String {
                 ftxt,
                 r#"<tr><td>{}</td><td><a href="./{}/">{}</a></td><td>{}</td><td></td></tr>"#,
                 DIR_ICON,
-                encode_url_path(&dir.name),
-                dir.name,
+                escape_markup(&encode_url_path(&dir.name)),
+                escape_markup(&dir.name),
                 dir.modified.format(&format).expect("format time failed"),
             )
             .ok();
@@ -577,8 +661,8 @@ fn list_html(current: &CurrentInfo) -> String {
                 ftxt,
                 r#"<tr><td>{}</td><td><a href="./{}">{}</a></td><td>{}</td><td>{}</td></tr>"#,
                 FILE_ICON,
-                encode_url_path(&file.name),
-                file.name,
+                escape_markup(&encode_url_path(&file.name)),
+                escape_markup(&file.name),
                 file.modified.format(&format).expect("format time failed"),
                 human_size(file.size)
             )
@@ -9,6 +9,25 @@ use salvo_core::{async_trait, Depot, FlowCtrl, Handler, IntoVecString};
 use super::{decode_url_path_safely, format_url_path_safely, redirect_to_dir_url, join_path};
 
 /// Handler that serves embed file.
+///
+/// Files are compiled into the binary by [`RustEmbed`], and served with a content type guessed from their
+/// extension and an `ETag` of their content hash. Set [`fallback`](StaticEmbed::fallback) to `index.html` to serve
+/// single page applications, which route unknown paths in the browser.
+///
+/// # Example
+///
+/// ```ignore
+/// use rust_embed::RustEmbed;
+/// use salvo_core::prelude::*;
+/// use salvo_serve_static::static_embed;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "dist"]
+/// struct Assets;
+///
+/// let spa = static_embed::<Assets>().defaults("index.html").fallback("index.html");
+/// let router = Router::with_path("<*path>").get(spa);
+/// ```
 #[non_exhaustive]
 #[derive(Default)]
 pub struct StaticEmbed<T> {
@@ -36,6 +55,24 @@ pub fn render_embedded_file(file: EmbeddedFile, req: &Request, res: &mut Respons
     render_embedded_data(data, &metadata, req, res, mime);
 }
 
+/// Adds `charset=utf-8` to text content types, embedded text files are expected to be UTF-8 encoded.
+fn with_charset(mime: Mime) -> Mime {
+    let is_text = mime.type_() == mime::TEXT || mime.subtype() == mime::JSON || mime.subtype() == mime::JAVASCRIPT;
+    if is_text && mime.get_param(mime::CHARSET).is_none() {
+        format!("{mime}; charset=utf-8").parse::<Mime>().unwrap_or(mime)
+    } else {
+        mime
+    }
+}
+
+/// Returns `true` if the `If-None-Match` header value matches `etag`, using the weak comparison.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
 fn render_embedded_data(
     data: Cow<'static, [u8]>,
     metadata: &Metadata,
@@ -44,23 +81,23 @@ fn render_embedded_data(
     mime: Option<Mime>,
 ) {
     let mime = mime.unwrap_or_else(|| mime_infer::from_path(req.uri().path()).first_or_octet_stream());
-    res.headers_mut().insert(CONTENT_TYPE, mime.as_ref().parse().unwrap());
+    res.headers_mut()
+        .insert(CONTENT_TYPE, with_charset(mime).as_ref().parse().unwrap());
 
-    let hash = hex::encode(metadata.sha256_hash());
+    // The content hash is a strong validator, it changes whenever the binary embeds other content.
+    let etag = format!("\"{}\"", hex::encode(metadata.sha256_hash()));
+    res.headers_mut().insert(ETAG, etag.parse().unwrap());
     // if etag is matched, return 304
     if req
         .headers()
         .get(IF_NONE_MATCH)
-        .map(|etag| etag.to_str().unwrap_or("000000").eq(&hash))
-        .unwrap_or(false)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag))
     {
         res.status_code(StatusCode::NOT_MODIFIED);
         return;
     }
 
-    // otherwise, return 200 with etag hash
-    res.headers_mut().insert(ETAG, hash.parse().unwrap());
-
     match data {
         Cow::Borrowed(data) => {
             res.write_body(data).ok();
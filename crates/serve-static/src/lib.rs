@@ -96,6 +96,7 @@ pub(crate) fn redirect_to_dir_url(req_uri: &Uri, res: &mut Response) {
 
 #[cfg(test)]
 mod tests {
+    use salvo_core::http::HeaderValue;
     use salvo_core::prelude::*;
     use salvo_core::test::{ResponseExt, TestClient};
 
@@ -163,6 +164,82 @@ mod tests {
         assert!(content == "copy3");
     }
 
+    #[tokio::test]
+    async fn test_serve_static_dir_policies() {
+        let router = Router::with_path("<*path>").get(
+            StaticDir::new(vec!["test/static"])
+                .auto_list(true)
+                .exclude(|path| path.ends_with("test2.txt")),
+        );
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801/.hidden/secret.txt")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+        let res = TestClient::get("http://127.0.0.1:5801/test2.txt").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+        let content = TestClient::get("http://127.0.0.1:5801/")
+            .add_header("accept", "text/html", true)
+            .send(&service)
+            .await
+            .take_string()
+            .await
+            .unwrap();
+        assert!(content.contains("test1.txt") && !content.contains("test2.txt") && !content.contains(".hidden"));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/compressed.txt")
+            .add_header("accept-encoding", "br;q=0.5, gzip", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.headers().get("content-encoding").unwrap(), "gzip");
+        assert_eq!(res.headers().get("vary").unwrap(), "accept-encoding");
+        assert_eq!(res.take_string().await.unwrap(), "compressed text");
+        let mut res = TestClient::get("http://127.0.0.1:5801/compressed.txt")
+            .add_header("accept-encoding", "gzip;q=0", true)
+            .send(&service)
+            .await;
+        assert!(res.headers().get("content-encoding").is_none());
+        assert_eq!(res.take_string().await.unwrap(), "compressed text");
+    }
+
+    #[tokio::test]
+    async fn test_serve_static_dir_spa() {
+        #[handler]
+        async fn api() -> &'static str {
+            "api"
+        }
+        let static_dir = StaticDir::new(vec!["test/static"])
+            .spa("index.html")
+            .spa_exclude("/api")
+            .index_cache_control(HeaderValue::from_static("no-cache"))
+            .assets_cache_control(HeaderValue::from_static("public, max-age=31536000, immutable"));
+        let router = Router::new()
+            .push(Router::with_path("api/users").get(api))
+            .push(Router::with_path("<*path>").get(static_dir));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/users/42").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.headers()["cache-control"], "no-cache");
+        assert!(res.take_string().await.unwrap().contains("Index page"));
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/test1.txt").send(&service).await;
+        assert_eq!(res.headers()["cache-control"], "public, max-age=31536000, immutable");
+        assert_eq!(res.take_string().await.unwrap(), "copy1");
+        let mut res = TestClient::get("http://127.0.0.1:5801/api/users").send(&service).await;
+        assert_eq!(res.take_string().await.unwrap(), "api");
+
+        for path in ["/missing.js", "/api/missing", "/api"] {
+            let res = TestClient::get(format!("http://127.0.0.1:5801{path}"))
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND), "{path}");
+        }
+        let res = TestClient::post("http://127.0.0.1:5801/users").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+    }
+
     #[tokio::test]
     async fn test_serve_static_file() {
         let router = Router::new()
@@ -241,5 +318,23 @@ mod tests {
             .send(&service)
             .await;
         assert_eq!(response.status_code.unwrap(), StatusCode::NOT_FOUND);
+
+        let response = TestClient::get("http://127.0.0.1:5801/dir/test1.txt")
+            .send(&service)
+            .await;
+        assert_eq!(response.headers()["content-type"], "text/plain; charset=utf-8");
+        let etag = response.headers()["etag"].to_str().unwrap().to_owned();
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        let response = TestClient::get("http://127.0.0.1:5801/dir/test1.txt")
+            .add_header("if-none-match", format!("\"other\", W/{etag}"), true)
+            .send(&service)
+            .await;
+        assert_eq!(response.status_code.unwrap(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        let response = TestClient::get("http://127.0.0.1:5801/dir/test1.txt")
+            .add_header("if-none-match", "\"other\"", true)
+            .send(&service)
+            .await;
+        assert_eq!(response.status_code.unwrap(), StatusCode::OK);
     }
 }
@@ -18,6 +18,14 @@ external-datastore-backed session storage. For a list of currently
 available session stores, see [the documentation for
 async-session](https://github.com/http-rs/async-session).
 
+This crate re-exports these stores:
+
+- [`MemoryStore`]: keeps sessions in memory, only for development and tests.
+- [`CookieStore`]: serializes the whole session into the signed cookie, no server side storage is needed.
+- `RedisSessionStore`: keeps sessions in Redis, enabled by the `redis-store` feature.
+
+Any type implementing [`SessionStore`] can be used as a custom store.
+
 ## Security
 
 Although each session store may have different security implications,
@@ -49,6 +57,34 @@ adversary were able to tamper with the expiry of a cookie, salvo
 sessions would still check the expiry on the contained session before
 using it
 
+By default the expiry is rolling, it is extended by `session_ttl` on
+every request. With [`HandlerBuilder::rolling`] disabled, the expiry is
+set once when the session is created, so the session ends after
+`session_ttl` no matter how active it is.
+
+A [`HandlerBuilder::max_lifetime`] caps the lifetime of a session, even
+a rolling one: the session ends after this duration since its creation.
+
+### Regenerate
+
+Call [`SessionDepotExt::regenerate_session`] after login or privilege
+changes to prevent session fixation, the session keeps its data but is
+stored under a new id, and the old one is destroyed.
+
+The session keys holding privileges, like the user id or the roles, can
+be declared with [`HandlerBuilder::privilege_keys`], the session is then
+regenerated automatically when their values change.
+
+### Cookie attributes
+
+The session cookie is `HttpOnly` and `SameSite=Lax` by default, and
+`Secure` when the request is served over HTTPS. They can be changed
+with [`HandlerBuilder::http_only`], [`HandlerBuilder::same_site_policy`]
+and [`HandlerBuilder::secure`].
+
+[`HandlerBuilder::hardened`] applies a stricter profile for sensitive
+applications.
+
 ### If anything goes wrong with the above process
 
 If there are any failures in the above session retrieval process, a
@@ -69,21 +105,28 @@ Read more: <https://salvo.rs>
 #![doc(html_logo_url = "https://salvo.rs/images/logo.svg")]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(feature = "redis-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redis-store")))]
+pub use async_redis_session::RedisSessionStore;
 pub use async_session::{CookieStore, MemoryStore, Session, SessionStore};
 
 use std::fmt::{self, Formatter};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use async_session::base64;
 use async_session::hmac::{Hmac, Mac, NewMac};
 use async_session::sha2::Sha256;
 use cookie::{Cookie, Key, SameSite};
+use salvo_core::clock::{Clock, SystemClock};
 use salvo_core::http::uri::Scheme;
 use salvo_core::{async_trait, Depot, Error, FlowCtrl, Handler, Request, Response};
 
 /// Key for store data in depot.
 pub const SESSION_KEY: &str = "::salvo::session";
 const BASE64_DIGEST_LEN: usize = 44;
+/// Key of the creation time of the session in its data, used by `max_lifetime`.
+const CREATED_AT_KEY: &str = "__salvo_session_created_at";
 
 /// Trait for `Depot` to get and set session.
 pub trait SessionDepotExt {
@@ -95,6 +138,11 @@ pub trait SessionDepotExt {
     fn session(&self) -> Option<&Session>;
     /// Get session mutable reference
     fn session_mut(&mut self) -> Option<&mut Session>;
+    /// Changes the id of the session and keeps its data, the old session is destroyed
+    /// after the request is handled.
+    fn regenerate_session(&mut self) -> Option<&mut Session>;
+    /// Marks the session as destroyed, it is removed from the store and the cookie is removed.
+    fn destroy_session(&mut self) -> Option<&mut Session>;
 }
 
 impl SessionDepotExt for Depot {
@@ -115,6 +163,18 @@ impl SessionDepotExt for Depot {
     fn session_mut(&mut self) -> Option<&mut Session> {
         self.get_mut(SESSION_KEY).ok()
     }
+    #[inline]
+    fn regenerate_session(&mut self) -> Option<&mut Session> {
+        let session = self.session_mut()?;
+        session.regenerate();
+        Some(session)
+    }
+    #[inline]
+    fn destroy_session(&mut self) -> Option<&mut Session> {
+        let session = self.session_mut()?;
+        session.destroy();
+        Some(session)
+    }
 }
 
 /// `HandlerBuilder` is a builder for [`SessionHandler`].
@@ -124,10 +184,16 @@ pub struct HandlerBuilder<S> {
     cookie_name: String,
     cookie_domain: Option<String>,
     session_ttl: Option<Duration>,
+    rolling: bool,
+    max_lifetime: Option<Duration>,
+    privilege_keys: Vec<String>,
     save_unchanged: bool,
     same_site_policy: SameSite,
+    secure: Option<bool>,
+    http_only: bool,
     key: Key,
     fallback_keys: Vec<Key>,
+    clock: Arc<dyn Clock>,
 }
 impl<S: SessionStore> fmt::Debug for HandlerBuilder<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -137,7 +203,12 @@ impl<S: SessionStore> fmt::Debug for HandlerBuilder<S> {
             .field("cookie_name", &self.cookie_name)
             .field("cookie_domain", &self.cookie_domain)
             .field("session_ttl", &self.session_ttl)
+            .field("rolling", &self.rolling)
+            .field("max_lifetime", &self.max_lifetime)
+            .field("privilege_keys", &self.privilege_keys)
             .field("same_site_policy", &self.same_site_policy)
+            .field("secure", &self.secure)
+            .field("http_only", &self.http_only)
             .field("key", &"..")
             .field("fallback_keys", &"..")
             .field("save_unchanged", &self.save_unchanged)
@@ -159,9 +230,15 @@ where
             cookie_name: "salvo.session.id".into(),
             cookie_domain: None,
             same_site_policy: SameSite::Lax,
+            secure: None,
+            http_only: true,
             session_ttl: Some(Duration::from_secs(24 * 60 * 60)),
+            rolling: true,
+            max_lifetime: None,
+            privilege_keys: vec![],
             key: Key::from(secret),
             fallback_keys: vec![],
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -185,6 +262,40 @@ where
         self
     }
 
+    /// Sets whether the session expiry is extended on every request.
+    ///
+    /// The default for this value is `true`. When disabled, the expiry is set once
+    /// when the session is created and never extended.
+    #[inline]
+    pub fn rolling(mut self, rolling: bool) -> Self {
+        self.rolling = rolling;
+        self
+    }
+
+    /// Sets the maximum lifetime of a session, counted from its creation.
+    ///
+    /// The session ends after this duration even if it is rolling and used on every
+    /// request. The default is `None`, sessions live as long as they are used.
+    #[inline]
+    pub fn max_lifetime(mut self, max_lifetime: Option<Duration>) -> Self {
+        self.max_lifetime = max_lifetime;
+        self
+    }
+
+    /// Sets the session keys holding privileges, like the user id or the roles.
+    ///
+    /// When the value of any of them changes during a request, the session is regenerated
+    /// like with [`SessionDepotExt::regenerate_session`], to prevent session fixation.
+    #[inline]
+    pub fn privilege_keys<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.privilege_keys = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
     /// Sets the name of the cookie that the session is stored with or in.
     ///
     /// If you are running multiple tide applications on the same
@@ -221,6 +332,48 @@ where
         self
     }
 
+    /// Sets whether the session cookie is `Secure`, so browsers only send it over HTTPS.
+    ///
+    /// The default is `None`, the cookie is `Secure` when the request is served over HTTPS.
+    /// Set it to `Some(true)` when TLS is terminated by a proxy.
+    #[inline]
+    pub fn secure(mut self, secure: impl Into<Option<bool>>) -> Self {
+        self.secure = secure.into();
+        self
+    }
+
+    /// Sets whether the session cookie is `HttpOnly`, so scripts can not read it.
+    ///
+    /// The default for this value is `true`.
+    #[inline]
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Applies a hardened profile, following the
+    /// [OWASP recommendations](https://cheatsheetseries.owasp.org/cheatsheets/Session_Management_Cheat_Sheet.html):
+    ///
+    /// - the cookie is `Secure`, `HttpOnly` and `SameSite=Strict`,
+    /// - the cookie is named `__Host-salvo.session.id`, so browsers only accept it from HTTPS, for the
+    ///   whole host and without domain,
+    /// - sessions expire after 30 minutes of inactivity, and after 8 hours at most.
+    ///
+    /// Options can still be changed after this call.
+    #[inline]
+    pub fn hardened(mut self) -> Self {
+        self.secure = Some(true);
+        self.http_only = true;
+        self.same_site_policy = SameSite::Strict;
+        self.cookie_name = "__Host-salvo.session.id".into();
+        self.cookie_path = "/".into();
+        self.cookie_domain = None;
+        self.session_ttl = Some(Duration::from_secs(30 * 60));
+        self.rolling = true;
+        self.max_lifetime = Some(Duration::from_secs(8 * 60 * 60));
+        self
+    }
+
     /// Sets the domain of the cookie.
     #[inline]
     pub fn cookie_domain(mut self, cookie_domain: impl AsRef<str>) -> Self {
@@ -241,6 +394,16 @@ where
         self
     }
 
+    /// Sets the [`Clock`] used to expire sessions, default is [`SystemClock`].
+    ///
+    /// A [`MockClock`](salvo_core::clock::MockClock) can be used in tests, so expiration can be tested without
+    /// sleeping.
+    #[inline]
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
     /// Build `SessionHandler`
     pub fn build(self) -> Result<SessionHandler<S>, Error> {
         let Self {
@@ -250,9 +413,15 @@ where
             cookie_name,
             cookie_domain,
             session_ttl,
+            rolling,
+            max_lifetime,
+            privilege_keys,
             same_site_policy,
+            secure,
+            http_only,
             key,
             fallback_keys,
+            clock,
         } = self;
         let hmac =
             Hmac::<Sha256>::new_from_slice(key.signing()).map_err(|_| Error::Other("invalid key length".into()))?;
@@ -268,9 +437,15 @@ where
             cookie_name,
             cookie_domain,
             session_ttl,
+            rolling,
+            max_lifetime,
+            privilege_keys,
             same_site_policy,
+            secure,
+            http_only,
             hmac,
             fallback_hmacs,
+            clock,
         })
     }
 }
@@ -282,10 +457,16 @@ pub struct SessionHandler<S> {
     cookie_name: String,
     cookie_domain: Option<String>,
     session_ttl: Option<Duration>,
+    rolling: bool,
+    max_lifetime: Option<Duration>,
+    privilege_keys: Vec<String>,
     save_unchanged: bool,
     same_site_policy: SameSite,
+    secure: Option<bool>,
+    http_only: bool,
     hmac: Hmac<Sha256>,
     fallback_hmacs: Vec<Hmac<Sha256>>,
+    clock: Arc<dyn Clock>,
 }
 impl<S: SessionStore> fmt::Debug for SessionHandler<S> {
     #[inline]
@@ -296,7 +477,12 @@ impl<S: SessionStore> fmt::Debug for SessionHandler<S> {
             .field("cookie_name", &self.cookie_name)
             .field("cookie_domain", &self.cookie_domain)
             .field("session_ttl", &self.session_ttl)
+            .field("rolling", &self.rolling)
+            .field("max_lifetime", &self.max_lifetime)
+            .field("privilege_keys", &self.privilege_keys)
             .field("same_site_policy", &self.same_site_policy)
+            .field("secure", &self.secure)
+            .field("http_only", &self.http_only)
             .field("key", &"..")
             .field("fallback_keys", &"..")
             .field("save_unchanged", &self.save_unchanged)
@@ -312,10 +498,17 @@ where
         let cookie = req.cookies().get(&self.cookie_name);
         let cookie_value = cookie.and_then(|cookie| self.verify_signature(cookie.value()).ok());
 
-        let mut session = self.load_or_create(cookie_value).await;
+        let loaded = self.load(cookie_value).await;
+        let mut session = loaded.clone().unwrap_or_default();
 
         if let Some(ttl) = self.session_ttl {
-            session.expire_in(ttl);
+            if self.rolling || loaded.is_none() {
+                let mut expiry = self.clock.now() + ttl;
+                if let Some(deadline) = self.deadline(&session) {
+                    expiry = expiry.min(deadline);
+                }
+                session.set_expiry(expiry.into());
+            }
         }
 
         depot.set_session(session);
@@ -325,18 +518,46 @@ where
             return;
         }
 
-        let session = depot.take_session().expect("session should exist in depot");
+        let Some(mut session) = depot.take_session() else {
+            return;
+        };
+        if let Some(loaded) = &loaded {
+            if loaded.id() == session.id()
+                && self
+                    .privilege_keys
+                    .iter()
+                    .any(|key| loaded.get_raw(key) != session.get_raw(key))
+            {
+                session.regenerate();
+            }
+        }
+        let regenerated = match loaded {
+            Some(loaded) if loaded.id() != session.id() => {
+                if let Err(e) = self.store.destroy_session(loaded).await {
+                    tracing::error!(error = ?e, "unable to destroy regenerated session");
+                }
+                true
+            }
+            _ => false,
+        };
         if session.is_destroyed() {
             if let Err(e) = self.store.destroy_session(session).await {
                 tracing::error!(error = ?e, "unable to destroy session");
             }
             res.remove_cookie(&self.cookie_name);
-        } else if self.save_unchanged || session.data_changed() {
+        } else if self.save_unchanged || regenerated || session.data_changed() {
+            if self.max_lifetime.is_some() && session.get_raw(CREATED_AT_KEY).is_none() {
+                let created_at = self.clock.now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                session.insert(CREATED_AT_KEY, created_at.as_secs()).ok();
+            }
+            let expires = session.expiry().map(|expiry| SystemTime::from(*expiry));
             match self.store.store_session(session).await {
                 Ok(cookie_value) => {
                     if let Some(cookie_value) = cookie_value {
-                        let secure_cookie = req.uri().scheme() == Some(&Scheme::HTTPS);
-                        let cookie = self.build_cookie(secure_cookie, cookie_value);
+                        let secure_cookie = self
+                            .secure
+                            .unwrap_or_else(|| req.uri().scheme() == Some(&Scheme::HTTPS));
+                        let cookie = self.build_cookie(secure_cookie, cookie_value, expires);
                         res.add_cookie(cookie);
                     }
                 }
@@ -357,13 +578,29 @@ where
         HandlerBuilder::new(store, secret)
     }
     #[inline]
-    async fn load_or_create(&self, cookie_value: Option<String>) -> Session {
+    async fn load(&self, cookie_value: Option<String>) -> Option<Session> {
         let session = match cookie_value {
             Some(cookie_value) => self.store.load_session(cookie_value).await.ok().flatten(),
             None => None,
         };
 
-        session.and_then(|session| session.validate()).unwrap_or_default()
+        let now = self.clock.now();
+        session.filter(|session| {
+            session
+                .expiry()
+                .map(|expiry| SystemTime::from(*expiry) >= now)
+                .unwrap_or(true)
+                && self.deadline(session).map(|deadline| deadline >= now).unwrap_or(true)
+        })
+    }
+    /// Returns the time the session ends because of `max_lifetime`.
+    fn deadline(&self, session: &Session) -> Option<SystemTime> {
+        let max_lifetime = self.max_lifetime?;
+        let created_at = session
+            .get::<u64>(CREATED_AT_KEY)
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap_or_else(|| self.clock.now());
+        Some(created_at + max_lifetime)
     }
     // the following is reused verbatim from
     // https://github.com/SergioBenitez/cookie-rs/blob/master/src/secure/signed.rs#L51-L66
@@ -394,16 +631,16 @@ where
         }
         Err(Error::Other("value did not verify".into()))
     }
-    fn build_cookie(&self, secure: bool, cookie_value: String) -> Cookie<'static> {
+    fn build_cookie(&self, secure: bool, cookie_value: String, expires: Option<SystemTime>) -> Cookie<'static> {
         let mut cookie = Cookie::build((self.cookie_name.clone(), cookie_value))
-            .http_only(true)
+            .http_only(self.http_only)
             .same_site(self.same_site_policy)
             .secure(secure)
             .path(self.cookie_path.clone())
             .build();
 
-        if let Some(ttl) = self.session_ttl {
-            cookie.set_expires(Some((std::time::SystemTime::now() + ttl).into()));
+        if let Some(expires) = expires {
+            cookie.set_expires(Some(expires.into()));
         }
 
         if let Some(cookie_domain) = self.cookie_domain.clone() {
@@ -528,4 +765,240 @@ mod tests {
         let mut respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
         assert_eq!(respone.take_string().await.unwrap(), "home");
     }
+
+    #[tokio::test]
+    async fn test_session_regenerate() {
+        #[handler]
+        async fn login(depot: &mut Depot) -> &'static str {
+            depot.session_mut().unwrap().insert("username", "salvo").unwrap();
+            depot.regenerate_session();
+            "login"
+        }
+        #[handler]
+        async fn home(depot: &mut Depot) -> String {
+            depot
+                .session()
+                .and_then(|session| session.get::<String>("username"))
+                .unwrap_or_default()
+        }
+
+        let store = MemoryStore::new();
+        let session_handler = SessionHandler::builder(
+            store.clone(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .build()
+        .unwrap();
+        let router = Router::new()
+            .hoop(session_handler)
+            .get(home)
+            .push(Router::with_path("login").get(login));
+        let service = Service::new(router);
+
+        let respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        let old_cookie = respone.headers().get(SET_COOKIE).unwrap().clone();
+        let respone = TestClient::get("http://127.0.0.1:5800/login")
+            .add_header(COOKIE, old_cookie.clone(), true)
+            .send(&service)
+            .await;
+        let new_cookie = respone.headers().get(SET_COOKIE).unwrap().clone();
+        assert_ne!(old_cookie, new_cookie);
+        assert_eq!(store.count().await, 1);
+
+        let mut respone = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(COOKIE, new_cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.take_string().await.unwrap(), "salvo");
+        let mut respone = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(COOKIE, old_cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.take_string().await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn test_session_absolute_expiry() {
+        #[handler]
+        async fn expiry(depot: &mut Depot) -> String {
+            format!("{:?}", depot.session().unwrap().expiry())
+        }
+
+        let session_handler = SessionHandler::builder(
+            MemoryStore::new(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .rolling(false)
+        .build()
+        .unwrap();
+        let service = Service::new(Router::new().hoop(session_handler).get(expiry));
+
+        let mut respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        let cookie = respone.headers().get(SET_COOKIE).unwrap().clone();
+        let first = respone.take_string().await.unwrap();
+        assert!(first.starts_with("Some"));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let mut respone = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(COOKIE, cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.take_string().await.unwrap(), first);
+    }
+
+    #[tokio::test]
+    async fn test_session_mock_clock() {
+        use salvo_core::clock::MockClock;
+
+        #[handler]
+        async fn count(depot: &mut Depot) -> String {
+            let session = depot.session_mut().unwrap();
+            let count = session.get::<usize>("count").unwrap_or_default() + 1;
+            session.insert("count", count).unwrap();
+            count.to_string()
+        }
+
+        let clock = MockClock::new();
+        let session_handler = SessionHandler::builder(
+            MemoryStore::new(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .session_ttl(Some(Duration::from_secs(60)))
+        .rolling(false)
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+        let service = Service::new(Router::new().hoop(session_handler).get(count));
+
+        let mut respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        let cookie = respone.headers().get(SET_COOKIE).unwrap().clone();
+        assert_eq!(respone.take_string().await.unwrap(), "1");
+
+        clock.advance(Duration::from_secs(59));
+        let mut respone = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(COOKIE, cookie.clone(), true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.take_string().await.unwrap(), "2");
+
+        clock.advance(Duration::from_secs(2));
+        let mut respone = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(COOKIE, cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.take_string().await.unwrap(), "1");
+    }
+
+    #[tokio::test]
+    async fn test_session_hardened_cookie() {
+        #[handler]
+        async fn hello() -> &'static str {
+            "hello"
+        }
+
+        let session_handler = SessionHandler::builder(
+            MemoryStore::new(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .hardened()
+        .build()
+        .unwrap();
+        assert_eq!(session_handler.max_lifetime, Some(Duration::from_secs(8 * 60 * 60)));
+        let service = Service::new(Router::new().hoop(session_handler).get(hello));
+
+        let respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        let cookie = respone.headers().get(SET_COOKIE).unwrap().to_str().unwrap();
+        assert!(cookie.starts_with("__Host-salvo.session.id="));
+        assert!(cookie.contains("HttpOnly"));
+        assert!(cookie.contains("SameSite=Strict"));
+        assert!(cookie.contains("Secure"));
+        assert!(cookie.contains("Path=/"));
+    }
+
+    #[tokio::test]
+    async fn test_session_privilege_keys() {
+        #[handler]
+        async fn login(depot: &mut Depot) -> &'static str {
+            depot.session_mut().unwrap().insert("user_id", 1).unwrap();
+            "login"
+        }
+        #[handler]
+        async fn visit(depot: &mut Depot) -> &'static str {
+            depot.session_mut().unwrap().insert("visited", true).unwrap();
+            "visit"
+        }
+
+        let store = MemoryStore::new();
+        let session_handler = SessionHandler::builder(
+            store.clone(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .privilege_keys(["user_id"])
+        .build()
+        .unwrap();
+        let router = Router::new()
+            .hoop(session_handler)
+            .push(Router::with_path("login").get(login))
+            .push(Router::with_path("visit").get(visit));
+        let service = Service::new(router);
+
+        let respone = TestClient::get("http://127.0.0.1:5800/visit").send(&service).await;
+        let anonymous_cookie = respone.headers().get(SET_COOKIE).unwrap().clone();
+        let respone = TestClient::get("http://127.0.0.1:5800/visit")
+            .add_header(COOKIE, anonymous_cookie.clone(), true)
+            .send(&service)
+            .await;
+        assert!(respone.headers().get(SET_COOKIE).is_none());
+
+        let respone = TestClient::get("http://127.0.0.1:5800/login")
+            .add_header(COOKIE, anonymous_cookie.clone(), true)
+            .send(&service)
+            .await;
+        assert_ne!(respone.headers().get(SET_COOKIE).unwrap(), anonymous_cookie);
+        assert_eq!(store.count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_max_lifetime() {
+        use salvo_core::clock::MockClock;
+
+        #[handler]
+        async fn count(depot: &mut Depot) -> String {
+            let session = depot.session_mut().unwrap();
+            let count = session.get::<usize>("count").unwrap_or_default() + 1;
+            session.insert("count", count).unwrap();
+            count.to_string()
+        }
+
+        let clock = MockClock::new();
+        let session_handler = SessionHandler::builder(
+            MemoryStore::new(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .session_ttl(Some(Duration::from_secs(60)))
+        .max_lifetime(Some(Duration::from_secs(100)))
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+        let service = Service::new(Router::new().hoop(session_handler).get(count));
+
+        let mut respone = TestClient::get("http://127.0.0.1:5800/").send(&service).await;
+        let cookie = respone.headers().get(SET_COOKIE).unwrap().clone();
+        assert_eq!(respone.take_string().await.unwrap(), "1");
+
+        for expected in ["2", "3"] {
+            clock.advance(Duration::from_secs(45));
+            let mut respone = TestClient::get("http://127.0.0.1:5800/")
+                .add_header(COOKIE, cookie.clone(), true)
+                .send(&service)
+                .await;
+            assert_eq!(respone.take_string().await.unwrap(), expected);
+        }
+
+        clock.advance(Duration::from_secs(45));
+        let mut respone = TestClient::get("http://127.0.0.1:5800/")
+            .add_header(COOKIE, cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(respone.take_string().await.unwrap(), "1");
+    }
 }
@@ -9,13 +9,16 @@ async fn hello() -> &'static str {
 async fn main() {
     tracing_subscriber::fmt().init();
 
-    let mut router = Router::new().get(hello);
+    let router = Router::new().get(hello);
+    // Serves ACME HTTP-01 challenges on port 80, other requests are redirected to HTTPS.
+    let mut http_router = Router::new();
     let listener = TcpListener::new("0.0.0.0:443")
         .acme()
         // .directory("letsencrypt", salvo::conn::acme::LETS_ENCRYPT_STAGING)
         .cache_path("/temp/letsencrypt")
         .add_domain("test.salvo.rs")
-        .http01_challege(&mut router);
-    let acceptor = listener.join(TcpListener::new("0.0.0.0:80")).bind().await;
+        .http01_challege(&mut http_router);
+    let http_listener = TcpListener::new("0.0.0.0:80").with_service(ForceHttps::new().into_service_with(http_router));
+    let acceptor = listener.join(http_listener).bind().await;
     Server::new(acceptor).serve(router).await;
 }